@@ -197,6 +197,30 @@ pub fn bench_analyser_node() {
     assert_eq!(ctx.start_rendering_sync().length(), SAMPLES);
 }
 
+// Large graph of voices that are scheduled but do not play yet, the idle branches are skipped
+pub fn bench_idle_voices() {
+    let ctx = OfflineAudioContext::new(2, black_box(SAMPLES), SAMPLE_RATE);
+    let file = std::fs::File::open("samples/think-stereo-48000.wav").unwrap();
+    let buffer = ctx.decode_audio_data_sync(file).unwrap();
+
+    let bus = ctx.create_gain();
+    bus.connect(&ctx.destination());
+
+    for _ in 0..500 {
+        let gain = ctx.create_gain();
+        gain.connect(&bus);
+        let panner = ctx.create_stereo_panner();
+        panner.connect(&gain);
+
+        let src = ctx.create_buffer_source();
+        src.connect(&panner);
+        src.set_buffer(buffer.clone());
+        src.start_at(DURATION as f64 + 1.);
+    }
+
+    assert_eq!(ctx.start_rendering_sync().length(), SAMPLES);
+}
+
 iai::main!(
     bench_ctor,
     bench_sine,
@@ -209,4 +233,5 @@ iai::main!(
     bench_stereo_positional,
     bench_stereo_panning_automation,
     bench_analyser_node,
+    bench_idle_voices,
 );
@@ -617,6 +617,30 @@ fn main() {
         benchmark(name, context, &mut results);
     }
 
+    {
+        let name = "Idle voice pool (1000 voices waiting to start)";
+
+        let context = OfflineAudioContext::new(2, DURATION * sample_rate as usize, sample_rate);
+        let bus = context.create_gain();
+        bus.connect(&context.destination());
+        let buffer = get_buffer(&sources, sample_rate, 2);
+
+        // voices of a synth or game engine, scheduled after the end of the rendering
+        for _ in 0..1000 {
+            let gain = context.create_gain();
+            gain.connect(&bus);
+            let panner = context.create_stereo_panner();
+            panner.connect(&gain);
+
+            let src = context.create_buffer_source();
+            src.connect(&panner);
+            src.set_buffer(buffer.clone());
+            src.start_at(DURATION as f64 + 1.);
+        }
+
+        benchmark(name, context, &mut results);
+    }
+
     println!("> All done! {:<67}\n", "");
 
     // -------------------------------------------------------
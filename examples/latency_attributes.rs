@@ -11,11 +11,10 @@ fn main() {
 
     sine.start();
 
-    println!("- BaseLatency: {:?}", context.base_latency());
-
     loop {
         println!("-------------------------------------------------");
         println!("+ currentTime {:?}", context.current_time());
+        println!("+ BaseLatency: {:?}", context.base_latency());
         println!("+ OutputLatency: {:?}", context.output_latency());
 
         std::thread::sleep(std::time::Duration::from_secs(1));
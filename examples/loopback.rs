@@ -0,0 +1,52 @@
+use web_audio_api::context::{AudioContext, AudioContextOptions, BaseAudioContext};
+use web_audio_api::media_devices;
+use web_audio_api::media_devices::MediaStreamConstraints;
+use web_audio_api::node::AudioNode;
+
+// Display the level of the audio played by the system
+//
+// `cargo run --release --example loopback`
+//
+// On Linux, use the `cubeb` feature to capture the PulseAudio or PipeWire monitor source
+fn main() {
+    env_logger::init();
+
+    let stream = match media_devices::get_display_media_sync(MediaStreamConstraints::Audio) {
+        Ok(stream) => stream,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    println!(
+        "Loopback settings: {:?}",
+        stream.get_tracks()[0].get_settings()
+    );
+
+    // do not play the captured audio, it would be captured again
+    let context = AudioContext::new(AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    });
+
+    let analyser = context.create_analyser();
+    analyser.connect(&context.destination());
+
+    let stream_source = context.create_media_stream_source(&stream);
+    stream_source.connect(&analyser);
+
+    let mut samples = vec![0.; analyser.fft_size()];
+
+    loop {
+        analyser.get_float_time_domain_data(&mut samples);
+        let mean_square = samples.iter().map(|v| v * v).sum::<f32>() / samples.len() as f32;
+        let level = 10. * mean_square.max(1e-10).log10();
+        println!(
+            "{:>6.1} dBFS {}",
+            level,
+            "#".repeat((level + 60.).max(0.) as usize)
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+}
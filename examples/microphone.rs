@@ -58,6 +58,10 @@ fn main() {
     constraints.device_id = source_id;
     let stream_constraints = MediaStreamConstraints::AudioWithConstraints(constraints);
     let mic = media_devices::get_user_media_sync(stream_constraints);
+    println!(
+        "Microphone settings: {:?}",
+        mic.get_tracks()[0].get_settings()
+    );
 
     // create media stream source node with mic stream
     let stream_source = context.create_media_stream_source(&mic);
@@ -68,6 +72,6 @@ fn main() {
     }
 
     // println!("Closing microphone");
-    // mic.get_tracks()[0].close();
+    // mic.get_tracks()[0].stop();
     // std::thread::sleep(std::time::Duration::from_secs(2));
 }
@@ -36,7 +36,10 @@ fn main() {
     let context = AudioContext::new(options);
     println!("Playing beep for sink {:?}", context.sink_id());
 
-    context.set_onsinkchange(|_| println!("sink change event"));
+    context.set_onsinkchange(|e| {
+        let label = e.device.as_ref().map(|d| d.label().to_owned());
+        println!("sink change event: {:?} ({:?})", e.sink_id, label);
+    });
 
     // Create an oscillator node with sine (default) type
     let osc = context.create_oscillator();
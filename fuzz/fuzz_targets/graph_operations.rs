@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use web_audio_api::testing::{run_graph_operations, GraphOperation};
+
+fuzz_target!(|data: &[u8]| {
+    let operations = GraphOperation::decode(data);
+    if let Err(e) = run_graph_operations(&operations) {
+        panic!("{} in {:?}", e, operations);
+    }
+});
@@ -2,13 +2,15 @@
 //!
 //! These are used in the [`AnalyserNode`](crate::node::AnalyserNode)
 
+use std::collections::VecDeque;
 use std::f32::consts::PI;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
-
-use realfft::{num_complex::Complex, RealFftPlanner};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
 
+use crate::fft::{self, Complex, RealToComplexFft};
 use crate::{AtomicF32, RENDER_QUANTUM_SIZE};
+use arc_swap::ArcSwapOption;
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
 
 /// Blackman window values iterator with alpha = 0.16
 fn generate_blackman(size: usize) -> impl Iterator<Item = f32> {
@@ -23,6 +25,11 @@ fn generate_blackman(size: usize) -> impl Iterator<Item = f32> {
     })
 }
 
+/// Hann window values iterator
+pub(crate) fn generate_hann(size: usize) -> impl Iterator<Item = f32> {
+    (0..size).map(move |i| 0.5 - 0.5 * (2. * PI * i as f32 / size as f32).cos())
+}
+
 pub(crate) const DEFAULT_SMOOTHING_TIME_CONSTANT: f64 = 0.8;
 pub(crate) const DEFAULT_MIN_DECIBELS: f64 = -100.;
 pub(crate) const DEFAULT_MAX_DECIBELS: f64 = -30.;
@@ -33,7 +40,7 @@ const MAX_FFT_SIZE: usize = 32768;
 
 // [spec] This MUST be a power of two in the range 32 to 32768, otherwise an
 // IndexSizeError exception MUST be thrown.
-fn assert_valid_fft_size(fft_size: usize) {
+pub(crate) fn assert_valid_fft_size(fft_size: usize) {
     if !fft_size.is_power_of_two() {
         panic!(
             "IndexSizeError - Invalid fft size: {:?} is not a power of two",
@@ -82,6 +89,249 @@ fn assert_valid_max_decibels(max_decibels: f64, min_decibels: f64) {
     }
 }
 
+// Frequency bands are logarithmically spaced, so the lower bound must be strictly
+// positive and lower than the upper bound
+fn assert_valid_frequency_range(min_frequency: f32, max_frequency: f32) {
+    if !(min_frequency > 0. && min_frequency < max_frequency) {
+        panic!(
+            "IndexSizeError - Invalid frequency range: [{:?}, {:?}] is not a positive increasing range",
+            min_frequency, max_frequency
+        );
+    }
+}
+
+// Absolute threshold of the cumulative mean normalized difference function used
+// by the YIN algorithm, cf. de Cheveigné & Kawahara (2002), section II.D
+const YIN_THRESHOLD: f32 = 0.15;
+
+/// Fundamental frequency estimate returned by
+/// [`AnalyserNode::get_pitch`](crate::node::AnalyserNode::get_pitch)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PitchEstimate {
+    /// Estimated fundamental frequency in Hertz
+    pub frequency: f32,
+    /// Confidence of the estimate, in the range [0, 1]
+    pub confidence: f32,
+}
+
+/// Number of render quanta that can be buffered for a [`Spectrogram`] before the
+/// render thread starts dropping frames (~1.4s at 48kHz)
+pub(crate) const SPECTROGRAM_QUEUE_CAPACITY: usize = 512;
+
+/// Window function applied to the time domain data of each [`Spectrogram`] frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpectrogramWindow {
+    /// Blackman window (alpha = 0.16), as used by the `AnalyserNode`
+    Blackman,
+    /// Hann window
+    Hann,
+    /// No windowing
+    Rectangular,
+}
+
+/// Scaling of the frequency bins of each [`Spectrogram`] frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpectrogramScaling {
+    /// Magnitude normalized by the FFT size
+    Magnitude,
+    /// Squared normalized magnitude
+    Power,
+    /// Normalized magnitude in decibels
+    Decibels,
+}
+
+/// Options for [`AnalyserNode::spectrogram`](crate::node::AnalyserNode::spectrogram)
+#[derive(Clone, Debug)]
+pub struct SpectrogramOptions {
+    /// Size of the FFT, must be a power of two in the range [32, 32768]
+    pub fft_size: usize,
+    /// Number of sample frames between the start of two successive frames
+    pub hop_size: usize,
+    pub window: SpectrogramWindow,
+    pub scaling: SpectrogramScaling,
+}
+
+impl Default for SpectrogramOptions {
+    fn default() -> Self {
+        Self {
+            fft_size: DEFAULT_FFT_SIZE,
+            hop_size: DEFAULT_FFT_SIZE / 4,
+            window: SpectrogramWindow::Blackman,
+            scaling: SpectrogramScaling::Decibels,
+        }
+    }
+}
+
+/// Place of a [`Spectrogram`] in an `AnalyserNode`, shared with the render thread
+#[derive(Default)]
+pub(crate) struct SpectrogramPlace {
+    /// Sender of the frames of a new spectrogram, taken by the render thread
+    pub(crate) pending: ArcSwapOption<Sender<[f32; RENDER_QUANTUM_SIZE]>>,
+    /// The place is taken by a spectrogram
+    taken: AtomicBool,
+}
+
+/// Frees the place of a [`Spectrogram`] when dropped
+pub(crate) struct SpectrogramSlot(Weak<SpectrogramPlace>);
+
+impl SpectrogramSlot {
+    /// Take the place if it is free
+    pub(crate) fn take(place: &Arc<SpectrogramPlace>) -> Option<Self> {
+        place
+            .taken
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()?;
+
+        Some(Self(Arc::downgrade(place)))
+    }
+}
+
+impl Drop for SpectrogramSlot {
+    fn drop(&mut self) {
+        if let Some(place) = self.0.upgrade() {
+            // the render thread drops the sender once it notices the spectrogram is gone
+            place.pending.store(None);
+            place.taken.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Stream of successive FFT frames of the input of an [`AnalyserNode`](crate::node::AnalyserNode)
+///
+/// Created with [`AnalyserNode::spectrogram`](crate::node::AnalyserNode::spectrogram). Each
+/// frame contains `fft_size / 2` frequency bins. The time domain data is shipped from the
+/// render thread without any gaps, so no frames are missed as long as the consumer keeps up.
+/// Frames are dropped on the render thread when the internal queue is full.
+///
+/// The iterator implementation blocks until the next frame is available and ends when the
+/// `AnalyserNode` has been removed from the audio graph.
+pub struct Spectrogram {
+    receiver: Receiver<[f32; RENDER_QUANTUM_SIZE]>,
+    _slot: SpectrogramSlot,
+    samples: VecDeque<f32>,
+    fft_size: usize,
+    hop_size: usize,
+    scaling: SpectrogramScaling,
+    window: Vec<f32>,
+    r2c: Arc<dyn RealToComplexFft>,
+    fft_input: Vec<f32>,
+    fft_scratch: Vec<Complex<f32>>,
+    fft_output: Vec<Complex<f32>>,
+}
+
+impl std::fmt::Debug for Spectrogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Spectrogram")
+            .field("fft_size", &self.fft_size)
+            .field("hop_size", &self.hop_size)
+            .field("scaling", &self.scaling)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Spectrogram {
+    pub(crate) fn new(
+        options: SpectrogramOptions,
+        receiver: Receiver<[f32; RENDER_QUANTUM_SIZE]>,
+        slot: SpectrogramSlot,
+    ) -> Self {
+        let SpectrogramOptions {
+            fft_size,
+            hop_size,
+            window,
+            scaling,
+        } = options;
+
+        assert_valid_fft_size(fft_size);
+        if hop_size == 0 || hop_size > fft_size {
+            panic!(
+                "IndexSizeError - Invalid hop size: {:?} is outside range [1, {:?}]",
+                hop_size, fft_size
+            );
+        }
+
+        let window = match window {
+            SpectrogramWindow::Blackman => generate_blackman(fft_size).collect(),
+            SpectrogramWindow::Hann => generate_hann(fft_size).collect(),
+            SpectrogramWindow::Rectangular => vec![1.; fft_size],
+        };
+
+        let r2c = fft::plan_forward(fft_size);
+        let fft_input = vec![0.; fft_size];
+        let fft_scratch = vec![Complex::default(); r2c.scratch_len()];
+        let fft_output = vec![Complex::default(); fft_size / 2 + 1];
+
+        Self {
+            receiver,
+            _slot: slot,
+            samples: VecDeque::with_capacity(fft_size + RENDER_QUANTUM_SIZE),
+            fft_size,
+            hop_size,
+            scaling,
+            window,
+            r2c,
+            fft_input,
+            fft_scratch,
+            fft_output,
+        }
+    }
+
+    /// Return the next frame if it is available, without blocking
+    pub fn try_next(&mut self) -> Option<Vec<f32>> {
+        while self.samples.len() < self.fft_size {
+            match self.receiver.try_recv() {
+                Ok(quantum) => self.samples.extend(quantum.iter()),
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => return None,
+            }
+        }
+
+        Some(self.compute_frame())
+    }
+
+    fn compute_frame(&mut self) -> Vec<f32> {
+        self.fft_input
+            .iter_mut()
+            .zip(self.samples.iter())
+            .zip(self.window.iter())
+            .for_each(|((i, s), w)| *i = s * w);
+
+        self.samples.drain(..self.hop_size);
+
+        self.r2c.process(
+            &mut self.fft_input,
+            &mut self.fft_output,
+            &mut self.fft_scratch,
+        );
+
+        // ignore the Nyquist bin, as in the `Analyser`
+        let normalize_factor = 1. / self.fft_size as f32;
+        self.fft_output[..self.fft_size / 2]
+            .iter()
+            .map(|c| {
+                let magnitude = c.norm() * normalize_factor;
+                match self.scaling {
+                    SpectrogramScaling::Magnitude => magnitude,
+                    SpectrogramScaling::Power => magnitude * magnitude,
+                    SpectrogramScaling::Decibels => 20. * magnitude.log10(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Iterator for Spectrogram {
+    type Item = Vec<f32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.samples.len() < self.fft_size {
+            let quantum = self.receiver.recv().ok()?;
+            self.samples.extend(quantum.iter());
+        }
+
+        Some(self.compute_frame())
+    }
+}
+
 // as the queue is composed of AtomicF32 having only 1 render quantum of extra
 // room should be enough
 const RING_BUFFER_SIZE: usize = MAX_FFT_SIZE + RENDER_QUANTUM_SIZE;
@@ -158,7 +408,8 @@ pub(crate) struct Analyser {
     smoothing_time_constant: f64,
     min_decibels: f64,
     max_decibels: f64,
-    fft_planner: Mutex<RealFftPlanner<f32>>, // RealFftPlanner is not `Sync` on all platforms
+    /// transform of the current FFT size
+    r2c: Arc<dyn RealToComplexFft>,
     fft_input: Vec<f32>,
     fft_scratch: Vec<Complex<f32>>,
     fft_output: Vec<Complex<f32>>,
@@ -170,13 +421,12 @@ pub(crate) struct Analyser {
 impl Analyser {
     pub fn new() -> Self {
         let ring_buffer = AnalyserRingBuffer::new();
-        // FFT utils
-        let mut fft_planner = RealFftPlanner::<f32>::new();
-        let max_fft = fft_planner.plan_fft_forward(MAX_FFT_SIZE);
+        // FFT utils, the buffers are large enough for all FFT sizes
+        let r2c = fft::plan_forward(DEFAULT_FFT_SIZE);
 
-        let fft_input = max_fft.make_input_vec();
-        let fft_scratch = max_fft.make_scratch_vec();
-        let fft_output = max_fft.make_output_vec();
+        let fft_input = vec![0.; MAX_FFT_SIZE];
+        let fft_scratch = vec![Complex::default(); r2c.scratch_len()];
+        let fft_output = vec![Complex::default(); MAX_FFT_SIZE / 2 + 1];
         let mut last_fft_output = Vec::with_capacity(fft_output.len());
         last_fft_output.resize_with(fft_output.len(), || 0.);
 
@@ -190,7 +440,7 @@ impl Analyser {
             smoothing_time_constant: DEFAULT_SMOOTHING_TIME_CONSTANT,
             min_decibels: DEFAULT_MIN_DECIBELS,
             max_decibels: DEFAULT_MAX_DECIBELS,
-            fft_planner: Mutex::new(fft_planner),
+            r2c,
             fft_input,
             fft_scratch,
             fft_output,
@@ -220,6 +470,9 @@ impl Analyser {
             self.blackman.clear();
             generate_blackman(fft_size).for_each(|v| self.blackman.push(v));
 
+            self.r2c = fft::plan_forward(fft_size);
+            self.fft_scratch
+                .resize(self.r2c.scratch_len(), Complex::default());
             self.fft_size = fft_size;
         }
     }
@@ -281,10 +534,9 @@ impl Analyser {
         let fft_size = self.fft_size();
         let smoothing_time_constant = self.smoothing_time_constant() as f32;
         // setup FFT planner and properly sized buffers
-        let r2c = self.fft_planner.lock().unwrap().plan_fft_forward(fft_size);
         let input = &mut self.fft_input[..fft_size];
         let output = &mut self.fft_output[..fft_size / 2 + 1];
-        let scratch = &mut self.fft_scratch[..r2c.get_scratch_len()];
+        let scratch = &mut self.fft_scratch[..];
         // we ignore the Nyquist bin in output, see comment below
         let last_fft_output = &mut self.last_fft_output[..fft_size / 2];
 
@@ -300,7 +552,7 @@ impl Analyser {
 
         // Apply a Fourier transform to the windowed time domain input data to
         // get real and imaginary frequency data.
-        r2c.process_with_scratch(input, output, scratch).unwrap();
+        self.r2c.process(input, output, scratch);
 
         // Notes from chromium source code (tbc)
         //
@@ -401,6 +653,120 @@ impl Analyser {
                 *v = clamped as u8;
             });
     }
+
+    // Average the current frequency data into logarithmically spaced bands
+    // covering [min_frequency, max_frequency], one band per element of dst.
+    // Each band holds the mean power of the bins it contains (in dB), bands
+    // narrower than a bin interpolate the magnitude at their center frequency.
+    pub fn get_float_frequency_bands(
+        &mut self,
+        dst: &mut [f32],
+        min_frequency: f32,
+        max_frequency: f32,
+        sample_rate: f32,
+        current_time: f64,
+    ) {
+        assert_valid_frequency_range(min_frequency, max_frequency);
+
+        if dst.is_empty() {
+            return;
+        }
+
+        if current_time != self.last_fft_time {
+            self.compute_fft();
+            self.last_fft_time = current_time;
+        }
+
+        let bins = &self.last_fft_output[..self.frequency_bin_count()];
+        let bin_width = sample_rate / self.fft_size() as f32;
+        let max_frequency = max_frequency.min(sample_rate / 2.);
+        let min_frequency = min_frequency.min(max_frequency);
+        let ratio = (max_frequency / min_frequency).powf(1. / dst.len() as f32);
+
+        let mut low = min_frequency;
+        dst.iter_mut().for_each(|v| {
+            let high = low * ratio;
+            let start = (low / bin_width).ceil() as usize;
+            let end = ((high / bin_width).ceil() as usize).min(bins.len());
+
+            let power = if start < end {
+                let sum: f32 = bins[start..end].iter().map(|m| m * m).sum();
+                sum / (end - start) as f32
+            } else {
+                let position = (low * high).sqrt() / bin_width;
+                let index = (position as usize).min(bins.len() - 1);
+                let next = (index + 1).min(bins.len() - 1);
+                let frac = (position - index as f32).clamp(0., 1.);
+                let magnitude = bins[index] + (bins[next] - bins[index]) * frac;
+                magnitude * magnitude
+            };
+
+            *v = 10. * power.log10();
+            low = high;
+        });
+    }
+
+    // Estimate the fundamental frequency of the most recent fftSize frames
+    // using the YIN algorithm, cf. <http://audition.ens.fr/adc/pdf/2002_JASA_YIN.pdf>
+    //
+    // The lag search is limited to half of the analysis window, so the lowest
+    // detectable frequency is `2 * sample_rate / fft_size`.
+    pub fn get_pitch(&self, sample_rate: f32) -> Option<PitchEstimate> {
+        let fft_size = self.fft_size();
+        let max_lag = fft_size / 2;
+
+        let mut input = vec![0.; fft_size];
+        self.ring_buffer.read(&mut input, fft_size);
+
+        if input.iter().all(|v| *v == 0.) {
+            return None;
+        }
+
+        // Step 2 & 3: difference function and cumulative mean normalized
+        // difference function, d'(0) is defined as 1
+        let mut cmndf = vec![1.; max_lag];
+        let mut running_sum = 0.;
+
+        for lag in 1..max_lag {
+            let diff: f32 = input[..max_lag]
+                .iter()
+                .zip(input[lag..].iter())
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum();
+
+            running_sum += diff;
+            cmndf[lag] = if running_sum > 0. {
+                diff * lag as f32 / running_sum
+            } else {
+                1.
+            };
+        }
+
+        // Step 4: absolute threshold, take the first dip below the threshold
+        // and follow it down to its local minimum
+        let mut lag = (2..max_lag).find(|&lag| cmndf[lag] < YIN_THRESHOLD)?;
+        while lag + 1 < max_lag && cmndf[lag + 1] < cmndf[lag] {
+            lag += 1;
+        }
+
+        // Step 5: parabolic interpolation around the selected minimum
+        let refined_lag = if lag + 1 < max_lag {
+            let (prev, curr, next) = (cmndf[lag - 1], cmndf[lag], cmndf[lag + 1]);
+            let denom = prev + next - 2. * curr;
+            if denom.abs() > f32::EPSILON {
+                lag as f32 + (prev - next) / (2. * denom)
+            } else {
+                lag as f32
+            }
+        } else {
+            lag as f32
+        };
+
+        Some(PitchEstimate {
+            frequency: sample_rate / refined_lag,
+            confidence: (1. - cmndf[lag]).clamp(0., 1.),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -789,6 +1155,131 @@ mod tests {
         assert!(bins[(RENDER_QUANTUM_SIZE / 2)..] == [255; (RENDER_QUANTUM_SIZE / 2)][..],);
     }
 
+    #[test]
+    fn test_get_float_frequency_bands() {
+        let sample_rate = 44100.;
+        let fft_size = 4096;
+        let freq = 1000.;
+
+        let mut analyser = Analyser::new();
+        analyser.set_fft_size(fft_size);
+        analyser.set_smoothing_time_constant(0.);
+
+        let signal: Vec<f32> = (0..fft_size)
+            .map(|i| (2. * PI * freq * i as f32 / sample_rate).sin())
+            .collect();
+        analyser.get_ring_buffer_clone().write(&signal);
+
+        // 1/3 octave bands from 20Hz to 20480Hz
+        let mut bands = vec![0.; 30];
+        analyser.get_float_frequency_bands(&mut bands, 20., 20480., sample_rate, 0.);
+
+        // the 1kHz sine falls in the 17th band, i.e. [~806Hz, ~1016Hz[
+        let loudest = bands
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .unwrap()
+            .0;
+        let expected = (30. * (freq / 20f32).log2() / 10.) as usize;
+        assert_eq!(loudest, expected);
+        assert!(bands.iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn test_get_float_frequency_bands_silence() {
+        let mut analyser = Analyser::new();
+
+        let mut bands = vec![0.; 10];
+        analyser.get_float_frequency_bands(&mut bands, 20., 20000., 44100., 0.);
+        assert!(bands.iter().all(|v| *v == f32::NEG_INFINITY));
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_get_float_frequency_bands_invalid_range() {
+        let mut analyser = Analyser::new();
+
+        let mut bands = vec![0.; 10];
+        analyser.get_float_frequency_bands(&mut bands, 1000., 20., 44100., 0.);
+    }
+
+    #[test]
+    fn test_get_pitch() {
+        let sample_rate = 44100.;
+
+        for freq in [82.41, 220., 440., 1000.] {
+            let mut analyser = Analyser::new();
+            analyser.set_fft_size(4096);
+
+            let signal: Vec<f32> = (0..4096)
+                .map(|i| (2. * PI * freq * i as f32 / sample_rate).sin())
+                .collect();
+            analyser.get_ring_buffer_clone().write(&signal);
+
+            let pitch = analyser.get_pitch(sample_rate).unwrap();
+            assert_float_eq!(pitch.frequency, freq, r2nd <= 0.005);
+            assert!(pitch.confidence > 0.9);
+        }
+    }
+
+    #[test]
+    fn test_spectrogram_frames() {
+        let (sender, receiver) = crossbeam_channel::bounded(SPECTROGRAM_QUEUE_CAPACITY);
+        let options = SpectrogramOptions {
+            fft_size: 256,
+            hop_size: 64,
+            window: SpectrogramWindow::Hann,
+            scaling: SpectrogramScaling::Magnitude,
+        };
+        let mut spectrogram = Spectrogram::new(options, receiver, SpectrogramSlot(Weak::new()));
+
+        // sine centered on bin 8
+        let signal: Vec<f32> = (0..RENDER_QUANTUM_SIZE * 4)
+            .map(|i| (2. * PI * 8. * i as f32 / 256.).sin())
+            .collect();
+
+        signal.chunks(RENDER_QUANTUM_SIZE).for_each(|chunk| {
+            let mut quantum = [0.; RENDER_QUANTUM_SIZE];
+            quantum.copy_from_slice(chunk);
+            sender.send(quantum).unwrap();
+        });
+        drop(sender);
+
+        // (512 - 256) / 64 + 1 frames
+        let frames: Vec<_> = spectrogram.by_ref().collect();
+        assert_eq!(frames.len(), 5);
+
+        frames.iter().for_each(|frame| {
+            assert_eq!(frame.len(), 128);
+            let (max_bin, _) =
+                frame
+                    .iter()
+                    .enumerate()
+                    .fold((0, 0.), |acc, (i, &v)| if v > acc.1 { (i, v) } else { acc });
+            assert_eq!(max_bin, 8);
+        });
+
+        assert!(spectrogram.try_next().is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_spectrogram_invalid_hop_size() {
+        let (_sender, receiver) = crossbeam_channel::bounded(1);
+        let options = SpectrogramOptions {
+            hop_size: 0,
+            ..SpectrogramOptions::default()
+        };
+        let _ = Spectrogram::new(options, receiver, SpectrogramSlot(Weak::new()));
+    }
+
+    #[test]
+    fn test_get_pitch_silence() {
+        let analyser = Analyser::new();
+        assert_eq!(analyser.get_pitch(44100.), None);
+    }
+
     // this mostly tries to show that it works concurrently and we don't fall into
     // SEGFAULT traps or something, but this is difficult to really test something
     // in an accurante way, other tests are there for such thing
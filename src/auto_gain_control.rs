@@ -0,0 +1,259 @@
+//! Automatic gain control of media streams
+//!
+//! The processing is only compiled with an audio backend, it is applied to the microphone
+//! streams of [`get_user_media_sync`](crate::media_devices::get_user_media_sync).
+#[cfg(any(test, feature = "cpal", feature = "cubeb"))]
+use crate::{AudioBuffer, FallibleBuffer};
+
+#[cfg(any(test, feature = "cpal", feature = "cubeb"))]
+/// Time constant of the level detection, in seconds
+const LEVEL_TIME_CONSTANT: f64 = 0.1;
+#[cfg(any(test, feature = "cpal", feature = "cubeb"))]
+/// Level below which the input is considered silent and the gain is held, in dBFS
+const SILENCE_THRESHOLD: f32 = -60.;
+
+/// Options for the automatic gain control of a media stream, applied when the
+/// [`auto_gain_control`](crate::media_devices::MediaTrackConstraints::auto_gain_control)
+/// constraint is set
+#[derive(Clone, Debug)]
+pub struct AutoGainControlOptions {
+    /// Target RMS level of the output, in dBFS
+    pub target_level: f32,
+    /// Maximum gain applied to quiet input, and maximum attenuation of loud input, in dB
+    pub max_gain: f32,
+    /// Time constant of the gain reduction for louder input, in seconds
+    pub attack: f64,
+    /// Time constant of the gain increase for quieter input, in seconds
+    pub decay: f64,
+}
+
+impl Default for AutoGainControlOptions {
+    fn default() -> Self {
+        Self {
+            target_level: -18.,
+            max_gain: 30.,
+            attack: 0.1,
+            decay: 2.,
+        }
+    }
+}
+
+impl AutoGainControlOptions {
+    /// Panics on invalid options, see
+    /// [`get_user_media_sync`](crate::media_devices::get_user_media_sync)
+    pub(crate) fn validate(&self) {
+        assert!(
+            self.target_level <= 0.,
+            "RangeError - Invalid target level: {:?}, should be at most 0 dBFS",
+            self.target_level
+        );
+        assert!(
+            self.max_gain >= 0.,
+            "RangeError - Invalid max gain: {:?}, should be positive",
+            self.max_gain
+        );
+        assert!(
+            self.attack > 0. && self.decay > 0.,
+            "RangeError - Invalid attack {:?} or decay {:?}, should be strictly positive",
+            self.attack,
+            self.decay
+        );
+    }
+}
+
+#[cfg(any(test, feature = "cpal", feature = "cubeb"))]
+/// Smoothing coefficient per sample of a time constant, in seconds
+fn coefficient(time_constant: f64, sample_rate: f32) -> f32 {
+    (1. - (-1. / (time_constant * f64::from(sample_rate))).exp()) as f32
+}
+
+#[cfg(any(test, feature = "cpal", feature = "cubeb"))]
+/// Slow gain control, bringing the level of the input to the target level
+///
+/// A single gain is applied to all channels, preserving the stereo image.
+pub(crate) struct AutoGainControl {
+    options: AutoGainControlOptions,
+    /// smoothed mean square of the input
+    mean_square: f32,
+    /// current gain, in dB
+    gain: f32,
+    sample_rate: f32,
+    level_coefficient: f32,
+    attack_coefficient: f32,
+    decay_coefficient: f32,
+}
+
+#[cfg(any(test, feature = "cpal", feature = "cubeb"))]
+impl AutoGainControl {
+    pub fn new(options: AutoGainControlOptions, sample_rate: f32) -> Self {
+        Self {
+            level_coefficient: coefficient(LEVEL_TIME_CONSTANT, sample_rate),
+            attack_coefficient: coefficient(options.attack, sample_rate),
+            decay_coefficient: coefficient(options.decay, sample_rate),
+            options,
+            mean_square: 0.,
+            gain: 0.,
+            sample_rate,
+        }
+    }
+
+    /// Apply the gain to the `frame` of samples of each channel
+    pub fn process(&mut self, frame: &mut [f32]) {
+        let power = frame.iter().map(|v| v * v).sum::<f32>() / frame.len() as f32;
+        self.mean_square += self.level_coefficient * (power - self.mean_square);
+
+        let level = 10. * self.mean_square.max(f32::MIN_POSITIVE).log10();
+        // do not amplify silence, or the noise in between words
+        if level > SILENCE_THRESHOLD {
+            let target = (self.options.target_level - level)
+                .clamp(-self.options.max_gain, self.options.max_gain);
+            let coefficient = if target < self.gain {
+                self.attack_coefficient
+            } else {
+                self.decay_coefficient
+            };
+            self.gain += coefficient * (target - self.gain);
+        }
+
+        let gain = 10_f32.powf(self.gain / 20.);
+        frame.iter_mut().for_each(|v| *v *= gain);
+    }
+}
+
+#[cfg(any(test, feature = "cpal", feature = "cubeb"))]
+/// Media stream with the automatic gain control applied
+pub(crate) struct AutoGainControlStream<I> {
+    input: I,
+    options: AutoGainControlOptions,
+    /// set up with the first buffer
+    control: Option<AutoGainControl>,
+}
+
+#[cfg(any(test, feature = "cpal", feature = "cubeb"))]
+impl<I> AutoGainControlStream<I> {
+    pub fn new(input: I, options: AutoGainControlOptions) -> Self {
+        Self {
+            input,
+            options,
+            control: None,
+        }
+    }
+}
+
+#[cfg(any(test, feature = "cpal", feature = "cubeb"))]
+impl<I: Iterator<Item = FallibleBuffer>> Iterator for AutoGainControlStream<I> {
+    type Item = FallibleBuffer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buffer = match self.input.next()? {
+            Ok(buffer) => buffer,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if buffer.length() == 0 {
+            return Some(Ok(buffer));
+        }
+
+        let sample_rate = buffer.sample_rate();
+        let control = match &mut self.control {
+            Some(control) if control.sample_rate == sample_rate => control,
+            control => control.insert(AutoGainControl::new(self.options.clone(), sample_rate)),
+        };
+
+        let number_of_channels = buffer.number_of_channels();
+        let mut channels: Vec<Vec<f32>> = (0..number_of_channels)
+            .map(|i| buffer.get_channel_data(i).to_vec())
+            .collect();
+        let mut frame = vec![0.; number_of_channels];
+
+        for i in 0..buffer.length() {
+            frame
+                .iter_mut()
+                .zip(&channels)
+                .for_each(|(f, channel)| *f = channel[i]);
+            control.process(&mut frame);
+            channels
+                .iter_mut()
+                .zip(&frame)
+                .for_each(|(channel, f)| channel[i] = *f);
+        }
+
+        Some(Ok(AudioBuffer::from(channels, sample_rate)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sine wave with the given RMS level in dBFS
+    fn sine(len: usize, level: f32) -> Vec<f32> {
+        let amplitude = 10_f32.powf(level / 20.) * std::f32::consts::SQRT_2;
+        (0..len)
+            .map(|i| amplitude * (i as f32 * 0.05).sin())
+            .collect()
+    }
+
+    fn level(samples: &[f32]) -> f32 {
+        let mean_square = samples.iter().map(|v| v * v).sum::<f32>() / samples.len() as f32;
+        10. * mean_square.log10()
+    }
+
+    fn process(input: &[f32], options: AutoGainControlOptions) -> Vec<f32> {
+        let chunks: Vec<FallibleBuffer> = input
+            .chunks(480)
+            .map(|chunk| Ok(AudioBuffer::from(vec![chunk.to_vec()], 48_000.)))
+            .collect();
+        AutoGainControlStream::new(chunks.into_iter(), options)
+            .flat_map(|buffer| buffer.unwrap().get_channel_data(0).to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_raise_quiet_input() {
+        let input = sine(48_000 * 15, -40.);
+        let output = process(&input, AutoGainControlOptions::default());
+
+        let level = level(&output[48_000 * 14..]);
+        assert!((level + 18.).abs() < 1., "level {}", level);
+    }
+
+    #[test]
+    fn test_reduce_loud_input() {
+        let input = sine(48_000 * 2, -3.);
+        let output = process(&input, AutoGainControlOptions::default());
+
+        let level = level(&output[48_000..]);
+        assert!((level + 18.).abs() < 1., "level {}", level);
+    }
+
+    #[test]
+    fn test_max_gain() {
+        let input = sine(48_000 * 15, -50.);
+        let options = AutoGainControlOptions {
+            max_gain: 12.,
+            ..AutoGainControlOptions::default()
+        };
+        let output = process(&input, options);
+
+        let level = level(&output[48_000 * 14..]);
+        assert!((level + 38.).abs() < 1., "level {}", level);
+    }
+
+    #[test]
+    fn test_silence() {
+        let input = sine(48_000 * 5, -80.);
+        let output = process(&input, AutoGainControlOptions::default());
+        assert_eq!(input, output);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_options() {
+        let options = AutoGainControlOptions {
+            attack: 0.,
+            ..AutoGainControlOptions::default()
+        };
+        options.validate();
+    }
+}
@@ -1,6 +1,7 @@
 //! General purpose audio signal data structures
 use std::sync::Arc;
 
+use crate::resampling::sinc_resample;
 use crate::{
     assert_valid_channel_number, assert_valid_number_of_channels, assert_valid_sample_rate,
 };
@@ -94,6 +95,7 @@ impl AudioBuffer {
     /// Convert raw samples to an AudioBuffer
     ///
     /// The outer Vec determine the channels. The inner Vecs should have the same length.
+    /// The samples are moved into the AudioBuffer, no copy is involved.
     ///
     /// # Panics
     ///
@@ -117,6 +119,104 @@ impl AudioBuffer {
         }
     }
 
+    /// Convert interleaved samples to an AudioBuffer
+    ///
+    /// The samples are laid out frame by frame, e.g. `[l0, r0, l1, r1, ...]` for stereo data.
+    /// They are deinterleaved in a single pass, directly into the channels of the AudioBuffer.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if:
+    /// - the given sample rate is zero
+    /// - the given number of channels is outside the [1, 32] range,
+    ///   32 being defined by the MAX_CHANNELS constant.
+    /// - the number of samples is not a multiple of the number of channels
+    pub fn from_interleaved(samples: &[f32], number_of_channels: usize, sample_rate: f32) -> Self {
+        assert_valid_sample_rate(sample_rate);
+        assert_valid_number_of_channels(number_of_channels);
+
+        let length = samples.len() / number_of_channels;
+        if length * number_of_channels != samples.len() {
+            panic!(
+                "IndexSizeError - Invalid interleaved data length {:?} for {:?} channels",
+                samples.len(),
+                number_of_channels
+            );
+        }
+
+        let channels = (0..number_of_channels)
+            .map(|channel_number| {
+                let data: Vec<f32> = samples
+                    .iter()
+                    .skip(channel_number)
+                    .step_by(number_of_channels)
+                    .copied()
+                    .collect();
+                debug_assert_eq!(data.len(), length);
+                ChannelData::from(data)
+            })
+            .collect();
+
+        Self {
+            channels,
+            sample_rate,
+        }
+    }
+
+    /// Create an AudioBuffer sharing its channels memory with the given samples
+    ///
+    /// This allows multiple AudioBuffers (and the application) to refer to a large asset
+    /// without copying it. The AudioBuffer has copy-on-write semantics: the shared memory
+    /// is never written to, mutating the AudioBuffer first makes a private copy of the
+    /// mutated channel.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if:
+    /// - the given sample rate is zero
+    /// - the given number of channels defined by `channels.len()`is outside the
+    ///   [1, 32] range, 32 being defined by the MAX_CHANNELS constant.
+    /// - any of its items have different lengths
+    pub fn from_shared(channels: Vec<Arc<Vec<f32>>>, sample_rate: f32) -> Self {
+        assert_valid_sample_rate(sample_rate);
+        assert_valid_number_of_channels(channels.len());
+
+        let channels: Vec<_> = channels
+            .into_iter()
+            .map(|data| ChannelData {
+                data: ChannelStorage::Owned(data),
+            })
+            .collect();
+        if !channels.iter().all(|c| c.len() == channels[0].len()) {
+            panic!("Trying to create AudioBuffer from channel data with unequal length");
+        }
+
+        Self {
+            channels,
+            sample_rate,
+        }
+    }
+
+    /// Copy the samples of all channels to a Vec, interleaved frame by frame
+    pub fn to_interleaved(&self) -> Vec<f32> {
+        let number_of_channels = self.number_of_channels();
+        let mut interleaved = vec![0.; self.length() * number_of_channels];
+
+        self.channels
+            .iter()
+            .enumerate()
+            .for_each(|(channel_number, channel)| {
+                interleaved
+                    .iter_mut()
+                    .skip(channel_number)
+                    .step_by(number_of_channels)
+                    .zip(channel.as_slice())
+                    .for_each(|(o, i)| *o = *i);
+            });
+
+        interleaved
+    }
+
     /// Number of channels in this `AudioBuffer`
     pub fn number_of_channels(&self) -> usize {
         self.channels.len()
@@ -139,18 +239,24 @@ impl AudioBuffer {
 
     /// Copy data from a given channel to the given `Vec`
     ///
+    /// Returns the number of frames copied, see
+    /// [`copy_from_channel_with_offset`](Self::copy_from_channel_with_offset).
+    ///
     /// # Panics
     ///
     /// This function will panic if `channel_number` is greater or equal than
     /// `AudioBuffer::number_of_channels()`
-    pub fn copy_from_channel(&self, destination: &mut [f32], channel_number: usize) {
-        assert_valid_channel_number(channel_number, self.number_of_channels());
-
-        self.copy_from_channel_with_offset(destination, channel_number, 0);
+    pub fn copy_from_channel(&self, destination: &mut [f32], channel_number: usize) -> usize {
+        self.copy_from_channel_with_offset(destination, channel_number, 0)
     }
 
     /// Copy data from a given channel to the given `Vec` starting at `offset`
     ///
+    /// The number of frames copied is the smallest of the destination length and the number of
+    /// frames in the buffer after `offset`. The remaining elements of `destination` are not
+    /// modified. Returns the number of frames copied, which allows the buffer to be read in
+    /// consecutive chunks until it is exhausted.
+    ///
     /// # Panics
     ///
     /// This function will panic if:
@@ -160,34 +266,41 @@ impl AudioBuffer {
         destination: &mut [f32],
         channel_number: usize,
         offset: usize,
-    ) {
+    ) -> usize {
         assert_valid_channel_number(channel_number, self.number_of_channels());
         let offset = offset.min(self.length());
         // [spec] Let buffer be the AudioBuffer with 𝑁𝑏 frames, let 𝑁𝑓 be the number
         // of elements in the destination array, and 𝑘 be the value of bufferOffset.
         // Then the number of frames copied from buffer to destination is max(0,min(𝑁𝑏−𝑘,𝑁𝑓)).
         // If this is less than 𝑁𝑓, then the remaining elements of destination are not modified.
-        let dest_length = destination.len();
-        let max_frame = (self.length() - offset).clamp(0, dest_length);
+        let max_frame = (self.length() - offset).min(destination.len());
         let channel = self.channel_data(channel_number).as_slice();
 
         destination[..max_frame].copy_from_slice(&channel[offset..(max_frame + offset)]);
+
+        max_frame
     }
 
     /// Copy data from a given source to the given channel.
     ///
+    /// Returns the number of frames copied, see
+    /// [`copy_to_channel_with_offset`](Self::copy_to_channel_with_offset).
+    ///
     /// # Panics
     ///
     /// This function will panic if:
     /// - the given channel number is greater than or equal to the given number of channels.
-    pub fn copy_to_channel(&mut self, source: &[f32], channel_number: usize) {
-        assert_valid_channel_number(channel_number, self.number_of_channels());
-
-        self.copy_to_channel_with_offset(source, channel_number, 0);
+    pub fn copy_to_channel(&mut self, source: &[f32], channel_number: usize) -> usize {
+        self.copy_to_channel_with_offset(source, channel_number, 0)
     }
 
     /// Copy data from a given source to the given channel starting at `offset`.
     ///
+    /// The number of frames copied is the smallest of the source length and the number of
+    /// frames in the buffer after `offset`. The remaining frames of the buffer are not
+    /// modified. Returns the number of frames copied, which allows the buffer to be filled in
+    /// consecutive chunks until it is full.
+    ///
     /// # Panics
     ///
     /// This function will panic if:
@@ -197,18 +310,19 @@ impl AudioBuffer {
         source: &[f32],
         channel_number: usize,
         offset: usize,
-    ) {
+    ) -> usize {
         assert_valid_channel_number(channel_number, self.number_of_channels());
         let offset = offset.min(self.length());
         // [spec] Let buffer be the AudioBuffer with 𝑁𝑏 frames, let 𝑁𝑓 be the number
         // of elements in the source array, and 𝑘 be the value of bufferOffset. Then
         // the number of frames copied from source to the buffer is max(0,min(𝑁𝑏−𝑘,𝑁𝑓)).
         // If this is less than 𝑁𝑓, then the remaining elements of buffer are not modified.
-        let src_len = source.len();
-        let max_frame = (self.length() - offset).clamp(0, src_len);
+        let max_frame = (self.length() - offset).min(source.len());
         let channel = self.channel_data_mut(channel_number).as_mut_slice();
 
         channel[offset..(max_frame + offset)].copy_from_slice(&source[..max_frame]);
+
+        max_frame
     }
 
     /// Return a read-only copy of the underlying data of the channel
@@ -280,33 +394,98 @@ impl AudioBuffer {
         data.iter_mut()
             .zip(other.channels.iter())
             .for_each(|(channel, other_channel)| {
-                let cur_channel_data = Arc::make_mut(&mut channel.data);
+                let cur_channel_data = channel.make_mut();
                 cur_channel_data.extend(other_channel.as_slice());
             })
     }
 
+    /// Concatenate AudioBuffers, returns `None` if `buffers` is empty
+    ///
+    /// Contrary to repeated calls to [`extend`](Self::extend), each channel is allocated once.
+    ///
+    /// This function will panic if the sample_rate and channel_count are not equal
+    pub(crate) fn concat(buffers: &[Self]) -> Option<Self> {
+        let first = buffers.first()?;
+        let length = buffers.iter().map(Self::length).sum();
+
+        let channels = (0..first.number_of_channels())
+            .map(|channel_number| {
+                let mut samples = Vec::with_capacity(length);
+                buffers.iter().for_each(|buffer| {
+                    assert_eq!(first.sample_rate, buffer.sample_rate);
+                    assert_eq!(first.number_of_channels(), buffer.number_of_channels());
+                    samples.extend_from_slice(buffer.get_channel_data(channel_number));
+                });
+                ChannelData::from(samples)
+            })
+            .collect();
+
+        Some(Self::from_channels(channels, first.sample_rate))
+    }
+
     /// Split an AudioBuffer in two at the given index.
     pub(crate) fn split_off(&mut self, index: usize) -> Self {
         let channels: Vec<_> = self
             .channels_mut()
             .iter_mut()
-            .map(|channel_data| Arc::make_mut(&mut channel_data.data).split_off(index))
+            .map(|channel_data| channel_data.make_mut().split_off(index))
             .map(ChannelData::from)
             .collect();
 
         AudioBuffer::from_channels(channels, self.sample_rate)
     }
 
+    /// Resample to the desired sample rate
+    ///
+    /// The method performs a band-limited (windowed sinc) interpolation, which
+    /// is suitable to adapt assets to the sample rate of an audio context without
+    /// audible artifacts. When downsampling, the content above the new Nyquist
+    /// frequency is filtered out. The new number of samples is always ceiled
+    /// according the ratio defined by old and new sample rates.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if:
+    /// - the given sample rate is zero
+    pub fn resample(&mut self, sample_rate: f32) {
+        assert_valid_sample_rate(sample_rate);
+
+        // if requested sample rate is very similar, do not resample
+        if float_eq::float_eq!(self.sample_rate, sample_rate, abs <= 0.1) {
+            self.sample_rate = sample_rate;
+            return;
+        }
+
+        // handle zero length case
+        if self.length() == 0 {
+            self.sample_rate = sample_rate;
+            return;
+        }
+
+        let source_sr = self.sample_rate as f64;
+        let target_sr = sample_rate as f64;
+
+        self.channels.iter_mut().for_each(|channel_data| {
+            let resampled = sinc_resample(channel_data.as_slice(), source_sr, target_sr);
+            *channel_data = ChannelData::from(resampled);
+        });
+
+        self.sample_rate = sample_rate;
+    }
+
     /// Resample to the desired sample rate. The method performs a simple linear
     /// interpolation an keep the first and last sample intacts. The new number
     /// of samples is always ceiled according the ratio defined by old and new
     /// sample rates.
     ///
+    /// This is cheaper than [`Self::resample`] and operates on isolated chunks,
+    /// which makes it suitable for streams.
+    ///
     /// # Panics
     ///
     /// This function will panic if:
     /// - the given sample rate is zero
-    pub(crate) fn resample(&mut self, sample_rate: f32) {
+    pub(crate) fn resample_linear(&mut self, sample_rate: f32) {
         assert_valid_sample_rate(sample_rate);
 
         // if requested sample rate is very similar, do not resample
@@ -342,8 +521,8 @@ impl AudioBuffer {
             let k_inv = 1. - k;
 
             for (channel, resampled_data) in resampled.iter_mut().enumerate() {
-                let prev_sample = self.channels[channel].data[prev_index];
-                let next_sample = self.channels[channel].data[next_index];
+                let prev_sample = self.channels[channel].as_slice()[prev_index];
+                let next_sample = self.channels[channel].as_slice()[next_index];
 
                 let value = k_inv * prev_sample + k * next_sample;
                 resampled_data.push(value);
@@ -354,51 +533,89 @@ impl AudioBuffer {
             .iter_mut()
             .zip(resampled)
             .for_each(|(channel_data, resampled_data)| {
-                channel_data.data = Arc::new(resampled_data);
+                *channel_data = ChannelData::from(resampled_data);
             });
 
         self.sample_rate = sample_rate;
     }
 }
 
+/// Storage of the samples of a channel
+#[derive(Clone, Debug)]
+enum ChannelStorage {
+    Owned(Arc<Vec<f32>>),
+    #[cfg(feature = "mmap")]
+    Mapped(crate::mmap::MappedChannel),
+}
+
 /// Single channel audio samples, basically wraps a `Arc<Vec<f32>>`
 ///
-/// ChannelData has copy-on-write semantics, so it is cheap to clone.
-#[derive(Clone, Debug, PartialEq)]
+/// ChannelData has copy-on-write semantics, so it is cheap to clone. The samples may also be
+/// memory-mapped from a file, in which case mutating them first makes a private copy.
+#[derive(Clone, Debug)]
 pub(crate) struct ChannelData {
-    data: Arc<Vec<f32>>,
+    data: ChannelStorage,
+}
+
+impl PartialEq for ChannelData {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
 }
 
 impl ChannelData {
     pub fn new(length: usize) -> Self {
         let buffer = vec![0.; length];
-        let data = Arc::new(buffer);
-
-        Self { data }
+        Self::from(buffer)
     }
 
     pub fn from(data: Vec<f32>) -> Self {
         Self {
-            data: Arc::new(data),
+            data: ChannelStorage::Owned(Arc::new(data)),
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    pub(crate) fn from_mapped(mapped: crate::mmap::MappedChannel) -> Self {
+        Self {
+            data: ChannelStorage::Mapped(mapped),
         }
     }
 
     pub fn len(&self) -> usize {
-        self.data.len()
+        self.as_slice().len()
     }
 
     // clippy wants to keep it, so keep it :)
     #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.as_slice().is_empty()
     }
 
     pub fn as_slice(&self) -> &[f32] {
-        &self.data[..]
+        match &self.data {
+            ChannelStorage::Owned(data) => &data[..],
+            #[cfg(feature = "mmap")]
+            ChannelStorage::Mapped(mapped) => mapped.as_slice(),
+        }
     }
 
     pub fn as_mut_slice(&mut self) -> &mut [f32] {
-        &mut Arc::make_mut(&mut self.data)[..]
+        &mut self.make_mut()[..]
+    }
+
+    /// Mutable access to the samples, copying them if they are shared or mapped
+    fn make_mut(&mut self) -> &mut Vec<f32> {
+        #[cfg(feature = "mmap")]
+        if let ChannelStorage::Mapped(mapped) = &self.data {
+            self.data = ChannelStorage::Owned(Arc::new(mapped.as_slice().to_vec()));
+        }
+
+        match &mut self.data {
+            ChannelStorage::Owned(data) => Arc::make_mut(data),
+            #[cfg(feature = "mmap")]
+            ChannelStorage::Mapped(_) => unreachable!(),
+        }
     }
 }
 
@@ -467,6 +684,69 @@ mod tests {
         AudioBuffer::from(samples, sample_rate); // should panic
     }
 
+    #[test]
+    fn test_from_interleaved() {
+        let samples = [0., 1., 2., 10., 11., 12.];
+
+        let audio_buffer = AudioBuffer::from_interleaved(&samples, 3, 48000.);
+        assert_eq!(audio_buffer.number_of_channels(), 3);
+        assert_eq!(audio_buffer.length(), 2);
+        assert_float_eq!(
+            audio_buffer.get_channel_data(0),
+            &[0., 10.][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            audio_buffer.get_channel_data(1),
+            &[1., 11.][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            audio_buffer.get_channel_data(2),
+            &[2., 12.][..],
+            abs_all <= 0.
+        );
+
+        assert_float_eq!(
+            audio_buffer.to_interleaved()[..],
+            samples[..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_interleaved_invalid_length() {
+        let samples = [0., 1., 2., 10., 11.];
+        AudioBuffer::from_interleaved(&samples, 3, 48000.); // should panic
+    }
+
+    #[test]
+    fn test_from_shared() {
+        let data = Arc::new(vec![1.; 10]);
+
+        let mut audio_buffer = AudioBuffer::from_shared(vec![Arc::clone(&data)], 48000.);
+        assert_eq!(Arc::strong_count(&data), 2);
+        assert_eq!(audio_buffer.get_channel_data(0).as_ptr(), data.as_ptr());
+
+        // copy on write, the shared memory is left untouched
+        audio_buffer.get_channel_data_mut(0).fill(0.);
+        assert_eq!(Arc::strong_count(&data), 1);
+        assert_float_eq!(data[..], [1.; 10][..], abs_all <= 0.);
+        assert_float_eq!(
+            audio_buffer.get_channel_data(0),
+            &[0.; 10][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_shared_unequal_length() {
+        let channels = vec![Arc::new(vec![1.; 10]), Arc::new(vec![1.; 9])];
+        AudioBuffer::from_shared(channels, 48000.); // should panic
+    }
+
     #[test]
     fn test_channel_data_get_set() {
         let options = AudioBufferOptions {
@@ -485,7 +765,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "IndexSizeError")]
     fn test_invalid_copy_from_channel() {
         let options = AudioBufferOptions {
             number_of_channels: 1,
@@ -546,7 +826,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "IndexSizeError")]
     fn test_invalid_copy_to_channel() {
         let options = AudioBufferOptions {
             number_of_channels: 1,
@@ -630,6 +910,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_copy_channel_in_chunks() {
+        let data: Vec<f32> = (0..10).map(|i| i as f32).collect();
+        let source = AudioBuffer::from(vec![data.clone()], 48000.);
+        let mut destination = AudioBuffer::new(AudioBufferOptions {
+            number_of_channels: 1,
+            length: 10,
+            sample_rate: 48000.,
+        });
+
+        let mut chunk = [0.; 4];
+        let mut offset = 0;
+        let mut copied = vec![];
+
+        loop {
+            let read = source.copy_from_channel_with_offset(&mut chunk, 0, offset);
+            let written = destination.copy_to_channel_with_offset(&chunk[..read], 0, offset);
+            assert_eq!(read, written);
+            copied.push(read);
+
+            if read < chunk.len() {
+                break;
+            }
+            offset += read;
+        }
+
+        assert_eq!(copied, vec![4, 4, 2]);
+        assert_float_eq!(destination.get_channel_data(0), &data[..], abs_all <= 0.);
+
+        // nothing left to copy past the end
+        assert_eq!(source.copy_from_channel_with_offset(&mut chunk, 0, 10), 0);
+        assert_eq!(destination.copy_to_channel_with_offset(&chunk, 0, 11), 0);
+    }
+
     #[test]
     #[should_panic]
     fn test_invalid_get_channel_data() {
@@ -692,33 +1006,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_concat_and_split_off() {
+        let b1 = AudioBuffer::from(vec![vec![1., 2.], vec![3., 4.]], 44100.);
+        let b2 = AudioBuffer::from(vec![vec![5.], vec![6.]], 44100.);
+        assert!(AudioBuffer::concat(&[]).is_none());
+
+        let mut buffer = AudioBuffer::concat(&[b1, b2]).unwrap();
+        assert_float_eq!(buffer.get_channel_data(0), &[1., 2., 5.][..], abs_all <= 0.);
+        assert_float_eq!(buffer.get_channel_data(1), &[3., 4., 6.][..], abs_all <= 0.);
+
+        let tail = buffer.split_off(1);
+        assert_float_eq!(buffer.get_channel_data(0), &[1.][..], abs_all <= 0.);
+        assert_float_eq!(tail.get_channel_data(1), &[4., 6.][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_extend_in_place() {
+        let mut samples = Vec::with_capacity(20);
+        samples.resize(10, 1.);
+        let mut buffer = AudioBuffer::from(vec![samples], 44100.);
+        let ptr = buffer.get_channel_data(0).as_ptr();
+
+        // the last owner reuses the allocation of the channel
+        let other = AudioBuffer::from(vec![vec![2.; 10]], 44100.);
+        buffer.extend(&other);
+        assert_eq!(buffer.get_channel_data(0).as_ptr(), ptr);
+
+        let tail = buffer.split_off(10);
+        assert_eq!(buffer.get_channel_data(0).as_ptr(), ptr);
+        assert_float_eq!(tail.get_channel_data(0)[0], 2., abs <= 0.);
+    }
+
+    #[test]
+    fn test_clone_shares_memory() {
+        let buffer = AudioBuffer::from(vec![vec![1.; 10]; 2], 44100.);
+        let mut clone = buffer.clone();
+        assert_eq!(
+            clone.get_channel_data(0).as_ptr(),
+            buffer.get_channel_data(0).as_ptr()
+        );
+
+        // copy on write, only the mutated channel is copied
+        clone.get_channel_data_mut(0)[0] = 0.;
+        assert_ne!(
+            clone.get_channel_data(0).as_ptr(),
+            buffer.get_channel_data(0).as_ptr()
+        );
+        assert_eq!(
+            clone.get_channel_data(1).as_ptr(),
+            buffer.get_channel_data(1).as_ptr()
+        );
+        assert_float_eq!(buffer.get_channel_data(0)[0], 1., abs <= 0.);
+
+        // the last owner mutates in place
+        let ptr = clone.get_channel_data(0).as_ptr();
+        clone.get_channel_data_mut(0)[1] = 0.;
+        assert_eq!(clone.get_channel_data(0).as_ptr(), ptr);
+    }
+
     #[test]
     #[should_panic]
-    fn test_resample_to_zero_hertz() {
+    fn test_resample_to_zero_hertz_linear() {
         let channel = ChannelData::from(vec![1., 2., 3., 4., 5.]);
         let mut buffer = AudioBuffer::from_channels(vec![channel], 48000.);
-        buffer.resample(0.);
+        buffer.resample_linear(0.);
     }
 
     #[test]
-    fn test_resample_from_empty() {
+    fn test_resample_from_empty_linear() {
         let options = AudioBufferOptions {
             number_of_channels: 1,
             length: 0,
             sample_rate: 48000.,
         };
         let mut buffer = AudioBuffer::new(options);
-        buffer.resample(48000.);
+        buffer.resample_linear(48000.);
 
         assert_eq!(buffer.length(), 0);
         assert_float_eq!(buffer.sample_rate, 48000., abs_all <= 0.);
     }
 
     #[test]
-    fn test_upsample() {
+    fn test_upsample_linear() {
         let channel = ChannelData::from(vec![1., 2., 3., 4., 5.]);
         let mut buffer = AudioBuffer::from_channels(vec![channel], 48000.);
-        buffer.resample(96000.); // double
+        buffer.resample_linear(96000.); // double
 
         let mut expected = [0.; 10];
         let incr = 4. / 9.; // (5 - 1) / (10 - 1)
@@ -737,10 +1110,10 @@ mod tests {
     }
 
     #[test]
-    fn test_downsample() {
+    fn test_downsample_linear() {
         let channel = ChannelData::from(vec![1., 2., 3., 4., 5.]);
         let mut buffer = AudioBuffer::from_channels(vec![channel], 96000.);
-        buffer.resample(48000.); // half
+        buffer.resample_linear(48000.); // half
 
         assert_float_eq!(
             buffer.channel_data(0).as_slice(),
@@ -752,7 +1125,7 @@ mod tests {
     }
 
     #[test]
-    fn test_resample_stereo() {
+    fn test_resample_stereo_linear() {
         [22500, 38000, 48000, 96000].iter().for_each(|sr| {
             let source_sr = *sr;
             let target_sr = 44_100;
@@ -770,7 +1143,7 @@ mod tests {
             let right_chan = ChannelData::from(right);
             let mut buffer =
                 AudioBuffer::from_channels(vec![left_chan, right_chan], source_sr as f32);
-            buffer.resample(target_sr as f32);
+            buffer.resample_linear(target_sr as f32);
 
             let mut expected_left = vec![];
             let mut expected_right = vec![];
@@ -796,4 +1169,79 @@ mod tests {
             assert_float_eq!(buffer.sample_rate, target_sr as f32, abs_all <= 0.);
         });
     }
+
+    #[test]
+    #[should_panic]
+    fn test_resample_to_zero_hertz() {
+        let mut buffer = AudioBuffer::from(vec![vec![1., 2., 3., 4., 5.]], 48000.);
+        buffer.resample(0.);
+    }
+
+    #[test]
+    fn test_resample_from_empty() {
+        let mut buffer = AudioBuffer::from(vec![vec![]], 48000.);
+        buffer.resample(44100.);
+
+        assert_eq!(buffer.length(), 0);
+        assert_float_eq!(buffer.sample_rate(), 44100., abs <= 0.);
+    }
+
+    #[test]
+    fn test_resample_sine() {
+        [
+            (48000, 44100),
+            (44100, 48000),
+            (22050, 96000),
+            (96000, 32000),
+        ]
+        .iter()
+        .for_each(|&(source_sr, target_sr)| {
+            let frequency = 1000.;
+
+            let sine = |sample_rate: usize| -> Vec<f32> {
+                (0..sample_rate)
+                    .map(|i| {
+                        let phase = i as f64 / sample_rate as f64 * 2. * PI as f64 * frequency;
+                        phase.sin() as f32
+                    })
+                    .collect()
+            };
+
+            let mut buffer = AudioBuffer::from(vec![sine(source_sr)], source_sr as f32);
+            buffer.resample(target_sr as f32);
+
+            assert_eq!(buffer.length(), target_sr);
+            assert_float_eq!(buffer.sample_rate(), target_sr as f32, abs <= 0.);
+
+            // ignore edges, where the signal is abruptly cut
+            let expected = sine(target_sr);
+            let margin = target_sr / 100;
+            assert_float_eq!(
+                buffer.get_channel_data(0)[margin..target_sr - margin],
+                expected[margin..target_sr - margin],
+                abs_all <= 1e-3
+            );
+        });
+    }
+
+    #[test]
+    fn test_resample_removes_aliases() {
+        let source_sr = 96000;
+        let target_sr = 48000;
+
+        // 30kHz is above the Nyquist frequency of the target sample rate
+        let samples: Vec<f32> = (0..source_sr)
+            .map(|i| {
+                (i as f64 / source_sr as f64 * 2. * std::f64::consts::PI * 30000.).sin() as f32
+            })
+            .collect();
+
+        let mut buffer = AudioBuffer::from(vec![samples], source_sr as f32);
+        buffer.resample(target_sr as f32);
+
+        let margin = target_sr / 100;
+        let data = &buffer.get_channel_data(0)[margin..target_sr - margin];
+        let rms = (data.iter().map(|s| s * s).sum::<f32>() / data.len() as f32).sqrt();
+        assert!(rms < 1e-3, "rms {}", rms);
+    }
 }
@@ -0,0 +1,489 @@
+//! Recording the output of nodes, while playing or rendering offline
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::buffer::AudioBuffer;
+use crate::context::{AudioContext, AudioContextRegistration, AudioNodeId, BaseAudioContext};
+use crate::node::{
+    AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
+};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::wav::{self, WavSampleFormat};
+use crate::{assert_valid_number_of_channels, RENDER_QUANTUM_SIZE};
+
+/// Number of render quanta allocated up front, and recycled afterwards
+const PREALLOCATED_QUANTA: usize = 64;
+
+/// Outcome of the collector thread
+type CaptureResult = Result<Option<AudioBuffer>, Box<dyn Error + Send + Sync>>;
+
+/// Where the captured audio is stored, see [`CaptureOptions`]
+#[derive(Clone, Debug)]
+pub enum CaptureTarget {
+    /// Collect the audio in memory, it is returned by [`CaptureHandle::stop`]
+    AudioBuffer,
+    /// Write the audio to a WAV file while capturing
+    Wav {
+        /// Path of the file, an existing file is overwritten
+        path: PathBuf,
+        /// Sample format of the file
+        sample_format: WavSampleFormat,
+    },
+}
+
+/// Options for [`AudioContext::start_capture`]
+#[derive(Clone, Debug)]
+pub struct CaptureOptions {
+    /// Number of channels of the capture, the output of the node is up or down-mixed to it
+    /// (defaults to 2)
+    pub number_of_channels: usize,
+    /// Where the audio is stored (defaults to [`CaptureTarget::AudioBuffer`])
+    pub target: CaptureTarget,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            number_of_channels: 2,
+            target: CaptureTarget::AudioBuffer,
+        }
+    }
+}
+
+/// Handle of a running capture, created by [`AudioContext::start_capture`]
+///
+/// The capture runs until [`stop`](Self::stop) is called. Dropping the handle stops the
+/// capture too, the WAV file is completed but a captured [`AudioBuffer`] is lost.
+///
+/// The capture is connected to the captured node like any other node, so disconnecting all
+/// outputs of the captured node with [`AudioNode::disconnect`] ends the capture.
+pub struct CaptureHandle {
+    node: CaptureNode,
+    source: AudioNodeId,
+    stop: Option<Sender<()>>,
+    frames: Arc<AtomicUsize>,
+    collector: Option<JoinHandle<CaptureResult>>,
+}
+
+impl std::fmt::Debug for CaptureHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CaptureHandle")
+            .field("length", &self.length())
+            .finish_non_exhaustive()
+    }
+}
+
+impl CaptureHandle {
+    pub(crate) fn new(
+        context: &AudioContext,
+        source: &dyn AudioNode,
+        options: CaptureOptions,
+    ) -> std::io::Result<Self> {
+        let CaptureOptions {
+            number_of_channels,
+            target,
+        } = options;
+        assert_valid_number_of_channels(number_of_channels);
+        assert!(
+            source.number_of_outputs() > 0,
+            "InvalidAccessError - Cannot capture a node without outputs"
+        );
+
+        let sink = match target {
+            CaptureTarget::AudioBuffer => Sink::Buffer(vec![vec![]; number_of_channels]),
+            CaptureTarget::Wav {
+                path,
+                sample_format,
+            } => Sink::wav(
+                File::create(path)?,
+                number_of_channels,
+                context.sample_rate(),
+                sample_format,
+            )?,
+        };
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let (recycle_sender, recycle) = crossbeam_channel::bounded(PREALLOCATED_QUANTA);
+        for _ in 0..PREALLOCATED_QUANTA {
+            let _ = recycle_sender
+                .try_send(Vec::with_capacity(number_of_channels * RENDER_QUANTUM_SIZE));
+        }
+
+        let render = CaptureRenderer {
+            sender,
+            recycle,
+            number_of_channels,
+        };
+        let node = CaptureNode::new(context, number_of_channels, Box::new(render));
+        source.connect(&node);
+
+        let (stop, stopped) = crossbeam_channel::bounded(1);
+        let frames = Arc::new(AtomicUsize::new(0));
+        let collector = Collector {
+            receiver,
+            recycle: recycle_sender,
+            stopped,
+            frames: Arc::clone(&frames),
+            number_of_channels,
+            sink,
+        };
+        let sample_rate = context.sample_rate();
+        let collector = std::thread::Builder::new()
+            .name("web-audio-capture".into())
+            .spawn(move || collector.run(sample_rate))?;
+
+        Ok(Self {
+            node,
+            source: source.registration().id(),
+            stop: Some(stop),
+            frames,
+            collector: Some(collector),
+        })
+    }
+
+    /// Number of sample-frames captured so far
+    #[must_use]
+    pub fn length(&self) -> usize {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    /// Stop the capture, the captured audio is returned for [`CaptureTarget::AudioBuffer`]
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if writing to the WAV file failed.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the capture thread panicked.
+    pub fn stop(mut self) -> CaptureResult {
+        self.disconnect();
+        match self.collector.take().unwrap().join() {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e),
+        }
+    }
+
+    /// Release the capture node from the audio graph and signal the collector to finish
+    fn disconnect(&mut self) {
+        let registration = self.node.registration();
+        // the connection may have been removed already
+        registration
+            .context()
+            .disconnect(self.source, Some(0), Some(registration.id()), None);
+        self.stop.take();
+    }
+}
+
+impl Drop for CaptureHandle {
+    fn drop(&mut self) {
+        if self.stop.is_some() {
+            self.disconnect();
+        }
+    }
+}
+
+/// Sink node in the audio graph shipping the rendered quanta to the collector thread
+struct CaptureNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+}
+
+impl CaptureNode {
+    fn new<C: BaseAudioContext>(
+        context: &C,
+        number_of_channels: usize,
+        render: Box<dyn AudioProcessor>,
+    ) -> Self {
+        context.register(move |registration| {
+            let node = Self {
+                registration,
+                channel_config: ChannelConfigOptions {
+                    count: number_of_channels,
+                    count_mode: ChannelCountMode::Explicit,
+                    interpretation: ChannelInterpretation::Speakers,
+                }
+                .into(),
+            };
+            (node, render)
+        })
+    }
+}
+
+impl AudioNode for CaptureNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        0
+    }
+}
+
+struct CaptureRenderer {
+    sender: Sender<Vec<f32>>,
+    recycle: Receiver<Vec<f32>>,
+    number_of_channels: usize,
+}
+
+impl AudioProcessor for CaptureRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        _outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input, no output
+        let input = &inputs[0];
+
+        // planar samples, allocating only when the collector thread lags behind
+        let mut quantum = self.recycle.try_recv().unwrap_or_default();
+        quantum.clear();
+        for channel_number in 0..self.number_of_channels {
+            match input.channels().get(channel_number) {
+                Some(channel) => quantum.extend_from_slice(&channel[..]),
+                // silent inputs have a single channel
+                None => quantum.resize(quantum.len() + RENDER_QUANTUM_SIZE, 0.),
+            }
+        }
+
+        // the collector is gone when the capture was stopped
+        let _ = self.sender.send(quantum);
+
+        false
+    }
+}
+
+/// Named capture of an offline rendering, see
+/// [`OfflineAudioContext::add_stem`](crate::context::OfflineAudioContext::add_stem)
+pub(crate) struct Stem {
+    name: String,
+    // keeps the node in the audio graph
+    _node: CaptureNode,
+    channels: Arc<Mutex<Vec<Vec<f32>>>>,
+}
+
+impl Stem {
+    pub fn new<C: BaseAudioContext>(
+        context: &C,
+        name: String,
+        source: &dyn AudioNode,
+        number_of_channels: usize,
+        length: usize,
+    ) -> Self {
+        assert_valid_number_of_channels(number_of_channels);
+        assert!(
+            source.number_of_outputs() > 0,
+            "InvalidAccessError - Cannot capture a node without outputs"
+        );
+
+        let channels = vec![Vec::with_capacity(length); number_of_channels];
+        let channels = Arc::new(Mutex::new(channels));
+        let render = StemRenderer {
+            channels: Arc::clone(&channels),
+        };
+        let node = CaptureNode::new(context, number_of_channels, Box::new(render));
+        source.connect(&node);
+
+        Self {
+            name,
+            _node: node,
+            channels,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The captured audio, truncated or padded with silence to the given length
+    pub fn into_buffer(self, length: usize, sample_rate: f32) -> AudioBuffer {
+        let mut channels = std::mem::take(&mut *self.channels.lock().unwrap());
+        channels
+            .iter_mut()
+            .for_each(|channel| channel.resize(length, 0.));
+        AudioBuffer::from(channels, sample_rate)
+    }
+}
+
+struct StemRenderer {
+    channels: Arc<Mutex<Vec<Vec<f32>>>>,
+}
+
+impl AudioProcessor for StemRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        _outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input, no output
+        let input = &inputs[0];
+
+        // the lock is only contended once the rendering is over
+        let mut channels = self.channels.lock().unwrap();
+        for (channel_number, channel) in channels.iter_mut().enumerate() {
+            // stems added while the rendering is suspended start with silence
+            channel.resize(channel.len().max(scope.current_frame as usize), 0.);
+            match input.channels().get(channel_number) {
+                Some(samples) => channel.extend_from_slice(&samples[..]),
+                // silent inputs have a single channel
+                None => channel.resize(channel.len() + RENDER_QUANTUM_SIZE, 0.),
+            }
+        }
+
+        false
+    }
+}
+
+/// Storage of the captured audio
+enum Sink {
+    Buffer(Vec<Vec<f32>>),
+    Wav {
+        writer: BufWriter<File>,
+        sample_format: WavSampleFormat,
+        data_size: u64,
+        frame: Vec<u8>,
+    },
+}
+
+impl Sink {
+    fn wav(
+        file: File,
+        number_of_channels: usize,
+        sample_rate: f32,
+        sample_format: WavSampleFormat,
+    ) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(file);
+        // the sizes are filled in when the capture is stopped
+        wav::write_header(
+            &mut writer,
+            number_of_channels,
+            sample_rate.round() as u32,
+            sample_format,
+            0,
+        )?;
+
+        Ok(Self::Wav {
+            writer,
+            sample_format,
+            data_size: 0,
+            frame: vec![],
+        })
+    }
+
+    /// Append a quantum of planar samples
+    fn write(&mut self, quantum: &[f32]) -> std::io::Result<()> {
+        match self {
+            Self::Buffer(channels) => channels
+                .iter_mut()
+                .zip(quantum.chunks_exact(RENDER_QUANTUM_SIZE))
+                .for_each(|(channel, samples)| channel.extend_from_slice(samples)),
+            Self::Wav {
+                writer,
+                sample_format,
+                data_size,
+                frame,
+            } => {
+                let number_of_channels = quantum.len() / RENDER_QUANTUM_SIZE;
+                frame.clear();
+                for i in 0..RENDER_QUANTUM_SIZE {
+                    for channel_number in 0..number_of_channels {
+                        let sample = quantum[channel_number * RENDER_QUANTUM_SIZE + i];
+                        sample_format.encode_sample(sample, frame);
+                    }
+                }
+
+                *data_size += frame.len() as u64;
+                if *data_size > u64::from(u32::MAX - 36) {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "Capture is too large to be encoded as a WAV file",
+                    ));
+                }
+                writer.write_all(frame)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Complete the storage, the captured audio is returned for the in memory storage
+    fn finish(self, sample_rate: f32) -> std::io::Result<Option<AudioBuffer>> {
+        match self {
+            Self::Buffer(channels) => Ok(Some(AudioBuffer::from(channels, sample_rate))),
+            Self::Wav {
+                writer, data_size, ..
+            } => {
+                let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+                let data_size = data_size as u32;
+                file.seek(SeekFrom::Start(4))?;
+                file.write_all(&(36 + data_size).to_le_bytes())?;
+                file.seek(SeekFrom::Start(40))?;
+                file.write_all(&data_size.to_le_bytes())?;
+                file.flush()?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Receives the rendered quanta on a dedicated thread, to keep the I/O off the render thread
+struct Collector {
+    receiver: Receiver<Vec<f32>>,
+    recycle: Sender<Vec<f32>>,
+    stopped: Receiver<()>,
+    frames: Arc<AtomicUsize>,
+    number_of_channels: usize,
+    sink: Sink,
+}
+
+impl Collector {
+    fn run(mut self, sample_rate: f32) -> CaptureResult {
+        loop {
+            crossbeam_channel::select! {
+                recv(self.receiver) -> quantum => match quantum {
+                    Ok(quantum) => self.write(quantum)?,
+                    // the capture node has been dropped
+                    Err(_) => break,
+                },
+                // the stop sender is dropped when the capture is stopped
+                recv(self.stopped) -> _ => {
+                    // keep the quanta rendered before the capture was stopped
+                    while let Ok(quantum) = self.receiver.try_recv() {
+                        self.write(quantum)?;
+                    }
+                    break;
+                },
+            }
+        }
+
+        Ok(self.sink.finish(sample_rate)?)
+    }
+
+    fn write(&mut self, quantum: Vec<f32>) -> std::io::Result<()> {
+        debug_assert_eq!(quantum.len(), self.number_of_channels * RENDER_QUANTUM_SIZE);
+        self.sink.write(&quantum)?;
+        self.frames
+            .fetch_add(RENDER_QUANTUM_SIZE, Ordering::Relaxed);
+        let _ = self.recycle.try_send(quantum);
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ use crate::context::{
     DESTINATION_NODE_ID,
 };
 use crate::decoding::MediaDecoder;
+use crate::message::ControlMessage;
 use crate::node::{AudioNode, ChannelConfigOptions};
 use crate::param::AudioParamDescriptor;
 use crate::periodic_wave::{PeriodicWave, PeriodicWaveOptions};
@@ -37,6 +38,87 @@ pub trait BaseAudioContext {
         self.base().register(f)
     }
 
+    /// Register a processor factory under the given name, to construct
+    /// [`AudioWorkletNode`](node::AudioWorkletNode)s from
+    ///
+    /// This is the counterpart of `registerProcessor` in the `AudioWorkletGlobalScope` of the
+    /// browser.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if:
+    /// - the name is empty, or a processor is already registered under this name
+    /// - two parameter descriptors of the factory have the same name
+    /// - the default value of a parameter descriptor is outside its range
+    fn register_processor<F: node::AudioWorkletProcessorFactory + 'static>(
+        &self,
+        name: &str,
+        factory: F,
+    ) {
+        self.base()
+            .register_processor_factory(name, std::sync::Arc::new(factory));
+    }
+
+    /// Replace the [`AudioProcessor`] of an existing node
+    ///
+    /// The new processor takes over at the next render quantum boundary, the connections of the
+    /// node are left untouched and the previous processor is dropped on the render thread. This
+    /// allows reloading the processing code of a node, e.g. in a live coding session.
+    ///
+    /// The new processor must handle the same number of inputs and outputs as the node. To read
+    /// the `AudioParam`s of the node, keep clones of their
+    /// [`AudioParamId`]s.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the node belongs to another context
+    fn replace_processor(&self, node: &dyn AudioNode, processor: Box<dyn AudioProcessor>) {
+        if self.base() != node.context() {
+            panic!("InvalidAccessError: Attempting to replace the processor of a node from a different context");
+        }
+
+        let message = ControlMessage::ReplaceProcessor {
+            id: node.registration().id(),
+            processor,
+        };
+        // the render thread may already be shut down
+        let _ = self.base().send_control_msg(message);
+    }
+
+    /// Apply the graph edits made in the closure at once
+    ///
+    /// All nodes created, (dis)connected or dropped and all `AudioParam` changes made in `f` are
+    /// queued and handed to the render thread at a single render quantum boundary. This way a
+    /// restructuring of multiple nodes is never rendered in a half-wired intermediate state.
+    ///
+    /// Batches can be nested, the edits of an inner batch are applied with the outer one. The
+    /// edits made by other threads while the batch runs are part of the batch as well.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use web_audio_api::context::{AudioContext, BaseAudioContext};
+    /// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+    ///
+    /// let context = AudioContext::default();
+    /// let osc = context.create_oscillator();
+    /// osc.connect(&context.destination());
+    /// osc.start();
+    ///
+    /// // insert a gain node between the oscillator and the destination without glitches
+    /// let gain = context.batch(|context| {
+    ///     let gain = context.create_gain();
+    ///     gain.gain().set_value(0.5);
+    ///     osc.disconnect();
+    ///     osc.connect(&gain);
+    ///     gain.connect(&context.destination());
+    ///     gain
+    /// });
+    /// ```
+    fn batch<R, F: FnOnce(&Self) -> R>(&self, f: F) -> R {
+        self.base().batch_control_msgs(|| f(self))
+    }
+
     /// Decode an [`AudioBuffer`] from a given input stream.
     ///
     /// The current implementation can decode FLAC, Opus, PCM, Vorbis, and Wav.
@@ -80,13 +162,8 @@ pub trait BaseAudioContext {
         input: R,
     ) -> Result<AudioBuffer, Box<dyn std::error::Error + Send + Sync>> {
         // Set up a media decoder, consume the stream in full and construct a single buffer out of it
-        let mut buffer = MediaDecoder::try_new(input)?
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .reduce(|mut accum, item| {
-                accum.extend(&item);
-                accum
-            })
+        let buffers = MediaDecoder::try_new(input)?.collect::<Result<Vec<_>, _>>()?;
+        let mut buffer = AudioBuffer::concat(&buffers)
             // if there are no samples decoded, return an empty buffer
             .unwrap_or_else(|| AudioBuffer::from(vec![vec![]], self.sample_rate()));
 
@@ -1,19 +1,23 @@
 //! The `ConcreteBaseAudioContext` type
 
 use crate::context::{
-    AudioContextRegistration, AudioContextState, AudioNodeId, BaseAudioContext,
+    AudioContextRegistration, AudioContextState, AudioNodeId, AudioParamId, BaseAudioContext,
     DESTINATION_NODE_ID, LISTENER_NODE_ID, LISTENER_PARAM_IDS,
 };
 use crate::events::{EventDispatch, EventHandler, EventLoop, EventType};
 use crate::message::ControlMessage;
-use crate::node::{AudioDestinationNode, AudioNode, ChannelConfig, ChannelConfigOptions};
-use crate::param::{AudioParam, AudioParamEvent};
+use crate::node::{
+    assert_valid_parameter_descriptors, AudioDestinationNode, AudioNode,
+    AudioWorkletProcessorFactory, ChannelConfig, ChannelConfigOptions,
+};
+use crate::param::{AudioParam, AudioParamDescriptor, AudioParamEvent};
 use crate::render::AudioProcessor;
 use crate::spatial::AudioListenerParams;
 
 use crate::AudioListener;
 
 use crossbeam_channel::{Receiver, SendError, Sender};
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
 
@@ -55,6 +59,10 @@ struct ConcreteBaseAudioContextInner {
     render_channel: RwLock<Sender<ControlMessage>>,
     /// control messages that cannot be sent immediately
     queued_messages: Mutex<Vec<ControlMessage>>,
+    /// control messages of a running batch, sent at once when the batch ends
+    batch: Mutex<Option<Vec<ControlMessage>>>,
+    /// connections between nodes as (from, output, to, input), mirrors the render graph edges
+    connections: Mutex<HashSet<(AudioNodeId, usize, AudioNodeId, usize)>>,
     /// number of frames played
     frames_played: Arc<AtomicU64>,
     /// control msg to add the AudioListener, to be sent when the first panner is created
@@ -69,6 +77,8 @@ struct ConcreteBaseAudioContextInner {
     event_loop: EventLoop,
     /// Sender for events that will be handled by the EventLoop
     event_send: Option<Sender<EventDispatch>>,
+    /// Processor factories of the `AudioWorkletNode`s, by name
+    processor_factories: Mutex<HashMap<String, Arc<dyn AudioWorkletProcessorFactory>>>,
 }
 
 impl BaseAudioContext for ConcreteBaseAudioContext {
@@ -138,6 +148,9 @@ impl ConcreteBaseAudioContext {
             max_channel_count,
             render_channel: RwLock::new(render_channel),
             queued_messages: Mutex::new(Vec::new()),
+            batch: Mutex::new(None),
+            connections: Mutex::new(HashSet::new()),
+            processor_factories: Mutex::new(HashMap::new()),
             node_id_inc: AtomicU64::new(0),
             destination_channel_config: ChannelConfigOptions::default().into(),
             frames_played,
@@ -219,9 +232,57 @@ impl ConcreteBaseAudioContext {
         &self,
         msg: ControlMessage,
     ) -> Result<(), SendError<ControlMessage>> {
+        if let Some(messages) = self.inner.batch.lock().unwrap().as_mut() {
+            messages.push(msg);
+            return Ok(());
+        }
+
         self.inner.render_channel.read().unwrap().send(msg)
     }
 
+    /// Run `f`, the control messages it sends are applied at the same render quantum boundary
+    ///
+    /// Nested batches are merged into the outer one. The messages are also sent when `f` panics,
+    /// as the control thread has already registered the changes.
+    pub(crate) fn batch_control_msgs<R>(&self, f: impl FnOnce() -> R) -> R {
+        {
+            let mut batch = self.inner.batch.lock().unwrap();
+            if batch.is_some() {
+                drop(batch);
+                return f();
+            }
+            *batch = Some(vec![]);
+        }
+
+        /// Sends the collected messages when the batch ends, also when unwinding
+        struct BatchGuard<'a>(&'a ConcreteBaseAudioContext);
+
+        impl Drop for BatchGuard<'_> {
+            fn drop(&mut self) {
+                let messages = self
+                    .0
+                    .inner
+                    .batch
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .unwrap_or_default();
+                if !messages.is_empty() {
+                    // the render thread may already be shut down
+                    let _ = self.0.send_control_msg(ControlMessage::Batch { messages });
+                }
+            }
+        }
+
+        let _guard = BatchGuard(self);
+        f()
+    }
+
+    /// Channel to the event thread of the context, `None` when the context has no event thread
+    pub(crate) fn event_sender(&self) -> Option<Sender<EventDispatch>> {
+        self.inner.event_send.clone()
+    }
+
     pub(crate) fn send_event(&self, msg: EventDispatch) -> Result<(), SendError<EventDispatch>> {
         match self.inner.event_send.as_ref() {
             Some(s) => s.send(msg),
@@ -241,6 +302,13 @@ impl ConcreteBaseAudioContext {
             || LISTENER_PARAM_IDS.contains(&id.0);
 
         if !magic {
+            // the node can no longer be (dis)connected by the user
+            self.inner
+                .connections
+                .lock()
+                .unwrap()
+                .retain(|&(from, _, to, _)| from != id && to != id);
+
             let message = ControlMessage::FreeWhenFinished { id };
 
             // Sending the message will fail when the render thread has already shut down.
@@ -294,7 +362,11 @@ impl ConcreteBaseAudioContext {
 
     /// Updates state of current context
     pub(super) fn set_state(&self, state: AudioContextState) {
-        self.inner.state.store(state as u8, Ordering::SeqCst);
+        let previous = self.inner.state.swap(state as u8, Ordering::SeqCst);
+        if previous != state as u8 {
+            // the event loop may not be running, e.g. for an offline context
+            let _ = self.send_event(EventDispatch::state_change());
+        }
     }
 
     /// The sample rate (in sample-frames per second) at which the `AudioContext` handles audio.
@@ -320,6 +392,67 @@ impl ConcreteBaseAudioContext {
         self.inner.max_channel_count
     }
 
+    /// Register a processor factory under the given name
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name is empty or already registered, or if the parameter descriptors of the
+    /// factory are invalid
+    pub(crate) fn register_processor_factory(
+        &self,
+        name: &str,
+        factory: Arc<dyn AudioWorkletProcessorFactory>,
+    ) {
+        if name.is_empty() {
+            panic!("NotSupportedError - AudioWorkletProcessor name cannot be empty");
+        }
+
+        assert_valid_parameter_descriptors(&factory.parameter_descriptors());
+
+        let mut factories = self.inner.processor_factories.lock().unwrap();
+        if factories.contains_key(name) {
+            panic!(
+                "NotSupportedError - AudioWorkletProcessor {:?} is already registered",
+                name
+            );
+        }
+        factories.insert(name.to_string(), factory);
+    }
+
+    /// The processor factory registered under the given name
+    pub(crate) fn processor_factory(
+        &self,
+        name: &str,
+    ) -> Option<Arc<dyn AudioWorkletProcessorFactory>> {
+        self.inner
+            .processor_factories
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+    }
+
+    /// Create an `AudioParam` of a custom processor, see
+    /// [`BaseAudioContext::create_audio_param`]
+    ///
+    /// At a-rate, the processor is always given a full render quantum of values.
+    pub(crate) fn create_full_buffer_audio_param(
+        &self,
+        opts: AudioParamDescriptor,
+        dest: &AudioContextRegistration,
+    ) -> (AudioParam, AudioParamId) {
+        let param = self.register(move |registration| {
+            let (node, proc) = crate::param::full_buffer_audio_param_pair(opts, registration);
+
+            (node, Box::new(proc))
+        });
+
+        self.queue_audio_param_connect(&param, dest.id());
+
+        let proc_id = AudioParamId(param.registration().id().0);
+        (param, proc_id)
+    }
+
     /// Release queued control messages to the render thread that were blocking on the availability
     /// of the Node with the given `id`
     fn resolve_queued_control_msgs(&self, id: AudioNodeId) {
@@ -336,8 +469,28 @@ impl ConcreteBaseAudioContext {
         }
     }
 
+    /// Connections between nodes as (from, output, to, input) tuples
+    pub(crate) fn connections(&self) -> Vec<(AudioNodeId, usize, AudioNodeId, usize)> {
+        self.inner
+            .connections
+            .lock()
+            .unwrap()
+            .iter()
+            .copied()
+            .collect()
+    }
+
     /// Connects the output of the `from` audio node to the input of the `to` audio node
     pub(crate) fn connect(&self, from: AudioNodeId, to: AudioNodeId, output: usize, input: usize) {
+        // hidden AudioParam connections are not tracked
+        if input != usize::MAX {
+            self.inner
+                .connections
+                .lock()
+                .unwrap()
+                .insert((from, output, to, input));
+        }
+
         let message = ControlMessage::ConnectNode {
             from,
             to,
@@ -360,16 +513,38 @@ impl ConcreteBaseAudioContext {
         self.inner.queued_messages.lock().unwrap().push(message);
     }
 
-    /// Disconnects all outputs of the audio node that go to a specific destination node.
-    pub(crate) fn disconnect_from(&self, from: AudioNodeId, to: AudioNodeId) {
-        let message = ControlMessage::DisconnectNode { from, to };
-        self.send_control_msg(message).unwrap();
-    }
+    /// Disconnects the outgoing connections of the audio node, optionally restricted to the
+    /// given output port, destination node and input port.
+    ///
+    /// Returns `false` if no connection matched.
+    pub(crate) fn disconnect(
+        &self,
+        from: AudioNodeId,
+        output: Option<usize>,
+        to: Option<AudioNodeId>,
+        input: Option<usize>,
+    ) -> bool {
+        let mut connections = self.inner.connections.lock().unwrap();
+        let count = connections.len();
+        connections.retain(|&(f, o, t, i)| {
+            let matches = f == from
+                && (output.is_none() || output == Some(o))
+                && (to.is_none() || to == Some(t))
+                && (input.is_none() || input == Some(i));
+            !matches
+        });
+        let matched = connections.len() != count;
+        drop(connections);
 
-    /// Disconnects all outgoing connections from the audio node.
-    pub(crate) fn disconnect(&self, from: AudioNodeId) {
-        let message = ControlMessage::DisconnectAll { from };
+        let message = ControlMessage::DisconnectNode {
+            from,
+            output,
+            to,
+            input,
+        };
         self.send_control_msg(message).unwrap();
+
+        matched
     }
 
     /// Pass an `AudioParam::AudioParamEvent` to the render thread
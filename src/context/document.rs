@@ -0,0 +1,1065 @@
+//! Serialization of the audio graph, to save and restore sessions
+use std::fmt;
+use std::str::FromStr;
+
+use crate::context::{AudioNodeId, BaseAudioContext, DESTINATION_NODE_ID};
+use crate::node::{
+    AudioNode, AudioScheduledSourceNode, BiquadFilterNode, BiquadFilterType, ChannelCountMode,
+    ChannelInterpretation, ConstantSourceNode, DelayInterpolation, DelayNode, GainNode,
+    OscillatorNode, OscillatorType, StereoPannerNode,
+};
+use crate::{AudioParam, AutomationEvent};
+
+/// First line of the text representation of a [`GraphDocument`]
+const HEADER: &str = "web-audio-api graph 1";
+
+const CHANNEL_COUNT_MODES: [(ChannelCountMode, &str); 3] = [
+    (ChannelCountMode::Max, "max"),
+    (ChannelCountMode::ClampedMax, "clamped-max"),
+    (ChannelCountMode::Explicit, "explicit"),
+];
+
+const CHANNEL_INTERPRETATIONS: [(ChannelInterpretation, &str); 2] = [
+    (ChannelInterpretation::Speakers, "speakers"),
+    (ChannelInterpretation::Discrete, "discrete"),
+];
+
+const OSCILLATOR_TYPES: [(OscillatorType, &str); 4] = [
+    (OscillatorType::Sine, "sine"),
+    (OscillatorType::Square, "square"),
+    (OscillatorType::Sawtooth, "sawtooth"),
+    (OscillatorType::Triangle, "triangle"),
+];
+
+const BIQUAD_FILTER_TYPES: [(BiquadFilterType, &str); 8] = [
+    (BiquadFilterType::Lowpass, "lowpass"),
+    (BiquadFilterType::Highpass, "highpass"),
+    (BiquadFilterType::Bandpass, "bandpass"),
+    (BiquadFilterType::Notch, "notch"),
+    (BiquadFilterType::Allpass, "allpass"),
+    (BiquadFilterType::Peaking, "peaking"),
+    (BiquadFilterType::Lowshelf, "lowshelf"),
+    (BiquadFilterType::Highshelf, "highshelf"),
+];
+
+const DELAY_INTERPOLATIONS: [(DelayInterpolation, &str); 2] = [
+    (DelayInterpolation::Linear, "linear"),
+    (DelayInterpolation::Cubic, "cubic"),
+];
+
+fn name_of<T: Copy + PartialEq>(table: &[(T, &'static str)], value: T) -> &'static str {
+    table.iter().find(|(v, _)| *v == value).unwrap().1
+}
+
+fn parse_name<T: Copy>(table: &[(T, &'static str)], name: &str) -> Result<T, String> {
+    table
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(v, _)| *v)
+        .ok_or_else(|| format!("unknown value {:?}", name))
+}
+
+fn parse_number<T: FromStr>(token: Option<&str>) -> Result<T, String> {
+    let token = token.ok_or_else(|| "missing value".to_string())?;
+    token
+        .parse()
+        .map_err(|_| format!("invalid number {:?}", token))
+}
+
+/// Error returned when an audio graph can not be captured or parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphDocumentError {
+    /// The oscillator at the given index renders a custom `PeriodicWave`, which can not be
+    /// retrieved from the node
+    CustomOscillator(usize),
+    /// The text representation is invalid at the given line (starting at 1)
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for GraphDocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CustomOscillator(index) => write!(
+                f,
+                "NotSupportedError: oscillator {} with a custom periodic wave can not be captured",
+                index
+            ),
+            Self::Parse { line, message } => {
+                write!(f, "SyntaxError: line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphDocumentError {}
+
+/// Borrowed audio node of a graph to capture with [`GraphDocument::capture`]
+#[derive(Clone, Copy)]
+pub enum GraphNode<'a> {
+    Gain(&'a GainNode),
+    Delay(&'a DelayNode),
+    BiquadFilter(&'a BiquadFilterNode),
+    StereoPanner(&'a StereoPannerNode),
+    ConstantSource(&'a ConstantSourceNode),
+    Oscillator(&'a OscillatorNode),
+}
+
+/// Audio node created by [`GraphDocument::restore`]
+pub enum RestoredNode {
+    Gain(GainNode),
+    Delay(DelayNode),
+    BiquadFilter(BiquadFilterNode),
+    StereoPanner(StereoPannerNode),
+    ConstantSource(ConstantSourceNode),
+    Oscillator(OscillatorNode),
+}
+
+macro_rules! impl_graph_node_from {
+    ($($variant:ident: $node:ty),*) => {
+        $(
+            impl<'a> From<&'a $node> for GraphNode<'a> {
+                fn from(node: &'a $node) -> Self {
+                    GraphNode::$variant(node)
+                }
+            }
+        )*
+    };
+}
+
+impl_graph_node_from!(
+    Gain: GainNode,
+    Delay: DelayNode,
+    BiquadFilter: BiquadFilterNode,
+    StereoPanner: StereoPannerNode,
+    ConstantSource: ConstantSourceNode,
+    Oscillator: OscillatorNode
+);
+
+impl<'a> GraphNode<'a> {
+    fn node(&self) -> &'a dyn AudioNode {
+        match *self {
+            GraphNode::Gain(n) => n,
+            GraphNode::Delay(n) => n,
+            GraphNode::BiquadFilter(n) => n,
+            GraphNode::StereoPanner(n) => n,
+            GraphNode::ConstantSource(n) => n,
+            GraphNode::Oscillator(n) => n,
+        }
+    }
+
+    /// Id of the node the outgoing connections start from
+    fn output_id(&self) -> AudioNodeId {
+        match *self {
+            GraphNode::Delay(n) => n.reader_registration().id(),
+            _ => self.node().registration().id(),
+        }
+    }
+
+    fn params(&self) -> Vec<(&'static str, &'a AudioParam)> {
+        match *self {
+            GraphNode::Gain(n) => vec![("gain", n.gain())],
+            GraphNode::Delay(n) => vec![("delayTime", n.delay_time())],
+            GraphNode::BiquadFilter(n) => vec![
+                ("frequency", n.frequency()),
+                ("detune", n.detune()),
+                ("Q", n.q()),
+                ("gain", n.gain()),
+            ],
+            GraphNode::StereoPanner(n) => vec![("pan", n.pan())],
+            GraphNode::ConstantSource(n) => vec![("offset", n.offset())],
+            GraphNode::Oscillator(n) => {
+                vec![("frequency", n.frequency()), ("detune", n.detune())]
+            }
+        }
+    }
+}
+
+impl RestoredNode {
+    /// Borrow the restored node, e.g. to capture it again
+    pub fn as_graph_node(&self) -> GraphNode<'_> {
+        match self {
+            RestoredNode::Gain(n) => GraphNode::Gain(n),
+            RestoredNode::Delay(n) => GraphNode::Delay(n),
+            RestoredNode::BiquadFilter(n) => GraphNode::BiquadFilter(n),
+            RestoredNode::StereoPanner(n) => GraphNode::StereoPanner(n),
+            RestoredNode::ConstantSource(n) => GraphNode::ConstantSource(n),
+            RestoredNode::Oscillator(n) => GraphNode::Oscillator(n),
+        }
+    }
+
+    /// The restored node as a generic `AudioNode`, e.g. to connect it
+    pub fn as_audio_node(&self) -> &dyn AudioNode {
+        self.as_graph_node().node()
+    }
+}
+
+/// Node type and the options that can not be changed through an `AudioParam`
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind {
+    Gain,
+    Delay {
+        max_delay_time: f64,
+        interpolation: DelayInterpolation,
+    },
+    BiquadFilter {
+        type_: BiquadFilterType,
+    },
+    StereoPanner,
+    ConstantSource,
+    Oscillator {
+        type_: OscillatorType,
+    },
+}
+
+/// Value and pending automation of an `AudioParam`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamDocument {
+    /// Name of the param, as in the specification (e.g. `"delayTime"`)
+    pub name: String,
+    /// Current value of the param
+    pub value: f32,
+    /// Automation events that did not complete yet, with times relative to the capture
+    pub automation: Vec<AutomationEvent>,
+}
+
+/// State of an audio node
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeDocument {
+    pub kind: NodeKind,
+    pub channel_count: usize,
+    pub channel_count_mode: ChannelCountMode,
+    pub channel_interpretation: ChannelInterpretation,
+    pub params: Vec<ParamDocument>,
+    /// Start time of a source node relative to the capture, `None` when not started
+    pub start_time: Option<f64>,
+    /// Stop time of a source node relative to the capture, `None` when not stopped
+    pub stop_time: Option<f64>,
+}
+
+/// Destination of a connection of a [`GraphDocument`]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConnectionTarget {
+    /// Input of the node at the given index of the document
+    Node { index: usize, input: usize },
+    /// Param with the given name of the node at the given index of the document
+    Param { index: usize, name: String },
+    /// Input of the `AudioDestinationNode`
+    Destination { input: usize },
+}
+
+/// Connection from the output of a node of a [`GraphDocument`]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ConnectionDocument {
+    /// Index of the source node in the document
+    pub from: usize,
+    pub output: usize,
+    pub to: ConnectionTarget,
+}
+
+/// Serializable state of an audio graph: its nodes, options, connections, param values and
+/// pending automation (non-standard)
+///
+/// A document is captured from a running context with [`GraphDocument::capture`] and can be
+/// restored in a new context with [`GraphDocument::restore`], e.g. to save and load sessions.
+/// All times of the document are relative to the moment of the capture, which becomes time
+/// zero of the restored graph. The `Display` and `FromStr` implementations convert the
+/// document to and from a line based text representation.
+///
+/// Only the node types of [`GraphNode`] are supported. Connections from or to nodes that are
+/// not part of the captured nodes are not included, except for connections to the
+/// destination of the context. The phase of a running source is not preserved.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext, GraphDocument, OfflineAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+/// let osc = context.create_oscillator();
+/// let gain = context.create_gain();
+/// osc.connect(&gain);
+/// gain.connect(&context.destination());
+/// osc.start();
+///
+/// // save the session
+/// let document = GraphDocument::capture(&context, &[(&osc).into(), (&gain).into()]).unwrap();
+/// let text = document.to_string();
+///
+/// // load the session in another context
+/// let offline = OfflineAudioContext::new(2, 44_100, 44_100.);
+/// let document: GraphDocument = text.parse().unwrap();
+/// let nodes = document.restore(&offline);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphDocument {
+    pub nodes: Vec<NodeDocument>,
+    pub connections: Vec<ConnectionDocument>,
+}
+
+impl GraphDocument {
+    /// Capture the state of the given nodes of the context
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GraphDocumentError::CustomOscillator`] when an oscillator renders a custom
+    /// `PeriodicWave`
+    pub fn capture<C: BaseAudioContext>(
+        context: &C,
+        nodes: &[GraphNode<'_>],
+    ) -> Result<Self, GraphDocumentError> {
+        let now = context.current_time();
+
+        let nodes_doc = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, graph_node)| {
+                let (kind, scheduler) = match *graph_node {
+                    GraphNode::Gain(_) => (NodeKind::Gain, None),
+                    GraphNode::Delay(n) => (
+                        NodeKind::Delay {
+                            max_delay_time: n.delay_time().max_value() as f64,
+                            interpolation: n.interpolation(),
+                        },
+                        None,
+                    ),
+                    GraphNode::BiquadFilter(n) => {
+                        (NodeKind::BiquadFilter { type_: n.type_() }, None)
+                    }
+                    GraphNode::StereoPanner(_) => (NodeKind::StereoPanner, None),
+                    GraphNode::ConstantSource(n) => (NodeKind::ConstantSource, Some(n.scheduler())),
+                    GraphNode::Oscillator(n) => {
+                        if n.type_() == OscillatorType::Custom {
+                            return Err(GraphDocumentError::CustomOscillator(index));
+                        }
+                        (
+                            NodeKind::Oscillator { type_: n.type_() },
+                            Some(n.scheduler()),
+                        )
+                    }
+                };
+
+                // unscheduled times are stored as f64::MAX
+                let relative = |time: f64| (time != f64::MAX).then(|| (time - now).max(0.));
+
+                let node = graph_node.node();
+                let params = graph_node
+                    .params()
+                    .into_iter()
+                    .map(|(name, param)| ParamDocument {
+                        name: name.to_string(),
+                        value: param.value(),
+                        automation: param.pending_automation(now),
+                    })
+                    .collect();
+
+                Ok(NodeDocument {
+                    kind,
+                    channel_count: node.channel_count(),
+                    channel_count_mode: node.channel_count_mode(),
+                    channel_interpretation: node.channel_interpretation(),
+                    params,
+                    start_time: scheduler.and_then(|s| relative(s.get_start_at())),
+                    stop_time: scheduler.and_then(|s| relative(s.get_stop_at())),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let output_index = |id: AudioNodeId| nodes.iter().position(|n| n.output_id() == id);
+        let input_index = |id: AudioNodeId| {
+            nodes
+                .iter()
+                .position(|n| n.node().registration().id() == id)
+        };
+        let param_target = |id: AudioNodeId| {
+            nodes.iter().enumerate().find_map(|(index, n)| {
+                n.params()
+                    .into_iter()
+                    .find(|(_, param)| param.registration().id() == id)
+                    .map(|(name, _)| ConnectionTarget::Param {
+                        index,
+                        name: name.to_string(),
+                    })
+            })
+        };
+
+        let mut connections: Vec<_> = context
+            .base()
+            .connections()
+            .into_iter()
+            .filter_map(|(from, output, to, input)| {
+                let from = output_index(from)?;
+                let to = if to == DESTINATION_NODE_ID {
+                    ConnectionTarget::Destination { input }
+                } else if let Some(index) = input_index(to) {
+                    ConnectionTarget::Node { index, input }
+                } else {
+                    param_target(to)?
+                };
+
+                Some(ConnectionDocument { from, output, to })
+            })
+            .collect();
+        connections.sort();
+
+        Ok(Self {
+            nodes: nodes_doc,
+            connections,
+        })
+    }
+
+    /// Create the nodes of the document in the given context, in the order of the document
+    ///
+    /// The params are set to their captured value, the pending automation is scheduled, the
+    /// sources are started and stopped and the nodes are connected. The times of the document
+    /// are offset by the current time of the context.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when the document is invalid, e.g. when a connection refers
+    /// to a node or param that does not exist
+    pub fn restore<C: BaseAudioContext>(&self, context: &C) -> Vec<RestoredNode> {
+        let now = context.current_time();
+
+        let restored: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|doc| {
+                let restored = match doc.kind {
+                    NodeKind::Gain => RestoredNode::Gain(context.create_gain()),
+                    NodeKind::Delay {
+                        max_delay_time,
+                        interpolation,
+                    } => {
+                        let node = context.create_delay(max_delay_time);
+                        node.set_interpolation(interpolation);
+                        RestoredNode::Delay(node)
+                    }
+                    NodeKind::BiquadFilter { type_ } => {
+                        let node = context.create_biquad_filter();
+                        node.set_type(type_);
+                        RestoredNode::BiquadFilter(node)
+                    }
+                    NodeKind::StereoPanner => {
+                        RestoredNode::StereoPanner(context.create_stereo_panner())
+                    }
+                    NodeKind::ConstantSource => {
+                        RestoredNode::ConstantSource(context.create_constant_source())
+                    }
+                    NodeKind::Oscillator { type_ } => {
+                        let node = context.create_oscillator();
+                        node.set_type(type_);
+                        RestoredNode::Oscillator(node)
+                    }
+                };
+
+                let node = restored.as_audio_node();
+                node.set_channel_count(doc.channel_count);
+                node.set_channel_count_mode(doc.channel_count_mode);
+                node.set_channel_interpretation(doc.channel_interpretation);
+
+                let params = restored.as_graph_node().params();
+                for param_doc in &doc.params {
+                    let (_, param) = params
+                        .iter()
+                        .find(|(name, _)| *name == param_doc.name)
+                        .unwrap_or_else(|| panic!("unknown param {:?}", param_doc.name));
+                    param.set_value(param_doc.value);
+                    param_doc
+                        .automation
+                        .iter()
+                        .for_each(|event| param.schedule_automation(&event.offset(now)));
+                }
+
+                match &restored {
+                    RestoredNode::ConstantSource(n) => schedule_source(n, doc, now),
+                    RestoredNode::Oscillator(n) => schedule_source(n, doc, now),
+                    _ => (),
+                }
+
+                restored
+            })
+            .collect();
+
+        let destination = context.destination();
+        for connection in &self.connections {
+            let from = &restored[connection.from];
+            match &connection.to {
+                ConnectionTarget::Node { index, input } => {
+                    from.as_audio_node().connect_at(
+                        restored[*index].as_audio_node(),
+                        connection.output,
+                        *input,
+                    );
+                }
+                ConnectionTarget::Param { index, name } => {
+                    let params = restored[*index].as_graph_node().params();
+                    let (_, param) = params
+                        .iter()
+                        .find(|(n, _)| *n == name.as_str())
+                        .unwrap_or_else(|| panic!("unknown param {:?}", name));
+                    from.as_audio_node()
+                        .connect_at(*param, connection.output, 0);
+                }
+                ConnectionTarget::Destination { input } => {
+                    from.as_audio_node()
+                        .connect_at(&destination, connection.output, *input);
+                }
+            }
+        }
+
+        restored
+    }
+}
+
+fn schedule_source<S: AudioScheduledSourceNode>(source: &S, doc: &NodeDocument, now: f64) {
+    if let Some(start_time) = doc.start_time {
+        source.start_at(now + start_time);
+    }
+    if let Some(stop_time) = doc.stop_time {
+        source.stop_at(now + stop_time);
+    }
+}
+
+impl AutomationEvent {
+    fn offset(&self, offset: f64) -> Self {
+        let mut event = self.clone();
+        match &mut event {
+            AutomationEvent::SetValueAtTime { start_time, .. }
+            | AutomationEvent::SetTargetAtTime { start_time, .. }
+            | AutomationEvent::SetValueCurveAtTime { start_time, .. } => *start_time += offset,
+            AutomationEvent::LinearRampToValueAtTime { end_time, .. }
+            | AutomationEvent::ExponentialRampToValueAtTime { end_time, .. } => *end_time += offset,
+            AutomationEvent::CancelAndHoldAtTime { cancel_time } => *cancel_time += offset,
+        }
+        event
+    }
+}
+
+impl fmt::Display for GraphDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+
+        for node in &self.nodes {
+            write!(f, "node ")?;
+            match node.kind {
+                NodeKind::Gain => write!(f, "gain")?,
+                NodeKind::Delay {
+                    max_delay_time,
+                    interpolation,
+                } => write!(
+                    f,
+                    "delay {} {}",
+                    max_delay_time,
+                    name_of(&DELAY_INTERPOLATIONS, interpolation)
+                )?,
+                NodeKind::BiquadFilter { type_ } => {
+                    write!(f, "biquad-filter {}", name_of(&BIQUAD_FILTER_TYPES, type_))?
+                }
+                NodeKind::StereoPanner => write!(f, "stereo-panner")?,
+                NodeKind::ConstantSource => write!(f, "constant-source")?,
+                NodeKind::Oscillator { type_ } => {
+                    write!(f, "oscillator {}", name_of(&OSCILLATOR_TYPES, type_))?
+                }
+            }
+            writeln!(
+                f,
+                " channels {} {} {}",
+                node.channel_count,
+                name_of(&CHANNEL_COUNT_MODES, node.channel_count_mode),
+                name_of(&CHANNEL_INTERPRETATIONS, node.channel_interpretation)
+            )?;
+
+            if let Some(start_time) = node.start_time {
+                writeln!(f, "start {}", start_time)?;
+            }
+            if let Some(stop_time) = node.stop_time {
+                writeln!(f, "stop {}", stop_time)?;
+            }
+
+            for param in &node.params {
+                writeln!(f, "param {} {}", param.name, param.value)?;
+                for event in &param.automation {
+                    match event {
+                        AutomationEvent::SetValueAtTime { value, start_time } => {
+                            writeln!(f, "set-value-at-time {} {}", value, start_time)?
+                        }
+                        AutomationEvent::LinearRampToValueAtTime { value, end_time } => {
+                            writeln!(f, "linear-ramp-to-value-at-time {} {}", value, end_time)?
+                        }
+                        AutomationEvent::ExponentialRampToValueAtTime { value, end_time } => {
+                            writeln!(
+                                f,
+                                "exponential-ramp-to-value-at-time {} {}",
+                                value, end_time
+                            )?
+                        }
+                        AutomationEvent::SetTargetAtTime {
+                            value,
+                            start_time,
+                            time_constant,
+                        } => writeln!(
+                            f,
+                            "set-target-at-time {} {} {}",
+                            value, start_time, time_constant
+                        )?,
+                        AutomationEvent::CancelAndHoldAtTime { cancel_time } => {
+                            writeln!(f, "cancel-and-hold-at-time {}", cancel_time)?
+                        }
+                        AutomationEvent::SetValueCurveAtTime {
+                            values,
+                            start_time,
+                            duration,
+                        } => {
+                            write!(f, "set-value-curve-at-time {} {}", start_time, duration)?;
+                            for value in values {
+                                write!(f, " {}", value)?;
+                            }
+                            writeln!(f)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        for connection in &self.connections {
+            write!(f, "connect {} {} ", connection.from, connection.output)?;
+            match &connection.to {
+                ConnectionTarget::Node { index, input } => writeln!(f, "node {} {}", index, input)?,
+                ConnectionTarget::Param { index, name } => writeln!(f, "param {} {}", index, name)?,
+                ConnectionTarget::Destination { input } => writeln!(f, "destination {}", input)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for GraphDocument {
+    type Err = GraphDocumentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate();
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            _ => {
+                return Err(GraphDocumentError::Parse {
+                    line: 1,
+                    message: format!("expected header {:?}", HEADER),
+                })
+            }
+        }
+
+        let mut document = Self::default();
+        for (index, line) in lines {
+            parse_line(&mut document, line).map_err(|message| GraphDocumentError::Parse {
+                line: index + 1,
+                message,
+            })?;
+        }
+
+        Ok(document)
+    }
+}
+
+fn parse_line(document: &mut GraphDocument, line: &str) -> Result<(), String> {
+    let mut tokens = line.split_whitespace();
+    let keyword = match tokens.next() {
+        Some(keyword) => keyword,
+        None => return Ok(()), // empty line
+    };
+
+    let no_node = || format!("{:?} before the first node", keyword);
+    let no_param = || format!("{:?} before the first param", keyword);
+    fn node(document: &mut GraphDocument) -> Option<&mut NodeDocument> {
+        document.nodes.last_mut()
+    }
+    fn param(document: &mut GraphDocument) -> Option<&mut ParamDocument> {
+        document.nodes.last_mut()?.params.last_mut()
+    }
+
+    match keyword {
+        "node" => {
+            let kind = match tokens.next() {
+                Some("gain") => NodeKind::Gain,
+                Some("delay") => NodeKind::Delay {
+                    max_delay_time: parse_number(tokens.next())?,
+                    interpolation: parse_name(&DELAY_INTERPOLATIONS, tokens.next().unwrap_or(""))?,
+                },
+                Some("biquad-filter") => NodeKind::BiquadFilter {
+                    type_: parse_name(&BIQUAD_FILTER_TYPES, tokens.next().unwrap_or(""))?,
+                },
+                Some("stereo-panner") => NodeKind::StereoPanner,
+                Some("constant-source") => NodeKind::ConstantSource,
+                Some("oscillator") => NodeKind::Oscillator {
+                    type_: parse_name(&OSCILLATOR_TYPES, tokens.next().unwrap_or(""))?,
+                },
+                other => return Err(format!("unknown node {:?}", other.unwrap_or(""))),
+            };
+            if tokens.next() != Some("channels") {
+                return Err("expected channels".to_string());
+            }
+            document.nodes.push(NodeDocument {
+                kind,
+                channel_count: parse_number(tokens.next())?,
+                channel_count_mode: parse_name(&CHANNEL_COUNT_MODES, tokens.next().unwrap_or(""))?,
+                channel_interpretation: parse_name(
+                    &CHANNEL_INTERPRETATIONS,
+                    tokens.next().unwrap_or(""),
+                )?,
+                params: vec![],
+                start_time: None,
+                stop_time: None,
+            });
+        }
+        "start" => {
+            node(document).ok_or_else(no_node)?.start_time = Some(parse_number(tokens.next())?)
+        }
+        "stop" => {
+            node(document).ok_or_else(no_node)?.stop_time = Some(parse_number(tokens.next())?)
+        }
+        "param" => {
+            let name = tokens.next().ok_or_else(|| "missing name".to_string())?;
+            node(document)
+                .ok_or_else(no_node)?
+                .params
+                .push(ParamDocument {
+                    name: name.to_string(),
+                    value: parse_number(tokens.next())?,
+                    automation: vec![],
+                });
+        }
+        "set-value-at-time" => {
+            param(document).ok_or_else(no_param)?.automation.push(
+                AutomationEvent::SetValueAtTime {
+                    value: parse_number(tokens.next())?,
+                    start_time: parse_number(tokens.next())?,
+                },
+            );
+        }
+        "linear-ramp-to-value-at-time" => {
+            param(document).ok_or_else(no_param)?.automation.push(
+                AutomationEvent::LinearRampToValueAtTime {
+                    value: parse_number(tokens.next())?,
+                    end_time: parse_number(tokens.next())?,
+                },
+            );
+        }
+        "exponential-ramp-to-value-at-time" => {
+            param(document).ok_or_else(no_param)?.automation.push(
+                AutomationEvent::ExponentialRampToValueAtTime {
+                    value: parse_number(tokens.next())?,
+                    end_time: parse_number(tokens.next())?,
+                },
+            );
+        }
+        "set-target-at-time" => {
+            param(document).ok_or_else(no_param)?.automation.push(
+                AutomationEvent::SetTargetAtTime {
+                    value: parse_number(tokens.next())?,
+                    start_time: parse_number(tokens.next())?,
+                    time_constant: parse_number(tokens.next())?,
+                },
+            );
+        }
+        "cancel-and-hold-at-time" => {
+            param(document).ok_or_else(no_param)?.automation.push(
+                AutomationEvent::CancelAndHoldAtTime {
+                    cancel_time: parse_number(tokens.next())?,
+                },
+            );
+        }
+        "set-value-curve-at-time" => {
+            let start_time = parse_number(tokens.next())?;
+            let duration = parse_number(tokens.next())?;
+            let values = tokens
+                .by_ref()
+                .map(|value| parse_number(Some(value)))
+                .collect::<Result<_, _>>()?;
+            param(document).ok_or_else(no_param)?.automation.push(
+                AutomationEvent::SetValueCurveAtTime {
+                    values,
+                    start_time,
+                    duration,
+                },
+            );
+        }
+        "connect" => {
+            let from = parse_number(tokens.next())?;
+            let output = parse_number(tokens.next())?;
+            let to = match tokens.next() {
+                Some("node") => ConnectionTarget::Node {
+                    index: parse_number(tokens.next())?,
+                    input: parse_number(tokens.next())?,
+                },
+                Some("param") => ConnectionTarget::Param {
+                    index: parse_number(tokens.next())?,
+                    name: tokens
+                        .next()
+                        .ok_or_else(|| "missing name".to_string())?
+                        .to_string(),
+                },
+                Some("destination") => ConnectionTarget::Destination {
+                    input: parse_number(tokens.next())?,
+                },
+                other => return Err(format!("unknown target {:?}", other.unwrap_or(""))),
+            };
+            document
+                .connections
+                .push(ConnectionDocument { from, output, to });
+        }
+        _ => return Err(format!("unknown keyword {:?}", keyword)),
+    }
+
+    match tokens.next() {
+        Some(token) => Err(format!("unexpected {:?}", token)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::context::OfflineAudioContext;
+
+    fn build_graph(context: &OfflineAudioContext) -> Vec<RestoredNode> {
+        let osc = context.create_oscillator();
+        osc.set_type(OscillatorType::Square);
+        osc.frequency().set_value(220.);
+        osc.frequency().linear_ramp_to_value_at_time(880., 0.5);
+
+        let lfo = context.create_constant_source();
+        lfo.offset().set_value(0.25);
+        lfo.offset().set_value_at_time(0.5, 0.25);
+
+        let filter = context.create_biquad_filter();
+        filter.set_type(BiquadFilterType::Highpass);
+        filter.frequency().set_value(500.);
+
+        let delay = context.create_delay(0.5);
+        delay.delay_time().set_value(0.01);
+
+        let gain = context.create_gain();
+        gain.gain().set_value(0.);
+
+        osc.connect(&filter);
+        filter.connect(&delay);
+        filter.connect(&gain);
+        delay.connect(&gain);
+        lfo.connect(gain.gain());
+        gain.connect(&context.destination());
+
+        osc.start();
+        lfo.start_at(0.1);
+        osc.stop_at(0.75);
+
+        vec![
+            RestoredNode::Oscillator(osc),
+            RestoredNode::ConstantSource(lfo),
+            RestoredNode::BiquadFilter(filter),
+            RestoredNode::Delay(delay),
+            RestoredNode::Gain(gain),
+        ]
+    }
+
+    fn capture(context: &OfflineAudioContext, nodes: &[RestoredNode]) -> GraphDocument {
+        let nodes: Vec<_> = nodes.iter().map(RestoredNode::as_graph_node).collect();
+        GraphDocument::capture(context, &nodes).unwrap()
+    }
+
+    #[test]
+    fn test_capture() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let nodes = build_graph(&context);
+        let document = capture(&context, &nodes);
+
+        assert_eq!(document.nodes.len(), 5);
+        assert_eq!(
+            document.nodes[0].kind,
+            NodeKind::Oscillator {
+                type_: OscillatorType::Square
+            }
+        );
+        assert_eq!(document.nodes[0].start_time, Some(0.));
+        assert_eq!(document.nodes[0].stop_time, Some(0.75));
+        assert_eq!(
+            document.nodes[0].params[0],
+            ParamDocument {
+                name: "frequency".to_string(),
+                value: 220.,
+                automation: vec![AutomationEvent::LinearRampToValueAtTime {
+                    value: 880.,
+                    end_time: 0.5
+                }],
+            }
+        );
+        assert_eq!(document.nodes[1].start_time, Some(0.1));
+        assert_eq!(document.nodes[1].stop_time, None);
+        assert_eq!(
+            document.nodes[3].kind,
+            NodeKind::Delay {
+                max_delay_time: 0.5,
+                interpolation: DelayInterpolation::Linear
+            }
+        );
+
+        assert_eq!(
+            document.connections,
+            vec![
+                ConnectionDocument {
+                    from: 0,
+                    output: 0,
+                    to: ConnectionTarget::Node { index: 2, input: 0 }
+                },
+                ConnectionDocument {
+                    from: 1,
+                    output: 0,
+                    to: ConnectionTarget::Param {
+                        index: 4,
+                        name: "gain".to_string()
+                    }
+                },
+                ConnectionDocument {
+                    from: 2,
+                    output: 0,
+                    to: ConnectionTarget::Node { index: 3, input: 0 }
+                },
+                ConnectionDocument {
+                    from: 2,
+                    output: 0,
+                    to: ConnectionTarget::Node { index: 4, input: 0 }
+                },
+                ConnectionDocument {
+                    from: 3,
+                    output: 0,
+                    to: ConnectionTarget::Node { index: 4, input: 0 }
+                },
+                ConnectionDocument {
+                    from: 4,
+                    output: 0,
+                    to: ConnectionTarget::Destination { input: 0 }
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_roundtrip() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let nodes = build_graph(&context);
+        if let RestoredNode::Gain(gain) = &nodes[4] {
+            gain.gain()
+                .set_target_at_time(1., 0.1, 0.05)
+                .cancel_and_hold_at_time(0.2)
+                .set_value_curve_at_time(&[0., 0.5, 1.], 0.3, 0.1)
+                .exponential_ramp_to_value_at_time(0.1, 0.6);
+        }
+        let document = capture(&context, &nodes);
+
+        let text = document.to_string();
+        let parsed: GraphDocument = text.parse().unwrap();
+        assert_eq!(parsed, document);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            "node gain".parse::<GraphDocument>(),
+            Err(GraphDocumentError::Parse { line: 1, .. })
+        ));
+
+        let text = format!("{}\nparam gain 1", HEADER);
+        assert!(matches!(
+            text.parse::<GraphDocument>(),
+            Err(GraphDocumentError::Parse { line: 2, .. })
+        ));
+
+        let text = format!("{}\nnode gain channels 2 max speakers extra", HEADER);
+        assert!(matches!(
+            text.parse::<GraphDocument>(),
+            Err(GraphDocumentError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_custom_oscillator() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let gain = context.create_gain();
+        let osc = context.create_oscillator();
+        let wave = context.create_periodic_wave(Default::default());
+        osc.set_periodic_wave(wave);
+
+        let result = GraphDocument::capture(&context, &[(&gain).into(), (&osc).into()]);
+        assert_eq!(result, Err(GraphDocumentError::CustomOscillator(1)));
+    }
+
+    #[test]
+    fn test_pending_automation_relative_to_capture() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let gain = context.create_gain();
+        gain.gain()
+            .set_value_at_time(0., 0.)
+            .linear_ramp_to_value_at_time(1., 2.)
+            .set_value_curve_at_time(&[1., 0.5, 0.], 3., 2.);
+
+        let param = gain.gain();
+        // the ramp is running, its starting point is the current value
+        assert_eq!(
+            param.pending_automation(1.),
+            vec![
+                AutomationEvent::LinearRampToValueAtTime {
+                    value: 1.,
+                    end_time: 1.
+                },
+                AutomationEvent::SetValueCurveAtTime {
+                    values: vec![1., 0.5, 0.],
+                    start_time: 2.,
+                    duration: 2.
+                },
+            ]
+        );
+        // the curve is running, the points that passed are dropped
+        let automation = param.pending_automation(4.5);
+        assert_eq!(automation.len(), 1);
+        match &automation[0] {
+            AutomationEvent::SetValueCurveAtTime {
+                values,
+                start_time,
+                duration,
+            } => {
+                assert_eq!(values.len(), 2);
+                assert_float_eq!(values[1], 0., abs <= 0.);
+                assert_float_eq!(*start_time, 0., abs <= 0.);
+                assert_float_eq!(*duration, 0.5, abs <= 1e-12);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(param.pending_automation(6.), vec![]);
+    }
+
+    #[test]
+    fn test_restore_renders_identical() {
+        let sample_rate = 44_100.;
+        let length = sample_rate as usize;
+
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+        let nodes = build_graph(&context);
+        let text = capture(&context, &nodes).to_string();
+        let expected = context.start_rendering_sync();
+
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+        let document: GraphDocument = text.parse().unwrap();
+        let restored = document.restore(&context);
+        assert_eq!(restored.len(), 5);
+        assert_eq!(capture(&context, &restored), document);
+        let result = context.start_rendering_sync();
+
+        assert_float_eq!(
+            result.get_channel_data(0),
+            expected.get_channel_data(0),
+            abs_all <= 0.
+        );
+    }
+}
@@ -7,6 +7,9 @@ pub use base::*;
 mod concrete_base;
 pub use concrete_base::*;
 
+mod document;
+pub use document::*;
+
 mod offline;
 pub use offline::*;
 
@@ -19,7 +22,7 @@ const DESTINATION_NODE_ID: AudioNodeId = AudioNodeId(0);
 /// listener node id is always at index 1
 const LISTENER_NODE_ID: AudioNodeId = AudioNodeId(1);
 /// listener audio parameters ids are always at index 2 through 10
-const LISTENER_PARAM_IDS: Range<u64> = 2..11;
+pub(crate) const LISTENER_PARAM_IDS: Range<u64> = 2..11;
 /// listener audio parameters ids are always at index 2 through 10
 pub(crate) const LISTENER_AUDIO_PARAM_IDS: [AudioParamId; 9] = [
     AudioParamId(2),
@@ -41,8 +44,9 @@ pub(crate) struct AudioNodeId(pub u64);
 
 /// Unique identifier for audio params.
 ///
-/// Store these in your `AudioProcessor` to get access to `AudioParam` values.
-#[derive(Debug)]
+/// Store these in your `AudioProcessor` to get access to `AudioParam` values. Clone them to
+/// build a replacement processor, see [`BaseAudioContext::replace_processor`].
+#[derive(Clone, Debug)]
 pub struct AudioParamId(u64);
 
 // bit contrived, but for type safety only the context mod can access the inner u64
@@ -63,6 +67,9 @@ pub enum AudioContextState {
     /// This context has been released, and can no longer be used to process audio.
     /// All system audio resources have been released.
     Closed,
+    /// The audio output device was lost, e.g. an USB interface was unplugged. Context time is
+    /// not proceeding, the context resumes when the device is available again.
+    Interrupted,
 }
 
 impl From<u8> for AudioContextState {
@@ -71,6 +78,7 @@ impl From<u8> for AudioContextState {
             0 => Self::Suspended,
             1 => Self::Running,
             2 => Self::Closed,
+            3 => Self::Interrupted,
             _ => unreachable!(),
         }
     }
@@ -111,7 +119,7 @@ impl Drop for AudioContextRegistration {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::node::AudioNode;
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
 
     use float_eq::assert_float_eq;
 
@@ -194,4 +202,42 @@ mod tests {
         let dest = context.destination();
         assert!(dest.context() == context.base());
     }
+
+    #[test]
+    fn test_batch() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+
+        let src = context.batch(|context| {
+            let src = context.create_constant_source();
+            let gain = context.batch(|context| context.create_gain()); // nested
+            gain.gain().set_value(0.5);
+            src.connect(&gain);
+            gain.connect(&context.destination());
+            src.start();
+            src
+        });
+        assert_eq!(src.number_of_outputs(), 1);
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(0), &[0.5; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_batch_panic() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+
+        // the edits made before the panic are applied
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            context.batch(|context| {
+                let src = context.create_constant_source();
+                src.connect(&context.destination());
+                src.start();
+                panic!("oops");
+            })
+        }));
+        assert!(result.is_err());
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(0), &[1.; 128][..], abs_all <= 0.);
+    }
 }
@@ -1,11 +1,17 @@
 //! The `OfflineAudioContext` type
-use std::sync::atomic::AtomicU64;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::assert_valid_sample_rate;
 use crate::buffer::AudioBuffer;
-use crate::context::{BaseAudioContext, ConcreteBaseAudioContext};
+use crate::capture::Stem;
+use crate::context::{AudioNodeId, BaseAudioContext, ConcreteBaseAudioContext};
+use crate::node::AudioNode;
 use crate::render::RenderThread;
+use crate::{assert_valid_sample_rate, Event, RENDER_QUANTUM_SIZE};
+
+/// Callback receiving the progress of the rendering
+type ProgressHandler = Box<dyn FnMut(OfflineRenderProgressEvent) + Send + 'static>;
 
 /// The `OfflineAudioContext` doesn't render the audio to the device hardware; instead, it generates
 /// it, as fast as it can, and outputs the result to an `AudioBuffer`.
@@ -18,10 +24,72 @@ pub struct OfflineAudioContext {
     length: usize,
     /// the rendering 'thread', fully controlled by the offline context
     renderer: SingleUseRenderThread,
+    /// number of threads rendering the audio graph
+    render_threads: usize,
+    /// callback receiving the progress of the rendering
+    onprogress: Mutex<Option<ProgressHandler>>,
+    /// cancellation of the rendering, shared with the application
+    cancellation: CancellationToken,
+    /// named captures rendered along with the output
+    stems: Mutex<Vec<Stem>>,
+}
+
+/// Progress of an offline rendering, see [`OfflineAudioContext::set_onprogress`]
+#[derive(Clone, Debug)]
+pub struct OfflineRenderProgressEvent {
+    /// Number of sample-frames rendered so far
+    pub rendered_frames: usize,
+    /// Total number of sample-frames to render, the length of the context
+    pub length: usize,
+    /// Inherits from this base Event
+    pub event: Event,
+}
+
+impl OfflineRenderProgressEvent {
+    /// Fraction of the rendering that is done, between 0 and 1
+    pub fn progress(&self) -> f64 {
+        if self.length == 0 {
+            return 1.;
+        }
+        self.rendered_frames as f64 / self.length as f64
+    }
+}
+
+/// Handle to cancel an offline rendering, e.g. from a GUI thread, see
+/// [`OfflineAudioContext::cancellation_token`]
+///
+/// Clones of a token refer to the same rendering.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Stop the rendering at the next render quantum
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the rendering has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Statistics of an offline rendering, see
+/// [`OfflineAudioContext::start_rendering_sync_with_stats`]
+#[derive(Clone, Debug)]
+pub struct OfflineRenderStats {
+    /// Number of threads that rendered the audio graph
+    pub render_threads: usize,
+    /// Wall clock time spent rendering
+    pub elapsed: Duration,
+    /// Duration of the rendered audio divided by the elapsed time, i.e. how many times faster
+    /// than realtime the rendering ran
+    pub speedup: f64,
 }
 
 mod private {
     use super::*;
+    use crate::render::graph::Graph;
 
     pub(crate) struct SingleUseRenderThread(RenderThread);
 
@@ -30,13 +98,31 @@ mod private {
             Self(rt)
         }
 
-        pub fn render_audiobuffer(self, buffer_size: usize) -> AudioBuffer {
-            self.0.render_audiobuffer(buffer_size)
+        pub fn render_audiobuffer<F: FnMut(usize) -> bool>(
+            self,
+            buffer_size: usize,
+            on_quantum: F,
+        ) -> AudioBuffer {
+            self.0.render_audiobuffer(buffer_size, on_quantum)
+        }
+
+        #[cfg(feature = "parallel-rendering")]
+        pub fn set_render_threads(&mut self, number_of_threads: usize) {
+            self.0.set_render_threads(number_of_threads);
+        }
+
+        pub fn render_quanta(&mut self, number_of_quanta: usize, number_of_channels: usize) {
+            let mut buffer = vec![0.; number_of_quanta * RENDER_QUANTUM_SIZE * number_of_channels];
+            self.0.render::<f32>(&mut buffer);
+        }
+
+        pub fn sync_graph(&mut self) -> Option<&Graph> {
+            self.0.sync_graph()
         }
     }
 
     // SAFETY:
-    // The RenderThread is not Sync since it contains `AudioRenderQuantum`s (which use Rc) and `dyn
+    // The RenderThread is not Sync since it contains the render graph (which uses RefCell) and `dyn
     // AudioProcessor` which may not allow sharing between threads. However we mark the
     // SingleUseRenderThread as Sync because it can only run once (and thus on a single thread)
     // NB: the render thread should never hand out the contained `Rc` and `AudioProcessor`s
@@ -66,7 +152,7 @@ impl OfflineAudioContext {
         // communication channel to the render thread
         let (sender, receiver) = crossbeam_channel::unbounded();
 
-        let graph = crate::render::graph::Graph::new();
+        let graph = Box::new(crate::render::graph::Graph::new());
         let message = crate::message::ControlMessage::Startup { graph };
         sender.send(message).unwrap();
 
@@ -82,6 +168,7 @@ impl OfflineAudioContext {
             frames_played_clone,
             None,
             None,
+            Arc::default(),
         );
 
         // first, setup the base audio context
@@ -98,15 +185,197 @@ impl OfflineAudioContext {
             base,
             length,
             renderer: SingleUseRenderThread::new(renderer),
+            render_threads: 1,
+            onprogress: Mutex::new(None),
+            cancellation: CancellationToken::default(),
+            stems: Mutex::new(vec![]),
         }
     }
 
+    /// Render the audio graph on the given number of threads, 1 by default
+    ///
+    /// The nodes that do not depend on each other, e.g. the voices or the tracks of a mix, are
+    /// rendered in parallel, which speeds up the rendering of large graphs. The output is the
+    /// same as with a single thread, up to rounding errors of the summing of the signals.
+    ///
+    /// Distributing the work costs some synchronization at every render quantum, so only graphs
+    /// with enough work to share benefit from it. The speedup is reported by
+    /// [`start_rendering_sync_with_stats`](Self::start_rendering_sync_with_stats), and
+    /// [`std::thread::available_parallelism`] provides a sensible upper bound.
+    ///
+    /// Only available with the `parallel-rendering` feature, which shares the audio buffers of
+    /// every context between threads and thereby makes their reference counting a bit slower.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of threads is zero
+    #[cfg(feature = "parallel-rendering")]
+    pub fn set_render_threads(&mut self, number_of_threads: usize) {
+        assert!(
+            number_of_threads > 0,
+            "RangeError - the number of render threads should be at least 1"
+        );
+        self.render_threads = number_of_threads;
+        self.renderer.set_render_threads(number_of_threads);
+    }
+
+    /// Number of threads rendering the audio graph
+    pub fn render_threads(&self) -> usize {
+        self.render_threads
+    }
+
+    /// Register a callback receiving the progress of the rendering, e.g. to update a progress
+    /// bar
+    ///
+    /// The callback runs on the thread calling
+    /// [`start_rendering_sync`](Self::start_rendering_sync), about every percent of the
+    /// rendering and once it is complete. Only a single handler can be registered, it replaces
+    /// the previous one.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn set_onprogress<F: FnMut(OfflineRenderProgressEvent) + Send + 'static>(
+        &self,
+        callback: F,
+    ) {
+        *self.onprogress.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Unset the callback receiving the progress of the rendering
+    #[allow(clippy::missing_panics_doc)]
+    pub fn clear_onprogress(&self) {
+        *self.onprogress.lock().unwrap() = None;
+    }
+
+    /// Token to cancel the rendering, from another thread
+    ///
+    /// When cancelled, the rendering stops at the next render quantum and the `AudioBuffer`
+    /// returned by [`start_rendering_sync`](Self::start_rendering_sync) only contains the
+    /// sample-frames rendered so far.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Record the output of `node` as a separate stem of the rendering
+    ///
+    /// The stems are rendered in the same pass as the output of the context, and returned by
+    /// [`start_rendering_sync_with_stems`](Self::start_rendering_sync_with_stems), e.g. to
+    /// export the tracks of a mix without rendering it once per track. The first output of the
+    /// node is captured, up or down-mixed to the given number of channels. The node does not
+    /// have to be connected to the destination.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when:
+    /// - a stem with the same name exists already
+    /// - the node has no outputs or belongs to another context
+    /// - the number of channels is outside the [1, 32] range
+    pub fn add_stem(&self, name: &str, node: &dyn AudioNode, number_of_channels: usize) {
+        let mut stems = self.stems.lock().unwrap();
+        assert!(
+            stems.iter().all(|stem| stem.name() != name),
+            "InvalidStateError - a stem named {:?} exists already",
+            name
+        );
+
+        let stem = Stem::new(self, name.into(), node, number_of_channels, self.length);
+        stems.push(stem);
+    }
+
     /// Given the current connections and scheduled changes, starts rendering audio.
     ///
     /// This function will block the current thread and returns the rendered `AudioBuffer`
     /// synchronously. An async version is currently not implemented.
+    ///
+    /// The rendering can be followed with [`set_onprogress`](Self::set_onprogress) and aborted
+    /// with the [`cancellation_token`](Self::cancellation_token).
     pub fn start_rendering_sync(self) -> AudioBuffer {
-        self.renderer.render_audiobuffer(self.length)
+        self.start_rendering_sync_with_stems().0
+    }
+
+    /// Starts rendering audio like [`start_rendering_sync`](Self::start_rendering_sync), and
+    /// returns the stems registered with [`add_stem`](Self::add_stem) along with the output
+    ///
+    /// The stems are returned in the order they were added, with the same length as the
+    /// output.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn start_rendering_sync_with_stems(self) -> (AudioBuffer, Vec<(String, AudioBuffer)>) {
+        let length = self.length;
+        let sample_rate = self.base.sample_rate();
+        let cancellation = self.cancellation;
+        let mut onprogress = self.onprogress.into_inner().unwrap();
+        let stems = self.stems.into_inner().unwrap();
+
+        // report about every percent, and at the end
+        let interval = (length / 100).max(RENDER_QUANTUM_SIZE);
+        let mut next_report = interval;
+
+        let buffer = self
+            .renderer
+            .render_audiobuffer(length, move |rendered_frames| {
+                if let Some(callback) = onprogress.as_mut() {
+                    if rendered_frames >= next_report || rendered_frames == length {
+                        next_report = (rendered_frames / interval + 1) * interval;
+                        callback(OfflineRenderProgressEvent {
+                            rendered_frames,
+                            length,
+                            event: Event { type_: "progress" },
+                        });
+                    }
+                }
+
+                !cancellation.is_cancelled()
+            });
+
+        // a cancelled rendering is shorter
+        let stems = stems
+            .into_iter()
+            .map(|stem| {
+                let name = stem.name().to_string();
+                (name, stem.into_buffer(buffer.length(), sample_rate))
+            })
+            .collect();
+
+        (buffer, stems)
+    }
+
+    /// Starts rendering audio like [`start_rendering_sync`](Self::start_rendering_sync), and
+    /// reports how fast the rendering ran
+    pub fn start_rendering_sync_with_stats(self) -> (AudioBuffer, OfflineRenderStats) {
+        let render_threads = self.render_threads;
+        let start = Instant::now();
+        let buffer = self.start_rendering_sync();
+        let elapsed = start.elapsed();
+
+        let stats = OfflineRenderStats {
+            render_threads,
+            elapsed,
+            speedup: buffer.duration() / elapsed.as_secs_f64().max(f64::MIN_POSITIVE),
+        };
+
+        (buffer, stats)
+    }
+
+    /// Render the given number of quanta, discarding the output, used by the
+    /// [`testing`](crate::testing) module to interleave rendering with graph mutations
+    ///
+    /// The context must not be used with [`Self::start_rendering_sync`] afterwards.
+    pub(crate) fn render_quanta(&mut self, number_of_quanta: usize) {
+        let number_of_channels = self.base.max_channel_count();
+        self.renderer
+            .render_quanta(number_of_quanta, number_of_channels);
+    }
+
+    /// Validate that the render graph is consistent and mirrors the connections of the control
+    /// thread, used by the [`testing`](crate::testing) module
+    ///
+    /// Returns the ids of the nodes present in the render graph.
+    pub(crate) fn check_graph_consistency(&mut self) -> Result<Vec<AudioNodeId>, String> {
+        let connections = self.base.connections();
+        let graph = self
+            .renderer
+            .sync_graph()
+            .ok_or_else(|| String::from("the render graph is not available"))?;
+        graph.check_consistency(&connections)?;
+        Ok(graph.node_ids().collect())
     }
 
     /// get the length of rendering audio buffer
@@ -133,4 +402,188 @@ mod tests {
         assert_float_eq!(buffer.get_channel_data(0), &[0.; 555][..], abs_all <= 0.);
         assert_float_eq!(buffer.get_channel_data(1), &[0.; 555][..], abs_all <= 0.);
     }
+
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    #[cfg(feature = "parallel-rendering")]
+    fn render_voices(render_threads: usize) -> (AudioBuffer, OfflineRenderStats) {
+        let mut context = OfflineAudioContext::new(2, 44_100, 44_100.);
+        context.set_render_threads(render_threads);
+
+        for i in 0..16 {
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(110. * (i + 1) as f32);
+            let gain = context.create_gain();
+            gain.gain()
+                .linear_ramp_to_value_at_time(0.05, 0.5 + i as f64 * 0.01);
+            let panner = context.create_stereo_panner();
+            panner.pan().set_value(i as f32 / 8. - 1.);
+
+            osc.connect(&gain);
+            gain.connect(&panner);
+            panner.connect(&context.destination());
+            osc.start_at(i as f64 * 0.02);
+        }
+
+        context.start_rendering_sync_with_stats()
+    }
+
+    #[test]
+    #[cfg(feature = "parallel-rendering")]
+    fn test_parallel_rendering() {
+        let (expected, stats) = render_voices(1);
+        assert_eq!(stats.render_threads, 1);
+        assert!(stats.speedup > 0.);
+
+        let (output, stats) = render_voices(4);
+        assert_eq!(stats.render_threads, 4);
+
+        for c in 0..2 {
+            let error = output
+                .get_channel_data(c)
+                .iter()
+                .zip(expected.get_channel_data(c))
+                .map(|(o, e)| (o - e).abs())
+                .fold(0., f32::max);
+            assert!(error < 1e-5, "error {}", error);
+        }
+        assert!(expected.get_channel_data(0).iter().any(|s| *s != 0.));
+    }
+
+    #[test]
+    fn test_progress() {
+        let context = OfflineAudioContext::new(1, 44_100, 44_100.);
+        let events = Arc::new(Mutex::new(vec![]));
+        let events_clone = Arc::clone(&events);
+        context.set_onprogress(move |event| events_clone.lock().unwrap().push(event));
+
+        let buffer = context.start_rendering_sync();
+        assert_eq!(buffer.length(), 44_100);
+
+        let events = events.lock().unwrap();
+        // about every percent, and at the end
+        assert!(
+            events.len() >= 90 && events.len() <= 101,
+            "{}",
+            events.len()
+        );
+        assert!(events
+            .windows(2)
+            .all(|w| w[0].rendered_frames < w[1].rendered_frames));
+        let last = events.last().unwrap();
+        assert_eq!(last.rendered_frames, 44_100);
+        assert_eq!(last.length, 44_100);
+        assert_float_eq!(last.progress(), 1., abs <= 0.);
+        assert_eq!(last.event.type_, "progress");
+    }
+
+    #[test]
+    fn test_cancel() {
+        let context = OfflineAudioContext::new(1, 44_100, 44_100.);
+        let token = context.cancellation_token();
+        assert!(!token.is_cancelled());
+
+        // cancel halfway, as a GUI would from another thread
+        context.set_onprogress(move |event| {
+            if event.progress() >= 0.5 {
+                token.cancel();
+            }
+        });
+
+        let buffer = context.start_rendering_sync();
+        assert!(buffer.length() >= 22_050 && buffer.length() < 44_100);
+        assert_eq!(buffer.length() % RENDER_QUANTUM_SIZE, 0);
+    }
+
+    #[test]
+    fn test_cancel_before_rendering() {
+        let context = OfflineAudioContext::new(1, 44_100, 44_100.);
+        context.cancellation_token().cancel();
+
+        let buffer = context.start_rendering_sync();
+        assert_eq!(buffer.length(), RENDER_QUANTUM_SIZE);
+    }
+
+    #[test]
+    fn test_stems() {
+        let context = OfflineAudioContext::new(2, 1000, 44_100.);
+
+        let bass = context.create_constant_source();
+        bass.offset().set_value(0.5);
+        bass.connect(&context.destination());
+        bass.start_at(0.01);
+        context.add_stem("bass", &bass, 1);
+
+        let drums = context.create_constant_source();
+        drums.offset().set_value(0.25);
+        let panner = context.create_stereo_panner();
+        panner.pan().set_value(1.);
+        drums.connect(&panner);
+        panner.connect(&context.destination());
+        drums.start();
+        context.add_stem("drums", &panner, 2);
+
+        let (output, stems) = context.start_rendering_sync_with_stems();
+        let names: Vec<_> = stems.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["bass", "drums"]);
+
+        let bass = &stems[0].1;
+        assert_eq!(bass.number_of_channels(), 1);
+        assert_eq!(bass.length(), 1000);
+        assert_float_eq!(
+            bass.get_channel_data(0)[..441],
+            [0.; 441][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            bass.get_channel_data(0)[441..],
+            [0.5; 559][..],
+            abs_all <= 0.
+        );
+
+        let drums = &stems[1].1;
+        assert_eq!(drums.number_of_channels(), 2);
+        assert_float_eq!(drums.get_channel_data(0), &[0.; 1000][..], abs_all <= 1e-7);
+        // the up-mixed input is panned to the right
+        assert_float_eq!(drums.get_channel_data(1), &[0.5; 1000][..], abs_all <= 1e-7);
+
+        // the stems add up to the output
+        for i in 0..1000 {
+            let left = bass.get_channel_data(0)[i] + drums.get_channel_data(0)[i];
+            let right = bass.get_channel_data(0)[i] + drums.get_channel_data(1)[i];
+            assert_float_eq!(output.get_channel_data(0)[i], left, abs <= 1e-6);
+            assert_float_eq!(output.get_channel_data(1)[i], right, abs <= 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_cancel_stems() {
+        let context = OfflineAudioContext::new(1, 44_100, 44_100.);
+        let src = context.create_constant_source();
+        src.start();
+        context.add_stem("src", &src, 1);
+
+        let token = context.cancellation_token();
+        token.cancel();
+
+        let (output, stems) = context.start_rendering_sync_with_stems();
+        assert_eq!(stems[0].1.length(), output.length());
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_duplicate_stem() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let src = context.create_constant_source();
+        context.add_stem("src", &src, 1);
+        context.add_stem("src", &src, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel-rendering")]
+    #[should_panic]
+    fn test_zero_render_threads() {
+        let mut context = OfflineAudioContext::new(2, 555, 44_100.);
+        context.set_render_threads(0);
+    }
 }
@@ -1,16 +1,25 @@
 //! The `AudioContext` type and constructor options
 use std::error::Error;
-use std::sync::Mutex;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
 
 use crate::context::{AudioContextState, BaseAudioContext, ConcreteBaseAudioContext};
-use crate::events::{EventDispatch, EventHandler, EventType};
+use crate::echo_cancellation::{EchoCancellationOptions, EchoCancellingStream};
+use crate::events::{EventDispatch, EventHandler, EventPayload, EventType};
 use crate::io::{self, AudioBackendManager, ControlThreadInit, RenderThreadInit};
-use crate::media_devices::{enumerate_devices_sync, MediaDeviceInfoKind};
+use crate::media_devices::{enumerate_devices_sync, MediaDeviceInfo, MediaDeviceInfoKind};
 use crate::media_streams::{MediaStream, MediaStreamTrack};
 use crate::message::ControlMessage;
-use crate::node::{self, ChannelConfigOptions};
+use crate::node::{self, AudioNode};
+use crate::render::RenderTiming;
 use crate::MediaElement;
-use crate::{AudioRenderCapacity, Event};
+use crate::{
+    AudioRenderCapacity, CaptureHandle, CaptureOptions, Event, OverloadEvent, WatchdogOptions,
+    RENDER_QUANTUM_SIZE,
+};
 
 /// Check if the provided sink_id is available for playback
 ///
@@ -26,6 +35,263 @@ fn is_valid_sink_id(sink_id: &str) -> bool {
     }
 }
 
+/// Interval at which the devices are checked while the output device is lost
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Check if the output device of the provided sink_id is available, any device for the default
+/// sink `""`
+fn is_output_device_available(sink_id: &str) -> bool {
+    if sink_id == "none" {
+        true
+    } else {
+        enumerate_devices_sync()
+            .into_iter()
+            .filter(|d| d.kind() == MediaDeviceInfoKind::AudioOutput)
+            .any(|d| sink_id.is_empty() || d.device_id() == sink_id)
+    }
+}
+
+/// Replace the audio backend by a new one for the given sink and latency, the audio graph is
+/// moved to the new render thread
+///
+/// Returns `false` when the context is closed, the backend is not replaced then.
+#[allow(clippy::needless_collect)]
+fn hotswap_backend(
+    base: &ConcreteBaseAudioContext,
+    backend_manager: &Mutex<Box<dyn AudioBackendManager>>,
+    render_thread_init: &RenderThreadInit,
+    sink_id: String,
+    latency_hint: AudioContextLatencyCategory,
+) -> bool {
+    let mut backend_manager_guard = backend_manager.lock().unwrap();
+    let original_state = base.state();
+    if original_state == AudioContextState::Closed {
+        return false;
+    }
+
+    // Temporarily set the state to Suspended, resume after the new backend is up
+    if original_state != AudioContextState::Interrupted {
+        base.set_state(AudioContextState::Suspended);
+    }
+
+    // Acquire exclusive lock on ctrl msg sender
+    let ctrl_msg_send = base.lock_control_msg_sender();
+
+    // Flush out the ctrl msg receiver, cache
+    let mut pending_msgs: Vec<_> = render_thread_init.ctrl_msg_recv.try_iter().collect();
+
+    // Acquire the active audio graph from the current render thread, shutting it down
+    let graph = if matches!(pending_msgs.get(0), Some(ControlMessage::Startup { .. })) {
+        // Handle the edge case where the previous backend was suspended for its entire lifetime.
+        // In this case, the `Startup` control message was never processed.
+        let msg = pending_msgs.remove(0);
+        match msg {
+            ControlMessage::Startup { graph } => *graph,
+            _ => unreachable!(),
+        }
+    } else {
+        // Acquire the audio graph from the current render thread, shutting it down
+        let (graph_send, graph_recv) = crossbeam_channel::bounded(1);
+        let message = ControlMessage::Shutdown { sender: graph_send };
+        ctrl_msg_send.send(message).unwrap();
+        if original_state == AudioContextState::Suspended {
+            // We must wake up the render thread to be able to handle the shutdown.
+            // No new audio will be produced because it will receive the shutdown command first.
+            backend_manager_guard.resume();
+        } else if original_state == AudioContextState::Interrupted {
+            // The stream of a lost device does not call back anymore. Closing it drops the render
+            // thread, which handles the shutdown on drop.
+            backend_manager_guard.close();
+        }
+        graph_recv.recv().unwrap()
+    };
+
+    // hotswap the backend
+    let options = AudioContextOptions {
+        sample_rate: Some(base.sample_rate()),
+        latency_hint,
+        sink_id,
+        render_size_hint: AudioContextRenderSizeCategory::default(), // todo reuse existing setting
+        max_channel_count: Some(base.max_channel_count()),
+    };
+    *backend_manager_guard = io::build_output(options, render_thread_init.clone());
+
+    // if the previous backend state was suspend, suspend the new one before shipping the graph
+    if original_state == AudioContextState::Suspended {
+        backend_manager_guard.suspend();
+    }
+
+    // send the audio graph to the new render thread
+    let message = ControlMessage::Startup {
+        graph: Box::new(graph),
+    };
+    ctrl_msg_send.send(message).unwrap();
+
+    // an interrupted context resumes when the output device is available again
+    if matches!(
+        original_state,
+        AudioContextState::Running | AudioContextState::Interrupted
+    ) {
+        base.set_state(AudioContextState::Running);
+    }
+
+    // flush the cached msgs
+    pending_msgs
+        .into_iter()
+        .for_each(|m| base.send_control_msg(m).unwrap());
+
+    // explicitly release the lock to prevent concurrent render threads
+    drop(backend_manager_guard);
+
+    true
+}
+
+/// Information of the effective output device of the provided sink_id, if available
+fn output_device_info(sink_id: &str) -> Option<MediaDeviceInfo> {
+    if sink_id == "none" {
+        return None;
+    }
+
+    let default_label = if sink_id.is_empty() {
+        Some(io::default_output_device_label()?)
+    } else {
+        None
+    };
+
+    enumerate_devices_sync()
+        .into_iter()
+        .filter(|d| d.kind() == MediaDeviceInfoKind::AudioOutput)
+        .find(|d| match &default_label {
+            Some(label) => d.label() == label,
+            None => d.device_id() == sink_id,
+        })
+}
+
+/// Move a context playing on the default sink `""` to the new default output device of the host,
+/// when it has changed since the last call
+fn follow_default_device(
+    base: &ConcreteBaseAudioContext,
+    backend_manager: &Mutex<Box<dyn AudioBackendManager>>,
+    render_thread_init: &RenderThreadInit,
+    latency_hint: &Mutex<AudioContextLatencyCategory>,
+    default_device: &mut Option<String>,
+) {
+    let label = io::default_output_device_label();
+    if label == *default_device {
+        return;
+    }
+    let previous = std::mem::replace(default_device, label);
+
+    // the loss of the device is reported by the backend
+    if previous.is_none() || default_device.is_none() {
+        return;
+    }
+
+    let sink_id = backend_manager.lock().unwrap().sink_id().to_owned();
+    if !sink_id.is_empty() {
+        return;
+    }
+
+    log::info!(
+        "Default audio output device changed to {:?}",
+        default_device
+    );
+    let swapped = hotswap_backend(
+        base,
+        backend_manager,
+        render_thread_init,
+        sink_id.clone(),
+        *latency_hint.lock().unwrap(),
+    );
+    if swapped {
+        let _ = base.send_event(EventDispatch::sink_change(sink_id));
+    }
+}
+
+/// Watch the audio output device of the context, runs until the context is dropped
+///
+/// The context is interrupted when the backend reports the loss of its output device, and resumed
+/// on a new backend when the device (or any default device) is available again. A context playing
+/// on the default sink follows the changes of the default output device of the host.
+fn monitor_output_device(
+    base: ConcreteBaseAudioContext,
+    backend_manager: Weak<Mutex<Box<dyn AudioBackendManager>>>,
+    render_thread_init: RenderThreadInit,
+    latency_hint: Arc<Mutex<AudioContextLatencyCategory>>,
+    device_lost_recv: Receiver<()>,
+) {
+    let mut default_device = io::default_output_device_label();
+
+    loop {
+        // wait for a device loss, or stop when the context is dropped
+        match device_lost_recv.recv_timeout(DEVICE_POLL_INTERVAL) {
+            Ok(()) => (),
+            Err(RecvTimeoutError::Timeout) => {
+                let backend_manager = match backend_manager.upgrade() {
+                    Some(backend_manager) => backend_manager,
+                    None => return,
+                };
+                follow_default_device(
+                    &base,
+                    &backend_manager,
+                    &render_thread_init,
+                    &latency_hint,
+                    &mut default_device,
+                );
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        // a suspended or closed context is left alone
+        if base.state() != AudioContextState::Running {
+            continue;
+        }
+
+        log::warn!("Audio output device lost, interrupting the context");
+        base.set_state(AudioContextState::Interrupted);
+
+        loop {
+            std::thread::sleep(DEVICE_POLL_INTERVAL);
+
+            let backend_manager = match backend_manager.upgrade() {
+                Some(backend_manager) => backend_manager,
+                None => return,
+            };
+
+            // the context was closed, or moved to another sink in the meantime
+            if base.state() != AudioContextState::Interrupted {
+                break;
+            }
+
+            let sink_id = backend_manager.lock().unwrap().sink_id().to_owned();
+            if !is_output_device_available(&sink_id) {
+                continue;
+            }
+
+            log::info!("Audio output device available again, resuming the context");
+            let swapped = hotswap_backend(
+                &base,
+                &backend_manager,
+                &render_thread_init,
+                sink_id.clone(),
+                *latency_hint.lock().unwrap(),
+            );
+
+            // the default device may have changed in the meantime
+            let label = io::default_output_device_label();
+            if swapped && sink_id.is_empty() && label != default_device {
+                let _ = base.send_event(EventDispatch::sink_change(sink_id));
+            }
+            default_device = label;
+            break;
+        }
+
+        // discard the notifications of the lost stream
+        device_lost_recv.try_iter().for_each(drop);
+    }
+}
+
 /// Identify the type of playback, which affects tradeoffs
 /// between audio output latency and power consumption
 #[derive(Copy, Clone, Debug)]
@@ -40,7 +306,9 @@ pub enum AudioContextLatencyCategory {
     Playback,
     /// Specify the number of seconds of latency
     ///
-    /// This latency is not guaranted to be applied, it depends on the audio hardware capabilities
+    /// The latency is mapped to the closest buffer size supported by the audio backend, so it is
+    /// not guaranteed to be applied exactly. The achieved value is reported by
+    /// [`AudioContext::base_latency`].
     Custom(f64),
 }
 
@@ -98,6 +366,71 @@ pub struct AudioContextOptions {
 
     /// Option to request a default, optimized or specific render quantum size. It is a hint that might not be honored.
     pub render_size_hint: AudioContextRenderSizeCategory,
+
+    /// Upper clamp of the number of output channels of the audio context, in the range
+    /// [1, [`MAX_CHANNELS`](crate::MAX_CHANNELS)]. Use `None` to use all channels of the audio
+    /// output device, up to `MAX_CHANNELS`.
+    ///
+    /// The channel count of the destination is the channel count of the device, clamped to
+    /// this value. The device channels beyond it are left silent. This option can only lower
+    /// the channel ceiling, e.g. to render stereo on a multichannel interface: `MAX_CHANNELS`
+    /// is a compile time constant, see its documentation.
+    pub max_channel_count: Option<usize>,
+}
+
+/// Options for the adaptive buffer size of an [`AudioContext`], see
+/// [`AudioContext::set_adaptive_latency`]
+#[derive(Clone, Debug)]
+pub struct AdaptiveLatencyOptions {
+    /// Upper bound of the latency in seconds, the buffer size is never increased beyond it
+    pub max_latency: f64,
+    /// Interval in seconds over which the missed deadlines are counted
+    pub interval: f64,
+    /// Number of missed deadlines within an interval which triggers an increase of the buffer
+    /// size
+    pub max_underruns: u64,
+}
+
+impl Default for AdaptiveLatencyOptions {
+    fn default() -> Self {
+        Self {
+            max_latency: 0.1,
+            interval: 2.,
+            max_underruns: 4,
+        }
+    }
+}
+
+/// Correlation of the context time with the system time, see
+/// [`AudioContext::get_output_timestamp`]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct AudioTimestamp {
+    /// Position in the context time, in seconds, of the sample frame played by the output device
+    pub context_time: f64,
+    /// The moment the sample frame is played by the output device, in milliseconds since the
+    /// [`time_origin`](AudioContext::time_origin) of the context
+    pub performance_time: f64,
+}
+
+/// Notification of a change of the audio output device of an [`AudioContext`], see
+/// [`AudioContext::set_onsinkchange`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SinkChangeEvent {
+    /// The `sinkId` of the context, `""` for the default device
+    pub sink_id: String,
+    /// The effective output device, `None` for the `"none"` sink or when the device is not found
+    pub device: Option<MediaDeviceInfo>,
+    /// Inherits from this base Event
+    pub event: Event,
+}
+
+/// Handle to the clock of an [`AudioContext`], to align the clock of other contexts with it, see
+/// [`AudioContext::set_clock`]
+#[derive(Clone, Debug)]
+pub struct SharedClock {
+    pub(crate) timing: Arc<RenderTiming>,
+    pub(crate) sample_rate: f32,
 }
 
 /// This interface represents an audio graph whose `AudioDestinationNode` is routed to a real-time
@@ -108,11 +441,19 @@ pub struct AudioContext {
     /// represents the underlying `BaseAudioContext`
     base: ConcreteBaseAudioContext,
     /// audio backend (play/pause functionality)
-    backend_manager: Mutex<Box<dyn AudioBackendManager>>,
+    backend_manager: Arc<Mutex<Box<dyn AudioBackendManager>>>,
     /// Provider for rendering performance metrics
     render_capacity: AudioRenderCapacity,
     /// Initializer for the render thread (when restart is required)
     render_thread_init: RenderThreadInit,
+    /// Latency the current backend was built for, the latency hint unless adapted
+    latency_hint: Arc<Mutex<AudioContextLatencyCategory>>,
+    /// Timing of the render callbacks
+    timing: Arc<RenderTiming>,
+    /// Stops the adaptive latency thread, if running
+    adaptive_latency_stop: Mutex<Option<Sender<()>>>,
+    /// Origin of the performance time of the output timestamps
+    time_origin: Instant,
 }
 
 impl BaseAudioContext for AudioContext {
@@ -153,26 +494,36 @@ impl AudioContext {
     /// The `AudioContext` constructor will panic when an invalid `sinkId` is provided in the
     /// `AudioContextOptions`. In a future version, a `try_new` constructor will be introduced that
     /// never panics.
+    ///
+    /// It will also panic if `max_channel_count` is outside the [1, 32] range, 32 being defined
+    /// by the MAX_CHANNELS constant.
     #[allow(clippy::needless_pass_by_value)]
     #[must_use]
     pub fn new(mut options: AudioContextOptions) -> Self {
+        if let Some(max_channel_count) = options.max_channel_count {
+            crate::assert_valid_number_of_channels(max_channel_count);
+        }
+
         if !is_valid_sink_id(&options.sink_id) {
             log::error!("NotFoundError: invalid sinkId {:?}", options.sink_id);
             options.sink_id = String::from("");
         }
 
         let (control_thread_init, render_thread_init) = io::thread_init();
+        let latency_hint = options.latency_hint;
         let backend = io::build_output(options, render_thread_init.clone());
 
         let ControlThreadInit {
             frames_played,
+            timing,
             ctrl_msg_send,
             load_value_recv,
             event_send,
             event_recv,
+            device_lost_recv,
         } = control_thread_init;
 
-        let graph = crate::render::graph::Graph::new();
+        let graph = Box::new(crate::render::graph::Graph::new());
         let message = crate::message::ControlMessage::Startup { graph };
         ctrl_msg_send.send(message).unwrap();
 
@@ -190,35 +541,149 @@ impl AudioContext {
         let base_clone = base.clone();
         let render_capacity = AudioRenderCapacity::new(base_clone, load_value_recv);
 
+        let backend_manager = Arc::new(Mutex::new(backend));
+        let latency_hint = Arc::new(Mutex::new(latency_hint));
+
+        // monitor the output device, do not keep the backend alive when the context is dropped
+        let base_clone = base.clone();
+        let backend_manager_clone = Arc::downgrade(&backend_manager);
+        let render_thread_init_clone = render_thread_init.clone();
+        let latency_hint_clone = Arc::clone(&latency_hint);
+        std::thread::spawn(move || {
+            monitor_output_device(
+                base_clone,
+                backend_manager_clone,
+                render_thread_init_clone,
+                latency_hint_clone,
+                device_lost_recv,
+            )
+        });
+
         Self {
             base,
-            backend_manager: Mutex::new(backend),
+            backend_manager,
             render_capacity,
             render_thread_init,
+            latency_hint,
+            timing,
+            adaptive_latency_stop: Mutex::new(None),
+            time_origin: Instant::now(),
         }
     }
 
     /// This represents the number of seconds of processing latency incurred by
     /// the `AudioContext` passing the audio from the `AudioDestinationNode`
     /// to the audio subsystem.
-    // We don't do any buffering between rendering the audio and sending
-    // it to the audio subsystem, so this value is zero. (see Gecko)
-    #[allow(clippy::unused_self)]
+    ///
+    /// This is the duration of the buffer of the audio callback (the device period), as reported
+    /// by the audio backend. It is updated when the audio output device or the buffer size
+    /// changes.
     #[must_use]
+    #[allow(clippy::missing_panics_doc)]
     pub fn base_latency(&self) -> f64 {
-        0.
+        self.backend_manager.lock().unwrap().base_latency()
     }
 
     /// The estimation in seconds of audio output latency, i.e., the interval
     /// between the time the UA requests the host system to play a buffer and
     /// the time at which the first sample in the buffer is actually processed
     /// by the audio output device.
+    ///
+    /// This is the latency reported by the audio backend for its stream, it is updated when the
+    /// audio output device changes.
     #[must_use]
     #[allow(clippy::missing_panics_doc)]
     pub fn output_latency(&self) -> f64 {
         self.backend_manager.lock().unwrap().output_latency()
     }
 
+    /// The time origin of the [`performance_time`](AudioTimestamp::performance_time) of the
+    /// output timestamps, i.e. the moment the context was created
+    ///
+    /// This is the counterpart of `performance.timeOrigin` in the browser.
+    #[must_use]
+    pub fn time_origin(&self) -> Instant {
+        self.time_origin
+    }
+
+    /// The context time of the sample frame being played by the output device and the moment it
+    /// is played, to synchronize the audio with e.g. video frames or input events
+    ///
+    /// The values are derived from the start of the last render callback and the output latency
+    /// reported by the audio backend. Both values are zero before the first render callback.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get_output_timestamp(&self) -> AudioTimestamp {
+        let last_callback = *self.timing.last_callback.lock().unwrap();
+        let (frame, instant) = match last_callback {
+            Some(last_callback) => last_callback,
+            None => return AudioTimestamp::default(),
+        };
+
+        let played_at = instant + Duration::from_secs_f64(self.output_latency());
+
+        AudioTimestamp {
+            context_time: frame as f64 / f64::from(self.sample_rate()),
+            performance_time: played_at
+                .saturating_duration_since(self.time_origin)
+                .as_secs_f64()
+                * 1000.,
+        }
+    }
+
+    /// The clock of this context, to be followed by other contexts
+    #[must_use]
+    pub fn clock(&self) -> SharedClock {
+        SharedClock {
+            timing: Arc::clone(&self.timing),
+            sample_rate: self.sample_rate(),
+        }
+    }
+
+    /// Align the clock of this context with the clock of another context, or run freely with
+    /// `None`
+    ///
+    /// The `current_time` of this context jumps to the time of the followed clock, and is then
+    /// continuously corrected by single sample frames to compensate the drift of the audio
+    /// devices. This allows to render in sync on several output devices (e.g. with different
+    /// `sink_id`s), events scheduled at the same time in each context are played at the same
+    /// moment, up to the difference of output latency of the devices. The contexts may run at
+    /// different sample rates.
+    ///
+    /// The followed context should not follow another clock itself.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when following the clock of this context itself
+    ///
+    /// # Usage
+    ///
+    /// ```no_run
+    /// use web_audio_api::context::{AudioContext, AudioContextOptions};
+    ///
+    /// let main = AudioContext::default();
+    /// let options = AudioContextOptions {
+    ///     sink_id: "my-second-device-id".into(),
+    ///     ..AudioContextOptions::default()
+    /// };
+    /// let second = AudioContext::new(options);
+    /// second.set_clock(Some(main.clock()));
+    /// ```
+    pub fn set_clock(&self, clock: Option<SharedClock>) {
+        if let Some(clock) = &clock {
+            assert!(
+                !Arc::ptr_eq(&clock.timing, &self.timing),
+                "InvalidStateError - a context can not follow its own clock"
+            );
+        }
+
+        *self
+            .timing
+            .clock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = clock;
+    }
+
     /// Identifier or the information of the current audio output device.
     ///
     /// The initial value is `""`, which means the default audio output device.
@@ -236,7 +701,7 @@ impl AudioContext {
     ///
     /// This function operates synchronously and might block the current thread. An async version
     /// is currently not implemented.
-    #[allow(clippy::needless_collect, clippy::missing_panics_doc)]
+    #[allow(clippy::missing_panics_doc)]
     pub fn set_sink_id_sync(&self, sink_id: String) -> Result<(), Box<dyn Error>> {
         if self.sink_id() == sink_id {
             return Ok(()); // sink is already active
@@ -246,88 +711,41 @@ impl AudioContext {
             Err(format!("NotFoundError: invalid sinkId {sink_id}"))?;
         };
 
-        let mut backend_manager_guard = self.backend_manager.lock().unwrap();
-        let original_state = self.state();
-        if original_state == AudioContextState::Closed {
+        let swapped = hotswap_backend(
+            &self.base,
+            &self.backend_manager,
+            &self.render_thread_init,
+            sink_id.clone(),
+            *self.latency_hint.lock().unwrap(),
+        );
+        if !swapped {
             return Ok(());
         }
 
-        // Temporarily set the state to Suspended, resume after the new backend is up
-        self.base().set_state(AudioContextState::Suspended);
-
-        // Acquire exclusive lock on ctrl msg sender
-        let ctrl_msg_send = self.base.lock_control_msg_sender();
-
-        // Flush out the ctrl msg receiver, cache
-        let mut pending_msgs: Vec<_> = self.render_thread_init.ctrl_msg_recv.try_iter().collect();
-
-        // Acquire the active audio graph from the current render thread, shutting it down
-        let graph = if matches!(pending_msgs.get(0), Some(ControlMessage::Startup { .. })) {
-            // Handle the edge case where the previous backend was suspended for its entire lifetime.
-            // In this case, the `Startup` control message was never processed.
-            let msg = pending_msgs.remove(0);
-            match msg {
-                ControlMessage::Startup { graph } => graph,
-                _ => unreachable!(),
-            }
-        } else {
-            // Acquire the audio graph from the current render thread, shutting it down
-            let (graph_send, graph_recv) = crossbeam_channel::bounded(1);
-            let message = ControlMessage::Shutdown { sender: graph_send };
-            ctrl_msg_send.send(message).unwrap();
-            if original_state == AudioContextState::Suspended {
-                // We must wake up the render thread to be able to handle the shutdown.
-                // No new audio will be produced because it will receive the shutdown command first.
-                backend_manager_guard.resume();
-            }
-            graph_recv.recv().unwrap()
-        };
-
-        // hotswap the backend
-        let options = AudioContextOptions {
-            sample_rate: Some(self.sample_rate()),
-            latency_hint: AudioContextLatencyCategory::default(), // todo reuse existing setting
-            sink_id,
-            render_size_hint: AudioContextRenderSizeCategory::default(), // todo reuse existing setting
-        };
-        *backend_manager_guard = io::build_output(options, self.render_thread_init.clone());
-
-        // if the previous backend state was suspend, suspend the new one before shipping the graph
-        if original_state == AudioContextState::Suspended {
-            backend_manager_guard.suspend();
-        }
-
-        // send the audio graph to the new render thread
-        let message = ControlMessage::Startup { graph };
-        ctrl_msg_send.send(message).unwrap();
-
-        if original_state == AudioContextState::Running {
-            self.base().set_state(AudioContextState::Running);
-        }
-
-        // flush the cached msgs
-        pending_msgs
-            .into_iter()
-            .for_each(|m| self.base().send_control_msg(m).unwrap());
-
-        // explicitly release the lock to prevent concurrent render threads
-        drop(backend_manager_guard);
-
         // trigger event when all the work is done
-        let _ = self.base.send_event(EventDispatch::sink_change());
+        let _ = self.base.send_event(EventDispatch::sink_change(sink_id));
 
         Ok(())
     }
 
     /// Register callback to run when the audio sink has changed
     ///
+    /// The event is emitted by [`set_sink_id_sync`](Self::set_sink_id_sync), and when a context
+    /// playing on the default device moves to a new default output device of the host.
+    ///
     /// Only a single event handler is active at any time. Calling this method multiple times will
     /// override the previous event handler.
-    pub fn set_onsinkchange<F: FnMut(Event) + Send + 'static>(&self, mut callback: F) {
-        let callback = move |_| {
-            callback(Event {
-                type_: "onsinkchange",
-            })
+    pub fn set_onsinkchange<F: FnMut(SinkChangeEvent) + Send + 'static>(&self, mut callback: F) {
+        let callback = move |v| match v {
+            EventPayload::SinkChange(sink_id) => callback(SinkChangeEvent {
+                // the device is looked up on the event thread, it is not sendable
+                device: output_device_info(&sink_id),
+                sink_id,
+                event: Event {
+                    type_: "onsinkchange",
+                },
+            }),
+            _ => unreachable!(),
         };
 
         self.base().set_event_handler(
@@ -341,11 +759,39 @@ impl AudioContext {
         self.base().clear_event_handler(EventType::SinkChange);
     }
 
+    /// Register callback to run when the state of the context has changed
+    ///
+    /// Notably, the state becomes [`AudioContextState::Interrupted`] when the audio output device
+    /// is lost, and `Running` again when the device is back.
+    ///
+    /// Only a single event handler is active at any time. Calling this method multiple times will
+    /// override the previous event handler.
+    pub fn set_onstatechange<F: FnMut(Event) + Send + 'static>(&self, mut callback: F) {
+        let callback = move |_| {
+            callback(Event {
+                type_: "onstatechange",
+            })
+        };
+
+        self.base().set_event_handler(
+            EventType::StateChange,
+            EventHandler::Multiple(Box::new(callback)),
+        );
+    }
+
+    /// Unset the callback to run when the state of the context has changed
+    pub fn clear_onstatechange(&self) {
+        self.base().clear_event_handler(EventType::StateChange);
+    }
+
     /// Suspends the progression of time in the audio context.
     ///
     /// This will temporarily halt audio hardware access and reducing CPU/battery usage in the
     /// process.
     ///
+    /// This function has no effect while the context is interrupted by the loss of the audio
+    /// output device.
+    ///
     /// This function operates synchronously and might block the current thread. An async version
     /// is currently not implemented.
     ///
@@ -357,6 +803,10 @@ impl AudioContext {
     /// * For a `BackendSpecificError`
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
     pub fn suspend_sync(&self) {
+        if self.state() == AudioContextState::Interrupted {
+            return;
+        }
+
         if self.backend_manager.lock().unwrap().suspend() {
             self.base().set_state(AudioContextState::Suspended);
         }
@@ -365,6 +815,9 @@ impl AudioContext {
     /// Resumes the progression of time in an audio context that has previously been
     /// suspended/paused.
     ///
+    /// An interrupted context resumes by itself when the audio output device is available again,
+    /// this function has no effect then.
+    ///
     /// This function operates synchronously and might block the current thread. An async version
     /// is currently not implemented.
     ///
@@ -376,6 +829,10 @@ impl AudioContext {
     /// * For a `BackendSpecificError`
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
     pub fn resume_sync(&self) {
+        if self.state() == AudioContextState::Interrupted {
+            return;
+        }
+
         if self.backend_manager.lock().unwrap().resume() {
             self.base().set_state(AudioContextState::Running);
         }
@@ -394,6 +851,7 @@ impl AudioContext {
     /// Will panic when this function is called multiple times
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
     pub fn close_sync(&self) {
+        self.set_adaptive_latency(None);
         self.backend_manager.lock().unwrap().close();
         self.render_capacity.stop();
         self.base().set_state(AudioContextState::Closed);
@@ -408,6 +866,7 @@ impl AudioContext {
     ) -> node::MediaStreamAudioSourceNode {
         let opts = node::MediaStreamAudioSourceOptions {
             media_stream: media,
+            track_id: None,
         };
         node::MediaStreamAudioSourceNode::new(self, opts)
     }
@@ -415,7 +874,7 @@ impl AudioContext {
     /// Creates a [`MediaStreamAudioDestinationNode`](node::MediaStreamAudioDestinationNode)
     #[must_use]
     pub fn create_media_stream_destination(&self) -> node::MediaStreamAudioDestinationNode {
-        let opts = ChannelConfigOptions::default();
+        let opts = node::MediaStreamAudioDestinationOptions::default();
         node::MediaStreamAudioDestinationNode::new(self, opts)
     }
 
@@ -443,9 +902,402 @@ impl AudioContext {
         node::MediaElementAudioSourceNode::new(self, opts)
     }
 
+    /// Creates a [`MediaStream`] from the first track of the `media` stream, with the echo of the
+    /// output of this context removed
+    ///
+    /// This is typically used for the microphone stream of a voice chat, when the remote voices
+    /// are played through speakers and would be picked up again by the microphone. An adaptive
+    /// filter models the echo path from the output to the input, with the rendered output of this
+    /// context as the reference signal. The filter converges within a few seconds of playback.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when:
+    /// - the stream has no tracks
+    /// - the filter duration is not strictly positive, or the delay is negative
+    /// - the step size is not in the ]0, 2[ range
+    #[must_use]
+    pub fn create_echo_cancelled_stream(
+        &self,
+        media: &MediaStream,
+        options: EchoCancellationOptions,
+    ) -> MediaStream {
+        assert!(
+            options.filter_duration > 0.,
+            "RangeError - Invalid filter duration: {:?}, should be strictly positive",
+            options.filter_duration
+        );
+        assert!(
+            options.delay >= 0.,
+            "RangeError - Invalid delay: {:?}, should be positive",
+            options.delay
+        );
+        assert!(
+            options.step_size > 0. && options.step_size < 2.,
+            "RangeError - Invalid step size: {:?}, should be in the ]0, 2[ range",
+            options.step_size
+        );
+        let track = media
+            .get_tracks()
+            .first()
+            .expect("InvalidStateError - the media stream has no tracks");
+
+        // buffer up to a second of rendered quanta
+        let capacity = (self.sample_rate() as usize).div_ceil(RENDER_QUANTUM_SIZE);
+        let (reference_send, reference_recv) = crossbeam_channel::bounded(capacity);
+        self.timing
+            .echo_references
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(reference_send);
+
+        let mut settings = track.get_settings();
+        settings.echo_cancellation = Some(true);
+
+        let stream =
+            EchoCancellingStream::new(track.iter(), reference_recv, self.sample_rate(), options);
+        let track = MediaStreamTrack::from_iter_with_settings(stream, settings);
+        MediaStream::from_tracks(vec![track])
+    }
+
+    /// Start recording the output of `node` while the playback continues
+    ///
+    /// The first output of the node is captured, e.g. the [`destination`](Self::destination)
+    /// to record what is heard, without changing the audio graph otherwise. The audio is
+    /// collected in memory or written to a WAV file on a dedicated thread, see
+    /// [`CaptureOptions`]. Nothing is captured while the context is suspended.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the WAV file cannot be created.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when:
+    /// - the node has no outputs or belongs to another context
+    /// - the number of channels is outside the [1, 32] range
+    pub fn start_capture(
+        &self,
+        node: &dyn AudioNode,
+        options: CaptureOptions,
+    ) -> std::io::Result<CaptureHandle> {
+        CaptureHandle::new(self, node, options)
+    }
+
     /// Returns an [`AudioRenderCapacity`] instance associated with an AudioContext.
     #[must_use]
     pub fn render_capacity(&self) -> &AudioRenderCapacity {
         &self.render_capacity
     }
+
+    /// Enable, update or disable (with `None`) the adaptive buffer size
+    ///
+    /// The missed deadlines of the render thread are monitored. When they exceed
+    /// `max_underruns` within an interval, the audio backend is rebuilt with the double buffer
+    /// size, up to `max_latency`, trading latency for stability. The buffer size of the latency
+    /// hint is the lower bound, the buffer size is not decreased again.
+    ///
+    /// Renegotiation is only effective for backends supporting the requested buffer size, see
+    /// [`output_latency`](Self::output_latency) for the resulting latency.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the interval or the maximum latency is not strictly positive
+    pub fn set_adaptive_latency(&self, options: Option<AdaptiveLatencyOptions>) {
+        // stop the current monitoring, if any
+        if let Some(stop) = self.adaptive_latency_stop.lock().unwrap().take() {
+            let _ = stop.send(());
+        }
+
+        let options = match options {
+            Some(options) => options,
+            None => return,
+        };
+
+        assert!(
+            options.interval > 0. && options.max_latency > 0.,
+            "RangeError - adaptive latency interval and maximum latency must be strictly positive"
+        );
+
+        let (stop_send, stop_recv) = crossbeam_channel::bounded(0);
+        *self.adaptive_latency_stop.lock().unwrap() = Some(stop_send);
+
+        let sample_rate = self.sample_rate();
+        let max_buffer_size = (options.max_latency * f64::from(sample_rate)) as usize;
+        let mut buffer_size =
+            io::buffer_size_for_latency_category(*self.latency_hint.lock().unwrap(), sample_rate);
+
+        let base = self.base.clone();
+        // do not keep the backend alive when the context is dropped
+        let backend_manager = Arc::downgrade(&self.backend_manager);
+        let render_thread_init = self.render_thread_init.clone();
+        let latency_hint = Arc::clone(&self.latency_hint);
+        let timing = Arc::clone(&self.timing);
+        let interval = Duration::from_secs_f64(options.interval);
+
+        std::thread::spawn(move || {
+            let mut previous = timing.underruns.load(Ordering::Relaxed);
+
+            loop {
+                // wait for the interval, or stop when requested or the context is dropped
+                match stop_recv.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => (),
+                    _ => return,
+                }
+
+                let current = timing.underruns.load(Ordering::Relaxed);
+                let count = current - previous;
+                previous = current;
+
+                if count <= options.max_underruns || buffer_size * 2 > max_buffer_size {
+                    continue;
+                }
+
+                buffer_size *= 2;
+                let latency = buffer_size as f64 / f64::from(sample_rate);
+                let category = AudioContextLatencyCategory::Custom(latency);
+                log::info!("Persistent underruns, increasing the latency to {latency} seconds");
+
+                let backend_manager = match backend_manager.upgrade() {
+                    Some(backend_manager) => backend_manager,
+                    None => return,
+                };
+                let sink_id = backend_manager.lock().unwrap().sink_id().to_owned();
+                *latency_hint.lock().unwrap() = category;
+                if !hotswap_backend(
+                    &base,
+                    &backend_manager,
+                    &render_thread_init,
+                    sink_id,
+                    category,
+                ) {
+                    return; // context is closed
+                }
+
+                // the new backend starts with a clean slate
+                previous = timing.underruns.load(Ordering::Relaxed);
+            }
+        });
+    }
+
+    /// Enable, update or disable (with `None`) the render thread watchdog
+    ///
+    /// The watchdog degrades the rendering on persistent overload, according to the given
+    /// options. Any current degradation is lifted when the watchdog is updated or disabled.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `max_missed_deadlines` or `recover_after` is zero
+    pub fn set_watchdog(&self, options: Option<WatchdogOptions>) {
+        if let Some(options) = &options {
+            assert!(
+                options.max_missed_deadlines > 0 && options.recover_after > 0,
+                "RangeError - watchdog thresholds must be greater than zero"
+            );
+        }
+
+        let message = ControlMessage::SetWatchdog { options };
+        // the render thread may already be shut down
+        let _ = self.base().send_control_msg(message);
+    }
+
+    /// Register callback to run when the watchdog intervenes, see [`set_watchdog`](Self::set_watchdog)
+    ///
+    /// Only a single event handler is active at any time. Calling this method multiple times will
+    /// override the previous event handler.
+    pub fn set_onoverload<F: FnMut(OverloadEvent) + Send + 'static>(&self, mut callback: F) {
+        let callback = move |v| match v {
+            EventPayload::Overload(v) => callback(v),
+            _ => unreachable!(),
+        };
+
+        self.base().set_event_handler(
+            EventType::Overload,
+            EventHandler::Multiple(Box::new(callback)),
+        );
+    }
+
+    /// Unset the callback to run when the watchdog intervenes
+    pub fn clear_onoverload(&self) {
+        self.base().clear_event_handler(EventType::Overload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::AtomicUsize;
+
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::AudioBuffer;
+
+    #[test]
+    fn test_recover_lost_device() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let state_changes = Arc::new(AtomicUsize::new(0));
+        let state_changes_clone = Arc::clone(&state_changes);
+        context.set_onstatechange(move |_| {
+            state_changes_clone.fetch_add(1, Ordering::Relaxed);
+        });
+
+        // ignore the startup of the context
+        std::thread::sleep(Duration::from_millis(50));
+        state_changes.store(0, Ordering::Relaxed);
+
+        // the audio graph survives the loss of the device
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+        src.start();
+
+        context
+            .render_thread_init
+            .device_lost_send
+            .send(())
+            .unwrap();
+        std::thread::sleep(DEVICE_POLL_INTERVAL / 2);
+        assert_eq!(context.state(), AudioContextState::Interrupted);
+
+        // the none sink is always available again
+        std::thread::sleep(DEVICE_POLL_INTERVAL * 2);
+        assert_eq!(context.state(), AudioContextState::Running);
+        assert_eq!(state_changes.load(Ordering::Relaxed), 2);
+
+        let time = context.current_time();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(context.current_time() > time);
+
+        context.close_sync();
+    }
+
+    #[test]
+    fn test_sink_change_event() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let (send, recv) = crossbeam_channel::bounded(1);
+        context.set_onsinkchange(move |e| {
+            send.send((e.sink_id, e.device.is_none())).unwrap();
+        });
+
+        let _ = context
+            .base()
+            .send_event(EventDispatch::sink_change("none".into()));
+        let event = recv.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event, ("none".to_owned(), true));
+
+        context.close_sync();
+    }
+
+    #[test]
+    fn test_echo_cancelled_stream() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let input = std::iter::repeat_with(|| Ok(AudioBuffer::from(vec![vec![0.5; 480]], 48000.)));
+        let media = MediaStream::from_tracks(vec![MediaStreamTrack::from_iter(input)]);
+        let stream =
+            context.create_echo_cancelled_stream(&media, EchoCancellationOptions::default());
+
+        let track = &stream.get_tracks()[0];
+        assert_eq!(track.get_settings().echo_cancellation, Some(true));
+        let buffer = track.iter().next().unwrap().unwrap();
+        assert_eq!(buffer.length(), 480);
+
+        // the rendered output is tapped until the stream is dropped
+        let references = || context.timing.echo_references.lock().unwrap().len();
+        assert_eq!(references(), 1);
+        drop(stream);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(references(), 0);
+
+        context.close_sync();
+    }
+
+    /// Wait until the capture has collected some audio
+    fn wait_for_capture(capture: &CaptureHandle) {
+        let start = Instant::now();
+        while capture.length() < 4 * RENDER_QUANTUM_SIZE {
+            assert!(start.elapsed() < Duration::from_secs(5), "nothing captured");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_capture_destination() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let src = context.create_constant_source();
+        src.offset().set_value(0.5);
+        src.connect(&context.destination());
+        src.start();
+
+        let capture = context
+            .start_capture(&context.destination(), CaptureOptions::default())
+            .unwrap();
+        wait_for_capture(&capture);
+
+        let buffer = capture.stop().unwrap().unwrap();
+        assert!(buffer.length() >= 4 * RENDER_QUANTUM_SIZE);
+        assert_eq!(buffer.number_of_channels(), 2);
+        assert_eq!(buffer.sample_rate(), context.sample_rate());
+        // the mono source is up-mixed
+        for channel_number in 0..2 {
+            let channel = buffer.get_channel_data(channel_number);
+            assert_eq!(channel[channel.len() - 1], 0.5);
+        }
+
+        context.close_sync();
+    }
+
+    #[test]
+    fn test_capture_wav() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let src = context.create_constant_source();
+        src.offset().set_value(0.25);
+        src.start();
+
+        // the node is captured without being connected to the destination
+        let path =
+            std::env::temp_dir().join(format!("web-audio-api-{}-capture.wav", std::process::id()));
+        let options = CaptureOptions {
+            number_of_channels: 1,
+            target: crate::CaptureTarget::Wav {
+                path: path.clone(),
+                sample_format: crate::WavSampleFormat::Float32,
+            },
+        };
+        let capture = context.start_capture(&src, options).unwrap();
+        wait_for_capture(&capture);
+        assert!(capture.stop().unwrap().is_none());
+
+        let buffer = AudioBuffer::from_wav(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(buffer.length() >= 4 * RENDER_QUANTUM_SIZE);
+        assert_eq!(buffer.length() % RENDER_QUANTUM_SIZE, 0);
+        assert_eq!(buffer.number_of_channels(), 1);
+        assert!(buffer.get_channel_data(0).iter().all(|&s| s == 0.25));
+
+        context.close_sync();
+    }
 }
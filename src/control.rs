@@ -3,7 +3,37 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use crate::AtomicF64;
+use crate::{AtomicF64, RENDER_QUANTUM_SIZE};
+
+/// Number of frames, starting at frame 0, whose time is strictly lower than
+/// the given time expressed in (fractional) frames
+///
+/// This is the number of frames to render before a source starts or stops at
+/// that time. Values within a tiny epsilon of an integer are snapped to this
+/// integer to absorb rounding errors of the time to frame conversions, e.g.
+/// `(129. / 48000.) * 48000.` does not exactly equal `129.`.
+#[inline]
+pub(crate) fn frames_before(time_in_frames: f64) -> f64 {
+    let rounded = time_in_frames.round();
+    if (time_in_frames - rounded).abs() < 1e-4 {
+        rounded
+    } else {
+        time_in_frames.ceil()
+    }
+}
+
+/// Index, within the render quantum starting at `block_time`, of the first
+/// frame whose time is greater than or equal to `time`
+///
+/// The result is clamped to `0..=RENDER_QUANTUM_SIZE`. It should be used to
+/// compare frame times against a start or stop time, as accumulating `dt` over
+/// the render quantum drifts enough to render a source one frame too late when
+/// it is scheduled exactly on a frame.
+#[inline]
+pub(crate) fn frame_index(time: f64, block_time: f64, sample_rate: f64) -> usize {
+    let frames = frames_before((time - block_time) * sample_rate);
+    frames.clamp(0., RENDER_QUANTUM_SIZE as f64) as usize
+}
 
 /// Helper struct to start and stop audio streams
 #[derive(Clone, Debug)]
@@ -42,6 +72,13 @@ impl Scheduler {
         // todo panic on invalid values, or when already called
         self.stop.store(stop);
     }
+
+    /// Playback was never scheduled and the control side handle has been dropped
+    ///
+    /// The source can then never start, so the renderer is allowed to be freed.
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.start) == 1 && self.get_start_at() == f64::MAX
+    }
 }
 
 impl Default for Scheduler {
@@ -78,6 +115,11 @@ impl Controller {
         &self.scheduler
     }
 
+    /// Playback was never scheduled and the control side handle has been dropped
+    pub fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.scheduler) == 1 && self.scheduler.get_start_at() == f64::MAX
+    }
+
     pub fn loop_(&self) -> bool {
         self.loop_.load(Ordering::SeqCst)
     }
@@ -129,6 +171,34 @@ impl Default for Controller {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_frames_before() {
+        let sample_rate = 48000.;
+
+        assert!(frames_before(0.) == 0.);
+        assert!(frames_before(100.3) == 101.);
+        assert!(frames_before(200.5) == 201.);
+        assert!(frames_before(300.7) == 301.);
+        assert!(frames_before((129. / sample_rate) * sample_rate) == 129.);
+    }
+
+    #[test]
+    fn test_frame_index() {
+        let sample_rate = 44100.;
+        let block_time = 128. / sample_rate;
+
+        assert_eq!(frame_index(0., block_time, sample_rate), 0);
+        assert_eq!(
+            frame_index(f64::MAX, block_time, sample_rate),
+            RENDER_QUANTUM_SIZE
+        );
+
+        for frame in 128..256 {
+            let time = frame as f64 / sample_rate;
+            assert_eq!(frame_index(time, block_time, sample_rate), frame - 128);
+        }
+    }
+
     #[test]
     fn test_controller() {
         let controller = Controller::new();
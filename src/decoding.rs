@@ -63,6 +63,21 @@ impl MediaDecoder {
     /// This method returns an Error in various cases (IO, mime sniffing, decoding).
     pub fn try_new<R: std::io::Read + Send + Sync + 'static>(
         input: R,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        // Create a hint to help the format registry guess what format reader is appropriate. In this
+        // function we'll leave it empty.
+        Self::try_new_with_hint(input, Hint::new())
+    }
+
+    /// Try to construct a new instance from a `Read` implementor, with a `hint` of the format
+    /// (e.g. the MIME type of an HTTP response)
+    ///
+    /// # Errors
+    ///
+    /// This method returns an Error in various cases (IO, mime sniffing, decoding).
+    pub fn try_new_with_hint<R: std::io::Read + Send + Sync + 'static>(
+        input: R,
+        hint: Hint,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         // Symfonia lib needs a Box<dyn MediaSource> - use our own MediaInput
         let input = Box::new(MediaInput::new(input));
@@ -70,10 +85,6 @@ impl MediaDecoder {
         // Create the media source stream using the boxed media source from above.
         let mss = symphonia::core::io::MediaSourceStream::new(input, Default::default());
 
-        // Create a hint to help the format registry guess what format reader is appropriate. In this
-        // function we'll leave it empty.
-        let hint = Hint::new();
-
         // Use the default options when reading and decoding.
         let format_opts: FormatOptions = Default::default();
         let metadata_opts: MetadataOptions = Default::default();
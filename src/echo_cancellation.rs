@@ -0,0 +1,298 @@
+//! Acoustic echo cancellation of media streams
+use std::collections::VecDeque;
+
+use crossbeam_channel::Receiver;
+
+use crate::{AudioBuffer, FallibleBuffer};
+
+/// Maximum duration of the buffered reference signal, in seconds, older frames are dropped
+const MAX_REFERENCE_DURATION: f64 = 1.;
+/// Regularization of the normalized step size, prevents the blow up on a silent reference
+const REGULARIZATION: f32 = 1e-6;
+
+/// Options for the echo cancellation of a media stream, see
+/// [`AudioContext::create_echo_cancelled_stream`](crate::context::AudioContext::create_echo_cancelled_stream)
+#[derive(Clone, Debug)]
+pub struct EchoCancellationOptions {
+    /// Duration of the echo path covered by the adaptive filter, in seconds. Longer filters
+    /// cancel the reverberation of larger rooms, at a higher CPU cost.
+    pub filter_duration: f64,
+    /// Delay of the echo path which is not covered by the filter, in seconds, e.g. the latency
+    /// of the output and input devices
+    pub delay: f64,
+    /// Step size of the adaptation, in the ]0, 2[ range. Larger values converge faster, but leave
+    /// more residual echo.
+    pub step_size: f32,
+}
+
+impl Default for EchoCancellationOptions {
+    fn default() -> Self {
+        Self {
+            filter_duration: 0.05,
+            delay: 0.,
+            step_size: 0.3,
+        }
+    }
+}
+
+/// Normalized least mean squares adaptive filter, modelling the echo path of a single channel
+pub(crate) struct EchoCanceller {
+    weights: Vec<f32>,
+    /// reference history, stored twice in a row so the last samples are always contiguous
+    history: Vec<f32>,
+    /// write position in the history
+    index: usize,
+    /// energy of the reference samples covered by the filter
+    energy: f32,
+    step_size: f32,
+}
+
+impl EchoCanceller {
+    pub fn new(length: usize, step_size: f32) -> Self {
+        let length = length.max(1);
+
+        Self {
+            weights: vec![0.; length],
+            history: vec![0.; 2 * length],
+            index: 0,
+            energy: 0.,
+            step_size,
+        }
+    }
+
+    /// Remove the echo of the `reference` sample from the `input` sample
+    pub fn process(&mut self, reference: f32, input: f32) -> f32 {
+        let length = self.weights.len();
+
+        // the oldest sample leaves the filter
+        let oldest = self.history[self.index];
+        self.energy = (self.energy + reference * reference - oldest * oldest).max(0.);
+
+        self.history[self.index] = reference;
+        self.history[self.index + length] = reference;
+        self.index = (self.index + 1) % length;
+
+        // the last `length` reference samples, oldest first
+        let history = &self.history[self.index..self.index + length];
+
+        let echo: f32 = self
+            .weights
+            .iter()
+            .rev()
+            .zip(history)
+            .map(|(w, x)| w * x)
+            .sum();
+        let error = input - echo;
+
+        let gain = self.step_size * error / (self.energy + REGULARIZATION);
+        self.weights
+            .iter_mut()
+            .rev()
+            .zip(history)
+            .for_each(|(w, x)| *w += gain * x);
+
+        error
+    }
+}
+
+/// Media stream with the echo of the rendered output of a context removed
+pub(crate) struct EchoCancellingStream<I> {
+    input: I,
+    /// mono rendered output of the context
+    reference: Receiver<Vec<f32>>,
+    reference_sample_rate: f32,
+    /// buffered reference frames
+    fifo: VecDeque<f32>,
+    /// fractional read position in the buffered reference frames
+    position: f64,
+    options: EchoCancellationOptions,
+    /// a filter per channel of the input, set up with the first buffer
+    cancellers: Vec<EchoCanceller>,
+    sample_rate: f32,
+}
+
+impl<I: Iterator<Item = FallibleBuffer>> EchoCancellingStream<I> {
+    pub fn new(
+        input: I,
+        reference: Receiver<Vec<f32>>,
+        reference_sample_rate: f32,
+        options: EchoCancellationOptions,
+    ) -> Self {
+        // the delay of the echo path is covered by delaying the reference
+        let delay = (options.delay * f64::from(reference_sample_rate)) as usize;
+
+        Self {
+            input,
+            reference,
+            reference_sample_rate,
+            fifo: VecDeque::from(vec![0.; delay]),
+            position: 0.,
+            options,
+            cancellers: Vec::new(),
+            sample_rate: 0.,
+        }
+    }
+
+    /// Set up the filters for the channel layout and sample rate of the input
+    fn reset(&mut self, number_of_channels: usize, sample_rate: f32) {
+        let length = (self.options.filter_duration * f64::from(sample_rate)) as usize;
+        self.cancellers = (0..number_of_channels)
+            .map(|_| EchoCanceller::new(length, self.options.step_size))
+            .collect();
+        self.sample_rate = sample_rate;
+    }
+
+    /// Next reference sample at the sample rate of the input, interpolated linearly
+    fn next_reference(&mut self, ratio: f64) -> f32 {
+        let index = self.position as usize;
+        if index + 1 >= self.fifo.len() {
+            // the context is not rendering, e.g. it is suspended
+            return 0.;
+        }
+
+        let k = (self.position - index as f64) as f32;
+        let value = self.fifo[index] * (1. - k) + self.fifo[index + 1] * k;
+
+        self.position += ratio;
+        let consumed = self.position as usize;
+        self.fifo.drain(..consumed);
+        self.position -= consumed as f64;
+
+        value
+    }
+}
+
+impl<I: Iterator<Item = FallibleBuffer>> Iterator for EchoCancellingStream<I> {
+    type Item = FallibleBuffer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buffer = match self.input.next()? {
+            Ok(buffer) => buffer,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let fifo = &mut self.fifo;
+        self.reference
+            .try_iter()
+            .for_each(|chunk| fifo.extend(chunk));
+
+        // keep the latency of the reference bounded when the input stalls
+        let max_len = (MAX_REFERENCE_DURATION * f64::from(self.reference_sample_rate)) as usize;
+        if self.fifo.len() > max_len {
+            let excess = self.fifo.len() - max_len;
+            self.fifo.drain(..excess);
+            self.position = 0.;
+        }
+
+        if buffer.length() == 0 {
+            return Some(Ok(buffer));
+        }
+
+        let sample_rate = buffer.sample_rate();
+        if self.cancellers.len() != buffer.number_of_channels() || self.sample_rate != sample_rate {
+            self.reset(buffer.number_of_channels(), sample_rate);
+        }
+
+        let ratio = f64::from(self.reference_sample_rate) / f64::from(sample_rate);
+        let mut channels: Vec<Vec<f32>> = (0..buffer.number_of_channels())
+            .map(|i| buffer.get_channel_data(i).to_vec())
+            .collect();
+
+        for i in 0..buffer.length() {
+            let reference = self.next_reference(ratio);
+            self.cancellers
+                .iter_mut()
+                .zip(channels.iter_mut())
+                .for_each(|(canceller, channel)| {
+                    channel[i] = canceller.process(reference, channel[i]);
+                });
+        }
+
+        Some(Ok(AudioBuffer::from(channels, sample_rate)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic white noise in [-1, 1]
+    fn noise(len: usize) -> Vec<f32> {
+        let mut state = 12345_u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as f32 / 32768. - 1.
+            })
+            .collect()
+    }
+
+    /// Echo path: a delayed and attenuated copy, with a short reflection
+    fn echo(reference: &[f32]) -> Vec<f32> {
+        (0..reference.len())
+            .map(|i| {
+                let direct = if i >= 10 { 0.5 * reference[i - 10] } else { 0. };
+                let reflection = if i >= 25 {
+                    -0.2 * reference[i - 25]
+                } else {
+                    0.
+                };
+                direct + reflection
+            })
+            .collect()
+    }
+
+    fn energy(samples: &[f32]) -> f32 {
+        samples.iter().map(|v| v * v).sum()
+    }
+
+    #[test]
+    fn test_echo_canceller() {
+        let reference = noise(48_000);
+        let input = echo(&reference);
+
+        let mut canceller = EchoCanceller::new(64, 0.5);
+        let output: Vec<f32> = reference
+            .iter()
+            .zip(&input)
+            .map(|(&x, &d)| canceller.process(x, d))
+            .collect();
+
+        // more than 40 dB of echo return loss enhancement once converged
+        let tail = 40_000..;
+        let erle = 10. * (energy(&input[tail.clone()]) / energy(&output[tail])).log10();
+        assert!(erle > 40., "erle {}", erle);
+    }
+
+    #[test]
+    fn test_echo_cancelling_stream() {
+        let reference = noise(48_000);
+        let input = echo(&reference);
+
+        // the reference is rendered in quanta, the input is captured in larger chunks
+        let (send, recv) = crossbeam_channel::unbounded();
+        reference
+            .chunks(128)
+            .for_each(|chunk| send.send(chunk.to_vec()).unwrap());
+
+        let chunks: Vec<FallibleBuffer> = input
+            .chunks(480)
+            .map(|chunk| Ok(AudioBuffer::from(vec![chunk.to_vec()], 48_000.)))
+            .collect();
+        let options = EchoCancellationOptions {
+            filter_duration: 64. / 48_000.,
+            step_size: 0.5,
+            ..EchoCancellationOptions::default()
+        };
+        let stream = EchoCancellingStream::new(chunks.into_iter(), recv, 48_000., options);
+
+        let output: Vec<f32> = stream
+            .flat_map(|buffer| buffer.unwrap().get_channel_data(0).to_vec())
+            .collect();
+        assert_eq!(output.len(), input.len());
+
+        let tail = 40_000..;
+        let erle = 10. * (energy(&input[tail.clone()]) / energy(&output[tail])).log10();
+        assert!(erle > 40., "erle {}", erle);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::context::AudioNodeId;
-use crate::AudioRenderCapacityEvent;
+use crate::media_streams::MediaStreamTrackEvent;
+use crate::{AudioRenderCapacityEvent, OverloadEvent};
 
 use std::any::Any;
 use std::collections::HashMap;
@@ -19,8 +20,12 @@ pub struct Event {
 pub(crate) enum EventType {
     Ended(AudioNodeId),
     SinkChange,
+    StateChange,
     RenderCapacity,
+    Overload,
     ProcessorError(AudioNodeId),
+    Message(AudioNodeId),
+    MediaStreamTrack,
 }
 
 /// The Error Event interface
@@ -37,8 +42,12 @@ pub struct ErrorEvent {
 
 pub(crate) enum EventPayload {
     None,
+    SinkChange(String),
     RenderCapacity(AudioRenderCapacityEvent),
+    Overload(OverloadEvent),
     ProcessorError(ErrorEvent),
+    Message(Box<dyn Any + Send>),
+    MediaStreamTrack(MediaStreamTrackEvent),
 }
 
 pub(crate) struct EventDispatch {
@@ -54,9 +63,16 @@ impl EventDispatch {
         }
     }
 
-    pub fn sink_change() -> Self {
+    pub fn sink_change(sink_id: String) -> Self {
         EventDispatch {
             type_: EventType::SinkChange,
+            payload: EventPayload::SinkChange(sink_id),
+        }
+    }
+
+    pub fn state_change() -> Self {
+        EventDispatch {
+            type_: EventType::StateChange,
             payload: EventPayload::None,
         }
     }
@@ -68,12 +84,33 @@ impl EventDispatch {
         }
     }
 
+    pub fn overload(value: OverloadEvent) -> Self {
+        EventDispatch {
+            type_: EventType::Overload,
+            payload: EventPayload::Overload(value),
+        }
+    }
+
     pub fn processor_error(id: AudioNodeId, value: ErrorEvent) -> Self {
         EventDispatch {
             type_: EventType::ProcessorError(id),
             payload: EventPayload::ProcessorError(value),
         }
     }
+
+    pub fn message(id: AudioNodeId, msg: Box<dyn Any + Send>) -> Self {
+        EventDispatch {
+            type_: EventType::Message(id),
+            payload: EventPayload::Message(msg),
+        }
+    }
+
+    pub fn media_stream_track(value: MediaStreamTrackEvent) -> Self {
+        EventDispatch {
+            type_: EventType::MediaStreamTrack,
+            payload: EventPayload::MediaStreamTrack(value),
+        }
+    }
 }
 
 pub(crate) enum EventHandler {
@@ -97,6 +134,19 @@ impl EventLoop {
         std::thread::spawn(move || loop {
             // this thread is dedicated to event handling so we can block
             for event in event_channel.iter() {
+                // the callbacks of the media stream tracks are kept by the tracks, which can be
+                // played by several contexts
+                let event = match event.payload {
+                    EventPayload::MediaStreamTrack(e) => {
+                        e.dispatch();
+                        continue;
+                    }
+                    payload => EventDispatch {
+                        type_: event.type_,
+                        payload,
+                    },
+                };
+
                 let mut handlers = self_clone.event_handlers.lock().unwrap();
                 if let Some(callback) = handlers.remove(&event.type_) {
                     match callback {
@@ -0,0 +1,421 @@
+//! Pluggable FFT implementation
+//!
+//! The FFTs of the [`AnalyserNode`](crate::node::AnalyserNode), the
+//! [`ConvolverNode`](crate::node::ConvolverNode) and the
+//! [`SpectralAdapter`](crate::render::SpectralAdapter) are planned by the global
+//! [`FftBackend`]. The built-in backends are selected by feature:
+//!
+//! - `realfft` (default): `RealFftBackend`, real-valued transforms of the `realfft` crate
+//! - `rustfft`: `RustFftBackend`, complex transforms of the `rustfft` crate
+//!
+//! A platform-accelerated FFT, e.g. vDSP or IPP, is plugged in by implementing the traits of
+//! this module and installing it with [`set_backend`].
+use std::sync::{Arc, RwLock};
+
+use lazy_static::lazy_static;
+
+pub use num_complex::Complex;
+
+/// Forward FFT of real-valued input of a fixed length
+///
+/// The output is not normalized. It contains the `len / 2 + 1` bins from DC up to Nyquist,
+/// the other bins follow from the conjugate symmetry of the spectrum of real-valued input.
+pub trait RealToComplexFft: Send + Sync {
+    /// Length of the transform
+    fn length(&self) -> usize;
+
+    /// Length of the scratch buffer needed by [`process`](Self::process)
+    fn scratch_len(&self) -> usize;
+
+    /// Transform the `input` into the `output` bins
+    ///
+    /// The contents of the `input` and `scratch` buffers are undefined afterwards. This method
+    /// must not allocate, as it is called on the render thread.
+    ///
+    /// # Panics
+    ///
+    /// Implementations may panic if the lengths of the buffers do not match the transform.
+    fn process(&self, input: &mut [f32], output: &mut [Complex<f32>], scratch: &mut [Complex<f32>]);
+}
+
+/// Inverse FFT of the bins of real-valued output of a fixed length
+///
+/// The output is not normalized, a round trip scales the signal by the length of the
+/// transform.
+pub trait ComplexToRealFft: Send + Sync {
+    /// Length of the transform
+    fn length(&self) -> usize;
+
+    /// Length of the scratch buffer needed by [`process`](Self::process)
+    fn scratch_len(&self) -> usize;
+
+    /// Transform the `len / 2 + 1` `input` bins into the `output` signal
+    ///
+    /// The imaginary part of the DC bin, and of the Nyquist bin for even lengths, is ignored.
+    /// The contents of the `input` and `scratch` buffers are undefined afterwards. This method
+    /// must not allocate, as it is called on the render thread.
+    ///
+    /// # Panics
+    ///
+    /// Implementations may panic if the lengths of the buffers do not match the transform.
+    fn process(&self, input: &mut [Complex<f32>], output: &mut [f32], scratch: &mut [Complex<f32>]);
+}
+
+/// Planner of the FFTs, see the [module documentation](self)
+///
+/// Planning happens on the control thread, when nodes are created or reconfigured, so it may
+/// allocate and cache the plans.
+pub trait FftBackend: Send + Sync {
+    /// Plan a forward transform of the given length
+    fn plan_forward(&self, len: usize) -> Arc<dyn RealToComplexFft>;
+
+    /// Plan an inverse transform of the given length
+    fn plan_inverse(&self, len: usize) -> Arc<dyn ComplexToRealFft>;
+}
+
+#[allow(unreachable_code)]
+fn default_backend() -> Option<Arc<dyn FftBackend>> {
+    #[cfg(feature = "realfft")]
+    return Some(Arc::new(RealFftBackend::default()));
+    #[cfg(feature = "rustfft")]
+    return Some(Arc::new(RustFftBackend::default()));
+    None
+}
+
+lazy_static! {
+    static ref BACKEND: RwLock<Option<Arc<dyn FftBackend>>> = RwLock::new(default_backend());
+}
+
+/// Install the backend planning the FFTs of the nodes created afterwards
+///
+/// Existing nodes keep their plans.
+#[allow(clippy::missing_panics_doc)]
+pub fn set_backend(backend: Arc<dyn FftBackend>) {
+    *BACKEND.write().unwrap() = Some(backend);
+}
+
+/// The current backend, `None` when no backend is enabled by feature and none was installed
+#[allow(clippy::missing_panics_doc)]
+#[must_use]
+pub fn backend() -> Option<Arc<dyn FftBackend>> {
+    BACKEND.read().unwrap().clone()
+}
+
+fn expect_backend() -> Arc<dyn FftBackend> {
+    backend().expect(
+        "InvalidStateError - No FFT backend, enable the `realfft` or `rustfft` feature or install \
+        one with `fft::set_backend`",
+    )
+}
+
+/// Plan a forward transform with the current backend
+pub(crate) fn plan_forward(len: usize) -> Arc<dyn RealToComplexFft> {
+    expect_backend().plan_forward(len)
+}
+
+/// Plan an inverse transform with the current backend
+pub(crate) fn plan_inverse(len: usize) -> Arc<dyn ComplexToRealFft> {
+    expect_backend().plan_inverse(len)
+}
+
+#[cfg(feature = "realfft")]
+pub use self::realfft_backend::RealFftBackend;
+
+#[cfg(feature = "realfft")]
+mod realfft_backend {
+    use std::sync::{Arc, Mutex};
+
+    use realfft::RealFftPlanner;
+
+    use super::{Complex, ComplexToRealFft, FftBackend, RealToComplexFft};
+
+    /// Backend of the real-valued transforms of the `realfft` crate
+    #[derive(Default)]
+    pub struct RealFftBackend {
+        // RealFftPlanner is not `Sync` on all platforms
+        planner: Mutex<RealFftPlanner<f32>>,
+    }
+
+    impl std::fmt::Debug for RealFftBackend {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RealFftBackend").finish_non_exhaustive()
+        }
+    }
+
+    struct Forward(Arc<dyn realfft::RealToComplex<f32>>);
+
+    impl RealToComplexFft for Forward {
+        fn length(&self) -> usize {
+            self.0.len()
+        }
+
+        fn scratch_len(&self) -> usize {
+            self.0.get_scratch_len()
+        }
+
+        fn process(
+            &self,
+            input: &mut [f32],
+            output: &mut [Complex<f32>],
+            scratch: &mut [Complex<f32>],
+        ) {
+            self.0
+                .process_with_scratch(input, output, scratch)
+                .expect("buffer lengths should match the transform");
+        }
+    }
+
+    struct Inverse(Arc<dyn realfft::ComplexToReal<f32>>);
+
+    impl ComplexToRealFft for Inverse {
+        fn length(&self) -> usize {
+            self.0.len()
+        }
+
+        fn scratch_len(&self) -> usize {
+            self.0.get_scratch_len()
+        }
+
+        fn process(
+            &self,
+            input: &mut [Complex<f32>],
+            output: &mut [f32],
+            scratch: &mut [Complex<f32>],
+        ) {
+            // realfft rejects imaginary parts of these bins
+            input[0].im = 0.;
+            if self.0.len() & 1 == 0 {
+                input[self.0.len() / 2].im = 0.;
+            }
+            self.0
+                .process_with_scratch(input, output, scratch)
+                .expect("buffer lengths should match the transform");
+        }
+    }
+
+    impl FftBackend for RealFftBackend {
+        fn plan_forward(&self, len: usize) -> Arc<dyn RealToComplexFft> {
+            Arc::new(Forward(self.planner.lock().unwrap().plan_fft_forward(len)))
+        }
+
+        fn plan_inverse(&self, len: usize) -> Arc<dyn ComplexToRealFft> {
+            Arc::new(Inverse(self.planner.lock().unwrap().plan_fft_inverse(len)))
+        }
+    }
+}
+
+#[cfg(feature = "rustfft")]
+pub use self::rustfft_backend::RustFftBackend;
+
+#[cfg(feature = "rustfft")]
+mod rustfft_backend {
+    use std::sync::{Arc, Mutex};
+
+    use rustfft::{Fft, FftPlanner};
+
+    use super::{Complex, ComplexToRealFft, FftBackend, RealToComplexFft};
+
+    /// Backend of the complex transforms of the `rustfft` crate
+    ///
+    /// The real-valued signals are transformed as complex signals of the same length, which
+    /// is about twice as costly as the dedicated real-valued transforms.
+    pub struct RustFftBackend {
+        planner: Mutex<FftPlanner<f32>>,
+    }
+
+    impl Default for RustFftBackend {
+        fn default() -> Self {
+            Self {
+                planner: Mutex::new(FftPlanner::new()),
+            }
+        }
+    }
+
+    impl std::fmt::Debug for RustFftBackend {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RustFftBackend").finish_non_exhaustive()
+        }
+    }
+
+    /// The scratch holds the complex signal, followed by the scratch of the transform
+    fn split_scratch<'a>(
+        fft: &dyn Fft<f32>,
+        scratch: &'a mut [Complex<f32>],
+    ) -> (&'a mut [Complex<f32>], &'a mut [Complex<f32>]) {
+        let (buffer, scratch) = scratch.split_at_mut(fft.len());
+        (buffer, &mut scratch[..fft.get_inplace_scratch_len()])
+    }
+
+    struct Forward(Arc<dyn Fft<f32>>);
+
+    impl RealToComplexFft for Forward {
+        fn length(&self) -> usize {
+            self.0.len()
+        }
+
+        fn scratch_len(&self) -> usize {
+            self.0.len() + self.0.get_inplace_scratch_len()
+        }
+
+        fn process(
+            &self,
+            input: &mut [f32],
+            output: &mut [Complex<f32>],
+            scratch: &mut [Complex<f32>],
+        ) {
+            let (buffer, scratch) = split_scratch(&*self.0, scratch);
+            buffer
+                .iter_mut()
+                .zip(input.iter())
+                .for_each(|(b, &i)| *b = Complex::new(i, 0.));
+            self.0.process_with_scratch(buffer, scratch);
+            output.copy_from_slice(&buffer[..buffer.len() / 2 + 1]);
+        }
+    }
+
+    struct Inverse(Arc<dyn Fft<f32>>);
+
+    impl ComplexToRealFft for Inverse {
+        fn length(&self) -> usize {
+            self.0.len()
+        }
+
+        fn scratch_len(&self) -> usize {
+            self.0.len() + self.0.get_inplace_scratch_len()
+        }
+
+        fn process(
+            &self,
+            input: &mut [Complex<f32>],
+            output: &mut [f32],
+            scratch: &mut [Complex<f32>],
+        ) {
+            let len = self.0.len();
+            let (buffer, scratch) = split_scratch(&*self.0, scratch);
+
+            // restore the conjugate symmetric spectrum
+            buffer[..len / 2 + 1].copy_from_slice(input);
+            buffer[0].im = 0.;
+            if len & 1 == 0 {
+                buffer[len / 2].im = 0.;
+            }
+            (len / 2 + 1..len).for_each(|k| buffer[k] = input[len - k].conj());
+
+            self.0.process_with_scratch(buffer, scratch);
+            output
+                .iter_mut()
+                .zip(buffer.iter())
+                .for_each(|(o, b)| *o = b.re);
+        }
+    }
+
+    impl FftBackend for RustFftBackend {
+        fn plan_forward(&self, len: usize) -> Arc<dyn RealToComplexFft> {
+            Arc::new(Forward(self.planner.lock().unwrap().plan_fft_forward(len)))
+        }
+
+        fn plan_inverse(&self, len: usize) -> Arc<dyn ComplexToRealFft> {
+            Arc::new(Inverse(self.planner.lock().unwrap().plan_fft_inverse(len)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use float_eq::assert_float_eq;
+
+    use super::*;
+
+    #[cfg(any(feature = "realfft", feature = "rustfft"))]
+    fn signal(len: usize) -> Vec<f32> {
+        (0..len).map(|i| (i as f32 * 0.3).sin() + 0.25).collect()
+    }
+
+    #[cfg(any(feature = "realfft", feature = "rustfft"))]
+    fn forward(backend: &dyn FftBackend, input: &[f32]) -> Vec<Complex<f32>> {
+        let fft = backend.plan_forward(input.len());
+        let mut input = input.to_vec();
+        let mut output = vec![Complex::default(); input.len() / 2 + 1];
+        let mut scratch = vec![Complex::default(); fft.scratch_len()];
+        fft.process(&mut input, &mut output, &mut scratch);
+        output
+    }
+
+    #[cfg(any(feature = "realfft", feature = "rustfft"))]
+    fn round_trip(backend: &dyn FftBackend) {
+        for len in [32, 100, 1024, 1001] {
+            let input = signal(len);
+            let mut bins = forward(backend, &input);
+
+            // DC bin of the naive transform
+            let dc: f32 = input.iter().sum();
+            assert_float_eq!(bins[0].re, dc, abs <= 1e-3);
+
+            let ifft = backend.plan_inverse(len);
+            assert_eq!(ifft.length(), len);
+            let mut output = vec![0.; len];
+            let mut scratch = vec![Complex::default(); ifft.scratch_len()];
+            ifft.process(&mut bins, &mut output, &mut scratch);
+            output.iter_mut().for_each(|o| *o /= len as f32);
+            assert_float_eq!(output, input, abs_all <= 1e-4);
+        }
+    }
+
+    #[cfg(feature = "realfft")]
+    #[test]
+    fn test_realfft_round_trip() {
+        round_trip(&RealFftBackend::default());
+    }
+
+    #[cfg(feature = "rustfft")]
+    #[test]
+    fn test_rustfft_round_trip() {
+        round_trip(&RustFftBackend::default());
+    }
+
+    #[cfg(all(feature = "realfft", feature = "rustfft"))]
+    #[test]
+    fn test_backends_match() {
+        let input = signal(512);
+        let real = forward(&RealFftBackend::default(), &input);
+        let complex = forward(&RustFftBackend::default(), &input);
+        for (r, c) in real.iter().zip(complex.iter()) {
+            assert_float_eq!(r.re, c.re, abs <= 1e-3);
+            assert_float_eq!(r.im, c.im, abs <= 1e-3);
+        }
+    }
+
+    /// Backend delegating to the current one, counting the plans
+    struct Counting(Arc<dyn FftBackend>, AtomicUsize);
+
+    impl FftBackend for Counting {
+        fn plan_forward(&self, len: usize) -> Arc<dyn RealToComplexFft> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.plan_forward(len)
+        }
+
+        fn plan_inverse(&self, len: usize) -> Arc<dyn ComplexToRealFft> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.plan_inverse(len)
+        }
+    }
+
+    #[test]
+    fn test_set_backend() {
+        let previous = match backend() {
+            Some(backend) => backend,
+            None => return,
+        };
+
+        let counting = Arc::new(Counting(Arc::clone(&previous), AtomicUsize::new(0)));
+        set_backend(counting.clone());
+        let _ = plan_forward(64);
+        let _ = plan_inverse(64);
+        set_backend(previous);
+
+        // other tests may plan in the meantime
+        assert!(counting.1.load(Ordering::SeqCst) >= 2);
+    }
+}
@@ -4,8 +4,8 @@ use std::sync::Mutex;
 
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
-    BuildStreamError, Device, OutputCallbackInfo, SampleFormat, Stream, StreamConfig,
-    SupportedBufferSize,
+    BuildStreamError, Device, OutputCallbackInfo, SampleFormat, SampleRate, Stream, StreamConfig,
+    StreamError, SupportedBufferSize, SupportedStreamConfig,
 };
 
 use super::{AudioBackendManager, RenderThreadInit};
@@ -13,11 +13,11 @@ use super::{AudioBackendManager, RenderThreadInit};
 use crate::buffer::AudioBuffer;
 use crate::context::AudioContextOptions;
 use crate::io::microphone::MicrophoneRender;
-use crate::media_devices::{MediaDeviceInfo, MediaDeviceInfoKind};
+use crate::media_devices::{MediaDeviceInfo, MediaDeviceInfoKind, MediaTrackConstraints};
 use crate::render::RenderThread;
-use crate::AtomicF64;
+use crate::{AtomicF64, MAX_CHANNELS};
 
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
 
 mod private {
     use super::*;
@@ -103,6 +103,8 @@ fn get_host() -> cpal::Host {
 #[derive(Clone)]
 pub(crate) struct CpalBackend {
     stream: ThreadSafeClosableStream,
+    /// Duration of the buffer of the last callback, updated by the render thread
+    base_latency: Arc<AtomicF64>,
     output_latency: Arc<AtomicF64>,
     sample_rate: f32,
     number_of_channels: usize,
@@ -120,9 +122,11 @@ impl AudioBackendManager for CpalBackend {
 
         let RenderThreadInit {
             frames_played,
+            timing,
             ctrl_msg_recv,
             load_value_send,
             event_send,
+            device_lost_send,
         } = render_thread_init;
 
         let device = if options.sink_id.is_empty() {
@@ -166,8 +170,15 @@ impl AudioBackendManager for CpalBackend {
 
         prefered.buffer_size = cpal::BufferSize::Fixed(clamped_buffer_size);
 
+        // the device may offer more channels than the context renders, these are left silent
+        let max_channel_count = options.max_channel_count.unwrap_or(MAX_CHANNELS);
+
+        // report the requested buffer size until the first callback reports the actual one
+        let base_latency = Arc::new(AtomicF64::new(
+            f64::from(clamped_buffer_size) / f64::from(prefered.sample_rate.0),
+        ));
         let output_latency = Arc::new(AtomicF64::new(0.));
-        let mut number_of_channels = usize::from(prefered.channels);
+        let mut number_of_channels = usize::from(prefered.channels).min(max_channel_count);
         let mut sample_rate = prefered.sample_rate.0 as f32;
 
         let renderer = RenderThread::new(
@@ -177,6 +188,7 @@ impl AudioBackendManager for CpalBackend {
             frames_played.clone(),
             Some(load_value_send.clone()),
             Some(event_send.clone()),
+            timing.clone(),
         );
 
         log::debug!(
@@ -188,7 +200,9 @@ impl AudioBackendManager for CpalBackend {
             supported.sample_format(),
             &prefered,
             renderer,
+            base_latency.clone(),
             output_latency.clone(),
+            device_lost_send.clone(),
         );
 
         let stream = match spawned {
@@ -200,29 +214,37 @@ impl AudioBackendManager for CpalBackend {
                 log::warn!("Output stream build failed with prefered config: {}", e);
 
                 let supported_config: StreamConfig = supported.clone().into();
-                number_of_channels = usize::from(supported_config.channels);
-                sample_rate = supported_config.sample_rate.0 as f32;
+                number_of_channels = usize::from(supported_config.channels).min(max_channel_count);
+                // keep the requested sample rate of the context, the rendered audio is converted
+                // to the sample rate of the device
+                if options.sample_rate.is_none() {
+                    sample_rate = supported_config.sample_rate.0 as f32;
+                }
 
                 log::debug!(
                     "Attempt output stream with fallback config: {:?}",
                     &supported_config
                 );
 
-                let renderer = RenderThread::new(
+                let mut renderer = RenderThread::new(
                     sample_rate,
                     supported_config.channels as usize,
                     ctrl_msg_recv,
                     frames_played,
                     Some(load_value_send),
                     Some(event_send),
+                    timing,
                 );
+                renderer.set_output_sample_rate(supported_config.sample_rate.0 as f32);
 
                 let spawned = spawn_output_stream(
                     &device,
                     supported.sample_format(),
                     &supported_config,
                     renderer,
+                    base_latency.clone(),
                     output_latency.clone(),
+                    device_lost_send,
                 );
                 spawned.expect("OutputStream build failed with default config")
             }
@@ -232,6 +254,7 @@ impl AudioBackendManager for CpalBackend {
 
         CpalBackend {
             stream: ThreadSafeClosableStream::new(stream),
+            base_latency,
             output_latency,
             sample_rate,
             number_of_channels,
@@ -239,7 +262,7 @@ impl AudioBackendManager for CpalBackend {
         }
     }
 
-    fn build_input(options: AudioContextOptions) -> (Self, Receiver<AudioBuffer>)
+    fn build_input(constraints: MediaTrackConstraints) -> (Self, Receiver<AudioBuffer>)
     where
         Self: Sized,
     {
@@ -247,13 +270,14 @@ impl AudioBackendManager for CpalBackend {
 
         log::info!("Audio Input Host: cpal {:?}", host.id());
 
-        let device = if options.sink_id.is_empty() {
+        let sink_id = constraints.device_id.clone().unwrap_or_default();
+        let device = if sink_id.is_empty() {
             host.default_input_device()
                 .expect("no input device available")
         } else {
             Self::enumerate_devices_sync()
                 .into_iter()
-                .find(|e| e.device_id() == options.sink_id)
+                .find(|e| e.device_id() == sink_id)
                 .map(|e| *e.device().downcast::<cpal::Device>().unwrap())
                 .unwrap_or_else(|| {
                     host.default_input_device()
@@ -263,22 +287,23 @@ impl AudioBackendManager for CpalBackend {
 
         log::info!("Input device: {:?}", device.name());
 
-        let supported = device
+        if let Some(sample_rate) = constraints.sample_rate {
+            crate::assert_valid_sample_rate(sample_rate);
+        }
+
+        let default = device
             .default_input_config()
+            // the loopback capture of an output device, supported by WASAPI
+            .or_else(|_| device.default_output_config())
             .expect("error while querying configs");
+        let supported = select_input_config(&device, default, &constraints);
 
         // clone the config, we may need to fall back on it later
         let mut prefered: StreamConfig = supported.clone().into();
 
-        // set specific sample rate if requested
-        if let Some(sample_rate) = options.sample_rate {
-            crate::assert_valid_sample_rate(sample_rate);
-            prefered.sample_rate.0 = sample_rate as u32;
-        }
-
         // always try to set a decent buffer size
         let buffer_size = super::buffer_size_for_latency_category(
-            options.latency_hint,
+            constraints.latency_hint(),
             prefered.sample_rate.0 as f32,
         ) as u32;
 
@@ -333,12 +358,14 @@ impl AudioBackendManager for CpalBackend {
         // Required because some hosts don't play the stream automatically
         stream.play().expect("Input stream refused to play");
 
+        let base_latency = f64::from(clamped_buffer_size) / f64::from(sample_rate);
         let backend = CpalBackend {
             stream: ThreadSafeClosableStream::new(stream),
+            base_latency: Arc::new(AtomicF64::new(base_latency)),
             output_latency: Arc::new(AtomicF64::new(0.)),
             sample_rate,
             number_of_channels,
-            sink_id: options.sink_id,
+            sink_id,
         };
 
         (backend, receiver)
@@ -364,6 +391,10 @@ impl AudioBackendManager for CpalBackend {
         self.number_of_channels
     }
 
+    fn base_latency(&self) -> f64 {
+        self.base_latency.load()
+    }
+
     fn output_latency(&self) -> f64 {
         self.output_latency.load()
     }
@@ -421,6 +452,71 @@ impl AudioBackendManager for CpalBackend {
 
         inputs
     }
+
+    fn default_output_device_label() -> Option<String>
+    where
+        Self: Sized,
+    {
+        get_host().default_output_device()?.name().ok()
+    }
+
+    fn loopback_device_id() -> Option<String>
+    where
+        Self: Sized,
+    {
+        let devices = Self::enumerate_devices_sync();
+        let default_output = Self::default_output_device_label();
+
+        // WASAPI captures the output devices themselves in loopback mode
+        #[cfg(target_os = "windows")]
+        if get_host().id() == cpal::HostId::Wasapi {
+            return devices
+                .iter()
+                .filter(|d| d.kind() == MediaDeviceInfoKind::AudioOutput)
+                .find(|d| Some(d.label()) == default_output.as_deref())
+                .map(|d| d.device_id().to_owned());
+        }
+
+        super::find_loopback_device(&devices, default_output.as_deref())
+    }
+}
+
+/// Select the input configuration of the `device` closest to the `constraints`
+///
+/// The `default` configuration is kept when it fits as well as any other.
+fn select_input_config(
+    device: &Device,
+    default: SupportedStreamConfig,
+    constraints: &MediaTrackConstraints,
+) -> SupportedStreamConfig {
+    let ranges = match device.supported_input_configs() {
+        Ok(ranges) => ranges,
+        Err(_) => return default,
+    };
+
+    let sample_rate = constraints
+        .sample_rate
+        .map(|v| v as u32)
+        .unwrap_or(default.sample_rate().0);
+    let fitness_distance = |config: &SupportedStreamConfig| {
+        constraints.fitness_distance(config.sample_rate().0 as f32, u32::from(config.channels()))
+    };
+
+    // the first of the equally fit configs is selected
+    std::iter::once(default.clone())
+        .chain(ranges.map(|range| {
+            let sample_rate =
+                sample_rate.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+            range.with_sample_rate(SampleRate(sample_rate))
+        }))
+        .min_by(|a, b| fitness_distance(a).total_cmp(&fitness_distance(b)))
+        .unwrap_or(default)
+}
+
+/// Duration in seconds of an interleaved callback buffer of `len` samples
+fn buffer_duration(len: usize, config: &StreamConfig) -> f64 {
+    let frames = len / usize::from(config.channels);
+    frames as f64 / f64::from(config.sample_rate.0)
 }
 
 fn latency_in_seconds(infos: &OutputCallbackInfo) -> f64 {
@@ -440,20 +536,32 @@ fn latency_in_seconds(infos: &OutputCallbackInfo) -> f64 {
 /// * `sample_format` - audio sample format of the stream
 /// * `config` - stream configuration
 /// * `render` - the render thread which process the audio data
+/// * `base_latency` - updated with the duration of the callback buffers
+/// * `output_latency` - updated with the latency reported by the host
+/// * `device_lost` - notified when the output device is no longer available
 fn spawn_output_stream(
     device: &Device,
     sample_format: SampleFormat,
     config: &StreamConfig,
     mut render: RenderThread,
+    base_latency: Arc<AtomicF64>,
     output_latency: Arc<AtomicF64>,
+    device_lost: Sender<()>,
 ) -> Result<Stream, BuildStreamError> {
-    let err_fn = |err| log::error!("an error occurred on the output audio stream: {}", err);
+    let err_fn = move |err| {
+        log::error!("an error occurred on the output audio stream: {}", err);
+        if matches!(err, StreamError::DeviceNotAvailable) {
+            let _ = device_lost.send(());
+        }
+    };
+    let stream_config = config.clone();
 
     match sample_format {
         SampleFormat::F32 => device.build_output_stream(
             config,
             move |d: &mut [f32], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -463,6 +571,7 @@ fn spawn_output_stream(
             config,
             move |d: &mut [f64], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -472,6 +581,7 @@ fn spawn_output_stream(
             config,
             move |d: &mut [u8], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -481,6 +591,7 @@ fn spawn_output_stream(
             config,
             move |d: &mut [u16], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -490,6 +601,7 @@ fn spawn_output_stream(
             config,
             move |d: &mut [u32], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -499,6 +611,7 @@ fn spawn_output_stream(
             config,
             move |d: &mut [u64], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -508,6 +621,7 @@ fn spawn_output_stream(
             config,
             move |d: &mut [i8], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -517,6 +631,7 @@ fn spawn_output_stream(
             config,
             move |d: &mut [i16], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -526,6 +641,7 @@ fn spawn_output_stream(
             config,
             move |d: &mut [i32], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -535,6 +651,7 @@ fn spawn_output_stream(
             config,
             move |d: &mut [i64], i: &OutputCallbackInfo| {
                 render.render(d);
+                base_latency.store(buffer_duration(d.len(), &stream_config));
                 output_latency.store(latency_in_seconds(i));
             },
             err_fn,
@@ -5,13 +5,13 @@ use super::{AudioBackendManager, RenderThreadInit};
 use crate::buffer::AudioBuffer;
 use crate::context::AudioContextOptions;
 use crate::io::microphone::MicrophoneRender;
-use crate::media_devices::{MediaDeviceInfo, MediaDeviceInfoKind};
+use crate::media_devices::{MediaDeviceInfo, MediaDeviceInfoKind, MediaTrackConstraints};
 use crate::render::RenderThread;
-use crate::RENDER_QUANTUM_SIZE;
+use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
 
 use cubeb::{Context, DeviceId, DeviceType, StereoFrame, Stream, StreamParams};
 
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
 
 // erase type of `Frame` in cubeb `Stream<Frame>`
 struct BoxedStream(Box<dyn CubebStream>);
@@ -100,6 +100,7 @@ fn init_output_backend<const N: usize>(
     buffer_size: u32,
     device: Option<DeviceId>,
     mut renderer: RenderThread,
+    device_lost: Sender<()>,
 ) -> ThreadSafeClosableStream {
     let mut builder = cubeb::StreamBuilder::<[f32; N]>::new();
 
@@ -124,8 +125,11 @@ fn init_output_backend<const N: usize>(
 
             output.len() as isize
         })
-        .state_callback(|state| {
+        .state_callback(move |state| {
             println!("stream state changed: {state:?}");
+            if matches!(state, cubeb::State::Error) {
+                let _ = device_lost.send(());
+            }
         });
 
     let stream = builder.init(ctx).expect("Failed to create cubeb stream");
@@ -136,6 +140,8 @@ fn init_output_backend<const N: usize>(
 #[derive(Clone)]
 pub(crate) struct CubebBackend {
     stream: ThreadSafeClosableStream,
+    /// Duration of the buffer requested for the stream
+    base_latency: f64,
     sample_rate: f32,
     number_of_channels: usize,
     sink_id: String,
@@ -148,9 +154,11 @@ impl AudioBackendManager for CubebBackend {
     {
         let RenderThreadInit {
             frames_played,
+            timing,
             ctrl_msg_recv,
             load_value_send,
             event_send,
+            device_lost_send: lost,
         } = render_thread_init;
 
         // Set up cubeb context
@@ -161,11 +169,13 @@ impl AudioBackendManager for CubebBackend {
         let device_sample_rate = ctx.preferred_sample_rate().map(|v| v as f32).ok();
         let sample_rate = options.sample_rate.or(device_sample_rate).unwrap_or(48000.);
 
+        let max_channel_count = options.max_channel_count.unwrap_or(MAX_CHANNELS);
         let number_of_channels = ctx
             .max_channel_count()
             .map(|v| v as usize)
             .ok()
-            .unwrap_or(2);
+            .unwrap_or(2)
+            .min(max_channel_count);
         crate::assert_valid_number_of_channels(number_of_channels);
 
         let layout = match number_of_channels {
@@ -182,6 +192,7 @@ impl AudioBackendManager for CubebBackend {
             frames_played,
             Some(load_value_send),
             Some(event_send),
+            timing,
         );
 
         let params = cubeb::StreamParamsBuilder::new()
@@ -211,43 +222,44 @@ impl AudioBackendManager for CubebBackend {
 
         let stream = match number_of_channels {
             // so sorry, but I need to constify the non-const `number_of_channels`
-            1 => init_output_backend::<1>(&ctx, params, buffer_size, device, renderer),
-            2 => init_output_backend::<2>(&ctx, params, buffer_size, device, renderer),
-            3 => init_output_backend::<3>(&ctx, params, buffer_size, device, renderer),
-            4 => init_output_backend::<4>(&ctx, params, buffer_size, device, renderer),
-            5 => init_output_backend::<5>(&ctx, params, buffer_size, device, renderer),
-            6 => init_output_backend::<6>(&ctx, params, buffer_size, device, renderer),
-            7 => init_output_backend::<7>(&ctx, params, buffer_size, device, renderer),
-            8 => init_output_backend::<8>(&ctx, params, buffer_size, device, renderer),
-            9 => init_output_backend::<9>(&ctx, params, buffer_size, device, renderer),
-            10 => init_output_backend::<10>(&ctx, params, buffer_size, device, renderer),
-            11 => init_output_backend::<11>(&ctx, params, buffer_size, device, renderer),
-            12 => init_output_backend::<12>(&ctx, params, buffer_size, device, renderer),
-            13 => init_output_backend::<13>(&ctx, params, buffer_size, device, renderer),
-            14 => init_output_backend::<14>(&ctx, params, buffer_size, device, renderer),
-            15 => init_output_backend::<15>(&ctx, params, buffer_size, device, renderer),
-            16 => init_output_backend::<16>(&ctx, params, buffer_size, device, renderer),
-            17 => init_output_backend::<17>(&ctx, params, buffer_size, device, renderer),
-            18 => init_output_backend::<18>(&ctx, params, buffer_size, device, renderer),
-            19 => init_output_backend::<19>(&ctx, params, buffer_size, device, renderer),
-            20 => init_output_backend::<20>(&ctx, params, buffer_size, device, renderer),
-            21 => init_output_backend::<21>(&ctx, params, buffer_size, device, renderer),
-            22 => init_output_backend::<22>(&ctx, params, buffer_size, device, renderer),
-            23 => init_output_backend::<23>(&ctx, params, buffer_size, device, renderer),
-            24 => init_output_backend::<24>(&ctx, params, buffer_size, device, renderer),
-            25 => init_output_backend::<25>(&ctx, params, buffer_size, device, renderer),
-            26 => init_output_backend::<26>(&ctx, params, buffer_size, device, renderer),
-            27 => init_output_backend::<27>(&ctx, params, buffer_size, device, renderer),
-            28 => init_output_backend::<28>(&ctx, params, buffer_size, device, renderer),
-            29 => init_output_backend::<29>(&ctx, params, buffer_size, device, renderer),
-            30 => init_output_backend::<30>(&ctx, params, buffer_size, device, renderer),
-            31 => init_output_backend::<31>(&ctx, params, buffer_size, device, renderer),
-            32 => init_output_backend::<32>(&ctx, params, buffer_size, device, renderer),
+            1 => init_output_backend::<1>(&ctx, params, buffer_size, device, renderer, lost),
+            2 => init_output_backend::<2>(&ctx, params, buffer_size, device, renderer, lost),
+            3 => init_output_backend::<3>(&ctx, params, buffer_size, device, renderer, lost),
+            4 => init_output_backend::<4>(&ctx, params, buffer_size, device, renderer, lost),
+            5 => init_output_backend::<5>(&ctx, params, buffer_size, device, renderer, lost),
+            6 => init_output_backend::<6>(&ctx, params, buffer_size, device, renderer, lost),
+            7 => init_output_backend::<7>(&ctx, params, buffer_size, device, renderer, lost),
+            8 => init_output_backend::<8>(&ctx, params, buffer_size, device, renderer, lost),
+            9 => init_output_backend::<9>(&ctx, params, buffer_size, device, renderer, lost),
+            10 => init_output_backend::<10>(&ctx, params, buffer_size, device, renderer, lost),
+            11 => init_output_backend::<11>(&ctx, params, buffer_size, device, renderer, lost),
+            12 => init_output_backend::<12>(&ctx, params, buffer_size, device, renderer, lost),
+            13 => init_output_backend::<13>(&ctx, params, buffer_size, device, renderer, lost),
+            14 => init_output_backend::<14>(&ctx, params, buffer_size, device, renderer, lost),
+            15 => init_output_backend::<15>(&ctx, params, buffer_size, device, renderer, lost),
+            16 => init_output_backend::<16>(&ctx, params, buffer_size, device, renderer, lost),
+            17 => init_output_backend::<17>(&ctx, params, buffer_size, device, renderer, lost),
+            18 => init_output_backend::<18>(&ctx, params, buffer_size, device, renderer, lost),
+            19 => init_output_backend::<19>(&ctx, params, buffer_size, device, renderer, lost),
+            20 => init_output_backend::<20>(&ctx, params, buffer_size, device, renderer, lost),
+            21 => init_output_backend::<21>(&ctx, params, buffer_size, device, renderer, lost),
+            22 => init_output_backend::<22>(&ctx, params, buffer_size, device, renderer, lost),
+            23 => init_output_backend::<23>(&ctx, params, buffer_size, device, renderer, lost),
+            24 => init_output_backend::<24>(&ctx, params, buffer_size, device, renderer, lost),
+            25 => init_output_backend::<25>(&ctx, params, buffer_size, device, renderer, lost),
+            26 => init_output_backend::<26>(&ctx, params, buffer_size, device, renderer, lost),
+            27 => init_output_backend::<27>(&ctx, params, buffer_size, device, renderer, lost),
+            28 => init_output_backend::<28>(&ctx, params, buffer_size, device, renderer, lost),
+            29 => init_output_backend::<29>(&ctx, params, buffer_size, device, renderer, lost),
+            30 => init_output_backend::<30>(&ctx, params, buffer_size, device, renderer, lost),
+            31 => init_output_backend::<31>(&ctx, params, buffer_size, device, renderer, lost),
+            32 => init_output_backend::<32>(&ctx, params, buffer_size, device, renderer, lost),
             _ => unreachable!(),
         };
 
         let backend = CubebBackend {
             stream,
+            base_latency: buffer_size as f64 / sample_rate as f64,
             number_of_channels,
             sample_rate,
             sink_id: options.sink_id,
@@ -258,7 +270,7 @@ impl AudioBackendManager for CubebBackend {
         backend
     }
 
-    fn build_input(options: AudioContextOptions) -> (Self, Receiver<AudioBuffer>)
+    fn build_input(constraints: MediaTrackConstraints) -> (Self, Receiver<AudioBuffer>)
     where
         Self: Sized,
     {
@@ -275,7 +287,10 @@ impl AudioBackendManager for CubebBackend {
 
         // Use user requested sample rate, or else the device preferred one
         let device_sample_rate = ctx.preferred_sample_rate().map(|v| v as f32).ok();
-        let sample_rate = options.sample_rate.or(device_sample_rate).unwrap_or(48000.);
+        let sample_rate = constraints
+            .sample_rate
+            .or(device_sample_rate)
+            .unwrap_or(48000.);
 
         // TODO support all channel configs
         let _max_channel_count = ctx.max_channel_count().map(|v| v as usize).ok();
@@ -291,19 +306,20 @@ impl AudioBackendManager for CubebBackend {
 
         // Calculate ideal latency
         let buffer_size_req =
-            super::buffer_size_for_latency_category(options.latency_hint, sample_rate) as u32;
+            super::buffer_size_for_latency_category(constraints.latency_hint(), sample_rate) as u32;
         let min_latency = ctx
             .min_latency(&params)
             .ok()
             .unwrap_or(RENDER_QUANTUM_SIZE as u32);
         let buffer_size = buffer_size_req.max(min_latency);
 
-        let device = if options.sink_id.is_empty() {
+        let sink_id = constraints.device_id.unwrap_or_default();
+        let device = if sink_id.is_empty() {
             None
         } else {
             Self::enumerate_devices_sync()
                 .into_iter()
-                .find(|e| e.device_id() == options.sink_id)
+                .find(|e| e.device_id() == sink_id)
                 .map(|e| *e.device().downcast::<DeviceId>().unwrap())
         };
 
@@ -343,9 +359,10 @@ impl AudioBackendManager for CubebBackend {
 
         let backend = CubebBackend {
             stream: ThreadSafeClosableStream::new(stream),
+            base_latency: buffer_size as f64 / sample_rate as f64,
             number_of_channels: NUMBER_OF_INPUT_CHANNELS,
             sample_rate,
-            sink_id: options.sink_id,
+            sink_id,
         };
 
         (backend, receiver)
@@ -371,6 +388,10 @@ impl AudioBackendManager for CubebBackend {
         self.number_of_channels
     }
 
+    fn base_latency(&self) -> f64 {
+        self.base_latency
+    }
+
     fn output_latency(&self) -> f64 {
         self.stream.output_latency(self.sample_rate)
     }
@@ -429,4 +450,27 @@ impl AudioBackendManager for CubebBackend {
 
         inputs
     }
+
+    fn default_output_device_label() -> Option<String>
+    where
+        Self: Sized,
+    {
+        Context::init(None, None)
+            .ok()?
+            .enumerate_devices(DeviceType::OUTPUT)
+            .ok()?
+            .iter()
+            .find(|d| d.preferred().contains(cubeb::DevicePref::MULTIMEDIA))
+            .and_then(|d| d.friendly_name().map(str::to_string))
+    }
+
+    fn loopback_device_id() -> Option<String>
+    where
+        Self: Sized,
+    {
+        super::find_loopback_device(
+            &Self::enumerate_devices_sync(),
+            Self::default_output_device_label().as_deref(),
+        )
+    }
 }
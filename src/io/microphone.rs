@@ -2,7 +2,6 @@ use std::error::Error;
 
 use crate::buffer::{AudioBuffer, AudioBufferOptions};
 use crate::io::AudioBackendManager;
-use crate::RENDER_QUANTUM_SIZE;
 
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 
@@ -44,12 +43,11 @@ impl Iterator for MicrophoneStream {
                 buffer
             }
             Err(TryRecvError::Empty) => {
-                // frame not received in time, emit silence
-                log::debug!("empty channel: input frame delayed");
-
+                // no frame available, emit an empty buffer so the consumer can
+                // decide how to handle the missing data
                 let options = AudioBufferOptions {
                     number_of_channels: self.number_of_channels,
-                    length: RENDER_QUANTUM_SIZE,
+                    length: 0,
                     sample_rate: self.sample_rate,
                 };
 
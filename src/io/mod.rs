@@ -8,9 +8,14 @@ use crossbeam_channel::{Receiver, Sender};
 use crate::buffer::AudioBuffer;
 use crate::context::{AudioContextLatencyCategory, AudioContextOptions};
 use crate::events::EventDispatch;
-use crate::media_devices::MediaDeviceInfo;
-use crate::media_streams::{MediaStream, MediaStreamTrack};
+#[cfg(any(test, feature = "cubeb", feature = "cpal"))]
+use crate::media_devices::MediaDeviceInfoKind;
+use crate::media_devices::{MediaDeviceInfo, MediaTrackConstraints};
+use crate::media_streams::MediaStream;
+#[cfg(any(feature = "cubeb", feature = "cpal"))]
+use crate::media_streams::{MediaStreamTrack, MediaTrackSettings};
 use crate::message::ControlMessage;
+use crate::render::RenderTiming;
 use crate::{AudioRenderCapacityLoad, RENDER_QUANTUM_SIZE};
 
 mod none;
@@ -27,43 +32,61 @@ mod microphone;
 #[derive(Debug)]
 pub(crate) struct ControlThreadInit {
     pub frames_played: Arc<AtomicU64>,
+    pub timing: Arc<RenderTiming>,
     pub ctrl_msg_send: Sender<ControlMessage>,
     pub load_value_recv: Receiver<AudioRenderCapacityLoad>,
     pub event_send: Sender<EventDispatch>,
     pub event_recv: Receiver<EventDispatch>,
+    pub device_lost_recv: Receiver<()>,
 }
 
 #[derive(Clone, Debug)]
 pub(crate) struct RenderThreadInit {
     pub frames_played: Arc<AtomicU64>,
+    pub timing: Arc<RenderTiming>,
     pub ctrl_msg_recv: Receiver<ControlMessage>,
     pub load_value_send: Sender<AudioRenderCapacityLoad>,
     pub event_send: Sender<EventDispatch>,
+    /// Signals the loss of the audio output device, sent by the backend
+    #[cfg(any(test, feature = "cubeb", feature = "cpal"))]
+    pub device_lost_send: Sender<()>,
 }
 
 pub(crate) fn thread_init() -> (ControlThreadInit, RenderThreadInit) {
     // track number of frames - synced from render thread to control thread
     let frames_played = Arc::new(AtomicU64::new(0));
+    // timing of the render callbacks - synced from render thread to control thread
+    let timing = Arc::new(RenderTiming::default());
     // communication channel for ctrl msgs to the render thread
     let (ctrl_msg_send, ctrl_msg_recv) = crossbeam_channel::unbounded();
     // communication channel for render load values
     let (load_value_send, load_value_recv) = crossbeam_channel::bounded(1);
     // communication channel for events for render thread to control thread
     let (event_send, event_recv) = crossbeam_channel::unbounded();
+    // communication channel for the loss of the output device from backend to control thread,
+    // closed right away without an audio backend, there is no device to lose or to follow
+    let (device_lost_send, device_lost_recv) = crossbeam_channel::unbounded();
+    #[cfg(not(any(test, feature = "cubeb", feature = "cpal")))]
+    drop(device_lost_send);
 
     let control_thread_init = ControlThreadInit {
         frames_played: frames_played.clone(),
+        timing: timing.clone(),
         ctrl_msg_send,
         load_value_recv,
         event_send: event_send.clone(),
         event_recv,
+        device_lost_recv,
     };
 
     let render_thread_init = RenderThreadInit {
         frames_played,
+        timing,
         ctrl_msg_recv,
         load_value_send,
         event_send,
+        #[cfg(any(test, feature = "cubeb", feature = "cpal"))]
+        device_lost_send,
     };
 
     (control_thread_init, render_thread_init)
@@ -96,30 +119,62 @@ pub(crate) fn build_output(
 }
 
 /// Set up an input stream (microphone) bases on the selected features (cubeb/cpal/none)
-pub(crate) fn build_input(options: AudioContextOptions) -> MediaStream {
-    #[cfg(all(not(feature = "cubeb"), not(feature = "cpal")))]
-    {
-        panic!("No audio backend available, enable the 'cpal' or 'cubeb' feature")
-    }
+#[cfg(all(not(feature = "cubeb"), not(feature = "cpal")))]
+pub(crate) fn build_input(_constraints: MediaTrackConstraints) -> MediaStream {
+    panic!("No audio backend available, enable the 'cpal' or 'cubeb' feature")
+}
 
-    #[cfg(any(feature = "cubeb", feature = "cpal"))]
-    {
-        let (backend, receiver) = {
-            #[cfg(feature = "cubeb")]
-            {
-                cubeb::CubebBackend::build_input(options)
-            }
+/// Set up an input stream (microphone) bases on the selected features (cubeb/cpal/none)
+#[cfg(any(feature = "cubeb", feature = "cpal"))]
+pub(crate) fn build_input(constraints: MediaTrackConstraints) -> MediaStream {
+    // the processing is only available with the corresponding features
+    let noise_suppression =
+        cfg!(feature = "noise-suppression") && constraints.noise_suppression == Some(true);
+    let auto_gain_control = constraints.auto_gain_control == Some(true);
+    let auto_gain_control_options = constraints.auto_gain_control_options.clone();
+
+    let (backend, receiver) = {
+        #[cfg(feature = "cubeb")]
+        {
+            cubeb::CubebBackend::build_input(constraints)
+        }
 
-            #[cfg(all(not(feature = "cubeb"), feature = "cpal"))]
-            {
-                cpal::CpalBackend::build_input(options)
-            }
-        };
+        #[cfg(all(not(feature = "cubeb"), feature = "cpal"))]
+        {
+            cpal::CpalBackend::build_input(constraints)
+        }
+    };
 
-        let media_iter = microphone::MicrophoneStream::new(receiver, Box::new(backend));
-        let track = MediaStreamTrack::from_iter(media_iter);
-        MediaStream::from_tracks(vec![track])
+    let settings = MediaTrackSettings {
+        sample_rate: Some(backend.sample_rate()),
+        echo_cancellation: Some(false),
+        auto_gain_control: Some(auto_gain_control),
+        noise_suppression: Some(noise_suppression),
+        latency: Some(backend.base_latency()),
+        channel_count: Some(backend.number_of_channels() as u32),
+        device_id: Some(backend.sink_id().to_owned()),
+    };
+
+    let mut media_iter: Box<dyn Iterator<Item = crate::FallibleBuffer> + Send + Sync> = Box::new(
+        microphone::MicrophoneStream::new(receiver, Box::new(backend)),
+    );
+
+    // remove the noise first, it should not be amplified
+    #[cfg(feature = "noise-suppression")]
+    if noise_suppression {
+        media_iter = Box::new(crate::noise_suppression::NoiseSuppressedStream::new(
+            media_iter,
+        ));
     }
+    if auto_gain_control {
+        media_iter = Box::new(crate::auto_gain_control::AutoGainControlStream::new(
+            media_iter,
+            auto_gain_control_options,
+        ));
+    }
+
+    let track = MediaStreamTrack::from_iter_with_settings(media_iter, settings);
+    MediaStream::from_tracks(vec![track])
 }
 
 /// Interface for audio backends
@@ -130,7 +185,7 @@ pub(crate) trait AudioBackendManager: Send + Sync + 'static {
         Self: Sized;
 
     /// Setup a new input stream (microphone capture)
-    fn build_input(options: AudioContextOptions) -> (Self, Receiver<AudioBuffer>)
+    fn build_input(constraints: MediaTrackConstraints) -> (Self, Receiver<AudioBuffer>)
     where
         Self: Sized;
 
@@ -149,6 +204,12 @@ pub(crate) trait AudioBackendManager: Send + Sync + 'static {
     /// Number of channels of the stream
     fn number_of_channels(&self) -> usize;
 
+    /// Base latency of the stream in seconds
+    ///
+    /// This is the duration of the buffer of a callback (the device period), the time the rendered
+    /// audio waits before it is handed to the audio subsystem.
+    fn base_latency(&self) -> f64;
+
     /// Output latency of the stream in seconds
     ///
     /// This is the difference between the time the backend acquires the data in the callback and
@@ -164,10 +225,22 @@ pub(crate) trait AudioBackendManager: Send + Sync + 'static {
     fn enumerate_devices_sync() -> Vec<MediaDeviceInfo>
     where
         Self: Sized;
+
+    /// Label of the default audio output device of the host, if any
+    #[cfg(any(feature = "cpal", feature = "cubeb"))]
+    fn default_output_device_label() -> Option<String>
+    where
+        Self: Sized;
+
+    /// Identifier of the input device capturing the audio output of the system, if any
+    #[cfg(any(feature = "cpal", feature = "cubeb"))]
+    fn loopback_device_id() -> Option<String>
+    where
+        Self: Sized;
 }
 
 /// Calculate buffer size in frames for a given latency category
-fn buffer_size_for_latency_category(
+pub(crate) fn buffer_size_for_latency_category(
     latency_cat: AudioContextLatencyCategory,
     sample_rate: f32,
 ) -> usize {
@@ -190,8 +263,15 @@ fn buffer_size_for_latency_category(
                 );
             }
 
-            let buffer_size = (latency * sample_rate as f64) as usize;
-            buffer_size.next_power_of_two()
+            // pick the closest power of two, the backends only support these
+            let buffer_size = (latency * sample_rate as f64).max(1.);
+            let upper = (buffer_size.ceil() as usize).next_power_of_two();
+            let lower = upper / 2;
+            if lower > 0 && buffer_size - (lower as f64) < (upper as f64) - buffer_size {
+                lower
+            } else {
+                upper
+            }
         }
     }
 }
@@ -210,3 +290,116 @@ pub(crate) fn enumerate_devices_sync() -> Vec<MediaDeviceInfo> {
     #[cfg(all(not(feature = "cubeb"), not(feature = "cpal")))]
     panic!("No audio backend available, enable the 'cpal' or 'cubeb' feature")
 }
+
+/// Label of the default audio output device, `None` when there is no device or no audio backend
+pub(crate) fn default_output_device_label() -> Option<String> {
+    #[cfg(feature = "cubeb")]
+    {
+        crate::io::cubeb::CubebBackend::default_output_device_label()
+    }
+
+    #[cfg(all(not(feature = "cubeb"), feature = "cpal"))]
+    {
+        crate::io::cpal::CpalBackend::default_output_device_label()
+    }
+
+    #[cfg(all(not(feature = "cubeb"), not(feature = "cpal")))]
+    None
+}
+
+/// Identifier of the input device capturing the audio output of the system, `None` when the
+/// platform or the audio backend does not provide one
+pub(crate) fn loopback_device_id() -> Option<String> {
+    #[cfg(feature = "cubeb")]
+    {
+        crate::io::cubeb::CubebBackend::loopback_device_id()
+    }
+
+    #[cfg(all(not(feature = "cubeb"), feature = "cpal"))]
+    {
+        crate::io::cpal::CpalBackend::loopback_device_id()
+    }
+
+    #[cfg(all(not(feature = "cubeb"), not(feature = "cpal")))]
+    None
+}
+
+/// Case insensitive keywords of the labels of the input devices capturing the system output:
+/// the monitor sources of PulseAudio and PipeWire, and virtual loopback drivers
+#[cfg(any(test, feature = "cubeb", feature = "cpal"))]
+const LOOPBACK_KEYWORDS: [&str; 3] = ["monitor", "loopback", "blackhole"];
+
+/// Find the input device capturing the system output, preferring the one of the
+/// `default_output` device
+#[cfg(any(test, feature = "cubeb", feature = "cpal"))]
+pub(crate) fn find_loopback_device(
+    devices: &[MediaDeviceInfo],
+    default_output: Option<&str>,
+) -> Option<String> {
+    let loopbacks: Vec<&MediaDeviceInfo> = devices
+        .iter()
+        .filter(|d| d.kind() == MediaDeviceInfoKind::AudioInput)
+        .filter(|d| {
+            let label = d.label().to_lowercase();
+            LOOPBACK_KEYWORDS.iter().any(|k| label.contains(k))
+        })
+        .collect();
+
+    default_output
+        .and_then(|output| loopbacks.iter().find(|d| d.label().contains(output)))
+        .or_else(|| loopbacks.first())
+        .map(|d| d.device_id().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_size_for_custom_latency() {
+        let size = |latency| {
+            buffer_size_for_latency_category(AudioContextLatencyCategory::Custom(latency), 48000.)
+        };
+
+        // 480 frames
+        assert_eq!(size(0.01), 512);
+        // 360 frames
+        assert_eq!(size(0.0075), 256);
+        assert_eq!(size(1024. / 48000.), 1024);
+        assert_eq!(size(1e-9), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_buffer_size_for_invalid_custom_latency() {
+        buffer_size_for_latency_category(AudioContextLatencyCategory::Custom(0.), 48000.);
+    }
+
+    #[test]
+    fn test_find_loopback_device() {
+        let device = |id: &str, kind, label: &str| {
+            MediaDeviceInfo::new(id.into(), None, kind, label.into(), Box::new(()))
+        };
+        let devices = [
+            device("1", MediaDeviceInfoKind::AudioInput, "Built-in Microphone"),
+            device(
+                "2",
+                MediaDeviceInfoKind::AudioInput,
+                "Monitor of USB Headset",
+            ),
+            device(
+                "3",
+                MediaDeviceInfoKind::AudioInput,
+                "Monitor of Built-in Audio",
+            ),
+            device("4", MediaDeviceInfoKind::AudioOutput, "Built-in Audio"),
+        ];
+
+        assert_eq!(
+            find_loopback_device(&devices, Some("Built-in Audio")),
+            Some("3".to_owned())
+        );
+        assert_eq!(find_loopback_device(&devices, None), Some("2".to_owned()));
+        assert_eq!(find_loopback_device(&devices[..1], None), None);
+    }
+}
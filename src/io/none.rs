@@ -5,7 +5,7 @@ use super::{AudioBackendManager, RenderThreadInit};
 
 use crate::buffer::AudioBuffer;
 use crate::context::AudioContextOptions;
-use crate::media_devices::MediaDeviceInfo;
+use crate::media_devices::{MediaDeviceInfo, MediaTrackConstraints};
 use crate::render::RenderThread;
 use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
 
@@ -21,19 +21,21 @@ enum NoneBackendMessage {
 pub(crate) struct NoneBackend {
     sender: Sender<NoneBackendMessage>,
     sample_rate: f32,
+    number_of_channels: usize,
 }
 
 struct Callback {
     receiver: Receiver<NoneBackendMessage>,
     render_thread: RenderThread,
     sample_rate: f32,
+    number_of_channels: usize,
     running: bool,
 }
 
 impl Callback {
     fn run(mut self) {
         let buffer_size = RENDER_QUANTUM_SIZE; // TODO Latency Category
-        let mut buffer = vec![0.; buffer_size * MAX_CHANNELS];
+        let mut buffer = vec![0.; buffer_size * self.number_of_channels];
         let interval = Duration::from_secs_f32(buffer_size as f32 / self.sample_rate);
 
         // For an isochronous callback we must calculate the deadline every render quantum
@@ -69,21 +71,25 @@ impl AudioBackendManager for NoneBackend {
         Self: Sized,
     {
         let sample_rate = options.sample_rate.unwrap_or(48000.);
+        let number_of_channels = options.max_channel_count.unwrap_or(MAX_CHANNELS);
 
         let RenderThreadInit {
             frames_played,
+            timing,
             ctrl_msg_recv,
             load_value_send,
             event_send,
+            ..
         } = render_thread_init;
 
         let render_thread = RenderThread::new(
             sample_rate,
-            MAX_CHANNELS,
+            number_of_channels,
             ctrl_msg_recv,
             frames_played,
             Some(load_value_send),
             Some(event_send),
+            timing,
         );
 
         let (sender, receiver) = crossbeam_channel::unbounded();
@@ -93,6 +99,7 @@ impl AudioBackendManager for NoneBackend {
             render_thread,
             receiver,
             sample_rate,
+            number_of_channels,
             running: true,
         };
 
@@ -101,11 +108,12 @@ impl AudioBackendManager for NoneBackend {
         Self {
             sender,
             sample_rate,
+            number_of_channels,
         }
     }
 
     /// Setup a new input stream (microphone capture)
-    fn build_input(_options: AudioContextOptions) -> (Self, Receiver<AudioBuffer>)
+    fn build_input(_constraints: MediaTrackConstraints) -> (Self, Receiver<AudioBuffer>)
     where
         Self: Sized,
     {
@@ -136,7 +144,14 @@ impl AudioBackendManager for NoneBackend {
 
     /// Number of channels of the stream
     fn number_of_channels(&self) -> usize {
-        MAX_CHANNELS
+        self.number_of_channels
+    }
+
+    /// Base latency of the stream in seconds
+    ///
+    /// The graph is rendered a render quantum at a time
+    fn base_latency(&self) -> f64 {
+        RENDER_QUANTUM_SIZE as f64 / self.sample_rate as f64
     }
 
     /// Output latency of the stream in seconds
@@ -163,4 +178,20 @@ impl AudioBackendManager for NoneBackend {
     {
         unimplemented!()
     }
+
+    #[cfg(any(feature = "cpal", feature = "cubeb"))]
+    fn default_output_device_label() -> Option<String>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    #[cfg(any(feature = "cpal", feature = "cubeb"))]
+    fn loopback_device_id() -> Option<String>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
@@ -33,6 +33,21 @@
 //! // enjoy listening
 //! std::thread::sleep(std::time::Duration::from_secs(4));
 //! ```
+//!
+//! # Extensions
+//!
+//! Besides the interfaces of the Web Audio API specification, this crate provides extensions
+//! which are not part of the specification, among others:
+//!
+//! - the nodes that are not created by a `create_*` method of the
+//!   [`BaseAudioContext`](context::BaseAudioContext), and the
+//!   [`AudioEffectNode`](node::AudioEffectNode) bypass
+//! - the batched graph edits, render threads, stems, progress and cancellation of the
+//!   contexts, and the clock, watchdog, capture and adaptive latency of the `AudioContext`
+//! - the network, HTTP, jitter buffer and voice activity [`media_streams`], and the echo
+//!   cancellation and gain control of the microphone
+//! - the pluggable [`fft`], the [`render`] helpers for custom processors, and memory-mapped
+//!   audio buffers
 
 #![warn(clippy::missing_panics_doc)]
 #![deny(trivial_numeric_casts)]
@@ -42,9 +57,17 @@ use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 /// Render quantum size, the audio graph is rendered in blocks of RENDER_QUANTUM_SIZE samples
 /// see. <https://webaudio.github.io/web-audio-api/#render-quantum>
-pub(crate) const RENDER_QUANTUM_SIZE: usize = 128;
+pub const RENDER_QUANTUM_SIZE: usize = 128;
 
 /// Maximum number of channels for audio processing
+///
+/// This is the upper bound for the channel count of audio buffers, nodes and audio contexts.
+/// All of these support up to 32 channels, e.g. for multichannel interfaces or third order
+/// ambisonics (16 channels). The limit is a compile time constant as the render quanta store
+/// their channels inline, so the render thread never allocates when the channel count changes.
+///
+/// The number of output channels of an `AudioContext` can only be lowered, with
+/// [`AudioContextOptions::max_channel_count`](context::AudioContextOptions::max_channel_count).
 pub const MAX_CHANNELS: usize = 32;
 
 mod buffer;
@@ -56,6 +79,8 @@ pub use capacity::*;
 pub mod context;
 pub(crate) mod control;
 
+pub mod fft;
+
 pub mod media_devices;
 pub mod media_recorder;
 pub mod media_streams;
@@ -73,6 +98,10 @@ pub use periodic_wave::*;
 
 pub mod render;
 
+pub mod testing;
+
+pub mod transport;
+
 mod spatial;
 pub use spatial::AudioListener;
 
@@ -81,6 +110,12 @@ mod io;
 mod analysis;
 mod message;
 
+mod capture;
+pub use capture::{CaptureHandle, CaptureOptions, CaptureTarget};
+
+mod message_port;
+pub use message_port::MessagePort;
+
 mod decoding;
 
 mod media_element;
@@ -88,6 +123,24 @@ pub use media_element::MediaElement;
 
 mod resampling;
 
+mod wav;
+pub use wav::WavSampleFormat;
+
+#[cfg(feature = "mmap")]
+mod mmap;
+
+mod watchdog;
+pub use watchdog::{OverloadEvent, WatchdogOptions};
+
+mod echo_cancellation;
+pub use echo_cancellation::EchoCancellationOptions;
+
+#[cfg(feature = "noise-suppression")]
+mod noise_suppression;
+
+mod auto_gain_control;
+pub use auto_gain_control::AutoGainControlOptions;
+
 #[derive(Debug)]
 pub(crate) struct AtomicF32 {
     inner: AtomicU32,
@@ -4,8 +4,17 @@
 //!
 //! <https://developer.mozilla.org/en-US/docs/Web/API/MediaDevices>
 
+use std::error::Error;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
 use crate::context::{AudioContextLatencyCategory, AudioContextOptions};
 use crate::media_streams::MediaStream;
+use crate::AutoGainControlOptions;
 
 /// List the available media output devices, such as speakers, headsets, loopbacks, etc
 ///
@@ -24,6 +33,29 @@ pub fn enumerate_devices_sync() -> Vec<MediaDeviceInfo> {
     crate::io::enumerate_devices_sync()
 }
 
+/// List the available media output devices, such as speakers, headsets, loopbacks, etc
+///
+/// This is the async version of [`enumerate_devices_sync`]. The devices are enumerated on a
+/// separate thread, the returned future can be awaited on any async runtime.
+pub fn enumerate_devices() -> impl Future<Output = Vec<MediaDeviceInfo>> {
+    // the backend handles of the devices can not be sent across threads, only the descriptions
+    let task = BlockingTask::spawn(|| {
+        enumerate_devices_sync()
+            .into_iter()
+            .map(|d| (d.device_id, d.group_id, d.kind, d.label))
+            .collect::<Vec<_>>()
+    });
+
+    async move {
+        task.await
+            .into_iter()
+            .map(|(device_id, group_id, kind, label)| {
+                MediaDeviceInfo::new(device_id, group_id, kind, label, Box::new(()))
+            })
+            .collect()
+    }
+}
+
 /// Describes input/output type of a media device
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum MediaDeviceInfoKind {
@@ -97,7 +129,11 @@ pub enum MediaStreamConstraints {
 }
 
 /// Desired media stream track settings for [`MediaTrackConstraints`]
-#[derive(Default)]
+///
+/// The values are ideal values: the device configuration closest to them is selected, see
+/// [`MediaStreamTrack::get_settings`](crate::media_streams::MediaStreamTrack::get_settings) for
+/// the applied settings.
+#[derive(Clone, Debug, Default)]
 #[non_exhaustive]
 pub struct MediaTrackConstraints {
     // ConstrainULong width;
@@ -108,21 +144,56 @@ pub struct MediaTrackConstraints {
     // ConstrainDOMString resizeMode;
     pub sample_rate: Option<f32>,
     // ConstrainULong sampleSize;
-    // ConstrainBoolean echoCancellation;
-    // ConstrainBoolean autoGainControl;
-    // ConstrainBoolean noiseSuppression;
+    pub echo_cancellation: Option<bool>,
+    /// Bring the level of the captured audio to a target level, see
+    /// [`auto_gain_control_options`](Self::auto_gain_control_options)
+    pub auto_gain_control: Option<bool>,
+    /// Remove the stationary background noise of the captured audio, only available with the
+    /// `noise-suppression` feature
+    pub noise_suppression: Option<bool>,
     pub latency: Option<f64>,
-    //ConstrainULong channelCount;
+    pub channel_count: Option<u32>,
     pub device_id: Option<String>,
     // ConstrainDOMString groupId;
+    /// Options of the automatic gain control, when enabled with `auto_gain_control`
+    ///
+    /// This is not part of the specification.
+    pub auto_gain_control_options: AutoGainControlOptions,
 }
 
-impl From<MediaTrackConstraints> for AudioContextOptions {
-    fn from(value: MediaTrackConstraints) -> Self {
-        let latency_hint = match value.latency {
+impl MediaTrackConstraints {
+    /// Latency category of the capture stream
+    pub(crate) fn latency_hint(&self) -> AudioContextLatencyCategory {
+        match self.latency {
             Some(v) => AudioContextLatencyCategory::Custom(v),
             None => AudioContextLatencyCategory::Interactive,
-        };
+        }
+    }
+
+    /// Fitness distance of a device configuration to the constraints, lower is better
+    ///
+    /// The processing constraints are not taken into account, no configuration provides them.
+    ///
+    /// <https://w3c.github.io/mediacapture-main/#dfn-fitness-distance>
+    #[cfg(any(test, feature = "cpal"))]
+    pub(crate) fn fitness_distance(&self, sample_rate: f32, channel_count: u32) -> f64 {
+        fn distance(actual: f64, ideal: Option<f64>) -> f64 {
+            match ideal {
+                Some(ideal) if actual != ideal => {
+                    (actual - ideal).abs() / actual.abs().max(ideal.abs())
+                }
+                _ => 0.,
+            }
+        }
+
+        distance(f64::from(sample_rate), self.sample_rate.map(f64::from))
+            + distance(f64::from(channel_count), self.channel_count.map(f64::from))
+    }
+}
+
+impl From<MediaTrackConstraints> for AudioContextOptions {
+    fn from(value: MediaTrackConstraints) -> Self {
+        let latency_hint = value.latency_hint();
         let sink_id = value.device_id.unwrap_or(String::from(""));
 
         AudioContextOptions {
@@ -130,6 +201,7 @@ impl From<MediaTrackConstraints> for AudioContextOptions {
             sample_rate: value.sample_rate,
             sink_id,
             render_size_hint: Default::default(),
+            max_channel_count: None,
         }
     }
 }
@@ -157,8 +229,8 @@ fn is_valid_device_id(device_id: &str) -> bool {
 /// kept alive and emit audio buffers. Call the `close()` method if you want to stop the media
 /// input and release all system resources.
 ///
-/// This function operates synchronously, which may be undesirable on the control thread. An async
-/// version is currently not implemented.
+/// This function operates synchronously, which may be undesirable on the control thread. Use
+/// [`get_user_media`] in an async context.
 ///
 /// # Example
 ///
@@ -181,16 +253,198 @@ fn is_valid_device_id(device_id: &str) -> bool {
 /// // enjoy listening
 /// std::thread::sleep(std::time::Duration::from_secs(4));
 /// ```
+///
+/// # Panics
+///
+/// Will panic when the automatic gain control is enabled with invalid
+/// [`AutoGainControlOptions`]
 pub fn get_user_media_sync(constraints: MediaStreamConstraints) -> MediaStream {
-    let mut options = match constraints {
-        MediaStreamConstraints::Audio => AudioContextOptions::default(),
-        MediaStreamConstraints::AudioWithConstraints(cs) => cs.into(),
+    let mut constraints = match constraints {
+        MediaStreamConstraints::Audio => MediaTrackConstraints::default(),
+        MediaStreamConstraints::AudioWithConstraints(cs) => cs,
     };
 
-    if !is_valid_device_id(&options.sink_id) {
-        log::error!("NotFoundError: invalid deviceId {:?}", options.sink_id);
-        options.sink_id = String::from("");
+    if constraints.auto_gain_control == Some(true) {
+        constraints.auto_gain_control_options.validate();
     }
 
-    crate::io::build_input(options)
+    if let Some(device_id) = &constraints.device_id {
+        if !is_valid_device_id(device_id) {
+            log::error!("NotFoundError: invalid deviceId {:?}", device_id);
+            constraints.device_id = None;
+        }
+    }
+
+    crate::io::build_input(constraints)
+}
+
+/// Prompt for permission to use a media input (audio only)
+///
+/// This is the async version of [`get_user_media_sync`]. The input device is opened on a separate
+/// thread, the returned future can be awaited on any async runtime.
+pub fn get_user_media(constraints: MediaStreamConstraints) -> impl Future<Output = MediaStream> {
+    BlockingTask::spawn(move || get_user_media_sync(constraints))
+}
+
+/// Prompt for permission to capture the audio output of the system (audio only)
+///
+/// This produces a [`MediaStream`] of what the system is playing, e.g. for visualization or
+/// recording. The loopback capture depends on the platform:
+/// - WASAPI (Windows) captures the default output device
+/// - PulseAudio and PipeWire capture the monitor source of the default output device, with the
+///   `cubeb` feature, or when the monitor is exposed as an input device
+/// - CoreAudio requires a virtual loopback device, e.g. BlackHole
+///
+/// The `device_id` of the constraints is ignored, the other constraints apply as for
+/// [`get_user_media_sync`].
+///
+/// This function operates synchronously, which may be undesirable on the control thread. Use
+/// [`get_display_media`] in an async context.
+///
+/// # Errors
+///
+/// Will return a `NotSupportedError` when no loopback capture is available
+///
+/// # Panics
+///
+/// Will panic when the automatic gain control is enabled with invalid
+/// [`AutoGainControlOptions`]
+pub fn get_display_media_sync(
+    constraints: MediaStreamConstraints,
+) -> Result<MediaStream, Box<dyn Error + Send + Sync>> {
+    let mut constraints = match constraints {
+        MediaStreamConstraints::Audio => MediaTrackConstraints::default(),
+        MediaStreamConstraints::AudioWithConstraints(cs) => cs,
+    };
+
+    let device_id = crate::io::loopback_device_id()
+        .ok_or("NotSupportedError - no loopback capture of the system audio available")?;
+    constraints.device_id = Some(device_id);
+
+    Ok(get_user_media_sync(
+        MediaStreamConstraints::AudioWithConstraints(constraints),
+    ))
+}
+
+/// Prompt for permission to capture the audio output of the system (audio only)
+///
+/// This is the async version of [`get_display_media_sync`].
+pub fn get_display_media(
+    constraints: MediaStreamConstraints,
+) -> impl Future<Output = Result<MediaStream, Box<dyn Error + Send + Sync>>> {
+    BlockingTask::spawn(move || get_display_media_sync(constraints))
+}
+
+/// State shared by a [`BlockingTask`] and its thread
+struct BlockingTaskState<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Future of a blocking OS call running on a dedicated thread
+///
+/// It does not depend on a specific async runtime. A panic of the call is resumed when the
+/// future is polled.
+struct BlockingTask<T> {
+    state: Arc<Mutex<BlockingTaskState<T>>>,
+}
+
+impl<T: Send + 'static> BlockingTask<T> {
+    fn spawn<F: FnOnce() -> T + Send + 'static>(f: F) -> Self {
+        let state = Arc::new(Mutex::new(BlockingTaskState {
+            result: None,
+            waker: None,
+        }));
+
+        let state_clone = Arc::clone(&state);
+        thread::spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            let mut state = state_clone.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        Self { state }
+    }
+}
+
+impl<T> Future for BlockingTask<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::task::Wake;
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_fitness_distance() {
+        let constraints = MediaTrackConstraints::default();
+        assert_eq!(constraints.fitness_distance(44_100., 2), 0.);
+
+        let constraints = MediaTrackConstraints {
+            sample_rate: Some(48_000.),
+            channel_count: Some(1),
+            ..MediaTrackConstraints::default()
+        };
+        assert_eq!(constraints.fitness_distance(48_000., 1), 0.);
+        assert_eq!(constraints.fitness_distance(48_000., 2), 0.5);
+        assert_eq!(constraints.fitness_distance(96_000., 1), 0.5);
+        // the closest sample rate is preferred
+        assert!(
+            constraints.fitness_distance(44_100., 1) < constraints.fitness_distance(96_000., 1)
+        );
+    }
+
+    #[test]
+    fn test_blocking_task() {
+        let task = BlockingTask::spawn(|| {
+            thread::sleep(std::time::Duration::from_millis(10));
+            42
+        });
+        assert_eq!(block_on(task), 42);
+    }
+
+    #[test]
+    #[should_panic(expected = "device error")]
+    fn test_blocking_task_panic() {
+        let task = BlockingTask::spawn(|| -> usize { panic!("device error") });
+        block_on(task);
+    }
 }
@@ -0,0 +1,382 @@
+//! Minimal built-in FLAC encoder for the [`MediaRecorder`](super::MediaRecorder)
+//!
+//! The stream is encoded with fixed predictors and Rice coded residuals, in blocks of variable
+//! size so the recorder can flush its data at any time.
+use std::error::Error;
+
+use crate::AudioBuffer;
+
+/// Size of the encoded blocks, in sample-frames
+const BLOCK_SIZE: usize = 4096;
+/// Smallest block allowed by the format, but for the last one
+const MIN_BLOCK_SIZE: usize = 16;
+/// Largest number of channels of the format
+const MAX_CHANNELS: usize = 8;
+/// Largest sample rate of the format, in Hz
+const MAX_SAMPLE_RATE: u32 = 655_350;
+/// Largest Rice parameter of the 4 bits residual coding method
+const MAX_RICE_PARAMETER: u32 = 14;
+
+/// Writer of a big-endian bit stream
+struct BitWriter {
+    bytes: Vec<u8>,
+    accumulator: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            accumulator: 0,
+            bits: 0,
+        }
+    }
+
+    /// Write the `bits` lowest bits of `value`, `bits` being at most 32
+    fn write(&mut self, value: u32, bits: u32) {
+        if bits == 0 {
+            return;
+        }
+        let mask = u64::MAX >> (64 - bits);
+        self.accumulator = (self.accumulator << bits) | (u64::from(value) & mask);
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.accumulator >> self.bits) as u8);
+        }
+    }
+
+    /// Write `value` in unary, as zeros terminated by a one
+    fn write_unary(&mut self, mut value: u32) {
+        while value >= 32 {
+            self.write(0, 32);
+            value -= 32;
+        }
+        self.write(1, value + 1);
+    }
+
+    /// Pad the stream with zeros to a byte boundary
+    fn into_bytes(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+        self.bytes
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ (u16::from(*byte) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Append `value` to `output` in the extended UTF-8 coding of the frame headers
+fn write_utf8(value: u64, output: &mut Vec<u8>) {
+    if value < 0x80 {
+        output.push(value as u8);
+        return;
+    }
+
+    let length: u32 = match value {
+        0..=0x7FF => 2,
+        0x800..=0xFFFF => 3,
+        0x1_0000..=0x1F_FFFF => 4,
+        0x20_0000..=0x3FF_FFFF => 5,
+        0x400_0000..=0x7FFF_FFFF => 6,
+        _ => 7,
+    };
+    let lead = (0xFF00_u16 >> length) as u8;
+    output.push(lead | (value >> (6 * (length - 1))) as u8);
+    for i in (0..length - 1).rev() {
+        output.push(0x80 | ((value >> (6 * i)) & 0x3F) as u8);
+    }
+}
+
+/// Residuals of the fixed predictor of `order`, for the samples following the warm-up
+fn fixed_residuals(samples: &[i32], order: usize) -> Vec<i64> {
+    let s = |i: usize| i64::from(samples[i]);
+    (order..samples.len())
+        .map(|i| match order {
+            0 => s(i),
+            1 => s(i) - s(i - 1),
+            2 => s(i) - 2 * s(i - 1) + s(i - 2),
+            3 => s(i) - 3 * s(i - 1) + 3 * s(i - 2) - s(i - 3),
+            _ => s(i) - 4 * s(i - 1) + 6 * s(i - 2) - 4 * s(i - 3) + s(i - 4),
+        })
+        .collect()
+}
+
+/// Residual folded to an unsigned value, the sign in the lowest bit
+fn fold(residual: i64) -> u64 {
+    ((residual << 1) ^ (residual >> 63)) as u64
+}
+
+/// Rice parameter for the `residuals`, and the size of the coded residuals in bits
+fn rice_parameter(residuals: &[i64]) -> (u32, u64) {
+    let sum: u64 = residuals.iter().map(|r| fold(*r)).sum();
+    let mean = sum / residuals.len().max(1) as u64;
+    let parameter = (64 - mean.leading_zeros())
+        .saturating_sub(1)
+        .min(MAX_RICE_PARAMETER);
+    let bits = residuals
+        .iter()
+        .map(|r| (fold(*r) >> parameter) + 1 + u64::from(parameter))
+        .sum();
+    (parameter, bits)
+}
+
+/// Encoder of a FLAC stream
+pub(crate) struct FlacEncoder {
+    number_of_channels: usize,
+    sample_rate: u32,
+    bits_per_sample: u32,
+    /// quantized samples of each channel, waiting to be encoded
+    pending: Vec<Vec<i32>>,
+    /// number of the first pending sample-frame
+    sample_number: u64,
+}
+
+impl FlacEncoder {
+    /// Create an encoder of `bits_per_sample`, either 16 or 24
+    pub fn new(
+        number_of_channels: usize,
+        sample_rate: f32,
+        bits_per_sample: u32,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        if number_of_channels > MAX_CHANNELS {
+            return Err(format!(
+                "NotSupportedError - FLAC does not support {:?} channels",
+                number_of_channels
+            )
+            .into());
+        }
+        let sample_rate = sample_rate.round() as u32;
+        if sample_rate > MAX_SAMPLE_RATE {
+            return Err(format!(
+                "NotSupportedError - FLAC does not support a sample rate of {:?} Hz",
+                sample_rate
+            )
+            .into());
+        }
+
+        Ok(Self {
+            number_of_channels,
+            sample_rate,
+            bits_per_sample,
+            pending: vec![Vec::with_capacity(BLOCK_SIZE); number_of_channels],
+            sample_number: 0,
+        })
+    }
+
+    /// Append the stream marker and the STREAMINFO metadata block to `output`
+    pub fn write_header(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(b"fLaC");
+        // last metadata block, of type STREAMINFO
+        output.push(0x80);
+        output.extend_from_slice(&34_u32.to_be_bytes()[1..]);
+
+        let mut writer = BitWriter::new(std::mem::take(output));
+        writer.write(MIN_BLOCK_SIZE as u32, 16);
+        writer.write(BLOCK_SIZE as u32, 16);
+        // unknown frame sizes
+        writer.write(0, 24);
+        writer.write(0, 24);
+        writer.write(self.sample_rate, 20);
+        writer.write(self.number_of_channels as u32 - 1, 3);
+        writer.write(self.bits_per_sample - 1, 5);
+        // unknown number of samples and MD5 signature
+        writer.write(0, 4);
+        writer.write(0, 32);
+        (0..4).for_each(|_| writer.write(0, 32));
+        *output = writer.into_bytes();
+    }
+
+    /// Queue the samples of `buffer` for encoding, missing channels are silent
+    pub fn push(&mut self, buffer: &AudioBuffer) {
+        let scale = (1_i64 << (self.bits_per_sample - 1)) as f32;
+        self.pending
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, pending)| {
+                if i < buffer.number_of_channels() {
+                    pending.extend(buffer.get_channel_data(i).iter().map(|s| {
+                        // same quantization as the WAV encoder
+                        (s * scale).round().clamp(-scale, scale - 1.) as i32
+                    }));
+                } else {
+                    pending.resize(pending.len() + buffer.length(), 0);
+                }
+            });
+    }
+
+    /// Encode the pending samples into frames appended to `output`
+    ///
+    /// Full blocks are encoded, and with `flush` the remaining samples too. A remainder too
+    /// small for a block is kept pending, unless it is the `last` one of the stream.
+    pub fn encode(&mut self, output: &mut Vec<u8>, flush: bool, last: bool) {
+        loop {
+            let length = self.pending[0].len();
+            let block_size = match length {
+                0 => return,
+                l if l >= BLOCK_SIZE => BLOCK_SIZE,
+                l if last || flush && l >= MIN_BLOCK_SIZE => l,
+                _ => return,
+            };
+
+            let block: Vec<Vec<i32>> = self
+                .pending
+                .iter_mut()
+                .map(|pending| pending.drain(..block_size).collect())
+                .collect();
+            self.write_frame(&block, output);
+            self.sample_number += block_size as u64;
+        }
+    }
+
+    fn write_frame(&self, block: &[Vec<i32>], output: &mut Vec<u8>) {
+        let start = output.len();
+        let block_size = block[0].len();
+
+        // sync code, variable blocking strategy
+        output.extend_from_slice(&[0xFF, 0xF9]);
+        // 16 bits block size at the end of the header, sample rate of the STREAMINFO
+        output.push(0x70);
+        let sample_size = if self.bits_per_sample == 16 { 0x4 } else { 0x6 };
+        output.push((((self.number_of_channels - 1) as u8) << 4) | (sample_size << 1));
+        write_utf8(self.sample_number, output);
+        output.extend_from_slice(&(block_size as u16 - 1).to_be_bytes());
+        output.push(crc8(&output[start..]));
+
+        let mut writer = BitWriter::new(std::mem::take(output));
+        block
+            .iter()
+            .for_each(|samples| self.write_subframe(samples, &mut writer));
+        *output = writer.into_bytes();
+
+        let crc = crc16(&output[start..]);
+        output.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    fn write_subframe(&self, samples: &[i32], writer: &mut BitWriter) {
+        let bits_per_sample = self.bits_per_sample;
+
+        if samples.iter().all(|s| *s == samples[0]) {
+            // constant subframe
+            writer.write(0, 8);
+            writer.write(samples[0] as u32, bits_per_sample);
+            return;
+        }
+
+        // the fixed predictor of the smallest output
+        let verbatim_bits = samples.len() as u64 * u64::from(bits_per_sample);
+        let best = (0..=4)
+            .filter(|order| *order < samples.len())
+            .map(|order| {
+                let residuals = fixed_residuals(samples, order);
+                let (parameter, bits) = rice_parameter(&residuals);
+                (order, residuals, parameter, bits)
+            })
+            .min_by_key(|(order, _, _, bits)| bits + *order as u64 * u64::from(bits_per_sample))
+            .filter(|(order, _, _, bits)| {
+                bits + *order as u64 * u64::from(bits_per_sample) < verbatim_bits
+            });
+
+        match best {
+            Some((order, residuals, parameter, _)) => {
+                writer.write(0x10 | (order as u32) << 1, 8);
+                samples[..order]
+                    .iter()
+                    .for_each(|s| writer.write(*s as u32, bits_per_sample));
+                // Rice coding with 4 bits parameters, in a single partition
+                writer.write(0, 2);
+                writer.write(0, 4);
+                writer.write(parameter, 4);
+                residuals.iter().for_each(|r| {
+                    let folded = fold(*r);
+                    writer.write_unary((folded >> parameter) as u32);
+                    writer.write(folded as u32, parameter);
+                });
+            }
+            None => {
+                // verbatim subframe
+                writer.write(0x02, 8);
+                samples
+                    .iter()
+                    .for_each(|s| writer.write(*s as u32, bits_per_sample));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc() {
+        // check values of the CRC-8/SMBUS and CRC-16/UMTS algorithms
+        assert_eq!(crc8(b"123456789"), 0xF4);
+        assert_eq!(crc16(b"123456789"), 0xFEE8);
+    }
+
+    #[test]
+    fn test_utf8() {
+        let encode = |value| {
+            let mut output = vec![];
+            write_utf8(value, &mut output);
+            output
+        };
+        assert_eq!(encode(0x7F), vec![0x7F]);
+        assert_eq!(encode(0x80), vec![0xC2, 0x80]);
+        assert_eq!(encode(0xFFFF), vec![0xEF, 0xBF, 0xBF]);
+        assert_eq!(
+            encode(1 << 35),
+            vec![0xFE, 0xA0, 0x80, 0x80, 0x80, 0x80, 0x80]
+        );
+    }
+
+    #[test]
+    fn test_encode_blocks() {
+        let mut encoder = FlacEncoder::new(2, 48000., 24).unwrap();
+        let buffer = AudioBuffer::from(vec![vec![0.5; 5000], vec![-0.5; 5000]], 48000.);
+        encoder.push(&buffer);
+
+        let mut output = vec![];
+        encoder.write_header(&mut output);
+        assert_eq!(output.len(), 42);
+
+        // a full block is encoded
+        encoder.encode(&mut output, false, false);
+        assert_eq!(encoder.pending[0].len(), 904);
+        assert_eq!(encoder.sample_number, 4096);
+
+        // the remainder is encoded on flush
+        encoder.encode(&mut output, true, false);
+        assert!(encoder.pending[0].is_empty());
+        assert_eq!(encoder.sample_number, 5000);
+    }
+
+    #[test]
+    fn test_too_many_channels() {
+        assert!(FlacEncoder::new(9, 48000., 16).is_err());
+    }
+}
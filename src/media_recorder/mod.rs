@@ -6,7 +6,7 @@
 //! <https://developer.mozilla.org/en-US/docs/Web/API/MediaRecorder>
 
 use crate::media_streams::MediaStream;
-use crate::{AudioBuffer, ErrorEvent, Event};
+use crate::{AudioBuffer, ErrorEvent, Event, WavSampleFormat};
 use std::error::Error;
 
 use std::io::Write;
@@ -14,14 +14,68 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
+mod flac;
+use flac::FlacEncoder;
+
 type EventCallback = Box<dyn FnOnce(Event) + Send + 'static>;
 type BlobEventCallback = Box<dyn FnMut(BlobEvent) + Send + 'static>;
 type ErrorEventCallback = Box<dyn FnOnce(ErrorEvent) + Send + 'static>;
 
+/// Container and sample format of the recorded data
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum RecordingFormat {
+    Wav(WavSampleFormat),
+    Flac { bits_per_sample: u32 },
+}
+
+impl RecordingFormat {
+    /// Parse a MIME type such as `audio/wav` or `audio/flac;codecs=pcm16`
+    fn from_mime_type(mime_type: &str) -> Option<Self> {
+        let mut parts = mime_type.split(';').map(str::trim);
+        let container = parts.next()?.to_ascii_lowercase();
+        let codecs = parts
+            .filter_map(|parameter| parameter.split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("codecs"))
+            .map(|(_, value)| value.trim().trim_matches('"').to_ascii_lowercase());
+
+        match (container.as_str(), codecs.as_deref()) {
+            ("audio/wav" | "audio/wave" | "audio/x-wav", None | Some("float32")) => {
+                Some(Self::Wav(WavSampleFormat::Float32))
+            }
+            ("audio/wav" | "audio/wave" | "audio/x-wav", Some("pcm16")) => {
+                Some(Self::Wav(WavSampleFormat::Int16))
+            }
+            ("audio/wav" | "audio/wave" | "audio/x-wav", Some("pcm24")) => {
+                Some(Self::Wav(WavSampleFormat::Int24))
+            }
+            ("audio/wav" | "audio/wave" | "audio/x-wav", Some("pcm32")) => {
+                Some(Self::Wav(WavSampleFormat::Int32))
+            }
+            ("audio/flac" | "audio/x-flac", None | Some("flac" | "pcm24")) => Some(Self::Flac {
+                bits_per_sample: 24,
+            }),
+            ("audio/flac" | "audio/x-flac", Some("pcm16")) => Some(Self::Flac {
+                bits_per_sample: 16,
+            }),
+            _ => None,
+        }
+    }
+}
+
+enum Encoder {
+    Wav(WavSampleFormat),
+    Flac(FlacEncoder),
+}
+
 struct RecordedData {
     blob: Vec<u8>,
+    encoder: Option<Encoder>,
     start_timecode: Instant,
     current_timecode: Instant,
+    /// number of sample-frames per blob, when recording with a timeslice
+    timeslice_frames: Option<usize>,
+    /// number of sample-frames recorded since the last blob
+    recorded_frames: usize,
 }
 
 impl RecordedData {
@@ -30,37 +84,92 @@ impl RecordedData {
 
         Self {
             blob,
+            encoder: None,
             start_timecode: now,
             current_timecode: now,
+            timeslice_frames: None,
+            recorded_frames: 0,
         }
     }
 
     /// Start encoding audio into the blob buffer
-    fn encode_first(&mut self, buf: AudioBuffer) {
-        let spec = hound::WavSpec {
-            channels: buf.number_of_channels() as u16,
-            sample_rate: buf.sample_rate() as u32,
-            bits_per_sample: 32,
-            sample_format: hound::SampleFormat::Float,
+    fn encode_first(
+        &mut self,
+        buf: AudioBuffer,
+        format: RecordingFormat,
+        timeslice: Option<f64>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let encoder = match format {
+            RecordingFormat::Wav(sample_format) => {
+                let spec = hound::WavSpec {
+                    channels: buf.number_of_channels() as u16,
+                    sample_rate: buf.sample_rate() as u32,
+                    bits_per_sample: sample_format.bits_per_sample(),
+                    sample_format: match sample_format {
+                        WavSampleFormat::Float32 => hound::SampleFormat::Float,
+                        _ => hound::SampleFormat::Int,
+                    },
+                };
+                let v = spec.into_header_for_infinite_file();
+                self.blob.write_all(&v).unwrap();
+                Encoder::Wav(sample_format)
+            }
+            RecordingFormat::Flac { bits_per_sample } => {
+                let encoder =
+                    FlacEncoder::new(buf.number_of_channels(), buf.sample_rate(), bits_per_sample)?;
+                encoder.write_header(&mut self.blob);
+                Encoder::Flac(encoder)
+            }
         };
-        let v = spec.into_header_for_infinite_file();
-        self.blob.write_all(&v).unwrap();
+
+        self.encoder = Some(encoder);
+        self.timeslice_frames = timeslice
+            .map(|timeslice| ((timeslice * f64::from(buf.sample_rate())).round() as usize).max(1));
         self.encode_next(buf);
+        Ok(())
     }
 
     /// Encode subsequent buffers into the blob buffer
     fn encode_next(&mut self, buf: AudioBuffer) {
-        for i in 0..buf.length() {
-            for c in 0..buf.number_of_channels() {
-                let v = buf.get_channel_data(c)[i];
-                hound::Sample::write(v, &mut self.blob, 32).unwrap();
+        self.recorded_frames += buf.length();
+
+        match self.encoder.as_mut() {
+            Some(Encoder::Wav(sample_format)) => {
+                for i in 0..buf.length() {
+                    for c in 0..buf.number_of_channels() {
+                        let v = buf.get_channel_data(c)[i];
+                        sample_format.encode_sample(v, &mut self.blob);
+                    }
+                }
             }
+            Some(Encoder::Flac(encoder)) => {
+                encoder.push(&buf);
+                encoder.encode(&mut self.blob, false, false);
+            }
+            None => (),
+        }
+    }
+
+    /// Encode the audio still pending in the encoder, `last` at the end of the recording
+    fn flush(&mut self, last: bool) {
+        if let Some(Encoder::Flac(encoder)) = self.encoder.as_mut() {
+            encoder.encode(&mut self.blob, true, last);
+        }
+    }
+
+    /// A blob should be delivered, after a timeslice or when the buffer is large
+    fn is_due(&self) -> bool {
+        match self.timeslice_frames {
+            Some(frames) => self.recorded_frames >= frames,
+            None => self.blob.len() > 128 * 1024,
         }
     }
 }
 
 struct MediaRecorderInner {
     stream: MediaStream,
+    mime_type: String,
+    format: RecordingFormat,
     active: AtomicBool,
     recorded_data: Mutex<RecordedData>,
     data_available_callback: Mutex<Option<BlobEventCallback>>,
@@ -74,14 +183,14 @@ impl MediaRecorderInner {
 
         recorded_data.encode_next(buf);
 
-        if recorded_data.blob.len() > 128 * 1024 {
+        if recorded_data.is_due() {
             drop(recorded_data);
-            self.flush();
+            self.flush(false);
         }
     }
 
     fn handle_error(&self, error: Box<dyn Error + Send + Sync>) {
-        self.flush();
+        self.flush(true);
 
         if let Some(f) = self.error_callback.lock().unwrap().take() {
             (f)(ErrorEvent {
@@ -96,8 +205,11 @@ impl MediaRecorderInner {
         self.stop();
     }
 
-    fn flush(&self) {
+    /// Deliver the recorded data, `last` at the end of the recording
+    fn flush(&self, last: bool) {
         let mut recorded_data = self.recorded_data.lock().unwrap();
+        recorded_data.flush(last);
+        recorded_data.recorded_frames = 0;
 
         let timecode = recorded_data
             .current_timecode
@@ -126,16 +238,37 @@ impl MediaRecorderInner {
     }
 }
 
+/// Options for constructing a [`MediaRecorder`]
+#[derive(Clone, Debug)]
+pub struct MediaRecorderOptions {
+    /// The container and codec of the recording
+    ///
+    /// Supported are `audio/wav` (32 bits float), `audio/wav;codecs=pcm16`, `pcm24` or `pcm32`
+    /// for integer samples, and the lossless `audio/flac` (24 bits), or `audio/flac;codecs=pcm16`.
+    pub mime_type: String,
+}
+
+impl Default for MediaRecorderOptions {
+    fn default() -> Self {
+        Self {
+            mime_type: String::from("audio/wav"),
+        }
+    }
+}
+
 /// Record and encode media
 ///
 /// ```no_run
 /// use web_audio_api::context::AudioContext;
-/// use web_audio_api::media_recorder::MediaRecorder;
+/// use web_audio_api::media_recorder::{MediaRecorder, MediaRecorderOptions};
 ///
 /// let context = AudioContext::default();
 /// let output = context.create_media_stream_destination();
 ///
-/// let recorder = MediaRecorder::new(output.stream());
+/// let options = MediaRecorderOptions {
+///     mime_type: String::from("audio/flac"),
+/// };
+/// let recorder = MediaRecorder::new_with_options(output.stream(), options);
 /// recorder.set_ondataavailable(|event| {
 ///     println!("Received {} bytes of data", event.blob.len());
 /// });
@@ -150,12 +283,36 @@ pub struct MediaRecorder {
 }
 
 impl MediaRecorder {
+    /// Check if the recorder supports the `mime_type`, see [`MediaRecorderOptions`]
+    pub fn is_type_supported(mime_type: &str) -> bool {
+        RecordingFormat::from_mime_type(mime_type).is_some()
+    }
+
     /// Creates a new `MediaRecorder` object, given a [`MediaStream`] to record.
     ///
-    /// Only supports WAV file format currently.
+    /// The recording is encoded as `audio/wav` (32 bits float).
     pub fn new(stream: &MediaStream) -> Self {
+        Self::new_with_options(stream, MediaRecorderOptions::default())
+    }
+
+    /// Creates a new `MediaRecorder` object, given a [`MediaStream`] to record and the
+    /// [`MediaRecorderOptions`] to encode it.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the recorder does not support the MIME type of the `options`
+    pub fn new_with_options(stream: &MediaStream, options: MediaRecorderOptions) -> Self {
+        let format = RecordingFormat::from_mime_type(&options.mime_type).unwrap_or_else(|| {
+            panic!(
+                "NotSupportedError - MediaRecorder does not support MIME type {:?}",
+                options.mime_type
+            )
+        });
+
         let inner = MediaRecorderInner {
             stream: stream.clone(),
+            mime_type: options.mime_type,
+            format,
             active: AtomicBool::new(false),
             recorded_data: Mutex::new(RecordedData::new(vec![])),
             data_available_callback: Mutex::new(None),
@@ -168,6 +325,11 @@ impl MediaRecorder {
         }
     }
 
+    /// The MIME type of the recording
+    pub fn mime_type(&self) -> &str {
+        &self.inner.mime_type
+    }
+
     #[allow(clippy::missing_panics_doc)]
     pub fn set_ondataavailable<F: FnMut(BlobEvent) + Send + 'static>(&self, callback: F) {
         *self.inner.data_available_callback.lock().unwrap() = Some(Box::new(callback));
@@ -204,6 +366,26 @@ impl MediaRecorder {
     ///
     /// Will panic when the recorder has already started
     pub fn start(&self) {
+        self.start_recording(None);
+    }
+
+    /// Begin recording media, delivering the recorded data in blobs of `timeslice` seconds
+    ///
+    /// # Panics
+    ///
+    /// Will panic when:
+    /// - the recorder has already started
+    /// - the `timeslice` is not strictly positive
+    pub fn start_with_timeslice(&self, timeslice: f64) {
+        assert!(
+            timeslice > 0.,
+            "RangeError - Invalid timeslice: {:?}, should be strictly positive",
+            timeslice
+        );
+        self.start_recording(Some(timeslice));
+    }
+
+    fn start_recording(&self, timeslice: Option<f64>) {
         if self.inner.active.swap(true, Ordering::Relaxed) {
             panic!("InvalidStateError: recorder has already started")
         }
@@ -224,8 +406,12 @@ impl MediaRecorder {
             };
 
             let mut recorded_data = RecordedData::new(blob);
-            recorded_data.encode_first(buf);
+            let result = recorded_data.encode_first(buf, inner.format, timeslice);
             *inner.recorded_data.lock().unwrap() = recorded_data;
+            if let Err(error) = result {
+                inner.handle_error(error);
+                return;
+            }
 
             for item in stream_iter {
                 if !inner.active.load(Ordering::Relaxed) {
@@ -243,13 +429,25 @@ impl MediaRecorder {
                 inner.record(buf);
             }
 
-            inner.flush();
+            inner.flush(true);
             inner.stop();
         });
     }
 
+    /// Deliver the data recorded so far in a `dataavailable` event, and continue recording
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the recorder is not recording
+    pub fn request_data(&self) {
+        if !self.inner.active.load(Ordering::Relaxed) {
+            panic!("InvalidStateError: recorder is not recording")
+        }
+        self.inner.flush(false);
+    }
+
     pub fn stop(&self) {
-        self.inner.flush();
+        self.inner.flush(true);
         self.inner.stop();
     }
 }
@@ -378,4 +576,135 @@ mod tests {
         assert_float_eq!(buf.get_channel_data(0), &[1.; 1024][..], abs_all <= 0.);
         assert_float_eq!(buf.get_channel_data(1), &[-1.; 1024][..], abs_all <= 0.);
     }
+
+    /// Record the buffers, returning the blobs
+    fn record_blobs(
+        buffers: Vec<AudioBuffer>,
+        mime_type: &str,
+        timeslice: Option<f64>,
+    ) -> Vec<Vec<u8>> {
+        let track = MediaStreamTrack::from_iter(buffers.into_iter().map(Ok));
+        let stream = MediaStream::from_tracks(vec![track]);
+        let options = MediaRecorderOptions {
+            mime_type: String::from(mime_type),
+        };
+        let recorder = MediaRecorder::new_with_options(&stream, options);
+
+        let blobs: Arc<Mutex<Vec<Vec<u8>>>> = Default::default();
+        {
+            let blobs = blobs.clone();
+            recorder.set_ondataavailable(move |e| blobs.lock().unwrap().push(e.blob));
+        }
+
+        let (send, recv) = crossbeam_channel::bounded(1);
+        recorder.set_onstop(move |_| {
+            let _ = send.send(());
+        });
+
+        match timeslice {
+            Some(timeslice) => recorder.start_with_timeslice(timeslice),
+            None => recorder.start(),
+        }
+        let _ = recv.recv();
+
+        let blobs = blobs.lock().unwrap().clone();
+        blobs
+    }
+
+    fn sine_buffers() -> Vec<AudioBuffer> {
+        (0..10)
+            .map(|b| {
+                let left: Vec<f32> = (0..1000)
+                    .map(|i| ((b * 1000 + i) as f32 * 0.01).sin() * 0.5)
+                    .collect();
+                let right = left.iter().map(|v| -v).collect();
+                AudioBuffer::from(vec![left, right], 48000.)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mime_types() {
+        assert!(MediaRecorder::is_type_supported("audio/wav"));
+        assert!(MediaRecorder::is_type_supported("audio/wav; codecs=pcm24"));
+        assert!(MediaRecorder::is_type_supported("audio/flac"));
+        assert!(MediaRecorder::is_type_supported(
+            "audio/FLAC;codecs=\"pcm16\""
+        ));
+        assert!(!MediaRecorder::is_type_supported("audio/ogg;codecs=opus"));
+        assert!(!MediaRecorder::is_type_supported("audio/wav;codecs=pcm8"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unsupported_mime_type() {
+        let stream = MediaStream::from_tracks(vec![]);
+        let options = MediaRecorderOptions {
+            mime_type: String::from("audio/webm"),
+        };
+        let _ = MediaRecorder::new_with_options(&stream, options);
+    }
+
+    #[test]
+    fn test_wav_pcm24() {
+        let buffers = sine_buffers();
+        let blobs = record_blobs(buffers.clone(), "audio/wav;codecs=pcm24", None);
+        let data = blobs.concat();
+
+        let decoded = AudioBuffer::from_wav(&data[..]).unwrap();
+        assert_eq!(decoded.number_of_channels(), 2);
+        assert_eq!(decoded.length(), 10_000);
+        let expected: Vec<f32> = buffers
+            .iter()
+            .flat_map(|b| b.get_channel_data(1).to_vec())
+            .collect();
+        assert_float_eq!(
+            decoded.get_channel_data(1),
+            &expected[..],
+            abs_all <= 1. / 8_388_608.
+        );
+    }
+
+    #[test]
+    fn test_timeslice() {
+        // blobs of 2000 frames
+        let blobs = record_blobs(sine_buffers(), "audio/flac", Some(2000. / 48000.));
+        assert_eq!(blobs.len(), 6);
+        assert!(blobs[..5].iter().all(|blob| !blob.is_empty()));
+        // nothing is left for the final blob
+        assert!(blobs[5].is_empty());
+        assert_eq!(&blobs[0][..4], b"fLaC");
+    }
+
+    #[test]
+    #[cfg(feature = "flac")]
+    fn test_flac() {
+        let buffers = sine_buffers();
+
+        for (mime_type, tolerance) in [
+            ("audio/flac", 1. / 8_388_608.),
+            ("audio/flac;codecs=pcm16", 1. / 32768.),
+        ] {
+            for timeslice in [None, Some(0.01)] {
+                let data = record_blobs(buffers.clone(), mime_type, timeslice).concat();
+
+                let ctx = OfflineAudioContext::new(1, 128, 48000.);
+                let decoded = ctx.decode_audio_data_sync(Cursor::new(data)).unwrap();
+                assert_eq!(decoded.number_of_channels(), 2);
+                assert_eq!(decoded.length(), 10_000);
+
+                for c in 0..2 {
+                    let expected: Vec<f32> = buffers
+                        .iter()
+                        .flat_map(|b| b.get_channel_data(c).to_vec())
+                        .collect();
+                    assert_float_eq!(
+                        decoded.get_channel_data(c),
+                        &expected[..],
+                        abs_all <= tolerance
+                    );
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,391 @@
+//! Streaming of audio from HTTP(S) endpoints, e.g. internet radio (Icecast, SHOUTcast)
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use symphonia::core::probe::Hint;
+
+use super::{MediaStream, MediaStreamTrack, MediaTrackSettings};
+use crate::decoding::MediaDecoder;
+use crate::{AudioBuffer, AudioBufferOptions, FallibleBuffer};
+
+/// Maximum number of decoded buffers waiting for the consumer, in addition to the buffered
+/// duration. Decoding pauses when the queue is full.
+const MAX_QUEUED_BUFFERS: usize = 16;
+/// Timeout of the connection to the server
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Timeout of the reads, a stalled stream is reconnected
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Options for the streaming of audio from an HTTP(S) endpoint, see [`open_http_stream`]
+#[derive(Clone, Debug)]
+pub struct HttpStreamOptions {
+    /// Duration of audio buffered before the playback starts, and again after running out of
+    /// data, in seconds
+    pub buffer_duration: f64,
+    /// Delay before reconnecting to the server after the stream was interrupted, in seconds
+    pub reconnect_delay: f64,
+    /// Maximum number of consecutive failed reconnections before the stream ends
+    pub max_reconnects: u32,
+}
+
+impl Default for HttpStreamOptions {
+    fn default() -> Self {
+        Self {
+            buffer_duration: 2.,
+            reconnect_delay: 1.,
+            max_reconnects: 10,
+        }
+    }
+}
+
+/// Open an audio stream from the HTTP(S) endpoint at `url`, e.g. an internet radio station
+///
+/// The format (MP3, Ogg, or AAC with the `aac` feature) is detected from the content type of
+/// the response and the content itself. Live streams are reconnected when they are interrupted,
+/// the track is muted in the meantime. A stream of a finite length (e.g. a file) ends with its
+/// content. The stream is closed when the track is stopped.
+///
+/// # Errors
+///
+/// Will return an error when the endpoint cannot be reached, or the response cannot be decoded
+///
+/// # Panics
+///
+/// Will panic when the buffer duration or reconnect delay is negative
+///
+/// # Example
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::media_streams::{open_http_stream, HttpStreamOptions};
+/// use web_audio_api::node::AudioNode;
+///
+/// let url = "https://icecast.example.com/radio.mp3";
+/// let stream = open_http_stream(url, HttpStreamOptions::default()).unwrap();
+///
+/// let context = AudioContext::default();
+/// let source = context.create_media_stream_source(&stream);
+/// source.connect(&context.destination());
+/// ```
+pub fn open_http_stream(
+    url: &str,
+    options: HttpStreamOptions,
+) -> Result<MediaStream, Box<dyn Error + Send + Sync>> {
+    assert!(
+        options.buffer_duration >= 0.,
+        "RangeError - Invalid buffer duration: {:?}, should be positive",
+        options.buffer_duration
+    );
+    assert!(
+        options.reconnect_delay >= 0.,
+        "RangeError - Invalid reconnect delay: {:?}, should be positive",
+        options.reconnect_delay
+    );
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .build();
+
+    // report the errors of the first connection, the later ones are retried
+    let connection = Connection::open(&agent, url)?;
+
+    let (sender, receiver) = crossbeam_channel::bounded(MAX_QUEUED_BUFFERS);
+    let closed = Arc::new(AtomicBool::new(false));
+    let streaming = Streaming {
+        agent,
+        url: url.to_string(),
+        options: options.clone(),
+        sender,
+        closed: Arc::clone(&closed),
+    };
+    std::thread::spawn(move || streaming.run(connection));
+
+    let stream = HttpStream {
+        receiver,
+        closed,
+        buffer_duration: options.buffer_duration,
+        queue: VecDeque::new(),
+        queued: 0.,
+        playing: false,
+        finished: false,
+        number_of_channels: 1,
+        sample_rate: 48000.,
+    };
+    let settings = MediaTrackSettings {
+        latency: Some(options.buffer_duration),
+        device_id: Some(url.to_string()),
+        ..MediaTrackSettings::default()
+    };
+    let track = MediaStreamTrack::from_iter_with_settings(stream, settings);
+
+    Ok(MediaStream::from_tracks(vec![track]))
+}
+
+/// Response of the server, being decoded
+struct Connection {
+    decoder: MediaDecoder,
+    /// the response has a length, it is not a live stream
+    finite: bool,
+}
+
+impl Connection {
+    fn open(agent: &ureq::Agent, url: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let response = agent.get(url).call()?;
+
+        let mut hint = Hint::new();
+        let content_type = response.content_type();
+        // the codec of AAC streams can not be detected from their content
+        match content_type {
+            "audio/aac" | "audio/aacp" | "audio/x-aac" => hint.with_extension("aac"),
+            "audio/mpeg" | "audio/mp3" => hint.with_extension("mp3"),
+            _ => hint.mime_type(content_type),
+        };
+        let finite = response.header("Content-Length").is_some();
+        log::debug!("Streaming {} ({})", url, content_type);
+
+        let decoder = MediaDecoder::try_new_with_hint(response.into_reader(), hint)?;
+
+        Ok(Self { decoder, finite })
+    }
+}
+
+/// Thread decoding the response of the server, and reconnecting when it is interrupted
+struct Streaming {
+    agent: ureq::Agent,
+    url: String,
+    options: HttpStreamOptions,
+    sender: Sender<FallibleBuffer>,
+    /// set when the stream is dropped
+    closed: Arc<AtomicBool>,
+}
+
+impl Streaming {
+    fn run(self, connection: Connection) {
+        let mut connection = Some(connection);
+        let mut failures = 0;
+
+        while !self.closed.load(Ordering::Relaxed) {
+            let Connection { decoder, finite } = match connection.take() {
+                Some(connection) => connection,
+                None => match Connection::open(&self.agent, &self.url) {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        failures += 1;
+                        log::warn!("Reconnecting to {} failed: {}", self.url, e);
+                        if failures > self.options.max_reconnects {
+                            let _ = self.sender.send(Err(e));
+                            return;
+                        }
+                        self.wait();
+                        continue;
+                    }
+                },
+            };
+
+            for result in decoder {
+                match result {
+                    Ok(buffer) => {
+                        failures = 0;
+                        // blocks while the consumer is buffered enough, fails once it is dropped
+                        if self.sender.send(Ok(buffer)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        log::warn!("Stream of {} interrupted: {}", self.url, e);
+                        break;
+                    }
+                }
+            }
+
+            if finite {
+                log::debug!("Stream of {} ended", self.url);
+                return;
+            }
+
+            log::warn!("Stream of {} interrupted, reconnecting", self.url);
+            self.wait();
+        }
+    }
+
+    /// Wait for the reconnect delay, or until the stream is closed
+    fn wait(&self) {
+        let interval = Duration::from_millis(100);
+        let mut remaining = Duration::from_secs_f64(self.options.reconnect_delay);
+        while !remaining.is_zero() && !self.closed.load(Ordering::Relaxed) {
+            let sleep = remaining.min(interval);
+            std::thread::sleep(sleep);
+            remaining -= sleep;
+        }
+    }
+}
+
+/// Media stream of the decoded response, starting once the buffer is filled
+struct HttpStream {
+    receiver: Receiver<FallibleBuffer>,
+    closed: Arc<AtomicBool>,
+    buffer_duration: f64,
+    queue: VecDeque<AudioBuffer>,
+    /// duration of the queued buffers, in seconds
+    queued: f64,
+    playing: bool,
+    /// the stream has ended, the remaining buffers are played
+    finished: bool,
+    /// layout of the last buffer, for the empty buffers
+    number_of_channels: usize,
+    sample_rate: f32,
+}
+
+impl Drop for HttpStream {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Iterator for HttpStream {
+    type Item = FallibleBuffer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // only take the buffers needed, so the decoding keeps pace with the playback
+        while !self.finished && (self.queued < self.buffer_duration || self.queue.is_empty()) {
+            match self.receiver.try_recv() {
+                Ok(Ok(buffer)) => {
+                    self.queued += buffer.duration();
+                    self.queue.push_back(buffer);
+                }
+                Ok(Err(e)) => return Some(Err(e)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.finished = true,
+            }
+        }
+
+        if self.playing || self.finished || self.queued >= self.buffer_duration {
+            if let Some(buffer) = self.queue.pop_front() {
+                self.playing = true;
+                self.queued -= buffer.duration();
+                self.number_of_channels = buffer.number_of_channels();
+                self.sample_rate = buffer.sample_rate();
+                return Some(Ok(buffer));
+            }
+        }
+
+        if self.finished {
+            return None;
+        }
+
+        // no data, or filling the buffer again after running out of data
+        self.playing = false;
+        let options = AudioBufferOptions {
+            number_of_channels: self.number_of_channels,
+            length: 0,
+            sample_rate: self.sample_rate,
+        };
+        Some(Ok(AudioBuffer::new(options)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Instant;
+
+    /// Serve the `body` to each connection, the first `connections` times
+    fn serve(
+        body: Vec<u8>,
+        content_length: bool,
+        connections: usize,
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/stream", listener.local_addr().unwrap());
+        let count = Arc::new(AtomicUsize::new(0));
+
+        let served = Arc::clone(&count);
+        std::thread::spawn(move || {
+            for mut socket in listener.incoming().take(connections).flatten() {
+                let mut request = [0; 1024];
+                let _ = socket.read(&mut request);
+
+                let mut response = b"HTTP/1.0 200 OK\r\nContent-Type: audio/wav\r\n".to_vec();
+                if content_length {
+                    response.extend(format!("Content-Length: {}\r\n", body.len()).as_bytes());
+                }
+                response.extend(b"\r\n");
+                response.extend(&body);
+                let _ = socket.write_all(&response);
+                served.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        (url, count)
+    }
+
+    fn read_to_end(stream: &MediaStream, timeout: Duration) -> Vec<f32> {
+        let mut samples = vec![];
+        let start = Instant::now();
+        for buffer in stream.get_tracks()[0].iter() {
+            samples.extend_from_slice(buffer.unwrap().get_channel_data(0));
+            if start.elapsed() > timeout {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        samples
+    }
+
+    #[test]
+    fn test_finite_stream() {
+        let body = std::fs::read("samples/sample.wav").unwrap();
+        let expected = MediaDecoder::try_new(std::io::Cursor::new(body.clone()))
+            .unwrap()
+            .map(|buffer| buffer.unwrap().length())
+            .sum::<usize>();
+
+        let (url, _) = serve(body, true, 1);
+        let stream = open_http_stream(&url, HttpStreamOptions::default()).unwrap();
+
+        let samples = read_to_end(&stream, Duration::from_secs(10));
+        assert_eq!(samples.len(), expected);
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let body = std::fs::read("samples/sample.wav").unwrap();
+        let (url, count) = serve(body, false, 2);
+        let options = HttpStreamOptions {
+            buffer_duration: 0.,
+            reconnect_delay: 0.,
+            max_reconnects: 0,
+        };
+        let stream = open_http_stream(&url, options).unwrap();
+
+        // the stream is served twice, the third connection fails and ends the stream
+        let mut iter = stream.get_tracks()[0].iter();
+        let start = Instant::now();
+        let error = loop {
+            match iter.next() {
+                Some(Ok(_)) => assert!(start.elapsed() < Duration::from_secs(10)),
+                Some(Err(e)) => break e,
+                None => panic!("stream ended without an error"),
+            }
+        };
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+        assert!(!error.to_string().is_empty());
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/stream", listener.local_addr().unwrap());
+        drop(listener);
+
+        assert!(open_http_stream(&url, HttpStreamOptions::default()).is_err());
+    }
+}
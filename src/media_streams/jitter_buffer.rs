@@ -0,0 +1,502 @@
+//! Elastic buffering of media streams
+//!
+//! Streams from the network or from slow producers deliver their audio irregularly. Played as it
+//! arrives, they alternate between gaps of silence and bursts of backlog. The jitter buffer
+//! plays the stream at a steady pace after a target delay, and time-stretches the playback to
+//! catch up with a growing backlog or to slow down before running dry, without altering the
+//! pitch.
+use std::time::Instant;
+
+use crate::{AudioBuffer, AudioBufferOptions, FallibleBuffer};
+
+/// Distance between two consecutive grains, in sample-frames
+const HOP_SIZE: usize = 256;
+/// Length of the grains, in sample-frames
+const GRAIN_SIZE: usize = 2 * HOP_SIZE;
+/// Maximum deviation from the nominal grain position when searching for the best overlap, in
+/// sample-frames
+const SEARCH_TOLERANCE: usize = 64;
+/// Decimation factor used when computing the cross-correlation
+const SEARCH_STEP: usize = 4;
+/// Smoothing factor of the delay, per hop, filtering out the irregularities of the input
+const DELAY_SMOOTHING: f64 = 0.02;
+/// Relative deviation of the delay from the target which is tolerated without time-stretching
+const DEADBAND: f64 = 0.2;
+/// Time over which a deviation of the delay is corrected, in seconds, within the maximum stretch
+const CATCH_UP_TIME: f64 = 1.;
+/// Maximum number of hops rendered at once, after a stall of the consumer
+const MAX_HOPS: u64 = 16;
+
+/// Options for the jitter buffer of a media stream, see
+/// [`MediaStreamTrack::with_jitter_buffer`](super::MediaStreamTrack::with_jitter_buffer)
+#[derive(Clone, Debug)]
+pub struct JitterBufferOptions {
+    /// Delay of the playback, in seconds: the audio buffered to absorb the irregularities of the
+    /// stream
+    pub target_delay: f64,
+    /// Maximum delay of the playback, in seconds. A larger backlog is dropped, down to the
+    /// target delay.
+    pub max_delay: f64,
+    /// Maximum relative change of the playback speed to correct the delay, e.g. `0.1` for a
+    /// playback between 90% and 110% of the nominal speed. `0` disables the time-stretching.
+    pub max_stretch: f64,
+}
+
+impl Default for JitterBufferOptions {
+    fn default() -> Self {
+        Self {
+            target_delay: 0.05,
+            max_delay: 0.3,
+            max_stretch: 0.1,
+        }
+    }
+}
+
+impl JitterBufferOptions {
+    /// Panics on invalid options, see
+    /// [`MediaStreamTrack::with_jitter_buffer`](super::MediaStreamTrack::with_jitter_buffer)
+    pub(crate) fn validate(&self) {
+        assert!(
+            self.target_delay >= 0. && self.max_delay > self.target_delay,
+            "RangeError - Invalid target delay {:?} or max delay {:?}, should be positive with \
+            the max delay larger than the target",
+            self.target_delay,
+            self.max_delay
+        );
+        assert!(
+            (0. ..1.).contains(&self.max_stretch),
+            "RangeError - Invalid max stretch: {:?}, should be in the [0, 1[ range",
+            self.max_stretch
+        );
+    }
+}
+
+/// Media stream played at a steady pace, after buffering
+pub(crate) struct JitterBufferStream<I> {
+    input: I,
+    options: JitterBufferOptions,
+    /// the input has ended, the buffered frames are played out
+    ended: bool,
+    /// buffered frames of each channel
+    fifo: Vec<Vec<f32>>,
+    sample_rate: f32,
+    /// nominal position of the next grain in the buffered frames
+    nominal: f64,
+    /// position of the natural continuation of the previous grain
+    natural: usize,
+    /// second half of the previous grain, overlap-added to the next one
+    overlap: Vec<Vec<f32>>,
+    /// Hann window of the grains
+    window: Vec<f32>,
+    /// start of the playback, and the number of frames played since
+    playing: Option<(Instant, u64)>,
+    /// delay averaged over the recent hops, in seconds
+    smoothed_delay: f64,
+}
+
+impl<I: Iterator<Item = FallibleBuffer>> JitterBufferStream<I> {
+    pub fn new(input: I, options: JitterBufferOptions) -> Self {
+        let window = (0..GRAIN_SIZE)
+            .map(|i| {
+                let phase = 2. * std::f32::consts::PI * i as f32 / GRAIN_SIZE as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+
+        Self {
+            input,
+            options,
+            ended: false,
+            fifo: Vec::new(),
+            sample_rate: 0.,
+            nominal: 0.,
+            natural: 0,
+            overlap: Vec::new(),
+            window,
+            playing: None,
+            smoothed_delay: 0.,
+        }
+    }
+
+    /// Append a chunk of the input to the buffered frames
+    fn push(&mut self, buffer: &AudioBuffer) {
+        if buffer.number_of_channels() != self.fifo.len()
+            || buffer.sample_rate() != self.sample_rate
+        {
+            // the layout of the stream changed, restart from scratch
+            self.fifo = vec![Vec::new(); buffer.number_of_channels()];
+            self.overlap = vec![vec![0.; HOP_SIZE]; buffer.number_of_channels()];
+            self.sample_rate = buffer.sample_rate();
+            self.restart();
+        }
+
+        self.fifo
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, fifo)| fifo.extend_from_slice(buffer.get_channel_data(i)));
+    }
+
+    /// Stop the playback, it restarts once the target delay is buffered again
+    fn restart(&mut self) {
+        self.playing = None;
+        self.natural = self.nominal as usize;
+        self.nominal = self.natural as f64;
+        self.overlap.iter_mut().for_each(|o| o.fill(0.));
+    }
+
+    fn buffered_frames(&self) -> usize {
+        self.fifo.first().map(Vec::len).unwrap_or(0)
+    }
+
+    /// Buffered duration ahead of the playback, in seconds
+    fn delay(&self) -> f64 {
+        (self.buffered_frames() as f64 - self.nominal).max(0.) / f64::from(self.sample_rate)
+    }
+
+    /// The buffered frames suffice to render the next hop
+    fn can_render(&self) -> bool {
+        self.buffered_frames() >= self.nominal as usize + SEARCH_TOLERANCE + GRAIN_SIZE
+    }
+
+    /// Playback speed correcting the deviation of the delay from the target
+    fn rate(&self) -> f64 {
+        let error = self.smoothed_delay - self.options.target_delay;
+        if error.abs() <= DEADBAND * self.options.target_delay {
+            return 1.;
+        }
+
+        let max_stretch = self.options.max_stretch;
+        1. + (error / CATCH_UP_TIME).clamp(-max_stretch, max_stretch)
+    }
+
+    /// Start of the buffered grain most similar to the natural continuation of the previous one
+    fn search_grain(&self) -> usize {
+        let nominal = self.nominal as usize;
+        let natural = self.natural;
+        let reference = |i: usize| self.fifo.iter().map(|c| c[natural + i]).sum::<f32>();
+        let candidate = |s: usize, i: usize| self.fifo.iter().map(|c| c[s + i]).sum::<f32>();
+
+        let score = |s: usize| {
+            let (dot, energy) = (0..HOP_SIZE).step_by(SEARCH_STEP).fold((0., 0.), |acc, i| {
+                let c = candidate(s, i);
+                (acc.0 + reference(i) * c, acc.1 + c * c)
+            });
+            dot / (energy + f32::MIN_POSITIVE).sqrt()
+        };
+
+        let mut best = nominal;
+        let mut best_score = score(nominal);
+        let start = nominal.saturating_sub(SEARCH_TOLERANCE);
+        for s in start..=nominal + SEARCH_TOLERANCE {
+            let value = score(s);
+            if value > best_score {
+                best = s;
+                best_score = value;
+            }
+        }
+
+        best
+    }
+
+    /// Render the next hop of each channel, at the playback `rate`
+    fn render_hop(&mut self, rate: f64, output: &mut [Vec<f32>]) {
+        let start = if rate == 1. {
+            // play the buffered frames as they are
+            self.natural
+        } else {
+            self.search_grain()
+        };
+
+        let (rise, fall) = self.window.split_at(HOP_SIZE);
+        self.fifo
+            .iter()
+            .zip(self.overlap.iter_mut())
+            .zip(output.iter_mut())
+            .for_each(|((fifo, overlap), output)| {
+                let grain = &fifo[start..start + GRAIN_SIZE];
+                output.extend(
+                    overlap
+                        .iter()
+                        .zip(&grain[..HOP_SIZE])
+                        .zip(rise)
+                        .map(|((o, g), w)| o + g * w),
+                );
+                overlap
+                    .iter_mut()
+                    .zip(&grain[HOP_SIZE..])
+                    .zip(fall)
+                    .for_each(|((o, g), w)| *o = g * w);
+            });
+
+        self.natural = start + HOP_SIZE;
+        self.nominal = if rate == 1. {
+            self.natural as f64
+        } else {
+            self.nominal + HOP_SIZE as f64 * rate
+        };
+
+        // release the frames which can no longer be part of a grain
+        let consumed = self
+            .natural
+            .min(self.nominal as usize)
+            .saturating_sub(SEARCH_TOLERANCE);
+        self.fifo.iter_mut().for_each(|fifo| {
+            fifo.drain(..consumed);
+        });
+        self.natural -= consumed;
+        self.nominal -= consumed as f64;
+    }
+
+    fn empty_buffer(&self) -> AudioBuffer {
+        let options = AudioBufferOptions {
+            number_of_channels: self.fifo.len().max(1),
+            length: 0,
+            sample_rate: if self.sample_rate > 0. {
+                self.sample_rate
+            } else {
+                48000.
+            },
+        };
+        AudioBuffer::new(options)
+    }
+
+    /// The frames due for playback at the time `now`
+    fn step(&mut self, now: Instant) -> Option<FallibleBuffer> {
+        while !self.ended {
+            match self.input.next() {
+                Some(Ok(buffer)) if buffer.length() == 0 => break,
+                Some(Ok(buffer)) => self.push(&buffer),
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    // pad the stream, so the last frames can be played out
+                    self.ended = true;
+                    self.fifo
+                        .iter_mut()
+                        .for_each(|fifo| fifo.extend(vec![0.; GRAIN_SIZE + SEARCH_TOLERANCE]));
+                }
+            }
+        }
+
+        if self.fifo.is_empty() {
+            return Some(Ok(self.empty_buffer())).filter(|_| !self.ended);
+        }
+
+        let (start, played) = match self.playing {
+            Some(playing) => playing,
+            None if self.ended && !self.can_render() => return None,
+            None if self.ended
+                || self.delay() >= self.options.target_delay && self.can_render() =>
+            {
+                self.playing = Some((now, 0));
+                self.smoothed_delay = self.delay();
+                (now, 0)
+            }
+            None => return Some(Ok(self.empty_buffer())),
+        };
+
+        let due = (now.duration_since(start).as_secs_f64() * f64::from(self.sample_rate)) as u64;
+        let hops = (due.saturating_sub(played) / HOP_SIZE as u64).min(MAX_HOPS);
+        if hops == 0 {
+            return Some(Ok(self.empty_buffer()));
+        }
+
+        if self.delay() > self.options.max_delay {
+            // way too much backlog, e.g. after a stall of the producer
+            log::debug!("jitter buffer: dropping the backlog");
+            let target = self.options.target_delay * f64::from(self.sample_rate);
+            self.nominal = self.buffered_frames() as f64 - target - GRAIN_SIZE as f64;
+            self.natural = self.nominal as usize;
+        }
+
+        let mut output = vec![Vec::with_capacity(hops as usize * HOP_SIZE); self.fifo.len()];
+        for _ in 0..hops {
+            if !self.can_render() {
+                if self.ended {
+                    break;
+                }
+
+                // ran dry, fade out and buffer again
+                log::debug!("jitter buffer: buffer underrun");
+                output
+                    .iter_mut()
+                    .zip(&self.overlap)
+                    .for_each(|(output, overlap)| output.extend_from_slice(overlap));
+                self.restart();
+                return Some(Ok(AudioBuffer::from(output, self.sample_rate)));
+            }
+
+            self.smoothed_delay += (self.delay() - self.smoothed_delay) * DELAY_SMOOTHING;
+            let rate = if self.ended { 1. } else { self.rate() };
+            self.render_hop(rate, &mut output);
+        }
+
+        if output[0].is_empty() {
+            return None;
+        }
+
+        let rendered = output[0].len() as u64;
+        self.playing = Some((start, played + rendered));
+        Some(Ok(AudioBuffer::from(output, self.sample_rate)))
+    }
+}
+
+impl<I: Iterator<Item = FallibleBuffer>> Iterator for JitterBufferStream<I> {
+    type Item = FallibleBuffer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step(Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::{Receiver, Sender, TryRecvError};
+    use std::time::Duration;
+
+    const SAMPLE_RATE: f32 = 48000.;
+
+    /// Input of the chunks sent by the test, empty while no chunk is available
+    struct Producer(Receiver<AudioBuffer>);
+
+    impl Iterator for Producer {
+        type Item = FallibleBuffer;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            match self.0.try_recv() {
+                Ok(buffer) => Some(Ok(buffer)),
+                Err(TryRecvError::Empty) => Some(Ok(AudioBuffer::from(vec![vec![]], SAMPLE_RATE))),
+                Err(TryRecvError::Disconnected) => None,
+            }
+        }
+    }
+
+    fn setup(options: JitterBufferOptions) -> (Sender<AudioBuffer>, JitterBufferStream<Producer>) {
+        let (send, recv) = crossbeam_channel::unbounded();
+        (send, JitterBufferStream::new(Producer(recv), options))
+    }
+
+    fn max_error(output: &[f32], expected: &[f32]) -> f32 {
+        assert_eq!(output.len(), expected.len());
+        output
+            .iter()
+            .zip(expected)
+            .map(|(o, e)| (o - e).abs())
+            .fold(0., f32::max)
+    }
+
+    fn sine(range: std::ops::Range<usize>) -> Vec<f32> {
+        range.map(|i| (i as f32 * 0.05).sin()).collect()
+    }
+
+    /// Run the stream for `duration` seconds in steps of a render quantum, with the producer
+    /// sending a chunk of 10 ms from `offset` on each 10 ms
+    fn run(
+        send: &Sender<AudioBuffer>,
+        stream: &mut JitterBufferStream<Producer>,
+        start: Instant,
+        offset: usize,
+        duration: f64,
+    ) -> Vec<f32> {
+        let mut output = vec![];
+        let mut sent = 0;
+        let quanta = (duration * f64::from(SAMPLE_RATE) / 128.) as usize;
+        for q in 0..quanta {
+            let frame = q * 128;
+            if frame >= sent {
+                let chunk = sine(offset + sent..offset + sent + 480);
+                send.send(AudioBuffer::from(vec![chunk], SAMPLE_RATE))
+                    .unwrap();
+                sent += 480;
+            }
+            let now = start + Duration::from_secs_f64(frame as f64 / f64::from(SAMPLE_RATE));
+            let buffer = stream.step(now).unwrap().unwrap();
+            output.extend_from_slice(buffer.get_channel_data(0));
+        }
+        output
+    }
+
+    #[test]
+    fn test_steady_stream() {
+        let (send, mut stream) = setup(JitterBufferOptions::default());
+        let output = run(&send, &mut stream, Instant::now(), 0, 1.);
+
+        // the stream is played as it is, after a fade in
+        assert!(output.len() > 40_000);
+        let expected = sine(HOP_SIZE..output.len());
+        assert!(max_error(&output[HOP_SIZE..], &expected) < 1e-5);
+        assert!(
+            (stream.delay() - 0.05).abs() < 0.02,
+            "delay {}",
+            stream.delay()
+        );
+    }
+
+    #[test]
+    fn test_catch_up() {
+        let (send, mut stream) = setup(JitterBufferOptions::default());
+
+        // a backlog of 200 ms arrives at once
+        let start = Instant::now();
+        send.send(AudioBuffer::from(vec![sine(0..9600)], SAMPLE_RATE))
+            .unwrap();
+        let output = run(&send, &mut stream, start, 9600, 5.);
+
+        // the backlog is played faster, without dropping it
+        let delay = stream.delay();
+        assert!((delay - 0.05).abs() <= 0.02, "delay {}", delay);
+        let played = output.len() as f64 / f64::from(SAMPLE_RATE);
+        assert!(played > 5. - 0.01 && played < 5.01, "played {}", played);
+    }
+
+    #[test]
+    fn test_underrun() {
+        let (send, mut stream) = setup(JitterBufferOptions::default());
+        let start = Instant::now();
+        let _ = run(&send, &mut stream, start, 0, 0.5);
+
+        // the producer stalls, the playback stops once the buffer is empty
+        let later = start + Duration::from_secs(1);
+        let buffer = stream.step(later).unwrap().unwrap();
+        assert!(buffer.length() > 0);
+        assert!(stream.playing.is_none());
+        assert_eq!(stream.step(later).unwrap().unwrap().length(), 0);
+
+        // and restarts once the target delay is buffered again
+        let output = run(&send, &mut stream, later, 24_000, 0.5);
+        assert!(stream.playing.is_some());
+        assert!(output.len() > 20_000);
+    }
+
+    #[test]
+    fn test_max_delay() {
+        let (send, mut stream) = setup(JitterBufferOptions::default());
+
+        // a backlog of 1 s exceeds the max delay, and is dropped
+        send.send(AudioBuffer::from(vec![sine(0..48_000)], SAMPLE_RATE))
+            .unwrap();
+        let _ = run(&send, &mut stream, Instant::now(), 48_000, 0.1);
+        assert!(stream.delay() < 0.15, "delay {}", stream.delay());
+    }
+
+    #[test]
+    fn test_ended() {
+        let (send, mut stream) = setup(JitterBufferOptions::default());
+        send.send(AudioBuffer::from(vec![sine(0..4800)], SAMPLE_RATE))
+            .unwrap();
+        drop(send);
+
+        // the stream is played out
+        let start = Instant::now();
+        let mut output = vec![];
+        for q in 0.. {
+            let now = start + Duration::from_secs_f64(q as f64 * 128. / 48000.);
+            match stream.step(now) {
+                Some(buffer) => output.extend_from_slice(buffer.unwrap().get_channel_data(0)),
+                None => break,
+            }
+        }
+        assert!(output.len() >= 4800);
+        assert!(max_error(&output[HOP_SIZE..4800], &sine(HOP_SIZE..4800)) < 1e-5);
+    }
+}
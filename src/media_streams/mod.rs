@@ -9,10 +9,35 @@
 //!
 //! <https://developer.mozilla.org/en-US/docs/Web/API/Media_Capture_and_Streams_API>
 
-use crate::{AudioBuffer, FallibleBuffer};
-use arc_swap::ArcSwap;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use crate::events::EventDispatch;
+use crate::{AudioBuffer, AudioBufferOptions, Event, FallibleBuffer};
+use arc_swap::{ArcSwap, ArcSwapOption};
+use crossbeam_channel::Sender;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+mod jitter_buffer;
+pub use jitter_buffer::JitterBufferOptions;
+use jitter_buffer::JitterBufferStream;
+
+mod voice_activity;
+pub use voice_activity::*;
+
+#[cfg(feature = "network")]
+mod network;
+#[cfg(feature = "network")]
+pub use network::*;
+#[cfg(feature = "network")]
+mod network_sink;
+#[cfg(feature = "network")]
+pub use network_sink::*;
+
+#[cfg(feature = "http-stream")]
+mod http;
+#[cfg(feature = "http-stream")]
+pub use http::*;
 
 /// Ready-state of a [`MediaStreamTrack`]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -26,89 +51,469 @@ pub enum MediaStreamTrackState {
     Ended,
 }
 
+/// Actual settings of a [`MediaStreamTrack`], see [`MediaStreamTrack::get_settings`]
+///
+/// The settings are unknown (`None`) for tracks which are not captured from a media device.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MediaTrackSettings {
+    pub sample_rate: Option<f32>,
+    pub echo_cancellation: Option<bool>,
+    pub auto_gain_control: Option<bool>,
+    pub noise_suppression: Option<bool>,
+    pub latency: Option<f64>,
+    pub channel_count: Option<u32>,
+    pub device_id: Option<String>,
+}
+
+/// Duration of the absence of data from the source after which the track is muted
+const MUTE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Counter of the identifiers of the tracks
+static NEXT_TRACK_ID: AtomicU64 = AtomicU64::new(1);
+
+type EventCallback = Box<dyn FnMut(Event) + Send + 'static>;
+
 /// Single media track within a [`MediaStream`]
+///
+/// Cloning the struct yields another handle to the same track, use
+/// [`clone_track`](Self::clone_track) for an independent track of the same source.
 #[derive(Clone)]
 pub struct MediaStreamTrack {
     inner: Arc<MediaStreamTrackInner>,
 }
 
+/// State of a track, each clone of the track has its own
 struct MediaStreamTrackInner {
+    id: String,
+    source: Arc<MediaStreamTrackSource>,
+    enabled: AtomicBool,
+    stopped: AtomicBool,
+    mute_callback: Mutex<Option<EventCallback>>,
+    unmute_callback: Mutex<Option<EventCallback>>,
+}
+
+/// Media source shared by a track and its clones
+struct MediaStreamTrackSource {
     data: ArcSwap<FallibleBuffer>,
     position: AtomicU64,
     ended: AtomicBool,
-    provider: Mutex<Box<dyn Iterator<Item = FallibleBuffer> + Send + Sync + 'static>>,
+    muted: AtomicBool,
+    provider: Mutex<MediaStreamTrackProvider>,
+    settings: MediaTrackSettings,
+    /// number of tracks which have not been stopped
+    live_tracks: AtomicUsize,
+    /// tracks to notify of the (un)muting of the source
+    tracks: Mutex<Vec<Weak<MediaStreamTrackInner>>>,
+    /// event channel of the context rendering the source, its event thread runs the callbacks
+    event_sender: ArcSwapOption<Sender<EventDispatch>>,
+    /// source of the track this track is derived from, which is consumed along
+    parent: Option<Arc<MediaStreamTrackSource>>,
+}
+
+struct MediaStreamTrackProvider {
+    iter: Box<dyn Iterator<Item = FallibleBuffer> + Send + Sync + 'static>,
+    /// start of the absence of data, the source provides empty buffers meanwhile
+    empty_since: Option<Instant>,
+}
+
+/// (Un)muting of a track source, dispatched to the callbacks of its tracks on the event thread
+pub(crate) struct MediaStreamTrackEvent {
+    source: Arc<MediaStreamTrackSource>,
+    muted: bool,
+}
+
+impl MediaStreamTrackEvent {
+    pub(crate) fn dispatch(self) {
+        self.source.dispatch_mute(self.muted);
+    }
+}
+
+impl MediaStreamTrackSource {
+    /// Fetch the next buffer of the provider, `false` when the source has ended
+    ///
+    /// This runs on the render thread when the track is played by a context, so the callbacks
+    /// are handed to the event thread of the context.
+    fn advance(self: &Arc<Self>) -> bool {
+        let mut provider = self.provider.lock().unwrap();
+        let buf = match provider.iter.next() {
+            Some(buf) => buf,
+            None => {
+                self.ended.store(true, Ordering::Relaxed);
+                return false;
+            }
+        };
+
+        let muted = match &buf {
+            Ok(buffer) if buffer.length() == 0 => {
+                let since = *provider.empty_since.get_or_insert_with(Instant::now);
+                since.elapsed() > MUTE_TIMEOUT
+            }
+            _ => {
+                provider.empty_since = None;
+                false
+            }
+        };
+
+        let _ = self.data.swap(Arc::new(buf));
+        self.position.fetch_add(1, Ordering::Relaxed);
+        drop(provider);
+
+        if self.muted.swap(muted, Ordering::Relaxed) != muted {
+            let event = MediaStreamTrackEvent {
+                source: Arc::clone(self),
+                muted,
+            };
+            match &*self.event_sender.load() {
+                Some(sender) => {
+                    let _ = sender.send(EventDispatch::media_stream_track(event));
+                }
+                // not rendered by a context, dispatch on the consuming thread
+                None => event.dispatch(),
+            }
+        }
+
+        true
+    }
+
+    fn dispatch_mute(&self, muted: bool) {
+        let tracks: Vec<_> = self
+            .tracks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+
+        let type_ = if muted { "mute" } else { "unmute" };
+        for track in tracks {
+            let callback = if muted {
+                &track.mute_callback
+            } else {
+                &track.unmute_callback
+            };
+            if let Some(f) = callback.lock().unwrap().as_mut() {
+                (f)(Event { type_ });
+            }
+        }
+    }
+
+    /// Dispatch the events of this source and of the sources it consumes to the given channel
+    fn set_event_sender(&self, sender: &Arc<Sender<EventDispatch>>) {
+        self.event_sender.store(Some(Arc::clone(sender)));
+        if let Some(parent) = &self.parent {
+            parent.set_event_sender(sender);
+        }
+    }
+
+    /// Release the provider, e.g. the media device, when the last track is stopped
+    fn stop_track(&self) {
+        if self.live_tracks.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.provider.lock().unwrap().iter = Box::new(std::iter::empty());
+        }
+    }
 }
 
 impl MediaStreamTrack {
     #[allow(clippy::should_implement_trait)]
     pub fn from_iter<T: IntoIterator<Item = FallibleBuffer>>(iter: T) -> Self
+    where
+        <T as IntoIterator>::IntoIter: Send + Sync + 'static,
+    {
+        Self::from_iter_with_settings(iter, MediaTrackSettings::default())
+    }
+
+    /// Track of a media device, with the settings applied to the device
+    pub(crate) fn from_iter_with_settings<T: IntoIterator<Item = FallibleBuffer>>(
+        iter: T,
+        settings: MediaTrackSettings,
+    ) -> Self
+    where
+        <T as IntoIterator>::IntoIter: Send + Sync + 'static,
+    {
+        Self::from_iter_with_parent(iter, settings, None)
+    }
+
+    /// Track consuming the track of the `parent` source, or a media device without parent
+    fn from_iter_with_parent<T: IntoIterator<Item = FallibleBuffer>>(
+        iter: T,
+        settings: MediaTrackSettings,
+        parent: Option<Arc<MediaStreamTrackSource>>,
+    ) -> Self
     where
         <T as IntoIterator>::IntoIter: Send + Sync + 'static,
     {
         let initial = Ok(AudioBuffer::from(vec![vec![0.]], 48000.));
-        let inner = MediaStreamTrackInner {
+        let provider = MediaStreamTrackProvider {
+            iter: Box::new(iter.into_iter()),
+            empty_since: None,
+        };
+        let source = MediaStreamTrackSource {
             data: ArcSwap::from_pointee(initial),
             position: AtomicU64::new(0),
             ended: AtomicBool::new(false),
-            provider: Mutex::new(Box::new(iter.into_iter())),
+            muted: AtomicBool::new(false),
+            provider: Mutex::new(provider),
+            settings,
+            live_tracks: AtomicUsize::new(0),
+            tracks: Mutex::new(Vec::new()),
+            event_sender: ArcSwapOption::empty(),
+            parent,
         };
-        MediaStreamTrack {
-            inner: Arc::new(inner),
+        Self::from_source(Arc::new(source))
+    }
+
+    fn from_source(source: Arc<MediaStreamTrackSource>) -> Self {
+        source.live_tracks.fetch_add(1, Ordering::Relaxed);
+
+        let id = NEXT_TRACK_ID.fetch_add(1, Ordering::Relaxed).to_string();
+        let inner = Arc::new(MediaStreamTrackInner {
+            id,
+            source: Arc::clone(&source),
+            enabled: AtomicBool::new(true),
+            stopped: AtomicBool::new(false),
+            mute_callback: Mutex::new(None),
+            unmute_callback: Mutex::new(None),
+        });
+
+        let mut tracks = source.tracks.lock().unwrap();
+        tracks.retain(|track| track.strong_count() > 0);
+        tracks.push(Arc::downgrade(&inner));
+
+        MediaStreamTrack { inner }
+    }
+
+    /// Create an independent track of the same source, with the enabled state of this track
+    ///
+    /// This is the `clone()` method of the specification, named to not collide with [`Clone`].
+    pub fn clone_track(&self) -> Self {
+        let track = Self::from_source(Arc::clone(&self.inner.source));
+        track.set_enabled(self.enabled());
+        if self.inner.stopped.load(Ordering::Relaxed) {
+            track.stop();
+        }
+        track
+    }
+
+    /// Unique identifier of the track
+    pub fn id(&self) -> &str {
+        &self.inner.id
+    }
+
+    /// Create a track of the `channels` of this track, e.g. a stereo pair of a multichannel audio
+    /// interface
+    ///
+    /// The channels missing in the buffers of this track are left out, a buffer without any of
+    /// the channels yields a single silent channel.
+    ///
+    /// This is not part of the specification.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the range of `channels` is empty
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use web_audio_api::media_devices::{self, MediaStreamConstraints};
+    /// use web_audio_api::media_streams::MediaStream;
+    ///
+    /// let input = media_devices::get_user_media_sync(MediaStreamConstraints::Audio);
+    /// let track = &input.get_tracks()[0];
+    ///
+    /// // a track per stereo pair
+    /// let number_of_channels = track.get_settings().channel_count.unwrap_or(2) as usize;
+    /// let pairs = (0..number_of_channels)
+    ///     .step_by(2)
+    ///     .map(|c| track.select_channels(c..c + 2))
+    ///     .collect();
+    /// let stream = MediaStream::from_tracks(pairs);
+    /// ```
+    pub fn select_channels(&self, channels: Range<usize>) -> Self {
+        assert!(
+            !channels.is_empty(),
+            "RangeError - Invalid channel range: {:?}, should not be empty",
+            channels
+        );
+
+        let mut settings = self.get_settings();
+        if let Some(count) = settings.channel_count {
+            let end = channels.end.min(count as usize);
+            settings.channel_count = Some(end.saturating_sub(channels.start).max(1) as u32);
         }
+
+        let iter = self.iter().map(move |item| {
+            let buffer = item?;
+            let end = channels.end.min(buffer.number_of_channels());
+            let mut selected: Vec<Vec<f32>> = (channels.start..end)
+                .map(|c| buffer.get_channel_data(c).to_vec())
+                .collect();
+            if selected.is_empty() {
+                selected.push(vec![0.; buffer.length()]);
+            }
+            Ok(AudioBuffer::from(selected, buffer.sample_rate()))
+        });
+
+        Self::from_iter_with_parent(iter, settings, Some(Arc::clone(&self.inner.source)))
+    }
+
+    /// Create a track playing this track at a steady pace, through a jitter buffer
+    ///
+    /// The audio of this track is buffered up to the target delay before it is played. The
+    /// playback is then time-stretched to catch up with a growing backlog, or to slow down when
+    /// the buffer runs low, so a network stream or a slow producer does not alternate between
+    /// gaps of silence and bursts of backlog. When the buffer runs dry anyway, the playback
+    /// fades out and restarts once the target delay is buffered again.
+    ///
+    /// This is not part of the specification.
+    ///
+    /// # Panics
+    ///
+    /// Will panic when:
+    /// - the target delay is negative, or the max delay is not larger than the target delay
+    /// - the max stretch is outside the [0, 1[ range
+    pub fn with_jitter_buffer(&self, options: JitterBufferOptions) -> Self {
+        options.validate();
+        let iter = JitterBufferStream::new(self.iter(), options);
+        Self::from_iter_with_parent(
+            iter,
+            self.get_settings(),
+            Some(Arc::clone(&self.inner.source)),
+        )
+    }
+
+    /// The settings applied to the media device of the track, which may differ from the
+    /// requested [`MediaTrackConstraints`](crate::media_devices::MediaTrackConstraints)
+    pub fn get_settings(&self) -> MediaTrackSettings {
+        self.inner.source.settings.clone()
     }
 
     pub fn ready_state(&self) -> MediaStreamTrackState {
-        if self.inner.ended.load(Ordering::Relaxed) {
+        if self.inner.stopped.load(Ordering::Relaxed)
+            || self.inner.source.ended.load(Ordering::Relaxed)
+        {
             MediaStreamTrackState::Ended
         } else {
             MediaStreamTrackState::Live
         }
     }
 
+    /// `false` when the track is disabled and produces silence
+    pub fn enabled(&self) -> bool {
+        self.inner.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable the track, a disabled track produces silence
+    pub fn set_enabled(&self, enabled: bool) {
+        self.inner.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// `true` when the source is temporarily unable to provide data, e.g. a disconnected
+    /// microphone. The track produces silence meanwhile.
+    pub fn muted(&self) -> bool {
+        self.inner.source.muted.load(Ordering::Relaxed)
+    }
+
+    /// Register a callback to run when the source of the track is muted
+    ///
+    /// The callback runs on the event thread of the audio context playing the track, or on the
+    /// thread consuming the track when it is not played by a context.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn set_onmute<F: FnMut(Event) + Send + 'static>(&self, callback: F) {
+        *self.inner.mute_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    pub fn clear_onmute(&self) {
+        *self.inner.mute_callback.lock().unwrap() = None;
+    }
+
+    /// Register a callback to run when the source of the track is unmuted
+    ///
+    /// The callback runs on the event thread of the audio context playing the track, or on the
+    /// thread consuming the track when it is not played by a context.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn set_onunmute<F: FnMut(Event) + Send + 'static>(&self, callback: F) {
+        *self.inner.unmute_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    pub fn clear_onunmute(&self) {
+        *self.inner.unmute_callback.lock().unwrap() = None;
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = FallibleBuffer> {
         MediaStreamTrackIter {
             track: self.inner.clone(),
             position: 0,
+            silence: None,
         }
     }
 
-    #[allow(clippy::missing_panics_doc)]
+    /// Run the mute and unmute callbacks on the event thread of the context rendering the track
+    pub(crate) fn set_event_sender(&self, sender: Sender<EventDispatch>) {
+        self.inner.source.set_event_sender(&Arc::new(sender));
+    }
+
+    /// Stop the track, it ends and its consumers receive no more data
+    ///
+    /// The source, e.g. the media device, is released when all clones of the track are stopped.
+    pub fn stop(&self) {
+        if !self.inner.stopped.swap(true, Ordering::Relaxed) {
+            self.inner.source.stop_track();
+        }
+    }
+
+    /// Stop the track, see [`stop`](Self::stop)
     pub fn close(&self) {
-        // TODO, close should only close this instance but should leave clones unaltered.
-        *self.inner.provider.lock().unwrap() = Box::new(std::iter::empty());
+        self.stop();
     }
 }
 
 struct MediaStreamTrackIter {
     track: Arc<MediaStreamTrackInner>,
     position: u64,
+    /// silence yielded while the track is disabled or muted, reused while the format of the
+    /// source does not change
+    silence: Option<AudioBuffer>,
 }
 
 impl Iterator for MediaStreamTrackIter {
     type Item = FallibleBuffer;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.track.ended.load(Ordering::Relaxed) {
+        let source = &self.track.source;
+        if self.track.stopped.load(Ordering::Relaxed) || source.ended.load(Ordering::Relaxed) {
             return None;
         }
 
-        let mut stream_position = self.track.position.load(Ordering::Relaxed);
+        let mut stream_position = source.position.load(Ordering::Relaxed);
         if stream_position == self.position {
-            match self.track.provider.lock().unwrap().next() {
-                Some(buf) => {
-                    let _ = self.track.data.swap(Arc::new(buf));
-                }
-                None => {
-                    self.track.ended.store(true, Ordering::Relaxed);
-                    return None;
-                }
+            if !source.advance() {
+                return None;
             }
             stream_position += 1;
-            self.track.position.fetch_add(1, Ordering::Relaxed);
         }
 
         self.position = stream_position;
-        Some(match &self.track.data.load().as_ref() {
+        let silent =
+            !self.track.enabled.load(Ordering::Relaxed) || source.muted.load(Ordering::Relaxed);
+        Some(match &source.data.load().as_ref() {
+            Ok(buf) if silent => {
+                let reusable = matches!(&self.silence, Some(silence)
+                    if silence.number_of_channels() == buf.number_of_channels()
+                        && silence.length() == buf.length()
+                        && silence.sample_rate() == buf.sample_rate());
+                if !reusable {
+                    let options = AudioBufferOptions {
+                        number_of_channels: buf.number_of_channels(),
+                        length: buf.length(),
+                        sample_rate: buf.sample_rate(),
+                    };
+                    self.silence = Some(AudioBuffer::new(options));
+                }
+                Ok(self.silence.clone().unwrap())
+            }
             Ok(buf) => Ok(buf.clone()),
             Err(e) => Err(e.to_string().into()),
         })
@@ -132,6 +537,28 @@ impl MediaStream {
     pub fn get_tracks(&self) -> &[MediaStreamTrack] {
         &self.tracks
     }
+
+    /// The audio tracks of the stream, which are all tracks as video is not supported
+    pub fn get_audio_tracks(&self) -> &[MediaStreamTrack] {
+        &self.tracks
+    }
+
+    /// The track with the identifier `id`, if any
+    pub fn get_track_by_id(&self, id: &str) -> Option<&MediaStreamTrack> {
+        self.tracks.iter().find(|track| track.id() == id)
+    }
+
+    /// Add the `track` to the stream, unless it is already part of it
+    pub fn add_track(&mut self, track: MediaStreamTrack) {
+        if self.get_track_by_id(track.id()).is_none() {
+            self.tracks.push(track);
+        }
+    }
+
+    /// Remove the `track` from the stream, if it is part of it
+    pub fn remove_track(&mut self, track: &MediaStreamTrack) {
+        self.tracks.retain(|t| t.id() != track.id());
+    }
 }
 
 #[cfg(test)]
@@ -240,4 +667,227 @@ mod tests {
         track.close();
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn test_enabled() {
+        let buffers = vec![
+            Ok(AudioBuffer::from(vec![vec![1.]], 48000.)),
+            Ok(AudioBuffer::from(vec![vec![2.]], 48000.)),
+        ];
+        let track = MediaStreamTrack::from_iter(buffers);
+        let mut iter = track.iter();
+
+        track.set_enabled(false);
+        assert!(!track.enabled());
+        assert_float_eq!(
+            iter.next().unwrap().unwrap().get_channel_data(0)[..],
+            [0.][..],
+            abs_all <= 0.
+        );
+
+        track.set_enabled(true);
+        assert_float_eq!(
+            iter.next().unwrap().unwrap().get_channel_data(0)[..],
+            [2.][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_clone_track() {
+        let source = Arc::new(());
+        let source_clone = Arc::clone(&source);
+        let buffers = std::iter::repeat_with(move || {
+            let _ = &source_clone;
+            Ok(AudioBuffer::from(vec![vec![1.]], 48000.))
+        });
+        let track = MediaStreamTrack::from_iter(buffers);
+        let clone = track.clone_track();
+        let mut iter = track.iter();
+        let mut clone_iter = clone.iter();
+
+        // the clones are enabled independently
+        clone.set_enabled(false);
+        assert_float_eq!(
+            iter.next().unwrap().unwrap().get_channel_data(0)[..],
+            [1.][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            clone_iter.next().unwrap().unwrap().get_channel_data(0)[..],
+            [0.][..],
+            abs_all <= 0.
+        );
+
+        // and stopped independently
+        track.stop();
+        assert_eq!(track.ready_state(), MediaStreamTrackState::Ended);
+        assert!(iter.next().is_none());
+        assert_eq!(clone.ready_state(), MediaStreamTrackState::Live);
+        assert!(clone_iter.next().is_some());
+        assert_eq!(Arc::strong_count(&source), 2);
+
+        // the source is released with the last track
+        clone.stop();
+        assert!(clone_iter.next().is_none());
+        assert_eq!(Arc::strong_count(&source), 1);
+    }
+
+    #[test]
+    fn test_muted() {
+        let available = Arc::new(AtomicBool::new(true));
+        let available_clone = Arc::clone(&available);
+        let buffers = std::iter::repeat_with(move || {
+            let length = if available_clone.load(Ordering::Relaxed) {
+                1
+            } else {
+                0
+            };
+            Ok(AudioBuffer::from(vec![vec![1.; length]], 48000.))
+        });
+        let track = MediaStreamTrack::from_iter(buffers);
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let events_clone = Arc::clone(&events);
+        track.set_onmute(move |e| events_clone.lock().unwrap().push(e.type_));
+        let events_clone = Arc::clone(&events);
+        track.set_onunmute(move |e| events_clone.lock().unwrap().push(e.type_));
+
+        let mut iter = track.iter();
+        assert!(iter.next().is_some());
+        assert!(!track.muted());
+
+        // the source provides no data
+        available.store(false, Ordering::Relaxed);
+        assert!(iter.next().is_some());
+        std::thread::sleep(MUTE_TIMEOUT + Duration::from_millis(50));
+        assert!(iter.next().is_some());
+        assert!(track.muted());
+
+        available.store(true, Ordering::Relaxed);
+        assert!(iter.next().is_some());
+        assert!(!track.muted());
+        assert_eq!(*events.lock().unwrap(), ["mute", "unmute"]);
+    }
+
+    #[test]
+    fn test_muted_event_thread() {
+        let available = Arc::new(AtomicBool::new(false));
+        let available_clone = Arc::clone(&available);
+        let buffers = std::iter::repeat_with(move || {
+            let length = if available_clone.load(Ordering::Relaxed) {
+                1
+            } else {
+                0
+            };
+            Ok(AudioBuffer::from(vec![vec![1.; length]], 48000.))
+        });
+        let source = MediaStreamTrack::from_iter(buffers);
+        let track = source.with_jitter_buffer(JitterBufferOptions::default());
+
+        // the callbacks of the source consumed by the jitter buffer run on the event thread
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        crate::events::EventLoop::new().run(receiver);
+        track.set_event_sender(sender);
+
+        let (event_send, event_recv) = crossbeam_channel::unbounded();
+        source.set_onmute(move |e| {
+            event_send
+                .send((e.type_, std::thread::current().id()))
+                .unwrap()
+        });
+
+        let mut iter = source.iter();
+        assert!(iter.next().is_some());
+        std::thread::sleep(MUTE_TIMEOUT + Duration::from_millis(50));
+        assert!(iter.next().is_some());
+        assert!(source.muted());
+
+        let (type_, thread) = event_recv.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(type_, "mute");
+        assert_ne!(thread, std::thread::current().id());
+    }
+
+    #[test]
+    fn test_reuse_silence() {
+        let buffers = std::iter::repeat_with(|| Ok(AudioBuffer::from(vec![vec![1.; 128]], 48000.)));
+        let track = MediaStreamTrack::from_iter(buffers);
+        track.set_enabled(false);
+
+        let mut iter = track.iter();
+        let first = iter.next().unwrap().unwrap();
+        let second = iter.next().unwrap().unwrap();
+        assert_eq!(first.get_channel_data(0), &[0.; 128][..]);
+        assert_eq!(
+            first.get_channel_data(0).as_ptr(),
+            second.get_channel_data(0).as_ptr()
+        );
+    }
+
+    #[test]
+    fn test_tracks() {
+        let first = MediaStreamTrack::from_iter(std::iter::empty());
+        let second = first.clone_track();
+        assert_ne!(first.id(), second.id());
+
+        let mut stream = MediaStream::from_tracks(vec![first.clone()]);
+        stream.add_track(second.clone());
+        stream.add_track(first.clone());
+        assert_eq!(stream.get_audio_tracks().len(), 2);
+        assert_eq!(
+            stream.get_track_by_id(second.id()).unwrap().id(),
+            second.id()
+        );
+
+        stream.remove_track(&first);
+        assert_eq!(stream.get_tracks().len(), 1);
+        assert!(stream.get_track_by_id(first.id()).is_none());
+    }
+
+    #[test]
+    fn test_select_channels() {
+        let buffers = vec![
+            Ok(AudioBuffer::from(
+                vec![vec![1.], vec![2.], vec![3.]],
+                48000.,
+            )),
+            Ok(AudioBuffer::from(vec![vec![1.]], 48000.)),
+        ];
+        let track = MediaStreamTrack::from_iter_with_settings(
+            buffers,
+            MediaTrackSettings {
+                channel_count: Some(3),
+                ..MediaTrackSettings::default()
+            },
+        );
+
+        let pair = track.select_channels(1..3);
+        assert_eq!(pair.get_settings().channel_count, Some(2));
+
+        let mut iter = pair.iter();
+        let buffer = iter.next().unwrap().unwrap();
+        assert_eq!(buffer.number_of_channels(), 2);
+        assert_float_eq!(buffer.get_channel_data(0)[..], [2.][..], abs_all <= 0.);
+        assert_float_eq!(buffer.get_channel_data(1)[..], [3.][..], abs_all <= 0.);
+
+        // the channels are missing in the second buffer
+        let buffer = iter.next().unwrap().unwrap();
+        assert_eq!(buffer.number_of_channels(), 1);
+        assert_float_eq!(buffer.get_channel_data(0)[..], [0.][..], abs_all <= 0.);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_settings() {
+        let track = MediaStreamTrack::from_iter(std::iter::empty());
+        assert_eq!(track.get_settings(), MediaTrackSettings::default());
+
+        let settings = MediaTrackSettings {
+            sample_rate: Some(48000.),
+            channel_count: Some(1),
+            ..MediaTrackSettings::default()
+        };
+        let track = MediaStreamTrack::from_iter_with_settings(std::iter::empty(), settings.clone());
+        assert_eq!(track.clone().get_settings(), settings);
+    }
 }
@@ -0,0 +1,778 @@
+//! Reception of audio streams from the network
+//!
+//! Remote audio is received over UDP, either as RTP packets (RFC 3550) or as raw datagrams of
+//! samples, and exposed as a [`MediaStream`] so it can be mixed into a local audio graph.
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+
+use super::{MediaStream, MediaStreamTrack, MediaTrackSettings};
+use crate::{AudioBuffer, AudioBufferOptions, FallibleBuffer};
+
+/// Maximum size of a datagram
+const MAX_DATAGRAM_SIZE: usize = 65_536;
+/// Interval at which the receiving thread checks if the stream is still in use
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+/// Maximum number of decoded packets waiting for the consumer, later packets are dropped
+const MAX_QUEUED_PACKETS: usize = 256;
+/// Maximum number of packets held for reordering
+const MAX_REORDERED_PACKETS: usize = 256;
+/// Sample rate of the RTP clock of Opus, and of the decoded audio
+#[cfg(feature = "opus")]
+const OPUS_SAMPLE_RATE: f32 = 48_000.;
+/// Maximum duration of an Opus packet (120 ms), in samples per channel
+#[cfg(feature = "opus")]
+const OPUS_MAX_FRAME_SIZE: usize = 5760;
+
+/// Encoding of the audio received from the network
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum NetworkAudioFormat {
+    /// RTP packets of interleaved 16-bit big-endian samples (`L16`, RFC 3551)
+    RtpL16 {
+        sample_rate: f32,
+        number_of_channels: usize,
+    },
+    /// RTP packets of Opus frames (RFC 7587), decoded at 48 kHz
+    #[cfg(feature = "opus")]
+    RtpOpus { number_of_channels: usize },
+    /// Datagrams of interleaved 32-bit float little-endian samples, without any header
+    ///
+    /// The datagrams are played in order of arrival, lost datagrams are not concealed.
+    RawPcm {
+        sample_rate: f32,
+        number_of_channels: usize,
+    },
+}
+
+impl NetworkAudioFormat {
+    fn sample_rate(&self) -> f32 {
+        match *self {
+            Self::RtpL16 { sample_rate, .. } | Self::RawPcm { sample_rate, .. } => sample_rate,
+            #[cfg(feature = "opus")]
+            Self::RtpOpus { .. } => OPUS_SAMPLE_RATE,
+        }
+    }
+
+    fn number_of_channels(&self) -> usize {
+        match *self {
+            Self::RtpL16 {
+                number_of_channels, ..
+            }
+            | Self::RawPcm {
+                number_of_channels, ..
+            } => number_of_channels,
+            #[cfg(feature = "opus")]
+            Self::RtpOpus { number_of_channels } => number_of_channels,
+        }
+    }
+}
+
+/// Options for the reception of an audio stream from the network, see [`receive_network_stream`]
+#[derive(Clone, Debug)]
+pub struct NetworkStreamOptions {
+    pub format: NetworkAudioFormat,
+    /// Duration of the jitter buffer, in seconds. Late packets are waited for during this
+    /// duration, and the playback starts once this duration of audio is received.
+    pub jitter_buffer: f64,
+}
+
+impl NetworkStreamOptions {
+    pub fn new(format: NetworkAudioFormat) -> Self {
+        Self {
+            format,
+            jitter_buffer: 0.06,
+        }
+    }
+}
+
+/// Receive an audio stream on the `socket`, e.g. from a remote microphone
+///
+/// The stream contains a single track, which is muted while no packets are received. The
+/// reception stops when the track is stopped.
+///
+/// # Errors
+///
+/// Will return an error when the read timeout of the socket cannot be set
+///
+/// # Panics
+///
+/// Will panic when the sample rate or number of channels of the format is not supported, or the
+/// duration of the jitter buffer is negative
+///
+/// # Example
+///
+/// ```no_run
+/// use std::net::UdpSocket;
+///
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::media_streams::{
+///     receive_network_stream, NetworkAudioFormat, NetworkStreamOptions,
+/// };
+/// use web_audio_api::node::AudioNode;
+///
+/// let socket = UdpSocket::bind("0.0.0.0:5004").unwrap();
+/// let format = NetworkAudioFormat::RtpL16 {
+///     sample_rate: 48000.,
+///     number_of_channels: 2,
+/// };
+/// let stream = receive_network_stream(socket, NetworkStreamOptions::new(format)).unwrap();
+///
+/// let context = AudioContext::default();
+/// let source = context.create_media_stream_source(&stream);
+/// source.connect(&context.destination());
+/// ```
+pub fn receive_network_stream(
+    socket: UdpSocket,
+    options: NetworkStreamOptions,
+) -> std::io::Result<MediaStream> {
+    let sample_rate = options.format.sample_rate();
+    let number_of_channels = options.format.number_of_channels();
+    crate::assert_valid_sample_rate(sample_rate);
+    crate::assert_valid_number_of_channels(number_of_channels);
+    #[cfg(feature = "opus")]
+    if let NetworkAudioFormat::RtpOpus { number_of_channels } = options.format {
+        assert!(
+            number_of_channels <= 2,
+            "NotSupportedError - Invalid number of channels: {:?}, Opus streams are mono or stereo",
+            number_of_channels
+        );
+    }
+    assert!(
+        options.jitter_buffer >= 0.,
+        "RangeError - Invalid jitter buffer duration: {:?}, should be positive",
+        options.jitter_buffer
+    );
+
+    socket.set_read_timeout(Some(READ_TIMEOUT))?;
+    let settings = MediaTrackSettings {
+        sample_rate: Some(sample_rate),
+        latency: Some(options.jitter_buffer),
+        channel_count: Some(number_of_channels as u32),
+        device_id: socket.local_addr().ok().map(|addr| addr.to_string()),
+        ..MediaTrackSettings::default()
+    };
+
+    let (sender, receiver) = crossbeam_channel::bounded(MAX_QUEUED_PACKETS);
+    let closed = Arc::new(AtomicBool::new(false));
+    let receiving = Receiving {
+        socket,
+        format: options.format,
+        jitter_buffer: options.jitter_buffer,
+        sender,
+        closed: Arc::clone(&closed),
+    };
+    std::thread::spawn(move || receiving.run());
+
+    let prebuffer = (options.jitter_buffer * f64::from(sample_rate)) as usize;
+    let stream = NetworkStream {
+        receiver,
+        closed,
+        number_of_channels,
+        sample_rate,
+        prebuffer,
+        queue: VecDeque::new(),
+        queued: 0,
+        playing: false,
+    };
+    let track = MediaStreamTrack::from_iter_with_settings(stream, settings);
+
+    Ok(MediaStream::from_tracks(vec![track]))
+}
+
+/// Header fields and payload of an RTP packet
+#[derive(Debug, PartialEq)]
+struct RtpPacket<'a> {
+    sequence_number: u16,
+    timestamp: u32,
+    ssrc: u32,
+    payload: &'a [u8],
+}
+
+/// Parse an RTP packet, `None` if it is malformed
+fn parse_rtp(packet: &[u8]) -> Option<RtpPacket<'_>> {
+    if packet.len() < 12 || packet[0] >> 6 != 2 {
+        return None;
+    }
+
+    let padding = packet[0] & 0x20 != 0;
+    let extension = packet[0] & 0x10 != 0;
+    let csrc_count = usize::from(packet[0] & 0x0f);
+
+    let mut start = 12 + 4 * csrc_count;
+    if extension {
+        let header = packet.get(start..start + 4)?;
+        let length = usize::from(u16::from_be_bytes([header[2], header[3]]));
+        start += 4 + 4 * length;
+    }
+
+    let mut end = packet.len();
+    if padding {
+        end = end.checked_sub(usize::from(packet[end - 1]))?;
+    }
+
+    Some(RtpPacket {
+        sequence_number: u16::from_be_bytes([packet[2], packet[3]]),
+        timestamp: u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]),
+        ssrc: u32::from_be_bytes([packet[8], packet[9], packet[10], packet[11]]),
+        payload: packet.get(start..end)?,
+    })
+}
+
+/// Next step of the playout of the jitter buffer
+#[derive(Debug, PartialEq)]
+enum Playout {
+    /// Payload of the next packet
+    Packet(Vec<u8>),
+    /// Number of samples per channel of the lost packets to conceal
+    Lost(usize),
+}
+
+/// Reorders the RTP packets, and detects the lost ones
+struct JitterBuffer {
+    /// waiting packets, by extended sequence number
+    packets: BTreeMap<u64, (u32, Vec<u8>)>,
+    /// extended sequence number of the next packet to play, `None` before the first packet
+    next: Option<u64>,
+    /// highest extended sequence number received
+    highest: u64,
+    /// timestamp of the packet with the highest sequence number
+    newest_timestamp: u32,
+    /// timestamp at which the next packet is expected
+    next_timestamp: u32,
+    /// duration the packets wait for the missing ones, in units of the RTP clock
+    delay: u32,
+    /// maximum duration of the lost packets, longer gaps are a discontinuity of the stream
+    max_gap: u32,
+}
+
+impl JitterBuffer {
+    fn new(delay: u32, max_gap: u32) -> Self {
+        Self {
+            packets: BTreeMap::new(),
+            next: None,
+            highest: 0,
+            newest_timestamp: 0,
+            next_timestamp: 0,
+            delay,
+            max_gap,
+        }
+    }
+
+    /// Extend the 16-bit sequence number, to the one closest to the highest received
+    fn extend(&self, sequence_number: u16) -> u64 {
+        let candidate = (self.highest & !0xffff) | u64::from(sequence_number);
+        [
+            candidate.wrapping_sub(0x1_0000),
+            candidate,
+            candidate + 0x1_0000,
+        ]
+        .iter()
+        .copied()
+        .min_by_key(|c| c.abs_diff(self.highest))
+        .unwrap()
+    }
+
+    fn push(&mut self, sequence_number: u16, timestamp: u32, payload: &[u8]) {
+        let extended = match self.next {
+            Some(_) => self.extend(sequence_number),
+            None => {
+                // start well away from zero, so earlier packets can still be extended
+                let extended = (1 << 32) | u64::from(sequence_number);
+                self.next = Some(extended);
+                self.highest = extended;
+                self.newest_timestamp = timestamp;
+                self.next_timestamp = timestamp;
+                extended
+            }
+        };
+
+        // too late, or a duplicate
+        if extended < self.next.unwrap() || self.packets.contains_key(&extended) {
+            return;
+        }
+
+        if extended >= self.highest {
+            self.highest = extended;
+            self.newest_timestamp = timestamp;
+        }
+        self.packets.insert(extended, (timestamp, payload.to_vec()));
+
+        // give up on the missing packets rather than growing without bounds
+        if self.packets.len() > MAX_REORDERED_PACKETS {
+            self.delay = 0;
+        }
+    }
+
+    /// The next packet to play, or the samples to conceal before it
+    fn pop(&mut self) -> Option<Playout> {
+        let next = self.next?;
+        let (&extended, &(timestamp, _)) = self.packets.iter().next()?;
+
+        if extended == next {
+            let (_, payload) = self.packets.remove(&extended).unwrap();
+            self.next = Some(next + 1);
+            self.next_timestamp = timestamp;
+            return Some(Playout::Packet(payload));
+        }
+
+        // the missing packets are lost once the later ones have waited long enough
+        let waited = self.newest_timestamp.wrapping_sub(timestamp);
+        if waited >= self.delay || self.packets.len() > MAX_REORDERED_PACKETS {
+            self.next = Some(extended);
+            let gap = timestamp.wrapping_sub(self.next_timestamp);
+            if gap > 0 && gap <= self.max_gap {
+                self.next_timestamp = timestamp;
+                return Some(Playout::Lost(gap as usize));
+            }
+            return self.pop();
+        }
+
+        None
+    }
+
+    /// Advance the expected timestamp by the samples of the played packet
+    fn advance(&mut self, length: usize) {
+        self.next_timestamp = self.next_timestamp.wrapping_add(length as u32);
+    }
+}
+
+/// Decoder of the payloads of a format
+trait PayloadDecoder: Send {
+    /// Decode the samples of each channel of the `payload`
+    fn decode(&mut self, payload: &[u8]) -> Result<Vec<Vec<f32>>, Box<dyn Error + Send + Sync>>;
+
+    /// Samples of each channel replacing `length` lost samples per channel
+    fn conceal(&mut self, length: usize) -> Vec<Vec<f32>>;
+}
+
+/// Split interleaved samples into channels
+fn deinterleave(samples: impl Iterator<Item = f32>, number_of_channels: usize) -> Vec<Vec<f32>> {
+    let mut channels = vec![Vec::new(); number_of_channels];
+    (0..number_of_channels)
+        .cycle()
+        .zip(samples)
+        .for_each(|(i, sample)| channels[i].push(sample));
+
+    // drop the incomplete last frame
+    let length = channels.iter().map(Vec::len).min().unwrap_or(0);
+    channels
+        .iter_mut()
+        .for_each(|channel| channel.truncate(length));
+    channels
+}
+
+struct L16Decoder {
+    number_of_channels: usize,
+}
+
+impl PayloadDecoder for L16Decoder {
+    fn decode(&mut self, payload: &[u8]) -> Result<Vec<Vec<f32>>, Box<dyn Error + Send + Sync>> {
+        let samples = payload
+            .chunks_exact(2)
+            .map(|b| f32::from(i16::from_be_bytes([b[0], b[1]])) / 32768.);
+        Ok(deinterleave(samples, self.number_of_channels))
+    }
+
+    fn conceal(&mut self, length: usize) -> Vec<Vec<f32>> {
+        vec![vec![0.; length]; self.number_of_channels]
+    }
+}
+
+struct RawPcmDecoder {
+    number_of_channels: usize,
+}
+
+impl PayloadDecoder for RawPcmDecoder {
+    fn decode(&mut self, payload: &[u8]) -> Result<Vec<Vec<f32>>, Box<dyn Error + Send + Sync>> {
+        let samples = payload
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+        Ok(deinterleave(samples, self.number_of_channels))
+    }
+
+    fn conceal(&mut self, length: usize) -> Vec<Vec<f32>> {
+        vec![vec![0.; length]; self.number_of_channels]
+    }
+}
+
+#[cfg(feature = "opus")]
+struct OpusDecoder {
+    decoder: audiopus::coder::Decoder,
+    number_of_channels: usize,
+    output: Vec<f32>,
+}
+
+#[cfg(feature = "opus")]
+impl OpusDecoder {
+    fn new(number_of_channels: usize) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let channels = if number_of_channels == 1 {
+            audiopus::Channels::Mono
+        } else {
+            audiopus::Channels::Stereo
+        };
+        let decoder = audiopus::coder::Decoder::new(audiopus::SampleRate::Hz48000, channels)?;
+
+        Ok(Self {
+            decoder,
+            number_of_channels,
+            output: vec![0.; OPUS_MAX_FRAME_SIZE * number_of_channels],
+        })
+    }
+}
+
+#[cfg(feature = "opus")]
+impl PayloadDecoder for OpusDecoder {
+    fn decode(&mut self, payload: &[u8]) -> Result<Vec<Vec<f32>>, Box<dyn Error + Send + Sync>> {
+        use std::convert::TryFrom;
+
+        let packet = audiopus::packet::Packet::try_from(payload)?;
+        let signals = audiopus::MutSignals::try_from(&mut self.output[..])?;
+        let length = self.decoder.decode_float(Some(packet), signals, false)?;
+
+        let samples = self.output[..length * self.number_of_channels]
+            .iter()
+            .copied();
+        Ok(deinterleave(samples, self.number_of_channels))
+    }
+
+    fn conceal(&mut self, length: usize) -> Vec<Vec<f32>> {
+        use std::convert::TryFrom;
+
+        // packet loss concealment of the decoder, in packets of at most the maximum duration
+        let mut channels = vec![Vec::with_capacity(length); self.number_of_channels];
+        let mut remaining = length;
+        while remaining > 0 {
+            let frame_size = remaining.min(OPUS_MAX_FRAME_SIZE);
+            let output = &mut self.output[..frame_size * self.number_of_channels];
+            let concealed = audiopus::MutSignals::try_from(&mut *output)
+                .ok()
+                .and_then(|signals| self.decoder.decode_float(None, signals, false).ok());
+            if concealed.is_none() {
+                output.fill(0.);
+            }
+
+            let frame = deinterleave(output.iter().copied(), self.number_of_channels);
+            channels
+                .iter_mut()
+                .zip(frame)
+                .for_each(|(channel, frame)| channel.extend(frame));
+            remaining -= frame_size;
+        }
+
+        channels
+    }
+}
+
+/// Thread receiving the datagrams of the socket
+struct Receiving {
+    socket: UdpSocket,
+    format: NetworkAudioFormat,
+    jitter_buffer: f64,
+    sender: Sender<FallibleBuffer>,
+    /// set when the stream is dropped
+    closed: Arc<AtomicBool>,
+}
+
+impl Receiving {
+    fn run(self) {
+        let sample_rate = self.format.sample_rate();
+        let number_of_channels = self.format.number_of_channels();
+
+        let (mut decoder, rtp): (Box<dyn PayloadDecoder>, bool) = match self.format {
+            NetworkAudioFormat::RtpL16 { .. } => {
+                (Box::new(L16Decoder { number_of_channels }), true)
+            }
+            NetworkAudioFormat::RawPcm { .. } => {
+                (Box::new(RawPcmDecoder { number_of_channels }), false)
+            }
+            #[cfg(feature = "opus")]
+            NetworkAudioFormat::RtpOpus { .. } => match OpusDecoder::new(number_of_channels) {
+                Ok(decoder) => (Box::new(decoder), true),
+                Err(e) => {
+                    let _ = self.sender.send(Err(e));
+                    return;
+                }
+            },
+        };
+
+        // the RTP clock of the supported formats runs at the sample rate
+        let delay = (self.jitter_buffer * f64::from(sample_rate)) as u32;
+        let max_gap = sample_rate as u32;
+        let mut jitter_buffer = JitterBuffer::new(delay, max_gap);
+        let mut ssrc = None;
+
+        let mut datagram = vec![0; MAX_DATAGRAM_SIZE];
+        while !self.closed.load(Ordering::Relaxed) {
+            let len = match self.socket.recv(&mut datagram) {
+                Ok(len) => len,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    continue
+                }
+                Err(e) => {
+                    log::warn!("Network stream failed: {}", e);
+                    let _ = self.sender.send(Err(Box::new(e)));
+                    return;
+                }
+            };
+
+            if !rtp {
+                match decoder.decode(&datagram[..len]) {
+                    Ok(channels) => self.send(channels, sample_rate),
+                    Err(e) => log::warn!("Dropped undecodable datagram: {}", e),
+                }
+                continue;
+            }
+
+            let packet = match parse_rtp(&datagram[..len]) {
+                Some(packet) => packet,
+                None => {
+                    log::debug!("Dropped malformed RTP packet");
+                    continue;
+                }
+            };
+
+            // a new source restarts the stream
+            if matches!(ssrc.replace(packet.ssrc), Some(s) if s != packet.ssrc) {
+                jitter_buffer = JitterBuffer::new(delay, max_gap);
+            }
+            jitter_buffer.push(packet.sequence_number, packet.timestamp, packet.payload);
+
+            while let Some(playout) = jitter_buffer.pop() {
+                let channels = match playout {
+                    Playout::Packet(payload) => match decoder.decode(&payload) {
+                        Ok(channels) => channels,
+                        Err(e) => {
+                            log::warn!("Dropped undecodable RTP packet: {}", e);
+                            continue;
+                        }
+                    },
+                    Playout::Lost(length) => decoder.conceal(length),
+                };
+                jitter_buffer.advance(channels[0].len());
+                self.send(channels, sample_rate);
+            }
+        }
+    }
+
+    fn send(&self, channels: Vec<Vec<f32>>, sample_rate: f32) {
+        if channels[0].is_empty() {
+            return;
+        }
+
+        // can fail when the consumer is not keeping up (packet dropped)
+        let result = self
+            .sender
+            .try_send(Ok(AudioBuffer::from(channels, sample_rate)));
+        if result.is_err() {
+            log::debug!("Network stream: dropping packet, the queue is full");
+        }
+    }
+}
+
+/// Media stream of the received packets, starting once the jitter buffer is filled
+struct NetworkStream {
+    receiver: Receiver<FallibleBuffer>,
+    closed: Arc<AtomicBool>,
+    number_of_channels: usize,
+    sample_rate: f32,
+    /// number of samples per channel received before the playback starts
+    prebuffer: usize,
+    queue: VecDeque<AudioBuffer>,
+    /// number of samples per channel in the queue
+    queued: usize,
+    playing: bool,
+}
+
+impl Drop for NetworkStream {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Iterator for NetworkStream {
+    type Item = FallibleBuffer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Ok(buffer)) => {
+                    self.queued += buffer.length();
+                    self.queue.push_back(buffer);
+                }
+                Ok(Err(e)) => return Some(Err(e)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) if self.queue.is_empty() => return None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+
+        if self.playing || self.queued >= self.prebuffer.max(1) {
+            if let Some(buffer) = self.queue.pop_front() {
+                self.playing = true;
+                self.queued -= buffer.length();
+                return Some(Ok(buffer));
+            }
+        }
+
+        // no data, or filling the jitter buffer again after running out of data
+        self.playing = false;
+        let options = AudioBufferOptions {
+            number_of_channels: self.number_of_channels,
+            length: 0,
+            sample_rate: self.sample_rate,
+        };
+        Some(Ok(AudioBuffer::new(options)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use float_eq::assert_float_eq;
+    use std::time::Instant;
+
+    fn rtp_packet(sequence_number: u16, timestamp: u32, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x80, 10];
+        packet.extend(sequence_number.to_be_bytes());
+        packet.extend(timestamp.to_be_bytes());
+        packet.extend(0x1234_5678_u32.to_be_bytes());
+        packet.extend(payload);
+        packet
+    }
+
+    #[test]
+    fn test_parse_rtp() {
+        let packet = rtp_packet(7, 1000, &[1, 2, 3]);
+        let parsed = parse_rtp(&packet).unwrap();
+        assert_eq!(parsed.sequence_number, 7);
+        assert_eq!(parsed.timestamp, 1000);
+        assert_eq!(parsed.ssrc, 0x1234_5678);
+        assert_eq!(parsed.payload, &[1, 2, 3]);
+
+        // a contributing source, an extension and padding
+        let mut packet = rtp_packet(7, 1000, &[]);
+        packet[0] = 0x80 | 0x20 | 0x10 | 1;
+        packet.extend([0; 4]);
+        packet.extend([0xbe, 0xde, 0, 1, 9, 9, 9, 9]);
+        packet.extend([1, 2, 3, 0, 2]);
+        assert_eq!(parse_rtp(&packet).unwrap().payload, &[1, 2, 3]);
+
+        // wrong version, and truncated
+        assert!(parse_rtp(&[0x40; 12]).is_none());
+        assert!(parse_rtp(&packet[..8]).is_none());
+    }
+
+    #[test]
+    fn test_jitter_buffer_reorder() {
+        let mut jitter_buffer = JitterBuffer::new(100, 1000);
+        jitter_buffer.push(65_535, 0, &[0]);
+        jitter_buffer.push(1, 20, &[2]);
+        jitter_buffer.push(0, 10, &[1]);
+
+        let mut played = vec![];
+        while let Some(playout) = jitter_buffer.pop() {
+            played.push(playout);
+            jitter_buffer.advance(10);
+        }
+        assert_eq!(
+            played,
+            vec![
+                Playout::Packet(vec![0]),
+                Playout::Packet(vec![1]),
+                Playout::Packet(vec![2])
+            ]
+        );
+
+        // late packets are dropped
+        jitter_buffer.push(0, 10, &[1]);
+        assert_eq!(jitter_buffer.pop(), None);
+    }
+
+    #[test]
+    fn test_jitter_buffer_loss() {
+        let mut jitter_buffer = JitterBuffer::new(30, 1000);
+        jitter_buffer.push(0, 0, &[0]);
+        assert_eq!(jitter_buffer.pop(), Some(Playout::Packet(vec![0])));
+        jitter_buffer.advance(10);
+
+        // the missing packet is waited for
+        jitter_buffer.push(2, 20, &[2]);
+        assert_eq!(jitter_buffer.pop(), None);
+        jitter_buffer.push(3, 30, &[3]);
+        assert_eq!(jitter_buffer.pop(), None);
+
+        // and concealed after the delay
+        jitter_buffer.push(4, 50, &[4]);
+        assert_eq!(jitter_buffer.pop(), Some(Playout::Lost(10)));
+        jitter_buffer.advance(10);
+        assert_eq!(jitter_buffer.pop(), Some(Playout::Packet(vec![2])));
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut decoder = L16Decoder {
+            number_of_channels: 2,
+        };
+        let payload = [0x40, 0, 0xc0, 0, 0x20, 0, 0, 0, 0x7f];
+        let channels = decoder.decode(&payload).unwrap();
+        assert_float_eq!(channels[0][..], [0.5, 0.25][..], abs_all <= 0.);
+        assert_float_eq!(channels[1][..], [-0.5, 0.][..], abs_all <= 0.);
+
+        let mut decoder = RawPcmDecoder {
+            number_of_channels: 1,
+        };
+        let payload: Vec<u8> = [0.5_f32, -1.]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let channels = decoder.decode(&payload).unwrap();
+        assert_float_eq!(channels[0][..], [0.5, -1.][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_receive_network_stream() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        let format = NetworkAudioFormat::RtpL16 {
+            sample_rate: 8000.,
+            number_of_channels: 1,
+        };
+        let stream = receive_network_stream(socket, NetworkStreamOptions::new(format)).unwrap();
+        let track = &stream.get_tracks()[0];
+        assert_eq!(track.get_settings().sample_rate, Some(8000.));
+
+        // 15 packets of 80 samples, out of order and one of them lost
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for i in [0_u16, 2, 1, 3, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14] {
+            let payload: Vec<u8> = (0..80)
+                .flat_map(|_| (i as i16 * 100).to_be_bytes())
+                .collect();
+            let packet = rtp_packet(i, u32::from(i) * 80, &payload);
+            sender.send_to(&packet, address).unwrap();
+        }
+
+        let mut received = vec![];
+        let start = Instant::now();
+        let mut iter = track.iter();
+        while received.len() < 1200 && start.elapsed() < Duration::from_secs(5) {
+            let buffer = iter.next().unwrap().unwrap();
+            received.extend_from_slice(buffer.get_channel_data(0));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received.len(), 1200);
+
+        // in order, and the 4th packet is concealed with silence
+        let expected: Vec<f32> = [0, 1, 2, 3, 0, 5, 6, 7]
+            .iter()
+            .flat_map(|&i| vec![f32::from(i as i16 * 100) / 32768.; 80])
+            .collect();
+        assert_float_eq!(received[..640], expected[..], abs_all <= 0.);
+    }
+}
@@ -0,0 +1,641 @@
+//! Transmission of audio streams over the network
+//!
+//! The counterpart of [`receive_network_stream`](super::receive_network_stream): the audio of a
+//! [`MediaStream`], e.g. of a
+//! [`MediaStreamAudioDestinationNode`](crate::node::MediaStreamAudioDestinationNode), is encoded
+//! and sent as RTP packets or raw datagrams over UDP, or in the binary frames of a WebSocket.
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::{MediaStream, MediaStreamTrack};
+use crate::{AudioBuffer, ErrorEvent, Event};
+
+type ErrorEventCallback = Box<dyn FnOnce(ErrorEvent) + Send + 'static>;
+
+/// Dynamic RTP payload type (RFC 3551) of the formats without a static one
+const DYNAMIC_PAYLOAD_TYPE: u8 = 96;
+/// Sample rate of the RTP clock of Opus, regardless of the encoded sample rate
+#[cfg(feature = "opus")]
+const OPUS_CLOCK_RATE: f64 = 48_000.;
+/// Maximum size of an encoded Opus packet
+#[cfg(feature = "opus")]
+const OPUS_MAX_PACKET_SIZE: usize = 4000;
+
+/// Encoding of the audio sent to the network, at the sample rate and with the channels of the
+/// stream
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum NetworkSinkFormat {
+    /// RTP packets of interleaved 16-bit big-endian samples (`L16`, RFC 3551)
+    RtpL16,
+    /// RTP packets of Opus frames (RFC 7587), for mono or stereo streams at 8, 12, 16, 24 or
+    /// 48 kHz
+    #[cfg(feature = "opus")]
+    RtpOpus {
+        /// Target bitrate of the encoder, in bits per second
+        bitrate: u32,
+    },
+    /// Datagrams of interleaved 32-bit float little-endian samples, without any header
+    RawPcm,
+}
+
+/// Destination of the packets of a [`NetworkSink`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum NetworkSinkTransport {
+    /// Datagrams sent from the `socket` to the `destination` address
+    Udp {
+        socket: UdpSocket,
+        destination: SocketAddr,
+    },
+    /// Binary frames of a WebSocket connection to the `url`, one per packet
+    #[cfg(feature = "websocket")]
+    WebSocket { url: String },
+}
+
+/// Options for the transmission of an audio stream, see [`NetworkSink`]
+#[derive(Clone, Debug)]
+pub struct NetworkSinkOptions {
+    pub format: NetworkSinkFormat,
+    /// Duration of the audio of each packet, in seconds. Opus supports 2.5, 5, 10, 20, 40 and
+    /// 60 ms.
+    pub packet_duration: f64,
+}
+
+impl NetworkSinkOptions {
+    pub fn new(format: NetworkSinkFormat) -> Self {
+        Self {
+            format,
+            packet_duration: 0.02,
+        }
+    }
+}
+
+struct NetworkSinkInner {
+    active: AtomicBool,
+    error_callback: Mutex<Option<ErrorEventCallback>>,
+}
+
+/// Everything needed to start sending
+struct Sending {
+    track: MediaStreamTrack,
+    transport: Transport,
+    options: NetworkSinkOptions,
+}
+
+impl NetworkSinkInner {
+    fn handle_error(&self, error: Box<dyn Error + Send + Sync>) {
+        log::warn!("Network sink failed: {}", error);
+        self.active.store(false, Ordering::Relaxed);
+
+        if let Some(f) = self.error_callback.lock().unwrap().take() {
+            (f)(ErrorEvent {
+                message: error.to_string(),
+                error: Box::new(error),
+                event: Event {
+                    type_: "ErrorEvent",
+                },
+            })
+        }
+    }
+}
+
+/// Send the audio of a media stream over the network, e.g. to broadcast the output of an audio
+/// graph
+///
+/// The first track of the stream is sent from the start of the sink until it is stopped, the
+/// track ends, or an error occurs.
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::net::UdpSocket;
+///
+/// use web_audio_api::context::AudioContext;
+/// use web_audio_api::media_streams::{
+///     NetworkSink, NetworkSinkFormat, NetworkSinkOptions, NetworkSinkTransport,
+/// };
+///
+/// let context = AudioContext::default();
+/// let output = context.create_media_stream_destination();
+///
+/// let transport = NetworkSinkTransport::Udp {
+///     socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
+///     destination: "192.168.1.10:5004".parse().unwrap(),
+/// };
+/// let options = NetworkSinkOptions::new(NetworkSinkFormat::RtpL16);
+/// let sink = NetworkSink::new(output.stream(), transport, options).unwrap();
+/// sink.set_onerror(|event| println!("Broadcast failed: {}", event.message));
+/// sink.start();
+/// ```
+pub struct NetworkSink {
+    inner: Arc<NetworkSinkInner>,
+    sending: Mutex<Option<Sending>>,
+}
+
+impl NetworkSink {
+    /// Set up the sending of the `stream`
+    ///
+    /// # Errors
+    ///
+    /// Will return an error when the WebSocket connection cannot be established
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the stream has no tracks, or the packet duration is not strictly positive
+    pub fn new(
+        stream: &MediaStream,
+        transport: NetworkSinkTransport,
+        options: NetworkSinkOptions,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let track = stream
+            .get_tracks()
+            .first()
+            .expect("InvalidStateError - the media stream has no tracks")
+            .clone();
+        assert!(
+            options.packet_duration > 0.,
+            "RangeError - Invalid packet duration: {:?}, should be strictly positive",
+            options.packet_duration
+        );
+
+        let transport = Transport::connect(transport)?;
+        let inner = Arc::new(NetworkSinkInner {
+            active: AtomicBool::new(false),
+            error_callback: Mutex::new(None),
+        });
+        let sending = Sending {
+            track,
+            transport,
+            options,
+        };
+
+        Ok(Self {
+            inner,
+            sending: Mutex::new(Some(sending)),
+        })
+    }
+
+    /// Start sending
+    ///
+    /// # Panics
+    ///
+    /// Will panic when the sink has already started
+    pub fn start(&self) {
+        let sending = self
+            .sending
+            .lock()
+            .unwrap()
+            .take()
+            .expect("InvalidStateError: sink has already started");
+        self.inner.active.store(true, Ordering::Relaxed);
+
+        let inner = Arc::clone(&self.inner);
+        std::thread::spawn(move || send(&inner, sending));
+    }
+
+    /// Indicates if the sink is still sending
+    pub fn active(&self) -> bool {
+        self.inner.active.load(Ordering::Relaxed)
+    }
+
+    /// Register a callback for the failure of the transmission, e.g. an unsupported stream
+    /// format or a closed connection
+    #[allow(clippy::missing_panics_doc)]
+    pub fn set_onerror<F: FnOnce(ErrorEvent) + Send + 'static>(&self, callback: F) {
+        *self.inner.error_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Unset the callback to run on error
+    #[allow(clippy::missing_panics_doc)]
+    pub fn clear_onerror(&self) {
+        *self.inner.error_callback.lock().unwrap() = None;
+    }
+
+    /// Stop sending, the sink stops before the next buffer of the stream
+    pub fn stop(&self) {
+        self.inner.active.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Thread encoding and sending the buffers of the `track`
+fn send(inner: &NetworkSinkInner, sending: Sending) {
+    let Sending {
+        track,
+        mut transport,
+        options,
+    } = sending;
+    let mut packetizer = Packetizer::new(options);
+
+    for item in track.iter() {
+        if !inner.active.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let result = item
+            .and_then(|buffer| packetizer.push(&buffer))
+            .and_then(|packets| packets.into_iter().try_for_each(|p| transport.send(p)));
+        if let Err(e) = result {
+            inner.handle_error(e);
+            return;
+        }
+    }
+
+    inner.active.store(false, Ordering::Relaxed);
+}
+
+/// Connected destination of the packets
+enum Transport {
+    Udp(UdpSocket, SocketAddr),
+    #[cfg(feature = "websocket")]
+    WebSocket(
+        Box<tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>>,
+    ),
+}
+
+impl Transport {
+    fn connect(transport: NetworkSinkTransport) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        match transport {
+            NetworkSinkTransport::Udp {
+                socket,
+                destination,
+            } => Ok(Self::Udp(socket, destination)),
+            #[cfg(feature = "websocket")]
+            NetworkSinkTransport::WebSocket { url } => {
+                let (socket, _) = tungstenite::connect(url)?;
+                Ok(Self::WebSocket(Box::new(socket)))
+            }
+        }
+    }
+
+    fn send(&mut self, packet: Vec<u8>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self {
+            Self::Udp(socket, destination) => {
+                socket.send_to(&packet, *destination)?;
+            }
+            #[cfg(feature = "websocket")]
+            Self::WebSocket(socket) => {
+                socket.write_message(tungstenite::Message::Binary(packet))?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Encoder of the payloads of a format
+trait PayloadEncoder: Send {
+    /// Encode the interleaved samples of a packet
+    fn encode(&mut self, samples: &[f32]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+}
+
+struct L16Encoder;
+
+impl PayloadEncoder for L16Encoder {
+    fn encode(&mut self, samples: &[f32]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        Ok(samples
+            .iter()
+            .flat_map(|&v| ((v.clamp(-1., 1.) * 32767.).round() as i16).to_be_bytes())
+            .collect())
+    }
+}
+
+struct RawPcmEncoder;
+
+impl PayloadEncoder for RawPcmEncoder {
+    fn encode(&mut self, samples: &[f32]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        Ok(samples.iter().flat_map(|v| v.to_le_bytes()).collect())
+    }
+}
+
+#[cfg(feature = "opus")]
+struct OpusEncoder {
+    encoder: audiopus::coder::Encoder,
+    output: Vec<u8>,
+}
+
+#[cfg(feature = "opus")]
+impl OpusEncoder {
+    fn new(
+        number_of_channels: usize,
+        sample_rate: f32,
+        bitrate: u32,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        use std::convert::TryFrom;
+
+        let channels = match number_of_channels {
+            1 => audiopus::Channels::Mono,
+            2 => audiopus::Channels::Stereo,
+            _ => {
+                return Err(format!(
+                    "NotSupportedError - Opus streams are mono or stereo, not {} channels",
+                    number_of_channels
+                )
+                .into())
+            }
+        };
+        let sample_rate = audiopus::SampleRate::try_from(sample_rate as i32)?;
+
+        let mut encoder =
+            audiopus::coder::Encoder::new(sample_rate, channels, audiopus::Application::Audio)?;
+        encoder.set_bitrate(audiopus::Bitrate::BitsPerSecond(bitrate as i32))?;
+
+        Ok(Self {
+            encoder,
+            output: vec![0; OPUS_MAX_PACKET_SIZE],
+        })
+    }
+}
+
+#[cfg(feature = "opus")]
+impl PayloadEncoder for OpusEncoder {
+    fn encode(&mut self, samples: &[f32]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let length = self.encoder.encode_float(samples, &mut self.output)?;
+        Ok(self.output[..length].to_vec())
+    }
+}
+
+/// State of the RTP session
+struct RtpSession {
+    payload_type: u8,
+    sequence_number: u16,
+    timestamp: u32,
+    ssrc: u32,
+    /// increment of the timestamp per packet
+    timestamp_increment: u32,
+}
+
+impl RtpSession {
+    fn new(payload_type: u8, timestamp_increment: u32) -> Self {
+        // the initial values are random (RFC 3550)
+        let random = RandomState::new().build_hasher().finish();
+
+        Self {
+            payload_type,
+            sequence_number: random as u16,
+            timestamp: (random >> 16) as u32,
+            ssrc: (random >> 32) as u32 ^ random as u32,
+            timestamp_increment,
+        }
+    }
+
+    /// Prepend the RTP header to the `payload`
+    fn packet(&mut self, payload: Vec<u8>) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(12 + payload.len());
+        packet.extend([0x80, self.payload_type & 0x7f]);
+        packet.extend(self.sequence_number.to_be_bytes());
+        packet.extend(self.timestamp.to_be_bytes());
+        packet.extend(self.ssrc.to_be_bytes());
+        packet.extend(payload);
+
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+        self.timestamp = self.timestamp.wrapping_add(self.timestamp_increment);
+        packet
+    }
+}
+
+/// Splits the stream into packets of a fixed duration, and encodes them
+struct Packetizer {
+    options: NetworkSinkOptions,
+    /// set up with the first buffer, and when the layout of the stream changes
+    encoder: Option<Box<dyn PayloadEncoder>>,
+    rtp: Option<RtpSession>,
+    number_of_channels: usize,
+    sample_rate: f32,
+    /// number of samples per channel of a packet
+    packet_size: usize,
+    /// interleaved samples of the next packet
+    pending: Vec<f32>,
+}
+
+impl Packetizer {
+    fn new(options: NetworkSinkOptions) -> Self {
+        Self {
+            options,
+            encoder: None,
+            rtp: None,
+            number_of_channels: 0,
+            sample_rate: 0.,
+            packet_size: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Set up the encoder for the layout of the stream
+    fn reset(
+        &mut self,
+        number_of_channels: usize,
+        sample_rate: f32,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.number_of_channels = number_of_channels;
+        self.sample_rate = sample_rate;
+        self.packet_size =
+            ((self.options.packet_duration * f64::from(sample_rate)) as usize).max(1);
+        self.pending.clear();
+
+        let (encoder, rtp): (Box<dyn PayloadEncoder>, _) = match self.options.format {
+            NetworkSinkFormat::RtpL16 => {
+                let payload_type = match (sample_rate as u32, number_of_channels) {
+                    (44_100, 2) => 10,
+                    (44_100, 1) => 11,
+                    _ => DYNAMIC_PAYLOAD_TYPE,
+                };
+                let rtp = RtpSession::new(payload_type, self.packet_size as u32);
+                (Box::new(L16Encoder), Some(rtp))
+            }
+            NetworkSinkFormat::RawPcm => (Box::new(RawPcmEncoder), None),
+            #[cfg(feature = "opus")]
+            NetworkSinkFormat::RtpOpus { bitrate } => {
+                let encoder = OpusEncoder::new(number_of_channels, sample_rate, bitrate)?;
+                let increment = self.options.packet_duration * OPUS_CLOCK_RATE;
+                let rtp = RtpSession::new(DYNAMIC_PAYLOAD_TYPE, increment as u32);
+                (Box::new(encoder), Some(rtp))
+            }
+        };
+        self.encoder = Some(encoder);
+        self.rtp = rtp;
+
+        Ok(())
+    }
+
+    /// Append the `buffer`, and encode the completed packets
+    fn push(&mut self, buffer: &AudioBuffer) -> Result<Vec<Vec<u8>>, Box<dyn Error + Send + Sync>> {
+        if buffer.length() == 0 {
+            return Ok(vec![]);
+        }
+
+        if self.encoder.is_none()
+            || buffer.number_of_channels() != self.number_of_channels
+            || buffer.sample_rate() != self.sample_rate
+        {
+            self.reset(buffer.number_of_channels(), buffer.sample_rate())?;
+        }
+
+        self.pending.extend(buffer.to_interleaved());
+
+        let encoder = self.encoder.as_mut().unwrap();
+        let samples_per_packet = self.packet_size * self.number_of_channels;
+        let mut packets = vec![];
+        while self.pending.len() >= samples_per_packet {
+            let payload = encoder.encode(&self.pending[..samples_per_packet])?;
+            self.pending.drain(..samples_per_packet);
+
+            let packet = match &mut self.rtp {
+                Some(rtp) => rtp.packet(payload),
+                None => payload,
+            };
+            packets.push(packet);
+        }
+
+        Ok(packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media_streams::{receive_network_stream, NetworkAudioFormat, NetworkStreamOptions};
+    use float_eq::assert_float_eq;
+    use std::time::{Duration, Instant};
+
+    fn sine(len: usize) -> Vec<f32> {
+        (0..len).map(|i| 0.5 * (i as f32 * 0.05).sin()).collect()
+    }
+
+    #[test]
+    fn test_packetizer() {
+        let mut packetizer = Packetizer::new(NetworkSinkOptions {
+            format: NetworkSinkFormat::RtpL16,
+            packet_duration: 0.001,
+        });
+
+        // 48 samples per packet, from buffers of 128 samples
+        let buffer = AudioBuffer::from(vec![vec![0.5; 128], vec![-0.5; 128]], 48_000.);
+        let packets = packetizer.push(&buffer).unwrap();
+        assert_eq!(packets.len(), 2);
+        let packets: Vec<_> = packets
+            .into_iter()
+            .chain(packetizer.push(&buffer).unwrap())
+            .collect();
+        assert_eq!(packets.len(), 5);
+
+        for (i, packet) in packets.iter().enumerate() {
+            assert_eq!(packet.len(), 12 + 48 * 2 * 2);
+            assert_eq!(packet[0], 0x80);
+            assert_eq!(packet[1], DYNAMIC_PAYLOAD_TYPE);
+
+            let first = &packets[0];
+            let sequence_number = u16::from_be_bytes([packet[2], packet[3]]);
+            let first_sequence_number = u16::from_be_bytes([first[2], first[3]]);
+            assert_eq!(
+                sequence_number,
+                first_sequence_number.wrapping_add(i as u16)
+            );
+            let timestamp = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+            let first_timestamp = u32::from_be_bytes([first[4], first[5], first[6], first[7]]);
+            assert_eq!(timestamp, first_timestamp.wrapping_add(48 * i as u32));
+            assert_eq!(packet[8..12], first[8..12]);
+
+            // interleaved samples
+            assert_eq!(packet[12..14], 16384_i16.to_be_bytes());
+            assert_eq!(packet[14..16], (-16384_i16).to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn test_send_receive() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let destination = receiver.local_addr().unwrap();
+        let format = NetworkAudioFormat::RtpL16 {
+            sample_rate: 48_000.,
+            number_of_channels: 1,
+        };
+        let options = NetworkStreamOptions {
+            format,
+            jitter_buffer: 0.,
+        };
+        let received = receive_network_stream(receiver, options).unwrap();
+
+        // send 10 buffers of 480 samples, in packets of 240 samples
+        let input = sine(4800);
+        let buffers: Vec<_> = input
+            .chunks(480)
+            .map(|chunk| Ok(AudioBuffer::from(vec![chunk.to_vec()], 48_000.)))
+            .collect();
+        let stream = MediaStream::from_tracks(vec![MediaStreamTrack::from_iter(buffers)]);
+
+        let transport = NetworkSinkTransport::Udp {
+            socket: UdpSocket::bind("127.0.0.1:0").unwrap(),
+            destination,
+        };
+        let options = NetworkSinkOptions {
+            format: NetworkSinkFormat::RtpL16,
+            packet_duration: 0.005,
+        };
+        let sink = NetworkSink::new(&stream, transport, options).unwrap();
+        sink.start();
+
+        let mut output = vec![];
+        let start = Instant::now();
+        let mut iter = received.get_tracks()[0].iter();
+        while output.len() < input.len() && start.elapsed() < Duration::from_secs(5) {
+            output.extend_from_slice(iter.next().unwrap().unwrap().get_channel_data(0));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_float_eq!(output[..], input[..], abs_all <= 1. / 32767.);
+    }
+
+    #[test]
+    fn test_error() {
+        let buffers: Vec<crate::FallibleBuffer> = vec![
+            Ok(AudioBuffer::from(vec![vec![0.; 128]], 48_000.)),
+            Err("broken stream".into()),
+        ];
+        let stream = MediaStream::from_tracks(vec![MediaStreamTrack::from_iter(buffers)]);
+
+        let transport = NetworkSinkTransport::Udp {
+            socket: UdpSocket::bind("127.0.0.1:0").unwrap(),
+            destination: "127.0.0.1:9".parse().unwrap(),
+        };
+        let options = NetworkSinkOptions::new(NetworkSinkFormat::RawPcm);
+
+        let (send, recv) = crossbeam_channel::bounded(1);
+        let sink = NetworkSink::new(&stream, transport, options).unwrap();
+        sink.set_onerror(move |event| send.send(event.message).unwrap());
+        sink.start();
+
+        let message = recv.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(message, "broken stream");
+        assert!(!sink.active());
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn test_websocket() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            let mut websocket = tungstenite::accept(socket).unwrap();
+            websocket.read_message().unwrap()
+        });
+
+        let buffers = vec![Ok(AudioBuffer::from(vec![vec![0.25; 960]], 48_000.))];
+        let stream = MediaStream::from_tracks(vec![MediaStreamTrack::from_iter(buffers)]);
+        let transport = NetworkSinkTransport::WebSocket { url };
+        let options = NetworkSinkOptions::new(NetworkSinkFormat::RtpL16);
+        let sink = NetworkSink::new(&stream, transport, options).unwrap();
+        sink.start();
+
+        // a frame per packet of 20 ms
+        match server.join().unwrap() {
+            tungstenite::Message::Binary(packet) => assert_eq!(packet.len(), 12 + 960 * 2),
+            message => panic!("unexpected message {:?}", message),
+        }
+    }
+}
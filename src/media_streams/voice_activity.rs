@@ -0,0 +1,402 @@
+//! Voice activity detection of media streams
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use super::{MediaStream, MediaStreamTrack};
+use crate::{AudioBuffer, Event, FallibleBuffer};
+
+/// Duration of the analysis frames, in seconds
+const FRAME_DURATION: f64 = 0.01;
+/// Growth of the noise floor per frame, in dB, to follow increasing background noise
+const NOISE_FLOOR_RISE: f32 = 0.02;
+/// Level below which the input is never considered speech, in dBFS
+const MIN_SPEECH_LEVEL: f32 = -70.;
+/// Number of events waiting for their dispatch, further events are dropped
+const EVENT_CAPACITY: usize = 32;
+
+type VoiceActivityCallback = Box<dyn FnMut(VoiceActivityEvent) + Send + 'static>;
+
+/// Options for the [`VoiceActivityDetector`]
+#[derive(Clone, Debug)]
+pub struct VoiceActivityOptions {
+    /// Level above the background noise from which the input is considered speech, in dB
+    pub threshold: f32,
+    /// Duration of the non-speech input after which the speech ends, in seconds. This bridges
+    /// the short pauses in between words.
+    pub hangover: f64,
+    /// Silence the output stream while no speech is detected
+    pub gate: bool,
+}
+
+impl Default for VoiceActivityOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 10.,
+            hangover: 0.3,
+            gate: false,
+        }
+    }
+}
+
+/// Interface for the `speechstart` and `speechend` events of a [`VoiceActivityDetector`]
+#[derive(Clone, Debug)]
+pub struct VoiceActivityEvent {
+    /// Position in the stream of the start or the end of the speech, in seconds
+    pub timestamp: f64,
+    /// Inherits from this base Event
+    pub event: Event,
+}
+
+struct VoiceActivityInner {
+    speaking: AtomicBool,
+    speech_start_callback: Mutex<Option<VoiceActivityCallback>>,
+    speech_end_callback: Mutex<Option<VoiceActivityCallback>>,
+}
+
+impl VoiceActivityInner {
+    /// Run the callbacks of the events until the stream is dropped
+    ///
+    /// The stream is consumed by the render thread when it is played by a context, so the
+    /// callbacks run on a thread of their own.
+    fn dispatch(self: Arc<Self>, events: Receiver<VoiceActivityEvent>) {
+        std::thread::spawn(move || {
+            for event in events.iter() {
+                let callback = match event.event.type_ {
+                    "speechstart" => &self.speech_start_callback,
+                    _ => &self.speech_end_callback,
+                };
+
+                if let Some(f) = callback.lock().unwrap().as_mut() {
+                    (f)(event)
+                }
+            }
+        });
+    }
+}
+
+/// Detect speech in a [`MediaStream`]
+///
+/// The detector compares the level of the input with an estimate of the background noise. It
+/// runs while its [`stream`](Self::stream) is consumed, e.g. by a
+/// [`MediaStreamAudioSourceNode`](crate::node::MediaStreamAudioSourceNode) or a
+/// [`MediaRecorder`](crate::media_recorder::MediaRecorder). The callbacks are called on a thread
+/// of the detector, so they do not hold up the thread consuming the stream.
+///
+/// ```no_run
+/// use web_audio_api::media_devices::{self, MediaStreamConstraints};
+/// use web_audio_api::media_recorder::MediaRecorder;
+/// use web_audio_api::media_streams::{VoiceActivityDetector, VoiceActivityOptions};
+///
+/// let mic = media_devices::get_user_media_sync(MediaStreamConstraints::Audio);
+///
+/// // only record the speech
+/// let options = VoiceActivityOptions {
+///     gate: true,
+///     ..VoiceActivityOptions::default()
+/// };
+/// let vad = VoiceActivityDetector::new(&mic, options);
+/// vad.set_onspeechstart(|event| println!("speech started at {}", event.timestamp));
+/// vad.set_onspeechend(|event| println!("speech ended at {}", event.timestamp));
+///
+/// let recorder = MediaRecorder::new(vad.stream());
+/// recorder.start();
+/// ```
+pub struct VoiceActivityDetector {
+    inner: Arc<VoiceActivityInner>,
+    stream: MediaStream,
+}
+
+impl VoiceActivityDetector {
+    /// Creates a new `VoiceActivityDetector` of the first track of the `stream`
+    ///
+    /// # Panics
+    ///
+    /// Will panic when:
+    /// - the stream has no tracks
+    /// - the threshold is not strictly positive, or the hangover is negative
+    pub fn new(stream: &MediaStream, options: VoiceActivityOptions) -> Self {
+        assert!(
+            options.threshold > 0.,
+            "RangeError - Invalid threshold: {:?}, should be strictly positive",
+            options.threshold
+        );
+        assert!(
+            options.hangover >= 0.,
+            "RangeError - Invalid hangover: {:?}, should be positive",
+            options.hangover
+        );
+        let track = stream
+            .get_tracks()
+            .first()
+            .expect("InvalidStateError - the media stream has no tracks");
+
+        let inner = Arc::new(VoiceActivityInner {
+            speaking: AtomicBool::new(false),
+            speech_start_callback: Mutex::new(None),
+            speech_end_callback: Mutex::new(None),
+        });
+
+        let (event_send, event_recv) = crossbeam_channel::bounded(EVENT_CAPACITY);
+        Arc::clone(&inner).dispatch(event_recv);
+
+        let iter = VoiceActivityStream::new(track.iter(), Arc::clone(&inner), event_send, options);
+        let track = MediaStreamTrack::from_iter_with_parent(
+            iter,
+            track.get_settings(),
+            Some(Arc::clone(&track.inner.source)),
+        );
+
+        Self {
+            inner,
+            stream: MediaStream::from_tracks(vec![track]),
+        }
+    }
+
+    /// The analysed stream, silenced in between speech with the `gate` option
+    pub fn stream(&self) -> &MediaStream {
+        &self.stream
+    }
+
+    /// `true` while speech is detected
+    pub fn speaking(&self) -> bool {
+        self.inner.speaking.load(Ordering::Relaxed)
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    pub fn set_onspeechstart<F: FnMut(VoiceActivityEvent) + Send + 'static>(&self, callback: F) {
+        *self.inner.speech_start_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    pub fn clear_onspeechstart(&self) {
+        *self.inner.speech_start_callback.lock().unwrap() = None;
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    pub fn set_onspeechend<F: FnMut(VoiceActivityEvent) + Send + 'static>(&self, callback: F) {
+        *self.inner.speech_end_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    pub fn clear_onspeechend(&self) {
+        *self.inner.speech_end_callback.lock().unwrap() = None;
+    }
+}
+
+/// Media stream passing through the input, while detecting the speech
+struct VoiceActivityStream<I> {
+    input: I,
+    inner: Arc<VoiceActivityInner>,
+    /// events handed to the thread running the callbacks
+    events: Sender<VoiceActivityEvent>,
+    options: VoiceActivityOptions,
+    /// sum of the squared samples of the current frame
+    sum: f32,
+    /// number of samples of the current frame
+    count: usize,
+    /// estimated level of the background noise, in dBFS
+    noise_floor: Option<f32>,
+    /// number of samples processed
+    position: u64,
+    /// position of the end of the last speech frame
+    speech_end: u64,
+}
+
+impl<I> VoiceActivityStream<I> {
+    fn new(
+        input: I,
+        inner: Arc<VoiceActivityInner>,
+        events: Sender<VoiceActivityEvent>,
+        options: VoiceActivityOptions,
+    ) -> Self {
+        Self {
+            input,
+            inner,
+            events,
+            options,
+            sum: 0.,
+            count: 0,
+            noise_floor: None,
+            position: 0,
+            speech_end: 0,
+        }
+    }
+
+    /// Hand an event over to the thread running the callbacks, without blocking
+    fn dispatch(&self, type_: &'static str, timestamp: f64) {
+        let _ = self.events.try_send(VoiceActivityEvent {
+            timestamp,
+            event: Event { type_ },
+        });
+    }
+
+    /// Classify the completed frame, and dispatch the changes of the speech state
+    fn process_frame(&mut self, sample_rate: f32) {
+        let level = 10.
+            * (self.sum / self.count as f32)
+                .max(f32::MIN_POSITIVE)
+                .log10();
+        let frame_start = self.position + 1 - self.count as u64;
+        self.sum = 0.;
+        self.count = 0;
+
+        let noise_floor = match self.noise_floor {
+            Some(floor) if level > floor => floor + NOISE_FLOOR_RISE,
+            _ => level,
+        };
+        self.noise_floor = Some(noise_floor);
+
+        let speech = level > MIN_SPEECH_LEVEL && level > noise_floor + self.options.threshold;
+        let speaking = self.inner.speaking.load(Ordering::Relaxed);
+        let seconds = |position: u64| position as f64 / f64::from(sample_rate);
+
+        if speech {
+            self.speech_end = self.position + 1;
+            if !speaking {
+                self.inner.speaking.store(true, Ordering::Relaxed);
+                self.dispatch("speechstart", seconds(frame_start));
+            }
+        } else if speaking && seconds(self.position + 1 - self.speech_end) >= self.options.hangover
+        {
+            self.inner.speaking.store(false, Ordering::Relaxed);
+            self.dispatch("speechend", seconds(self.speech_end));
+        }
+    }
+}
+
+impl<I: Iterator<Item = FallibleBuffer>> Iterator for VoiceActivityStream<I> {
+    type Item = FallibleBuffer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buffer = match self.input.next()? {
+            Ok(buffer) => buffer,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if buffer.length() == 0 {
+            return Some(Ok(buffer));
+        }
+
+        let sample_rate = buffer.sample_rate();
+        let frame_length = ((FRAME_DURATION * f64::from(sample_rate)) as usize).max(1);
+        let number_of_channels = buffer.number_of_channels();
+        let mut channels: Vec<Vec<f32>> = (0..number_of_channels)
+            .map(|i| buffer.get_channel_data(i).to_vec())
+            .collect();
+
+        for i in 0..buffer.length() {
+            // gate with the state of the previous frames
+            if self.options.gate && !self.inner.speaking.load(Ordering::Relaxed) {
+                channels.iter_mut().for_each(|channel| channel[i] = 0.);
+            }
+
+            self.sum += (0..number_of_channels)
+                .map(|c| buffer.get_channel_data(c)[i].powi(2))
+                .sum::<f32>()
+                / number_of_channels as f32;
+            self.count += 1;
+            if self.count == frame_length {
+                self.process_frame(sample_rate);
+            }
+            self.position += 1;
+        }
+
+        Some(Ok(AudioBuffer::from(channels, sample_rate)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Background noise at -60 dBFS, with a tone at -20 dBFS in between 1 and 2 seconds
+    fn input() -> Vec<FallibleBuffer> {
+        let mut state = 12345_u32;
+        let samples: Vec<f32> = (0..48_000 * 3)
+            .map(|i| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                let noise = ((state >> 16) as f32 / 32768. - 1.) * 0.001;
+                let tone = if (48_000..96_000).contains(&i) {
+                    0.14 * (i as f32 * 0.05).sin()
+                } else {
+                    0.
+                };
+                noise + tone
+            })
+            .collect();
+
+        samples
+            .chunks(480)
+            .map(|chunk| Ok(AudioBuffer::from(vec![chunk.to_vec()], 48_000.)))
+            .collect()
+    }
+
+    #[test]
+    fn test_speech_events() {
+        let track = MediaStreamTrack::from_iter(input());
+        let stream = MediaStream::from_tracks(vec![track]);
+        let vad = VoiceActivityDetector::new(&stream, VoiceActivityOptions::default());
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let sender_clone = sender.clone();
+        vad.set_onspeechstart(move |e| {
+            sender_clone.send((e, std::thread::current().id())).unwrap()
+        });
+        vad.set_onspeechend(move |e| sender.send((e, std::thread::current().id())).unwrap());
+
+        let output: Vec<FallibleBuffer> = vad.stream().get_tracks()[0].iter().collect();
+        assert_eq!(output.len(), 300);
+        assert!(!vad.speaking());
+
+        // the callbacks do not run on the consuming thread
+        let events: Vec<_> = (0..2)
+            .map(|_| receiver.recv_timeout(Duration::from_secs(1)).unwrap())
+            .map(|(event, thread)| {
+                assert_ne!(thread, std::thread::current().id());
+                event
+            })
+            .collect();
+        assert_eq!(events[0].event.type_, "speechstart");
+        assert!((events[0].timestamp - 1.).abs() < 0.02);
+        assert_eq!(events[1].event.type_, "speechend");
+        assert!((events[1].timestamp - 2.).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_gate() {
+        let track = MediaStreamTrack::from_iter(input());
+        let stream = MediaStream::from_tracks(vec![track]);
+        let options = VoiceActivityOptions {
+            gate: true,
+            ..VoiceActivityOptions::default()
+        };
+        let vad = VoiceActivityDetector::new(&stream, options);
+
+        let output: Vec<f32> = vad.stream().get_tracks()[0]
+            .iter()
+            .flat_map(|buffer| buffer.unwrap().get_channel_data(0).to_vec())
+            .collect();
+
+        // silent before the speech, and after the hangover
+        assert!(output[..48_000].iter().all(|&v| v == 0.));
+        assert!(output[48_000 * 2 + 24_000..].iter().all(|&v| v == 0.));
+        // the speech passes once detected
+        assert!(output[48_000 + 960..48_000 * 2].iter().any(|&v| v != 0.));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_threshold() {
+        let track = MediaStreamTrack::from_iter(input());
+        let stream = MediaStream::from_tracks(vec![track]);
+        let options = VoiceActivityOptions {
+            threshold: 0.,
+            ..VoiceActivityOptions::default()
+        };
+        let _ = VoiceActivityDetector::new(&stream, options);
+    }
+}
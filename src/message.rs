@@ -4,9 +4,11 @@ use crate::node::ChannelConfig;
 use crate::param::AudioParamEvent;
 use crate::render::graph::Graph;
 use crate::render::AudioProcessor;
+use crate::WatchdogOptions;
 
 use crate::context::AudioNodeId;
 use crossbeam_channel::Sender;
+use std::any::Any;
 
 /// Commands from the control thread to the render thread
 pub(crate) enum ControlMessage {
@@ -19,6 +21,18 @@ pub(crate) enum ControlMessage {
         channel_config: ChannelConfig,
     },
 
+    /// Replace the processor of a node in the audio graph
+    ReplaceProcessor {
+        id: AudioNodeId,
+        processor: Box<dyn AudioProcessor>,
+    },
+
+    /// Pass a message from a `MessagePort` to the processor of a node
+    NodeMessage {
+        id: AudioNodeId,
+        msg: Box<dyn Any + Send>,
+    },
+
     /// Connect a node to another in the audio graph
     ConnectNode {
         from: AudioNodeId,
@@ -27,11 +41,14 @@ pub(crate) enum ControlMessage {
         output: usize,
     },
 
-    /// Clear the connection between two given nodes in the audio graph
-    DisconnectNode { from: AudioNodeId, to: AudioNodeId },
-
-    /// Disconnect this node from the audio graph (drop all its connections)
-    DisconnectAll { from: AudioNodeId },
+    /// Clear the outgoing connections of a node in the audio graph, optionally restricted to the
+    /// given output port, destination node and input port
+    DisconnectNode {
+        from: AudioNodeId,
+        output: Option<usize>,
+        to: Option<AudioNodeId>,
+        input: Option<usize>,
+    },
 
     /// Notify the render thread this node is dropped in the control thread
     FreeWhenFinished { id: AudioNodeId },
@@ -45,9 +62,15 @@ pub(crate) enum ControlMessage {
     /// Mark node as a cycle breaker (DelayNode only)
     MarkCycleBreaker { id: AudioNodeId },
 
+    /// Enable, update or disable the render thread watchdog
+    SetWatchdog { options: Option<WatchdogOptions> },
+
+    /// Apply the given messages in order, at the same render quantum boundary
+    Batch { messages: Vec<ControlMessage> },
+
     /// Shut down and recycle the audio graph
     Shutdown { sender: Sender<Graph> },
 
     /// Start rendering with given audio graph
-    Startup { graph: Graph },
+    Startup { graph: Box<Graph> },
 }
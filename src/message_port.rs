@@ -0,0 +1,172 @@
+//! Messaging between the control and render side of an audio node
+use std::any::Any;
+
+use crate::events::{EventHandler, EventPayload, EventType};
+use crate::message::ControlMessage;
+use crate::node::AudioNode;
+
+/// Bidirectional message channel between an audio node and its processor
+///
+/// Messages posted with [`post_message`](Self::post_message) are delivered to
+/// [`AudioProcessor::onmessage`](crate::render::AudioProcessor::onmessage) at the next render
+/// quantum boundary. The processor answers with
+/// [`RenderScope::post_message`](crate::render::RenderScope::post_message), which invokes the
+/// callback set with [`set_onmessage`](Self::set_onmessage) on the event thread.
+///
+/// This removes the need to set up a dedicated channel for every custom processor. Messages
+/// are passed over the lock-free control and event channels of the context.
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::any::Any;
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioWorkletNode, AudioWorkletNodeOptions};
+/// use web_audio_api::node::AudioWorkletProcessorOptions;
+/// use web_audio_api::render::{
+///     AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope,
+/// };
+///
+/// struct Volume {
+///     gain: f32,
+/// }
+///
+/// impl AudioProcessor for Volume {
+///     fn process(
+///         &mut self,
+///         inputs: &[AudioRenderQuantum],
+///         outputs: &mut [AudioRenderQuantum],
+///         _params: AudioParamValues,
+///         _scope: &RenderScope,
+///     ) -> bool {
+///         outputs[0] = inputs[0].clone();
+///         let gain = self.gain;
+///         outputs[0].channels_mut().iter_mut().for_each(|channel| {
+///             channel.iter_mut().for_each(|s| *s *= gain);
+///         });
+///         false
+///     }
+///
+///     fn onmessage(&mut self, msg: Box<dyn Any + Send>) {
+///         if let Ok(gain) = msg.downcast::<f32>() {
+///             self.gain = *gain;
+///         }
+///     }
+/// }
+///
+/// let context = AudioContext::default();
+/// context.register_processor("volume", |_options: AudioWorkletProcessorOptions| {
+///     Box::new(Volume { gain: 1. }) as Box<dyn AudioProcessor>
+/// });
+///
+/// let volume = AudioWorkletNode::new(&context, "volume", AudioWorkletNodeOptions::default());
+/// volume.connect(&context.destination());
+///
+/// // e.g. in the event handler of a UI control
+/// volume.port().post_message(0.5_f32);
+/// ```
+pub struct MessagePort<'a> {
+    node: &'a dyn AudioNode,
+}
+
+impl<'a> MessagePort<'a> {
+    /// The message port of the given node, e.g. a custom node registered with
+    /// [`BaseAudioContext::register`](crate::context::BaseAudioContext::register)
+    pub fn from_node(node: &'a dyn AudioNode) -> Self {
+        Self { node }
+    }
+
+    /// Send a message to the processor of the node
+    pub fn post_message<M: Any + Send + 'static>(&self, msg: M) {
+        let message = ControlMessage::NodeMessage {
+            id: self.node.registration().id(),
+            msg: Box::new(msg),
+        };
+        // the render thread may already be shut down
+        let _ = self.node.context().send_control_msg(message);
+    }
+
+    /// Register the callback to run when the processor of the node posts a message
+    ///
+    /// Only a single event handler is active at any time. Calling this method multiple times will
+    /// override the previous event handler.
+    pub fn set_onmessage<F: FnMut(Box<dyn Any + Send>) + Send + 'static>(&self, mut callback: F) {
+        let callback = move |v| match v {
+            EventPayload::Message(v) => callback(v),
+            _ => unreachable!(),
+        };
+
+        self.node.context().set_event_handler(
+            EventType::Message(self.node.registration().id()),
+            EventHandler::Multiple(Box::new(callback)),
+        );
+    }
+
+    /// Unset the callback to run when the processor of the node posts a message
+    pub fn clear_onmessage(&self) {
+        self.node
+            .context()
+            .clear_event_handler(EventType::Message(self.node.registration().id()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioWorkletNode, AudioWorkletNodeOptions, AudioWorkletProcessorOptions};
+    use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    struct ConstantProcessor {
+        value: f32,
+    }
+
+    impl AudioProcessor for ConstantProcessor {
+        fn process(
+            &mut self,
+            _inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            let value = self.value;
+            outputs[0].channels_mut()[0]
+                .iter_mut()
+                .for_each(|s| *s = value);
+            true
+        }
+
+        fn onmessage(&mut self, msg: Box<dyn Any + Send>) {
+            if let Ok(value) = msg.downcast::<f32>() {
+                self.value = *value;
+            }
+        }
+    }
+
+    #[test]
+    fn test_post_message() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        context.register_processor("constant", |_options: AudioWorkletProcessorOptions| {
+            Box::new(ConstantProcessor { value: 1. }) as Box<dyn AudioProcessor>
+        });
+
+        let options = AudioWorkletNodeOptions {
+            number_of_inputs: 0,
+            ..AudioWorkletNodeOptions::default()
+        };
+        let node = AudioWorkletNode::new(&context, "constant", options);
+        node.connect(&context.destination());
+
+        // messages of an unexpected type are ignored
+        node.port().post_message("unexpected");
+        node.port().post_message(0.5_f32);
+
+        let output = context.start_rendering_sync();
+        let expected = [0.5; RENDER_QUANTUM_SIZE];
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+    }
+}
@@ -0,0 +1,316 @@
+//! Memory-mapped audio data for [`AudioBuffer`]
+//!
+//! The samples of huge assets, e.g. sample libraries, are mapped from their files instead of
+//! being loaded. The operating system pages them in lazily, when they are played, and may evict
+//! them again under memory pressure.
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+use crate::buffer::{AudioBuffer, ChannelData};
+use crate::wav::{invalid_data, WavFormat, WAVE_FORMAT_IEEE_FLOAT};
+use crate::{assert_valid_number_of_channels, assert_valid_sample_rate};
+
+/// Samples of a channel, mapped from a file
+#[derive(Clone)]
+pub(crate) struct MappedChannel {
+    map: Arc<Mmap>,
+    /// offset of the samples in the map, in bytes
+    offset: usize,
+    /// number of samples
+    length: usize,
+}
+
+impl MappedChannel {
+    pub fn as_slice(&self) -> &[f32] {
+        let bytes = &self.map[self.offset..self.offset + self.length * 4];
+        // Safety: the bytes are in bounds and aligned for f32, which is checked on construction,
+        // and any bit pattern is a valid f32
+        unsafe { std::slice::from_raw_parts(bytes.as_ptr().cast::<f32>(), self.length) }
+    }
+}
+
+impl fmt::Debug for MappedChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedChannel")
+            .field("offset", &self.offset)
+            .field("length", &self.length)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Split the planar samples at `offset` of the `map` into channels
+fn map_channels(
+    map: Mmap,
+    offset: usize,
+    number_of_channels: usize,
+    length: usize,
+    sample_rate: f32,
+) -> Result<AudioBuffer, Box<dyn Error + Send + Sync>> {
+    if cfg!(target_endian = "big") {
+        return Err(
+            "NotSupportedError - mapping little endian samples on a big endian target".into(),
+        );
+    }
+    if map[offset..]
+        .as_ptr()
+        .align_offset(std::mem::align_of::<f32>())
+        != 0
+    {
+        return Err(invalid_data(format!(
+            "Samples at offset {:?} are not aligned for mapping",
+            offset
+        )));
+    }
+
+    let map = Arc::new(map);
+    let channels = (0..number_of_channels)
+        .map(|c| {
+            ChannelData::from_mapped(MappedChannel {
+                map: Arc::clone(&map),
+                offset: offset + c * length * 4,
+                length,
+            })
+        })
+        .collect();
+
+    Ok(AudioBuffer::from_channels(channels, sample_rate))
+}
+
+impl AudioBuffer {
+    /// Create an `AudioBuffer` backed by a memory-mapped file of raw planar samples
+    ///
+    /// The file contains the 32 bits little endian float samples of each channel in turn, as
+    /// written by [`to_planar_pcm`](AudioBuffer::to_planar_pcm). The samples are not loaded,
+    /// they are paged in by the operating system when accessed. As with
+    /// [`from_shared`](AudioBuffer::from_shared), mutating the `AudioBuffer` first makes a
+    /// private copy of the mutated channel, the file is never written to.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the `AudioBuffer`, or any of its
+    /// clones, exists.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the file cannot be mapped, or if its size is not a
+    /// multiple of the size of a sample-frame.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if:
+    /// - the given sample rate is zero
+    /// - the given number of channels is outside the [1, 32] range
+    pub unsafe fn from_mapped_pcm(
+        file: &File,
+        number_of_channels: usize,
+        sample_rate: f32,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        assert_valid_sample_rate(sample_rate);
+        assert_valid_number_of_channels(number_of_channels);
+
+        let map = Mmap::map(file)?;
+        let frame_size = number_of_channels * 4;
+        if map.len() % frame_size != 0 {
+            return Err(invalid_data(format!(
+                "File size {:?} is not a multiple of the frame size {:?}",
+                map.len(),
+                frame_size
+            )));
+        }
+
+        let length = map.len() / frame_size;
+        map_channels(map, 0, number_of_channels, length, sample_rate)
+    }
+
+    /// Create an `AudioBuffer` backed by a memory-mapped WAV file
+    ///
+    /// Only mono WAV files of 32 bits float samples can be mapped, as the samples of the other
+    /// files need to be converted or deinterleaved. Write those once with
+    /// [`to_planar_pcm`](AudioBuffer::to_planar_pcm) to map them with
+    /// [`from_mapped_pcm`](AudioBuffer::from_mapped_pcm).
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the `AudioBuffer`, or any of its
+    /// clones, exists.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the file cannot be mapped, if it is not a WAV file or if
+    /// its samples cannot be mapped.
+    pub unsafe fn from_mapped_wav(file: &File) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let map = Mmap::map(file)?;
+        if map.len() < 12 || &map[..4] != b"RIFF" || &map[8..12] != b"WAVE" {
+            return Err(invalid_data("Not a RIFF WAVE file".into()));
+        }
+
+        let mut format: Option<WavFormat> = None;
+        let mut position = 12;
+        let (offset, size) = loop {
+            if position + 8 > map.len() {
+                return Err(invalid_data("Missing WAV data chunk".into()));
+            }
+            let id = &map[position..position + 4];
+            let mut size_bytes = [0; 4];
+            size_bytes.copy_from_slice(&map[position + 4..position + 8]);
+            let size = u32::from_le_bytes(size_bytes) as usize;
+            let start = position + 8;
+            let available = map.len() - start;
+
+            match id {
+                b"fmt " => {
+                    format = Some(WavFormat::parse(&map[start..start + size.min(available)])?)
+                }
+                // streamed files may not know the size of their data chunk
+                b"data" if size == 0 || size == u32::MAX as usize => break (start, available),
+                b"data" => break (start, size.min(available)),
+                _ => (),
+            }
+
+            // chunks are padded to an even size
+            position = start + size + size % 2;
+        };
+
+        let format = format.ok_or_else(|| invalid_data("Missing WAV fmt chunk".into()))?;
+        if format.format_tag != WAVE_FORMAT_IEEE_FLOAT
+            || format.bits_per_sample != 32
+            || format.number_of_channels != 1
+        {
+            return Err(invalid_data(format!(
+                "NotSupportedError - Only mono 32 bits float WAV files can be mapped, not \
+                {:?} channels of sample format {:?} with {:?} bits per sample",
+                format.number_of_channels, format.format_tag, format.bits_per_sample
+            )));
+        }
+        // same bound as `assert_valid_sample_rate`
+        if format.sample_rate <= 1000 {
+            return Err(invalid_data(format!(
+                "Unsupported sample rate: {:?}",
+                format.sample_rate
+            )));
+        }
+
+        map_channels(map, offset, 1, size / 4, format.sample_rate as f32)
+    }
+
+    /// Write the samples of each channel in turn, as 32 bits little endian floats
+    ///
+    /// The output can be memory-mapped with
+    /// [`from_mapped_pcm`](AudioBuffer::from_mapped_pcm).
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if writing to the output fails.
+    pub fn to_planar_pcm<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for channel_number in 0..self.number_of_channels() {
+            let bytes: Vec<u8> = self
+                .get_channel_data(channel_number)
+                .iter()
+                .flat_map(|sample| sample.to_le_bytes())
+                .collect();
+            writer.write_all(&bytes)?;
+        }
+
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::WavSampleFormat;
+
+    /// Temporary file, removed on drop
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("web-audio-api-{}-{}", std::process::id(), name));
+            std::fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+
+        fn open(&self) -> File {
+            File::open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn test_buffer() -> AudioBuffer {
+        let left = (0..1000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let right = (0..1000).map(|i| (i as f32 * 0.02).cos()).collect();
+        AudioBuffer::from(vec![left, right], 44100.)
+    }
+
+    #[test]
+    fn test_mapped_pcm() {
+        let buffer = test_buffer();
+        let mut bytes = vec![];
+        buffer.to_planar_pcm(&mut bytes).unwrap();
+        let file = TempFile::new("mapped.f32", &bytes);
+
+        let mut mapped = unsafe { AudioBuffer::from_mapped_pcm(&file.open(), 2, 44100.) }.unwrap();
+        assert_eq!(mapped.number_of_channels(), 2);
+        assert_eq!(mapped.length(), 1000);
+        for c in 0..2 {
+            assert_float_eq!(
+                mapped.get_channel_data(c),
+                buffer.get_channel_data(c),
+                abs_all <= 0.
+            );
+        }
+
+        // copy on write, the file is left untouched
+        mapped.get_channel_data_mut(0)[0] = 1.;
+        assert_float_eq!(mapped.get_channel_data(0)[0], 1., abs <= 0.);
+        assert_eq!(std::fs::read(&file.0).unwrap(), bytes);
+
+        // the size of the file does not match the channels
+        let file = TempFile::new("odd.f32", &bytes[..bytes.len() - 4]);
+        assert!(unsafe { AudioBuffer::from_mapped_pcm(&file.open(), 2, 44100.) }.is_err());
+    }
+
+    #[test]
+    fn test_mapped_wav() {
+        let buffer = AudioBuffer::from(vec![test_buffer().get_channel_data(0).to_vec()], 44100.);
+        let mut bytes = vec![];
+        buffer.to_wav(&mut bytes, WavSampleFormat::Float32).unwrap();
+        let file = TempFile::new("mapped.wav", &bytes);
+
+        let mapped = unsafe { AudioBuffer::from_mapped_wav(&file.open()) }.unwrap();
+        assert_eq!(mapped.number_of_channels(), 1);
+        assert_float_eq!(mapped.sample_rate(), 44100., abs <= 0.);
+        assert_float_eq!(
+            mapped.get_channel_data(0),
+            buffer.get_channel_data(0),
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_unsupported_wav() {
+        let mut bytes = vec![];
+        test_buffer()
+            .to_wav(&mut bytes, WavSampleFormat::Int16)
+            .unwrap();
+        let file = TempFile::new("int16.wav", &bytes);
+        assert!(unsafe { AudioBuffer::from_mapped_wav(&file.open()) }.is_err());
+
+        let file = TempFile::new("garbage.wav", b"RIFF");
+        assert!(unsafe { AudioBuffer::from_mapped_wav(&file.open()) }.is_err());
+    }
+}
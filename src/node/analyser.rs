@@ -1,14 +1,27 @@
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
+
+use crossbeam_channel::{Sender, TrySendError};
 
 use crate::analysis::{
-    Analyser, AnalyserRingBuffer, DEFAULT_FFT_SIZE, DEFAULT_MAX_DECIBELS, DEFAULT_MIN_DECIBELS,
-    DEFAULT_SMOOTHING_TIME_CONSTANT,
+    Analyser, AnalyserRingBuffer, SpectrogramPlace, SpectrogramSlot, DEFAULT_FFT_SIZE,
+    DEFAULT_MAX_DECIBELS, DEFAULT_MIN_DECIBELS, DEFAULT_SMOOTHING_TIME_CONSTANT,
+    SPECTROGRAM_QUEUE_CAPACITY,
+};
+pub use crate::analysis::{
+    PitchEstimate, Spectrogram, SpectrogramOptions, SpectrogramScaling, SpectrogramWindow,
 };
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
 
 use super::{AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
 
+/// Maximum number of spectrograms streamed at the same time by an [`AnalyserNode`]
+///
+/// The places of the spectrograms are allocated with the node, as the render thread must not
+/// allocate.
+pub const MAX_SPECTROGRAMS: usize = 4;
+
 /// Options for constructing an [`AnalyserNode`]
 // dictionary AnalyserOptions : AudioNodeOptions {
 //   unsigned long fftSize = 2048;
@@ -84,6 +97,8 @@ pub struct AnalyserNode {
     channel_config: ChannelConfig,
     // RwLock is needed to make the AnalyserNode API immutable
     analyser: RwLock<Analyser>,
+    // places of the spectrogram consumers, shared with the renderer
+    spectrogram_places: Vec<Arc<SpectrogramPlace>>,
 }
 
 impl AudioNode for AnalyserNode {
@@ -118,14 +133,21 @@ impl AnalyserNode {
             analyser.set_min_decibels(min_decibels);
             analyser.set_max_decibels(max_decibels);
 
+            let spectrogram_places: Vec<_> = (0..MAX_SPECTROGRAMS)
+                .map(|_| Arc::new(SpectrogramPlace::default()))
+                .collect();
+
             let render = AnalyserRenderer {
                 ring_buffer: analyser.get_ring_buffer_clone(),
+                spectrogram_places: spectrogram_places.clone(),
+                spectrogram_senders: vec![None; MAX_SPECTROGRAMS],
             };
 
             let node = AnalyserNode {
                 registration,
                 channel_config: options.channel_config.into(),
                 analyser: RwLock::new(analyser),
+                spectrogram_places,
             };
 
             (node, Box::new(render))
@@ -272,10 +294,89 @@ impl AnalyserNode {
             .unwrap()
             .get_byte_frequency_data(buffer, current_time);
     }
+
+    /// Copy the current frequency data, averaged into logarithmically spaced bands, into the
+    /// provided buffer
+    ///
+    /// The range `[min_frequency, max_frequency]` is divided into `buffer.len()` bands of equal
+    /// width on a logarithmic scale, e.g. 30 bands from 20Hz to 20480Hz yield 1/3 octave bands.
+    /// Each band contains the mean power (in dB) of the frequency bins it spans, bands narrower
+    /// than a single bin interpolate the magnitude at their center frequency. The
+    /// `max_frequency` is clamped to the Nyquist frequency.
+    ///
+    /// The bands are computed on the calling thread, from the same data as
+    /// [`get_float_frequency_data`](Self::get_float_frequency_data).
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `min_frequency` is not strictly positive or if it is greater than or
+    /// equal to `max_frequency`. It may also panic if the lock to the inner analyser is poisoned.
+    pub fn get_float_frequency_bands(
+        &self,
+        buffer: &mut [f32],
+        min_frequency: f32,
+        max_frequency: f32,
+    ) {
+        let context = self.registration.context();
+        let sample_rate = context.sample_rate();
+        let current_time = context.current_time();
+        self.analyser.write().unwrap().get_float_frequency_bands(
+            buffer,
+            min_frequency,
+            max_frequency,
+            sample_rate,
+            current_time,
+        );
+    }
+
+    /// Estimate the fundamental frequency of the current time domain data
+    ///
+    /// The estimate is computed with the YIN algorithm on the most recent `fft_size`
+    /// frames, so the lowest detectable frequency is `2 * sample_rate / fft_size`.
+    /// Returns `None` when no periodic signal could be found, e.g. for silence or noise.
+    ///
+    /// # Panics
+    ///
+    /// This method may panic if the lock to the inner analyser is poisoned
+    pub fn get_pitch(&self) -> Option<PitchEstimate> {
+        let sample_rate = self.registration.context().sample_rate();
+        self.analyser.read().unwrap().get_pitch(sample_rate)
+    }
+
+    /// Stream successive FFT frames of the input signal
+    ///
+    /// Contrary to [`get_float_frequency_data`](Self::get_float_frequency_data), which
+    /// only gives access to the most recent frame, the returned [`Spectrogram`] yields
+    /// every frame (spaced by `hop_size` sample frames) starting from the next render
+    /// quantum. The FFT is computed on the consuming thread, the `fft_size` and
+    /// `smoothing_time_constant` of this node do not apply.
+    ///
+    /// At most [`MAX_SPECTROGRAMS`] (4) spectrograms of a node are streamed at the same
+    /// time. Returns `None` when this limit is reached, dropping a spectrogram frees its
+    /// place.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the `fft_size` is not a power of two in the range
+    /// [32, 32768] or if the `hop_size` is zero or greater than the `fft_size`.
+    pub fn spectrogram(&self, options: SpectrogramOptions) -> Option<Spectrogram> {
+        let (place, slot) = self
+            .spectrogram_places
+            .iter()
+            .find_map(|place| SpectrogramSlot::take(place).map(|slot| (place, slot)))?;
+
+        let (sender, receiver) = crossbeam_channel::bounded(SPECTROGRAM_QUEUE_CAPACITY);
+        let spectrogram = Spectrogram::new(options, receiver, slot);
+        place.pending.store(Some(Arc::new(sender)));
+
+        Some(spectrogram)
+    }
 }
 
 struct AnalyserRenderer {
     ring_buffer: AnalyserRingBuffer,
+    spectrogram_places: Vec<Arc<SpectrogramPlace>>,
+    spectrogram_senders: Vec<Option<Arc<Sender<[f32; RENDER_QUANTUM_SIZE]>>>>,
 }
 
 impl AudioProcessor for AnalyserRenderer {
@@ -301,7 +402,72 @@ impl AudioProcessor for AnalyserRenderer {
         let data = mono.channel_data(0).as_ref();
         self.ring_buffer.write(data);
 
+        // ship current input to the spectrogram consumers, frames are lost when a consumer
+        // is lagging
+        let mut quantum = None;
+        let places = self.spectrogram_places.iter();
+        for (place, sender) in places.zip(self.spectrogram_senders.iter_mut()) {
+            // a new consumer replaces the one that was dropped from this place
+            if let Some(new_sender) = place.pending.swap(None) {
+                *sender = Some(new_sender);
+            }
+
+            if let Some(active) = sender {
+                let quantum = quantum.get_or_insert_with(|| {
+                    let mut quantum = [0.; RENDER_QUANTUM_SIZE];
+                    quantum.copy_from_slice(data);
+                    quantum
+                });
+                if let Err(TrySendError::Disconnected(_)) = active.try_send(*quantum) {
+                    *sender = None;
+                }
+            }
+        }
+
         // no tail-time
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::OfflineAudioContext;
+    use crate::node::{AudioScheduledSourceNode, SpectrogramWindow};
+
+    #[test]
+    fn test_spectrogram_limit() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 8, 48000.);
+        let analyser = context.create_analyser();
+        let src = context.create_oscillator();
+        src.connect(&analyser);
+        src.start();
+
+        let options = SpectrogramOptions {
+            fft_size: 256,
+            hop_size: 128,
+            window: SpectrogramWindow::Hann,
+            scaling: SpectrogramScaling::Magnitude,
+        };
+
+        let mut spectrograms: Vec<_> = (0..MAX_SPECTROGRAMS)
+            .map(|_| analyser.spectrogram(options.clone()).unwrap())
+            .collect();
+
+        // the spectrograms beyond the limit are rejected
+        assert!(analyser.spectrogram(options.clone()).is_none());
+
+        // dropping a spectrogram frees its place
+        spectrograms.pop();
+        spectrograms.push(analyser.spectrogram(options.clone()).unwrap());
+        assert!(analyser.spectrogram(options).is_none());
+
+        context.start_rendering_sync();
+
+        let frames: Vec<_> = spectrograms
+            .into_iter()
+            .map(|spectrogram| spectrogram.count())
+            .collect();
+        assert_eq!(frames, [7; MAX_SPECTROGRAMS]);
+    }
+}
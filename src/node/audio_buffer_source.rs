@@ -1,16 +1,45 @@
 use crossbeam_channel::{Receiver, Sender};
+use lazy_static::lazy_static;
 use once_cell::sync::OnceCell;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
 
 use crate::buffer::AudioBuffer;
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
-use crate::control::Controller;
+use crate::control::{frame_index, frames_before, Controller};
 use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::RENDER_QUANTUM_SIZE;
 
 use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
 
+/// Interpolation used to resample the [`AudioBuffer`] of an [`AudioBufferSourceNode`]
+///
+/// Resampling occurs when the computed playback rate differs from 1, or when the sample rate of
+/// the buffer differs from the sample rate of the context.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PlaybackQuality {
+    /// Linear interpolation between the two nearest samples
+    #[default]
+    Linear,
+    /// Windowed-sinc interpolation
+    ///
+    /// This avoids the high frequency loss and the aliasing of linear interpolation on
+    /// transposed samples, at the cost of some extra processing.
+    Sinc,
+}
+
+impl From<u32> for PlaybackQuality {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => PlaybackQuality::Linear,
+            1 => PlaybackQuality::Sinc,
+            _ => unreachable!(),
+        }
+    }
+}
+
 /// Options for constructing an [`AudioBufferSourceNode`]
 // dictionary AudioBufferSourceOptions {
 //   AudioBuffer? buffer;
@@ -28,6 +57,7 @@ pub struct AudioBufferSourceOptions {
     pub loop_start: f64,
     pub loop_end: f64,
     pub playback_rate: f32,
+    pub playback_quality: PlaybackQuality,
 }
 
 impl Default for AudioBufferSourceOptions {
@@ -39,6 +69,7 @@ impl Default for AudioBufferSourceOptions {
             loop_start: 0.,
             loop_end: 0.,
             playback_rate: 1.,
+            playback_quality: PlaybackQuality::default(),
         }
     }
 }
@@ -51,6 +82,74 @@ struct PlaybackInfo {
     k: f32,
 }
 
+/// Number of zero crossings on each side of the windowed-sinc kernel
+const SINC_ZERO_CROSSINGS: usize = 8;
+/// Number of kernel values stored per zero crossing
+const SINC_TABLE_RESOLUTION: usize = 512;
+/// Resampling ratio above which the kernel is no longer widened, bounding the processing cost
+const SINC_MAX_RATIO: f64 = 8.;
+
+// Compute the right half of a Blackman windowed-sinc kernel, followed by a zero
+// so that the linear interpolation between table values never goes out of bounds
+lazy_static! {
+    static ref SINC_TABLE: Vec<f32> = {
+        let len = SINC_ZERO_CROSSINGS * SINC_TABLE_RESOLUTION;
+        let mut table: Vec<f32> = (0..len)
+            .map(|i| {
+                let x = i as f64 / SINC_TABLE_RESOLUTION as f64;
+                let sinc = if i == 0 {
+                    1.
+                } else {
+                    (PI * x).sin() / (PI * x)
+                };
+                let u = x / SINC_ZERO_CROSSINGS as f64;
+                let window = 0.42 + 0.5 * (PI * u).cos() + 0.08 * (2. * PI * u).cos();
+                (sinc * window) as f32
+            })
+            .collect();
+        table.push(0.);
+        table
+    };
+}
+
+/// Windowed-sinc kernel value at a distance `x` (in zero crossings) from its center
+#[inline(always)]
+fn sinc_kernel(x: f64) -> f32 {
+    let position = x * SINC_TABLE_RESOLUTION as f64;
+    let index = position as usize;
+
+    if index >= SINC_ZERO_CROSSINGS * SINC_TABLE_RESOLUTION {
+        return 0.;
+    }
+
+    let k = (position - index as f64) as f32;
+    let table = &*SINC_TABLE;
+    (1. - k).mul_add(table[index], k * table[index + 1])
+}
+
+/// Band limited interpolation of `channel` between `prev_frame_index` and the next frame
+///
+/// The `cutoff` frequency is relative to the Nyquist frequency of the buffer, the kernel is
+/// widened accordingly so that frequencies above the cutoff are removed when downsampling.
+/// Samples outside of the buffer are considered to be zero.
+fn sinc_interpolation(channel: &[f32], prev_frame_index: usize, k: f32, cutoff: f64) -> f32 {
+    let half_width = (SINC_ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+    let position = prev_frame_index as f64 + k as f64;
+    let first = (prev_frame_index + 1).saturating_sub(half_width);
+    let last = (prev_frame_index + half_width).min(channel.len() - 1);
+
+    let sum: f32 = channel[first..=last]
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let distance = ((first + i) as f64 - position).abs();
+            s * sinc_kernel(distance * cutoff)
+        })
+        .sum();
+
+    sum * cutoff as f32
+}
+
 /// `AudioBufferSourceNode` represents an audio source that consists of an
 /// in-memory audio source (i.e. an audio file completely loaded in memory),
 /// stored in an [`AudioBuffer`].
@@ -91,6 +190,7 @@ pub struct AudioBufferSourceNode {
     detune: AudioParam,        // has constraints, no a-rate
     playback_rate: AudioParam, // has constraints, no a-rate
     buffer: OnceCell<AudioBuffer>,
+    playback_quality: Arc<AtomicU32>,
     source_started: AtomicBool,
 }
 
@@ -147,6 +247,7 @@ impl AudioBufferSourceNode {
                 loop_start,
                 loop_end,
                 playback_rate,
+                playback_quality,
             } = options;
 
             // @todo - these parameters can't be changed to a-rate
@@ -180,12 +281,15 @@ impl AudioBufferSourceNode {
 
             let controller = Controller::new();
 
+            let playback_quality = Arc::new(AtomicU32::new(playback_quality as u32));
+
             let renderer = AudioBufferSourceRenderer {
                 controller: controller.clone(),
                 receiver,
                 buffer: None,
                 detune: d_proc,
                 playback_rate: pr_proc,
+                playback_quality: playback_quality.clone(),
                 render_state: AudioBufferRendererState::default(),
                 ended_triggered: false,
             };
@@ -198,6 +302,7 @@ impl AudioBufferSourceNode {
                 detune: d_param,
                 playback_rate: pr_param,
                 buffer: OnceCell::new(),
+                playback_quality,
                 source_started: AtomicBool::new(false),
             };
 
@@ -304,6 +409,18 @@ impl AudioBufferSourceNode {
     pub fn set_loop_end(&self, value: f64) {
         self.controller.set_loop_end(value);
     }
+
+    /// Interpolation used when the [`AudioBuffer`] is resampled
+    #[must_use]
+    pub fn playback_quality(&self) -> PlaybackQuality {
+        self.playback_quality.load(Ordering::SeqCst).into()
+    }
+
+    /// Change the interpolation used when the [`AudioBuffer`] is resampled, which can be done
+    /// while rendering
+    pub fn set_playback_quality(&self, value: PlaybackQuality) {
+        self.playback_quality.store(value as u32, Ordering::SeqCst);
+    }
 }
 
 struct AudioBufferRendererState {
@@ -332,6 +449,7 @@ struct AudioBufferSourceRenderer {
     buffer: Option<AudioBuffer>,
     detune: AudioParamId,
     playback_rate: AudioParamId,
+    playback_quality: Arc<AtomicU32>,
     render_state: AudioBufferRendererState,
     ended_triggered: bool,
 }
@@ -372,7 +490,8 @@ impl AudioProcessor for AudioBufferSourceRenderer {
         // return early if start_time is beyond this block
         if start_time >= next_block_time {
             output.make_silent();
-            return true;
+            // keep alive until started, unless the control handle is gone
+            return !self.controller.is_abandoned();
         }
 
         // If the buffer has not been set wait for it.
@@ -392,8 +511,8 @@ impl AudioProcessor for AudioBufferSourceRenderer {
 
         let buffer_duration = buffer.duration();
         // multiplier to be applied on `position` to tackle possible difference
-        // between the context and buffer sample rates. As this is an edge case,
-        // we just linearly interpolate, thus favoring performance vs quality
+        // between the context and buffer sample rates. This is handled by the
+        // same interpolation as the playback rate, cf. `PlaybackQuality`
         let sampling_ratio = buffer.sample_rate() as f64 / sample_rate;
 
         // In addition, if the buffer has more than one channel, then the
@@ -440,7 +559,7 @@ impl AudioProcessor for AudioBufferSourceRenderer {
 
         // go through the algorithm described in the spec
         // @see <https://webaudio.github.io/web-audio-api/#playback-AudioBufferSourceNode>
-        let mut current_time = scope.current_time;
+        let current_time = scope.current_time;
 
         // prevent scheduling in the past
         // If 0 is passed in for this value or if the value is less than
@@ -496,7 +615,8 @@ impl AudioProcessor for AudioBufferSourceRenderer {
                     let dt =
                         (stop_time - current_time).min(duration - self.render_state.buffer_time);
                     let end_buffer_time = self.render_state.buffer_time + dt;
-                    (end_buffer_time * sample_rate).round() as usize
+                    // render every frame strictly before the stop time
+                    frames_before(end_buffer_time * sample_rate) as usize
                 } else {
                     buffer.length()
                 };
@@ -586,21 +706,24 @@ impl AudioProcessor for AudioBufferSourceRenderer {
         // according to the source buffer. (prev_sample_index, k)
         let mut playback_infos = [None; RENDER_QUANTUM_SIZE];
 
+        let start_index = frame_index(start_time, scope.current_time, sample_rate);
+        let stop_index = frame_index(stop_time, scope.current_time, sample_rate);
+
         // compute position for each sample and store into `self.positions`
-        for playback_info in playback_infos.iter_mut() {
-            if current_time < start_time
-                || current_time >= stop_time
+        for (index, playback_info) in playback_infos.iter_mut().enumerate() {
+            if index < start_index
+                || index >= stop_index
                 || self.render_state.buffer_time_elapsed >= duration
             {
                 *playback_info = None;
-                current_time += dt;
-
                 continue; // nothing more to do for this sample
             }
 
             // we have now reached start time
             if !self.render_state.started {
-                offset += current_time - start_time;
+                // start time may lie between the previous frame and this one
+                let current_time = scope.current_time + index as f64 * dt;
+                offset += (current_time - start_time).max(0.);
 
                 if loop_ && computed_playback_rate >= 0. && offset >= actual_loop_end {
                     offset = actual_loop_end;
@@ -663,10 +786,14 @@ impl AudioProcessor for AudioBufferSourceRenderer {
             let time_incr = dt * computed_playback_rate;
             self.render_state.buffer_time += time_incr;
             self.render_state.buffer_time_elapsed += time_incr;
-            current_time += dt;
         }
 
         // fill output according to computed positions
+        let playback_quality: PlaybackQuality = self.playback_quality.load(Ordering::SeqCst).into();
+        // when downsampling, lower the cutoff frequency of the sinc kernel to avoid aliasing
+        let resampling_ratio = (computed_playback_rate * sampling_ratio).abs();
+        let cutoff = resampling_ratio.recip().clamp(SINC_MAX_RATIO.recip(), 1.);
+
         buffer
             .channels()
             .iter()
@@ -679,6 +806,12 @@ impl AudioProcessor for AudioBufferSourceRenderer {
                     .zip(output_channel.iter_mut())
                     .for_each(|(playhead, o)| {
                         *o = match playhead {
+                            Some(PlaybackInfo {
+                                prev_frame_index,
+                                k,
+                            }) if playback_quality == PlaybackQuality::Sinc => {
+                                sinc_interpolation(buffer_channel, *prev_frame_index, *k, cutoff)
+                            }
                             Some(PlaybackInfo {
                                 prev_frame_index,
                                 k,
@@ -884,6 +1017,30 @@ mod tests {
             assert_float_eq!(channel[..], expected[..], abs_all <= 0.);
         }
 
+        // fast track, stop time closer to the first dirac
+        {
+            let sample_rate = 480000.;
+            let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+
+            let mut dirac = context.create_buffer(1, RENDER_QUANTUM_SIZE, sample_rate);
+            dirac.copy_to_channel(&[0., 0., 0., 0., 1., 1.], 0);
+
+            let src = context.create_buffer_source();
+            src.connect(&context.destination());
+            src.set_buffer(dirac);
+            src.start_at(0. / sample_rate as f64);
+            // the first dirac is before the stop time and should be played
+            src.stop_at(4.2 / sample_rate as f64);
+
+            let result = context.start_rendering_sync();
+            let channel = result.get_channel_data(0);
+
+            let mut expected = vec![0.; 128];
+            expected[4] = 1.;
+
+            assert_float_eq!(channel[..], expected[..], abs_all <= 0.);
+        }
+
         // slow track
         {
             let sample_rate = 480000.;
@@ -1288,4 +1445,108 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_playback_quality() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        let src = context.create_buffer_source();
+        assert_eq!(src.playback_quality(), PlaybackQuality::Linear);
+
+        src.set_playback_quality(PlaybackQuality::Sinc);
+        assert_eq!(src.playback_quality(), PlaybackQuality::Sinc);
+
+        let options = AudioBufferSourceOptions {
+            playback_quality: PlaybackQuality::Sinc,
+            ..AudioBufferSourceOptions::default()
+        };
+        let src = AudioBufferSourceNode::new(&context, options);
+        assert_eq!(src.playback_quality(), PlaybackQuality::Sinc);
+    }
+
+    // render a sine of the given frequency transposed by the given playback rate
+    fn render_transposed_sine(
+        frequency: f32,
+        playback_rate: f32,
+        playback_quality: PlaybackQuality,
+    ) -> Vec<f32> {
+        let sample_rate = 44_100.;
+        let length = RENDER_QUANTUM_SIZE * 8;
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+
+        let buffer_length = length * 4;
+        let mut buffer = context.create_buffer(1, buffer_length, sample_rate);
+        let sine: Vec<f32> = (0..buffer_length)
+            .map(|i| (i as f32 / sample_rate * frequency * 2. * PI).sin())
+            .collect();
+        buffer.copy_to_channel(&sine, 0);
+
+        let options = AudioBufferSourceOptions {
+            buffer: Some(buffer),
+            playback_rate,
+            playback_quality,
+            ..AudioBufferSourceOptions::default()
+        };
+        let src = AudioBufferSourceNode::new(&context, options);
+        src.connect(&context.destination());
+        src.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_sinc_interpolation_accuracy() {
+        // transposing a sine down an octave should be closer to the exact
+        // signal with the sinc interpolation than with the linear interpolation
+        let frequency = 2000.;
+        let playback_rate = 0.5;
+        let sample_rate = 44_100.;
+
+        let max_error = |rendered: &[f32]| {
+            rendered
+                .iter()
+                .enumerate()
+                // skip the start of the buffer, where the kernel sees silence
+                .skip(SINC_ZERO_CROSSINGS * 2)
+                .map(|(i, v)| {
+                    let t = i as f64 * playback_rate / sample_rate;
+                    let exact = (t * frequency * 2. * std::f64::consts::PI).sin();
+                    (*v as f64 - exact).abs()
+                })
+                .fold(0., f64::max)
+        };
+
+        let linear = render_transposed_sine(
+            frequency as f32,
+            playback_rate as f32,
+            PlaybackQuality::Linear,
+        );
+        let sinc = render_transposed_sine(
+            frequency as f32,
+            playback_rate as f32,
+            PlaybackQuality::Sinc,
+        );
+
+        let linear_error = max_error(&linear);
+        let sinc_error = max_error(&sinc);
+
+        assert!(sinc_error < linear_error / 4.);
+        assert!(sinc_error < 1e-3);
+    }
+
+    #[test]
+    fn test_sinc_interpolation_anti_aliasing() {
+        // transposing a 15kHz sine up an octave exceeds the Nyquist frequency,
+        // the sinc interpolation should filter it out while the linear one aliases
+        let rms = |rendered: &[f32]| {
+            let signal = &rendered[SINC_ZERO_CROSSINGS * 4..];
+            (signal.iter().map(|v| v * v).sum::<f32>() / signal.len() as f32).sqrt()
+        };
+
+        let linear = render_transposed_sine(15_000., 2., PlaybackQuality::Linear);
+        let sinc = render_transposed_sine(15_000., 2., PlaybackQuality::Sinc);
+
+        assert!(rms(&linear) > 0.1);
+        assert!(rms(&sinc) < 0.01);
+    }
 }
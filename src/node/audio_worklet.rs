@@ -0,0 +1,592 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::AudioProcessor;
+use crate::{MessagePort, MAX_CHANNELS};
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Describes an [`AudioParam`] of the nodes created for a registered processor
+#[derive(Clone, Debug)]
+pub struct AudioWorkletParamDescriptor {
+    /// Name of the parameter, key of [`AudioWorkletNode::parameters`]
+    pub name: String,
+    pub default_value: f32,
+    pub min_value: f32,
+    pub max_value: f32,
+    pub automation_rate: AutomationRate,
+}
+
+impl AudioWorkletParamDescriptor {
+    /// Describe an a-rate parameter with the full `f32` range and a default value of 0
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            default_value: 0.,
+            min_value: f32::MIN,
+            max_value: f32::MAX,
+            automation_rate: AutomationRate::A,
+        }
+    }
+}
+
+/// Everything a registered factory needs to construct the processor of an [`AudioWorkletNode`]
+#[derive(Debug)]
+pub struct AudioWorkletProcessorOptions {
+    pub number_of_inputs: usize,
+    pub number_of_outputs: usize,
+    /// Number of channels of each output, empty when not specified
+    pub output_channel_count: Vec<usize>,
+    /// Ids of the parameters declared by the factory, by name
+    pub parameters: HashMap<String, AudioParamId>,
+    /// The `processor_options` given to the node
+    pub processor_options: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+/// Factory of the processors of a named [`AudioWorkletNode`] type
+///
+/// This is the counterpart of the `AudioWorkletProcessor` class given to `registerProcessor`
+/// in the browser. A closure taking [`AudioWorkletProcessorOptions`] and returning the
+/// processor can be used directly when the processor has no parameters.
+pub trait AudioWorkletProcessorFactory: Send + Sync {
+    /// The parameters of the nodes created for this processor, the counterpart of the static
+    /// `parameterDescriptors` getter
+    fn parameter_descriptors(&self) -> Vec<AudioWorkletParamDescriptor> {
+        Vec::new()
+    }
+
+    /// Construct the processor of a new node
+    fn create(&self, options: AudioWorkletProcessorOptions) -> Box<dyn AudioProcessor>;
+}
+
+impl<F> AudioWorkletProcessorFactory for F
+where
+    F: Fn(AudioWorkletProcessorOptions) -> Box<dyn AudioProcessor> + Send + Sync,
+{
+    fn create(&self, options: AudioWorkletProcessorOptions) -> Box<dyn AudioProcessor> {
+        (self)(options)
+    }
+}
+
+/// Assert that the parameter descriptors of a factory are valid
+///
+/// # Panics
+///
+/// This function panics if two descriptors have the same name, or if a default value is outside
+/// the range of its descriptor
+///
+#[track_caller]
+#[inline(always)]
+pub(crate) fn assert_valid_parameter_descriptors(descriptors: &[AudioWorkletParamDescriptor]) {
+    descriptors.iter().enumerate().for_each(|(i, descriptor)| {
+        if descriptors[..i].iter().any(|d| d.name == descriptor.name) {
+            panic!(
+                "NotSupportedError - Duplicate parameter name: {:?}",
+                descriptor.name
+            );
+        }
+
+        if !(descriptor.min_value..=descriptor.max_value).contains(&descriptor.default_value) {
+            panic!(
+                "InvalidStateError - Default value {:?} of parameter {:?} is outside range [{:?}, {:?}]",
+                descriptor.default_value, descriptor.name, descriptor.min_value, descriptor.max_value
+            );
+        }
+    });
+}
+
+/// The parameters of an [`AudioWorkletNode`], in the order of their declaration
+///
+/// This is a read-only map of the [`AudioParam`]s by name, all automation methods are
+/// available on the params.
+pub struct AudioParamMap {
+    params: Vec<(String, AudioParam)>,
+}
+
+impl AudioParamMap {
+    /// The param with the given name
+    pub fn get(&self, name: &str) -> Option<&AudioParam> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, param)| param)
+    }
+
+    /// Check whether a param with the given name exists
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// The number of params
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Check whether the node has no params
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    /// The names of the params
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.params.iter().map(|(key, _)| key.as_str())
+    }
+
+    /// The params
+    pub fn values(&self) -> impl Iterator<Item = &AudioParam> {
+        self.params.iter().map(|(_, param)| param)
+    }
+
+    /// The params with their names
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AudioParam)> {
+        self.params.iter().map(|(key, param)| (key.as_str(), param))
+    }
+}
+
+impl std::ops::Index<&str> for AudioParamMap {
+    type Output = AudioParam;
+
+    /// # Panics
+    ///
+    /// Will panic if no param has the given name
+    fn index(&self, name: &str) -> &AudioParam {
+        self.get(name)
+            .unwrap_or_else(|| panic!("No AudioParam named {:?}", name))
+    }
+}
+
+/// Options for constructing an [`AudioWorkletNode`]
+#[derive(Clone, Debug)]
+pub struct AudioWorkletNodeOptions {
+    pub number_of_inputs: usize,
+    pub number_of_outputs: usize,
+    /// Number of channels of each output, empty to let the processor decide
+    pub output_channel_count: Vec<usize>,
+    /// Initial values of the parameters, by name
+    pub parameter_data: HashMap<String, f64>,
+    /// Arbitrary data passed to the processor factory
+    pub processor_options: Option<Arc<dyn Any + Send + Sync>>,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for AudioWorkletNodeOptions {
+    fn default() -> Self {
+        Self {
+            number_of_inputs: 1,
+            number_of_outputs: 1,
+            output_channel_count: Vec::new(),
+            parameter_data: HashMap::new(),
+            processor_options: None,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Assert that the number of inputs and outputs and the output channel counts are valid
+///
+/// # Panics
+///
+/// This function panics if the node has neither inputs nor outputs, if the number of output
+/// channel counts does not match the number of outputs, or if a channel count is outside the
+/// [1, 32] range
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_options(options: &AudioWorkletNodeOptions) {
+    if options.number_of_inputs == 0 && options.number_of_outputs == 0 {
+        panic!("NotSupportedError - AudioWorkletNode must have at least one input or output");
+    }
+
+    if options.output_channel_count.is_empty() {
+        return;
+    }
+
+    if options.output_channel_count.len() != options.number_of_outputs {
+        panic!(
+            "IndexSizeError - Length of output_channel_count {:?} does not match the number of outputs {:?}",
+            options.output_channel_count.len(),
+            options.number_of_outputs
+        );
+    }
+
+    options.output_channel_count.iter().for_each(|&count| {
+        if !(1..=MAX_CHANNELS).contains(&count) {
+            panic!(
+                "NotSupportedError - Invalid output channel count: {:?} is outside range [1, {:?}]",
+                count, MAX_CHANNELS
+            );
+        }
+    });
+}
+
+/// `AudioWorkletNode` runs a processor registered by name on the context
+///
+/// Processor factories are registered with
+/// [`BaseAudioContext::register_processor`], and instantiated by passing their name to
+/// [`AudioWorkletNode::new`], as with `registerProcessor` and the `AudioWorkletNode`
+/// constructor in the browser.
+///
+/// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/AudioWorkletNode>
+/// - specification: <https://webaudio.github.io/web-audio-api/#AudioWorkletNode>
+///
+/// The processor runs on the render thread of the context, there is no separate worklet
+/// scope. Messages are exchanged with the processor through the [`port`](Self::port) of the
+/// node.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{
+///     AudioWorkletNode, AudioWorkletNodeOptions, AudioWorkletProcessorOptions,
+/// };
+/// use web_audio_api::render::{
+///     AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope,
+/// };
+///
+/// struct BitCrusher;
+///
+/// impl AudioProcessor for BitCrusher {
+///     fn process(
+///         &mut self,
+///         inputs: &[AudioRenderQuantum],
+///         outputs: &mut [AudioRenderQuantum],
+///         _params: AudioParamValues,
+///         _scope: &RenderScope,
+///     ) -> bool {
+///         outputs[0] = inputs[0].clone();
+///         outputs[0].channels_mut().iter_mut().for_each(|channel| {
+///             channel.iter_mut().for_each(|s| *s = (*s * 8.).round() / 8.);
+///         });
+///         false
+///     }
+/// }
+///
+/// let context = AudioContext::default();
+/// context.register_processor("bit-crusher", |_options: AudioWorkletProcessorOptions| {
+///     Box::new(BitCrusher) as Box<dyn AudioProcessor>
+/// });
+///
+/// let crusher = AudioWorkletNode::new(&context, "bit-crusher", AudioWorkletNodeOptions::default());
+/// crusher.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&crusher);
+/// osc.start();
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct AudioWorkletNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    number_of_inputs: usize,
+    number_of_outputs: usize,
+    parameters: AudioParamMap,
+}
+
+impl AudioNode for AudioWorkletNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        self.number_of_inputs
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        self.number_of_outputs
+    }
+}
+
+impl AudioWorkletNode {
+    /// Construct a node running the processor registered under the given name
+    ///
+    /// # Panics
+    ///
+    /// This function panics if:
+    /// - no processor is registered under the given name
+    /// - the node has neither inputs nor outputs
+    /// - `options.output_channel_count` is not empty and its length does not match
+    ///   `options.number_of_outputs`, or one of its values is outside the [1, 32] range
+    pub fn new<C: BaseAudioContext>(
+        context: &C,
+        name: &str,
+        options: AudioWorkletNodeOptions,
+    ) -> Self {
+        let factory = context.base().processor_factory(name).unwrap_or_else(|| {
+            panic!(
+                "InvalidStateError - No AudioWorkletProcessor registered as {:?}",
+                name
+            )
+        });
+
+        assert_valid_options(&options);
+
+        context.register(move |registration| {
+            let AudioWorkletNodeOptions {
+                number_of_inputs,
+                number_of_outputs,
+                output_channel_count,
+                parameter_data,
+                processor_options,
+                channel_config,
+            } = options;
+
+            let mut parameters = Vec::new();
+            let mut parameter_ids = HashMap::new();
+
+            factory
+                .parameter_descriptors()
+                .into_iter()
+                .for_each(|descriptor| {
+                    let param_opts = AudioParamDescriptor {
+                        min_value: descriptor.min_value,
+                        max_value: descriptor.max_value,
+                        default_value: descriptor.default_value,
+                        automation_rate: descriptor.automation_rate,
+                    };
+                    let (param, proc) = context
+                        .base()
+                        .create_full_buffer_audio_param(param_opts, &registration);
+                    if let Some(&value) = parameter_data.get(&descriptor.name) {
+                        param.set_value(value as f32);
+                    }
+
+                    parameters.push((descriptor.name.clone(), param));
+                    parameter_ids.insert(descriptor.name, proc);
+                });
+
+            let processor_options = AudioWorkletProcessorOptions {
+                number_of_inputs,
+                number_of_outputs,
+                output_channel_count,
+                parameters: parameter_ids,
+                processor_options,
+            };
+            let render = factory.create(processor_options);
+
+            let node = AudioWorkletNode {
+                registration,
+                channel_config: channel_config.into(),
+                number_of_inputs,
+                number_of_outputs,
+                parameters: AudioParamMap { params: parameters },
+            };
+
+            (node, render)
+        })
+    }
+
+    /// The parameters declared by the processor factory, by name
+    ///
+    /// Custom processors get sample accurate automation of their parameters this way, read
+    /// with [`AudioParamValues::get`](crate::render::AudioParamValues::get) and the ids of
+    /// [`AudioWorkletProcessorOptions::parameters`]. Unlike for the built-in nodes, a-rate
+    /// parameters always provide a full render quantum of values, also when constant.
+    pub fn parameters(&self) -> &AudioParamMap {
+        &self.parameters
+    }
+
+    /// Message port to communicate with the processor
+    pub fn port(&self) -> MessagePort<'_> {
+        MessagePort::from_node(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+    use crate::render::{AudioParamValues, AudioRenderQuantum, RenderScope};
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    struct GainProcessor {
+        gain: AudioParamId,
+    }
+
+    impl AudioProcessor for GainProcessor {
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            let gain = params.get(&self.gain);
+            // a-rate params of custom processors always have a full render quantum of values
+            assert_eq!(gain.len(), RENDER_QUANTUM_SIZE);
+
+            outputs[0] = inputs[0].clone();
+            outputs[0].channels_mut().iter_mut().for_each(|channel| {
+                channel
+                    .iter_mut()
+                    .zip(gain.iter())
+                    .for_each(|(s, g)| *s *= g);
+            });
+            false
+        }
+    }
+
+    struct GainFactory;
+
+    impl AudioWorkletProcessorFactory for GainFactory {
+        fn parameter_descriptors(&self) -> Vec<AudioWorkletParamDescriptor> {
+            vec![AudioWorkletParamDescriptor {
+                default_value: 1.,
+                ..AudioWorkletParamDescriptor::new("gain")
+            }]
+        }
+
+        fn create(&self, mut options: AudioWorkletProcessorOptions) -> Box<dyn AudioProcessor> {
+            let gain = options.parameters.remove("gain").unwrap();
+            Box::new(GainProcessor { gain })
+        }
+    }
+
+    #[test]
+    fn test_registered_processor() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        context.register_processor("gain", GainFactory);
+
+        let mut options = AudioWorkletNodeOptions::default();
+        options.parameter_data.insert("gain".to_string(), 0.5);
+        let node = AudioWorkletNode::new(&context, "gain", options);
+        node.connect(&context.destination());
+
+        assert_eq!(node.number_of_inputs(), 1);
+        assert_eq!(node.number_of_outputs(), 1);
+        assert_eq!(node.parameters().len(), 1);
+        assert_float_eq!(node.parameters()["gain"].value(), 0.5, abs <= 0.);
+        assert_float_eq!(node.parameters()["gain"].default_value(), 1., abs <= 0.);
+
+        let src = context.create_constant_source();
+        src.connect(&node);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[0.5; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    struct ScaleProcessor(f32);
+
+    impl AudioProcessor for ScaleProcessor {
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0] = inputs[0].clone();
+            outputs[0].channels_mut().iter_mut().for_each(|channel| {
+                channel.iter_mut().for_each(|s| *s *= self.0);
+            });
+            false
+        }
+    }
+
+    #[test]
+    fn test_closure_factory() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        context.register_processor("scale", |options: AudioWorkletProcessorOptions| {
+            let scale = options
+                .processor_options
+                .and_then(|o| o.downcast_ref::<f32>().copied())
+                .unwrap_or(1.);
+            Box::new(ScaleProcessor(scale)) as Box<dyn AudioProcessor>
+        });
+
+        let options = AudioWorkletNodeOptions {
+            processor_options: Some(Arc::new(2_f32)),
+            ..AudioWorkletNodeOptions::default()
+        };
+        let node = AudioWorkletNode::new(&context, "scale", options);
+        node.connect(&context.destination());
+        assert!(node.parameters().is_empty());
+
+        let src = context.create_constant_source();
+        src.connect(&node);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[2.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_parameter_map() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        context.register_processor("gain", GainFactory);
+
+        let node = AudioWorkletNode::new(&context, "gain", AudioWorkletNodeOptions::default());
+        node.connect(&context.destination());
+
+        let params = node.parameters();
+        assert!(params.contains_key("gain"));
+        assert!(params.get("frequency").is_none());
+        assert_eq!(params.keys().collect::<Vec<_>>(), vec!["gain"]);
+
+        // automation is rendered by the custom processor
+        let end = RENDER_QUANTUM_SIZE as f64 / 44_100.;
+        params["gain"]
+            .set_value_at_time(0., 0.)
+            .linear_ramp_to_value_at_time(1., end);
+
+        let src = context.create_constant_source();
+        src.connect(&node);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let expected: Vec<f32> = (0..RENDER_QUANTUM_SIZE)
+            .map(|i| i as f32 / RENDER_QUANTUM_SIZE as f32)
+            .collect();
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_unknown_processor() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        let _ = AudioWorkletNode::new(&context, "unknown", AudioWorkletNodeOptions::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_duplicate_registration() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        context.register_processor("gain", GainFactory);
+        context.register_processor("gain", GainFactory);
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_invalid_output_channel_count() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        context.register_processor("gain", GainFactory);
+
+        let options = AudioWorkletNodeOptions {
+            output_channel_count: vec![1, 2],
+            ..AudioWorkletNodeOptions::default()
+        };
+        let _ = AudioWorkletNode::new(&context, "gain", options);
+    }
+}
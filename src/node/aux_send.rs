@@ -0,0 +1,223 @@
+use crate::context::BaseAudioContext;
+use crate::param::AudioParam;
+
+use super::{AudioNode, GainNode, GainOptions};
+
+/// Options for constructing an [`AuxBus`]
+#[derive(Clone, Debug)]
+pub struct AuxBusOptions {
+    /// Initial gain of the return to the main mix
+    pub return_level: f32,
+}
+
+impl Default for AuxBusOptions {
+    fn default() -> Self {
+        Self { return_level: 1. }
+    }
+}
+
+/// `AuxBus` is a shared effects bus, fed by any number of [`AuxSend`]s
+///
+/// The bus is a small subgraph: the sends are summed at the bus input, go through
+/// the effect (e.g. a [`ConvolverNode`](super::ConvolverNode) or a
+/// [`ReverbNode`](super::ReverbNode)) and come back through the return gain, which
+/// is to be connected to the main mix.
+///
+/// This is the send/return topology of a mixing console: the effect is instantiated
+/// once and each channel controls how much of its signal is sent to it.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{AuxBus, AuxBusOptions, ReverbNode, ReverbOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let reverb = ReverbNode::new(&context, ReverbOptions::default());
+/// let bus = AuxBus::new(&context, &reverb, AuxBusOptions::default());
+/// bus.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&context.destination());
+/// osc.start();
+///
+/// // send a quarter of the oscillator to the reverb
+/// let send = bus.send(&osc, 0.25);
+///
+/// // e.g. in the event handler of the UI send knob
+/// send.level().set_value(0.5);
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct AuxBus {
+    input: GainNode,
+    output: GainNode,
+}
+
+impl AuxBus {
+    /// Create a bus routing its input through the given effect to its return
+    ///
+    /// The effect node is not owned by the bus, its parameters are still controlled
+    /// through its own handle.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the effect belongs to another context
+    pub fn new<C: BaseAudioContext>(
+        context: &C,
+        effect: &dyn AudioNode,
+        options: AuxBusOptions,
+    ) -> Self {
+        let input = GainNode::new(context, GainOptions::default());
+        let output = GainNode::new(context, GainOptions::default());
+        output.gain().set_value(options.return_level);
+
+        input.connect(effect).connect(&output);
+
+        Self { input, output }
+    }
+
+    /// Summing point of the sends, connected to the effect
+    pub fn input(&self) -> &GainNode {
+        &self.input
+    }
+
+    /// Return of the effect, to be connected to the main mix
+    pub fn output(&self) -> &GainNode {
+        &self.output
+    }
+
+    /// Gain of the return to the main mix
+    pub fn return_level(&self) -> &AudioParam {
+        self.output.gain()
+    }
+
+    /// Connect the return of the bus to the given node
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the destination belongs to another context
+    pub fn connect<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
+        self.output.connect(dest)
+    }
+
+    /// Disconnect the return of the bus from all its destinations
+    pub fn disconnect(&self) {
+        self.output.disconnect();
+    }
+
+    /// Send the output of the given node to the bus, with an initial send level
+    ///
+    /// The source keeps its other connections, e.g. to the main mix.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the source belongs to another context
+    pub fn send(&self, source: &dyn AudioNode, level: f32) -> AuxSend {
+        let gain = GainNode::new(self.input.context(), GainOptions::default());
+        gain.gain().set_value(level);
+
+        source.connect(&gain).connect(&self.input);
+
+        AuxSend { gain }
+    }
+}
+
+/// A send of a signal to an [`AuxBus`], created with [`AuxBus::send`]
+pub struct AuxSend {
+    gain: GainNode,
+}
+
+impl AuxSend {
+    /// Gain applied to the signal sent to the bus
+    pub fn level(&self) -> &AudioParam {
+        self.gain.gain()
+    }
+
+    /// Stop sending to the bus
+    ///
+    /// The level can not be used anymore afterwards, the send is released once the
+    /// source is disconnected or ended.
+    pub fn remove(self) {
+        self.gain.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.;
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let effect = context.create_gain();
+
+        let options = AuxBusOptions { return_level: 0.5 };
+        let bus = AuxBus::new(&context, &effect, options);
+        assert_float_eq!(bus.return_level().value(), 0.5, abs <= 0.);
+
+        let src = context.create_constant_source();
+        let send = bus.send(&src, 0.25);
+        assert_float_eq!(send.level().value(), 0.25, abs <= 0.);
+    }
+
+    #[test]
+    fn test_send_return() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, SAMPLE_RATE);
+
+        let effect = context.create_gain();
+        effect.gain().set_value(2.);
+
+        let options = AuxBusOptions { return_level: 0.5 };
+        let bus = AuxBus::new(&context, &effect, options);
+        bus.connect(&context.destination());
+
+        let src1 = context.create_constant_source();
+        src1.connect(&context.destination());
+        src1.start();
+        let _send1 = bus.send(&src1, 0.5);
+
+        let src2 = context.create_constant_source();
+        src2.offset().set_value(2.);
+        src2.start();
+        let _send2 = bus.send(&src2, 0.25);
+
+        // dry: 1, wet: (1 * 0.5 + 2 * 0.25) * 2 * 0.5
+        let output = context.start_rendering_sync();
+        let expected = [2.; RENDER_QUANTUM_SIZE];
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_remove_send() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, SAMPLE_RATE);
+
+        let effect = context.create_gain();
+        let bus = AuxBus::new(&context, &effect, AuxBusOptions::default());
+        bus.connect(&context.destination());
+
+        let src1 = context.create_constant_source();
+        src1.start();
+        let send1 = bus.send(&src1, 1.);
+
+        let src2 = context.create_constant_source();
+        src2.start();
+        let _send2 = bus.send(&src2, 0.5);
+
+        send1.remove();
+
+        let output = context.start_rendering_sync();
+        let expected = [0.5; RENDER_QUANTUM_SIZE];
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+    }
+}
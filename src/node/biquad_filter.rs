@@ -9,7 +9,11 @@ use crate::param::{AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
 
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+use super::bypass::Bypass;
+use super::{AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions, MAX_DETUNE};
+
+/// Nominal maximum of the gain param in dB, i.e. `40 * log10(f32::MAX)`
+const MAX_GAIN: f32 = 1_541.273_6;
 
 fn get_computed_freq(freq: f32, detune: f32) -> f32 {
     freq * (detune / 1200.).exp2()
@@ -17,7 +21,7 @@ fn get_computed_freq(freq: f32, detune: f32) -> f32 {
 
 /// Biquad filter coefficients normalized against a0
 #[derive(Clone, Copy, Debug, Default)]
-struct Coefficients {
+pub(super) struct Coefficients {
     b0: f64,
     b1: f64,
     b2: f64,
@@ -25,9 +29,41 @@ struct Coefficients {
     a2: f64,
 }
 
+impl Coefficients {
+    /// Evaluate the transfer function at the given normalized frequency, i.e.
+    /// from 0 to 1 where 1 corresponds to the Nyquist frequency
+    pub(super) fn frequency_response(&self, frequency: f64) -> Complex<f64> {
+        let Self { b0, b1, b2, a1, a2 } = *self;
+
+        // @note - comment from Firefox source code, blink/Biquad.cpp
+        //
+        // Evaluate the Z-transform of the filter at given normalized
+        // frequency from 0 to 1.  (1 corresponds to the Nyquist
+        // frequency.)
+        //
+        // The z-transform of the filter is
+        //
+        // H(z) = (b0 + b1*z^(-1) + b2*z^(-2))/(1 + a1*z^(-1) + a2*z^(-2))
+        //
+        // Evaluate as
+        //
+        // b0 + (b1 + b2*z1)*z1
+        // --------------------
+        // 1 + (a1 + a2*z1)*z1
+        //
+        // with z1 = 1/z and z = exp(j*pi*frequency). Hence z1 = exp(-j*pi*frequency)
+        let omega = -1. * PI * frequency;
+        let z = Complex::new(omega.cos(), omega.sin());
+        let numerator = b0 + (b1 + b2 * z) * z;
+        let denominator = Complex::new(1., 0.) + (a1 + a2 * z) * z;
+
+        numerator / denominator
+    }
+}
+
 // allow non snake to better the variable names in the spec
 #[allow(non_snake_case)]
-fn calculate_coefs(
+pub(super) fn calculate_coefs(
     filter_type: BiquadFilterType,
     sample_rate: f64,
     f0: f64,
@@ -310,6 +346,7 @@ pub struct BiquadFilterNode {
     gain: AudioParam,
     /// `BiquadFilterType` represented as u32
     type_: Arc<AtomicU32>,
+    bypass: Bypass,
 }
 
 impl AudioNode for BiquadFilterNode {
@@ -330,6 +367,16 @@ impl AudioNode for BiquadFilterNode {
     }
 }
 
+impl AudioEffectNode for BiquadFilterNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
+}
+
 impl BiquadFilterNode {
     /// returns a `BiquadFilterNode` instance
     ///
@@ -351,8 +398,8 @@ impl BiquadFilterNode {
             q_param.set_value(options.q);
 
             let detune_options = AudioParamDescriptor {
-                min_value: -153_600.,
-                max_value: 153_600.,
+                min_value: -MAX_DETUNE,
+                max_value: MAX_DETUNE,
                 default_value: 0.,
                 automation_rate: crate::param::AutomationRate::A,
             };
@@ -370,7 +417,7 @@ impl BiquadFilterNode {
 
             let gain_options = AudioParamDescriptor {
                 min_value: f32::MIN,
-                max_value: f32::MAX,
+                max_value: MAX_GAIN,
                 default_value: 0.,
                 automation_rate: crate::param::AutomationRate::A,
             };
@@ -385,14 +432,12 @@ impl BiquadFilterNode {
                 frequency: f_proc,
                 q: q_proc,
                 type_: type_.clone(),
-                x1: Vec::with_capacity(MAX_CHANNELS),
-                x2: Vec::with_capacity(MAX_CHANNELS),
-                y1: Vec::with_capacity(MAX_CHANNELS),
-                y2: Vec::with_capacity(MAX_CHANNELS),
+                state: Vec::with_capacity(MAX_CHANNELS),
             };
 
             let node = Self {
                 registration,
+                bypass: Bypass::default(),
                 channel_config: options.channel_config.into(),
                 type_,
                 q: q_param,
@@ -401,6 +446,7 @@ impl BiquadFilterNode {
                 gain: g_param,
             };
 
+            let renderer = node.bypass.wrap(renderer, 0);
             (node, Box::new(renderer))
         })
     }
@@ -452,6 +498,9 @@ impl BiquadFilterNode {
     /// * `mag_response` - magnitude of the frequency response of the filter
     /// * `phase_response` - phase of the frequency response of the filter
     ///
+    /// Frequencies outside the `[0, sample_rate / 2]` range yield `NaN` for both
+    /// magnitude and phase.
+    ///
     /// # Panics
     ///
     /// This function will panic if arguments' lengths don't match
@@ -477,7 +526,7 @@ impl BiquadFilterNode {
 
         // get coefs
         let computed_freq = get_computed_freq(frequency, detune);
-        let Coefficients { b0, b1, b2, a1, a2 } = calculate_coefs(
+        let coefs = calculate_coefs(
             type_,
             sample_rate as f64,
             computed_freq as f64,
@@ -485,32 +534,19 @@ impl BiquadFilterNode {
             q as f64,
         );
 
-        // @note - comment from Firefox source code, blink/Biquad.cpp
-        //
-        // Evaluate the Z-transform of the filter at given normalized
-        // frequency from 0 to 1.  (1 corresponds to the Nyquist
-        // frequency.)
-        //
-        // The z-transform of the filter is
-        //
-        // H(z) = (b0 + b1*z^(-1) + b2*z^(-2))/(1 + a1*z^(-1) + a2*z^(-2))
-        //
-        // Evaluate as
-        //
-        // b0 + (b1 + b2*z1)*z1
-        // --------------------
-        // 1 + (a1 + a2*z1)*z1
-        //
-        // with z1 = 1/z and z = exp(j*pi*frequency). Hence z1 = exp(-j*pi*frequency)
-        for (i, freq) in frequency_hz.iter().enumerate() {
-            // clamp and normalize frequency
-            let f = freq.clamp(0., n_quist) / n_quist;
+        for (i, &freq) in frequency_hz.iter().enumerate() {
+            // [spec] If a value in the frequencyHz parameter is not within
+            // [0, sampleRate/2], the corresponding value in magResponse and
+            // phaseResponse must be NaN.
+            if !(0. ..=n_quist).contains(&freq) {
+                mag_response[i] = f32::NAN;
+                phase_response[i] = f32::NAN;
+                continue;
+            }
 
-            let omega = -1. * PI * f64::from(f);
-            let z = Complex::new(omega.cos(), omega.sin());
-            let numerator = b0 + (b1 + b2 * z) * z;
-            let denominator = Complex::new(1., 0.) + (a1 + a2 * z) * z;
-            let response = numerator / denominator;
+            // normalize frequency
+            let f = freq / n_quist;
+            let response = coefs.frequency_response(f64::from(f));
 
             let (mag, phase) = response.to_polar();
             mag_response[i] = mag as f32;
@@ -536,10 +572,7 @@ struct BiquadFilterRenderer {
     /// `BiquadFilterType` repesented as u32
     type_: Arc<AtomicU32>,
     // keep filter state for each channel
-    x1: Vec<f64>,
-    x2: Vec<f64>,
-    y1: Vec<f64>,
-    y2: Vec<f64>,
+    state: Vec<BiquadState>,
 }
 
 impl AudioProcessor for BiquadFilterRenderer {
@@ -557,18 +590,8 @@ impl AudioProcessor for BiquadFilterRenderer {
 
         // handle tail time
         if input.is_silent() {
-            let mut ended = true;
-
-            if self.x1.iter().any(|&v| v.is_normal())
-                || self.x2.iter().any(|&v| v.is_normal())
-                || self.y1.iter().any(|&v| v.is_normal())
-                || self.y2.iter().any(|&v| v.is_normal())
-            {
-                ended = false;
-            }
-
             // input is silent and filter history is clean
-            if ended {
+            if self.state.iter().all(BiquadState::is_silent) {
                 output.make_silent();
                 return false;
             }
@@ -582,16 +605,13 @@ impl AudioProcessor for BiquadFilterRenderer {
             // see https://webaudio.github.io/web-audio-api/#channels-tail-time
             let num_channels = input.number_of_channels();
 
-            if num_channels != self.x1.len() {
-                self.x1.resize(num_channels, 0.);
-                self.x2.resize(num_channels, 0.);
-                self.y1.resize(num_channels, 0.);
-                self.y2.resize(num_channels, 0.);
+            if num_channels != self.state.len() {
+                self.state.resize(num_channels, BiquadState::default());
             }
 
             output.set_number_of_channels(num_channels);
         } else {
-            let num_channels = self.x1.len();
+            let num_channels = self.state.len();
             output.set_number_of_channels(num_channels);
         }
 
@@ -635,41 +655,218 @@ impl AudioProcessor for BiquadFilterRenderer {
                 });
         };
 
-        for (channel_number, output_channel) in output.channels_mut().iter_mut().enumerate() {
-            let input_channel = input.channel_data(channel_number);
-            // retrieve state from previous block
-            let mut x1 = self.x1[channel_number];
-            let mut x2 = self.x2[channel_number];
-            let mut y1 = self.y1[channel_number];
-            let mut y2 = self.y2[channel_number];
+        output
+            .channels_mut()
+            .iter_mut()
+            .zip(self.state.iter_mut())
+            .enumerate()
+            .for_each(|(channel_number, (output_channel, state))| {
+                let input_channel = input.channel_data(channel_number);
+                state.process(&coefs_list, input_channel, output_channel);
+            });
 
-            output_channel
-                .iter_mut()
-                .zip(input_channel.iter())
-                .zip(coefs_list.iter())
-                .for_each(|((o, &i), c)| {
-                    // 𝑎0𝑦(𝑛)+𝑎1𝑦(𝑛−1)+𝑎2𝑦(𝑛−2)=𝑏0𝑥(𝑛)+𝑏1𝑥(𝑛−1)+𝑏2𝑥(𝑛−2)
-                    // as all coefs are normalized against 𝑎0, we get
-                    // 𝑦(𝑛) = 𝑏0𝑥(𝑛) + 𝑏1𝑥(𝑛−1) + 𝑏2𝑥(𝑛−2) - 𝑎1𝑦(𝑛−1) - 𝑎2𝑦(𝑛−2)
-                    let x = f64::from(i);
-                    let y = c.b0 * x + c.b1 * x1 + c.b2 * x2 - c.a1 * y1 - c.a2 * y2;
-                    // update state
-                    x2 = x1;
-                    x1 = x;
-                    y2 = y1;
-                    y1 = y;
-                    // cast output value as f32
-                    *o = y as f32;
-                });
+        true
+    }
+}
 
-            // store channel state for next block
-            self.x1[channel_number] = x1;
-            self.x2[channel_number] = x2;
-            self.y1[channel_number] = y1;
-            self.y2[channel_number] = y2;
-        }
+/// Filter history of a single channel
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct BiquadState {
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
 
-        true
+impl BiquadState {
+    /// Returns true if the history only contains (sub-normal) silence
+    pub(super) fn is_silent(&self) -> bool {
+        !(self.x1.is_normal() || self.x2.is_normal() || self.y1.is_normal() || self.y2.is_normal())
+    }
+
+    /// Filter a block of samples, one set of coefficients per frame
+    pub(super) fn process(&mut self, coefs: &[Coefficients], input: &[f32], output: &mut [f32]) {
+        // retrieve state from previous block
+        let Self {
+            mut x1,
+            mut x2,
+            mut y1,
+            mut y2,
+        } = *self;
+
+        output
+            .iter_mut()
+            .zip(input.iter())
+            .zip(coefs.iter())
+            .for_each(|((o, &i), c)| {
+                // 𝑎0𝑦(𝑛)+𝑎1𝑦(𝑛−1)+𝑎2𝑦(𝑛−2)=𝑏0𝑥(𝑛)+𝑏1𝑥(𝑛−1)+𝑏2𝑥(𝑛−2)
+                // as all coefs are normalized against 𝑎0, we get
+                // 𝑦(𝑛) = 𝑏0𝑥(𝑛) + 𝑏1𝑥(𝑛−1) + 𝑏2𝑥(𝑛−2) - 𝑎1𝑦(𝑛−1) - 𝑎2𝑦(𝑛−2)
+                let x = f64::from(i);
+                let y = c.b0 * x + c.b1 * x1 + c.b2 * x2 - c.a1 * y1 - c.a2 * y2;
+                // update state
+                x2 = x1;
+                x1 = x;
+                y2 = y1;
+                y1 = y;
+                // cast output value as f32
+                *o = y as f32;
+            });
+
+        // store channel state for next block
+        *self = Self { x1, x2, y1, y2 };
+    }
+}
+
+/// Standalone biquad filter, processing blocks of audio outside of any audio context
+///
+/// This runs the same DSP as the [`BiquadFilterNode`] renderer, so it can be reused in e.g.
+/// plugins or tests without setting up an audio graph. The parameters are plain values that
+/// are applied to the whole block.
+///
+/// # Usage
+///
+/// ```
+/// use web_audio_api::node::{BiquadFilter, BiquadFilterOptions, BiquadFilterType};
+/// use web_audio_api::RENDER_QUANTUM_SIZE;
+///
+/// let options = BiquadFilterOptions {
+///     type_: BiquadFilterType::Highpass,
+///     frequency: 1000.,
+///     ..BiquadFilterOptions::default()
+/// };
+/// let mut filter = BiquadFilter::new(44_100., options);
+///
+/// // stereo block of DC offset, which is removed by the highpass filter
+/// let input = [[1.; RENDER_QUANTUM_SIZE]; 2];
+/// let output = filter.process(&input);
+/// assert_eq!(output.len(), 2);
+/// assert!(output[0][RENDER_QUANTUM_SIZE - 1].abs() < 0.1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BiquadFilter {
+    sample_rate: f32,
+    type_: BiquadFilterType,
+    q: f32,
+    detune: f32,
+    frequency: f32,
+    gain: f32,
+    state: Vec<BiquadState>,
+    output: Vec<[f32; RENDER_QUANTUM_SIZE]>,
+}
+
+impl BiquadFilter {
+    /// Create a new filter for the given sample rate
+    ///
+    /// The `channel_config` of the options is ignored, the number of channels is determined by
+    /// the processed blocks.
+    pub fn new(sample_rate: f32, options: BiquadFilterOptions) -> Self {
+        let mut filter = Self {
+            sample_rate,
+            type_: options.type_,
+            q: options.q,
+            detune: 0.,
+            frequency: 0.,
+            gain: options.gain,
+            state: Vec::with_capacity(MAX_CHANNELS),
+            output: Vec::with_capacity(MAX_CHANNELS),
+        };
+        filter.set_detune(options.detune);
+        filter.set_frequency(options.frequency);
+
+        filter
+    }
+
+    /// Returns the biquad filter type
+    #[must_use]
+    pub fn type_(&self) -> BiquadFilterType {
+        self.type_
+    }
+
+    /// Set the biquad filter type
+    pub fn set_type(&mut self, type_: BiquadFilterType) {
+        self.type_ = type_;
+    }
+
+    /// Returns the quality factor
+    #[must_use]
+    pub fn q(&self) -> f32 {
+        self.q
+    }
+
+    /// Set the quality factor
+    pub fn set_q(&mut self, q: f32) {
+        self.q = q;
+    }
+
+    /// Returns the detune value, in cents
+    #[must_use]
+    pub fn detune(&self) -> f32 {
+        self.detune
+    }
+
+    /// Set the detune value in cents, clamped to the range of [`BiquadFilterNode::detune`]
+    pub fn set_detune(&mut self, detune: f32) {
+        self.detune = detune.clamp(-MAX_DETUNE, MAX_DETUNE);
+    }
+
+    /// Returns the frequency, in Hz
+    #[must_use]
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    /// Set the frequency in Hz, clamped between 0 and the Nyquist frequency
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency.clamp(0., self.sample_rate / 2.);
+    }
+
+    /// Returns the gain, in dB
+    #[must_use]
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Set the gain in dB, clamped to the range of [`BiquadFilterNode::gain`]
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain.min(MAX_GAIN);
+    }
+
+    /// Clear the filter history
+    pub fn reset(&mut self) {
+        self.state.clear();
+    }
+
+    /// Filter a block of audio, one array per channel
+    ///
+    /// The filter history is kept between calls. When the number of channels changes, the
+    /// history of the additional channels starts out silent.
+    pub fn process(
+        &mut self,
+        input: &[[f32; RENDER_QUANTUM_SIZE]],
+    ) -> &[[f32; RENDER_QUANTUM_SIZE]] {
+        let computed_freq = get_computed_freq(self.frequency, self.detune);
+        let coef = calculate_coefs(
+            self.type_,
+            f64::from(self.sample_rate),
+            f64::from(computed_freq),
+            f64::from(self.gain),
+            f64::from(self.q),
+        );
+        let coefs_list = [coef; RENDER_QUANTUM_SIZE];
+
+        self.state.resize(input.len(), BiquadState::default());
+        self.output.resize(input.len(), [0.; RENDER_QUANTUM_SIZE]);
+
+        input
+            .iter()
+            .zip(self.state.iter_mut())
+            .zip(self.output.iter_mut())
+            .for_each(|((input_channel, state), output_channel)| {
+                state.process(&coefs_list, input_channel, output_channel);
+            });
+
+        &self.output
     }
 }
 
@@ -678,6 +875,7 @@ mod tests {
     use float_eq::assert_float_eq;
 
     use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
 
     use super::*;
 
@@ -737,7 +935,32 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    fn test_param_ranges() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let biquad = context.create_biquad_filter();
+
+        assert_float_eq!(biquad.q().min_value(), f32::MIN, abs <= 0.);
+        assert_float_eq!(biquad.q().max_value(), f32::MAX, abs <= 0.);
+        assert_float_eq!(biquad.detune().min_value(), -153_600., abs <= 0.);
+        assert_float_eq!(biquad.detune().max_value(), 153_600., abs <= 0.);
+        assert_float_eq!(biquad.frequency().min_value(), 0., abs <= 0.);
+        assert_float_eq!(biquad.frequency().max_value(), 22_050., abs <= 0.);
+        assert_float_eq!(biquad.gain().min_value(), f32::MIN, abs <= 0.);
+        assert_float_eq!(biquad.gain().max_value(), 1_541.273_6, abs <= 1e-3);
+
+        // the computed value is clamped, the automation event is not
+        biquad.gain().set_value(2_000.);
+        assert_float_eq!(biquad.gain().value(), MAX_GAIN, abs <= 0.);
+
+        let mut filter = BiquadFilter::new(44_100., BiquadFilterOptions::default());
+        filter.set_gain(2_000.);
+        assert_float_eq!(filter.gain(), MAX_GAIN, abs <= 0.);
+        filter.set_detune(-200_000.);
+        assert_float_eq!(filter.detune(), -MAX_DETUNE, abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidAccessError")]
     fn test_frequency_response_arguments() {
         let context = OfflineAudioContext::new(2, 555, 44_100.);
         let biquad = BiquadFilterNode::new(&context, BiquadFilterOptions::default());
@@ -750,7 +973,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "InvalidAccessError")]
     fn test_frequency_response_arguments_2() {
         let context = OfflineAudioContext::new(2, 555, 44_100.);
         let biquad = BiquadFilterNode::new(&context, BiquadFilterOptions::default());
@@ -762,6 +985,29 @@ mod tests {
         biquad.get_frequency_response(&frequency_hz, &mut mag_response, &mut phase_response);
     }
 
+    #[test]
+    fn test_frequency_response_out_of_range() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let biquad = context.create_biquad_filter();
+
+        let frequency_hz = [-1., 0., 22_050., 22_051., f32::NAN];
+        let mut mag_response = [0.; 5];
+        let mut phase_response = [0.; 5];
+
+        biquad.get_frequency_response(&frequency_hz, &mut mag_response, &mut phase_response);
+
+        assert!(mag_response[0].is_nan());
+        assert!(phase_response[0].is_nan());
+        assert!(!mag_response[1].is_nan());
+        assert!(!phase_response[1].is_nan());
+        assert!(!mag_response[2].is_nan());
+        assert!(!phase_response[2].is_nan());
+        assert!(mag_response[3].is_nan());
+        assert!(phase_response[3].is_nan());
+        assert!(mag_response[4].is_nan());
+        assert!(phase_response[4].is_nan());
+    }
+
     // @note: expected values retrieved from chrome and firefox, both being coherent
     #[test]
     #[allow(clippy::excessive_precision)]
@@ -1100,4 +1346,44 @@ mod tests {
             assert_float_eq!(phases, expected_phases, abs_all <= 1e-6);
         }
     }
+
+    #[test]
+    fn test_standalone_matches_node() {
+        let sample_rate = 44_100.;
+        let length = 4 * RENDER_QUANTUM_SIZE;
+        let signal: Vec<f32> = (0..length).map(|i| (i as f32 * 0.05).sin()).collect();
+
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+        let options = BiquadFilterOptions {
+            type_: BiquadFilterType::Peaking,
+            frequency: 2000.,
+            q: 3.,
+            gain: 6.,
+            ..BiquadFilterOptions::default()
+        };
+        let node = BiquadFilterNode::new(&context, options.clone());
+        node.connect(&context.destination());
+
+        let mut buffer = context.create_buffer(1, length, sample_rate);
+        buffer.copy_to_channel(&signal, 0);
+
+        let src = context.create_buffer_source();
+        src.connect(&node);
+        src.set_buffer(buffer);
+        src.start();
+
+        let result = context.start_rendering_sync();
+
+        let mut standalone = BiquadFilter::new(sample_rate, options);
+        signal
+            .chunks(RENDER_QUANTUM_SIZE)
+            .zip(result.get_channel_data(0).chunks(RENDER_QUANTUM_SIZE))
+            .for_each(|(input, expected)| {
+                let mut block = [0.; RENDER_QUANTUM_SIZE];
+                block.copy_from_slice(input);
+                let output = standalone.process(&[block]);
+                assert_eq!(output.len(), 1);
+                assert_float_eq!(output[0][..], expected[..], abs_all <= 0.);
+            });
+    }
 }
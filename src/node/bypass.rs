@@ -0,0 +1,268 @@
+//! Render side of the bypass of the [`AudioEffectNode`](super::AudioEffectNode)s
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
+
+use super::ChannelInterpretation;
+
+/// Control side of the bypass of an effect node, shared with its [`BypassProcessor`]
+#[derive(Debug, Default)]
+pub(crate) struct Bypass {
+    bypass: Arc<AtomicBool>,
+}
+
+impl Bypass {
+    /// Whether the processing of the node is bypassed
+    pub(crate) fn get(&self) -> bool {
+        self.bypass.load(Ordering::SeqCst)
+    }
+
+    /// Bypass the processing of the node, or release the bypass
+    pub(crate) fn set(&self, value: bool) {
+        self.bypass.store(value, Ordering::SeqCst);
+    }
+
+    /// Wrap the renderer of the node, the dry signal is delayed by the `latency` of the node in
+    /// frames
+    pub(crate) fn wrap<P: AudioProcessor>(
+        &self,
+        processor: P,
+        latency: usize,
+    ) -> BypassProcessor<P> {
+        BypassProcessor::new(processor, Arc::clone(&self.bypass), latency)
+    }
+}
+
+/// Wraps the renderer of an effect node, to crossfade its output with the dry input
+///
+/// The wrapped renderer keeps processing while bypassed. The dry input is delayed by the
+/// latency of the node, so both signals are aligned.
+pub(crate) struct BypassProcessor<P> {
+    processor: P,
+    bypass: Arc<AtomicBool>,
+    /// Gain of the dry signal at the end of the previous block, not set before the first
+    /// rendered block
+    dry_gain: Option<f32>,
+    /// Latency of the node, in frames
+    latency: usize,
+    /// Ring buffer of each channel of the dry signal, allocated for the maximum number of
+    /// channels so the render thread does not allocate
+    delay_line: Vec<Vec<f32>>,
+    /// Number of channels in use of the delay line
+    number_of_channels: usize,
+    write_index: usize,
+    /// Number of silent frames written to the delay line since the last non silent block
+    silent_frames: usize,
+}
+
+impl<P: AudioProcessor> BypassProcessor<P> {
+    fn new(processor: P, bypass: Arc<AtomicBool>, latency: usize) -> Self {
+        let delay_line = if latency == 0 {
+            Vec::new()
+        } else {
+            vec![vec![0.; latency]; MAX_CHANNELS]
+        };
+
+        Self {
+            processor,
+            bypass,
+            dry_gain: None,
+            latency,
+            delay_line,
+            number_of_channels: 0,
+            write_index: 0,
+            silent_frames: latency,
+        }
+    }
+
+    fn delay_line_is_empty(&self) -> bool {
+        self.silent_frames >= self.latency
+    }
+
+    /// Delay the dry signal by the latency of the node
+    fn delay(&mut self, dry: &mut AudioRenderQuantum) {
+        if self.latency == 0 {
+            return;
+        }
+
+        let was_empty = self.delay_line_is_empty();
+
+        if dry.is_silent() {
+            if was_empty {
+                return;
+            }
+            self.silent_frames += RENDER_QUANTUM_SIZE;
+        } else {
+            self.silent_frames = 0;
+        }
+
+        // the number of channels can only be reduced when the history is silent, the unused
+        // channels are silent then
+        let number_of_channels = if was_empty {
+            dry.number_of_channels()
+        } else {
+            dry.number_of_channels().max(self.number_of_channels)
+        };
+        self.number_of_channels = number_of_channels;
+        dry.mix(number_of_channels, ChannelInterpretation::Speakers);
+
+        let latency = self.latency;
+
+        let write_index = self.write_index;
+
+        dry.channels_mut()
+            .iter_mut()
+            .zip(self.delay_line.iter_mut())
+            .for_each(|(channel, buffer)| {
+                let mut index = write_index;
+                channel.iter_mut().for_each(|s| {
+                    std::mem::swap(s, &mut buffer[index]);
+                    index += 1;
+                    if index == latency {
+                        index = 0;
+                    }
+                });
+            });
+
+        self.write_index = (write_index + RENDER_QUANTUM_SIZE) % latency;
+    }
+}
+
+impl<P: AudioProcessor> AudioProcessor for BypassProcessor<P> {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let tail_time = self.processor.process(inputs, outputs, params, scope);
+
+        let to = if self.bypass.load(Ordering::SeqCst) {
+            1.
+        } else {
+            0.
+        };
+        let from = self.dry_gain.unwrap_or(to);
+        self.dry_gain = Some(to);
+
+        // the delay line is fed even when not bypassed, to be up to date when switching
+        let mut dry = inputs[0].clone();
+        self.delay(&mut dry);
+
+        if from == 0. && to == 0. {
+            return tail_time;
+        }
+
+        let output = &mut outputs[0];
+        let tail_time = tail_time || !self.delay_line_is_empty();
+
+        if from == 1. && to == 1. {
+            *output = dry;
+            return tail_time;
+        }
+
+        // crossfade over the render quantum
+        let number_of_channels = output.number_of_channels().max(dry.number_of_channels());
+        output.mix(number_of_channels, ChannelInterpretation::Speakers);
+        dry.mix(number_of_channels, ChannelInterpretation::Speakers);
+
+        let step = (to - from) / RENDER_QUANTUM_SIZE as f32;
+
+        output
+            .channels_mut()
+            .iter_mut()
+            .zip(dry.channels())
+            .for_each(|(wet, dry)| {
+                wet.iter_mut()
+                    .zip(dry.iter())
+                    .enumerate()
+                    .for_each(|(i, (w, &d))| {
+                        let gain = from + step * (i + 1) as f32;
+                        *w = *w * (1. - gain) + d * gain;
+                    });
+            });
+
+        tail_time
+    }
+
+    fn onmessage(&mut self, msg: Box<dyn Any + Send>) {
+        self.processor.onmessage(msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{
+        AudioEffectNode, AudioNode, AudioScheduledSourceNode, BiquadFilterNode,
+        BiquadFilterOptions, BiquadFilterType, LimiterNode, LimiterOptions,
+    };
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.;
+
+    #[test]
+    fn test_bypass() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, SAMPLE_RATE);
+
+        let options = BiquadFilterOptions {
+            type_: BiquadFilterType::Lowpass,
+            frequency: 10.,
+            ..BiquadFilterOptions::default()
+        };
+        let filter = BiquadFilterNode::new(&context, options);
+        filter.connect(&context.destination());
+
+        assert!(!filter.bypass());
+        filter.set_bypass(true);
+        assert!(filter.bypass());
+
+        let mut buffer = context.create_buffer(1, RENDER_QUANTUM_SIZE, SAMPLE_RATE);
+        let signal: Vec<f32> = (0..RENDER_QUANTUM_SIZE)
+            .map(|i| if i % 2 == 0 { 1. } else { -1. })
+            .collect();
+        buffer.copy_to_channel(&signal, 0);
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&filter);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(0), &signal[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_bypass_latency() {
+        let length = RENDER_QUANTUM_SIZE * 4;
+        let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let limiter = LimiterNode::new(&context, LimiterOptions::default());
+        limiter.set_bypass(true);
+        limiter.connect(&context.destination());
+
+        let latency = (limiter.latency() * f64::from(SAMPLE_RATE)).round() as usize;
+        assert!(latency > 0);
+
+        let mut buffer = context.create_buffer(1, 1, SAMPLE_RATE);
+        buffer.copy_to_channel(&[1.], 0);
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&limiter);
+        src.start();
+
+        // the dry impulse is delayed by the latency of the node
+        let output = context.start_rendering_sync();
+        let mut expected = vec![0.; length];
+        expected[latency] = 1.;
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+    }
+}
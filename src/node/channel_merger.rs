@@ -2,6 +2,7 @@ use std::fmt::Debug;
 
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::MAX_CHANNELS;
 
 use super::{
     AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
@@ -30,10 +31,43 @@ impl Default for ChannelMergerOptions {
     }
 }
 
+/// Assert that the channel count is valid for the ChannelMergerNode
+/// see <https://webaudio.github.io/web-audio-api/#audionode-channelcount-constraints>
+///
+/// # Panics
+///
+/// This function panics if given count is not equal to 1
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count(count: usize) {
+    if count != 1 {
+        panic!("InvalidStateError: ChannelMergerNode channel count must be 1");
+    }
+}
+
+/// Assert that the channel count mode is valid for the ChannelMergerNode
+/// see <https://webaudio.github.io/web-audio-api/#audionode-channelcountmode-constraints>
+///
+/// # Panics
+///
+/// This function panics if given count mode is not [`ChannelCountMode::Explicit`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
+    if mode != ChannelCountMode::Explicit {
+        panic!("InvalidStateError: ChannelMergerNode channel count mode must be explicit");
+    }
+}
+
 /// AudioNode for combining channels from multiple audio streams into a single audio stream.
+///
+/// Each input is down-mixed to mono, the output has a channel for every input.
 pub struct ChannelMergerNode {
     registration: AudioContextRegistration,
     channel_config: ChannelConfig,
+    number_of_inputs: usize,
 }
 
 impl AudioNode for ChannelMergerNode {
@@ -45,16 +79,16 @@ impl AudioNode for ChannelMergerNode {
         &self.channel_config
     }
 
-    fn set_channel_count(&self, _v: usize) {
-        panic!("InvalidStateError: Cannot edit channel count of ChannelMergerNode")
+    fn set_channel_count(&self, v: usize) {
+        assert_valid_channel_count(v);
     }
 
-    fn set_channel_count_mode(&self, _v: ChannelCountMode) {
-        panic!("InvalidStateError: Cannot edit channel count mode of ChannelMergerNode")
+    fn set_channel_count_mode(&self, v: ChannelCountMode) {
+        assert_valid_channel_count_mode(v);
     }
 
     fn number_of_inputs(&self) -> usize {
-        self.channel_count()
+        self.number_of_inputs
     }
 
     fn number_of_outputs(&self) -> usize {
@@ -63,13 +97,30 @@ impl AudioNode for ChannelMergerNode {
 }
 
 impl ChannelMergerNode {
-    pub fn new<C: BaseAudioContext>(context: &C, mut options: ChannelMergerOptions) -> Self {
-        context.register(move |registration| {
-            options.channel_config.count = options.number_of_inputs;
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * the number of inputs is outside the [1, 32] range, 32 being defined by the
+    ///   MAX_CHANNELS constant
+    /// * `options.channel_config.count` is not equal to 1
+    /// * `options.channel_config.mode` is not `ChannelCountMode::Explicit`
+    pub fn new<C: BaseAudioContext>(context: &C, options: ChannelMergerOptions) -> Self {
+        if options.number_of_inputs == 0 || options.number_of_inputs > MAX_CHANNELS {
+            panic!(
+                "IndexSizeError - Invalid number of inputs: {:?} is outside range [1, {:?}]",
+                options.number_of_inputs, MAX_CHANNELS
+            );
+        }
+
+        assert_valid_channel_count(options.channel_config.count);
+        assert_valid_channel_count_mode(options.channel_config.count_mode);
 
+        context.register(move |registration| {
             let node = ChannelMergerNode {
                 registration,
                 channel_config: options.channel_config.into(),
+                number_of_inputs: options.number_of_inputs,
             };
 
             let render = ChannelMergerRenderer {};
@@ -109,4 +160,95 @@ impl AudioProcessor for ChannelMergerRenderer {
 
         false
     }
+
+    fn skip_when_silent(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let merger = ChannelMergerNode::new(&context, ChannelMergerOptions::default());
+
+        assert_eq!(merger.number_of_inputs(), 6);
+        assert_eq!(merger.channel_count(), 1);
+        assert_eq!(merger.channel_count_mode(), ChannelCountMode::Explicit);
+
+        // setting the current values is allowed, the interpretation is free
+        merger.set_channel_count(1);
+        merger.set_channel_count_mode(ChannelCountMode::Explicit);
+        merger.set_channel_interpretation(ChannelInterpretation::Discrete);
+        assert_eq!(
+            merger.channel_interpretation(),
+            ChannelInterpretation::Discrete
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let merger = context.create_channel_merger(2);
+        merger.set_channel_count(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_invalid_channel_count_mode() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let merger = context.create_channel_merger(2);
+        merger.set_channel_count_mode(ChannelCountMode::Max);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_invalid_channel_count_in_options() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let mut options = ChannelMergerOptions::default();
+        options.channel_config.count = 2;
+        let _ = ChannelMergerNode::new(&context, options);
+    }
+
+    #[test]
+    fn test_discrete_inputs_use_first_channel() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44_100.);
+
+        // stereo signal with 1. left and 3. right
+        let stereo = context.create_channel_merger(2);
+        for (i, value) in [1., 3.].iter().enumerate() {
+            let src = context.create_constant_source();
+            src.offset().set_value(*value);
+            src.connect_at(&stereo, 0, i);
+            src.start();
+        }
+
+        // with discrete interpretation only the first channel of an input is kept
+        let merger = context.create_channel_merger(2);
+        merger.set_channel_interpretation(ChannelInterpretation::Discrete);
+        stereo.connect_at(&merger, 0, 1);
+        merger.connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[0.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            output.get_channel_data(1),
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
 }
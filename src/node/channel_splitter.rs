@@ -2,6 +2,7 @@ use std::fmt::Debug;
 
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::MAX_CHANNELS;
 
 use super::{
     AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
@@ -14,6 +15,7 @@ use super::{
 #[derive(Clone, Debug)]
 pub struct ChannelSplitterOptions {
     pub number_of_outputs: usize,
+    /// The channel count is ignored, it always equals the number of outputs
     pub channel_config: ChannelConfigOptions,
 }
 
@@ -30,7 +32,39 @@ impl Default for ChannelSplitterOptions {
     }
 }
 
+/// Assert that the channel count mode is valid for the ChannelSplitterNode
+/// see <https://webaudio.github.io/web-audio-api/#audionode-channelcountmode-constraints>
+///
+/// # Panics
+///
+/// This function panics if given count mode is not [`ChannelCountMode::Explicit`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
+    if mode != ChannelCountMode::Explicit {
+        panic!("InvalidStateError: ChannelSplitterNode channel count mode must be explicit");
+    }
+}
+
+/// Assert that the channel interpretation is valid for the ChannelSplitterNode
+/// see <https://webaudio.github.io/web-audio-api/#audionode-channelinterpretation-constraints>
+///
+/// # Panics
+///
+/// This function panics if given interpretation is not [`ChannelInterpretation::Discrete`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_interpretation(interpretation: ChannelInterpretation) {
+    if interpretation != ChannelInterpretation::Discrete {
+        panic!("InvalidStateError: ChannelSplitterNode channel interpretation must be discrete");
+    }
+}
+
 /// AudioNode for accessing the individual channels of an audio stream in the routing graph
+///
+/// The input is up- or down-mixed discretely to the number of outputs, every output is mono.
 pub struct ChannelSplitterNode {
     registration: AudioContextRegistration,
     channel_config: ChannelConfig,
@@ -45,16 +79,18 @@ impl AudioNode for ChannelSplitterNode {
         &self.channel_config
     }
 
-    fn set_channel_count(&self, _v: usize) {
-        panic!("InvalidStateError: Cannot edit channel count of ChannelSplitterNode")
+    fn set_channel_count(&self, v: usize) {
+        if v != self.channel_count() {
+            panic!("InvalidStateError: ChannelSplitterNode channel count must equal its number of outputs");
+        }
     }
 
-    fn set_channel_count_mode(&self, _v: ChannelCountMode) {
-        panic!("InvalidStateError: Cannot edit channel count mode of ChannelSplitterNode")
+    fn set_channel_count_mode(&self, v: ChannelCountMode) {
+        assert_valid_channel_count_mode(v);
     }
 
-    fn set_channel_interpretation(&self, _v: ChannelInterpretation) {
-        panic!("InvalidStateError: Cannot edit channel interpretation of ChannelSplitterNode")
+    fn set_channel_interpretation(&self, v: ChannelInterpretation) {
+        assert_valid_channel_interpretation(v);
     }
 
     fn number_of_inputs(&self) -> usize {
@@ -67,8 +103,29 @@ impl AudioNode for ChannelSplitterNode {
 }
 
 impl ChannelSplitterNode {
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * the number of outputs is outside the [1, 32] range, 32 being defined by the
+    ///   MAX_CHANNELS constant
+    /// * `options.channel_config.mode` is not `ChannelCountMode::Explicit`
+    /// * `options.channel_config.interpretation` is not `ChannelInterpretation::Discrete`
+    ///
+    /// The `options.channel_config.count` is overridden by the number of outputs.
     pub fn new<C: BaseAudioContext>(context: &C, mut options: ChannelSplitterOptions) -> Self {
+        if options.number_of_outputs == 0 || options.number_of_outputs > MAX_CHANNELS {
+            panic!(
+                "IndexSizeError - Invalid number of outputs: {:?} is outside range [1, {:?}]",
+                options.number_of_outputs, MAX_CHANNELS
+            );
+        }
+
+        assert_valid_channel_count_mode(options.channel_config.count_mode);
+        assert_valid_channel_interpretation(options.channel_config.interpretation);
+
         context.register(move |registration| {
+            // the channel count always matches the number of outputs
             options.channel_config.count = options.number_of_outputs;
 
             let node = ChannelSplitterNode {
@@ -117,4 +174,106 @@ impl AudioProcessor for ChannelSplitterRenderer {
 
         false
     }
+
+    fn skip_when_silent(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let options = ChannelSplitterOptions {
+            number_of_outputs: 3,
+            ..ChannelSplitterOptions::default()
+        };
+        let splitter = ChannelSplitterNode::new(&context, options);
+
+        assert_eq!(splitter.number_of_outputs(), 3);
+        assert_eq!(splitter.channel_count(), 3);
+        assert_eq!(splitter.channel_count_mode(), ChannelCountMode::Explicit);
+        assert_eq!(
+            splitter.channel_interpretation(),
+            ChannelInterpretation::Discrete
+        );
+
+        // setting the current values is allowed
+        splitter.set_channel_count(3);
+        splitter.set_channel_count_mode(ChannelCountMode::Explicit);
+        splitter.set_channel_interpretation(ChannelInterpretation::Discrete);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let splitter = context.create_channel_splitter(2);
+        splitter.set_channel_count(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_invalid_channel_count_mode() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let splitter = context.create_channel_splitter(2);
+        splitter.set_channel_count_mode(ChannelCountMode::Max);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_invalid_channel_interpretation() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let splitter = context.create_channel_splitter(2);
+        splitter.set_channel_interpretation(ChannelInterpretation::Speakers);
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidStateError")]
+    fn test_invalid_channel_count_mode_in_options() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+        let mut options = ChannelSplitterOptions::default();
+        options.channel_config.count_mode = ChannelCountMode::ClampedMax;
+        let _ = ChannelSplitterNode::new(&context, options);
+    }
+
+    #[test]
+    fn test_outputs_are_mono() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        // stereo input is split in two mono outputs, the third output is silent
+        let stereo = context.create_channel_merger(2);
+        for (i, value) in [1., 2.].iter().enumerate() {
+            let src = context.create_constant_source();
+            src.offset().set_value(*value);
+            src.connect_at(&stereo, 0, i);
+            src.start();
+        }
+
+        let splitter = context.create_channel_splitter(3);
+        stereo.connect(&splitter);
+
+        // all outputs are summed in the mono destination, a non-mono output
+        // would be down-mixed and change the result
+        let destination = context.destination();
+        splitter.connect_at(&destination, 0, 0);
+        splitter.connect_at(&destination, 1, 0);
+        splitter.connect_at(&destination, 2, 0);
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[3.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
 }
@@ -1,5 +1,5 @@
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
-use crate::control::Scheduler;
+use crate::control::{frame_index, Scheduler};
 use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::RENDER_QUANTUM_SIZE;
@@ -134,6 +134,10 @@ impl ConstantSourceNode {
     pub fn offset(&self) -> &AudioParam {
         &self.offset
     }
+
+    pub(crate) fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
 }
 
 struct ConstantSourceRenderer {
@@ -161,20 +165,25 @@ impl AudioProcessor for ConstantSourceRenderer {
 
         if start_time >= next_block_time {
             output.make_silent();
-            return true;
+            // keep alive until started, unless the control handle is gone
+            return !self.scheduler.is_abandoned();
         }
 
         output.force_mono();
 
+        let sample_rate = scope.sample_rate as f64;
+        let start_index = frame_index(start_time, scope.current_time, sample_rate);
+        let stop_index = frame_index(stop_time, scope.current_time, sample_rate);
+
         let offset = params.get(&self.offset);
         let output_channel = output.channel_data_mut(0);
-        let mut current_time = scope.current_time;
 
         output_channel
             .iter_mut()
             .zip(offset.iter().cycle())
-            .for_each(|(o, &value)| {
-                if current_time < start_time || current_time >= stop_time {
+            .enumerate()
+            .for_each(|(index, (o, &value))| {
+                if index < start_index || index >= stop_index {
                     *o = 0.;
                 } else {
                     // as we pick values directly from the offset param which is already
@@ -182,8 +191,6 @@ impl AudioProcessor for ConstantSourceRenderer {
                     // copying the values to their right place.
                     *o = value;
                 }
-
-                current_time += dt;
             });
 
         // tail_time false when output has ended this quantum
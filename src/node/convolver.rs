@@ -1,11 +1,15 @@
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
+use super::bypass::Bypass;
+use super::{
+    AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode,
+    ChannelInterpretation,
+};
 use crate::buffer::AudioBuffer;
 use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::fft::{self, Complex, ComplexToRealFft, RealToComplexFft};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::RENDER_QUANTUM_SIZE;
 
 use crossbeam_channel::{Receiver, Sender};
-use realfft::{num_complex::Complex, ComplexToReal, RealFftPlanner, RealToComplex};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc, Mutex,
@@ -56,7 +60,7 @@ fn normalization(buffer: &AudioBuffer) -> f32 {
 //  AudioBuffer? buffer;
 //  boolean disableNormalization = false;
 //};
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct ConvolverOptions {
     /// The desired buffer for the ConvolverNode
     pub buffer: Option<AudioBuffer>,
@@ -66,6 +70,68 @@ pub struct ConvolverOptions {
     pub channel_config: ChannelConfigOptions,
 }
 
+impl Default for ConvolverOptions {
+    fn default() -> Self {
+        Self {
+            buffer: None,
+            disable_normalization: false,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Assert that the channel count is valid for the ConvolverNode
+/// see <https://webaudio.github.io/web-audio-api/#audionode-channelcount-constraints>
+///
+/// # Panics
+///
+/// This function panics if given count is greater than 2
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count(count: usize) {
+    if count > 2 {
+        panic!("NotSupportedError: ConvolverNode channel count cannot be greater than two");
+    }
+}
+
+/// Assert that the channel count mode is valid for the ConvolverNode
+/// see <https://webaudio.github.io/web-audio-api/#audionode-channelcountmode-constraints>
+///
+/// # Panics
+///
+/// This function panics if given count mode is [`ChannelCountMode::Max`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
+    if mode == ChannelCountMode::Max {
+        panic!("NotSupportedError: ConvolverNode channel count mode cannot be set to max");
+    }
+}
+
+/// Assert that the number of channels of the impulse response buffer is valid
+/// see <https://webaudio.github.io/web-audio-api/#dom-convolvernode-buffer>
+///
+/// # Panics
+///
+/// This function panics if given number of channels is not 1, 2 or 4
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_buffer_number_of_channels(number_of_channels: usize) {
+    if !matches!(number_of_channels, 1 | 2 | 4) {
+        panic!(
+            "NotSupportedError: ConvolverNode buffer must have 1, 2 or 4 channels, got {}",
+            number_of_channels
+        );
+    }
+}
+
 /// Processing node which applies a linear convolution effect given an impulse response.
 ///
 /// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/ConvolverNode>
@@ -73,8 +139,9 @@ pub struct ConvolverOptions {
 /// - see also:
 /// [`BaseAudioContext::create_convolver`](crate::context::BaseAudioContext::create_convolver)
 ///
-/// The current implementation only handles mono-to-mono convolutions. The provided impulse
-/// response buffer and the input signal will be downmixed appropriately.
+/// The impulse response can have 1, 2 or 4 channels. The input and response channels are
+/// combined as described in the specification: the output is mono for a mono input and a mono
+/// response and stereo otherwise, a 4 channel response performs a "true stereo" convolution.
 ///
 /// # Usage
 ///
@@ -118,6 +185,7 @@ pub struct ConvolverNode {
     buffer: Mutex<Option<AudioBuffer>>,
     /// Message bus to the renderer
     sender: Sender<ConvolverRendererInner>,
+    bypass: Bypass,
 }
 
 impl AudioNode for ConvolverNode {
@@ -136,6 +204,26 @@ impl AudioNode for ConvolverNode {
     fn number_of_outputs(&self) -> usize {
         1
     }
+
+    fn set_channel_count(&self, count: usize) {
+        assert_valid_channel_count(count);
+        self.channel_config.set_count(count);
+    }
+
+    fn set_channel_count_mode(&self, mode: ChannelCountMode) {
+        assert_valid_channel_count_mode(mode);
+        self.channel_config.set_count_mode(mode);
+    }
+}
+
+impl AudioEffectNode for ConvolverNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
 }
 
 impl ConvolverNode {
@@ -148,8 +236,12 @@ impl ConvolverNode {
     ///
     /// # Panics
     ///
-    /// Panics when an AudioBuffer is provided via the `ConvolverOptions` with a sample rate
-    /// different from the audio context sample rate.
+    /// Will panic if:
+    ///
+    /// * an AudioBuffer is provided via the `ConvolverOptions` with a number of channels other
+    ///   than 1, 2 or 4
+    /// * `options.channel_config.count` is greater than 2
+    /// * `options.channel_config.mode` is `ChannelCountMode::Max`
     pub fn new<C: BaseAudioContext>(context: &C, options: ConvolverOptions) -> Self {
         context.base().register(move |registration| {
             let ConvolverOptions {
@@ -158,6 +250,9 @@ impl ConvolverNode {
                 channel_config,
             } = options;
 
+            assert_valid_channel_count_mode(channel_config.count_mode);
+            assert_valid_channel_count(channel_config.count);
+
             // Channel to send buffer channels references to the renderer.  A capacity of 1
             // suffices, it will simply block the control thread when used concurrently
             let (sender, receiver) = crossbeam_channel::bounded(1);
@@ -166,6 +261,7 @@ impl ConvolverNode {
 
             let node = Self {
                 registration,
+                bypass: Bypass::default(),
                 channel_config: channel_config.into(),
                 normalize: AtomicBool::new(!disable_normalization),
                 sender,
@@ -176,6 +272,7 @@ impl ConvolverNode {
                 node.set_buffer(buffer);
             }
 
+            let renderer = node.bypass.wrap(renderer, 0);
             (node, Box::new(renderer))
         })
     }
@@ -188,11 +285,17 @@ impl ConvolverNode {
 
     /// Set or update the impulse response buffer
     ///
+    /// An impulse response recorded at a sample rate other than the audio context sample rate is
+    /// resampled first. When [`normalize`](Self::normalize) is set, the response is then scaled
+    /// with the equal-power normalization of the specification, so the perceived volume of the
+    /// output does not depend on the level, length or sample rate of the impulse response.
+    ///
     /// # Panics
     ///
-    /// Panics when the sample rate of the provided AudioBuffer differs from the audio context
-    /// sample rate.
+    /// Panics when the number of channels of the provided AudioBuffer is not 1, 2 or 4.
     pub fn set_buffer(&self, mut buffer: AudioBuffer) {
+        assert_valid_buffer_number_of_channels(buffer.number_of_channels());
+
         // resample if necessary
         buffer.resample(self.context().sample_rate());
         let sample_rate = buffer.sample_rate();
@@ -208,11 +311,11 @@ impl ConvolverNode {
         let length = buffer.length();
         let padded_length = length.next_power_of_two().max(2 * RENDER_QUANTUM_SIZE);
         let samples: Vec<_> = (0..buffer.number_of_channels())
-            .map(|_| {
+            .map(|channel| {
                 let mut samples = vec![0.; padded_length];
                 samples[..length]
                     .iter_mut()
-                    .zip(buffer.get_channel_data(0))
+                    .zip(buffer.get_channel_data(channel))
                     .for_each(|(o, i)| *o = *i * scale);
                 samples
             })
@@ -246,8 +349,8 @@ fn roll_zero<T: Default + Copy>(signal: &mut [T], n: usize) {
 }
 
 struct Fft {
-    fft_forward: Arc<dyn RealToComplex<f32>>,
-    fft_inverse: Arc<dyn ComplexToReal<f32>>,
+    fft_forward: Arc<dyn RealToComplexFft>,
+    fft_inverse: Arc<dyn ComplexToRealFft>,
     fft_input: Vec<f32>,
     fft_scratch: Vec<Complex<f32>>,
     fft_output: Vec<Complex<f32>>,
@@ -255,14 +358,13 @@ struct Fft {
 
 impl Fft {
     fn new(length: usize) -> Self {
-        let mut fft_planner = RealFftPlanner::<f32>::new();
-
-        let fft_forward = fft_planner.plan_fft_forward(length);
-        let fft_inverse = fft_planner.plan_fft_inverse(length);
+        let fft_forward = fft::plan_forward(length);
+        let fft_inverse = fft::plan_inverse(length);
 
-        let fft_input = fft_forward.make_input_vec();
-        let fft_scratch = fft_forward.make_scratch_vec();
-        let fft_output = fft_forward.make_output_vec();
+        let fft_input = vec![0.; length];
+        let scratch_len = fft_forward.scratch_len().max(fft_inverse.scratch_len());
+        let fft_scratch = vec![Complex::default(); scratch_len];
+        let fft_output = vec![Complex::default(); length / 2 + 1];
 
         Self {
             fft_forward,
@@ -282,24 +384,20 @@ impl Fft {
     }
 
     fn process(&mut self) -> &[Complex<f32>] {
-        self.fft_forward
-            .process_with_scratch(
-                &mut self.fft_input,
-                &mut self.fft_output,
-                &mut self.fft_scratch,
-            )
-            .unwrap();
+        self.fft_forward.process(
+            &mut self.fft_input,
+            &mut self.fft_output,
+            &mut self.fft_scratch,
+        );
         &self.fft_output[..]
     }
 
     fn inverse(&mut self) -> &[f32] {
-        self.fft_inverse
-            .process_with_scratch(
-                &mut self.fft_output,
-                &mut self.fft_input,
-                &mut self.fft_scratch,
-            )
-            .unwrap();
+        self.fft_inverse.process(
+            &mut self.fft_output,
+            &mut self.fft_input,
+            &mut self.fft_scratch,
+        );
         &self.fft_input[..]
     }
 }
@@ -318,55 +416,88 @@ impl ConvolverRenderer {
     }
 }
 
+/// Partitioned convolution of the input channels with the impulse response channels
+///
+/// The routing follows the channel configurations of the specification, see
+/// <https://webaudio.github.io/web-audio-api/#Convolution-channel-configurations>:
+/// - mono response: each input channel is convolved with the response
+/// - stereo response: the left and right outputs are the (upmixed) input convolved with the
+///   left and right responses respectively
+/// - 4 channel response (true stereo): the left output is the left input convolved with the
+///   first response plus the right input convolved with the third, the right output the left
+///   input convolved with the second response plus the right input convolved with the fourth
 struct ConvolverRendererInner {
     num_ir_blocks: usize,
-    h: Vec<Complex<f32>>,
-    fdl: Vec<Complex<f32>>,
-    out: Vec<f32>,
+    /// Number of blocks of the tail still to be rendered
+    remaining_tail: usize,
+    /// Frequency responses of the blocks of each impulse response channel
+    h: Vec<Vec<Complex<f32>>>,
+    /// Spectrum of each input channel of the current block
+    spectra: [Vec<Complex<f32>>; 2],
+    /// Frequency domain delay line of each output channel
+    fdl: [Vec<Complex<f32>>; 2],
+    /// Overlap-add buffer of each output channel
+    out: [Vec<f32>; 2],
+    /// Number of output channels, it stays stereo for the tail of a stereo convolution
+    number_of_channels: usize,
     fft2: Fft,
 }
 
 impl ConvolverRendererInner {
     fn new(response: AudioBuffer) -> Self {
-        // mono processing only for now
-        let response = response.channel_data(0).as_slice();
-
         let mut fft2 = Fft::new(2 * RENDER_QUANTUM_SIZE);
-        let p = response.len();
+        let p = response.length();
 
         let num_ir_blocks = p / RENDER_QUANTUM_SIZE;
 
-        let mut h = vec![Complex::default(); num_ir_blocks * 2 * RENDER_QUANTUM_SIZE];
-        for (resp_fft, resp) in h
-            .chunks_mut(2 * RENDER_QUANTUM_SIZE)
-            .zip(response.chunks(RENDER_QUANTUM_SIZE))
-        {
-            // fill resp_fft with FFT of resp.zero_pad(RENDER_QUANTUM_SIZE)
-            fft2.real()[..RENDER_QUANTUM_SIZE].copy_from_slice(resp);
-            fft2.real()[RENDER_QUANTUM_SIZE..].fill(0.);
-            resp_fft[..fft2.complex().len()].copy_from_slice(fft2.process());
-        }
+        let h = response
+            .channels()
+            .iter()
+            .map(|response| {
+                let mut h = vec![Complex::default(); num_ir_blocks * 2 * RENDER_QUANTUM_SIZE];
+                for (resp_fft, resp) in h
+                    .chunks_mut(2 * RENDER_QUANTUM_SIZE)
+                    .zip(response.as_slice().chunks(RENDER_QUANTUM_SIZE))
+                {
+                    // fill resp_fft with FFT of resp.zero_pad(RENDER_QUANTUM_SIZE)
+                    fft2.real()[..RENDER_QUANTUM_SIZE].copy_from_slice(resp);
+                    fft2.real()[RENDER_QUANTUM_SIZE..].fill(0.);
+                    resp_fft[..fft2.complex().len()].copy_from_slice(fft2.process());
+                }
+                h
+            })
+            .collect();
 
-        let fdl = vec![Complex::default(); 2 * RENDER_QUANTUM_SIZE * num_ir_blocks];
-        let out = vec![0.; 2 * RENDER_QUANTUM_SIZE - 1];
+        let c_len = fft2.complex().len();
+        let spectra = [
+            vec![Complex::default(); c_len],
+            vec![Complex::default(); c_len],
+        ];
+        let fdl = [
+            vec![Complex::default(); 2 * RENDER_QUANTUM_SIZE * num_ir_blocks],
+            vec![Complex::default(); 2 * RENDER_QUANTUM_SIZE * num_ir_blocks],
+        ];
+        let out = [
+            vec![0.; 2 * RENDER_QUANTUM_SIZE - 1],
+            vec![0.; 2 * RENDER_QUANTUM_SIZE - 1],
+        ];
 
         Self {
             num_ir_blocks,
+            remaining_tail: 0,
             h,
+            spectra,
             fdl,
             out,
+            number_of_channels: 1,
             fft2,
         }
     }
 
-    fn process(&mut self, input: &[f32], output: &mut [f32]) {
-        self.fft2.real()[..RENDER_QUANTUM_SIZE].copy_from_slice(input);
-        self.fft2.real()[RENDER_QUANTUM_SIZE..].fill(0.);
-        let spectrum = self.fft2.process();
-
-        self.fdl
-            .chunks_mut(2 * RENDER_QUANTUM_SIZE)
-            .zip(self.h.chunks(2 * RENDER_QUANTUM_SIZE))
+    /// Accumulate `spectrum` filtered by the impulse response `h` into the delay line `fdl`
+    fn accumulate(fdl: &mut [Complex<f32>], h: &[Complex<f32>], spectrum: &[Complex<f32>]) {
+        fdl.chunks_mut(2 * RENDER_QUANTUM_SIZE)
+            .zip(h.chunks(2 * RENDER_QUANTUM_SIZE))
             .for_each(|(fdl_c, h_c)| {
                 fdl_c
                     .iter_mut()
@@ -374,43 +505,92 @@ impl ConvolverRendererInner {
                     .zip(spectrum)
                     .for_each(|((f, h), s)| *f += h * s)
             });
+    }
+
+    /// Render the next block of each output channel from the delay lines
+    fn render(&mut self, output: &mut AudioRenderQuantum) {
+        output.set_number_of_channels(self.number_of_channels);
 
         let c_len = self.fft2.complex().len();
-        self.fft2.complex().copy_from_slice(&self.fdl[..c_len]);
-        let inverse = self.fft2.inverse();
-        self.out.iter_mut().zip(inverse).for_each(|(o, i)| {
-            *o += i / (2 * RENDER_QUANTUM_SIZE) as f32;
-        });
+        for (channel_number, (fdl, out)) in self
+            .fdl
+            .iter_mut()
+            .zip(self.out.iter_mut())
+            .take(self.number_of_channels)
+            .enumerate()
+        {
+            self.fft2.complex().copy_from_slice(&fdl[..c_len]);
+            let inverse = self.fft2.inverse();
+            out.iter_mut().zip(inverse).for_each(|(o, i)| {
+                *o += i / (2 * RENDER_QUANTUM_SIZE) as f32;
+            });
 
-        output.copy_from_slice(&self.out[..RENDER_QUANTUM_SIZE]);
+            output
+                .channel_data_mut(channel_number)
+                .copy_from_slice(&out[..RENDER_QUANTUM_SIZE]);
 
-        roll_zero(&mut self.fdl[..], 2 * RENDER_QUANTUM_SIZE);
-        roll_zero(&mut self.out[..], RENDER_QUANTUM_SIZE);
+            roll_zero(&mut fdl[..], 2 * RENDER_QUANTUM_SIZE);
+            roll_zero(&mut out[..], RENDER_QUANTUM_SIZE);
+        }
     }
 
-    fn tail(&mut self, output: &mut AudioRenderQuantum) -> bool {
-        if self.num_ir_blocks == 0 {
-            output.make_silent();
-            return false;
+    fn process(&mut self, input: &AudioRenderQuantum, output: &mut AudioRenderQuantum) {
+        let input_channels = input.number_of_channels().min(2);
+        let ir_channels = self.h.len();
+
+        // the output is only mono for a mono input convolved with a mono response
+        if input_channels > 1 || ir_channels > 1 {
+            self.number_of_channels = 2;
         }
 
-        self.num_ir_blocks -= 1;
+        for (channel, spectrum) in input.channels()[..input_channels]
+            .iter()
+            .zip(self.spectra.iter_mut())
+        {
+            self.fft2.real()[..RENDER_QUANTUM_SIZE].copy_from_slice(&channel[..]);
+            self.fft2.real()[RENDER_QUANTUM_SIZE..].fill(0.);
+            spectrum.copy_from_slice(self.fft2.process());
+        }
 
-        let c_len = self.fft2.complex().len();
-        self.fft2.complex().copy_from_slice(&self.fdl[..c_len]);
-        let inverse = self.fft2.inverse();
-        self.out.iter_mut().zip(inverse).for_each(|(o, i)| {
-            *o += i / (2 * RENDER_QUANTUM_SIZE) as f32;
-        });
+        // a mono input is upmixed to stereo when needed
+        let left = &self.spectra[0];
+        let right = &self.spectra[input_channels - 1];
+        let [fdl_left, fdl_right] = &mut self.fdl;
+
+        match ir_channels {
+            1 => {
+                Self::accumulate(fdl_left, &self.h[0], left);
+                if self.number_of_channels == 2 {
+                    Self::accumulate(fdl_right, &self.h[0], right);
+                }
+            }
+            2 => {
+                Self::accumulate(fdl_left, &self.h[0], left);
+                Self::accumulate(fdl_right, &self.h[1], right);
+            }
+            4 => {
+                Self::accumulate(fdl_left, &self.h[0], left);
+                Self::accumulate(fdl_left, &self.h[2], right);
+                Self::accumulate(fdl_right, &self.h[1], left);
+                Self::accumulate(fdl_right, &self.h[3], right);
+            }
+            _ => unreachable!(), // validated by `ConvolverNode::set_buffer`
+        }
 
-        output
-            .channel_data_mut(0)
-            .copy_from_slice(&self.out[..RENDER_QUANTUM_SIZE]);
+        self.render(output);
+        self.remaining_tail = self.num_ir_blocks;
+    }
 
-        roll_zero(&mut self.fdl[..], 2 * RENDER_QUANTUM_SIZE);
-        roll_zero(&mut self.out[..], RENDER_QUANTUM_SIZE);
+    fn tail(&mut self, output: &mut AudioRenderQuantum) -> bool {
+        if self.remaining_tail == 0 {
+            output.make_silent();
+            return false;
+        }
+
+        self.remaining_tail -= 1;
+        self.render(output);
 
-        self.num_ir_blocks > 0
+        self.remaining_tail > 0
     }
 }
 
@@ -425,7 +605,6 @@ impl AudioProcessor for ConvolverRenderer {
         // single input/output node
         let input = &inputs[0];
         let output = &mut outputs[0];
-        output.force_mono();
 
         // handle new impulse response buffer, if any
         if let Ok(msg) = self.receiver.try_recv() {
@@ -446,11 +625,6 @@ impl AudioProcessor for ConvolverRenderer {
             return convolver.tail(output);
         }
 
-        let mut mono = input.clone();
-        mono.mix(1, ChannelInterpretation::Speakers);
-        let input = &mono.channel_data(0)[..];
-        let output = &mut output.channel_data_mut(0)[..];
-
         convolver.process(input, output);
 
         true
@@ -466,6 +640,36 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_channel_config_constraints() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        let node = ConvolverNode::new(&context, ConvolverOptions::default());
+        assert_eq!(node.channel_count(), 2);
+        assert_eq!(node.channel_count_mode(), ChannelCountMode::ClampedMax);
+
+        node.set_channel_count(1);
+        node.set_channel_count_mode(ChannelCountMode::Explicit);
+        assert_eq!(node.channel_count(), 1);
+        assert_eq!(node.channel_count_mode(), ChannelCountMode::Explicit);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        let node = ConvolverNode::new(&context, ConvolverOptions::default());
+        node.set_channel_count(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_channel_count_mode() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        let mut options = ConvolverOptions::default();
+        options.channel_config.count_mode = ChannelCountMode::Max;
+        let _node = ConvolverNode::new(&context, options);
+    }
+
     #[test]
     fn test_roll_zero() {
         let mut input = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
@@ -545,6 +749,88 @@ mod tests {
         assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 1E-6);
     }
 
+    /// Convolve a stereo input with the given response channels, without normalization
+    fn test_convolve_stereo(input: [Vec<f32>; 2], response: Vec<Vec<f32>>) -> AudioBuffer {
+        let sample_rate = 44100.;
+        let context = OfflineAudioContext::new(2, 16, sample_rate);
+
+        let src = context.create_buffer_source();
+        src.set_buffer(AudioBuffer::from(input.to_vec(), sample_rate));
+        src.start();
+
+        let options = ConvolverOptions {
+            buffer: Some(AudioBuffer::from(response, sample_rate)),
+            disable_normalization: true,
+            ..ConvolverOptions::default()
+        };
+        let conv = ConvolverNode::new(&context, options);
+
+        src.connect(&conv);
+        conv.connect(&context.destination());
+
+        context.start_rendering_sync()
+    }
+
+    fn impulse(position: usize, value: f32) -> Vec<f32> {
+        let mut signal = vec![0.; 16];
+        signal[position] = value;
+        signal
+    }
+
+    #[test]
+    fn test_mono_response_stereo_input() {
+        let input = [impulse(0, 1.), impulse(0, 2.)];
+        let output = test_convolve_stereo(input, vec![impulse(1, 1.)]);
+
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &impulse(1, 1.)[..],
+            abs_all <= 1E-6
+        );
+        assert_float_eq!(
+            output.get_channel_data(1),
+            &impulse(1, 2.)[..],
+            abs_all <= 1E-6
+        );
+    }
+
+    #[test]
+    fn test_stereo_response() {
+        // the left and right inputs are convolved with the left and right responses
+        let input = [impulse(0, 1.), impulse(0, 1.)];
+        let output = test_convolve_stereo(input, vec![impulse(1, 1.), impulse(3, 0.5)]);
+
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &impulse(1, 1.)[..],
+            abs_all <= 1E-6
+        );
+        assert_float_eq!(
+            output.get_channel_data(1),
+            &impulse(3, 0.5)[..],
+            abs_all <= 1E-6
+        );
+    }
+
+    #[test]
+    fn test_true_stereo_response() {
+        let input = [impulse(0, 1.), impulse(8, 2.)];
+        let response = vec![
+            impulse(0, 1.), // left to left
+            impulse(1, 1.), // left to right
+            impulse(2, 1.), // right to left
+            impulse(3, 1.), // right to right
+        ];
+        let output = test_convolve_stereo(input, response);
+
+        let mut left = impulse(0, 1.);
+        left[10] = 2.;
+        let mut right = impulse(1, 1.);
+        right[11] = 2.;
+        assert_float_eq!(output.get_channel_data(0), &left[..], abs_all <= 1E-6);
+        assert_float_eq!(output.get_channel_data(1), &right[..], abs_all <= 1E-6);
+    }
+
     #[test]
     fn test_should_have_tail_time() {
         // impulse response of length 256
@@ -577,4 +863,46 @@ mod tests {
 
         assert_eq!(conv.buffer().unwrap().sample_rate(), ctx_sample_rate);
     }
+
+    #[test]
+    fn test_resample_normalization() {
+        // a constant impulse response yields the same normalized gain regardless of its
+        // sample rate
+        let sample_rate = 44100.;
+        let length = 2048;
+
+        for ir_sample_rate in [22050., 44100., 96000.] {
+            let context = OfflineAudioContext::new(1, length, sample_rate);
+
+            let mut input = context.create_buffer(1, 1, sample_rate);
+            input.copy_to_channel(&[1.], 0);
+            let src = context.create_buffer_source();
+            src.set_buffer(input);
+            src.start();
+
+            let conv = ConvolverNode::new(&context, ConvolverOptions::default());
+            let ir_length = (ir_sample_rate / 20.) as usize; // 50ms
+            let ir = AudioBuffer::from(vec![vec![1.; ir_length]], ir_sample_rate);
+            conv.set_buffer(ir);
+
+            src.connect(&conv);
+            conv.connect(&context.destination());
+
+            let output = context.start_rendering_sync();
+            // away from the resampling ringing at the edges of the response
+            assert_float_eq!(
+                &output.get_channel_data(0)[256..1024],
+                &[0.00125; 768][..],
+                abs_all <= 1E-4
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_buffer_number_of_channels() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let conv = ConvolverNode::new(&context, ConvolverOptions::default());
+        conv.set_buffer(AudioBuffer::from(vec![vec![1.; 128]; 3], 44_100.));
+    }
 }
@@ -0,0 +1,311 @@
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::MAX_CHANNELS;
+
+use super::bypass::Bypass;
+use super::{AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Default cutoff frequency of the DC blocker, in Hz
+pub(crate) const DEFAULT_DC_BLOCKER_CUTOFF: f32 = 10.;
+
+/// Options for constructing a [`DcBlockerNode`]
+#[derive(Clone, Debug)]
+pub struct DcBlockerOptions {
+    /// Cutoff frequency of the high-pass filter, in Hz
+    pub cutoff: f32,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for DcBlockerOptions {
+    fn default() -> Self {
+        Self {
+            cutoff: DEFAULT_DC_BLOCKER_CUTOFF,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Assert that the cutoff frequency is valid
+///
+/// # Panics
+///
+/// This function panics if the given frequency is not in the (0, nyquist) range
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_cutoff(cutoff: f32, sample_rate: f32) {
+    let nyquist = sample_rate / 2.;
+    if !(cutoff > 0. && cutoff < nyquist) {
+        panic!(
+            "RangeError - Invalid cutoff frequency: {:?} is outside range (0, {:?})",
+            cutoff, nyquist
+        );
+    }
+}
+
+/// One-pole high-pass filter removing the DC offset of a multichannel signal
+///
+/// `y(n) = x(n) - x(n-1) + r * y(n-1)`, with the pole `r` derived from the cutoff frequency.
+#[derive(Clone, Debug)]
+pub(crate) struct DcBlocker {
+    pole: f32,
+    /// Previous input and output of each channel
+    state: Vec<(f32, f32)>,
+}
+
+impl DcBlocker {
+    pub(crate) fn new(cutoff: f32, sample_rate: f32) -> Self {
+        Self {
+            pole: (-2. * std::f32::consts::PI * cutoff / sample_rate).exp(),
+            state: Vec::with_capacity(MAX_CHANNELS),
+        }
+    }
+
+    /// Returns true if the history only contains (sub-normal) silence
+    pub(crate) fn is_silent(&self) -> bool {
+        self.state
+            .iter()
+            .all(|(x1, y1)| !(x1.is_normal() || y1.is_normal()))
+    }
+
+    /// Number of channels of the last processed block
+    pub(crate) fn number_of_channels(&self) -> usize {
+        self.state.len()
+    }
+
+    /// Filter a block of audio in place
+    ///
+    /// When the number of channels changes, the history of the additional channels starts out
+    /// silent.
+    pub(crate) fn process(&mut self, quantum: &mut AudioRenderQuantum) {
+        self.state.resize(quantum.number_of_channels(), (0., 0.));
+        let pole = self.pole;
+
+        quantum
+            .channels_mut()
+            .iter_mut()
+            .zip(self.state.iter_mut())
+            .for_each(|(channel, (x1, y1))| {
+                channel.iter_mut().for_each(|s| {
+                    let x = *s;
+                    let y = x - *x1 + pole * *y1;
+                    *x1 = x;
+                    *y1 = y;
+                    *s = y;
+                });
+            });
+    }
+}
+
+/// `DcBlockerNode` removes the DC offset of its input
+///
+/// Custom processors, waveshapers or some filter settings can produce a constant
+/// offset, which wastes headroom and can damage speakers. This node is a one-pole
+/// high-pass filter with a very low cutoff frequency, so the audible spectrum is
+/// left unchanged.
+///
+/// The same filter is applied by default by the
+/// [`MediaStreamAudioDestinationNode`](super::MediaStreamAudioDestinationNode).
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{DcBlockerNode, DcBlockerOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let dc_blocker = DcBlockerNode::new(&context, DcBlockerOptions::default());
+/// dc_blocker.connect(&context.destination());
+///
+/// // an oscillator with a DC offset
+/// let osc = context.create_oscillator();
+/// let offset = context.create_constant_source();
+/// offset.offset().set_value(0.5);
+/// osc.connect(&dc_blocker);
+/// offset.connect(&dc_blocker);
+/// osc.start();
+/// offset.start();
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct DcBlockerNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    cutoff: f32,
+    bypass: Bypass,
+}
+
+impl AudioNode for DcBlockerNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioEffectNode for DcBlockerNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
+}
+
+impl DcBlockerNode {
+    /// # Panics
+    ///
+    /// Will panic if `options.cutoff` is not in the (0, nyquist) range
+    pub fn new<C: BaseAudioContext>(context: &C, options: DcBlockerOptions) -> Self {
+        context.register(move |registration| {
+            let DcBlockerOptions {
+                cutoff,
+                channel_config,
+            } = options;
+
+            assert_valid_cutoff(cutoff, context.sample_rate());
+
+            let render = DcBlockerRenderer {
+                dc_blocker: DcBlocker::new(cutoff, context.sample_rate()),
+            };
+
+            let node = DcBlockerNode {
+                registration,
+                bypass: Bypass::default(),
+                channel_config: channel_config.into(),
+                cutoff,
+            };
+
+            let render = node.bypass.wrap(render, 0);
+            (node, Box::new(render))
+        })
+    }
+
+    /// Cutoff frequency of the high-pass filter, in Hz
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+}
+
+struct DcBlockerRenderer {
+    dc_blocker: DcBlocker,
+}
+
+impl AudioProcessor for DcBlockerRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // handle tail time
+        if input.is_silent() {
+            // input is silent and filter history is clean
+            if self.dc_blocker.is_silent() {
+                output.make_silent();
+                return false;
+            }
+
+            // continue with the previous number of channels
+            output.make_silent();
+            output.set_number_of_channels(self.dc_blocker.number_of_channels());
+        } else {
+            *output = input.clone();
+        }
+
+        self.dc_blocker.process(output);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.;
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let dc_blocker = DcBlockerNode::new(&context, DcBlockerOptions::default());
+        assert_float_eq!(dc_blocker.cutoff(), 10., abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn test_invalid_cutoff() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let options = DcBlockerOptions {
+            cutoff: SAMPLE_RATE,
+            ..DcBlockerOptions::default()
+        };
+        let _ = DcBlockerNode::new(&context, options);
+    }
+
+    #[test]
+    fn test_dc_removal() {
+        let length = SAMPLE_RATE as usize;
+        let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let dc_blocker = DcBlockerNode::new(&context, DcBlockerOptions::default());
+        dc_blocker.connect(&context.destination());
+
+        let offset = context.create_constant_source();
+        offset.connect(&dc_blocker);
+        offset.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // the step goes through, then decays
+        assert_float_eq!(channel[0], 1., abs <= 0.);
+        assert!(channel[length - 1].abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_audible_signal_preserved() {
+        let length = RENDER_QUANTUM_SIZE * 100;
+        let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let dc_blocker = DcBlockerNode::new(&context, DcBlockerOptions::default());
+        dc_blocker.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.connect(&dc_blocker);
+        osc.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // a 440Hz sine is attenuated by less than 0.01dB
+        let peak = channel[length / 2..]
+            .iter()
+            .fold(0_f32, |acc, s| acc.max(s.abs()));
+        assert_float_eq!(peak, 1., abs <= 1e-3);
+    }
+}
@@ -1,13 +1,44 @@
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
 use crate::param::{AudioParam, AudioParamDescriptor};
-use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::render::{
+    AudioParamValues, AudioProcessor, AudioRenderQuantum, AudioRenderQuantumChannel, RenderScope,
+};
 use crate::RENDER_QUANTUM_SIZE;
 
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
+use super::{
+    disconnect_node, try_connect_node, AudioNode, ChannelConfig, ChannelConfigOptions,
+    ChannelInterpretation, ConnectError,
+};
 
-use std::cell::{Cell, RefCell, RefMut};
+use smallvec::SmallVec;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Interpolation used by the [`DelayNode`] to read samples at fractional delay times
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DelayInterpolation {
+    /// Linear interpolation between the two nearest samples (default)
+    #[default]
+    Linear,
+    /// Third order Lagrange interpolation using the four nearest samples
+    ///
+    /// This reduces the high frequency loss of modulated delays (e.g. chorus or flanger) at the
+    /// cost of some extra processing. Linear interpolation is used for delays shorter than two
+    /// samples, as the required samples are not available yet.
+    Cubic,
+}
+
+impl From<u32> for DelayInterpolation {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => DelayInterpolation::Linear,
+            1 => DelayInterpolation::Cubic,
+            _ => unreachable!(),
+        }
+    }
+}
 
 /// Options for constructing a [`DelayNode`]
 // dictionary DelayOptions : AudioNodeOptions {
@@ -18,6 +49,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 pub struct DelayOptions {
     pub max_delay_time: f64,
     pub delay_time: f64,
+    pub interpolation: DelayInterpolation,
     pub channel_config: ChannelConfigOptions,
 }
 
@@ -26,6 +58,7 @@ impl Default for DelayOptions {
         Self {
             max_delay_time: 1.,
             delay_time: 0.,
+            interpolation: DelayInterpolation::default(),
             channel_config: ChannelConfigOptions::default(),
         }
     }
@@ -36,6 +69,117 @@ struct PlaybackInfo {
     prev_block_index: usize,
     prev_frame_index: usize,
     k: f32,
+    cubic: bool,
+}
+
+/// Samples returned for the channels of silent blocks
+static SILENCE: [f32; RENDER_QUANTUM_SIZE] = [0.; RENDER_QUANTUM_SIZE];
+
+/// A render quantum in the delay line, silence does not hold any channel data
+type DelayBlock = SmallVec<[AudioRenderQuantumChannel; 2]>;
+
+/// Circular buffer of render quanta shared between the writer and the reader
+///
+/// Silent blocks are stored without channel data, so a long delay line (up to the three minutes
+/// allowed by the spec) only takes up memory for the audio that is actually delayed.
+struct DelayLine {
+    blocks: Vec<DelayBlock>,
+    /// Number of channels of all blocks
+    number_of_channels: usize,
+}
+
+impl DelayLine {
+    fn new(num_quanta: usize) -> Self {
+        Self {
+            blocks: vec![DelayBlock::new(); num_quanta],
+            number_of_channels: 1,
+        }
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Samples of the given channel of a block
+    #[inline(always)]
+    fn channel_data(&self, block_index: usize, channel_number: usize) -> &[f32] {
+        match self.blocks[block_index].get(channel_number) {
+            Some(channel) => channel,
+            None => &SILENCE,
+        }
+    }
+
+    /// Sample at an offset from the given frame, wrapping around the delay line
+    #[inline(always)]
+    fn sample_at(
+        &self,
+        block_index: usize,
+        frame_index: usize,
+        offset: isize,
+        channel_number: usize,
+    ) -> f32 {
+        let len = (self.len() * RENDER_QUANTUM_SIZE) as isize;
+        let index = (block_index * RENDER_QUANTUM_SIZE + frame_index) as isize + offset;
+        let index = index.rem_euclid(len) as usize;
+
+        self.channel_data(index / RENDER_QUANTUM_SIZE, channel_number)[index % RENDER_QUANTUM_SIZE]
+    }
+
+    /// Store a render quantum at the given block index
+    #[inline(always)]
+    fn write(&mut self, block_index: usize, render_quantum: &AudioRenderQuantum) {
+        let block = &mut self.blocks[block_index];
+        block.clear();
+
+        if !render_quantum.is_silent() {
+            block.extend(render_quantum.channels().iter().cloned());
+        }
+    }
+
+    #[inline(always)]
+    fn check_up_down_mix(&mut self, input: &AudioRenderQuantum) {
+        // [spec]
+        // When the number of channels in a DelayNode's input changes (thus changing
+        // the output channel count also), there may be delayed audio samples which
+        // have not yet been output by the node and are part of its internal state.
+        // If these samples were received earlier with a different channel count,
+        // they MUST be upmixed or downmixed before being combined with newly received
+        // input so that all internal delay-line mixing takes place using the single
+        // prevailing channel layout.
+        let input_number_of_channels = input.number_of_channels();
+
+        if self.number_of_channels != input_number_of_channels {
+            for block in self.blocks.iter_mut().filter(|block| !block.is_empty()) {
+                let mut render_quantum = AudioRenderQuantum::from(block[0].clone());
+                render_quantum.set_number_of_channels(block.len());
+                render_quantum
+                    .channels_mut()
+                    .iter_mut()
+                    .zip(block.iter())
+                    .for_each(|(c, b)| *c = b.clone());
+
+                render_quantum.mix(input_number_of_channels, ChannelInterpretation::Speakers);
+
+                block.clear();
+                block.extend(render_quantum.channels().iter().cloned());
+            }
+
+            self.number_of_channels = input_number_of_channels;
+        }
+    }
+}
+
+/// Third order Lagrange interpolation at position `k` between `x0` and `x1`
+#[inline(always)]
+fn cubic_interpolation(x_1: f32, x0: f32, x1: f32, x2: f32, k: f32) -> f32 {
+    let k_plus_1 = k + 1.;
+    let k_minus_1 = k - 1.;
+    let k_minus_2 = k - 2.;
+
+    -k * k_minus_1 * k_minus_2 / 6. * x_1 + k_plus_1 * k_minus_1 * k_minus_2 / 2. * x0
+        - k_plus_1 * k * k_minus_2 / 2. * x1
+        + k_plus_1 * k * k_minus_1 / 6. * x2
 }
 
 /// Node that delays the incoming audio signal by a certain amount
@@ -99,6 +243,7 @@ pub struct DelayNode {
     reader_registration: AudioContextRegistration,
     writer_registration: AudioContextRegistration,
     delay_time: AudioParam,
+    interpolation: Arc<AtomicU32>,
     channel_config: ChannelConfig,
 }
 
@@ -125,47 +270,94 @@ impl AudioNode for DelayNode {
     }
 
     /// Connect a specific output of this AudioNode to a specific input of another node.
-    fn connect_at<'a>(
+    fn try_connect_at<'a>(
         &self,
         dest: &'a dyn AudioNode,
         output: usize,
         input: usize,
-    ) -> &'a dyn AudioNode {
-        if self.context() != dest.context() {
-            panic!("InvalidAccessError: Attempting to connect nodes from different contexts");
-        }
-        if self.number_of_outputs() <= output {
-            panic!("IndexSizeError: output port {} is out of bounds", output);
-        }
-        if dest.number_of_inputs() <= input {
-            panic!("IndexSizeError: input port {} is out of bounds", input);
-        }
-
-        self.context().connect(
+    ) -> Result<&'a dyn AudioNode, ConnectError> {
+        try_connect_node(
+            self.context(),
             self.reader_registration.id(),
-            dest.registration().id(),
+            self.number_of_outputs(),
+            dest,
             output,
             input,
+        )?;
+        Ok(dest)
+    }
+
+    /// Disconnects all outgoing connections from the AudioNode.
+    fn disconnect(&self) {
+        disconnect_node(
+            self.context(),
+            self.reader_registration.id(),
+            self.number_of_outputs(),
+            None,
+            None,
+            None,
         );
+    }
 
-        dest
+    /// Disconnects all outgoing connections from the given output of the AudioNode.
+    fn disconnect_output(&self, output: usize) {
+        disconnect_node(
+            self.context(),
+            self.reader_registration.id(),
+            self.number_of_outputs(),
+            Some(output),
+            None,
+            None,
+        );
     }
 
     /// Disconnects all outputs of the AudioNode that go to a specific destination AudioNode.
-    fn disconnect_from<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
-        if self.context() != dest.context() {
-            panic!("attempting to disconnect nodes from different contexts");
-        }
-
-        self.context()
-            .disconnect_from(self.reader_registration.id(), dest.registration().id());
+    fn disconnect_destination<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
+        disconnect_node(
+            self.context(),
+            self.reader_registration.id(),
+            self.number_of_outputs(),
+            None,
+            Some(dest),
+            None,
+        );
+        dest
+    }
 
+    /// Disconnects a specific output of the AudioNode from a specific destination AudioNode.
+    fn disconnect_destination_output<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: usize,
+    ) -> &'a dyn AudioNode {
+        disconnect_node(
+            self.context(),
+            self.reader_registration.id(),
+            self.number_of_outputs(),
+            Some(output),
+            Some(dest),
+            None,
+        );
         dest
     }
 
-    /// Disconnects all outgoing connections from the AudioNode.
-    fn disconnect(&self) {
-        self.context().disconnect(self.reader_registration.id());
+    /// Disconnects a specific output of the AudioNode from a specific input of a destination
+    /// AudioNode.
+    fn disconnect_destination_output_input<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: usize,
+        input: usize,
+    ) -> &'a dyn AudioNode {
+        disconnect_node(
+            self.context(),
+            self.reader_registration.id(),
+            self.number_of_outputs(),
+            Some(output),
+            Some(dest),
+            Some(input),
+        );
+        dest
     }
 }
 
@@ -197,16 +389,20 @@ impl DelayNode {
 
         // allocate large enough buffer to store all delayed samples
         //
-        // we add 1 here so that in edge cases where num_samples is a multiple of
+        // we add 2 here so that in edge cases where num_samples is a multiple of
         // RENDER_QUANTUM_SIZE and delay_time == max_delay_time we are sure to
-        // enough room for history. (see. test_max_delay_multiple_of_quantum_size)
-        let num_samples = max_delay_time * sample_rate + 1.;
+        // enough room for history, including the extra sample needed by the
+        // cubic interpolation. (see. test_max_delay_multiple_of_quantum_size)
+        let num_samples = max_delay_time * sample_rate + 2.;
         let num_quanta =
             (num_samples.ceil() as usize + RENDER_QUANTUM_SIZE - 1) / RENDER_QUANTUM_SIZE;
-        let ring_buffer = Vec::with_capacity(num_quanta);
+        let delay_line = DelayLine::new(num_quanta);
+
+        let shared_delay_line = Rc::new(RefCell::new(delay_line));
+        let shared_delay_line_clone = shared_delay_line.clone();
 
-        let shared_ring_buffer = Rc::new(RefCell::new(ring_buffer));
-        let shared_ring_buffer_clone = shared_ring_buffer.clone();
+        let interpolation = Arc::new(AtomicU32::new(options.interpolation as u32));
+        let interpolation_clone = interpolation.clone();
 
         // shared value set by the writer when it is dropped
         let last_written_index = Rc::new(Cell::<Option<usize>>::new(None));
@@ -231,7 +427,8 @@ impl DelayNode {
 
                 let reader_render = DelayReader {
                     delay_time: proc,
-                    ring_buffer: shared_ring_buffer_clone,
+                    interpolation: interpolation_clone,
+                    delay_line: shared_delay_line_clone,
                     index: 0,
                     last_written_index: last_written_index_clone,
                     in_cycle: false,
@@ -244,13 +441,14 @@ impl DelayNode {
                     writer_registration,
                     channel_config: options.channel_config.into(),
                     delay_time: param,
+                    interpolation,
                 };
 
                 (node, Box::new(reader_render))
             });
 
             let writer_render = DelayWriter {
-                ring_buffer: shared_ring_buffer,
+                delay_line: shared_delay_line,
                 index: 0,
                 last_written_index,
                 latest_frame_written,
@@ -274,45 +472,43 @@ impl DelayNode {
     pub fn delay_time(&self) -> &AudioParam {
         &self.delay_time
     }
+
+    /// Interpolation used for fractional delay times
+    #[must_use]
+    pub fn interpolation(&self) -> DelayInterpolation {
+        self.interpolation.load(Ordering::SeqCst).into()
+    }
+
+    /// Change the interpolation used for fractional delay times, which can be done while
+    /// rendering
+    pub fn set_interpolation(&self, interpolation: DelayInterpolation) {
+        self.interpolation
+            .store(interpolation as u32, Ordering::SeqCst);
+    }
+
+    /// Registration of the reader, which is the source of the outgoing connections
+    pub(crate) fn reader_registration(&self) -> &AudioContextRegistration {
+        &self.reader_registration
+    }
 }
 
 struct DelayWriter {
-    ring_buffer: Rc<RefCell<Vec<AudioRenderQuantum>>>,
+    delay_line: Rc<RefCell<DelayLine>>,
     index: usize,
     latest_frame_written: Rc<AtomicU64>,
     last_written_index: Rc<Cell<Option<usize>>>,
 }
 
 // SAFETY:
-// AudioRenderQuantums are not Send but we promise the `ring_buffer` Vec is
-// empty before we ship it to the render thread.
+// AudioRenderQuantumChannels are not Send but we promise the `delay_line` does
+// not hold any channel data before we ship it to the render thread.
 #[allow(clippy::non_send_fields_in_send_ty)]
 unsafe impl Send for DelayWriter {}
 
-trait RingBufferChecker {
-    fn ring_buffer_mut(&self) -> RefMut<Vec<AudioRenderQuantum>>;
-
-    // This step guarantees the ring buffer is filled with silence buffers,
-    // This allow to simplify the code in both Writer and Reader as we know
-    // `len() == capacity()` and all inner buffers are initialized with zeros.
-    #[inline(always)]
-    fn check_ring_buffer_size(&self, render_quantum: &AudioRenderQuantum) {
-        let mut ring_buffer = self.ring_buffer_mut();
-
-        if ring_buffer.len() < ring_buffer.capacity() {
-            let len = ring_buffer.capacity();
-            let mut silence = render_quantum.clone();
-            silence.make_silent();
-
-            ring_buffer.resize(len, silence);
-        }
-    }
-}
-
 impl Drop for DelayWriter {
     fn drop(&mut self) {
         let last_written_index = if self.index == 0 {
-            self.ring_buffer.borrow().capacity() - 1
+            self.delay_line.borrow().len() - 1
         } else {
             self.index - 1
         };
@@ -321,13 +517,6 @@ impl Drop for DelayWriter {
     }
 }
 
-impl RingBufferChecker for DelayWriter {
-    #[inline(always)]
-    fn ring_buffer_mut(&self) -> RefMut<Vec<AudioRenderQuantum>> {
-        self.ring_buffer.borrow_mut()
-    }
-}
-
 impl AudioProcessor for DelayWriter {
     fn process(
         &mut self,
@@ -337,22 +526,19 @@ impl AudioProcessor for DelayWriter {
         scope: &RenderScope,
     ) -> bool {
         // single input/output node
-        let input = inputs[0].clone();
+        let input = &inputs[0];
         let output = &mut outputs[0];
 
-        // We must perform this check on both Writer and Reader as the order of
-        // the rendering between them is not guaranteed.
-        self.check_ring_buffer_size(&input);
-        // `check_ring_buffer_up_down_mix` can only be done on the Writer
-        // side as Reader do not access the "real" input
-        self.check_ring_buffer_up_down_mix(&input);
+        let mut delay_line = self.delay_line.borrow_mut();
+        // the up/down mix can only be done on the Writer side as Reader do not
+        // access the "real" input
+        delay_line.check_up_down_mix(input);
 
         // populate ring buffer
-        let mut buffer = self.ring_buffer.borrow_mut();
-        buffer[self.index] = input;
+        delay_line.write(self.index, input);
 
         // increment cursor and last written frame
-        self.index = (self.index + 1) % buffer.capacity();
+        self.index = (self.index + 1) % delay_line.len();
         self.latest_frame_written
             .store(scope.current_frame, Ordering::SeqCst);
 
@@ -365,32 +551,10 @@ impl AudioProcessor for DelayWriter {
     }
 }
 
-impl DelayWriter {
-    #[inline(always)]
-    fn check_ring_buffer_up_down_mix(&self, input: &AudioRenderQuantum) {
-        // [spec]
-        // When the number of channels in a DelayNode's input changes (thus changing
-        // the output channel count also), there may be delayed audio samples which
-        // have not yet been output by the node and are part of its internal state.
-        // If these samples were received earlier with a different channel count,
-        // they MUST be upmixed or downmixed before being combined with newly received
-        // input so that all internal delay-line mixing takes place using the single
-        // prevailing channel layout.
-        let mut ring_buffer = self.ring_buffer_mut();
-        let buffer_number_of_channels = ring_buffer[0].number_of_channels();
-        let input_number_of_channels = input.number_of_channels();
-
-        if buffer_number_of_channels != input_number_of_channels {
-            for render_quantum in ring_buffer.iter_mut() {
-                render_quantum.mix(input_number_of_channels, ChannelInterpretation::Speakers);
-            }
-        }
-    }
-}
-
 struct DelayReader {
     delay_time: AudioParamId,
-    ring_buffer: Rc<RefCell<Vec<AudioRenderQuantum>>>,
+    interpolation: Arc<AtomicU32>,
+    delay_line: Rc<RefCell<DelayLine>>,
     index: usize,
     latest_frame_written: Rc<AtomicU64>,
     in_cycle: bool,
@@ -400,18 +564,11 @@ struct DelayReader {
 }
 
 // SAFETY:
-// AudioRenderQuantums are not Send but we promise the `ring_buffer` Vec is
-// empty before we ship it to the render thread.
+// AudioRenderQuantumChannels are not Send but we promise the `delay_line` does
+// not hold any channel data before we ship it to the render thread.
 #[allow(clippy::non_send_fields_in_send_ty)]
 unsafe impl Send for DelayReader {}
 
-impl RingBufferChecker for DelayReader {
-    #[inline(always)]
-    fn ring_buffer_mut(&self) -> RefMut<Vec<AudioRenderQuantum>> {
-        self.ring_buffer.borrow_mut()
-    }
-}
-
 impl AudioProcessor for DelayReader {
     fn process(
         &mut self,
@@ -422,15 +579,11 @@ impl AudioProcessor for DelayReader {
     ) -> bool {
         // single input/output node
         let output = &mut outputs[0];
-        // We must perform the checks (buffer size and up/down mix) on both Writer
-        // and Reader as the order of processing between them is not guaranteed.
-        self.check_ring_buffer_size(output);
 
-        let ring_buffer = self.ring_buffer.borrow();
+        let delay_line = self.delay_line.borrow();
 
-        // we need to rely on ring buffer to know the actual number of output channels
-        let number_of_channels = ring_buffer[0].number_of_channels();
-        output.set_number_of_channels(number_of_channels);
+        // we need to rely on the delay line to know the actual number of output channels
+        output.set_number_of_channels(delay_line.number_of_channels);
 
         if !self.in_cycle {
             // check the latest written frame by the delay writer
@@ -443,16 +596,18 @@ impl AudioProcessor for DelayReader {
 
         // compute all playback infos for this block
         let delay = params.get(&self.delay_time);
+        let interpolation = self.interpolation.load(Ordering::SeqCst).into();
         let sample_rate = scope.sample_rate as f64;
         let dt = 1. / sample_rate;
         let quantum_duration = RENDER_QUANTUM_SIZE as f64 * dt;
-        let ring_size = ring_buffer.len() as i32;
+        let ring_size = delay_line.len() as i32;
         let ring_index = self.index as i32;
         let mut playback_infos = [PlaybackInfo::default(); RENDER_QUANTUM_SIZE];
 
         if delay.len() == 1 {
             playback_infos[0] = Self::get_playback_infos(
                 f64::from(delay[0]),
+                interpolation,
                 self.in_cycle,
                 0.,
                 quantum_duration,
@@ -466,13 +621,14 @@ impl AudioProcessor for DelayReader {
                     prev_block_index,
                     prev_frame_index,
                     k,
+                    cubic,
                 } = playback_infos[i - 1];
 
                 let mut prev_block_index = prev_block_index;
                 let mut prev_frame_index = prev_frame_index + 1;
 
                 if prev_frame_index >= RENDER_QUANTUM_SIZE {
-                    prev_block_index = (prev_block_index + 1) % ring_buffer.len();
+                    prev_block_index = (prev_block_index + 1) % delay_line.len();
                     prev_frame_index = 0;
                 }
 
@@ -480,6 +636,7 @@ impl AudioProcessor for DelayReader {
                     prev_block_index,
                     prev_frame_index,
                     k,
+                    cubic,
                 };
             }
         } else {
@@ -490,6 +647,7 @@ impl AudioProcessor for DelayReader {
                 .for_each(|(index, (&d, infos))| {
                     *infos = Self::get_playback_infos(
                         f64::from(d),
+                        interpolation,
                         self.in_cycle,
                         index as f64,
                         quantum_duration,
@@ -510,7 +668,7 @@ impl AudioProcessor for DelayReader {
         for (channel_number, output_channel) in output.channels_mut().iter_mut().enumerate() {
             // store channel data locally and update pointer only when needed
             let mut block_index = playback_infos[0].prev_block_index;
-            let mut channel_data = ring_buffer[block_index].channel_data(channel_number);
+            let mut channel_data = delay_line.channel_data(block_index, channel_number);
 
             output_channel
                 .iter_mut()
@@ -520,6 +678,7 @@ impl AudioProcessor for DelayReader {
                         prev_block_index,
                         prev_frame_index,
                         k,
+                        cubic,
                     } = *infos;
 
                     // find next sample address
@@ -527,7 +686,7 @@ impl AudioProcessor for DelayReader {
                     let mut next_frame_index = prev_frame_index + 1;
 
                     if next_frame_index >= RENDER_QUANTUM_SIZE {
-                        next_block_index = (next_block_index + 1) % ring_buffer.len();
+                        next_block_index = (next_block_index + 1) % delay_line.len();
                         next_frame_index = 0;
                     }
 
@@ -536,7 +695,7 @@ impl AudioProcessor for DelayReader {
                     // be in case of an automotation with increasing delay time
                     if block_index != prev_block_index {
                         block_index = prev_block_index;
-                        channel_data = ring_buffer[block_index].channel_data(channel_number);
+                        channel_data = delay_line.channel_data(block_index, channel_number);
                     }
 
                     let prev_sample = channel_data[prev_frame_index];
@@ -544,12 +703,35 @@ impl AudioProcessor for DelayReader {
                     // update pointer to channel_data if needed
                     if block_index != next_block_index {
                         block_index = next_block_index;
-                        channel_data = ring_buffer[block_index].channel_data(channel_number);
+                        channel_data = delay_line.channel_data(block_index, channel_number);
                     }
 
                     let next_sample = channel_data[next_frame_index];
 
-                    let value = (1. - k).mul_add(prev_sample, k * next_sample);
+                    let value = if cubic {
+                        let before_prev_sample = delay_line.sample_at(
+                            prev_block_index,
+                            prev_frame_index,
+                            -1,
+                            channel_number,
+                        );
+                        let after_next_sample = delay_line.sample_at(
+                            next_block_index,
+                            next_frame_index,
+                            1,
+                            channel_number,
+                        );
+
+                        cubic_interpolation(
+                            before_prev_sample,
+                            prev_sample,
+                            next_sample,
+                            after_next_sample,
+                            k,
+                        )
+                    } else {
+                        (1. - k).mul_add(prev_sample, k * next_sample)
+                    };
 
                     if value.is_normal() {
                         is_actively_processing = true;
@@ -577,7 +759,7 @@ impl AudioProcessor for DelayReader {
             self.last_written_index_checked = last_written_index;
         }
         // increment ring buffer cursor
-        self.index = (self.index + 1) % ring_buffer.capacity();
+        self.index = (self.index + 1) % delay_line.len();
 
         true
     }
@@ -585,8 +767,10 @@ impl AudioProcessor for DelayReader {
 
 impl DelayReader {
     #[inline(always)]
+    #[allow(clippy::too_many_arguments)]
     fn get_playback_infos(
         delay: f64,
+        interpolation: DelayInterpolation,
         in_cycle: bool,
         sample_index: f64,
         quantum_duration: f64,
@@ -602,6 +786,15 @@ impl DelayReader {
             delay
         };
         let num_samples = clamped_delay * sample_rate;
+        // the cubic interpolation needs the sample after the next one, which has
+        // only been written when the delay is at least two samples (or two samples
+        // more than a render quantum when the writer renders after the reader)
+        let min_cubic_samples = if in_cycle {
+            RENDER_QUANTUM_SIZE as f64 + 2.
+        } else {
+            2.
+        };
+        let cubic = interpolation == DelayInterpolation::Cubic && num_samples >= min_cubic_samples;
         // negative position of the playhead relative to this block start
         let position = sample_index - num_samples;
         let position_floored = position.floor();
@@ -639,6 +832,7 @@ impl DelayReader {
             prev_block_index: prev_block_index as usize,
             prev_frame_index: prev_frame_index as usize,
             k,
+            cubic,
         }
     }
 }
@@ -1038,4 +1232,169 @@ mod tests {
 
         assert_float_eq!(channel[..], expected[..], abs_all <= 1e-5);
     }
+
+    #[test]
+    fn test_interpolation() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+
+        let delay = context.create_delay(1.);
+        assert_eq!(delay.interpolation(), DelayInterpolation::Linear);
+
+        delay.set_interpolation(DelayInterpolation::Cubic);
+        assert_eq!(delay.interpolation(), DelayInterpolation::Cubic);
+
+        let options = DelayOptions {
+            interpolation: DelayInterpolation::Cubic,
+            ..DelayOptions::default()
+        };
+        let delay = DelayNode::new(&context, options);
+        assert_eq!(delay.interpolation(), DelayInterpolation::Cubic);
+    }
+
+    #[test]
+    fn test_cubic_interpolation_integer_delay() {
+        // cubic interpolation should not alter integer delays
+        for delay_in_samples in [1., 64., 131.].iter() {
+            let sample_rate = 48000.;
+            let context = OfflineAudioContext::new(1, 256, sample_rate);
+
+            let options = DelayOptions {
+                delay_time: delay_in_samples / sample_rate as f64,
+                interpolation: DelayInterpolation::Cubic,
+                ..DelayOptions::default()
+            };
+            let delay = DelayNode::new(&context, options);
+            delay.connect(&context.destination());
+
+            let mut dirac = context.create_buffer(1, 1, sample_rate);
+            dirac.copy_to_channel(&[1.], 0);
+
+            let src = context.create_buffer_source();
+            src.connect(&delay);
+            src.set_buffer(dirac);
+            src.start_at(0.);
+
+            let result = context.start_rendering_sync();
+            let channel = result.get_channel_data(0);
+
+            let mut expected = vec![0.; 256];
+            expected[*delay_in_samples as usize] = 1.;
+
+            assert_float_eq!(channel[..], expected[..], abs_all <= 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_cubic_interpolation_fallback() {
+        // below two samples of delay, the cubic interpolation falls back to linear
+        let delay_in_samples = 1.5;
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 128, sample_rate);
+
+        let options = DelayOptions {
+            delay_time: delay_in_samples / sample_rate as f64,
+            interpolation: DelayInterpolation::Cubic,
+            ..DelayOptions::default()
+        };
+        let delay = DelayNode::new(&context, options);
+        delay.connect(&context.destination());
+
+        let mut dirac = context.create_buffer(1, 1, sample_rate);
+        dirac.copy_to_channel(&[1.], 0);
+
+        let src = context.create_buffer_source();
+        src.connect(&delay);
+        src.set_buffer(dirac);
+        src.start_at(0.);
+
+        let result = context.start_rendering_sync();
+        let channel = result.get_channel_data(0);
+
+        let mut expected = vec![0.; 128];
+        expected[1] = 0.5;
+        expected[2] = 0.5;
+
+        assert_float_eq!(channel[..], expected[..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_cubic_interpolation_accuracy() {
+        // a fractional delay of a sine wave should be closer to the exact
+        // delayed signal with cubic interpolation than with linear interpolation
+        let delay_in_samples = 130.3;
+        let sample_rate = 48000.;
+        let length = RENDER_QUANTUM_SIZE * 4;
+        let frequency = 2000.;
+
+        let render = |interpolation| {
+            let context = OfflineAudioContext::new(1, length, sample_rate);
+
+            let options = DelayOptions {
+                delay_time: delay_in_samples / sample_rate as f64,
+                interpolation,
+                ..DelayOptions::default()
+            };
+            let delay = DelayNode::new(&context, options);
+            delay.connect(&context.destination());
+
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(frequency);
+            osc.connect(&delay);
+            osc.start();
+
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
+
+        let max_error = |rendered: &[f32]| {
+            rendered
+                .iter()
+                .enumerate()
+                .skip(delay_in_samples.ceil() as usize + 2)
+                .map(|(i, v)| {
+                    let t = (i as f64 - delay_in_samples) / sample_rate as f64;
+                    let exact = (2. * std::f64::consts::PI * frequency as f64 * t).sin();
+                    (*v as f64 - exact).abs()
+                })
+                .fold(0., f64::max)
+        };
+
+        let linear_error = max_error(&render(DelayInterpolation::Linear));
+        let cubic_error = max_error(&render(DelayInterpolation::Cubic));
+
+        assert!(cubic_error < linear_error / 4.);
+        assert!(cubic_error < 1e-3);
+    }
+
+    #[test]
+    fn test_max_delay_three_minutes() {
+        // long delay lines do not hold memory for silent blocks, but must still
+        // be able to delay a signal by almost three minutes
+        let sample_rate = 3000.;
+        let max_delay_time = 179.9;
+        // exactly representable as f32
+        let delay_time = 179.5;
+        let delay_in_samples = (delay_time * sample_rate as f64) as usize;
+        let length = delay_in_samples + RENDER_QUANTUM_SIZE;
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+
+        let delay = context.create_delay(max_delay_time);
+        delay.delay_time.set_value(delay_time as f32);
+        delay.connect(&context.destination());
+
+        let mut dirac = context.create_buffer(1, 1, sample_rate);
+        dirac.copy_to_channel(&[1.], 0);
+
+        let src = context.create_buffer_source();
+        src.connect(&delay);
+        src.set_buffer(dirac);
+        src.start_at(0.);
+
+        let result = context.start_rendering_sync();
+        let channel = result.get_channel_data(0);
+
+        let mut expected = vec![0.; length];
+        expected[delay_in_samples] = 1.;
+
+        assert_float_eq!(channel[..], expected[..], abs_all <= 1e-5);
+    }
 }
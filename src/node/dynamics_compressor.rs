@@ -6,7 +6,11 @@ use crate::param::{AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::{AtomicF32, RENDER_QUANTUM_SIZE};
 
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+use super::bypass::Bypass;
+use super::{
+    AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode,
+    ChannelInterpretation,
+};
 
 // Converting a value 𝑣 in decibels to linear gain unit means returning 10𝑣/20.
 fn db_to_lin(val: f32) -> f32 {
@@ -40,6 +44,8 @@ pub struct DynamicsCompressorOptions {
     pub ratio: f32,
     pub release: f32,
     pub threshold: f32,
+    /// Add a second input used as the detector signal, i.e. a sidechain (non-standard)
+    pub sidechain: bool,
     pub channel_config: ChannelConfigOptions,
 }
 
@@ -51,11 +57,48 @@ impl Default for DynamicsCompressorOptions {
             ratio: 12.,      // unit less
             release: 0.25,   // seconds
             threshold: -24., // dB
-            channel_config: ChannelConfigOptions::default(),
+            sidechain: false,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
         }
     }
 }
 
+/// Assert that the channel count is valid for the DynamicsCompressorNode
+/// see <https://webaudio.github.io/web-audio-api/#audionode-channelcount-constraints>
+///
+/// # Panics
+///
+/// This function panics if given count is greater than 2
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count(count: usize) {
+    if count > 2 {
+        panic!(
+            "NotSupportedError: DynamicsCompressorNode channel count cannot be greater than two"
+        );
+    }
+}
+
+/// Assert that the channel count mode is valid for the DynamicsCompressorNode
+/// see <https://webaudio.github.io/web-audio-api/#audionode-channelcountmode-constraints>
+///
+/// # Panics
+///
+/// This function panics if given count mode is [`ChannelCountMode::Max`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
+    if mode == ChannelCountMode::Max {
+        panic!("NotSupportedError: DynamicsCompressorNode channel count mode cannot be set to max");
+    }
+}
+
 /// `DynamicsCompressorNode` provides a compression effect.
 ///
 /// It lowers the volume of the loudest parts of the signal and raises the volume
@@ -92,6 +135,14 @@ impl Default for DynamicsCompressorOptions {
 /// src.start();
 /// ```
 ///
+/// # Sidechain
+///
+/// With the non-standard [`DynamicsCompressorOptions::sidechain`] option, the node
+/// has a second input which drives the level detector instead of the compressed
+/// signal, e.g. to duck a music track when a voice is speaking. The sidechain
+/// signal itself is not part of the output, and an unconnected sidechain input
+/// leaves the signal uncompressed.
+///
 /// # Examples
 ///
 /// - `cargo run --release --example compressor`
@@ -105,6 +156,8 @@ pub struct DynamicsCompressorNode {
     release: AudioParam,
     threshold: AudioParam,
     reduction: Arc<AtomicF32>,
+    sidechain: bool,
+    bypass: Bypass,
 }
 
 impl AudioNode for DynamicsCompressorNode {
@@ -116,18 +169,60 @@ impl AudioNode for DynamicsCompressorNode {
         &self.channel_config
     }
 
+    /// The compressed signal, and the sidechain if enabled
     fn number_of_inputs(&self) -> usize {
-        1
+        if self.sidechain {
+            2
+        } else {
+            1
+        }
     }
 
     fn number_of_outputs(&self) -> usize {
         1
     }
+
+    /// The look-ahead delay line of ~6ms, rounded up to whole render quanta
+    fn latency(&self) -> f64 {
+        let sample_rate = self.context().sample_rate();
+        let frames = (ring_buffer_size(sample_rate) - 1) * RENDER_QUANTUM_SIZE;
+        frames as f64 / f64::from(sample_rate)
+    }
+
+    fn set_channel_count(&self, count: usize) {
+        assert_valid_channel_count(count);
+        self.channel_config.set_count(count);
+    }
+
+    fn set_channel_count_mode(&self, mode: ChannelCountMode) {
+        assert_valid_channel_count_mode(mode);
+        self.channel_config.set_count_mode(mode);
+    }
+}
+
+impl AudioEffectNode for DynamicsCompressorNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
 }
 
 impl DynamicsCompressorNode {
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `options.channel_config.count` is greater than 2
+    /// * `options.channel_config.mode` is `ChannelCountMode::Max`
+    ///
     pub fn new<C: BaseAudioContext>(context: &C, options: DynamicsCompressorOptions) -> Self {
         context.register(move |registration| {
+            assert_valid_channel_count_mode(options.channel_config.count_mode);
+            assert_valid_channel_count(options.channel_config.count);
+
             // attack, knee, ratio, release and threshold have automation rate constraints
             // https://webaudio.github.io/web-audio-api/#audioparam-automation-rate-constraints
             let attack_param_opts = AudioParamDescriptor {
@@ -187,10 +282,7 @@ impl DynamicsCompressorNode {
 
             let reduction = Arc::new(AtomicF32::new(0.));
 
-            // define the number of buffers we need to have a delay line of ~6ms
-            // const delay = new DelayNode(context, {delayTime: 0.006});
-            let ring_buffer_size =
-                (context.sample_rate() * 0.006 / RENDER_QUANTUM_SIZE as f32).ceil() as usize + 1;
+            let ring_buffer_size = ring_buffer_size(context.sample_rate());
             let ring_buffer = Vec::<AudioRenderQuantum>::with_capacity(ring_buffer_size);
 
             let render = DynamicsCompressorRenderer {
@@ -203,10 +295,12 @@ impl DynamicsCompressorNode {
                 ring_buffer,
                 ring_index: 0,
                 prev_detector_value: 0.,
+                sidechain: options.sidechain,
             };
 
             let node = DynamicsCompressorNode {
                 registration,
+                bypass: Bypass::default(),
                 channel_config: options.channel_config.into(),
                 attack: attack_param,
                 knee: knee_param,
@@ -214,8 +308,12 @@ impl DynamicsCompressorNode {
                 release: release_param,
                 threshold: threshold_param,
                 reduction,
+                sidechain: options.sidechain,
             };
 
+            let render = node
+                .bypass
+                .wrap(render, (ring_buffer_size - 1) * RENDER_QUANTUM_SIZE);
             (node, Box::new(render))
         })
     }
@@ -243,6 +341,136 @@ impl DynamicsCompressorNode {
     pub fn reduction(&self) -> f32 {
         self.reduction.load(Ordering::SeqCst)
     }
+
+    /// Returns whether the level detector is driven by the second input
+    pub fn sidechain(&self) -> bool {
+        self.sidechain
+    }
+}
+
+/// Number of render quanta in the delay line of ~6ms
+fn ring_buffer_size(sample_rate: f32) -> usize {
+    // const delay = new DelayNode(context, {delayTime: 0.006});
+    (sample_rate * 0.006 / RENDER_QUANTUM_SIZE as f32).ceil() as usize + 1
+}
+
+/// Values of the compressor parameters, constant over a render quantum
+#[derive(Clone, Copy, Debug)]
+struct CompressorParams {
+    attack: f32,
+    knee: f32,
+    ratio: f32,
+    release: f32,
+    threshold: f32,
+}
+
+/// Run the gain computer and level detector stages over a render quantum
+///
+/// Returns the linear gain to apply to each frame of the delayed signal, and the last gain
+/// reduction in dB.
+fn compute_reduction_gains<C: AsRef<[f32]>>(
+    params: &CompressorParams,
+    sample_rate: f32,
+    prev_detector_value: &mut f32,
+    channels: &[C],
+) -> ([f32; RENDER_QUANTUM_SIZE], f32) {
+    // setup values for compression curve
+    // https://webaudio.github.io/web-audio-api/#compression-curve
+    let CompressorParams {
+        attack,
+        knee,
+        ratio,
+        release,
+        threshold,
+    } = *params;
+    // @note: if knee != 0. we shadow threshold to match definitions of knee
+    //   and threshold given in https://www.eecs.qmul.ac.uk/~josh/documents/2012/
+    //   where knee is centered around threshold.
+    //   We can thus reuse their formula for the gain computer stage.
+    // yG =
+    //     xG                                      if 2(xG − T) < −W
+    //     xG + (1/R − 1)(xG − T + W/2)^2 / (2W)   if 2|(xG − T)| ≤ W
+    //     T + (xG − T)/R                          if 2(xG − T) > W
+    // This is weird, and probably wrong because `knee` and `threshold` are not
+    // independant, but matches the spec.
+    let threshold = if knee > 0. {
+        threshold + knee / 2.
+    } else {
+        threshold
+    };
+    let half_knee = knee / 2.;
+    // pre-compute for this block the constant part of the formula of the knee
+    let knee_partial = (1. / ratio - 1.) / (2. * knee);
+
+    // compute time constants for attack and release - eq. (7) in paper
+    let attack_tau = (-1. / (attack * sample_rate)).exp();
+    let release_tau = (-1. / (release * sample_rate)).exp();
+
+    // Computing the makeup gain means executing the following steps:
+    // - Let full range gain be the value returned by applying the compression curve to the value 1.0.
+    // - Let full range makeup gain be the inverse of full range gain.
+    // - Return the result of taking the 0.6 power of full range makeup gain.
+    // @note: this should be confirmed / simplified, maybe could do all this in dB
+    // seems coherent with chrome implementation
+    let full_range_gain = threshold + (-threshold / ratio);
+    let full_range_makeup = 1. / db_to_lin(full_range_gain);
+    let makeup_gain = lin_to_db(full_range_makeup.powf(0.6));
+
+    let mut reduction_gain = 0.; // dB
+    let mut reduction_gains = [0.; 128]; // lin
+    let mut detector_values = [0.; 128]; // lin
+
+    for i in 0..RENDER_QUANTUM_SIZE {
+        // pick highest value for this index across all input channels
+        // @tbc - this seems to be what is done in chrome
+        let mut max = f32::MIN;
+
+        for channel in channels.iter() {
+            let sample = channel.as_ref()[i].abs();
+            if sample > max {
+                max = sample;
+            }
+        }
+
+        // pick absolute value and convert to dB domain
+        // var xG in paper
+        let sample_db = lin_to_db(max);
+
+        // Gain Computer stage
+        // ------------------------------------------------
+        // var yG - eq. 4 in paper
+        // if knee == 0. (hard knee), the `else if` branch is bypassed
+        let sample_attenuated = if sample_db <= threshold - half_knee {
+            sample_db
+        } else if sample_db <= threshold + half_knee {
+            sample_db + (sample_db - threshold + half_knee).powi(2) * knee_partial
+        } else {
+            threshold + (sample_db - threshold) / ratio
+        };
+        // variable xL in paper
+        let sample_attenuation = sample_db - sample_attenuated;
+
+        // Level Detector stage
+        // ------------------------------------------------
+        // Branching peak detector - eq. 16 in paper - var yL
+        // attack branch
+        let detector_value = if sample_attenuation > *prev_detector_value {
+            attack_tau * *prev_detector_value + (1. - attack_tau) * sample_attenuation
+        // release branch
+        } else {
+            release_tau * *prev_detector_value + (1. - release_tau) * sample_attenuation
+        };
+
+        detector_values[i] = detector_value;
+        // cdB = -yL + make up gain
+        reduction_gain = -1. * detector_value + makeup_gain;
+        // convert to lin now, so we just to multiply samples later
+        reduction_gains[i] = db_to_lin(reduction_gain);
+        // update prev_detector_value for next sample
+        *prev_detector_value = detector_value;
+    }
+
+    (reduction_gains, reduction_gain)
 }
 
 struct DynamicsCompressorRenderer {
@@ -255,6 +483,7 @@ struct DynamicsCompressorRenderer {
     ring_buffer: Vec<AudioRenderQuantum>,
     ring_index: usize,
     prev_detector_value: f32,
+    sidechain: bool,
 }
 
 // SAFETY:
@@ -287,104 +516,25 @@ impl AudioProcessor for DynamicsCompressorRenderer {
             self.ring_buffer.resize(ring_size, silence);
         }
 
-        // setup values for compression curve
-        // https://webaudio.github.io/web-audio-api/#compression-curve
-        let threshold = params.get(&self.threshold)[0];
-        let knee = params.get(&self.knee)[0];
-        let ratio = params.get(&self.ratio)[0];
-        // @note: if knee != 0. we shadow threshold to match definitions of knee
-        //   and threshold given in https://www.eecs.qmul.ac.uk/~josh/documents/2012/
-        //   where knee is centered around threshold.
-        //   We can thus reuse their formula for the gain computer stage.
-        // yG =
-        //     xG                                      if 2(xG − T) < −W
-        //     xG + (1/R − 1)(xG − T + W/2)^2 / (2W)   if 2|(xG − T)| ≤ W
-        //     T + (xG − T)/R                          if 2(xG − T) > W
-        // This is weird, and probably wrong because `knee` and `threshold` are not
-        // independant, but matches the spec.
-        let threshold = if knee > 0. {
-            threshold + knee / 2.
-        } else {
-            threshold
+        let params = CompressorParams {
+            attack: params.get(&self.attack)[0],
+            knee: params.get(&self.knee)[0],
+            ratio: params.get(&self.ratio)[0],
+            release: params.get(&self.release)[0],
+            threshold: params.get(&self.threshold)[0],
         };
-        let half_knee = knee / 2.;
-        // pre-compute for this block the constant part of the formula of the knee
-        let knee_partial = (1. / ratio - 1.) / (2. * knee);
-
-        // compute time constants for attack and release - eq. (7) in paper
-        let attack = params.get(&self.attack)[0];
-        let release = params.get(&self.release)[0];
-        let attack_tau = (-1. / (attack * sample_rate)).exp();
-        let release_tau = (-1. / (release * sample_rate)).exp();
-
-        // Computing the makeup gain means executing the following steps:
-        // - Let full range gain be the value returned by applying the compression curve to the value 1.0.
-        // - Let full range makeup gain be the inverse of full range gain.
-        // - Return the result of taking the 0.6 power of full range makeup gain.
-        // @note: this should be confirmed / simplified, maybe could do all this in dB
-        // seems coherent with chrome implementation
-        let full_range_gain = threshold + (-threshold / ratio);
-        let full_range_makeup = 1. / db_to_lin(full_range_gain);
-        let makeup_gain = lin_to_db(full_range_makeup.powf(0.6));
-
-        let mut prev_detector_value = self.prev_detector_value;
-
-        let mut reduction_gain = 0.; // dB
-        let mut reduction_gains = [0.; 128]; // lin
-        let mut detector_values = [0.; 128]; // lin
-
-        for i in 0..RENDER_QUANTUM_SIZE {
-            // pick highest value for this index across all input channels
-            // @tbc - this seems to be what is done in chrome
-            let mut max = f32::MIN;
-
-            for channel in input.channels().iter() {
-                let sample = channel[i].abs();
-                if sample > max {
-                    max = sample;
-                }
-            }
 
-            // pick absolute value and convert to dB domain
-            // var xG in paper
-            let sample_db = lin_to_db(max);
-
-            // Gain Computer stage
-            // ------------------------------------------------
-            // var yG - eq. 4 in paper
-            // if knee == 0. (hard knee), the `else if` branch is bypassed
-            let sample_attenuated = if sample_db <= threshold - half_knee {
-                sample_db
-            } else if sample_db <= threshold + half_knee {
-                sample_db + (sample_db - threshold + half_knee).powi(2) * knee_partial
-            } else {
-                threshold + (sample_db - threshold) / ratio
-            };
-            // variable xL in paper
-            let sample_attenuation = sample_db - sample_attenuated;
-
-            // Level Detector stage
-            // ------------------------------------------------
-            // Branching peak detector - eq. 16 in paper - var yL
-            // attack branch
-            let detector_value = if sample_attenuation > prev_detector_value {
-                attack_tau * prev_detector_value + (1. - attack_tau) * sample_attenuation
-            // release branch
-            } else {
-                release_tau * prev_detector_value + (1. - release_tau) * sample_attenuation
-            };
+        // the level detector runs on the sidechain input if enabled
+        let detector = if self.sidechain { &inputs[1] } else { &input };
 
-            detector_values[i] = detector_value;
-            // cdB = -yL + make up gain
-            reduction_gain = -1. * detector_value + makeup_gain;
-            // convert to lin now, so we just to multiply samples later
-            reduction_gains[i] = db_to_lin(reduction_gain);
-            // update prev_detector_value for next sample
-            prev_detector_value = detector_value;
-        }
+        // prev_detector_value is updated for the next block
+        let (reduction_gains, reduction_gain) = compute_reduction_gains(
+            &params,
+            sample_rate,
+            &mut self.prev_detector_value,
+            detector.channels(),
+        );
 
-        // update prev_detector_value for next block
-        self.prev_detector_value = prev_detector_value;
         // update reduction shared w/ main thread
         self.reduction.store(reduction_gain, Ordering::SeqCst);
 
@@ -417,6 +567,193 @@ impl AudioProcessor for DynamicsCompressorRenderer {
     }
 }
 
+/// Standalone dynamics compressor, processing blocks of audio outside of any audio context
+///
+/// This runs the same DSP as the [`DynamicsCompressorNode`] renderer, including its ~6ms
+/// look-ahead delay, so it can be reused in e.g. plugins or tests without setting up an audio
+/// graph. The parameters are plain values that are applied to the whole block.
+///
+/// # Usage
+///
+/// ```
+/// use web_audio_api::node::{DynamicsCompressor, DynamicsCompressorOptions};
+/// use web_audio_api::RENDER_QUANTUM_SIZE;
+///
+/// let mut compressor = DynamicsCompressor::new(44_100., DynamicsCompressorOptions::default());
+///
+/// // mono block of a loud DC signal
+/// let input = [[0.5; RENDER_QUANTUM_SIZE]];
+/// for _ in 0..10 {
+///     let output = compressor.process(&input);
+///     assert_eq!(output.len(), 1);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DynamicsCompressor {
+    sample_rate: f32,
+    params: CompressorParams,
+    reduction: f32,
+    ring_buffer: Vec<Vec<[f32; RENDER_QUANTUM_SIZE]>>,
+    ring_index: usize,
+    prev_detector_value: f32,
+    output: Vec<[f32; RENDER_QUANTUM_SIZE]>,
+}
+
+impl DynamicsCompressor {
+    /// Create a new compressor for the given sample rate
+    ///
+    /// The `channel_config` of the options is ignored, the number of channels is determined by
+    /// the processed blocks.
+    pub fn new(sample_rate: f32, options: DynamicsCompressorOptions) -> Self {
+        let mut compressor = Self {
+            sample_rate,
+            params: CompressorParams {
+                attack: 0.,
+                knee: 0.,
+                ratio: 1.,
+                release: 0.,
+                threshold: 0.,
+            },
+            reduction: 0.,
+            ring_buffer: vec![vec![]; ring_buffer_size(sample_rate)],
+            ring_index: 0,
+            prev_detector_value: 0.,
+            output: vec![],
+        };
+        compressor.set_attack(options.attack);
+        compressor.set_knee(options.knee);
+        compressor.set_ratio(options.ratio);
+        compressor.set_release(options.release);
+        compressor.set_threshold(options.threshold);
+
+        compressor
+    }
+
+    /// Returns the attack time, in seconds
+    #[must_use]
+    pub fn attack(&self) -> f32 {
+        self.params.attack
+    }
+
+    /// Set the attack time in seconds, clamped between 0 and 1
+    pub fn set_attack(&mut self, value: f32) {
+        self.params.attack = value.clamp(0., 1.);
+    }
+
+    /// Returns the knee, in dB
+    #[must_use]
+    pub fn knee(&self) -> f32 {
+        self.params.knee
+    }
+
+    /// Set the knee in dB, clamped between 0 and 40
+    pub fn set_knee(&mut self, value: f32) {
+        self.params.knee = value.clamp(0., 40.);
+    }
+
+    /// Returns the compression ratio
+    #[must_use]
+    pub fn ratio(&self) -> f32 {
+        self.params.ratio
+    }
+
+    /// Set the compression ratio, clamped between 1 and 20
+    pub fn set_ratio(&mut self, value: f32) {
+        self.params.ratio = value.clamp(1., 20.);
+    }
+
+    /// Returns the release time, in seconds
+    #[must_use]
+    pub fn release(&self) -> f32 {
+        self.params.release
+    }
+
+    /// Set the release time in seconds, clamped between 0 and 1
+    pub fn set_release(&mut self, value: f32) {
+        self.params.release = value.clamp(0., 1.);
+    }
+
+    /// Returns the threshold, in dB
+    #[must_use]
+    pub fn threshold(&self) -> f32 {
+        self.params.threshold
+    }
+
+    /// Set the threshold in dB, clamped between -100 and 0
+    pub fn set_threshold(&mut self, value: f32) {
+        self.params.threshold = value.clamp(-100., 0.);
+    }
+
+    /// Returns the gain reduction in dB applied to the last processed block
+    #[must_use]
+    pub fn reduction(&self) -> f32 {
+        self.reduction
+    }
+
+    /// Clear the delay line and the level detector
+    pub fn reset(&mut self) {
+        self.ring_buffer.iter_mut().for_each(Vec::clear);
+        self.ring_index = 0;
+        self.prev_detector_value = 0.;
+        self.reduction = 0.;
+    }
+
+    /// Compress a block of audio, one array per channel
+    ///
+    /// The output has the same number of channels as the input. Because of the look-ahead
+    /// delay, it contains the (compressed) input of previous calls.
+    pub fn process(
+        &mut self,
+        input: &[[f32; RENDER_QUANTUM_SIZE]],
+    ) -> &[[f32; RENDER_QUANTUM_SIZE]] {
+        self.process_with_sidechain(input, input)
+    }
+
+    /// Compress a block of audio, the level detector being driven by the `sidechain` block
+    ///
+    /// The `sidechain` may have a different number of channels than the `input`, see
+    /// [`Self::process`] for the output.
+    pub fn process_with_sidechain(
+        &mut self,
+        input: &[[f32; RENDER_QUANTUM_SIZE]],
+        sidechain: &[[f32; RENDER_QUANTUM_SIZE]],
+    ) -> &[[f32; RENDER_QUANTUM_SIZE]] {
+        let (reduction_gains, reduction_gain) = compute_reduction_gains(
+            &self.params,
+            self.sample_rate,
+            &mut self.prev_detector_value,
+            sidechain,
+        );
+        self.reduction = reduction_gain;
+
+        // store input in delay line, re-using the allocation
+        let stored = &mut self.ring_buffer[self.ring_index];
+        stored.clear();
+        stored.extend_from_slice(input);
+
+        // apply compression to delayed signal
+        let read_index = (self.ring_index + 1) % self.ring_buffer.len();
+        let delayed = &self.ring_buffer[read_index];
+
+        self.ring_index = read_index;
+
+        self.output.resize(input.len(), [0.; RENDER_QUANTUM_SIZE]);
+        self.output.iter_mut().enumerate().for_each(
+            |(channel_number, output_channel)| match delayed.get(channel_number) {
+                Some(delayed_channel) => output_channel
+                    .iter_mut()
+                    .zip(delayed_channel.iter())
+                    .zip(reduction_gains.iter())
+                    .for_each(|((o, &d), g)| *o = d * g),
+                // the delayed block had less channels
+                None => output_channel.fill(0.),
+            },
+        );
+
+        &self.output
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
@@ -426,6 +763,36 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_channel_config_constraints() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        let node = DynamicsCompressorNode::new(&context, DynamicsCompressorOptions::default());
+        assert_eq!(node.channel_count(), 2);
+        assert_eq!(node.channel_count_mode(), ChannelCountMode::ClampedMax);
+
+        node.set_channel_count(1);
+        node.set_channel_count_mode(ChannelCountMode::Explicit);
+        assert_eq!(node.channel_count(), 1);
+        assert_eq!(node.channel_count_mode(), ChannelCountMode::Explicit);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        let node = DynamicsCompressorNode::new(&context, DynamicsCompressorOptions::default());
+        node.set_channel_count(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_channel_count_mode() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        let mut options = DynamicsCompressorOptions::default();
+        options.channel_config.count_mode = ChannelCountMode::Max;
+        let _node = DynamicsCompressorNode::new(&context, options);
+    }
+
     #[test]
     fn test_constructor() {
         {
@@ -485,6 +852,9 @@ mod tests {
         src.connect(&compressor);
         src.start();
 
+        let latency = (compressor.latency() * f64::from(sample_rate)).round() as usize;
+        assert_eq!(latency, non_zero_index);
+
         let res = context.start_rendering_sync();
         let chan = res.channel_data(0).as_slice();
 
@@ -552,4 +922,119 @@ mod tests {
 
     //     let _res = context.start_rendering_sync();
     // }
+
+    #[test]
+    fn test_standalone_matches_node() {
+        let sample_rate = 44_100.;
+        let length = 4 * RENDER_QUANTUM_SIZE;
+        let signal: Vec<f32> = (0..length).map(|i| (i as f32 * 0.05).sin()).collect();
+
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+        let options = DynamicsCompressorOptions {
+            attack: 0.001,
+            ratio: 4.,
+            ..DynamicsCompressorOptions::default()
+        };
+        let node = DynamicsCompressorNode::new(&context, options.clone());
+        node.connect(&context.destination());
+
+        let mut buffer = context.create_buffer(1, length, sample_rate);
+        buffer.copy_to_channel(&signal, 0);
+
+        let src = context.create_buffer_source();
+        src.connect(&node);
+        src.set_buffer(buffer);
+        src.start();
+
+        let result = context.start_rendering_sync();
+
+        let mut standalone = DynamicsCompressor::new(sample_rate, options);
+        signal
+            .chunks(RENDER_QUANTUM_SIZE)
+            .zip(result.get_channel_data(0).chunks(RENDER_QUANTUM_SIZE))
+            .for_each(|(input, expected)| {
+                let mut block = [0.; RENDER_QUANTUM_SIZE];
+                block.copy_from_slice(input);
+                let output = standalone.process(&[block]);
+                assert_eq!(output.len(), 1);
+                assert_float_eq!(output[0][..], expected[..], abs_all <= 0.);
+            });
+    }
+
+    #[test]
+    fn test_sidechain() {
+        let sample_rate = 44_100.;
+        let length = 8 * RENDER_QUANTUM_SIZE;
+        let signal: Vec<f32> = (0..length).map(|i| 0.1 * (i as f32 * 0.05).sin()).collect();
+
+        let render = |sidechain: Option<f32>| {
+            let context = OfflineAudioContext::new(1, length, sample_rate);
+            let options = DynamicsCompressorOptions {
+                sidechain: true,
+                ..DynamicsCompressorOptions::default()
+            };
+            let node = DynamicsCompressorNode::new(&context, options);
+            assert!(node.sidechain());
+            assert_eq!(node.number_of_inputs(), 2);
+            node.connect(&context.destination());
+
+            let mut buffer = context.create_buffer(1, length, sample_rate);
+            buffer.copy_to_channel(&signal, 0);
+            let src = context.create_buffer_source();
+            src.connect(&node);
+            src.set_buffer(buffer);
+            src.start();
+
+            if let Some(value) = sidechain {
+                let key = context.create_constant_source();
+                key.offset().set_value(value);
+                key.connect_at(&node, 0, 1);
+                key.start();
+            }
+
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
+
+        let ducked = render(Some(1.));
+        let unconnected = render(None);
+
+        // the standalone compressor with the same sidechain matches the node
+        let mut standalone = DynamicsCompressor::new(sample_rate, Default::default());
+        let key = [[1.; RENDER_QUANTUM_SIZE]];
+        signal
+            .chunks(RENDER_QUANTUM_SIZE)
+            .zip(ducked.chunks(RENDER_QUANTUM_SIZE))
+            .for_each(|(input, expected)| {
+                let mut block = [0.; RENDER_QUANTUM_SIZE];
+                block.copy_from_slice(input);
+                let output = standalone.process_with_sidechain(&[block], &key);
+                assert_float_eq!(output[0][..], expected[..], abs_all <= 0.);
+            });
+
+        // the loud sidechain ducks the signal, while the quiet signal alone
+        // is not compressed, i.e. only the makeup gain applies
+        let last = length - RENDER_QUANTUM_SIZE..length;
+        let peak = |s: &[f32]| s.iter().fold(0_f32, |acc, v| acc.max(v.abs()));
+        assert!(peak(&ducked[last.clone()]) < 0.5 * peak(&unconnected[last.clone()]));
+
+        let mut standalone = DynamicsCompressor::new(sample_rate, Default::default());
+        let silence = [[0.; RENDER_QUANTUM_SIZE]];
+        signal
+            .chunks(RENDER_QUANTUM_SIZE)
+            .zip(unconnected.chunks(RENDER_QUANTUM_SIZE))
+            .for_each(|(input, expected)| {
+                let mut block = [0.; RENDER_QUANTUM_SIZE];
+                block.copy_from_slice(input);
+                let output = standalone.process_with_sidechain(&[block], &silence);
+                assert_float_eq!(output[0][..], expected[..], abs_all <= 0.);
+            });
+    }
+
+    #[test]
+    fn test_no_sidechain_by_default() {
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        let node = DynamicsCompressorNode::new(&context, DynamicsCompressorOptions::default());
+        assert!(!node.sidechain());
+        assert_eq!(node.number_of_inputs(), 1);
+    }
 }
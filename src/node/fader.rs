@@ -0,0 +1,324 @@
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{AtomicF64, RENDER_QUANTUM_SIZE};
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Nominal maximum of the gain param in dB, i.e. `20 * log10(f32::MAX)`
+const MAX_GAIN: f32 = 770.636_8;
+
+fn db_to_lin(val: f32) -> f32 {
+    (10.0_f32).powf(val / 20.)
+}
+
+/// Options for constructing a [`FaderNode`]
+#[derive(Clone, Debug)]
+pub struct FaderOptions {
+    /// Gain in dB
+    pub gain: f32,
+    /// Time constant (in seconds) of the gain smoothing
+    pub smoothing_time: f64,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for FaderOptions {
+    fn default() -> Self {
+        Self {
+            gain: 0.,
+            smoothing_time: 0.01,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Assert that the smoothing time is valid
+///
+/// # Panics
+///
+/// This function panics if the given time is negative or not finite
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_smoothing_time(smoothing_time: f64) {
+    if !(smoothing_time.is_finite() && smoothing_time >= 0.) {
+        panic!(
+            "RangeError - Invalid smoothing time: {:?} should be a positive finite number",
+            smoothing_time
+        );
+    }
+}
+
+/// `FaderNode` is a volume control with a gain expressed in dB
+///
+/// The gain changes are smoothed with a one-pole filter on the linear gain, so fast
+/// moves of e.g. a UI fader do not produce zipper noise. This is equivalent to a
+/// [`GainNode`](super::GainNode) driven by `set_target_at_time` for each new value.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{FaderNode, FaderOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let fader = FaderNode::new(&context, FaderOptions::default());
+/// fader.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&fader);
+/// osc.start();
+///
+/// // e.g. in the event handler of the UI fader
+/// fader.gain().set_value(-12.);
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct FaderNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    gain: AudioParam,
+    smoothing_time: Arc<AtomicF64>,
+}
+
+impl AudioNode for FaderNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl FaderNode {
+    /// # Panics
+    ///
+    /// Will panic if `options.smoothing_time` is negative or not finite
+    pub fn new<C: BaseAudioContext>(context: &C, options: FaderOptions) -> Self {
+        context.register(move |registration| {
+            let FaderOptions {
+                gain,
+                smoothing_time,
+                channel_config,
+            } = options;
+
+            assert_valid_smoothing_time(smoothing_time);
+
+            let param_opts = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: MAX_GAIN,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (param, proc) = context.create_audio_param(param_opts, &registration);
+            param.set_value(gain);
+
+            let smoothing_time = Arc::new(AtomicF64::new(smoothing_time));
+
+            let render = FaderRenderer {
+                gain: proc,
+                smoothing_time: Arc::clone(&smoothing_time),
+                current_gain: None,
+            };
+
+            let node = FaderNode {
+                registration,
+                channel_config: channel_config.into(),
+                gain: param,
+                smoothing_time,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// A-rate [`AudioParam`] defining the target gain, in dB
+    pub fn gain(&self) -> &AudioParam {
+        &self.gain
+    }
+
+    /// Time constant (in seconds) of the gain smoothing
+    pub fn smoothing_time(&self) -> f64 {
+        self.smoothing_time.load()
+    }
+
+    /// Set the time constant (in seconds) of the gain smoothing, 0 disables the smoothing
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the value is negative or not finite
+    pub fn set_smoothing_time(&self, value: f64) {
+        assert_valid_smoothing_time(value);
+        self.smoothing_time.store(value);
+    }
+}
+
+struct FaderRenderer {
+    gain: AudioParamId,
+    smoothing_time: Arc<AtomicF64>,
+    /// Smoothed linear gain, not set before the first rendered block
+    current_gain: Option<f32>,
+}
+
+impl AudioProcessor for FaderRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        let gain = params.get(&self.gain);
+
+        if input.is_silent() {
+            // nothing to smooth, jump to the target gain
+            self.current_gain = Some(db_to_lin(gain[gain.len() - 1]));
+            output.make_silent();
+            return false;
+        }
+
+        let smoothing_time = self.smoothing_time.load();
+        let coef = if smoothing_time > 0. {
+            (-1. / (smoothing_time * f64::from(scope.sample_rate))).exp() as f32
+        } else {
+            0.
+        };
+
+        let mut current = self.current_gain.unwrap_or_else(|| db_to_lin(gain[0]));
+        let constant_target = (gain.len() == 1).then(|| db_to_lin(gain[0]));
+
+        let mut gains = [0.; RENDER_QUANTUM_SIZE];
+        gains
+            .iter_mut()
+            .zip(gain.iter().cycle())
+            .for_each(|(g, &target_db)| {
+                let target = constant_target.unwrap_or_else(|| db_to_lin(target_db));
+                current = target + (current - target) * coef;
+                *g = current;
+            });
+        self.current_gain = Some(current);
+
+        *output = input.clone();
+
+        output.channels_mut().iter_mut().for_each(|channel| {
+            channel
+                .iter_mut()
+                .zip(gains.iter())
+                .for_each(|(o, g)| *o *= g);
+        });
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let fader = FaderNode::new(&context, FaderOptions::default());
+
+        assert_float_eq!(fader.gain().value(), 0., abs <= 0.);
+        assert_float_eq!(fader.smoothing_time(), 0.01, abs <= 0.);
+
+        fader.set_smoothing_time(0.1);
+        assert_float_eq!(fader.smoothing_time(), 0.1, abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn test_invalid_smoothing_time() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let fader = FaderNode::new(&context, FaderOptions::default());
+        fader.set_smoothing_time(-1.);
+    }
+
+    #[test]
+    fn test_db_gain() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, SAMPLE_RATE);
+
+        let options = FaderOptions {
+            gain: -6.,
+            ..FaderOptions::default()
+        };
+        let fader = FaderNode::new(&context, options);
+        fader.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&fader);
+        src.start();
+
+        // no smoothing from silence at start
+        let output = context.start_rendering_sync();
+        let expected = [db_to_lin(-6.); RENDER_QUANTUM_SIZE];
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_smoothing() {
+        let length = RENDER_QUANTUM_SIZE * 40;
+        let smoothing_time = 0.01;
+
+        let render = |smoothing_time| {
+            let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+            let options = FaderOptions {
+                smoothing_time,
+                ..FaderOptions::default()
+            };
+            let fader = FaderNode::new(&context, options);
+            fader.connect(&context.destination());
+            // jump from 0dB to -inf
+            fader.gain().set_value_at_time(
+                f32::MIN,
+                RENDER_QUANTUM_SIZE as f64 / f64::from(SAMPLE_RATE),
+            );
+
+            let src = context.create_constant_source();
+            src.connect(&fader);
+            src.start();
+
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
+
+        let result = render(smoothing_time);
+        assert_float_eq!(result[RENDER_QUANTUM_SIZE - 1], 1., abs <= 0.);
+        // exponential decay with the given time constant
+        let time_constant_frames = (smoothing_time * f64::from(SAMPLE_RATE)) as usize;
+        let value = result[RENDER_QUANTUM_SIZE - 1 + time_constant_frames];
+        assert_float_eq!(value, (-1_f32).exp(), abs <= 1e-3);
+        assert!(result.windows(2).all(|w| w[1] <= w[0]));
+        assert!(result[length - 1] < 1e-4);
+
+        // no smoothing
+        let result = render(0.);
+        assert_float_eq!(result[RENDER_QUANTUM_SIZE - 1], 1., abs <= 0.);
+        assert_float_eq!(result[RENDER_QUANTUM_SIZE], 0., abs <= 0.);
+    }
+}
@@ -0,0 +1,463 @@
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::control::{frame_index, Scheduler};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::{
+    AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode,
+    ChannelInterpretation, SINETABLE, TABLE_LENGTH_USIZE,
+};
+
+/// Options for constructing a [`FmOperatorNode`]
+#[derive(Clone, Debug)]
+pub struct FmOperatorOptions {
+    /// Frequency of the carrier, in Hz
+    pub frequency: f32,
+    /// Self-modulation of the carrier, in radians
+    pub feedback: f32,
+    /// Scaling of the modulation input
+    pub modulation_type: FmModulationType,
+    /// channel config options, applying to the modulation input
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for FmOperatorOptions {
+    fn default() -> Self {
+        Self {
+            frequency: 440.,
+            feedback: 0.,
+            modulation_type: FmModulationType::default(),
+            channel_config: ChannelConfigOptions {
+                count: 1,
+                count_mode: ChannelCountMode::Explicit,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Scaling of the modulation input of a `FmOperatorNode`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FmModulationType {
+    /// The modulation input is the frequency deviation in Hz
+    #[default]
+    Hz,
+    /// The modulation input is the frequency deviation relative to the carrier
+    /// frequency, i.e. the modulation index does not depend on the played pitch
+    Ratio,
+}
+
+impl From<u32> for FmModulationType {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => FmModulationType::Hz,
+            1 => FmModulationType::Ratio,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// `FmOperatorNode` represents a sine carrier whose frequency is modulated by
+/// its input, with an optional feedback loop on its own phase.
+///
+/// The instantaneous frequency of the carrier is `frequency + m` for
+/// [`FmModulationType::Hz`] or `frequency * (1 + m)` for [`FmModulationType::Ratio`],
+/// `m` being the first channel of the input signal. The feedback phase-modulates
+/// the carrier with the average of its last two output samples, as in classic
+/// FM synthesizers, which sweeps the waveform from a sine towards a sawtooth.
+///
+/// Operators can be chained, the output of an operator being the modulation
+/// input of the next one.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{FmModulationType, FmOperatorNode, FmOperatorOptions};
+///
+/// let context = AudioContext::default();
+///
+/// // modulator an octave above the carrier, with some feedback
+/// let options = FmOperatorOptions {
+///     frequency: 440.,
+///     feedback: 0.5,
+///     ..FmOperatorOptions::default()
+/// };
+/// let modulator = FmOperatorNode::new(&context, options);
+/// let index = context.create_gain();
+/// index.gain().set_value(2.);
+/// modulator.connect(&index);
+///
+/// // frequency deviation of twice the carrier frequency, whatever its pitch
+/// let options = FmOperatorOptions {
+///     frequency: 220.,
+///     modulation_type: FmModulationType::Ratio,
+///     ..FmOperatorOptions::default()
+/// };
+/// let carrier = FmOperatorNode::new(&context, options);
+/// index.connect(&carrier);
+/// carrier.connect(&context.destination());
+///
+/// modulator.start();
+/// carrier.start();
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct FmOperatorNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    frequency: AudioParam,
+    feedback: AudioParam,
+    modulation_type: Arc<AtomicU32>,
+    scheduler: Scheduler,
+}
+
+impl AudioNode for FmOperatorNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    /// The modulation input
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for FmOperatorNode {
+    fn start(&self) {
+        let when = self.registration.context().current_time();
+        self.start_at(when);
+    }
+
+    fn start_at(&self, when: f64) {
+        self.scheduler.start_at(when);
+    }
+
+    fn stop(&self) {
+        let when = self.registration.context().current_time();
+        self.stop_at(when);
+    }
+
+    fn stop_at(&self, when: f64) {
+        self.scheduler.stop_at(when);
+    }
+}
+
+impl FmOperatorNode {
+    pub fn new<C: BaseAudioContext>(context: &C, options: FmOperatorOptions) -> Self {
+        context.register(move |registration| {
+            let FmOperatorOptions {
+                frequency,
+                feedback,
+                modulation_type,
+                channel_config,
+            } = options;
+
+            let nyquist = context.sample_rate() / 2.;
+            let freq_param_opts = AudioParamDescriptor {
+                min_value: -nyquist,
+                max_value: nyquist,
+                default_value: 440.,
+                automation_rate: AutomationRate::A,
+            };
+            let (f_param, f_proc) = context.create_audio_param(freq_param_opts, &registration);
+            f_param.set_value(frequency);
+
+            let feedback_param_opts = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (fb_param, fb_proc) =
+                context.create_audio_param(feedback_param_opts, &registration);
+            fb_param.set_value(feedback);
+
+            let modulation_type = Arc::new(AtomicU32::new(modulation_type as u32));
+            let scheduler = Scheduler::new();
+
+            let render = FmOperatorRenderer {
+                frequency: f_proc,
+                feedback: fb_proc,
+                modulation_type: Arc::clone(&modulation_type),
+                scheduler: scheduler.clone(),
+                phase: 0.,
+                last_outputs: [0.; 2],
+                ended_triggered: false,
+            };
+
+            let node = FmOperatorNode {
+                registration,
+                channel_config: channel_config.into(),
+                frequency: f_param,
+                feedback: fb_param,
+                modulation_type,
+                scheduler,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// A-rate [`AudioParam`] defining the frequency of the carrier, in Hz
+    pub fn frequency(&self) -> &AudioParam {
+        &self.frequency
+    }
+
+    /// A-rate [`AudioParam`] defining the self-modulation of the carrier, in radians
+    ///
+    /// Values above ~1.5 turn the waveform into noise.
+    pub fn feedback(&self) -> &AudioParam {
+        &self.feedback
+    }
+
+    /// Returns the scaling of the modulation input
+    pub fn modulation_type(&self) -> FmModulationType {
+        self.modulation_type.load(Ordering::SeqCst).into()
+    }
+
+    /// Set the scaling of the modulation input
+    pub fn set_modulation_type(&self, modulation_type: FmModulationType) {
+        self.modulation_type
+            .store(modulation_type as u32, Ordering::SeqCst);
+    }
+}
+
+struct FmOperatorRenderer {
+    frequency: AudioParamId,
+    feedback: AudioParamId,
+    modulation_type: Arc<AtomicU32>,
+    scheduler: Scheduler,
+    /// Phase of the carrier, in cycles
+    phase: f64,
+    /// Last two output samples, for the feedback loop
+    last_outputs: [f32; 2],
+    ended_triggered: bool,
+}
+
+impl FmOperatorRenderer {
+    /// Sine lookup, `phase` being expressed in cycles
+    #[inline]
+    fn sine(phase: f64) -> f32 {
+        let position = (phase - phase.floor()) * TABLE_LENGTH_USIZE as f64;
+        let floored = position.floor();
+
+        let prev_index = floored as usize % TABLE_LENGTH_USIZE;
+        let next_index = (prev_index + 1) % TABLE_LENGTH_USIZE;
+
+        // linear interpolation into lookup table
+        let k = (position - floored) as f32;
+        SINETABLE[prev_index].mul_add(1. - k, SINETABLE[next_index] * k)
+    }
+}
+
+impl AudioProcessor for FmOperatorRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        let dt = 1. / scope.sample_rate as f64;
+        let next_block_time = scope.current_time + dt * RENDER_QUANTUM_SIZE as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if start_time >= next_block_time {
+            output.make_silent();
+            // keep alive until started, unless the control handle is gone
+            return !self.scheduler.is_abandoned();
+        }
+
+        output.force_mono();
+
+        let sample_rate = scope.sample_rate as f64;
+        let start_index = frame_index(start_time, scope.current_time, sample_rate);
+        let stop_index = frame_index(stop_time, scope.current_time, sample_rate);
+
+        let modulation_type = self.modulation_type.load(Ordering::SeqCst).into();
+        let frequency_values = params.get(&self.frequency);
+        let feedback_values = params.get(&self.feedback);
+        let modulation = if input.is_silent() {
+            None
+        } else {
+            Some(input.channel_data(0))
+        };
+
+        output
+            .channel_data_mut(0)
+            .iter_mut()
+            .zip(frequency_values.iter().cycle())
+            .zip(feedback_values.iter().cycle())
+            .enumerate()
+            .for_each(|(index, ((o, &frequency), &feedback))| {
+                if index < start_index || index >= stop_index {
+                    *o = 0.;
+                    return;
+                }
+
+                let m = modulation.map_or(0., |m| m[index]);
+                let computed_frequency = match modulation_type {
+                    FmModulationType::Hz => frequency + m,
+                    FmModulationType::Ratio => frequency * (1. + m),
+                };
+
+                // averaging the last two samples tames the feedback oscillations
+                let [prev, prev_prev] = self.last_outputs;
+                let feedback_phase = f64::from(feedback * 0.5 * (prev + prev_prev)) / (2. * PI);
+
+                let value = Self::sine(self.phase + feedback_phase);
+                self.last_outputs = [value, prev];
+                *o = value;
+
+                self.phase += f64::from(computed_frequency) * dt;
+                self.phase -= self.phase.floor();
+            });
+
+        // tail_time false when output has ended this quantum
+        let still_running = stop_time >= next_block_time;
+
+        if !still_running && !self.ended_triggered {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+        }
+
+        still_running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+    const LENGTH: usize = 1_000;
+
+    fn render_operator(options: FmOperatorOptions, modulation: Option<f32>) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+
+        let operator = FmOperatorNode::new(&context, options);
+        operator.connect(&context.destination());
+        operator.start();
+
+        if let Some(value) = modulation {
+            let src = context.create_constant_source();
+            src.offset().set_value(value);
+            src.connect(&operator);
+            src.start();
+        }
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    fn sine(frequency: f64) -> Vec<f32> {
+        (0..LENGTH)
+            .map(|i| (2. * PI * frequency * i as f64 / f64::from(SAMPLE_RATE)).sin() as f32)
+            .collect()
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, LENGTH, SAMPLE_RATE);
+        let operator = FmOperatorNode::new(&context, FmOperatorOptions::default());
+
+        assert_float_eq!(operator.frequency().value(), 440., abs <= 0.);
+        assert_float_eq!(operator.feedback().value(), 0., abs <= 0.);
+        assert_eq!(operator.modulation_type(), FmModulationType::Hz);
+        assert_eq!(operator.number_of_inputs(), 1);
+        assert_eq!(operator.channel_count(), 1);
+
+        operator.set_modulation_type(FmModulationType::Ratio);
+        assert_eq!(operator.modulation_type(), FmModulationType::Ratio);
+    }
+
+    #[test]
+    fn test_unmodulated_sine() {
+        let result = render_operator(FmOperatorOptions::default(), None);
+        assert_float_eq!(result[..], sine(440.)[..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_modulation_hz() {
+        let result = render_operator(FmOperatorOptions::default(), Some(100.));
+        assert_float_eq!(result[..], sine(540.)[..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_modulation_ratio() {
+        let options = FmOperatorOptions {
+            modulation_type: FmModulationType::Ratio,
+            ..FmOperatorOptions::default()
+        };
+        let result = render_operator(options, Some(1.));
+        assert_float_eq!(result[..], sine(880.)[..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_feedback() {
+        let feedback = 0.8;
+        let options = FmOperatorOptions {
+            feedback,
+            ..FmOperatorOptions::default()
+        };
+        let result = render_operator(options, None);
+
+        let mut expected = Vec::with_capacity(LENGTH);
+        let mut last = [0.; 2];
+        for i in 0..LENGTH {
+            let phase = 2. * PI * 440. * i as f64 / f64::from(SAMPLE_RATE);
+            let value = (phase + f64::from(feedback) * 0.5 * (last[0] + last[1])).sin();
+            last = [value, last[0]];
+            expected.push(value as f32);
+        }
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-4);
+        // the waveform is no longer a sine
+        assert!(result
+            .iter()
+            .zip(sine(440.).iter())
+            .any(|(a, b)| (a - b).abs() > 0.1));
+    }
+
+    #[test]
+    fn test_start_stop() {
+        let context = OfflineAudioContext::new(1, 128 * 3, SAMPLE_RATE);
+
+        let operator = FmOperatorNode::new(&context, FmOperatorOptions::default());
+        operator.connect(&context.destination());
+        operator.start_at(128. / f64::from(SAMPLE_RATE));
+        operator.stop_at(256. / f64::from(SAMPLE_RATE));
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        assert_float_eq!(channel[..128], [0.; 128][..], abs_all <= 0.);
+        assert!(channel[128..256].iter().any(|s| *s != 0.));
+        assert_float_eq!(channel[256..], [0.; 128][..], abs_all <= 0.);
+    }
+}
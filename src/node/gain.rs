@@ -1,6 +1,6 @@
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
 use crate::param::{AudioParam, AudioParamDescriptor};
-use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::render::{simd, AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
 use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
 
@@ -125,18 +125,21 @@ impl AudioProcessor for GainRenderer {
         if gain.len() == 1 {
             let g = gain[0];
 
-            output.channels_mut().iter_mut().for_each(|channel| {
-                channel.iter_mut().for_each(|o| *o *= g);
-            });
+            output
+                .channels_mut()
+                .iter_mut()
+                .for_each(|channel| simd::scale(channel, g));
         } else {
-            output.channels_mut().iter_mut().for_each(|channel| {
-                channel
-                    .iter_mut()
-                    .zip(gain.iter().cycle())
-                    .for_each(|(o, g)| *o *= g);
-            });
+            output
+                .channels_mut()
+                .iter_mut()
+                .for_each(|channel| simd::mul(channel, &gain));
         }
 
         false
     }
+
+    fn skip_when_silent(&self) -> bool {
+        true
+    }
 }
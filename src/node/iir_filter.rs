@@ -6,7 +6,8 @@ use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::MAX_CHANNELS;
 
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+use super::bypass::Bypass;
+use super::{AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions};
 
 /// Filter order is limited to 20
 const MAX_IIR_COEFFS_LEN: usize = 20;
@@ -22,7 +23,7 @@ const MAX_IIR_COEFFS_LEN: usize = 20;
 ///
 #[track_caller]
 #[inline(always)]
-fn assert_valid_feedforward_coefs(coefs: &Vec<f64>) {
+fn assert_valid_feedforward_coefs(coefs: &[f64]) {
     if coefs.is_empty() || coefs.len() > MAX_IIR_COEFFS_LEN {
         panic!("NotSupportedError - IIR Filter feedforward coefficients should have length >= 0 and <= 20");
     }
@@ -43,7 +44,7 @@ fn assert_valid_feedforward_coefs(coefs: &Vec<f64>) {
 ///
 #[track_caller]
 #[inline(always)]
-fn assert_valid_feedback_coefs(coefs: &Vec<f64>) {
+fn assert_valid_feedback_coefs(coefs: &[f64]) {
     if coefs.is_empty() || coefs.len() > MAX_IIR_COEFFS_LEN {
         panic!("NotSupportedError - IIR Filter feedback coefficients should have length >= 0 and <= 20");
     }
@@ -135,6 +136,7 @@ pub struct IIRFilterNode {
     feedforward: Vec<f64>,
     /// denomintor filter's coefficients
     feedback: Vec<f64>,
+    bypass: Bypass,
 }
 
 impl AudioNode for IIRFilterNode {
@@ -155,6 +157,16 @@ impl AudioNode for IIRFilterNode {
     }
 }
 
+impl AudioEffectNode for IIRFilterNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
+}
+
 impl IIRFilterNode {
     /// Creates an `IirFilterNode`
     ///
@@ -185,17 +197,24 @@ impl IIRFilterNode {
 
             let node = Self {
                 registration,
+                bypass: Bypass::default(),
                 channel_config: channel_config.into(),
                 feedforward,
                 feedback,
             };
 
+            let render = node.bypass.wrap(render, 0);
             (node, Box::new(render))
         })
     }
 
     /// Returns the frequency response for the specified frequencies
     ///
+    /// The response is computed from the full feedforward and feedback coefficients,
+    /// i.e. `H(z) = (b[0] + b[1]z^-1 + ... + b[M]z^-M) / (a[0] + a[1]z^-1 + ... + a[N]z^-N)`
+    /// evaluated on the unit circle. Frequencies outside the `[0, sample_rate / 2]` range
+    /// yield `NaN` for both magnitude and phase.
+    ///
     /// # Arguments
     ///
     /// - `frequency_hz` - frequencies for which frequency response of the filter should be calculated
@@ -219,29 +238,42 @@ impl IIRFilterNode {
         let sample_rate = self.context().sample_rate() as f64;
         let nquist = sample_rate / 2.;
 
-        for (i, &f) in frequency_hz.iter().enumerate() {
-            let freq = f64::from(f).clamp(0., nquist);
-            let z = -2.0 * PI * freq / sample_rate;
-            let mut num: Complex<f64> = Complex::new(0., 0.);
-            let mut denom: Complex<f64> = Complex::new(0., 0.);
-
-            for (idx, &b) in self.feedforward.iter().enumerate() {
-                num += Complex::from_polar(b, idx as f64 * z);
-            }
-
-            for (idx, &a) in self.feedback.iter().enumerate() {
-                denom += Complex::from_polar(a, idx as f64 * z);
+        for ((&f, mag), phase) in frequency_hz
+            .iter()
+            .zip(mag_response.iter_mut())
+            .zip(phase_response.iter_mut())
+        {
+            let freq = f64::from(f);
+
+            // [spec] If a value in the frequencyHz parameter is not within
+            // [0, sampleRate/2], the corresponding value in magResponse and
+            // phaseResponse must be NaN.
+            if !(0. ..=nquist).contains(&freq) {
+                *mag = f32::NAN;
+                *phase = f32::NAN;
+                continue;
             }
 
-            let response = num / denom;
+            // z^-1 on the unit circle
+            let z1 = Complex::from_polar(1., -2.0 * PI * freq / sample_rate);
+            let response =
+                eval_polynomial(&self.feedforward, z1) / eval_polynomial(&self.feedback, z1);
 
-            let (mag, phase) = response.to_polar();
-            mag_response[i] = mag as f32;
-            phase_response[i] = phase as f32;
+            let (m, p) = response.to_polar();
+            *mag = m as f32;
+            *phase = p as f32;
         }
     }
 }
 
+/// Evaluate `c[0] + c[1]x + ... + c[n]x^n` using Horner's method
+fn eval_polynomial(coefs: &[f64], x: Complex<f64>) -> Complex<f64> {
+    coefs
+        .iter()
+        .rev()
+        .fold(Complex::new(0., 0.), |acc, &c| acc * x + c)
+}
+
 /// Renderer associated with the `IirFilterNode`
 struct IirFilterRenderer {
     /// Normalized filter's coeffs -- `(b[n], a[n])`
@@ -258,23 +290,9 @@ impl IirFilterRenderer {
     /// * `config` - renderer config
     fn new(mut feedforward: Vec<f64>, mut feedback: Vec<f64>) -> Self {
         // make sure feedback and feedforward have same length, fill with 0. to match
-        match (feedforward.len(), feedback.len()) {
-            (feedforward_len, feedback_len) if feedforward_len > feedback_len => {
-                feedforward = feedforward
-                    .into_iter()
-                    .chain(std::iter::repeat(0.))
-                    .take(feedback_len)
-                    .collect();
-            }
-            (feedforward_len, feedback_len) if feedforward_len < feedback_len => {
-                feedback = feedback
-                    .into_iter()
-                    .chain(std::iter::repeat(0.))
-                    .take(feedforward_len)
-                    .collect();
-            }
-            _ => (),
-        };
+        let coeffs_len = feedforward.len().max(feedback.len());
+        feedforward.resize(coeffs_len, 0.);
+        feedback.resize(coeffs_len, 0.);
 
         let a0 = feedback[0];
         let mut norm_coeffs: Vec<(f64, f64)> = feedforward.into_iter().zip(feedback).collect();
@@ -284,7 +302,6 @@ impl IirFilterRenderer {
             *a /= a0;
         });
 
-        let coeffs_len = norm_coeffs.len();
         let states = vec![Vec::<f64>::with_capacity(MAX_CHANNELS); coeffs_len];
 
         Self {
@@ -455,7 +472,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "InvalidAccessError")]
     fn test_frequency_response_arguments() {
         let context = OfflineAudioContext::new(2, 555, 44_100.);
         let options = IIRFilterOptions {
@@ -473,7 +490,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "InvalidAccessError")]
     fn test_frequency_response_arguments_2() {
         let context = OfflineAudioContext::new(2, 555, 44_100.);
         let options = IIRFilterOptions {
@@ -733,6 +750,100 @@ mod tests {
         assert_float_eq!(mag_response, ref_mag, abs_all <= 0.);
     }
 
+    #[test]
+    fn test_frequency_response_out_of_range() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let iir = context.create_iir_filter(vec![1., 1.], vec![1.]);
+
+        let frequency_hz = [-1., 0., 22_050., 22_051., f32::NAN];
+        let mut mag_response = [0.; 5];
+        let mut phase_response = [0.; 5];
+
+        iir.get_frequency_response(&frequency_hz, &mut mag_response, &mut phase_response);
+
+        assert!(mag_response[0].is_nan());
+        assert!(phase_response[0].is_nan());
+        assert_float_eq!(mag_response[1], 2., abs <= 1e-6);
+        assert_float_eq!(mag_response[2], 0., abs <= 1e-6);
+        assert!(mag_response[3].is_nan());
+        assert!(phase_response[3].is_nan());
+        assert!(mag_response[4].is_nan());
+        assert!(phase_response[4].is_nan());
+    }
+
+    #[test]
+    fn test_frequency_response_arbitrary_order() {
+        let sample_rate = 44_100.;
+        let context = OfflineAudioContext::new(1, 1, sample_rate);
+
+        // 8th order feedforward with 3rd order feedback
+        let feedforward = vec![0.1, -0.2, 0.3, 0.05, -0.1, 0.2, 0.01, -0.02, 0.03];
+        let feedback = vec![1., -0.5, 0.25, -0.125];
+        let iir = context.create_iir_filter(feedforward.clone(), feedback.clone());
+
+        let frequency_hz = [0., 100., 1_000., 5_000., 10_000., 20_000.];
+        let mut mag_response = [0.; 6];
+        let mut phase_response = [0.; 6];
+
+        iir.get_frequency_response(&frequency_hz, &mut mag_response, &mut phase_response);
+
+        for (i, &f) in frequency_hz.iter().enumerate() {
+            let omega = 2. * PI * f64::from(f) / f64::from(sample_rate);
+            let eval = |coefs: &[f64]| {
+                coefs
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &c)| Complex::from_polar(c, -(k as f64) * omega))
+                    .sum::<Complex<f64>>()
+            };
+            let (mag, phase) = (eval(&feedforward) / eval(&feedback)).to_polar();
+
+            assert_float_eq!(mag_response[i], mag as f32, abs <= 1e-6);
+            assert_float_eq!(phase_response[i], phase as f32, abs <= 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_mismatched_coefs_length() {
+        let context = OfflineAudioContext::new(1, LENGTH, 44_100.);
+
+        // FIR moving average, i.e. feedforward longer than feedback
+        let iir = context.create_iir_filter(vec![0.25, 0.25, 0.25, 0.25], vec![1.]);
+        iir.connect(&context.destination());
+
+        let mut buffer = context.create_buffer(1, 8, 44_100.);
+        buffer.copy_to_channel(&[1., 0., 0., 0., 0., 0., 0., 0.], 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&iir);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+        assert_float_eq!(
+            channel[..6],
+            [0.25, 0.25, 0.25, 0.25, 0., 0.][..],
+            abs_all <= 0.
+        );
+
+        let context = OfflineAudioContext::new(1, LENGTH, 44_100.);
+
+        // one-pole filter, i.e. feedback longer than feedforward
+        let iir = context.create_iir_filter(vec![1.], vec![1., -0.5]);
+        iir.connect(&context.destination());
+
+        let mut buffer = context.create_buffer(1, 8, 44_100.);
+        buffer.copy_to_channel(&[1., 0., 0., 0., 0., 0., 0., 0.], 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&iir);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+        assert_float_eq!(channel[..4], [1., 0.5, 0.25, 0.125][..], abs_all <= 0.);
+    }
+
     #[test]
     fn test_frequency_responses_against_biquad() {
         fn compare_frequency_response(
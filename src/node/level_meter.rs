@@ -0,0 +1,296 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{AtomicF32, MAX_CHANNELS};
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Options for constructing a [`LevelMeterNode`]
+#[derive(Clone, Debug)]
+pub struct LevelMeterOptions {
+    /// Time (in seconds) for the peak level to fall back by 60dB
+    pub peak_release_time: f64,
+    /// Integration time (in seconds) of the RMS level
+    pub rms_time: f64,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for LevelMeterOptions {
+    fn default() -> Self {
+        Self {
+            peak_release_time: 1.5,
+            rms_time: 0.3,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+#[track_caller]
+#[inline(always)]
+fn assert_valid_time_constant(name: &str, value: f64) {
+    if value.is_nan() || value <= 0. {
+        panic!(
+            "RangeError - Invalid {}: {:?} should be positive",
+            name, value
+        );
+    }
+}
+
+/// Per sample coefficient of a one-pole smoother reaching -60dB after `time` seconds
+fn release_coef(time: f64, sample_rate: f32) -> f32 {
+    (-6.9 / (time * f64::from(sample_rate))).exp() as f32
+}
+
+/// Levels shared between the control and render thread
+struct LevelMeterState {
+    peak: Vec<AtomicF32>,
+    rms: Vec<AtomicF32>,
+    number_of_channels: AtomicUsize,
+}
+
+/// `LevelMeterNode` publishes the peak and RMS level of each channel of its input.
+///
+/// It is an AudioNode that passes the audio stream unchanged from the input to
+/// the output. Contrary to the [`AnalyserNode`](super::AnalyserNode) no FFT or
+/// buffer copy is involved, the levels are published via atomics at every render
+/// quantum, which makes this node suitable for drawing VU meters.
+///
+/// The peak level has an instantaneous attack and a release defined by the
+/// `peak_release_time` option. The RMS level is averaged by a one-pole smoother
+/// defined by the `rms_time` option. Levels are linear values.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{LevelMeterNode, LevelMeterOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let meter = LevelMeterNode::new(&context, LevelMeterOptions::default());
+/// meter.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&meter);
+/// osc.start();
+///
+/// loop {
+///     println!("peak: {:?}, rms: {:?}", meter.peak(), meter.rms());
+///     std::thread::sleep(std::time::Duration::from_millis(50));
+/// }
+/// ```
+pub struct LevelMeterNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    state: Arc<LevelMeterState>,
+}
+
+impl AudioNode for LevelMeterNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl LevelMeterNode {
+    /// # Panics
+    ///
+    /// This function panics if `peak_release_time` or `rms_time` is not strictly positive
+    pub fn new<C: BaseAudioContext>(context: &C, options: LevelMeterOptions) -> Self {
+        assert_valid_time_constant("peak release time", options.peak_release_time);
+        assert_valid_time_constant("rms time", options.rms_time);
+
+        context.register(move |registration| {
+            let mut peak = Vec::with_capacity(MAX_CHANNELS);
+            peak.resize_with(MAX_CHANNELS, || AtomicF32::new(0.));
+            let mut rms = Vec::with_capacity(MAX_CHANNELS);
+            rms.resize_with(MAX_CHANNELS, || AtomicF32::new(0.));
+
+            let state = Arc::new(LevelMeterState {
+                peak,
+                rms,
+                number_of_channels: AtomicUsize::new(0),
+            });
+
+            let sample_rate = context.sample_rate();
+            let render = LevelMeterRenderer {
+                state: Arc::clone(&state),
+                peak_release: release_coef(options.peak_release_time, sample_rate),
+                rms_smoothing: release_coef(options.rms_time, sample_rate),
+                peak: [0.; MAX_CHANNELS],
+                mean_square: [0.; MAX_CHANNELS],
+                number_of_channels: 0,
+            };
+
+            let node = LevelMeterNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                state,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Current peak level of each channel
+    pub fn peak(&self) -> Vec<f32> {
+        let number_of_channels = self.state.number_of_channels.load(Ordering::SeqCst);
+        self.state.peak[..number_of_channels]
+            .iter()
+            .map(|v| v.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Current RMS level of each channel
+    pub fn rms(&self) -> Vec<f32> {
+        let number_of_channels = self.state.number_of_channels.load(Ordering::SeqCst);
+        self.state.rms[..number_of_channels]
+            .iter()
+            .map(|v| v.load(Ordering::Relaxed))
+            .collect()
+    }
+}
+
+struct LevelMeterRenderer {
+    state: Arc<LevelMeterState>,
+    peak_release: f32,
+    rms_smoothing: f32,
+    peak: [f32; MAX_CHANNELS],
+    mean_square: [f32; MAX_CHANNELS],
+    number_of_channels: usize,
+}
+
+impl AudioProcessor for LevelMeterRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // pass through input
+        *output = input.clone();
+
+        let number_of_channels = input.number_of_channels();
+        if number_of_channels != self.number_of_channels {
+            // levels of channels that were dropped should not reappear later on
+            for channel_number in number_of_channels..self.number_of_channels {
+                self.peak[channel_number] = 0.;
+                self.mean_square[channel_number] = 0.;
+            }
+            self.number_of_channels = number_of_channels;
+            self.state
+                .number_of_channels
+                .store(number_of_channels, Ordering::SeqCst);
+        }
+
+        let peak_release = self.peak_release;
+        let rms_smoothing = self.rms_smoothing;
+
+        for (channel_number, channel) in input.channels().iter().enumerate() {
+            let mut peak = self.peak[channel_number];
+            let mut mean_square = self.mean_square[channel_number];
+
+            channel.iter().for_each(|&s| {
+                peak = s.abs().max(peak * peak_release);
+                mean_square = s * s + rms_smoothing * (mean_square - s * s);
+            });
+
+            self.peak[channel_number] = peak;
+            self.mean_square[channel_number] = mean_square;
+
+            self.state.peak[channel_number].store(peak, Ordering::Relaxed);
+            self.state.rms[channel_number].store(mean_square.sqrt(), Ordering::Relaxed);
+        }
+
+        // no tail-time
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::*;
+
+    #[test]
+    fn test_constant_levels() {
+        let context = OfflineAudioContext::new(2, 48000, 48000.);
+
+        let options = LevelMeterOptions {
+            rms_time: 0.05,
+            ..LevelMeterOptions::default()
+        };
+        let meter = LevelMeterNode::new(&context, options);
+        meter.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.offset().set_value(0.5);
+        src.connect(&meter);
+        src.start();
+
+        let _ = context.start_rendering_sync();
+
+        assert_float_eq!(meter.peak(), vec![0.5; 2], abs_all <= 1e-6);
+        assert_float_eq!(meter.rms(), vec![0.5; 2], abs_all <= 1e-4);
+    }
+
+    #[test]
+    fn test_peak_release() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 48000, sample_rate);
+
+        let options = LevelMeterOptions {
+            peak_release_time: 0.5,
+            ..LevelMeterOptions::default()
+        };
+        let meter = LevelMeterNode::new(&context, options);
+        meter.connect(&context.destination());
+
+        // short burst at the start of the rendering
+        let src = context.create_constant_source();
+        src.connect(&meter);
+        src.start();
+        src.stop_at(128. / f64::from(sample_rate));
+
+        let _ = context.start_rendering_sync();
+
+        // after 1 second, the peak level has dropped by more than 60dB
+        let peak = meter.peak();
+        assert!(peak[0] > 0.);
+        assert!(peak[0] < 1e-3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_rms_time() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let options = LevelMeterOptions {
+            rms_time: 0.,
+            ..LevelMeterOptions::default()
+        };
+        let _ = LevelMeterNode::new(&context, options);
+    }
+}
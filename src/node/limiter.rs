@@ -0,0 +1,537 @@
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{AtomicF32, MAX_CHANNELS, RENDER_QUANTUM_SIZE};
+
+use super::bypass::Bypass;
+use super::{AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Maximum look-ahead time, in seconds
+const MAX_LOOK_AHEAD: f64 = 1.;
+
+/// Options for constructing a [`LimiterNode`]
+#[derive(Clone, Debug)]
+pub struct LimiterOptions {
+    /// Maximum level of the output, in dB
+    pub ceiling: f32,
+    /// Time (in seconds) for the gain reduction to recover by 60dB
+    pub release: f32,
+    /// Look-ahead time (in seconds), i.e. the latency of the node
+    pub look_ahead: f64,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for LimiterOptions {
+    fn default() -> Self {
+        Self {
+            ceiling: -1.,      // dB
+            release: 0.1,      // seconds
+            look_ahead: 0.005, // seconds
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Assert that the look-ahead time is valid
+///
+/// # Panics
+///
+/// This function panics if the given time is not in the [0, 1] range
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_look_ahead(look_ahead: f64) {
+    if !(0. ..=MAX_LOOK_AHEAD).contains(&look_ahead) {
+        panic!(
+            "RangeError - Invalid look-ahead: {:?} is outside range [0, {:?}]",
+            look_ahead, MAX_LOOK_AHEAD
+        );
+    }
+}
+
+/// Number of frames of the look-ahead delay line
+fn look_ahead_frames(look_ahead: f64, sample_rate: f32) -> usize {
+    (look_ahead * f64::from(sample_rate)).round() as usize
+}
+
+/// `LimiterNode` is a brickwall limiter, the output never exceeds the ceiling.
+///
+/// The signal is delayed by the look-ahead time, so the gain reduction can be
+/// smoothly applied before each peak rather than distorting it. The delay is
+/// reported by [`AudioNode::latency`]. After a peak, the gain recovers according
+/// to the release time.
+///
+/// The gain reduction is linked across channels, so the stereo image is preserved.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{LimiterNode, LimiterOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let options = LimiterOptions {
+///     ceiling: -0.3,
+///     ..LimiterOptions::default()
+/// };
+/// let limiter = LimiterNode::new(&context, options);
+/// limiter.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// let gain = context.create_gain();
+/// gain.gain().set_value(4.);
+/// osc.connect(&gain);
+/// gain.connect(&limiter);
+/// osc.start();
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct LimiterNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    ceiling: AudioParam,
+    release: AudioParam,
+    look_ahead: f64,
+    reduction: Arc<AtomicF32>,
+    bypass: Bypass,
+}
+
+impl AudioNode for LimiterNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn latency(&self) -> f64 {
+        let sample_rate = self.context().sample_rate();
+        look_ahead_frames(self.look_ahead, sample_rate) as f64 / f64::from(sample_rate)
+    }
+}
+
+impl AudioEffectNode for LimiterNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
+}
+
+impl LimiterNode {
+    /// # Panics
+    ///
+    /// Will panic if `options.look_ahead` is outside the [0, 1] range
+    pub fn new<C: BaseAudioContext>(context: &C, options: LimiterOptions) -> Self {
+        context.register(move |registration| {
+            let LimiterOptions {
+                ceiling,
+                release,
+                look_ahead,
+                channel_config,
+            } = options;
+
+            assert_valid_look_ahead(look_ahead);
+
+            let ceiling_param_opts = AudioParamDescriptor {
+                min_value: -100.,
+                max_value: 0.,
+                default_value: -1.,
+                automation_rate: AutomationRate::K,
+            };
+            let (ceiling_param, ceiling_proc) =
+                context.create_audio_param(ceiling_param_opts, &registration);
+            ceiling_param.set_value(ceiling);
+
+            let release_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 10.,
+                default_value: 0.1,
+                automation_rate: AutomationRate::K,
+            };
+            let (release_param, release_proc) =
+                context.create_audio_param(release_param_opts, &registration);
+            release_param.set_value(release);
+
+            let reduction = Arc::new(AtomicF32::new(0.));
+            let look_ahead_frames = look_ahead_frames(look_ahead, context.sample_rate());
+
+            let render = LimiterRenderer {
+                ceiling: ceiling_proc,
+                release: release_proc,
+                reduction: Arc::clone(&reduction),
+                look_ahead: look_ahead_frames,
+                delay_line: Vec::with_capacity(MAX_CHANNELS),
+                delay_index: 0,
+                frame: 0,
+                min_window: VecDeque::with_capacity(look_ahead_frames + 1),
+                release_gain: 1.,
+                smoothing_window: vec![1.; look_ahead_frames + 1],
+                smoothing_index: 0,
+                smoothing_sum: (look_ahead_frames + 1) as f64,
+                number_of_channels: 1,
+                tail_frames: 0,
+            };
+
+            let node = LimiterNode {
+                registration,
+                bypass: Bypass::default(),
+                channel_config: channel_config.into(),
+                ceiling: ceiling_param,
+                release: release_param,
+                look_ahead,
+                reduction,
+            };
+
+            let render = node.bypass.wrap(render, look_ahead_frames);
+            (node, Box::new(render))
+        })
+    }
+
+    /// K-rate [`AudioParam`] defining the maximum level of the output, in dB
+    pub fn ceiling(&self) -> &AudioParam {
+        &self.ceiling
+    }
+
+    /// K-rate [`AudioParam`] defining the time (in seconds) for the gain
+    /// reduction to recover by 60dB
+    pub fn release(&self) -> &AudioParam {
+        &self.release
+    }
+
+    /// Look-ahead time, in seconds
+    pub fn look_ahead(&self) -> f64 {
+        self.look_ahead
+    }
+
+    /// Gain reduction in dB applied to the last sample of the last rendered block
+    pub fn reduction(&self) -> f32 {
+        self.reduction.load(Ordering::SeqCst)
+    }
+}
+
+struct LimiterRenderer {
+    ceiling: AudioParamId,
+    release: AudioParamId,
+    reduction: Arc<AtomicF32>,
+    /// Look-ahead, in frames
+    look_ahead: usize,
+    /// Delayed input, one ring buffer of `look_ahead` frames per channel
+    delay_line: Vec<Vec<f32>>,
+    delay_index: usize,
+    /// Number of processed frames
+    frame: usize,
+    /// Candidates for the minimum of the required gains over the look-ahead
+    /// window, as `(frame, gain)` with increasing gains
+    min_window: VecDeque<(usize, f32)>,
+    /// Required gain after the release stage
+    release_gain: f32,
+    /// Moving average of the released gains, which smoothes the attack
+    smoothing_window: Vec<f32>,
+    smoothing_index: usize,
+    smoothing_sum: f64,
+    number_of_channels: usize,
+    /// Remaining frames in the delay line after the input went silent
+    tail_frames: usize,
+}
+
+impl AudioProcessor for LimiterRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        if input.is_silent() {
+            // the delay line has been flushed, nothing left to process
+            if self.tail_frames == 0 {
+                output.make_silent();
+                return false;
+            }
+            self.tail_frames = self.tail_frames.saturating_sub(RENDER_QUANTUM_SIZE);
+        } else {
+            // keep the number of channels of the delayed signal while in tail time
+            self.number_of_channels = input.number_of_channels();
+            self.tail_frames = self.look_ahead;
+        }
+
+        let number_of_channels = self.number_of_channels;
+        if self.delay_line.len() < number_of_channels {
+            self.delay_line
+                .resize(number_of_channels, vec![0.; self.look_ahead]);
+        }
+
+        let ceiling = 10_f32.powf(params.get(&self.ceiling)[0] / 20.);
+        let release = params.get(&self.release)[0];
+        // one-pole smoother reaching -60dB after the release time
+        let release_coef = if release > 0. {
+            (-6.9 / (release * scope.sample_rate)).exp()
+        } else {
+            0.
+        };
+
+        let input_channels = input.channels();
+        let input_channel_count = if input.is_silent() {
+            0
+        } else {
+            input_channels.len()
+        };
+
+        output.set_number_of_channels(number_of_channels);
+        let output_channels = output.channels_mut();
+        let window_size = self.look_ahead + 1;
+        let mut gain = 1.;
+
+        for i in 0..RENDER_QUANTUM_SIZE {
+            // gain required for the loudest channel of the incoming frame
+            let peak = input_channels[..input_channel_count]
+                .iter()
+                .fold(0_f32, |acc, channel| acc.max(channel[i].abs()));
+            let required_gain = if peak > ceiling { ceiling / peak } else { 1. };
+
+            // minimum of the required gains over the look-ahead window, which
+            // includes the frame leaving the delay line
+            while self
+                .min_window
+                .back()
+                .is_some_and(|&(_, g)| g >= required_gain)
+            {
+                self.min_window.pop_back();
+            }
+            self.min_window.push_back((self.frame, required_gain));
+            while self
+                .min_window
+                .front()
+                .is_some_and(|&(frame, _)| frame + self.look_ahead < self.frame)
+            {
+                self.min_window.pop_front();
+            }
+            let min_gain = self.min_window.front().unwrap().1;
+
+            // instantaneous attack, smoothed recovery
+            self.release_gain = if min_gain < self.release_gain {
+                min_gain
+            } else {
+                min_gain + (self.release_gain - min_gain) * release_coef
+            };
+
+            // averaging over the look-ahead window turns the instantaneous attack
+            // into a ramp that ends on the peak leaving the delay line
+            let prev = std::mem::replace(
+                &mut self.smoothing_window[self.smoothing_index],
+                self.release_gain,
+            );
+            self.smoothing_sum += f64::from(self.release_gain) - f64::from(prev);
+            self.smoothing_index += 1;
+            if self.smoothing_index == window_size {
+                self.smoothing_index = 0;
+                // prevent the accumulation of rounding errors
+                self.smoothing_sum = self.smoothing_window.iter().map(|&g| f64::from(g)).sum();
+            }
+            // the minimum guards against rounding errors
+            gain = ((self.smoothing_sum / window_size as f64) as f32).min(min_gain);
+
+            for (channel_number, output_channel) in output_channels.iter_mut().enumerate() {
+                let sample = if channel_number < input_channel_count {
+                    input_channels[channel_number][i]
+                } else {
+                    0.
+                };
+
+                let delayed = if self.look_ahead == 0 {
+                    sample
+                } else {
+                    let delay_line = &mut self.delay_line[channel_number];
+                    std::mem::replace(&mut delay_line[self.delay_index], sample)
+                };
+
+                output_channel[i] = delayed * gain;
+            }
+
+            if self.look_ahead > 0 {
+                self.delay_index = (self.delay_index + 1) % self.look_ahead;
+            }
+            self.frame += 1;
+        }
+
+        let reduction = if gain > 0. {
+            20. * gain.log10()
+        } else {
+            -1000.
+        };
+        self.reduction.store(reduction, Ordering::SeqCst);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+
+    fn render_limiter(options: LimiterOptions, signal: &[f32], length: usize) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let limiter = LimiterNode::new(&context, options);
+        limiter.connect(&context.destination());
+
+        let mut buffer = context.create_buffer(1, signal.len(), SAMPLE_RATE);
+        buffer.copy_to_channel(signal, 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&limiter);
+        src.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let limiter = LimiterNode::new(&context, LimiterOptions::default());
+
+        assert_float_eq!(limiter.ceiling().value(), -1., abs <= 0.);
+        assert_float_eq!(limiter.release().value(), 0.1, abs <= 0.);
+        assert_float_eq!(limiter.look_ahead(), 0.005, abs <= 0.);
+        assert_float_eq!(limiter.latency(), 0.005, abs <= 1e-9);
+        assert_float_eq!(limiter.reduction(), 0., abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn test_invalid_look_ahead() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let options = LimiterOptions {
+            look_ahead: -0.1,
+            ..LimiterOptions::default()
+        };
+        let _ = LimiterNode::new(&context, options);
+    }
+
+    #[test]
+    fn test_default_latency() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let gain = context.create_gain();
+        assert_float_eq!(gain.latency(), 0., abs <= 0.);
+    }
+
+    #[test]
+    fn test_quiet_signal_is_delayed() {
+        let length = 4 * RENDER_QUANTUM_SIZE;
+        let signal: Vec<f32> = (0..length).map(|i| 0.5 * (i as f32 * 0.05).sin()).collect();
+
+        let options = LimiterOptions {
+            look_ahead: 100. / f64::from(SAMPLE_RATE),
+            ..LimiterOptions::default()
+        };
+        let result = render_limiter(options, &signal, length);
+
+        assert_float_eq!(result[..100], [0.; 100][..], abs_all <= 0.);
+        assert_float_eq!(result[100..], signal[..length - 100], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_brickwall() {
+        let length = 16 * RENDER_QUANTUM_SIZE;
+        // loud bursts with silence in between
+        let signal: Vec<f32> = (0..length)
+            .map(|i| {
+                let amplitude = if (i / 300) % 2 == 0 { 4. } else { 0.2 };
+                amplitude * (i as f32 * 0.03).sin()
+            })
+            .collect();
+
+        for look_ahead in [0., 0.001, 0.005] {
+            let options = LimiterOptions {
+                ceiling: -6.,
+                look_ahead,
+                ..LimiterOptions::default()
+            };
+            let result = render_limiter(options, &signal, length);
+
+            let ceiling = 10_f32.powf(-6. / 20.);
+            assert!(result.iter().all(|s| s.abs() <= ceiling + 1e-6));
+            assert!(result.iter().any(|s| s.abs() > 0.9 * ceiling));
+        }
+    }
+
+    #[test]
+    fn test_release() {
+        let length = 32 * RENDER_QUANTUM_SIZE;
+        // a single loud sample followed by a quiet signal
+        let mut signal = vec![0.1; length];
+        signal[0] = 10.;
+
+        let options = LimiterOptions {
+            ceiling: 0.,
+            release: 0.01,
+            look_ahead: 0.,
+            ..LimiterOptions::default()
+        };
+        let result = render_limiter(options, &signal, length);
+
+        assert_float_eq!(result[0], 1., abs <= 1e-6);
+        // gain is still reduced right after the peak, and recovers by 60dB
+        // after the release time
+        assert!(result[1] < 0.1 * 0.2);
+        let release_frames = (0.01 * SAMPLE_RATE) as usize;
+        assert_float_eq!(result[release_frames + 1], 0.1, rmax <= 1e-2);
+        assert_float_eq!(result[length - 1], 0.1, rmax <= 1e-4);
+    }
+
+    #[test]
+    fn test_tail_time() {
+        let context = OfflineAudioContext::new(1, 4 * RENDER_QUANTUM_SIZE, SAMPLE_RATE);
+
+        let options = LimiterOptions {
+            look_ahead: 200. / f64::from(SAMPLE_RATE),
+            ..LimiterOptions::default()
+        };
+        let limiter = LimiterNode::new(&context, options);
+        limiter.connect(&context.destination());
+
+        // a single impulse in the first render quantum
+        let mut buffer = context.create_buffer(1, 1, SAMPLE_RATE);
+        buffer.copy_to_channel(&[0.5], 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&limiter);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        assert_float_eq!(channel[200], 0.5, abs <= 0.);
+        let sum: f32 = channel.iter().sum();
+        assert_float_eq!(sum, 0.5, abs <= 0.);
+    }
+}
@@ -0,0 +1,517 @@
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{AtomicF32, AtomicF64, MAX_CHANNELS};
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+// Loudness measurement as specified in ITU-R BS.1770-4 and EBU R128
+// - momentary loudness: 400ms sliding window
+// - short-term loudness: 3s sliding window
+// - integrated loudness: gated mean over the whole measurement
+//
+// Windows are updated with a 100ms step, i.e. the 75% overlap of gating blocks
+// required by BS.1770.
+const STEP_DURATION: f64 = 0.1;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+// gating thresholds of the integrated loudness
+const ABSOLUTE_GATE: f64 = -70.;
+const RELATIVE_GATE: f64 = -10.;
+
+// gating blocks are accumulated in a histogram (so we never allocate on the render
+// thread), from the absolute gate up to +10 LUFS with a resolution of 0.1 LU
+const HISTOGRAM_RESOLUTION: f64 = 0.1;
+const HISTOGRAM_BINS: usize = 800;
+
+// 4x oversampling for true-peak detection, 12 taps per phase
+const TRUE_PEAK_OVERSAMPLING: usize = 4;
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// Convert a (channel weighted) mean square value to LUFS
+fn energy_to_loudness(energy: f64) -> f64 {
+    -0.691 + 10. * energy.log10()
+}
+
+/// Channel weights, cf. BS.1770-4 table 3
+///
+/// Only the 5.1 layout (L, R, C, LFE, SL, SR) gets special treatment: the LFE channel is
+/// discarded and the surround channels are boosted by +1.5dB.
+fn channel_weight(channel_number: usize, number_of_channels: usize) -> f64 {
+    if number_of_channels == 6 {
+        match channel_number {
+            3 => 0.,
+            4 | 5 => 1.41,
+            _ => 1.,
+        }
+    } else {
+        1.
+    }
+}
+
+/// Biquad filter coefficients normalized against a0
+#[derive(Clone, Copy, Debug)]
+struct Coefficients {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+// allow non snake to better the variable names in BS.1770
+#[allow(non_snake_case)]
+/// Pre-filter (high shelf) and RLB-filter (high pass) of the K-weighting curve
+///
+/// The coefficients given in BS.1770 are only valid for 48kHz, so we derive them for the
+/// actual sample rate from the analog prototypes.
+fn k_weighting_coefs(sample_rate: f64) -> [Coefficients; 2] {
+    // stage 1 - high shelf
+    let f0 = 1681.974450955533;
+    let G = 3.999843853973347;
+    let Q = 0.7071752369554196;
+
+    let K = (PI * f0 / sample_rate).tan();
+    let Vh = 10_f64.powf(G / 20.);
+    let Vb = Vh.powf(0.4996667741545416);
+    let a0 = 1. + K / Q + K * K;
+
+    let shelf = Coefficients {
+        b0: (Vh + Vb * K / Q + K * K) / a0,
+        b1: 2. * (K * K - Vh) / a0,
+        b2: (Vh - Vb * K / Q + K * K) / a0,
+        a1: 2. * (K * K - 1.) / a0,
+        a2: (1. - K / Q + K * K) / a0,
+    };
+
+    // stage 2 - high pass
+    let f0 = 38.13547087602444;
+    let Q = 0.5003270373238773;
+
+    let K = (PI * f0 / sample_rate).tan();
+    let a0 = 1. + K / Q + K * K;
+
+    let high_pass = Coefficients {
+        b0: 1.,
+        b1: -2.,
+        b2: 1.,
+        a1: 2. * (K * K - 1.) / a0,
+        a2: (1. - K / Q + K * K) / a0,
+    };
+
+    [shelf, high_pass]
+}
+
+/// Polyphase interpolation filter for the true-peak oversampling, Hann windowed sinc
+fn true_peak_coefs() -> [[f32; TRUE_PEAK_TAPS]; TRUE_PEAK_OVERSAMPLING] {
+    let len = TRUE_PEAK_TAPS * TRUE_PEAK_OVERSAMPLING;
+    let center = (len - 1) as f64 / 2.;
+    let mut coefs = [[0.; TRUE_PEAK_TAPS]; TRUE_PEAK_OVERSAMPLING];
+
+    for n in 0..len {
+        let x = (n as f64 - center) / TRUE_PEAK_OVERSAMPLING as f64;
+        let sinc = if x == 0. {
+            1.
+        } else {
+            (PI * x).sin() / (PI * x)
+        };
+        let window = 0.5 - 0.5 * (2. * PI * (n as f64 + 0.5) / len as f64).cos();
+        coefs[n % TRUE_PEAK_OVERSAMPLING][n / TRUE_PEAK_OVERSAMPLING] = (sinc * window) as f32;
+    }
+
+    coefs
+}
+
+/// Options for constructing a [`LoudnessMeterNode`]
+#[derive(Clone, Debug, Default)]
+pub struct LoudnessMeterOptions {
+    pub channel_config: ChannelConfigOptions,
+}
+
+/// Measurements shared between the control and render thread
+struct LoudnessMeterState {
+    momentary: AtomicF64,
+    short_term: AtomicF64,
+    integrated: AtomicF64,
+    true_peak: Vec<AtomicF32>,
+    number_of_channels: AtomicUsize,
+    reset: AtomicBool,
+}
+
+/// `LoudnessMeterNode` measures the loudness of its input according to EBU R128
+/// (ITU-R BS.1770-4).
+///
+/// It is an AudioNode that passes the audio stream unchanged from the input to
+/// the output, while computing the momentary, short-term and integrated loudness
+/// (in LUFS) and the true-peak level per channel (in dBTP). The measurements can be
+/// queried at any time from the control thread.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{LoudnessMeterNode, LoudnessMeterOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let meter = LoudnessMeterNode::new(&context, LoudnessMeterOptions::default());
+/// meter.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&meter);
+/// osc.start();
+///
+/// loop {
+///     println!(
+///         "M: {:.1} LUFS, S: {:.1} LUFS, I: {:.1} LUFS, TP: {:?} dBTP",
+///         meter.momentary_loudness(),
+///         meter.short_term_loudness(),
+///         meter.integrated_loudness(),
+///         meter.true_peak(),
+///     );
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+/// }
+/// ```
+pub struct LoudnessMeterNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    state: Arc<LoudnessMeterState>,
+}
+
+impl AudioNode for LoudnessMeterNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl LoudnessMeterNode {
+    pub fn new<C: BaseAudioContext>(context: &C, options: LoudnessMeterOptions) -> Self {
+        context.register(move |registration| {
+            let mut true_peak = Vec::with_capacity(MAX_CHANNELS);
+            true_peak.resize_with(MAX_CHANNELS, || AtomicF32::new(0.));
+
+            let state = Arc::new(LoudnessMeterState {
+                momentary: AtomicF64::new(f64::NEG_INFINITY),
+                short_term: AtomicF64::new(f64::NEG_INFINITY),
+                integrated: AtomicF64::new(f64::NEG_INFINITY),
+                true_peak,
+                number_of_channels: AtomicUsize::new(0),
+                reset: AtomicBool::new(false),
+            });
+
+            let sample_rate = f64::from(context.sample_rate());
+            let step_length = (sample_rate * STEP_DURATION).round() as usize;
+
+            let render = LoudnessMeterRenderer {
+                state: Arc::clone(&state),
+                k_weighting: k_weighting_coefs(sample_rate),
+                filter_state: Vec::with_capacity(MAX_CHANNELS),
+                true_peak_coefs: true_peak_coefs(),
+                true_peak_history: Vec::with_capacity(MAX_CHANNELS),
+                true_peak: [0.; MAX_CHANNELS],
+                step_length,
+                step_energy: 0.,
+                step_count: 0,
+                steps: [0.; SHORT_TERM_STEPS],
+                steps_index: 0,
+                steps_filled: 0,
+                histogram_energy: Box::new([0.; HISTOGRAM_BINS]),
+                histogram_count: Box::new([0; HISTOGRAM_BINS]),
+            };
+
+            let node = LoudnessMeterNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                state,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Loudness over the last 400ms, in LUFS
+    pub fn momentary_loudness(&self) -> f64 {
+        self.state.momentary.load()
+    }
+
+    /// Loudness over the last 3s, in LUFS
+    pub fn short_term_loudness(&self) -> f64 {
+        self.state.short_term.load()
+    }
+
+    /// Gated loudness since the start of the measurement (or the last call to
+    /// [`reset`](Self::reset)), in LUFS
+    pub fn integrated_loudness(&self) -> f64 {
+        self.state.integrated.load()
+    }
+
+    /// Maximum true-peak level per channel since the start of the measurement (or the last
+    /// call to [`reset`](Self::reset)), in dBTP
+    pub fn true_peak(&self) -> Vec<f64> {
+        let number_of_channels = self.state.number_of_channels.load(Ordering::SeqCst);
+        self.state.true_peak[..number_of_channels]
+            .iter()
+            .map(|p| 20. * f64::from(p.load(Ordering::SeqCst)).log10())
+            .collect()
+    }
+
+    /// Restart the integrated loudness and true-peak measurements
+    ///
+    /// The reset is applied by the render thread at the next render quantum.
+    pub fn reset(&self) {
+        self.state.reset.store(true, Ordering::SeqCst);
+    }
+}
+
+struct LoudnessMeterRenderer {
+    state: Arc<LoudnessMeterState>,
+    k_weighting: [Coefficients; 2],
+    // biquad state per channel: [x1, x2, y1, y2] for both stages
+    filter_state: Vec<[[f64; 4]; 2]>,
+    true_peak_coefs: [[f32; TRUE_PEAK_TAPS]; TRUE_PEAK_OVERSAMPLING],
+    true_peak_history: Vec<[f32; TRUE_PEAK_TAPS]>,
+    true_peak: [f32; MAX_CHANNELS],
+    // number of sample frames in a 100ms step
+    step_length: usize,
+    // channel weighted sum of squares of the current step
+    step_energy: f64,
+    // number of frames accumulated in the current step
+    step_count: usize,
+    // sum of squares of the last steps (ring buffer)
+    steps: [f64; SHORT_TERM_STEPS],
+    steps_index: usize,
+    steps_filled: usize,
+    // accumulated energy and number of gating blocks per loudness bin
+    histogram_energy: Box<[f64; HISTOGRAM_BINS]>,
+    histogram_count: Box<[u64; HISTOGRAM_BINS]>,
+}
+
+impl LoudnessMeterRenderer {
+    fn reset(&mut self) {
+        self.true_peak = [0.; MAX_CHANNELS];
+        self.histogram_energy.fill(0.);
+        self.histogram_count.fill(0);
+
+        self.state
+            .true_peak
+            .iter()
+            .for_each(|p| p.store(0., Ordering::SeqCst));
+        self.state.integrated.store(f64::NEG_INFINITY);
+    }
+
+    /// Mean square of the last `n` steps
+    fn window_energy(&self, n: usize) -> f64 {
+        let n = n.min(self.steps_filled);
+        let sum: f64 = (0..n)
+            .map(|i| self.steps[(self.steps_index + SHORT_TERM_STEPS - 1 - i) % SHORT_TERM_STEPS])
+            .sum();
+
+        sum / (n * self.step_length) as f64
+    }
+
+    fn integrated_loudness(&self) -> f64 {
+        let mean_above = |start: usize| {
+            let energy: f64 = self.histogram_energy[start..].iter().sum();
+            let count: u64 = self.histogram_count[start..].iter().sum();
+            (count > 0).then(|| energy / count as f64)
+        };
+
+        // absolute gating is applied when filling the histogram
+        let absolute = match mean_above(0) {
+            Some(energy) => energy,
+            None => return f64::NEG_INFINITY,
+        };
+
+        let relative_gate = energy_to_loudness(absolute) + RELATIVE_GATE;
+        let start = ((relative_gate - ABSOLUTE_GATE) / HISTOGRAM_RESOLUTION).ceil();
+        let start = (start.max(0.) as usize).min(HISTOGRAM_BINS - 1);
+
+        mean_above(start).map_or(f64::NEG_INFINITY, energy_to_loudness)
+    }
+
+    fn complete_step(&mut self) {
+        self.steps[self.steps_index] = self.step_energy;
+        self.steps_index = (self.steps_index + 1) % SHORT_TERM_STEPS;
+        self.steps_filled = (self.steps_filled + 1).min(SHORT_TERM_STEPS);
+        self.step_energy = 0.;
+        self.step_count = 0;
+
+        let momentary = self.window_energy(MOMENTARY_STEPS);
+        let short_term = self.window_energy(SHORT_TERM_STEPS);
+
+        // every completed step yields a new 400ms gating block
+        if self.steps_filled >= MOMENTARY_STEPS {
+            let loudness = energy_to_loudness(momentary);
+            if loudness > ABSOLUTE_GATE {
+                let bin = ((loudness - ABSOLUTE_GATE) / HISTOGRAM_RESOLUTION) as usize;
+                let bin = bin.min(HISTOGRAM_BINS - 1);
+                self.histogram_energy[bin] += momentary;
+                self.histogram_count[bin] += 1;
+            }
+        }
+
+        self.state.momentary.store(energy_to_loudness(momentary));
+        self.state.short_term.store(energy_to_loudness(short_term));
+        self.state.integrated.store(self.integrated_loudness());
+    }
+}
+
+impl AudioProcessor for LoudnessMeterRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // pass through input
+        *output = input.clone();
+
+        if self.state.reset.swap(false, Ordering::SeqCst) {
+            self.reset();
+        }
+
+        let number_of_channels = input.number_of_channels();
+        if number_of_channels != self.filter_state.len() {
+            self.filter_state.resize(number_of_channels, [[0.; 4]; 2]);
+            self.true_peak_history
+                .resize(number_of_channels, [0.; TRUE_PEAK_TAPS]);
+            self.state
+                .number_of_channels
+                .store(number_of_channels, Ordering::SeqCst);
+        }
+
+        let mut frame = 0;
+        let length = input.channel_data(0).len();
+
+        // process by chunks ending on the 100ms step boundaries
+        while frame < length {
+            let chunk_length = (self.step_length - self.step_count).min(length - frame);
+            let range = frame..frame + chunk_length;
+
+            for (channel_number, channel) in input.channels().iter().enumerate() {
+                let weight = channel_weight(channel_number, number_of_channels);
+                let [shelf, high_pass] = self.k_weighting;
+                let state = &mut self.filter_state[channel_number];
+                let mut sum_of_squares = 0.;
+
+                for &sample in &channel[range.clone()] {
+                    let mut x = f64::from(sample);
+                    for (c, s) in [shelf, high_pass].iter().zip(state.iter_mut()) {
+                        let y = c.b0 * x + c.b1 * s[0] + c.b2 * s[1] - c.a1 * s[2] - c.a2 * s[3];
+                        *s = [x, s[0], y, s[2]];
+                        x = y;
+                    }
+                    sum_of_squares += x * x;
+                }
+
+                self.step_energy += weight * sum_of_squares;
+
+                // true-peak: compare all interpolated phases
+                let history = &mut self.true_peak_history[channel_number];
+                let mut peak = self.true_peak[channel_number];
+
+                for &sample in &channel[range.clone()] {
+                    history.copy_within(0..TRUE_PEAK_TAPS - 1, 1);
+                    history[0] = sample;
+
+                    for phase in self.true_peak_coefs.iter() {
+                        let value: f32 = phase.iter().zip(history.iter()).map(|(c, h)| c * h).sum();
+                        peak = peak.max(value.abs());
+                    }
+                }
+
+                if peak > self.true_peak[channel_number] {
+                    self.true_peak[channel_number] = peak;
+                    self.state.true_peak[channel_number].store(peak, Ordering::SeqCst);
+                }
+            }
+
+            self.step_count += chunk_length;
+            frame += chunk_length;
+
+            if self.step_count == self.step_length {
+                self.complete_step();
+            }
+        }
+
+        // no tail-time
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode, ChannelCountMode};
+
+    use super::*;
+
+    #[test]
+    fn test_sine_loudness() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 48000 * 4, sample_rate);
+
+        let options = LoudnessMeterOptions {
+            channel_config: ChannelConfigOptions {
+                count: 1,
+                count_mode: ChannelCountMode::Explicit,
+                ..ChannelConfigOptions::default()
+            },
+        };
+        let meter = LoudnessMeterNode::new(&context, options);
+        meter.connect(&context.destination());
+
+        // a full scale 997Hz sine on a single channel measures -3.01 LUFS
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(997.);
+        osc.connect(&meter);
+        osc.start();
+
+        let _ = context.start_rendering_sync();
+
+        assert_float_eq!(meter.momentary_loudness(), -3.01, abs <= 0.05);
+        assert_float_eq!(meter.short_term_loudness(), -3.01, abs <= 0.05);
+        assert_float_eq!(meter.integrated_loudness(), -3.01, abs <= 0.05);
+
+        let true_peak = meter.true_peak();
+        assert_eq!(true_peak.len(), 1);
+        assert_float_eq!(true_peak[0], 0., abs <= 0.1);
+    }
+
+    #[test]
+    fn test_silence() {
+        let context = OfflineAudioContext::new(1, 48000, 48000.);
+
+        let meter = LoudnessMeterNode::new(&context, LoudnessMeterOptions::default());
+        meter.connect(&context.destination());
+
+        let _ = context.start_rendering_sync();
+
+        assert_eq!(meter.momentary_loudness(), f64::NEG_INFINITY);
+        assert_eq!(meter.integrated_loudness(), f64::NEG_INFINITY);
+    }
+}
@@ -4,11 +4,30 @@ use crate::buffer::AudioBuffer;
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
+use super::dc_blocker::{DcBlocker, DEFAULT_DC_BLOCKER_CUTOFF};
 use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
 
 use crate::media_streams::{MediaStream, MediaStreamTrack};
 use crossbeam_channel::{self, Receiver, Sender};
 
+/// Options for constructing a [`MediaStreamAudioDestinationNode`]
+#[derive(Clone, Debug)]
+pub struct MediaStreamAudioDestinationOptions {
+    /// Remove the DC offset of the recorded signal, see [`DcBlockerNode`](super::DcBlockerNode)
+    /// (non-standard, enabled by default)
+    pub dc_blocker: bool,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for MediaStreamAudioDestinationOptions {
+    fn default() -> Self {
+        Self {
+            dc_blocker: true,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
 /// An audio stream destination (e.g. WebRTC sink)
 ///
 /// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/MediaStreamAudioDestinationNode>
@@ -21,6 +40,9 @@ use crossbeam_channel::{self, Receiver, Sender};
 /// IMPORTANT: you must consume the buffers faster than the render thread produces them, or you
 /// will miss frames. Consider to spin up a dedicated thread to consume the buffers and cache them.
 ///
+/// By default, the DC offset of the recorded signal is removed with a high-pass filter, which
+/// can be disabled with [`MediaStreamAudioDestinationOptions::dc_blocker`].
+///
 /// # Usage
 ///
 /// ```no_run
@@ -80,8 +102,16 @@ impl AudioNode for MediaStreamAudioDestinationNode {
 
 impl MediaStreamAudioDestinationNode {
     /// Create a new MediaStreamAudioDestinationNode
-    pub fn new<C: BaseAudioContext>(context: &C, options: ChannelConfigOptions) -> Self {
+    pub fn new<C: BaseAudioContext>(
+        context: &C,
+        options: MediaStreamAudioDestinationOptions,
+    ) -> Self {
         context.register(move |registration| {
+            let MediaStreamAudioDestinationOptions {
+                dc_blocker,
+                channel_config,
+            } = options;
+
             let (send, recv) = crossbeam_channel::bounded(1);
 
             let iter = AudioDestinationNodeStream {
@@ -92,11 +122,17 @@ impl MediaStreamAudioDestinationNode {
 
             let node = MediaStreamAudioDestinationNode {
                 registration,
-                channel_config: options.into(),
+                channel_config: channel_config.into(),
                 stream,
             };
 
-            let render = DestinationRenderer { send, recv };
+            let dc_blocker = dc_blocker
+                .then(|| DcBlocker::new(DEFAULT_DC_BLOCKER_CUTOFF, context.sample_rate()));
+            let render = DestinationRenderer {
+                send,
+                recv,
+                dc_blocker,
+            };
 
             (node, Box::new(render))
         })
@@ -112,6 +148,7 @@ impl MediaStreamAudioDestinationNode {
 struct DestinationRenderer {
     send: Sender<AudioBuffer>,
     recv: Receiver<AudioBuffer>,
+    dc_blocker: Option<DcBlocker>,
 }
 
 impl AudioProcessor for DestinationRenderer {
@@ -123,7 +160,11 @@ impl AudioProcessor for DestinationRenderer {
         scope: &RenderScope,
     ) -> bool {
         // single input, no output
-        let input = &inputs[0];
+        let mut input = inputs[0].clone();
+
+        if let Some(dc_blocker) = &mut self.dc_blocker {
+            dc_blocker.process(&mut input);
+        }
 
         // convert AudioRenderQuantum to AudioBuffer
         let samples: Vec<_> = input.channels().iter().map(|c| c.to_vec()).collect();
@@ -1,6 +1,6 @@
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::media_streams::MediaStream;
-use crate::resampling::Resampler;
+use crate::resampling::AdaptiveResampler;
 use crate::RENDER_QUANTUM_SIZE;
 
 use super::{AudioNode, ChannelConfig, MediaStreamRenderer};
@@ -11,6 +11,8 @@ use super::{AudioNode, ChannelConfig, MediaStreamRenderer};
 // };
 pub struct MediaStreamAudioSourceOptions<'a> {
     pub media_stream: &'a MediaStream,
+    /// Identifier of the audio track to play, the first track of the stream when `None`
+    pub track_id: Option<&'a str>,
 }
 
 /// An audio source from a [`MediaStream`] (e.g. microphone input)
@@ -19,6 +21,12 @@ pub struct MediaStreamAudioSourceOptions<'a> {
 /// iterator never blocks. Use a
 /// [`MediaElementAudioSourceNode`](crate::node::MediaElementAudioSourceNode) for real time safe
 /// media playback.
+///
+/// The media stream is usually driven by another clock than the audio context, e.g. when the
+/// microphone and the speakers are different devices. The playback rate of the stream is
+/// continuously adjusted by a tiny amount to compensate the drift of the clocks, so long sessions
+/// do not accumulate latency or drop out. A stream signals that no data is available yet by
+/// yielding an empty buffer.
 pub struct MediaStreamAudioSourceNode {
     registration: AudioContextRegistration,
     channel_config: ChannelConfig,
@@ -47,19 +55,34 @@ impl MediaStreamAudioSourceNode {
     ///
     /// # Panics
     ///
-    /// This method will panic when the provided `MediaStream` does not contain any audio tracks.
+    /// This method will panic when the provided `MediaStream` does not contain any audio tracks,
+    /// or no track with the requested `track_id`.
     pub fn new<C: BaseAudioContext>(context: &C, options: MediaStreamAudioSourceOptions) -> Self {
+        let track = match options.track_id {
+            Some(id) => options
+                .media_stream
+                .get_track_by_id(id)
+                .unwrap_or_else(|| panic!("NotFoundError - no audio track with id {:?}", id)),
+            None => options
+                .media_stream
+                .get_audio_tracks()
+                .first()
+                .expect("InvalidStateError - the media stream has no audio tracks"),
+        };
+
+        // the callbacks of the track must not run on the render thread
+        if let Some(sender) = context.base().event_sender() {
+            track.set_event_sender(sender);
+        }
+
         context.register(move |registration| {
             let node = MediaStreamAudioSourceNode {
                 registration,
                 channel_config: ChannelConfig::default(),
             };
 
-            let resampler = Resampler::new(
-                context.sample_rate(),
-                RENDER_QUANTUM_SIZE,
-                options.media_stream.get_tracks()[0].iter(),
-            );
+            let resampler =
+                AdaptiveResampler::new(context.sample_rate(), RENDER_QUANTUM_SIZE, track.iter());
 
             let render = MediaStreamRenderer::new(resampler);
 
@@ -81,6 +81,11 @@ impl MediaStreamTrackAudioSourceNode {
         context: &C,
         options: MediaStreamTrackAudioSourceOptions,
     ) -> Self {
+        // the callbacks of the track must not run on the render thread
+        if let Some(sender) = context.base().event_sender() {
+            options.media_stream_track.set_event_sender(sender);
+        }
+
         context.register(move |registration| {
             let node = MediaStreamTrackAudioSourceNode {
                 registration,
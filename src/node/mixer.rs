@@ -0,0 +1,446 @@
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::stereo_panner::get_stereo_gains;
+use super::{
+    AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
+};
+
+/// Options for constructing a [`MixerNode`]
+#[derive(Clone, Debug)]
+pub struct MixerOptions {
+    pub number_of_inputs: usize,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for MixerOptions {
+    fn default() -> Self {
+        Self {
+            number_of_inputs: 2,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Assert that the channel count is valid for the MixerNode
+///
+/// # Panics
+///
+/// This function panics if given count is greater than 2
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count(count: usize) {
+    if count > 2 {
+        panic!("NotSupportedError: MixerNode channel count cannot be greater than two");
+    }
+}
+
+/// Assert that the channel count mode is valid for the MixerNode
+///
+/// # Panics
+///
+/// This function panics if given count mode is [`ChannelCountMode::Max`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
+    if mode == ChannelCountMode::Max {
+        panic!("NotSupportedError: MixerNode channel count mode cannot be set to max");
+    }
+}
+
+/// `MixerNode` sums its inputs to a stereo bus, with a gain and a pan for each input
+///
+/// This replaces the common pattern of a [`GainNode`](super::GainNode) and a
+/// [`StereoPannerNode`](super::StereoPannerNode) per source, all connected to a
+/// shared bus, with a single node. Each input is panned with the algorithm of the
+/// `StereoPannerNode`, as a mono or a stereo source depending on the channel count
+/// of the node.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{MixerNode, MixerOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let options = MixerOptions {
+///     number_of_inputs: 3,
+///     ..MixerOptions::default()
+/// };
+/// let mixer = MixerNode::new(&context, options);
+/// mixer.connect(&context.destination());
+///
+/// for (input, &frequency) in [220., 330., 440.].iter().enumerate() {
+///     let osc = context.create_oscillator();
+///     osc.frequency().set_value(frequency);
+///     osc.connect_at(&mixer, 0, input);
+///     osc.start();
+///
+///     mixer.gain(input).set_value(0.3);
+///     mixer.pan(input).set_value(input as f32 - 1.);
+/// }
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct MixerNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    gains: Vec<AudioParam>,
+    pans: Vec<AudioParam>,
+}
+
+impl AudioNode for MixerNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        self.gains.len()
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn set_channel_count_mode(&self, mode: ChannelCountMode) {
+        assert_valid_channel_count_mode(mode);
+        self.channel_config.set_count_mode(mode);
+    }
+
+    fn set_channel_count(&self, count: usize) {
+        assert_valid_channel_count(count);
+        self.channel_config.set_count(count);
+    }
+}
+
+impl MixerNode {
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * the number of inputs is 0
+    /// * `options.channel_config.count` is greater than 2
+    /// * `options.channel_config.mode` is `ChannelCountMode::Max`
+    pub fn new<C: BaseAudioContext>(context: &C, options: MixerOptions) -> Self {
+        if options.number_of_inputs == 0 {
+            panic!("IndexSizeError - Invalid number of inputs: 0, should be at least 1");
+        }
+
+        assert_valid_channel_count(options.channel_config.count);
+        assert_valid_channel_count_mode(options.channel_config.count_mode);
+
+        context.register(move |registration| {
+            let gain_opts = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 1.,
+                automation_rate: AutomationRate::A,
+            };
+            let pan_opts = AudioParamDescriptor {
+                min_value: -1.,
+                max_value: 1.,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+
+            let (gains, gain_procs): (Vec<_>, Vec<_>) = (0..options.number_of_inputs)
+                .map(|_| context.create_audio_param(gain_opts.clone(), &registration))
+                .unzip();
+            let (pans, pan_procs): (Vec<_>, Vec<_>) = (0..options.number_of_inputs)
+                .map(|_| context.create_audio_param(pan_opts.clone(), &registration))
+                .unzip();
+
+            let render = MixerRenderer {
+                gains: gain_procs,
+                pans: pan_procs,
+            };
+
+            let node = MixerNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                gains,
+                pans,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// A-rate [`AudioParam`] defining the gain of the given input
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `input` is not lower than the number of inputs
+    pub fn gain(&self, input: usize) -> &AudioParam {
+        self.assert_valid_input(input);
+        &self.gains[input]
+    }
+
+    /// A-rate [`AudioParam`] defining the position of the given input in the output's
+    /// stereo image, -1 represents full left and +1 represents full right
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `input` is not lower than the number of inputs
+    pub fn pan(&self, input: usize) -> &AudioParam {
+        self.assert_valid_input(input);
+        &self.pans[input]
+    }
+
+    #[track_caller]
+    #[inline(always)]
+    fn assert_valid_input(&self, input: usize) {
+        if input >= self.gains.len() {
+            panic!(
+                "IndexSizeError - Invalid input index: {:?} is outside range [0, {:?}]",
+                input,
+                self.gains.len() - 1
+            );
+        }
+    }
+}
+
+struct MixerRenderer {
+    gains: Vec<AudioParamId>,
+    pans: Vec<AudioParamId>,
+}
+
+impl AudioProcessor for MixerRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        if inputs.iter().all(AudioRenderQuantum::is_silent) {
+            output.make_silent();
+            return false;
+        }
+
+        output.set_number_of_channels(2);
+        let [left, right] = output.stereo_mut();
+        left.fill(0.);
+        right.fill(0.);
+
+        inputs
+            .iter()
+            .zip(self.gains.iter().zip(self.pans.iter()))
+            .filter(|(input, _)| !input.is_silent())
+            .for_each(|(input, (gain, pan))| {
+                let gain_values = params.get(gain);
+                let pan_values = params.get(pan);
+
+                let frames = left
+                    .iter_mut()
+                    .zip(right.iter_mut())
+                    .zip(gain_values.iter().cycle().zip(pan_values.iter().cycle()));
+
+                match input.number_of_channels() {
+                    1 => {
+                        frames.zip(input.channel_data(0).iter()).for_each(
+                            |(((l, r), (&gain, &pan)), &input)| {
+                                let x = (pan + 1.) * 0.5;
+                                let [gain_left, gain_right] = get_stereo_gains(x);
+                                let input = input * gain;
+
+                                *l += input * gain_left;
+                                *r += input * gain_right;
+                            },
+                        );
+                    }
+                    2 => {
+                        frames
+                            .zip(input.channel_data(0).iter())
+                            .zip(input.channel_data(1).iter())
+                            .for_each(|((((l, r), (&gain, &pan)), &input_left), &input_right)| {
+                                let input_left = input_left * gain;
+                                let input_right = input_right * gain;
+
+                                if pan <= 0. {
+                                    let [gain_left, gain_right] = get_stereo_gains(pan + 1.);
+                                    *l += input_right.mul_add(gain_left, input_left);
+                                    *r += input_right * gain_right;
+                                } else {
+                                    let [gain_left, gain_right] = get_stereo_gains(pan);
+                                    *l += input_left * gain_left;
+                                    *r += input_left.mul_add(gain_right, input_right);
+                                }
+                            });
+                    }
+                    _ => panic!("MixerNode should not have more than 2 channels to process"),
+                }
+            });
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioScheduledSourceNode, StereoPannerNode, StereoPannerOptions};
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let options = MixerOptions {
+            number_of_inputs: 3,
+            ..MixerOptions::default()
+        };
+        let mixer = MixerNode::new(&context, options);
+
+        assert_eq!(mixer.number_of_inputs(), 3);
+        assert_eq!(mixer.number_of_outputs(), 1);
+        for input in 0..3 {
+            assert_float_eq!(mixer.gain(input).value(), 1., abs <= 0.);
+            assert_float_eq!(mixer.pan(input).value(), 0., abs <= 0.);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_invalid_number_of_inputs() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let options = MixerOptions {
+            number_of_inputs: 0,
+            ..MixerOptions::default()
+        };
+        let _ = MixerNode::new(&context, options);
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_invalid_input_index() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let mixer = MixerNode::new(&context, MixerOptions::default());
+        let _ = mixer.gain(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let mixer = MixerNode::new(&context, MixerOptions::default());
+        mixer.set_channel_count(3);
+    }
+
+    #[test]
+    fn test_mix_mono() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44_100.);
+
+        // mix the inputs down to mono, so they are panned as mono sources
+        let options = MixerOptions {
+            number_of_inputs: 2,
+            channel_config: ChannelConfigOptions {
+                count: 1,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        };
+        let mixer = MixerNode::new(&context, options);
+        mixer.connect(&context.destination());
+
+        // hard left at half gain
+        let src = context.create_constant_source();
+        src.connect_at(&mixer, 0, 0);
+        src.start();
+        mixer.gain(0).set_value(0.5);
+        mixer.pan(0).set_value(-1.);
+
+        // hard right
+        let src = context.create_constant_source();
+        src.offset().set_value(0.25);
+        src.connect_at(&mixer, 0, 1);
+        src.start();
+        mixer.pan(1).set_value(1.);
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[0.5; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 1e-6
+        );
+        assert_float_eq!(
+            output.get_channel_data(1),
+            &[0.25; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 1e-6
+        );
+    }
+
+    #[test]
+    fn test_matches_stereo_panner() {
+        let length = RENDER_QUANTUM_SIZE * 2;
+        let pan = 0.3;
+
+        // stereo input through a mixer
+        let context = OfflineAudioContext::new(2, length, 44_100.);
+        let mixer = MixerNode::new(&context, MixerOptions::default());
+        mixer.connect(&context.destination());
+        mixer.gain(1).set_value(0.5);
+        mixer.pan(1).set_value(pan);
+
+        let merger = context.create_channel_merger(2);
+        merger.connect_at(&mixer, 0, 1);
+        let osc = context.create_oscillator();
+        osc.connect_at(&merger, 0, 0);
+        osc.start();
+        let constant = context.create_constant_source();
+        constant.connect_at(&merger, 0, 1);
+        constant.start();
+
+        let result = context.start_rendering_sync();
+
+        // same input through a gain and a stereo panner
+        let context = OfflineAudioContext::new(2, length, 44_100.);
+        let options = StereoPannerOptions {
+            pan,
+            ..StereoPannerOptions::default()
+        };
+        let panner = StereoPannerNode::new(&context, options);
+        panner.connect(&context.destination());
+        let gain = context.create_gain();
+        gain.gain().set_value(0.5);
+        gain.connect(&panner);
+
+        let merger = context.create_channel_merger(2);
+        merger.connect(&gain);
+        let osc = context.create_oscillator();
+        osc.connect_at(&merger, 0, 0);
+        osc.start();
+        let constant = context.create_constant_source();
+        constant.connect_at(&merger, 0, 1);
+        constant.start();
+
+        let expected = context.start_rendering_sync();
+
+        for channel in 0..2 {
+            assert_float_eq!(
+                result.get_channel_data(channel),
+                expected.get_channel_data(channel),
+                abs_all <= 1e-6
+            );
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::f32::consts::PI;
 use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::context::{AudioContextRegistration, ConcreteBaseAudioContext};
+use crate::context::{AudioContextRegistration, AudioNodeId, ConcreteBaseAudioContext};
 use crate::events::{ErrorEvent, EventHandler, EventPayload, EventType};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::AudioBufferIter;
@@ -15,8 +15,13 @@ mod analyser;
 pub use analyser::*;
 mod audio_buffer_source;
 pub use audio_buffer_source::*;
+mod audio_worklet;
+pub use audio_worklet::*;
+mod aux_send;
+pub use aux_send::*;
 mod biquad_filter;
 pub use biquad_filter::*;
+mod bypass;
 mod channel_merger;
 pub use channel_merger::*;
 mod channel_splitter;
@@ -25,16 +30,28 @@ mod constant_source;
 pub use constant_source::*;
 mod convolver;
 pub use convolver::*;
+mod dc_blocker;
+pub use dc_blocker::*;
 mod delay;
 pub use delay::*;
 mod destination;
 pub use destination::*;
 mod dynamics_compressor;
 pub use dynamics_compressor::*;
+mod fader;
+pub use fader::*;
+mod fm_operator;
+pub use fm_operator::*;
 mod gain;
 pub use gain::*;
 mod iir_filter;
 pub use iir_filter::*;
+mod level_meter;
+pub use level_meter::*;
+mod limiter;
+pub use limiter::*;
+mod loudness_meter;
+pub use loudness_meter::*;
 mod media_element_source;
 pub use media_element_source::*;
 mod media_stream_destination;
@@ -43,15 +60,38 @@ mod media_stream_source;
 pub use media_stream_source::*;
 mod media_stream_track_source;
 pub use media_stream_track_source::*;
+mod mixer;
+pub use mixer::*;
+mod mute_solo;
+pub use mute_solo::*;
+mod noise;
+pub use noise::*;
 mod oscillator;
 pub use oscillator::*;
 mod panner;
 pub use panner::*;
+mod parametric_eq;
+pub use parametric_eq::*;
+mod phaser;
+pub use phaser::*;
+mod reverb;
+pub use reverb::*;
 mod stereo_panner;
 pub use stereo_panner::*;
+mod streaming_buffer_source;
+pub use streaming_buffer_source::*;
+mod stereo_width;
+pub use stereo_width::*;
+#[cfg(feature = "time-stretch")]
+mod time_stretch;
+#[cfg(feature = "time-stretch")]
+pub use time_stretch::*;
 mod waveshaper;
 pub use waveshaper::*;
 
+/// Nominal range of detune params in cents, i.e. `1200 * log2(f32::MAX)`
+pub(crate) const MAX_DETUNE: f32 = 153_600.;
+
 pub(crate) const TABLE_LENGTH_USIZE: usize = 8192;
 pub(crate) const TABLE_LENGTH_BY_4_USIZE: usize = TABLE_LENGTH_USIZE / 4;
 
@@ -153,9 +193,16 @@ impl Default for ChannelConfigOptions {
 /// let _: ChannelConfig = opts.into();
 #[derive(Clone, Debug)]
 pub struct ChannelConfig {
-    count: Arc<AtomicUsize>,
-    count_mode: Arc<AtomicU32>,
-    interpretation: Arc<AtomicU32>,
+    inner: Arc<ChannelConfigInner>,
+}
+
+/// Shared state of a [`ChannelConfig`], in a single allocation so the render thread reads it
+/// with a single cache miss
+#[derive(Debug)]
+struct ChannelConfigInner {
+    count: AtomicUsize,
+    count_mode: AtomicU32,
+    interpretation: AtomicU32,
 }
 
 impl Default for ChannelConfig {
@@ -171,39 +218,154 @@ impl ChannelConfig {
     /// Represents an enumerated value describing the way channels must be matched between the
     /// node's inputs and outputs.
     pub(crate) fn count_mode(&self) -> ChannelCountMode {
-        self.count_mode.load(Ordering::SeqCst).into()
+        self.inner.count_mode.load(Ordering::SeqCst).into()
     }
     fn set_count_mode(&self, v: ChannelCountMode) {
-        self.count_mode.store(v as u32, Ordering::SeqCst)
+        self.inner.count_mode.store(v as u32, Ordering::SeqCst)
     }
 
     /// Represents an enumerated value describing the meaning of the channels. This interpretation
     /// will define how audio up-mixing and down-mixing will happen.
     pub(crate) fn interpretation(&self) -> ChannelInterpretation {
-        self.interpretation.load(Ordering::SeqCst).into()
+        self.inner.interpretation.load(Ordering::SeqCst).into()
     }
     fn set_interpretation(&self, v: ChannelInterpretation) {
-        self.interpretation.store(v as u32, Ordering::SeqCst)
+        self.inner.interpretation.store(v as u32, Ordering::SeqCst)
     }
 
     /// Represents an integer used to determine how many channels are used when up-mixing and
     /// down-mixing connections to any inputs to the node.
     pub(crate) fn count(&self) -> usize {
-        self.count.load(Ordering::SeqCst)
+        self.inner.count.load(Ordering::SeqCst)
     }
     fn set_count(&self, v: usize) {
         crate::assert_valid_number_of_channels(v);
-        self.count.store(v, Ordering::SeqCst)
+        self.inner.count.store(v, Ordering::SeqCst)
     }
 }
 
 impl From<ChannelConfigOptions> for ChannelConfig {
+    /// # Panics
+    ///
+    /// This function panics if the channel count is outside the [1, 32] range, 32 being
+    /// defined by the MAX_CHANNELS constant.
     fn from(opts: ChannelConfigOptions) -> Self {
+        crate::assert_valid_number_of_channels(opts.count);
+
         Self {
-            count: Arc::new(AtomicUsize::from(opts.count)),
-            count_mode: Arc::new(AtomicU32::from(opts.count_mode as u32)),
-            interpretation: Arc::new(AtomicU32::from(opts.interpretation as u32)),
+            inner: Arc::new(ChannelConfigInner {
+                count: AtomicUsize::from(opts.count),
+                count_mode: AtomicU32::from(opts.count_mode as u32),
+                interpretation: AtomicU32::from(opts.interpretation as u32),
+            }),
+        }
+    }
+}
+
+/// Error returned by the fallible [`AudioNode::try_connect`] and [`AudioNode::try_connect_at`]
+/// methods when a connection is invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectError {
+    /// The source and destination nodes belong to different audio contexts
+    InvalidAccessError,
+    /// The given output port is out of bounds for the source node
+    OutputIndexSizeError(usize),
+    /// The given input port is out of bounds for the destination node
+    InputIndexSizeError(usize),
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidAccessError => write!(
+                f,
+                "InvalidAccessError: Attempting to connect nodes from different contexts"
+            ),
+            Self::OutputIndexSizeError(output) => {
+                write!(f, "IndexSizeError: output port {} is out of bounds", output)
+            }
+            Self::InputIndexSizeError(input) => {
+                write!(f, "IndexSizeError: input port {} is out of bounds", input)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConnectError {}
+
+/// Connect an output of the node with the given id to an input of `dest`, shared by all
+/// implementations of [`AudioNode::try_connect_at`]
+///
+/// Nothing is connected when an error is returned.
+pub(crate) fn try_connect_node(
+    context: &ConcreteBaseAudioContext,
+    from: AudioNodeId,
+    number_of_outputs: usize,
+    dest: &dyn AudioNode,
+    output: usize,
+    input: usize,
+) -> Result<(), ConnectError> {
+    if context != dest.context() {
+        return Err(ConnectError::InvalidAccessError);
+    }
+    if number_of_outputs <= output {
+        return Err(ConnectError::OutputIndexSizeError(output));
+    }
+    if dest.number_of_inputs() <= input {
+        return Err(ConnectError::InputIndexSizeError(input));
+    }
+
+    context.connect(from, dest.registration().id(), output, input);
+    Ok(())
+}
+
+/// Disconnect the outgoing connections of the node with the given id, optionally restricted to
+/// the given output port, destination and input port, shared by all implementations of the
+/// `AudioNode::disconnect*` methods
+///
+/// # Panics
+///
+/// This function will panic when
+/// - the AudioContext of the source and destination does not match
+/// - if the output port is out of bounds for the source node
+/// - if the input port is out of bounds for the destination node
+/// - a destination is given but no matching connection exists
+pub(crate) fn disconnect_node(
+    context: &ConcreteBaseAudioContext,
+    from: AudioNodeId,
+    number_of_outputs: usize,
+    output: Option<usize>,
+    dest: Option<&dyn AudioNode>,
+    input: Option<usize>,
+) {
+    if let Some(output) = output {
+        if number_of_outputs <= output {
+            panic!("IndexSizeError: output port {} is out of bounds", output);
+        }
+    }
+
+    let to = match dest {
+        None => None,
+        Some(dest) => {
+            if context != dest.context() {
+                panic!(
+                    "InvalidAccessError: Attempting to disconnect nodes from different contexts"
+                );
+            }
+            if let Some(input) = input {
+                if dest.number_of_inputs() <= input {
+                    panic!("IndexSizeError: input port {} is out of bounds", input);
+                }
+            }
+            Some(dest.registration().id())
         }
+    };
+
+    let disconnected = context.disconnect(from, output, to, input);
+
+    // [spec] disconnecting from a given destination throws when there is no such connection
+    if to.is_some() && !disconnected {
+        panic!("InvalidAccessError: Attempting to disconnect nodes that are not connected");
     }
 }
 
@@ -250,40 +412,154 @@ pub trait AudioNode {
         output: usize,
         input: usize,
     ) -> &'a dyn AudioNode {
-        if self.context() != dest.context() {
-            panic!("InvalidAccessError: Attempting to connect nodes from different contexts");
-        }
-        if self.number_of_outputs() <= output {
-            panic!("IndexSizeError: output port {} is out of bounds", output);
-        }
-        if dest.number_of_inputs() <= input {
-            panic!("IndexSizeError: input port {} is out of bounds", input);
-        }
+        self.try_connect_at(dest, output, input)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Connect the output of this AudioNode to the input of another node, returning an error
+    /// instead of panicking when the connection is invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConnectError::InvalidAccessError`] when the AudioContext of the source and
+    /// destination does not match
+    fn try_connect<'a>(&self, dest: &'a dyn AudioNode) -> Result<&'a dyn AudioNode, ConnectError> {
+        self.try_connect_at(dest, 0, 0)
+    }
 
-        self.context().connect(
+    /// Connect a specific output of this AudioNode to a specific input of another node,
+    /// returning an error instead of panicking when the connection is invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// - [`ConnectError::InvalidAccessError`] when the AudioContext of the source and
+    ///   destination does not match
+    /// - [`ConnectError::OutputIndexSizeError`] when the output port is out of bounds for the
+    ///   source node
+    /// - [`ConnectError::InputIndexSizeError`] when the input port is out of bounds for the
+    ///   destination node
+    fn try_connect_at<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: usize,
+        input: usize,
+    ) -> Result<&'a dyn AudioNode, ConnectError> {
+        try_connect_node(
+            self.context(),
             self.registration().id(),
-            dest.registration().id(),
+            self.number_of_outputs(),
+            dest,
             output,
             input,
+        )?;
+        Ok(dest)
+    }
+
+    /// Disconnects all outgoing connections from the AudioNode.
+    fn disconnect(&self) {
+        disconnect_node(
+            self.context(),
+            self.registration().id(),
+            self.number_of_outputs(),
+            None,
+            None,
+            None,
+        );
+    }
+
+    /// Disconnects all outgoing connections from the given output of the AudioNode.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when
+    /// - if the output port is out of bounds for this node
+    fn disconnect_output(&self, output: usize) {
+        disconnect_node(
+            self.context(),
+            self.registration().id(),
+            self.number_of_outputs(),
+            Some(output),
+            None,
+            None,
+        );
+    }
+
+    /// Disconnects all outputs of the AudioNode that go to a specific destination AudioNode.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when
+    /// - the AudioContext of the source and destination does not match
+    /// - the source node was not connected to the destination node
+    fn disconnect_destination<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
+        disconnect_node(
+            self.context(),
+            self.registration().id(),
+            self.number_of_outputs(),
+            None,
+            Some(dest),
+            None,
         );
         dest
     }
 
     /// Disconnects all outputs of the AudioNode that go to a specific destination AudioNode.
+    #[deprecated(note = "use `disconnect_destination` instead")]
     fn disconnect_from<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
-        if self.context() != dest.context() {
-            panic!("attempting to disconnect nodes from different contexts");
-        }
-
-        self.context()
-            .disconnect_from(self.registration().id(), dest.registration().id());
+        self.disconnect_destination(dest)
+    }
 
+    /// Disconnects a specific output of the AudioNode from a specific destination AudioNode.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when
+    /// - the AudioContext of the source and destination does not match
+    /// - if the output port is out of bounds for the source node
+    /// - the output port of the source node was not connected to the destination node
+    fn disconnect_destination_output<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: usize,
+    ) -> &'a dyn AudioNode {
+        disconnect_node(
+            self.context(),
+            self.registration().id(),
+            self.number_of_outputs(),
+            Some(output),
+            Some(dest),
+            None,
+        );
         dest
     }
 
-    /// Disconnects all outgoing connections from the AudioNode.
-    fn disconnect(&self) {
-        self.context().disconnect(self.registration().id());
+    /// Disconnects a specific output of the AudioNode from a specific input of a destination
+    /// AudioNode.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when
+    /// - the AudioContext of the source and destination does not match
+    /// - if the output port is out of bounds for the source node
+    /// - if the input port is out of bounds for the destination node
+    /// - the output port of the source node was not connected to the input port of the
+    ///   destination node
+    fn disconnect_destination_output_input<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: usize,
+        input: usize,
+    ) -> &'a dyn AudioNode {
+        disconnect_node(
+            self.context(),
+            self.registration().id(),
+            self.number_of_outputs(),
+            Some(output),
+            Some(dest),
+            Some(input),
+        );
+        dest
     }
 
     /// The number of inputs feeding into the AudioNode. For source nodes, this will be 0.
@@ -292,6 +568,15 @@ pub trait AudioNode {
     /// The number of outputs coming out of the AudioNode.
     fn number_of_outputs(&self) -> usize;
 
+    /// Processing latency introduced by the AudioNode, in seconds (non-standard)
+    ///
+    /// Nodes with a look-ahead delay their output by this amount of time. The render
+    /// graph does not compensate for it, parallel paths can be aligned with a
+    /// [`DelayNode`] of the same delay time.
+    fn latency(&self) -> f64 {
+        0.
+    }
+
     /// Represents an enumerated value describing the way channels must be matched between the
     /// node's inputs and outputs.
     fn channel_count_mode(&self) -> ChannelCountMode {
@@ -327,6 +612,9 @@ pub trait AudioNode {
     /// Register callback to run when an unhandled exception occurs in the audio processor.
     ///
     /// Note that once a unhandled exception is thrown, the processor will output silence throughout its lifetime.
+    /// The node keeps its connections and the rest of the graph keeps rendering. A working
+    /// processor can be restored with
+    /// [`BaseAudioContext::replace_processor`](crate::context::BaseAudioContext::replace_processor).
     ///
     /// Only a single event handler is active at any time. Calling this method multiple times will
     /// override the previous event handler.
@@ -404,6 +692,25 @@ pub trait AudioScheduledSourceNode: AudioNode {
     }
 }
 
+/// Interface of effect nodes, whose processing can be bypassed.
+///
+/// When bypassed, the node outputs its input instead of the processed signal. Switching the
+/// bypass crossfades between both signals over one render quantum, and the input is delayed
+/// by the [`latency`](AudioNode::latency) of the node, so an A/B comparison neither clicks
+/// nor shifts in time.
+///
+/// The processing keeps running while bypassed, so the state of the effect is up to date when
+/// the bypass is released.
+///
+/// This interface is not part of the Web Audio API specification.
+pub trait AudioEffectNode: AudioNode {
+    /// Whether the processing of the node is bypassed
+    fn bypass(&self) -> bool;
+
+    /// Bypass the processing of the node, or release the bypass
+    fn set_bypass(&self, value: bool);
+}
+
 // `MediaStreamRenderer` is internally used by `MediaElementAudioSourceNode` and
 // `MediaStreamAudioSourceNode`.
 struct MediaStreamRenderer<R> {
@@ -0,0 +1,482 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+struct SoloGroupState {
+    exclusive: AtomicBool,
+    /// Number of soloed members, read by the renderers
+    solo_count: AtomicUsize,
+    /// Solo flags of the members, all solo changes are made while holding this lock
+    members: Mutex<Vec<Weak<AtomicBool>>>,
+}
+
+/// A group of [`MuteSoloNode`]s sharing their solo state
+///
+/// As soon as one member of the group is soloed, all members that are not soloed
+/// are silenced. In an exclusive group, soloing a member releases the solo of all
+/// other members.
+///
+/// The group is a cheap handle, clones refer to the same group.
+#[derive(Clone)]
+pub struct SoloGroup {
+    state: Arc<SoloGroupState>,
+}
+
+impl std::fmt::Debug for SoloGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SoloGroup")
+            .field("exclusive", &self.exclusive())
+            .field("solo_count", &self.solo_count())
+            .finish()
+    }
+}
+
+impl Default for SoloGroup {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl SoloGroup {
+    /// Create a new group, with exclusive solo semantics or not
+    pub fn new(exclusive: bool) -> Self {
+        let state = SoloGroupState {
+            exclusive: AtomicBool::new(exclusive),
+            solo_count: AtomicUsize::new(0),
+            members: Mutex::new(Vec::new()),
+        };
+        Self {
+            state: Arc::new(state),
+        }
+    }
+
+    /// Whether soloing a member releases the solo of the other members
+    pub fn exclusive(&self) -> bool {
+        self.state.exclusive.load(Ordering::SeqCst)
+    }
+
+    /// Set the exclusive solo semantics of the group
+    ///
+    /// Members that are already soloed keep their solo.
+    pub fn set_exclusive(&self, value: bool) {
+        self.state.exclusive.store(value, Ordering::SeqCst);
+    }
+
+    /// Number of soloed members of the group
+    pub fn solo_count(&self) -> usize {
+        self.state.solo_count.load(Ordering::SeqCst)
+    }
+
+    /// Release the solo of all members of the group
+    #[allow(clippy::missing_panics_doc)]
+    pub fn clear_solo(&self) {
+        let members = self.state.members.lock().unwrap();
+        members
+            .iter()
+            .filter_map(Weak::upgrade)
+            .for_each(|soloed| self.swap_solo(&soloed, false));
+    }
+
+    fn add_member(&self, soloed: &Arc<AtomicBool>) {
+        let mut members = self.state.members.lock().unwrap();
+        members.retain(|m| m.strong_count() > 0);
+        members.push(Arc::downgrade(soloed));
+    }
+
+    fn set_solo(&self, soloed: &Arc<AtomicBool>, value: bool) {
+        let members = self.state.members.lock().unwrap();
+
+        if value && self.exclusive() {
+            members
+                .iter()
+                .filter_map(Weak::upgrade)
+                .filter(|other| !Arc::ptr_eq(other, soloed))
+                .for_each(|other| self.swap_solo(&other, false));
+        }
+
+        self.swap_solo(soloed, value);
+    }
+
+    /// Update the solo flag of a member and the solo count, the members lock must be held
+    fn swap_solo(&self, soloed: &AtomicBool, value: bool) {
+        let previous = soloed.swap(value, Ordering::SeqCst);
+        match (previous, value) {
+            (false, true) => {
+                self.state.solo_count.fetch_add(1, Ordering::SeqCst);
+            }
+            (true, false) => {
+                self.state.solo_count.fetch_sub(1, Ordering::SeqCst);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Options for constructing a [`MuteSoloNode`]
+#[derive(Clone, Debug, Default)]
+pub struct MuteSoloOptions {
+    /// Initial mute state
+    pub muted: bool,
+    /// Initial solo state, in an exclusive group the solo of the other members is released
+    pub soloed: bool,
+    /// Solo group of the node, the node is alone in a new group if not set
+    pub group: Option<SoloGroup>,
+    pub channel_config: ChannelConfigOptions,
+}
+
+/// `MuteSoloNode` provides the mute and solo switches of a mixer channel or bus
+///
+/// The node passes its input through unless it is muted, or another member of its
+/// [`SoloGroup`] is soloed while it is not. The resulting gain changes are applied at
+/// the next render quantum boundary, with a linear ramp over one render quantum to
+/// avoid clicks.
+///
+/// Mute takes precedence over solo: a node that is muted and soloed is silent, but
+/// still silences the other members of the group.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{MuteSoloNode, MuteSoloOptions, SoloGroup};
+///
+/// let context = AudioContext::default();
+/// let group = SoloGroup::new(true);
+///
+/// let strips: Vec<_> = [220., 330., 440.]
+///     .iter()
+///     .map(|&frequency| {
+///         let options = MuteSoloOptions {
+///             group: Some(group.clone()),
+///             ..MuteSoloOptions::default()
+///         };
+///         let strip = MuteSoloNode::new(&context, options);
+///         strip.connect(&context.destination());
+///
+///         let osc = context.create_oscillator();
+///         osc.frequency().set_value(frequency);
+///         osc.connect(&strip);
+///         osc.start();
+///
+///         strip
+///     })
+///     .collect();
+///
+/// // e.g. in the event handlers of the UI buttons
+/// strips[0].set_muted(true);
+/// strips[1].set_soloed(true);
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct MuteSoloNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    muted: Arc<AtomicBool>,
+    soloed: Arc<AtomicBool>,
+    group: SoloGroup,
+}
+
+impl AudioNode for MuteSoloNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl Drop for MuteSoloNode {
+    fn drop(&mut self) {
+        // do not keep silencing the rest of the group
+        self.group.set_solo(&self.soloed, false);
+    }
+}
+
+impl MuteSoloNode {
+    pub fn new<C: BaseAudioContext>(context: &C, options: MuteSoloOptions) -> Self {
+        context.register(move |registration| {
+            let MuteSoloOptions {
+                muted,
+                soloed,
+                group,
+                channel_config,
+            } = options;
+
+            let group = group.unwrap_or_default();
+            let muted = Arc::new(AtomicBool::new(muted));
+            let solo_flag = Arc::new(AtomicBool::new(false));
+            group.add_member(&solo_flag);
+            if soloed {
+                group.set_solo(&solo_flag, true);
+            }
+
+            let render = MuteSoloRenderer {
+                muted: Arc::clone(&muted),
+                soloed: Arc::clone(&solo_flag),
+                group: Arc::clone(&group.state),
+                current_gain: None,
+            };
+
+            let node = MuteSoloNode {
+                registration,
+                channel_config: channel_config.into(),
+                muted,
+                soloed: solo_flag,
+                group,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Whether the node is muted
+    pub fn muted(&self) -> bool {
+        self.muted.load(Ordering::SeqCst)
+    }
+
+    /// Mute or unmute the node
+    pub fn set_muted(&self, value: bool) {
+        self.muted.store(value, Ordering::SeqCst);
+    }
+
+    /// Whether the node is soloed
+    pub fn soloed(&self) -> bool {
+        self.soloed.load(Ordering::SeqCst)
+    }
+
+    /// Solo or unsolo the node, in an exclusive group soloing the node releases the solo of
+    /// the other members
+    pub fn set_soloed(&self, value: bool) {
+        self.group.set_solo(&self.soloed, value);
+    }
+
+    /// The solo group of the node
+    pub fn group(&self) -> &SoloGroup {
+        &self.group
+    }
+}
+
+struct MuteSoloRenderer {
+    muted: Arc<AtomicBool>,
+    soloed: Arc<AtomicBool>,
+    group: Arc<SoloGroupState>,
+    /// Gain applied at the end of the previous block, not set before the first rendered block
+    current_gain: Option<f32>,
+}
+
+impl MuteSoloRenderer {
+    fn target_gain(&self) -> f32 {
+        let muted = self.muted.load(Ordering::SeqCst);
+        let soloed = self.soloed.load(Ordering::SeqCst);
+        let solo_count = self.group.solo_count.load(Ordering::SeqCst);
+
+        if muted || (solo_count > 0 && !soloed) {
+            0.
+        } else {
+            1.
+        }
+    }
+}
+
+impl AudioProcessor for MuteSoloRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        let target = self.target_gain();
+        let current = self.current_gain.unwrap_or(target);
+        self.current_gain = Some(target);
+
+        if input.is_silent() || (current == 0. && target == 0.) {
+            output.make_silent();
+            return false;
+        }
+
+        *output = input.clone();
+
+        if current != 1. || target != 1. {
+            output
+                .channels_mut()
+                .iter_mut()
+                .for_each(|channel| apply_gain(channel, current, target));
+        }
+
+        false
+    }
+}
+
+/// Apply a gain to a channel of a render quantum, with a linear ramp if the gain changes
+fn apply_gain(channel: &mut [f32], from: f32, to: f32) {
+    if from == to {
+        channel.iter_mut().for_each(|o| *o *= to);
+    } else {
+        let step = (to - from) / RENDER_QUANTUM_SIZE as f32;
+        channel
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, o)| *o *= from + step * (i + 1) as f32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.;
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let node = MuteSoloNode::new(&context, MuteSoloOptions::default());
+
+        assert!(!node.muted());
+        assert!(!node.soloed());
+        assert!(!node.group().exclusive());
+        assert_eq!(node.group().solo_count(), 0);
+
+        node.set_muted(true);
+        node.set_soloed(true);
+        assert!(node.muted());
+        assert!(node.soloed());
+        assert_eq!(node.group().solo_count(), 1);
+    }
+
+    #[test]
+    fn test_solo_group() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let group = SoloGroup::default();
+        let options = MuteSoloOptions {
+            group: Some(group.clone()),
+            ..MuteSoloOptions::default()
+        };
+
+        let node1 = MuteSoloNode::new(&context, options.clone());
+        let node2 = MuteSoloNode::new(&context, options.clone());
+        let node3 = MuteSoloNode::new(&context, options);
+
+        node1.set_soloed(true);
+        node2.set_soloed(true);
+        node2.set_soloed(true);
+        assert_eq!(group.solo_count(), 2);
+
+        // exclusive solo
+        group.set_exclusive(true);
+        node3.set_soloed(true);
+        assert!(!node1.soloed());
+        assert!(!node2.soloed());
+        assert!(node3.soloed());
+        assert_eq!(group.solo_count(), 1);
+
+        // dropping a soloed member releases its solo
+        drop(node3);
+        assert_eq!(group.solo_count(), 0);
+
+        node1.set_soloed(true);
+        group.clear_solo();
+        assert!(!node1.soloed());
+        assert_eq!(group.solo_count(), 0);
+    }
+
+    #[test]
+    fn test_gain_ramp() {
+        let mut channel = [1.; RENDER_QUANTUM_SIZE];
+        apply_gain(&mut channel, 0., 1.);
+
+        assert_float_eq!(channel[0], 1. / RENDER_QUANTUM_SIZE as f32, abs <= 1e-7);
+        assert_float_eq!(channel[RENDER_QUANTUM_SIZE - 1], 1., abs <= 0.);
+        assert!(channel.windows(2).all(|w| w[1] > w[0]));
+
+        let mut channel = [1.; RENDER_QUANTUM_SIZE];
+        apply_gain(&mut channel, 1., 0.);
+        assert_float_eq!(channel[RENDER_QUANTUM_SIZE - 1], 0., abs <= 0.);
+        assert!(channel.windows(2).all(|w| w[1] < w[0]));
+    }
+
+    #[test]
+    fn test_mute_render() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, SAMPLE_RATE);
+
+        let options = MuteSoloOptions {
+            muted: true,
+            ..MuteSoloOptions::default()
+        };
+        let node = MuteSoloNode::new(&context, options);
+        node.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&node);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[0.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_solo_render() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, SAMPLE_RATE);
+        let group = SoloGroup::default();
+
+        let offsets = [1., 2., 4.];
+        let nodes: Vec<_> = offsets
+            .iter()
+            .map(|&offset| {
+                let options = MuteSoloOptions {
+                    group: Some(group.clone()),
+                    ..MuteSoloOptions::default()
+                };
+                let node = MuteSoloNode::new(&context, options);
+                node.connect(&context.destination());
+
+                let src = context.create_constant_source();
+                src.offset().set_value(offset);
+                src.connect(&node);
+                src.start();
+
+                node
+            })
+            .collect();
+
+        nodes[0].set_soloed(true);
+        nodes[1].set_soloed(true);
+        // muted has precedence over soloed
+        nodes[1].set_muted(true);
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+}
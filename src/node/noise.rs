@@ -0,0 +1,425 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::control::{frame_index, Scheduler};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
+
+/// Options for constructing a [`NoiseNode`]
+#[derive(Clone, Debug)]
+pub struct NoiseOptions {
+    /// The color of the noise
+    pub type_: NoiseType,
+    /// Linear gain applied to the generated noise
+    pub gain: f32,
+    /// Seed of the random number generator, a random seed is picked if `None`
+    ///
+    /// Two nodes created with the same seed and type render the exact same
+    /// signal, which allows for reproducible offline renderings.
+    pub seed: Option<u64>,
+}
+
+impl Default for NoiseOptions {
+    fn default() -> Self {
+        Self {
+            type_: NoiseType::default(),
+            gain: 1.,
+            seed: None,
+        }
+    }
+}
+
+/// Color of the noise rendered by a `NoiseNode`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NoiseType {
+    /// Flat power spectrum
+    #[default]
+    White,
+    /// Power spectrum falling off by 3dB per octave
+    Pink,
+    /// Power spectrum falling off by 6dB per octave
+    Brownian,
+}
+
+impl From<u32> for NoiseType {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => NoiseType::White,
+            1 => NoiseType::Pink,
+            2 => NoiseType::Brownian,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// `NoiseNode` represents an audio source generating white, pink or brownian
+/// noise, with samples nominally in the [-1, 1] range.
+///
+/// The random number generator can be seeded, so offline renderings involving
+/// noise are reproducible.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{NoiseNode, NoiseOptions, NoiseType};
+///
+/// let context = AudioContext::default();
+///
+/// let options = NoiseOptions {
+///     type_: NoiseType::Pink,
+///     gain: 0.5,
+///     ..NoiseOptions::default()
+/// };
+/// let noise = NoiseNode::new(&context, options);
+/// noise.connect(&context.destination());
+/// noise.start();
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct NoiseNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    gain: AudioParam,
+    type_: Arc<AtomicU32>,
+    scheduler: Scheduler,
+}
+
+impl AudioNode for NoiseNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for NoiseNode {
+    fn start(&self) {
+        let when = self.registration.context().current_time();
+        self.start_at(when);
+    }
+
+    fn start_at(&self, when: f64) {
+        self.scheduler.start_at(when);
+    }
+
+    fn stop(&self) {
+        let when = self.registration.context().current_time();
+        self.stop_at(when);
+    }
+
+    fn stop_at(&self, when: f64) {
+        self.scheduler.stop_at(when);
+    }
+}
+
+impl NoiseNode {
+    pub fn new<C: BaseAudioContext>(context: &C, options: NoiseOptions) -> Self {
+        context.register(move |registration| {
+            let NoiseOptions { type_, gain, seed } = options;
+
+            let param_opts = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 1.,
+                automation_rate: AutomationRate::A,
+            };
+            let (param, proc) = context.create_audio_param(param_opts, &registration);
+            param.set_value(gain);
+
+            let type_ = Arc::new(AtomicU32::new(type_ as u32));
+            let scheduler = Scheduler::new();
+
+            let seed = seed.unwrap_or_else(|| RandomState::new().build_hasher().finish());
+
+            let render = NoiseRenderer {
+                gain: proc,
+                type_: Arc::clone(&type_),
+                scheduler: scheduler.clone(),
+                rng: XorShiftRng::new(seed),
+                pink: [0.; 7],
+                brown: 0.,
+                ended_triggered: false,
+            };
+
+            let node = NoiseNode {
+                registration,
+                channel_config: ChannelConfig::default(),
+                gain: param,
+                type_,
+                scheduler,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// A-rate [`AudioParam`] defining the linear gain of the generated noise
+    pub fn gain(&self) -> &AudioParam {
+        &self.gain
+    }
+
+    /// Returns the color of the generated noise
+    pub fn type_(&self) -> NoiseType {
+        self.type_.load(Ordering::SeqCst).into()
+    }
+
+    /// Set the color of the generated noise
+    pub fn set_type(&self, type_: NoiseType) {
+        self.type_.store(type_ as u32, Ordering::SeqCst);
+    }
+}
+
+/// Small and fast xorshift* pseudo random number generator, not suitable for
+/// cryptographic purposes
+struct XorShiftRng {
+    state: u64,
+}
+
+impl XorShiftRng {
+    fn new(seed: u64) -> Self {
+        // scramble the seed with splitmix64 so similar seeds yield unrelated
+        // sequences, the state of a xorshift generator must never be zero
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+
+        Self {
+            state: if z == 0 { 0x9E3779B97F4A7C15 } else { z },
+        }
+    }
+
+    /// Uniformly distributed value in the [-1, 1) range
+    #[inline]
+    fn next_f32(&mut self) -> f32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let value = self.state.wrapping_mul(0x2545F4914F6CDD1D);
+
+        // use the 24 most significant bits as mantissa
+        (value >> 40) as f32 / (1 << 23) as f32 - 1.
+    }
+}
+
+struct NoiseRenderer {
+    gain: AudioParamId,
+    type_: Arc<AtomicU32>,
+    scheduler: Scheduler,
+    rng: XorShiftRng,
+    /// State of the pink noise filter bank
+    pink: [f32; 7],
+    /// State of the brownian noise integrator
+    brown: f32,
+    ended_triggered: bool,
+}
+
+impl NoiseRenderer {
+    #[inline]
+    fn generate_white(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+
+    /// Paul Kellet's refined pink noise filter
+    #[inline]
+    fn generate_pink(&mut self) -> f32 {
+        let white = self.rng.next_f32();
+        let b = &mut self.pink;
+
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.969 * b[2] + white * 0.153852;
+        b[3] = 0.8665 * b[3] + white * 0.3104856;
+        b[4] = 0.55 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.016898;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+
+        // compensate for the gain of the filter bank
+        pink * 0.11
+    }
+
+    /// Leaky integration of white noise
+    #[inline]
+    fn generate_brownian(&mut self) -> f32 {
+        let white = self.rng.next_f32();
+        self.brown = (self.brown + 0.02 * white) / 1.02;
+
+        // compensate for the gain of the integrator
+        self.brown * 3.5
+    }
+}
+
+impl AudioProcessor for NoiseRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        let dt = 1. / scope.sample_rate as f64;
+        let next_block_time = scope.current_time + dt * RENDER_QUANTUM_SIZE as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if start_time >= next_block_time {
+            output.make_silent();
+            // keep alive until started, unless the control handle is gone
+            return !self.scheduler.is_abandoned();
+        }
+
+        output.force_mono();
+
+        let sample_rate = scope.sample_rate as f64;
+        let start_index = frame_index(start_time, scope.current_time, sample_rate);
+        let stop_index = frame_index(stop_time, scope.current_time, sample_rate);
+
+        let type_ = self.type_.load(Ordering::SeqCst).into();
+        let gain = params.get(&self.gain);
+
+        output
+            .channel_data_mut(0)
+            .iter_mut()
+            .zip(gain.iter().cycle())
+            .enumerate()
+            .for_each(|(index, (o, &g))| {
+                if index < start_index || index >= stop_index {
+                    *o = 0.;
+                } else {
+                    let value = match type_ {
+                        NoiseType::White => self.generate_white(),
+                        NoiseType::Pink => self.generate_pink(),
+                        NoiseType::Brownian => self.generate_brownian(),
+                    };
+                    *o = g * value;
+                }
+            });
+
+        // tail_time false when output has ended this quantum
+        let still_running = stop_time >= next_block_time;
+
+        if !still_running && !self.ended_triggered {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+        }
+
+        still_running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::*;
+
+    fn render_noise(options: NoiseOptions) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, 48000, 48000.);
+
+        let noise = NoiseNode::new(&context, options);
+        noise.connect(&context.destination());
+        noise.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_white_noise() {
+        let samples = render_noise(NoiseOptions::default());
+
+        assert!(samples.iter().all(|s| (-1. ..1.).contains(s)));
+
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert_float_eq!(mean, 0., abs <= 0.02);
+
+        // variance of a uniform distribution in [-1, 1] is 1/3
+        let variance = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+        assert_float_eq!(variance, 1. / 3., abs <= 0.02);
+    }
+
+    #[test]
+    fn test_colored_noise_range() {
+        for type_ in [NoiseType::Pink, NoiseType::Brownian] {
+            let options = NoiseOptions {
+                type_,
+                seed: Some(1),
+                ..NoiseOptions::default()
+            };
+            let samples = render_noise(options);
+
+            assert!(samples.iter().any(|s| *s != 0.));
+            assert!(samples.iter().all(|s| s.abs() <= 1.5));
+        }
+    }
+
+    #[test]
+    fn test_seed_is_reproducible() {
+        for type_ in [NoiseType::White, NoiseType::Pink, NoiseType::Brownian] {
+            let options = NoiseOptions {
+                type_,
+                seed: Some(42),
+                ..NoiseOptions::default()
+            };
+            let a = render_noise(options.clone());
+            let b = render_noise(options);
+            assert_float_eq!(a[..], b[..], abs_all <= 0.);
+
+            let options = NoiseOptions {
+                type_,
+                seed: Some(43),
+                ..NoiseOptions::default()
+            };
+            let c = render_noise(options);
+            assert!(a.iter().zip(c.iter()).any(|(a, c)| a != c));
+        }
+    }
+
+    #[test]
+    fn test_gain_and_start_stop() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 128 * 3, sample_rate);
+
+        let options = NoiseOptions {
+            gain: 0.5,
+            ..NoiseOptions::default()
+        };
+        let noise = NoiseNode::new(&context, options);
+        noise.connect(&context.destination());
+        noise.start_at(128. / f64::from(sample_rate));
+        noise.stop_at(256. / f64::from(sample_rate));
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        assert_float_eq!(channel[..128], [0.; 128][..], abs_all <= 0.);
+        assert!(channel[128..256].iter().any(|s| *s != 0.));
+        assert!(channel[128..256].iter().all(|s| s.abs() <= 0.5));
+        assert_float_eq!(channel[256..], [0.; 128][..], abs_all <= 0.);
+    }
+}
@@ -4,15 +4,17 @@ use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
-use crate::control::Scheduler;
+use crate::control::{frame_index, Scheduler};
 use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
 use crate::periodic_wave::PeriodicWave;
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+#[cfg(feature = "oscillator-ext")]
+use crate::AtomicF64;
 use crate::RENDER_QUANTUM_SIZE;
 
 use super::{
-    AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions, SINETABLE,
-    TABLE_LENGTH_USIZE,
+    AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions, MAX_DETUNE,
+    SINETABLE, TABLE_LENGTH_USIZE,
 };
 
 /// Options for constructing an [`OscillatorNode`]
@@ -32,6 +34,9 @@ pub struct OscillatorOptions {
     pub detune: f32,
     /// Optionnal custom waveform, if specified (set `type` to "custom")
     pub periodic_wave: Option<PeriodicWave>,
+    /// Phase offset of the waveform in cycles (non-standard extension)
+    #[cfg(feature = "oscillator-ext")]
+    pub phase: f64,
     /// channel config options
     pub channel_config: ChannelConfigOptions,
 }
@@ -43,6 +48,8 @@ impl Default for OscillatorOptions {
             frequency: 440.,
             detune: 0.,
             periodic_wave: None,
+            #[cfg(feature = "oscillator-ext")]
+            phase: 0.,
             channel_config: ChannelConfigOptions::default(),
         }
     }
@@ -105,6 +112,13 @@ impl From<u32> for OscillatorType {
 /// osc.start();
 /// ```
 ///
+/// # Extensions
+///
+/// With the non-standard `oscillator-ext` feature enabled, the oscillator exposes
+/// a phase offset (see `OscillatorNode::set_phase`) and has a single input used
+/// for hard-sync: the phase of the oscillator is reset on each rising zero
+/// crossing of the first channel of the input signal.
+///
 /// # Examples
 ///
 /// - `cargo run --release --example oscillators`
@@ -126,6 +140,9 @@ pub struct OscillatorNode {
     scheduler: Scheduler,
     /// channel between control and renderer parts (sender part)
     sender: Sender<PeriodicWave>,
+    /// Phase offset of the waveform in cycles
+    #[cfg(feature = "oscillator-ext")]
+    phase: Arc<AtomicF64>,
 }
 
 impl AudioNode for OscillatorNode {
@@ -137,9 +154,14 @@ impl AudioNode for OscillatorNode {
         &self.channel_config
     }
 
-    /// `OscillatorNode` is a source node. A source node is by definition with no input
+    /// `OscillatorNode` is a source node. A source node is by definition with no input,
+    /// except for the hard-sync input of the `oscillator-ext` feature
     fn number_of_inputs(&self) -> usize {
-        0
+        if cfg!(feature = "oscillator-ext") {
+            1
+        } else {
+            0
+        }
     }
 
     /// `OscillatorNode` is a mono source node.
@@ -186,6 +208,8 @@ impl OscillatorNode {
                 detune,
                 channel_config,
                 periodic_wave,
+                #[cfg(feature = "oscillator-ext")]
+                phase,
             } = options;
 
             // frequency audio parameter
@@ -200,8 +224,8 @@ impl OscillatorNode {
 
             // detune audio parameter
             let det_param_opts = AudioParamDescriptor {
-                min_value: -153_600.,
-                max_value: 153_600.,
+                min_value: -MAX_DETUNE,
+                max_value: MAX_DETUNE,
                 default_value: 0.,
                 automation_rate: AutomationRate::A,
             };
@@ -209,6 +233,8 @@ impl OscillatorNode {
             det_param.set_value(detune);
 
             let type_ = Arc::new(AtomicU32::new(type_ as u32));
+            #[cfg(feature = "oscillator-ext")]
+            let phase = Arc::new(AtomicF64::new(phase));
 
             let scheduler = Scheduler::new();
             let (sender, receiver) = crossbeam_channel::bounded(1);
@@ -223,6 +249,10 @@ impl OscillatorNode {
                 started: false,
                 periodic_wave: None,
                 ended_triggered: false,
+                #[cfg(feature = "oscillator-ext")]
+                phase_offset: phase.clone(),
+                #[cfg(feature = "oscillator-ext")]
+                last_sync_sample: 0.,
             };
 
             let node = Self {
@@ -233,6 +263,8 @@ impl OscillatorNode {
                 type_,
                 scheduler,
                 sender,
+                #[cfg(feature = "oscillator-ext")]
+                phase,
             };
 
             // if periodic wave has been given, init it
@@ -298,6 +330,10 @@ impl OscillatorNode {
     ///
     /// Calling this sets the oscillator type to `custom`, once set to `custom`
     /// the oscillator cannot be reverted back to a standard waveform.
+    ///
+    /// # Panics
+    ///
+    /// if the renderer of the node has been dropped, e.g. after the context has been closed
     pub fn set_periodic_wave(&self, periodic_wave: PeriodicWave) {
         self.type_
             .store(OscillatorType::Custom as u32, Ordering::SeqCst);
@@ -306,6 +342,35 @@ impl OscillatorNode {
             .send(periodic_wave)
             .expect("Sending periodic wave to the node renderer failed");
     }
+
+    pub(crate) fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    /// Returns the phase offset of the waveform, in cycles (non-standard extension)
+    #[cfg(feature = "oscillator-ext")]
+    #[must_use]
+    pub fn phase(&self) -> f64 {
+        self.phase.load()
+    }
+
+    /// Set the phase offset of the waveform, in cycles (non-standard extension)
+    ///
+    /// An offset of `0.25` renders a sine oscillator as a cosine. The offset is
+    /// applied on top of the running phase, so it can be changed while playing.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the offset is not finite
+    #[cfg(feature = "oscillator-ext")]
+    pub fn set_phase(&self, phase: f64) {
+        assert!(
+            phase.is_finite(),
+            "RangeError - phase offset must be finite, got {:?}",
+            phase
+        );
+        self.phase.store(phase);
+    }
 }
 
 /// Rendering component of the oscillator node
@@ -328,12 +393,19 @@ struct OscillatorRenderer {
     periodic_wave: Option<PeriodicWave>,
     /// defines if the `ended` events was already dispatched
     ended_triggered: bool,
+    /// phase offset of the waveform in cycles
+    #[cfg(feature = "oscillator-ext")]
+    phase_offset: Arc<AtomicF64>,
+    /// last sample of the sync input, to detect rising zero crossings
+    #[cfg(feature = "oscillator-ext")]
+    last_sync_sample: f32,
 }
 
 impl AudioProcessor for OscillatorRenderer {
+    #[cfg_attr(not(feature = "oscillator-ext"), allow(unused_variables))]
     fn process(
         &mut self,
-        _inputs: &[AudioRenderQuantum],
+        inputs: &[AudioRenderQuantum],
         outputs: &mut [AudioRenderQuantum],
         params: AudioParamValues,
         scope: &RenderScope,
@@ -358,7 +430,8 @@ impl AudioProcessor for OscillatorRenderer {
 
         if start_time >= next_block_time {
             output.make_silent();
-            return true;
+            // keep alive until started, unless the control handle is gone
+            return !self.scheduler.is_abandoned();
         } else if stop_time < scope.current_time {
             output.make_silent();
 
@@ -377,36 +450,75 @@ impl AudioProcessor for OscillatorRenderer {
         let frequency_values = params.get(&self.frequency);
         let detune_values = params.get(&self.detune);
 
-        let mut current_time = scope.current_time;
-
         // Prevent scheduling in the past
         //
         // [spec] If 0 is passed in for this value or if the value is less than
         // currentTime, then the sound will start playing immediately
         // cf. https://webaudio.github.io/web-audio-api/#dom-audioscheduledsourcenode-start-when-when
-        if !self.started && start_time < current_time {
-            start_time = current_time;
+        if !self.started && start_time < scope.current_time {
+            start_time = scope.current_time;
         }
 
+        let start_index = frame_index(start_time, scope.current_time, sample_rate);
+        let stop_index = frame_index(stop_time, scope.current_time, sample_rate);
+
+        // [spec] computedOscFrequency(t) = frequency(t) * pow(2, detune(t) / 1200)
+        // with a nominal range of [-Nyquist, Nyquist]
+        let nyquist = (sample_rate / 2.) as f32;
+        let compute_frequency = |frequency: f32, detune: f32| {
+            (frequency * (detune / 1200.).exp2()).clamp(-nyquist, nyquist)
+        };
+
+        // a-rate params are consumed per sample, only compute the frequency once
+        // if both params are constant during this block
+        let constant_frequency = if frequency_values.len() == 1 && detune_values.len() == 1 {
+            Some(compute_frequency(frequency_values[0], detune_values[0]))
+        } else {
+            None
+        };
+
+        #[cfg(feature = "oscillator-ext")]
+        let phase_offset = self.phase_offset.load();
+        #[cfg(not(feature = "oscillator-ext"))]
+        let phase_offset = 0.;
+
+        // hard-sync on the rising zero crossings of the first input channel
+        #[cfg(feature = "oscillator-ext")]
+        let sync_channel = if inputs[0].is_silent() {
+            self.last_sync_sample = 0.;
+            None
+        } else {
+            Some(inputs[0].channel_data(0))
+        };
+
         channel_data
             .iter_mut()
             .zip(frequency_values.iter().cycle())
             .zip(detune_values.iter().cycle())
-            .for_each(|((o, &frequency), &detune)| {
-                if current_time < start_time || current_time >= stop_time {
-                    *o = 0.;
-                    current_time += dt;
+            .enumerate()
+            .for_each(|(index, ((o, &frequency), &detune))| {
+                #[cfg(feature = "oscillator-ext")]
+                if let Some(sync_channel) = sync_channel {
+                    let sync_sample = sync_channel[index];
+                    if self.last_sync_sample <= 0. && sync_sample > 0. {
+                        self.phase = 0.;
+                    }
+                    self.last_sync_sample = sync_sample;
+                }
 
+                if index < start_index || index >= stop_index {
+                    *o = 0.;
                     return;
                 }
 
-                // @todo: we could avoid recompute that if both param lengths are 1
-                let computed_frequency = frequency * (detune / 1200.).exp2();
+                let computed_frequency =
+                    constant_frequency.unwrap_or_else(|| compute_frequency(frequency, detune));
 
                 // first sample to render
                 if !self.started {
                     // if start time was between last frame and current frame
                     // we need to adjust the phase first
+                    let current_time = scope.current_time + index as f64 * dt;
                     if current_time > start_time {
                         let phase_incr = computed_frequency as f64 / sample_rate;
                         let ratio = (current_time - start_time) / dt;
@@ -417,21 +529,20 @@ impl AudioProcessor for OscillatorRenderer {
                 }
 
                 let phase_incr = computed_frequency as f64 / sample_rate;
+                let phase = Self::unroll_phase(self.phase + phase_offset);
 
                 // @note: per spec all default oscillators should be rendered from a
                 // wavetable, define if it worth the assle...
                 // e.g. for now `generate_sine` and `generate_custom` are almost the sames
                 // cf. https://webaudio.github.io/web-audio-api/#oscillator-coefficients
                 *o = match type_ {
-                    OscillatorType::Sine => self.generate_sine(),
-                    OscillatorType::Sawtooth => self.generate_sawtooth(phase_incr),
-                    OscillatorType::Square => self.generate_square(phase_incr),
-                    OscillatorType::Triangle => self.generate_triangle(),
-                    OscillatorType::Custom => self.generate_custom(),
+                    OscillatorType::Sine => Self::generate_sine(phase),
+                    OscillatorType::Sawtooth => Self::generate_sawtooth(phase, phase_incr),
+                    OscillatorType::Square => Self::generate_square(phase, phase_incr),
+                    OscillatorType::Triangle => Self::generate_triangle(phase, phase_incr),
+                    OscillatorType::Custom => self.generate_custom(phase),
                 };
 
-                current_time += dt;
-
                 self.phase = Self::unroll_phase(self.phase + phase_incr);
             });
 
@@ -441,8 +552,8 @@ impl AudioProcessor for OscillatorRenderer {
 
 impl OscillatorRenderer {
     #[inline]
-    fn generate_sine(&mut self) -> f32 {
-        let position = self.phase * TABLE_LENGTH_USIZE as f64;
+    fn generate_sine(phase: f64) -> f32 {
+        let position = phase * TABLE_LENGTH_USIZE as f64;
         let floored = position.floor();
 
         let prev_index = floored as usize;
@@ -457,9 +568,9 @@ impl OscillatorRenderer {
     }
 
     #[inline]
-    fn generate_sawtooth(&mut self, phase_incr: f64) -> f32 {
+    fn generate_sawtooth(phase: f64, phase_incr: f64) -> f32 {
         // offset phase to start at 0. (not -1.)
-        let phase = Self::unroll_phase(self.phase + 0.5);
+        let phase = Self::unroll_phase(phase + 0.5);
         let mut sample = 2.0 * phase - 1.0;
         sample -= Self::poly_blep(phase, phase_incr, cfg!(test));
 
@@ -467,19 +578,19 @@ impl OscillatorRenderer {
     }
 
     #[inline]
-    fn generate_square(&mut self, phase_incr: f64) -> f32 {
-        let mut sample = if self.phase < 0.5 { 1.0 } else { -1.0 };
-        sample += Self::poly_blep(self.phase, phase_incr, cfg!(test));
+    fn generate_square(phase: f64, phase_incr: f64) -> f32 {
+        let mut sample = if phase < 0.5 { 1.0 } else { -1.0 };
+        sample += Self::poly_blep(phase, phase_incr, cfg!(test));
 
-        let shift_phase = Self::unroll_phase(self.phase + 0.5);
+        let shift_phase = Self::unroll_phase(phase + 0.5);
         sample -= Self::poly_blep(shift_phase, phase_incr, cfg!(test));
 
         sample as f32
     }
 
     #[inline]
-    fn generate_triangle(&mut self) -> f32 {
-        let mut sample = -4. * self.phase + 2.;
+    fn generate_triangle(phase: f64, phase_incr: f64) -> f32 {
+        let mut sample = -4. * phase + 2.;
 
         if sample > 1. {
             sample = 2. - sample;
@@ -487,13 +598,21 @@ impl OscillatorRenderer {
             sample = -2. - sample;
         }
 
+        // the slope changes by -8 at the peak (phase 0.25) and by +8 at the
+        // trough (phase 0.75), scaled to a slope change per sample
+        let slope_change = 8. * phase_incr.abs();
+        let peak_phase = Self::unroll_phase(phase + 0.75);
+        sample -= slope_change * Self::poly_blamp(peak_phase, phase_incr, cfg!(test));
+        let trough_phase = Self::unroll_phase(phase + 0.25);
+        sample += slope_change * Self::poly_blamp(trough_phase, phase_incr, cfg!(test));
+
         sample as f32
     }
 
     #[inline]
-    fn generate_custom(&mut self) -> f32 {
+    fn generate_custom(&self, phase: f64) -> f32 {
         let periodic_wave = self.periodic_wave.as_ref().unwrap().as_slice();
-        let position = self.phase * TABLE_LENGTH_USIZE as f64;
+        let position = phase * TABLE_LENGTH_USIZE as f64;
         let floored = position.floor();
 
         let prev_index = floored as usize;
@@ -516,6 +635,9 @@ impl OscillatorRenderer {
     // @note: do not apply in tests so we can avoid relying on snapshots
     #[inline]
     fn poly_blep(mut t: f64, dt: f64, is_test: bool) -> f64 {
+        // the phase runs backwards for negative frequencies
+        let dt = dt.abs();
+
         if is_test {
             0.
         } else if t < dt {
@@ -529,13 +651,35 @@ impl OscillatorRenderer {
         }
     }
 
+    // computes the `polyBLAMP` corrections to apply to aliasing signal
+    // `polyBLAMP` stands for `polyBandLimitedrAMP`, i.e. the integrated `polyBLEP`
+    // This softens the sharp corners of the triangle signal, the returned residual
+    // is for a slope change of 1 per sample.
+    // cf. Esqueda, Välimäki, Bilbao - Rounding Corners with BLAMP (DAFx-16)
+    //
+    // @note: do not apply in tests so we can avoid relying on snapshots
     #[inline]
-    fn unroll_phase(mut phase: f64) -> f64 {
-        if phase >= 1. {
-            phase -= 1.
+    fn poly_blamp(mut t: f64, dt: f64, is_test: bool) -> f64 {
+        // the phase runs backwards for negative frequencies
+        let dt = dt.abs();
+
+        if is_test {
+            0.
+        } else if t < dt {
+            t = 1. - t / dt;
+            t * t * t / 6.
+        } else if t > 1.0 - dt {
+            t = (t - 1.0) / dt + 1.;
+            t * t * t / 6.
+        } else {
+            0.0
         }
+    }
 
-        phase
+    #[inline]
+    fn unroll_phase(phase: f64) -> f64 {
+        // handles negative frequencies, i.e. a phase running backwards
+        phase - phase.floor()
     }
 }
 
@@ -716,6 +860,101 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sine_a_rate_frequency() {
+        let sample_rate = 44_100;
+        let length = 1_000;
+        let end_time = length as f64 / sample_rate as f64;
+
+        let context = OfflineAudioContext::new(1, length, sample_rate as f32);
+
+        // frequency glides from 440 to 880 Hz, detune from 0 to 1200 cents
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.frequency()
+            .set_value_at_time(440., 0.)
+            .linear_ramp_to_value_at_time(880., end_time);
+        osc.detune()
+            .set_value_at_time(0., 0.)
+            .linear_ramp_to_value_at_time(1200., end_time);
+        osc.start_at(0.);
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        let mut expected = Vec::<f32>::with_capacity(length);
+        let mut phase: f64 = 0.;
+
+        for i in 0..length {
+            expected.push((phase * 2. * PI).sin() as f32);
+
+            // phase increment is recomputed for each sample
+            let ratio = i as f64 / length as f64;
+            let frequency = (440. + 440. * ratio) * (ratio).exp2();
+            phase += frequency / sample_rate as f64;
+        }
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-3);
+    }
+
+    #[test]
+    fn sine_a_rate_modulation() {
+        let sample_rate = 44_100;
+        let length = 1_000;
+
+        let context = OfflineAudioContext::new(1, length, sample_rate as f32);
+
+        // 30 Hz vibrato with 100 Hz depth
+        let lfo = context.create_oscillator();
+        lfo.frequency().set_value(30.);
+        let depth = context.create_gain();
+        depth.gain().set_value(100.);
+        lfo.connect(&depth);
+        lfo.start_at(0.);
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        depth.connect(osc.frequency());
+        osc.start_at(0.);
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        let mut expected = Vec::<f32>::with_capacity(length);
+        let mut phase: f64 = 0.;
+
+        for i in 0..length {
+            expected.push((phase * 2. * PI).sin() as f32);
+
+            let t = i as f64 / sample_rate as f64;
+            let frequency = 440. + 100. * (2. * PI * 30. * t).sin();
+            phase += frequency / sample_rate as f64;
+        }
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-3);
+    }
+
+    #[test]
+    fn computed_frequency_is_clamped() {
+        let sample_rate = 44_100;
+        let length = 128;
+
+        let context = OfflineAudioContext::new(1, length, sample_rate as f32);
+
+        // two octaves above 20 kHz is clamped to Nyquist
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.frequency().set_value(20_000.);
+        osc.detune().set_value(2400.);
+        osc.start_at(0.);
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        // at Nyquist the phase alternates between 0 and 0.5, i.e. a sine is silent
+        assert_float_eq!(result[..], &[0.; 128][..], abs_all <= 1e-6);
+    }
+
     #[test]
     fn square_raw() {
         // 1, 10, 100, 1_000, 10_000 Hz
@@ -981,6 +1220,212 @@ mod tests {
         }
     }
 
+    #[test]
+    fn polyblamp_isolated() {
+        let dt = 0.125;
+
+        // residual is symmetric around the corner and vanishes after one sample
+        assert_float_eq!(
+            OscillatorRenderer::poly_blamp(0., dt, false),
+            1. / 6.,
+            abs <= 1e-12
+        );
+        assert_float_eq!(
+            OscillatorRenderer::poly_blamp(0.0625, dt, false),
+            OscillatorRenderer::poly_blamp(1. - 0.0625, dt, false),
+            abs <= 1e-12
+        );
+        assert_float_eq!(
+            OscillatorRenderer::poly_blamp(0.125, dt, false),
+            0.,
+            abs <= 0.
+        );
+        assert_float_eq!(
+            OscillatorRenderer::poly_blamp(0.5, dt, false),
+            0.,
+            abs <= 0.
+        );
+        assert_float_eq!(
+            OscillatorRenderer::poly_blamp(0.875, dt, false),
+            0.,
+            abs <= 0.
+        );
+
+        // negative frequencies use the same residual
+        assert_float_eq!(
+            OscillatorRenderer::poly_blamp(0.0625, -dt, false),
+            OscillatorRenderer::poly_blamp(0.0625, dt, false),
+            abs <= 0.
+        );
+
+        // triangle with 8 samples per period, peak between two samples:
+        // the corner is rounded symmetrically
+        let len = 8.;
+        let dt = 1. / len;
+        let signal: Vec<f64> = (0..8)
+            .map(|index| {
+                let phase = (index as f64 + 0.5) / len;
+                let mut sample = -4. * phase + 2.;
+                if sample > 1. {
+                    sample = 2. - sample;
+                } else if sample < -1. {
+                    sample = -2. - sample;
+                }
+
+                let slope_change = 8. * dt;
+                sample -=
+                    slope_change * OscillatorRenderer::poly_blamp((phase + 0.75) % 1., dt, false);
+                sample +=
+                    slope_change * OscillatorRenderer::poly_blamp((phase + 0.25) % 1., dt, false);
+                sample
+            })
+            .collect();
+
+        // samples around the peak are lowered by the same amount
+        let correction = 8. * dt * (0.5_f64).powi(3) / 6.;
+        assert_float_eq!(signal[1], 0.75 - correction, abs <= 1e-12);
+        assert_float_eq!(signal[2], 0.75 - correction, abs <= 1e-12);
+        assert_float_eq!(signal[5], -0.75 + correction, abs <= 1e-12);
+        assert_float_eq!(signal[6], -0.75 + correction, abs <= 1e-12);
+        // samples far from the corners are left untouched
+        assert_float_eq!(signal[0], 0.25, abs <= 1e-12);
+        assert_float_eq!(signal[3], 0.25, abs <= 1e-12);
+    }
+
+    #[test]
+    fn sine_negative_frequency() {
+        let sample_rate = 44_100;
+        let freq = -1_000.;
+
+        let context = OfflineAudioContext::new(1, sample_rate, sample_rate as f32);
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.frequency().set_value(freq);
+        osc.start_at(0.);
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        let expected: Vec<f32> = (0..sample_rate)
+            .map(|i| {
+                let phase = freq as f64 * i as f64 / sample_rate as f64;
+                (phase * 2. * PI).sin() as f32
+            })
+            .collect();
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+    }
+
+    #[cfg(feature = "oscillator-ext")]
+    #[test]
+    fn phase_offset() {
+        let sample_rate = 44_100;
+        let freq = 1_000.;
+
+        let context = OfflineAudioContext::new(1, sample_rate, sample_rate as f32);
+
+        // a sine with a quarter cycle offset is a cosine
+        let options = OscillatorOptions {
+            frequency: freq,
+            phase: 0.25,
+            ..OscillatorOptions::default()
+        };
+        let osc = OscillatorNode::new(&context, options);
+        assert_float_eq!(osc.phase(), 0.25, abs <= 0.);
+        osc.connect(&context.destination());
+        osc.start_at(0.);
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        let expected: Vec<f32> = (0..sample_rate)
+            .map(|i| {
+                let phase = freq as f64 * i as f64 / sample_rate as f64;
+                (phase * 2. * PI).cos() as f32
+            })
+            .collect();
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+    }
+
+    #[cfg(feature = "oscillator-ext")]
+    #[test]
+    fn set_phase_offset() {
+        let sample_rate = 44_100;
+        let freq = 1_000.;
+
+        let context = OfflineAudioContext::new(1, 256, sample_rate as f32);
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(freq);
+        osc.connect(&context.destination());
+        osc.start_at(0.);
+
+        // negative offsets wrap around, i.e. the waveform is inverted
+        osc.set_phase(-0.5);
+        assert_float_eq!(osc.phase(), -0.5, abs <= 0.);
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        let expected: Vec<f32> = (0..256)
+            .map(|i| {
+                let phase = freq as f64 * i as f64 / sample_rate as f64;
+                -(phase * 2. * PI).sin() as f32
+            })
+            .collect();
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+    }
+
+    #[cfg(feature = "oscillator-ext")]
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn invalid_phase_offset() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let osc = context.create_oscillator();
+        osc.set_phase(f64::NAN);
+    }
+
+    #[cfg(feature = "oscillator-ext")]
+    #[test]
+    fn hard_sync() {
+        let sample_rate = 44_100;
+        let freq = 1_000.;
+        let sync_index = 100;
+
+        let context = OfflineAudioContext::new(1, 256, sample_rate as f32);
+
+        // sync signal crosses zero upwards at `sync_index`
+        let sync = context.create_constant_source();
+        sync.offset().set_value_at_time(-1., 0.);
+        sync.offset()
+            .set_value_at_time(1., sync_index as f64 / sample_rate as f64);
+        sync.start();
+
+        let osc = context.create_oscillator();
+        assert_eq!(osc.number_of_inputs(), 1);
+        osc.frequency().set_value(freq);
+        sync.connect(&osc);
+        osc.connect(&context.destination());
+        osc.start_at(0.);
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        // the phase is reset on the rising zero crossing only
+        let expected: Vec<f32> = (0..256)
+            .map(|i| {
+                let frame = if i < sync_index { i } else { i - sync_index };
+                let phase = freq as f64 * frame as f64 / sample_rate as f64;
+                (phase * 2. * PI).sin() as f32
+            })
+            .collect();
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+    }
+
     #[test]
     fn osc_sub_quantum_start() {
         let freq = 1.25;
@@ -1,6 +1,8 @@
+use std::error::Error;
 use std::f32::consts::PI;
-use std::sync::atomic::{AtomicU8, Ordering};
-use std::sync::Arc;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
 use crate::param::{AudioParam, AudioParamDescriptor};
@@ -53,6 +55,58 @@ impl From<u8> for DistanceModelType {
     }
 }
 
+/// Set of head related impulse responses used by the [`PanningModelType::HRTF`] panning model
+///
+/// A dataset can be shared by several [`PannerNode`]s, see [`PannerNode::set_hrtf_dataset`].
+/// When no dataset is provided, the panner uses the IRCAM `IRC_1003_C` subject shipped
+/// with this library.
+#[derive(Clone)]
+pub struct HrtfDataset {
+    sphere: Arc<HrirSphere>,
+    sample_rate: f32,
+}
+
+impl std::fmt::Debug for HrtfDataset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HrtfDataset")
+            .field("sample_rate", &self.sample_rate)
+            .field("length", &self.sphere.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl HrtfDataset {
+    /// Load a dataset from a HRIR sphere (the `.bin` format of the `hrtf` crate), resampled to
+    /// the given sample rate
+    ///
+    /// # Errors
+    ///
+    /// This method returns an Error if the data cannot be read or is not a valid HRIR sphere
+    pub fn from_reader<R: Read>(
+        reader: R,
+        sample_rate: f32,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let sphere = HrirSphere::new(reader, sample_rate as u32)
+            .map_err(|e| format!("Invalid HRIR sphere: {:?}", e))?;
+
+        Ok(Self {
+            sphere: Arc::new(sphere),
+            sample_rate,
+        })
+    }
+
+    /// Dataset shipped with this library
+    fn builtin(sample_rate: f32) -> Self {
+        let resource = include_bytes!("../../resources/IRC_1003_C.bin");
+        Self::from_reader(&resource[..], sample_rate).unwrap()
+    }
+
+    /// Sample rate of the impulse responses
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+}
+
 /// Options for constructing a [`PannerNode`]
 // dictionary PannerOptions : AudioNodeOptions {
 //   PanningModelType panningModel = "equalpower";
@@ -86,6 +140,13 @@ pub struct PannerOptions {
     pub cone_inner_angle: f64,
     pub cone_outer_angle: f64,
     pub cone_outer_gain: f64,
+    /// Apply a Doppler shift when the source or the listener moves (non-standard)
+    pub doppler: bool,
+    /// Speed of sound used by the Doppler shift, in units per second (non-standard)
+    pub speed_of_sound: f64,
+    /// Impulse responses used by the HRTF panning model, the builtin dataset if `None`
+    /// (non-standard)
+    pub hrtf_dataset: Option<HrtfDataset>,
     pub channel_config: ChannelConfigOptions,
 }
 
@@ -106,6 +167,9 @@ impl Default for PannerOptions {
             cone_inner_angle: 360.,
             cone_outer_angle: 360.,
             cone_outer_gain: 0.,
+            doppler: false,
+            speed_of_sound: 343.3,
+            hrtf_dataset: None,
             channel_config: ChannelConfigOptions {
                 count: 2,
                 count_mode: ChannelCountMode::ClampedMax,
@@ -145,6 +209,122 @@ fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
     }
 }
 
+/// Assert that the reference distance is valid for the PannerNode
+///
+/// # Panics
+///
+/// This function panics if given value is negative
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_ref_distance(value: f64) {
+    if value.is_nan() || value < 0. {
+        panic!(
+            "RangeError - Invalid refDistance: {:?} should not be negative",
+            value
+        );
+    }
+}
+
+/// Assert that the maximum distance is valid for the PannerNode
+///
+/// # Panics
+///
+/// This function panics if given value is not strictly positive
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_max_distance(value: f64) {
+    if value.is_nan() || value <= 0. {
+        panic!(
+            "RangeError - Invalid maxDistance: {:?} should be strictly positive",
+            value
+        );
+    }
+}
+
+/// Assert that the rolloff factor is valid for the PannerNode
+///
+/// # Panics
+///
+/// This function panics if given value is negative
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_rolloff_factor(value: f64) {
+    if value.is_nan() || value < 0. {
+        panic!(
+            "RangeError - Invalid rolloffFactor: {:?} should not be negative",
+            value
+        );
+    }
+}
+
+/// Assert that the cone outer gain is valid for the PannerNode
+///
+/// # Panics
+///
+/// This function panics if given value is not in the range `[0, 1]`
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_cone_outer_gain(value: f64) {
+    if !(0. ..=1.).contains(&value) {
+        panic!(
+            "InvalidStateError - Invalid coneOuterGain: {:?} should be in the range [0, 1]",
+            value
+        );
+    }
+}
+
+/// Gain applied to a source at the given distance of the listener
+/// see <https://webaudio.github.io/web-audio-api/#Spatialization-distance-effects>
+fn distance_gain(
+    distance_model: DistanceModelType,
+    distance: f64,
+    ref_distance: f64,
+    max_distance: f64,
+    rolloff_factor: f64,
+) -> f64 {
+    match distance_model {
+        DistanceModelType::Linear => {
+            // the nominal range of the rolloff factor is [0, 1] for the linear model
+            let rolloff_factor = rolloff_factor.clamp(0., 1.);
+            let d2ref = ref_distance.min(max_distance);
+            let d2max = ref_distance.max(max_distance);
+
+            if d2ref == d2max {
+                return 1. - rolloff_factor;
+            }
+
+            let d_clamped = distance.clamp(d2ref, d2max);
+            1. - rolloff_factor * (d_clamped - d2ref) / (d2max - d2ref)
+        }
+        DistanceModelType::Inverse => {
+            let denominator =
+                ref_distance + rolloff_factor * (ref_distance.max(distance) - ref_distance);
+
+            if distance > 0. && denominator > 0. {
+                ref_distance / denominator
+            } else {
+                1.
+            }
+        }
+        DistanceModelType::Exponential => {
+            let distance = distance.max(ref_distance);
+
+            if distance > 0. {
+                (distance / ref_distance).powf(-rolloff_factor)
+            } else {
+                1.
+            }
+        }
+    }
+}
+
+/// Length (in samples) of the crossfade between two HRTF datasets
+const HRTF_CROSSFADE_LENGTH: usize = 4 * RENDER_QUANTUM_SIZE;
+
 /// Internal state of the HRTF renderer
 struct HrtfState {
     len: usize,
@@ -215,6 +395,68 @@ impl HrtfState {
     }
 }
 
+/// Maximum propagation delay of the Doppler delay line, sources further away than
+/// `speed_of_sound * MAX_DOPPLER_DELAY_TIME` are delayed as if they were at that distance
+const MAX_DOPPLER_DELAY_TIME: f64 = 1.;
+
+/// Maximum change of the propagation delay per sample, which limits the relative velocity
+/// of the source and the listener to half the speed of sound
+const MAX_DOPPLER_DELAY_CHANGE: f64 = 0.5;
+
+/// Internal state of the Doppler shift
+///
+/// The signal is delayed by the propagation time between the source and the listener, so that
+/// changes in their distance stretch or compress the signal as a moving source would.
+struct DopplerState {
+    buffer: Vec<f32>,
+    write_index: usize,
+    /// propagation delay (in samples) at the end of the previous render quantum
+    delay: Option<f64>,
+}
+
+impl DopplerState {
+    fn new(sample_rate: f32) -> Self {
+        let len = (MAX_DOPPLER_DELAY_TIME * sample_rate as f64).ceil() as usize + 2;
+
+        Self {
+            buffer: vec![0.; len],
+            write_index: 0,
+            delay: None,
+        }
+    }
+
+    fn process(&mut self, signal: &mut [f32], target_delay: f64) {
+        let len = self.buffer.len();
+        let target_delay = target_delay.max(0.).min((len - 2) as f64);
+
+        // glide from the previous delay, jumps in position would otherwise click
+        let start_delay = self.delay.unwrap_or(target_delay);
+        let max_change = MAX_DOPPLER_DELAY_CHANGE * signal.len() as f64;
+        let end_delay = start_delay + (target_delay - start_delay).clamp(-max_change, max_change);
+        let delay_incr = (end_delay - start_delay) / signal.len() as f64;
+
+        signal.iter_mut().enumerate().for_each(|(i, s)| {
+            self.buffer[self.write_index] = *s;
+
+            let delay = start_delay + delay_incr * (i + 1) as f64;
+            let position = (self.write_index as f64 - delay).rem_euclid(len as f64);
+            let prev_index = position.floor() as usize % len;
+            let next_index = (prev_index + 1) % len;
+            let k = (position - position.floor()) as f32;
+
+            *s = (1. - k).mul_add(self.buffer[prev_index], k * self.buffer[next_index]);
+
+            self.write_index = (self.write_index + 1) % len;
+        });
+
+        self.delay = Some(end_delay);
+    }
+
+    fn tail_time_samples(&self) -> usize {
+        self.delay.unwrap_or(0.).ceil() as usize + 1
+    }
+}
+
 /// `PannerNode` positions / spatializes an incoming audio stream in three-dimensional space.
 ///
 /// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/PannerNode>
@@ -278,9 +520,14 @@ pub struct PannerNode {
     ref_distance: Arc<AtomicF64>,
     max_distance: Arc<AtomicF64>,
     rolloff_factor: Arc<AtomicF64>,
+    speed_of_sound: Arc<AtomicF64>,
     panning_model: AtomicU8,
+    hrtf_dataset: Mutex<Option<HrtfDataset>>,
+    doppler: AtomicBool,
     /// HRTF message bus to the renderer
     sender: Sender<Option<HrtfState>>,
+    /// Doppler message bus to the renderer
+    doppler_sender: Sender<Option<DopplerState>>,
 }
 
 impl AudioNode for PannerNode {
@@ -327,11 +574,22 @@ impl PannerNode {
     ///
     /// * `options.channel_config.count` is greater than 2
     /// * `options.channel_config.mode` is `ChannelCountMode::Max`
+    /// * `options.ref_distance` or `options.rolloff_factor` is negative
+    /// * `options.max_distance` is not strictly positive
+    /// * `options.cone_outer_gain` is not in the range `[0, 1]`
+    /// * `options.hrtf_dataset` sample rate differs from the context sample rate
     ///
     /// Can panic when loading HRIR-sphere
     #[allow(clippy::missing_panics_doc)]
     pub fn new<C: BaseAudioContext>(context: &C, options: PannerOptions) -> Self {
         let node = context.register(move |registration| {
+            assert_valid_channel_count_mode(options.channel_config.count_mode);
+            assert_valid_channel_count(options.channel_config.count);
+            assert_valid_ref_distance(options.ref_distance);
+            assert_valid_max_distance(options.max_distance);
+            assert_valid_rolloff_factor(options.rolloff_factor);
+            assert_valid_cone_outer_gain(options.cone_outer_gain);
+
             use crate::spatial::PARAM_OPTS;
             // position params
             let (position_x, render_px) = context.create_audio_param(PARAM_OPTS, &registration);
@@ -365,9 +623,15 @@ impl PannerNode {
             let cone_outer_angle = Arc::new(AtomicF64::new(options.cone_outer_angle));
             let cone_outer_gain = Arc::new(AtomicF64::new(options.cone_outer_gain));
 
-            // Channel to send a HRTF processor to the renderer.  A capacity of 1 suffices, it will
-            // simply block the control thread when used concurrently
-            let (sender, receiver) = crossbeam_channel::bounded(1);
+            // doppler attributes
+            let speed_of_sound = Arc::new(AtomicF64::new(options.speed_of_sound));
+
+            // Channel to send a HRTF processor to the renderer. Changing the panning model or the
+            // HRTF dataset must not block, so only the latest message is used by the renderer.
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            // Channel to send the Doppler delay line to the renderer. Toggling the Doppler shift
+            // must not block, so only the latest message is used by the renderer.
+            let (doppler_sender, doppler_receiver) = crossbeam_channel::unbounded();
 
             let render = PannerRenderer {
                 position_x: render_px,
@@ -383,8 +647,12 @@ impl PannerNode {
                 cone_inner_angle: cone_inner_angle.clone(),
                 cone_outer_angle: cone_outer_angle.clone(),
                 cone_outer_gain: cone_outer_gain.clone(),
+                speed_of_sound: speed_of_sound.clone(),
                 hrtf_state: None,
+                hrtf_fade_out: None,
                 receiver,
+                doppler_state: None,
+                doppler_receiver,
                 tail_time_counter: 0,
             };
 
@@ -409,11 +677,22 @@ impl PannerNode {
                 cone_inner_angle,
                 cone_outer_angle,
                 cone_outer_gain,
+                speed_of_sound,
                 sender,
+                doppler_sender,
                 panning_model: AtomicU8::new(0),
+                hrtf_dataset: Mutex::new(None),
+                doppler: AtomicBool::new(false),
             };
 
+            if let Some(hrtf_dataset) = options.hrtf_dataset {
+                node.set_hrtf_dataset(hrtf_dataset);
+            }
+
             node.set_panning_model(options.panning_model);
+            if options.doppler {
+                node.set_doppler(true);
+            }
 
             // instruct to BaseContext to add the AudioListener if it has not already
             context.base().ensure_audio_listener_present();
@@ -453,6 +732,7 @@ impl PannerNode {
         &self.orientation_z
     }
 
+    /// Algorithm used to reduce the volume of the source as it moves away from the listener
     pub fn distance_model(&self) -> DistanceModelType {
         self.distance_model.load(Ordering::SeqCst).into()
     }
@@ -461,30 +741,55 @@ impl PannerNode {
         self.distance_model.store(value as u8, Ordering::SeqCst);
     }
 
+    /// Reference distance below which the volume is not reduced
     pub fn ref_distance(&self) -> f64 {
         self.ref_distance.load()
     }
 
+    /// Set the reference distance
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is negative
     pub fn set_ref_distance(&self, value: f64) {
+        assert_valid_ref_distance(value);
         self.ref_distance.store(value);
     }
 
+    /// Maximum distance after which the volume is not reduced any further, only used by
+    /// the [`DistanceModelType::Linear`] model
     pub fn max_distance(&self) -> f64 {
         self.max_distance.load()
     }
 
+    /// Set the maximum distance
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not strictly positive
     pub fn set_max_distance(&self, value: f64) {
+        assert_valid_max_distance(value);
         self.max_distance.store(value);
     }
 
+    /// How quickly the volume is reduced as the source moves away from the listener
     pub fn rolloff_factor(&self) -> f64 {
         self.rolloff_factor.load()
     }
 
+    /// Set the rolloff factor, which is clamped to `[0, 1]` by the
+    /// [`DistanceModelType::Linear`] model
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is negative
     pub fn set_rolloff_factor(&self, value: f64) {
+        assert_valid_rolloff_factor(value);
         self.rolloff_factor.store(value);
     }
 
+    /// Angle (in degrees) of the cone, centered on the source orientation, inside of
+    /// which the volume is not reduced
     pub fn cone_inner_angle(&self) -> f64 {
         self.cone_inner_angle.load()
     }
@@ -493,6 +798,8 @@ impl PannerNode {
         self.cone_inner_angle.store(value);
     }
 
+    /// Angle (in degrees) of the cone, centered on the source orientation, outside of
+    /// which the volume is reduced by [`cone_outer_gain`](Self::cone_outer_gain)
     pub fn cone_outer_angle(&self) -> f64 {
         self.cone_outer_angle.load()
     }
@@ -501,11 +808,18 @@ impl PannerNode {
         self.cone_outer_angle.store(value);
     }
 
+    /// Gain applied to the source when the listener is outside of the outer cone
     pub fn cone_outer_gain(&self) -> f64 {
         self.cone_outer_gain.load()
     }
 
+    /// Set the cone outer gain
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not in the range `[0, 1]`
     pub fn set_cone_outer_gain(&self, value: f64) {
+        assert_valid_cone_outer_gain(value);
         self.cone_outer_gain.store(value);
     }
 
@@ -518,17 +832,85 @@ impl PannerNode {
     pub fn set_panning_model(&self, value: PanningModelType) {
         let hrtf_option = match value {
             PanningModelType::EqualPower => None,
-            PanningModelType::HRTF => {
-                let resource = include_bytes!("../../resources/IRC_1003_C.bin");
-                let sample_rate = self.context().sample_rate() as u32;
-                let hrir_sphere = HrirSphere::new(&resource[..], sample_rate).unwrap();
-                Some(HrtfState::new(hrir_sphere))
-            }
+            PanningModelType::HRTF => Some(self.hrtf_state()),
         };
 
         let _ = self.sender.send(hrtf_option); // can fail when render thread shut down
         self.panning_model.store(value as u8, Ordering::SeqCst);
     }
+
+    /// Impulse responses used by the HRTF panning model, `None` for the builtin dataset
+    #[allow(clippy::missing_panics_doc)]
+    pub fn hrtf_dataset(&self) -> Option<HrtfDataset> {
+        self.hrtf_dataset.lock().unwrap().clone()
+    }
+
+    /// Change the impulse responses used by the HRTF panning model
+    ///
+    /// This is a non-standard extension. If the panning model is
+    /// [`PanningModelType::HRTF`], the rendering crossfades from the previous dataset to the
+    /// new one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sample rate of the dataset differs from the context sample rate
+    pub fn set_hrtf_dataset(&self, value: HrtfDataset) {
+        let sample_rate = self.context().sample_rate();
+        if value.sample_rate() != sample_rate {
+            panic!(
+                "NotSupportedError - HRTF dataset sample rate ({:?}) differs from context sample rate ({:?})",
+                value.sample_rate(),
+                sample_rate
+            );
+        }
+
+        *self.hrtf_dataset.lock().unwrap() = Some(value);
+
+        if self.panning_model() == PanningModelType::HRTF {
+            let _ = self.sender.send(Some(self.hrtf_state())); // can fail when render thread shut down
+        }
+    }
+
+    fn hrtf_state(&self) -> HrtfState {
+        let hrtf_dataset = self
+            .hrtf_dataset()
+            .unwrap_or_else(|| HrtfDataset::builtin(self.context().sample_rate()));
+
+        HrtfState::new((*hrtf_dataset.sphere).clone())
+    }
+
+    /// Whether a Doppler shift is applied when the source or the listener moves
+    ///
+    /// This is a non-standard extension, the Doppler shift has been removed from the
+    /// specification.
+    pub fn doppler(&self) -> bool {
+        self.doppler.load(Ordering::SeqCst)
+    }
+
+    /// Toggle the Doppler shift of this panner
+    ///
+    /// When enabled, the signal is delayed by the time the sound takes to travel from the source
+    /// to the listener (up to one second), so that changes in their distance shift the pitch as
+    /// a moving source would. The relative velocity is limited to half the speed of sound.
+    pub fn set_doppler(&self, value: bool) {
+        let doppler_option = if value {
+            Some(DopplerState::new(self.context().sample_rate()))
+        } else {
+            None
+        };
+
+        let _ = self.doppler_sender.send(doppler_option); // can fail when render thread shut down
+        self.doppler.store(value, Ordering::SeqCst);
+    }
+
+    /// Speed of sound used by the Doppler shift, in units per second
+    pub fn speed_of_sound(&self) -> f64 {
+        self.speed_of_sound.load()
+    }
+
+    pub fn set_speed_of_sound(&self, value: f64) {
+        self.speed_of_sound.store(value);
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -553,8 +935,13 @@ struct PannerRenderer {
     cone_inner_angle: Arc<AtomicF64>,
     cone_outer_angle: Arc<AtomicF64>,
     cone_outer_gain: Arc<AtomicF64>,
+    speed_of_sound: Arc<AtomicF64>,
     receiver: Receiver<Option<HrtfState>>,
     hrtf_state: Option<HrtfState>,
+    /// previous HRTF state and the number of samples already crossfaded
+    hrtf_fade_out: Option<(HrtfState, usize)>,
+    doppler_receiver: Receiver<Option<DopplerState>>,
+    doppler_state: Option<DopplerState>,
     tail_time_counter: usize,
 }
 
@@ -564,7 +951,7 @@ impl AudioProcessor for PannerRenderer {
         inputs: &[AudioRenderQuantum],
         outputs: &mut [AudioRenderQuantum],
         params: AudioParamValues,
-        _scope: &RenderScope,
+        scope: &RenderScope,
     ) -> bool {
         // single input/output node
         let input = &inputs[0];
@@ -576,25 +963,64 @@ impl AudioProcessor for PannerRenderer {
         // only handle mono for now (todo issue #44)
         output.mix(1, ChannelInterpretation::Speakers);
 
+        // handle changes in doppler mandated from control thread
+        if let Some(doppler_state) = self.doppler_receiver.try_iter().last() {
+            self.doppler_state = doppler_state;
+        }
+
         // early exit for silence
         if input.is_silent() {
             // HRTF panner has tail time equal to the max length of the impulse response buffers
-            // (12 ms)
-            let tail_time = match &self.hrtf_state {
-                None => false,
-                Some(hrtf_state) => hrtf_state.tail_time_samples() > self.tail_time_counter,
-            };
+            // (12 ms), the Doppler delay line adds the current propagation delay
+            let hrtf_tail_time = self
+                .hrtf_state
+                .as_ref()
+                .map_or(0, HrtfState::tail_time_samples);
+            let doppler_tail_time = self
+                .doppler_state
+                .as_ref()
+                .map_or(0, DopplerState::tail_time_samples);
+            let tail_time = hrtf_tail_time + doppler_tail_time > self.tail_time_counter;
             if !tail_time {
                 return false;
             }
             self.tail_time_counter += RENDER_QUANTUM_SIZE;
+        } else {
+            self.tail_time_counter = 0;
+        }
+
+        // Doppler shift - k-rate, the delay is interpolated within the render quantum
+        if let Some(doppler_state) = &mut self.doppler_state {
+            let source_position = [
+                params.get(&self.position_x)[0],
+                params.get(&self.position_y)[0],
+                params.get(&self.position_z)[0],
+            ];
+            let [listener_position_x, listener_position_y, listener_position_z, ..] =
+                params.listener_params();
+            let listener_position = [
+                listener_position_x[0],
+                listener_position_y[0],
+                listener_position_z[0],
+            ];
+            let distance = crate::spatial::distance(source_position, listener_position) as f64;
+            let delay = distance / self.speed_of_sound.load() * scope.sample_rate as f64;
+
+            // out of range delays (e.g. for a non-positive speed of sound) are
+            // clamped by the delay line
+            doppler_state.process(&mut output.channel_data_mut(0)[..], delay);
         }
 
         // convert mono to identical stereo
         output.mix(2, ChannelInterpretation::Speakers);
 
-        // handle changes in panning_model_type mandated from control thread
-        if let Ok(hrtf_state) = self.receiver.try_recv() {
+        // handle changes in panning_model_type or HRTF dataset mandated from control thread
+        if let Some(hrtf_state) = self.receiver.try_iter().last() {
+            // crossfade when switching between two HRTF datasets
+            self.hrtf_fade_out = match (self.hrtf_state.take(), &hrtf_state) {
+                (Some(prev_hrtf_state), Some(_)) => Some((prev_hrtf_state, 0)),
+                _ => None,
+            };
             self.hrtf_state = hrtf_state;
         }
         // for borrow reasons, take the hrtf_state out of self
@@ -666,7 +1092,8 @@ impl AudioProcessor for PannerRenderer {
             });
 
         if let Some(hrtf_state) = &mut hrtf_state {
-            // HRTF panning - always k-rate so take a single value from the a-rate iter
+            // HRTF panning - always k-rate so take a single value from the a-rate iter, the
+            // HRTF processor crossfades from the previous position over the render quantum
             let SpatialParams {
                 dist_gain,
                 cone_gain,
@@ -687,8 +1114,10 @@ impl AudioProcessor for PannerRenderer {
                 projected_source = [0., 0., 1.];
             }
 
+            // keep a (cheap) copy of the input for the crossfade
+            let input = output.channel_data(0).clone();
             let output_interleaved =
-                hrtf_state.process(output.channel_data(0), new_distance_gain, projected_source);
+                hrtf_state.process(&input[..], new_distance_gain, projected_source);
 
             let [left, right] = output.stereo_mut();
             output_interleaved
@@ -699,6 +1128,28 @@ impl AudioProcessor for PannerRenderer {
                     *l = p.0;
                     *r = p.1;
                 });
+
+            // crossfade from the previous HRTF dataset
+            if let Some((prev_hrtf_state, faded)) = &mut self.hrtf_fade_out {
+                let prev_output_interleaved =
+                    prev_hrtf_state.process(&input[..], new_distance_gain, projected_source);
+
+                prev_output_interleaved
+                    .iter()
+                    .zip(&mut left[..])
+                    .zip(&mut right[..])
+                    .enumerate()
+                    .for_each(|(i, ((p, l), r))| {
+                        let t = ((*faded + i + 1) as f32 / HRTF_CROSSFADE_LENGTH as f32).min(1.);
+                        *l = t.mul_add(*l, (1. - t) * p.0);
+                        *r = t.mul_add(*r, (1. - t) * p.1);
+                    });
+
+                *faded += RENDER_QUANTUM_SIZE;
+                if *faded >= HRTF_CROSSFADE_LENGTH {
+                    self.hrtf_fade_out = None;
+                }
+            }
         } else {
             // EqualPower panning
             let [left, right] = output.stereo_mut();
@@ -734,7 +1185,13 @@ impl AudioProcessor for PannerRenderer {
                 };
 
             // Optimize for static Panner & Listener
-            let single_valued = listener_position_x.len() == 1
+            let single_valued = source_position_x.len() == 1
+                && source_position_y.len() == 1
+                && source_position_z.len() == 1
+                && source_orientation_x.len() == 1
+                && source_orientation_y.len() == 1
+                && source_orientation_z.len() == 1
+                && listener_position_x.len() == 1
                 && listener_position_y.len() == 1
                 && listener_position_z.len() == 1
                 && listener_forward_x.len() == 1
@@ -759,8 +1216,8 @@ impl AudioProcessor for PannerRenderer {
         // put the hrtf_state back into self (borrow reasons)
         self.hrtf_state = hrtf_state;
 
-        // tail time only for HRTF panning
-        self.hrtf_state.is_some()
+        // tail time only for HRTF panning and Doppler shift
+        self.hrtf_state.is_some() || self.doppler_state.is_some()
     }
 }
 
@@ -781,7 +1238,7 @@ impl PannerRenderer {
             let abs_angle =
                 crate::spatial::angle(source_position, source_orientation, listener_position);
 
-            if abs_angle < abs_inner_angle {
+            if abs_angle <= abs_inner_angle {
                 1. // No attenuation
             } else if abs_angle >= abs_outer_angle {
                 cone_outer_gain // Max attenuation
@@ -797,30 +1254,16 @@ impl PannerRenderer {
         let distance_model = self.distance_model.load(Ordering::SeqCst).into();
         let ref_distance = self.ref_distance.load();
         let rolloff_factor = self.rolloff_factor.load();
+        let max_distance = self.max_distance.load();
         let distance = crate::spatial::distance(source_position, listener_position) as f64;
 
-        let dist_gain = match distance_model {
-            DistanceModelType::Linear => {
-                let max_distance = self.max_distance.load();
-                let d2ref = ref_distance.min(max_distance);
-                let d2max = ref_distance.max(max_distance);
-                let d_clamped = distance.clamp(d2ref, d2max);
-                1. - rolloff_factor * (d_clamped - d2ref) / (d2max - d2ref)
-            }
-            DistanceModelType::Inverse => {
-                if distance > 0. {
-                    ref_distance
-                        / (ref_distance
-                            + rolloff_factor * (ref_distance.max(distance) - ref_distance))
-                } else {
-                    1.
-                }
-            }
-            DistanceModelType::Exponential => {
-                (distance.max(ref_distance) / ref_distance).powf(-rolloff_factor)
-            }
-        };
-        dist_gain as f32
+        distance_gain(
+            distance_model,
+            distance,
+            ref_distance,
+            max_distance,
+            rolloff_factor,
+        ) as f32
     }
 }
 
@@ -931,4 +1374,419 @@ mod tests {
         let right = output.channel_data(1).as_slice();
         assert!(right[128..256].iter().any(|v| *v >= 1E-6));
     }
+
+    #[test]
+    fn test_doppler_propagation_delay() {
+        let sample_rate = 44100.;
+        let length = RENDER_QUANTUM_SIZE * 2;
+        let context = OfflineAudioContext::new(2, length, sample_rate);
+
+        let mut dirac = context.create_buffer(1, 1, sample_rate);
+        dirac.copy_to_channel(&[1.], 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(dirac);
+        src.start();
+
+        let options = PannerOptions {
+            doppler: true,
+            // sound travels one unit in 100 samples
+            speed_of_sound: sample_rate as f64 / 100.,
+            ..PannerOptions::default()
+        };
+        let panner = PannerNode::new(&context, options);
+        assert!(panner.doppler());
+        assert_float_eq!(panner.speed_of_sound(), 441., abs <= 0.);
+        panner.position_x().set_value(1.); // sound comes from the right
+
+        src.connect(&panner);
+        panner.connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+
+        let mut expected = vec![0.; length];
+        expected[100] = 1.;
+
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            &[0.; RENDER_QUANTUM_SIZE * 2][..],
+            abs_all <= 1E-6
+        );
+        assert_float_eq!(
+            output.get_channel_data(1)[..],
+            &expected[..],
+            abs_all <= 1E-6
+        );
+    }
+
+    #[test]
+    fn test_doppler_pitch_shift() {
+        // a source moving away at a tenth of the speed of sound is heard 10% lower
+        let count_zero_crossings = |doppler: bool| {
+            let sample_rate = 48000.;
+            let length = sample_rate as usize;
+            let context = OfflineAudioContext::new(1, length, sample_rate);
+
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(1000.);
+            osc.start();
+
+            let options = PannerOptions {
+                doppler,
+                ..PannerOptions::default()
+            };
+            let panner = PannerNode::new(&context, options);
+            assert_eq!(panner.doppler(), doppler);
+            let speed = panner.speed_of_sound() as f32 / 10.;
+            panner.position_z().set_value_at_time(1., 0.);
+            panner
+                .position_z()
+                .linear_ramp_to_value_at_time(1. + speed, 1.);
+
+            osc.connect(&panner);
+            panner.connect(&context.destination());
+
+            let output = context.start_rendering_sync();
+            let channel = output.get_channel_data(0);
+
+            // count in the second half, when the delayed sound has arrived
+            channel[length / 2..]
+                .windows(2)
+                .filter(|w| w[0] < 0. && w[1] >= 0.)
+                .count()
+        };
+
+        let reference = count_zero_crossings(false);
+        let shifted = count_zero_crossings(true);
+
+        assert!((reference as i32 - 500).abs() <= 2);
+        assert!((shifted as i32 - 450).abs() <= 2);
+    }
+
+    #[test]
+    fn test_set_doppler() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44100.);
+
+        let panner = context.create_panner();
+        assert!(!panner.doppler());
+        assert_float_eq!(panner.speed_of_sound(), 343.3, abs <= 0.);
+
+        panner.set_doppler(true);
+        assert!(panner.doppler());
+        panner.set_speed_of_sound(340.);
+        assert_float_eq!(panner.speed_of_sound(), 340., abs <= 0.);
+
+        panner.set_doppler(false);
+        assert!(!panner.doppler());
+    }
+
+    #[test]
+    fn test_distance_gain_linear() {
+        let model = DistanceModelType::Linear;
+
+        assert_float_eq!(distance_gain(model, 0., 1., 11., 1.), 1., abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 1., 1., 11., 1.), 1., abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 6., 1., 11., 1.), 0.5, abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 6., 1., 11., 0.5), 0.75, abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 11., 1., 11., 1.), 0., abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 20., 1., 11., 1.), 0., abs <= 1e-12);
+        // rolloff factor is clamped to [0, 1]
+        assert_float_eq!(distance_gain(model, 20., 1., 11., 2.), 0., abs <= 1e-12);
+        // ref distance equals max distance
+        assert_float_eq!(distance_gain(model, 20., 5., 5., 0.25), 0.75, abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_distance_gain_inverse() {
+        let model = DistanceModelType::Inverse;
+
+        assert_float_eq!(distance_gain(model, 0., 1., 10., 1.), 1., abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 0.5, 1., 10., 1.), 1., abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 2., 1., 10., 1.), 0.5, abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 4., 1., 10., 1.), 0.25, abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 3., 1., 10., 2.), 0.2, abs <= 1e-12);
+        // max distance is not used
+        assert_float_eq!(distance_gain(model, 100., 1., 10., 1.), 0.01, abs <= 1e-12);
+        // zero ref distance
+        assert_float_eq!(distance_gain(model, 2., 0., 10., 1.), 0., abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 2., 0., 10., 0.), 1., abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_distance_gain_exponential() {
+        let model = DistanceModelType::Exponential;
+
+        assert_float_eq!(distance_gain(model, 0., 1., 10., 1.), 1., abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 2., 1., 10., 1.), 0.5, abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 2., 1., 10., 2.), 0.25, abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 8., 2., 10., 0.5), 0.5, abs <= 1e-12);
+        // zero ref distance
+        assert_float_eq!(distance_gain(model, 0., 0., 10., 1.), 1., abs <= 1e-12);
+        assert_float_eq!(distance_gain(model, 2., 0., 10., 1.), 0., abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_distance_attenuation() {
+        let sample_rate = 44100.;
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, sample_rate);
+
+        let input = AudioBuffer::from(vec![vec![1.; RENDER_QUANTUM_SIZE]], sample_rate);
+        let src = AudioBufferSourceNode::new(&context, AudioBufferSourceOptions::default());
+        src.set_buffer(input);
+        src.start();
+
+        let options = PannerOptions {
+            distance_model: DistanceModelType::Exponential,
+            ref_distance: 2.,
+            rolloff_factor: 2.,
+            ..PannerOptions::default()
+        };
+        let panner = PannerNode::new(&context, options);
+        panner.position_x().set_value(4.); // sound comes from the right
+
+        src.connect(&panner);
+        panner.connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            &[0.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 1E-6
+        );
+        assert_float_eq!(
+            output.get_channel_data(1)[..],
+            &[0.25; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 1E-6
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_ref_distance() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44100.);
+        let panner = context.create_panner();
+        panner.set_ref_distance(-1.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_max_distance() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44100.);
+        let options = PannerOptions {
+            max_distance: 0.,
+            ..PannerOptions::default()
+        };
+        let _panner = PannerNode::new(&context, options);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_rolloff_factor() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44100.);
+        let panner = context.create_panner();
+        panner.set_rolloff_factor(-1.);
+    }
+
+    #[test]
+    fn test_cone_gain() {
+        // source on the right of the listener, facing the given direction
+        let render = |orientation_x: f32, orientation_z: f32| {
+            let sample_rate = 44100.;
+            let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, sample_rate);
+
+            let input = AudioBuffer::from(vec![vec![1.; RENDER_QUANTUM_SIZE]], sample_rate);
+            let src = AudioBufferSourceNode::new(&context, AudioBufferSourceOptions::default());
+            src.set_buffer(input);
+            src.start();
+
+            let options = PannerOptions {
+                position_x: 1.,
+                orientation_x,
+                orientation_z,
+                cone_inner_angle: 60.,
+                cone_outer_angle: 120.,
+                cone_outer_gain: 0.25,
+                ..PannerOptions::default()
+            };
+            let panner = PannerNode::new(&context, options);
+
+            src.connect(&panner);
+            panner.connect(&context.destination());
+
+            let output = context.start_rendering_sync();
+            output.get_channel_data(1)[0]
+        };
+
+        // facing the listener, inside the inner cone
+        assert_float_eq!(render(-1., 0.), 1., abs <= 1E-6);
+        // facing away from the listener, outside the outer cone
+        assert_float_eq!(render(1., 0.), 0.25, abs <= 1E-6);
+        // at 45 degrees, halfway between the inner and outer cones
+        assert_float_eq!(render(-1., 1.), 0.625, abs <= 1E-6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_cone_outer_gain() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44100.);
+        let panner = context.create_panner();
+        panner.set_cone_outer_gain(1.5);
+    }
+
+    // ratio of the right and left gains of the equal power panning, which only
+    // depends on the azimuth of the source
+    fn render_gain_ratios(context: OfflineAudioContext, panner: &PannerNode) -> Vec<f32> {
+        let sample_rate = context.sample_rate();
+        let input = AudioBuffer::from(vec![vec![1.; RENDER_QUANTUM_SIZE]], sample_rate);
+        let src = AudioBufferSourceNode::new(&context, AudioBufferSourceOptions::default());
+        src.set_buffer(input);
+        src.start();
+
+        src.connect(panner);
+        panner.connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+        output
+            .get_channel_data(1)
+            .iter()
+            .zip(output.get_channel_data(0))
+            .map(|(r, l)| r / l)
+            .collect()
+    }
+
+    #[test]
+    fn test_a_rate_panner_position() {
+        let sample_rate = 44100.;
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, sample_rate);
+
+        // source moves from the left to the right in front of the listener
+        let panner = context.create_panner();
+        panner.position_z().set_value(-1.);
+        panner.position_x().set_value_at_time(-0.5, 0.);
+        panner
+            .position_x()
+            .linear_ramp_to_value_at_time(0.5, RENDER_QUANTUM_SIZE as f64 / sample_rate as f64);
+
+        let ratios = render_gain_ratios(context, &panner);
+        assert!(ratios.windows(2).all(|w| w[1] > w[0]));
+    }
+
+    #[test]
+    fn test_a_rate_listener_position() {
+        let sample_rate = 44100.;
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, sample_rate);
+
+        // listener moves from the left to the right behind the source
+        let panner = context.create_panner();
+        panner.position_z().set_value(-1.);
+
+        let listener = context.listener();
+        listener.position_x().set_value_at_time(-0.5, 0.);
+        listener
+            .position_x()
+            .linear_ramp_to_value_at_time(0.5, RENDER_QUANTUM_SIZE as f64 / sample_rate as f64);
+
+        let ratios = render_gain_ratios(context, &panner);
+        assert!(ratios.windows(2).all(|w| w[1] < w[0]));
+    }
+
+    #[test]
+    fn test_a_rate_listener_orientation() {
+        let sample_rate = 44100.;
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, sample_rate);
+
+        let panner = context.create_panner();
+        panner.position_z().set_value(-1.);
+
+        // listener turns to the right, so the source moves to the left
+        let listener = context.listener();
+        listener.forward_x().set_value_at_time(-0.5, 0.);
+        listener
+            .forward_x()
+            .linear_ramp_to_value_at_time(0.5, RENDER_QUANTUM_SIZE as f64 / sample_rate as f64);
+
+        let ratios = render_gain_ratios(context, &panner);
+        assert!(ratios.windows(2).all(|w| w[1] < w[0]));
+    }
+
+    fn load_builtin_dataset(sample_rate: f32) -> HrtfDataset {
+        let file = std::fs::File::open("resources/IRC_1003_C.bin").unwrap();
+        HrtfDataset::from_reader(std::io::BufReader::new(file), sample_rate).unwrap()
+    }
+
+    fn render_hrtf(hrtf_dataset: Option<HrtfDataset>) -> AudioBuffer {
+        let sample_rate = 44100.;
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE * 4, sample_rate);
+
+        let input = AudioBuffer::from(vec![vec![1.; RENDER_QUANTUM_SIZE]], sample_rate);
+        let src = AudioBufferSourceNode::new(&context, AudioBufferSourceOptions::default());
+        src.set_buffer(input);
+        src.start();
+
+        let options = PannerOptions {
+            panning_model: PanningModelType::HRTF,
+            position_x: 1.,
+            hrtf_dataset,
+            ..PannerOptions::default()
+        };
+        let panner = PannerNode::new(&context, options);
+
+        src.connect(&panner);
+        panner.connect(&context.destination());
+
+        context.start_rendering_sync()
+    }
+
+    #[test]
+    fn test_hrtf_dataset() {
+        let hrtf_dataset = load_builtin_dataset(44100.);
+        assert_float_eq!(hrtf_dataset.sample_rate(), 44100., abs <= 0.);
+
+        let builtin = render_hrtf(None);
+        let loaded = render_hrtf(Some(hrtf_dataset));
+
+        assert_float_eq!(
+            builtin.get_channel_data(0),
+            loaded.get_channel_data(0),
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            builtin.get_channel_data(1),
+            loaded.get_channel_data(1),
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_invalid_hrtf_dataset() {
+        let result = HrtfDataset::from_reader(&[0_u8; 16][..], 44100.);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_set_hrtf_dataset() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44100.);
+
+        let panner = context.create_panner();
+        assert!(panner.hrtf_dataset().is_none());
+
+        // switching datasets and panning models does not block before rendering
+        panner.set_panning_model(PanningModelType::HRTF);
+        panner.set_hrtf_dataset(load_builtin_dataset(44100.));
+        panner.set_panning_model(PanningModelType::EqualPower);
+        panner.set_panning_model(PanningModelType::HRTF);
+        panner.set_hrtf_dataset(load_builtin_dataset(44100.));
+        assert!(panner.hrtf_dataset().is_some());
+
+        let output = context.start_rendering_sync();
+        assert!(output.get_channel_data(0).iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hrtf_dataset_sample_rate_mismatch() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44100.);
+        let panner = context.create_panner();
+        panner.set_hrtf_dataset(load_builtin_dataset(48000.));
+    }
 }
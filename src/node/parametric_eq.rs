@@ -0,0 +1,519 @@
+use std::sync::Mutex;
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
+
+use super::biquad_filter::{calculate_coefs, BiquadState, Coefficients};
+use super::bypass::Bypass;
+use super::{AudioEffectNode, AudioNode, BiquadFilterType, ChannelConfig, ChannelConfigOptions};
+
+/// Settings of a single band of a [`ParametricEqNode`]
+///
+/// The fields have the same meaning as the params of a [`BiquadFilterNode`](super::BiquadFilterNode)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EqBand {
+    pub type_: BiquadFilterType,
+    /// Frequency in Hz, clamped between 0 and the Nyquist frequency
+    pub frequency: f32,
+    pub q: f32,
+    /// Gain in dB
+    pub gain: f32,
+}
+
+impl Default for EqBand {
+    fn default() -> Self {
+        Self {
+            type_: BiquadFilterType::Peaking,
+            frequency: 1000.,
+            q: 1.,
+            gain: 0.,
+        }
+    }
+}
+
+/// Options for constructing a [`ParametricEqNode`]
+#[derive(Clone, Debug, Default)]
+pub struct ParametricEqOptions {
+    pub bands: Vec<EqBand>,
+    pub channel_config: ChannelConfigOptions,
+}
+
+/// Compute the coefficients of all bands
+fn calculate_band_coefs(bands: &[EqBand], sample_rate: f32) -> Vec<Coefficients> {
+    bands
+        .iter()
+        .map(|band| {
+            calculate_coefs(
+                band.type_,
+                f64::from(sample_rate),
+                f64::from(band.frequency.clamp(0., sample_rate / 2.)),
+                f64::from(band.gain),
+                f64::from(band.q),
+            )
+        })
+        .collect()
+}
+
+/// `ParametricEqNode` is a cascade of biquad filters, one per band.
+///
+/// This is equivalent to chaining [`BiquadFilterNode`](super::BiquadFilterNode)s, but the
+/// settings of all bands are updated at once on the render thread, so intermediate states
+/// (e.g. while switching presets) are never heard. The band settings are not automatable.
+///
+/// Without any band the node passes its input through unchanged.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, BiquadFilterType};
+/// use web_audio_api::node::{EqBand, ParametricEqNode, ParametricEqOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let options = ParametricEqOptions {
+///     bands: vec![
+///         EqBand { type_: BiquadFilterType::Highpass, frequency: 80., ..EqBand::default() },
+///         EqBand { frequency: 3000., gain: -6., ..EqBand::default() },
+///         EqBand { type_: BiquadFilterType::Highshelf, frequency: 8000., gain: 3., ..EqBand::default() },
+///     ],
+///     ..ParametricEqOptions::default()
+/// };
+/// let eq = ParametricEqNode::new(&context, options);
+/// eq.connect(&context.destination());
+///
+/// let noise = context.create_oscillator();
+/// noise.connect(&eq);
+/// noise.start();
+///
+/// // cut a little more in the mids
+/// eq.set_band(1, EqBand { frequency: 3000., gain: -12., ..EqBand::default() });
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct ParametricEqNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    bands: Mutex<Vec<EqBand>>,
+    /// Channel between node and renderer (sender part)
+    sender: Sender<Vec<Coefficients>>,
+    bypass: Bypass,
+}
+
+impl AudioNode for ParametricEqNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioEffectNode for ParametricEqNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
+}
+
+impl ParametricEqNode {
+    pub fn new<C: BaseAudioContext>(context: &C, options: ParametricEqOptions) -> Self {
+        context.register(move |registration| {
+            let ParametricEqOptions {
+                bands,
+                channel_config,
+            } = options;
+
+            let (sender, receiver) = crossbeam_channel::unbounded();
+
+            let render = ParametricEqRenderer {
+                receiver,
+                coefs: calculate_band_coefs(&bands, context.sample_rate()),
+                state: Vec::with_capacity(MAX_CHANNELS),
+            };
+
+            let node = ParametricEqNode {
+                registration,
+                bypass: Bypass::default(),
+                channel_config: channel_config.into(),
+                bands: Mutex::new(bands),
+                sender,
+            };
+
+            let render = node.bypass.wrap(render, 0);
+            (node, Box::new(render))
+        })
+    }
+
+    /// Returns the settings of all bands
+    #[allow(clippy::missing_panics_doc)]
+    pub fn bands(&self) -> Vec<EqBand> {
+        self.bands.lock().unwrap().clone()
+    }
+
+    /// Replace all bands at once
+    ///
+    /// All bands switch to the new settings in the same render quantum. When the number of bands
+    /// is unchanged, the filter history of each band is kept so the update is click-free.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn set_bands(&self, bands: Vec<EqBand>) {
+        let mut current = self.bands.lock().unwrap();
+        *current = bands;
+        self.send_coefs(&current);
+    }
+
+    /// Update the settings of a single band
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `index` is not lower than the number of bands
+    pub fn set_band(&self, index: usize, band: EqBand) {
+        let mut current = self.bands.lock().unwrap();
+        let number_of_bands = current.len();
+        match current.get_mut(index) {
+            Some(b) => *b = band,
+            None => panic!(
+                "IndexSizeError - Invalid band index: {:?} is outside range [0, {:?}]",
+                index,
+                number_of_bands.saturating_sub(1)
+            ),
+        }
+        self.send_coefs(&current);
+    }
+
+    fn send_coefs(&self, bands: &[EqBand]) {
+        let coefs = calculate_band_coefs(bands, self.context().sample_rate());
+        let _ = self.sender.send(coefs); // can fail when render thread shut down
+    }
+
+    /// Returns the combined frequency response of all bands for the specified frequencies
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency_hz` - frequencies for which frequency response of the node should be calculated
+    /// * `mag_response` - magnitude of the frequency response of the node
+    /// * `phase_response` - phase of the frequency response of the node
+    ///
+    /// Frequencies outside the `[0, sample_rate / 2]` range yield `NaN` for both
+    /// magnitude and phase.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if arguments' lengths don't match
+    ///
+    pub fn get_frequency_response(
+        &self,
+        frequency_hz: &[f32],
+        mag_response: &mut [f32],
+        phase_response: &mut [f32],
+    ) {
+        if frequency_hz.len() != mag_response.len() || mag_response.len() != phase_response.len() {
+            panic!("InvalidAccessError - Parameter lengths must match");
+        }
+
+        let sample_rate = self.context().sample_rate();
+        let n_quist = sample_rate / 2.;
+        let coefs = calculate_band_coefs(&self.bands(), sample_rate);
+
+        for (i, &freq) in frequency_hz.iter().enumerate() {
+            if !(0. ..=n_quist).contains(&freq) {
+                mag_response[i] = f32::NAN;
+                phase_response[i] = f32::NAN;
+                continue;
+            }
+
+            // normalize frequency
+            let f = f64::from(freq / n_quist);
+            let response = coefs
+                .iter()
+                .map(|c| c.frequency_response(f))
+                .fold(num_complex::Complex::new(1., 0.), |acc, r| acc * r);
+
+            let (mag, phase) = response.to_polar();
+            mag_response[i] = mag as f32;
+            phase_response[i] = phase as f32;
+        }
+    }
+}
+
+struct ParametricEqRenderer {
+    /// Channel between node and renderer (receiver part)
+    receiver: Receiver<Vec<Coefficients>>,
+    /// Coefficients of each band
+    coefs: Vec<Coefficients>,
+    /// Filter history of each band, for each channel
+    state: Vec<Vec<BiquadState>>,
+}
+
+impl AudioProcessor for ParametricEqRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        if let Some(coefs) = self.receiver.try_iter().last() {
+            if coefs.len() != self.coefs.len() {
+                // the bands do not match anymore, restart from a clean history
+                self.state.clear();
+            }
+            self.coefs = coefs;
+        }
+
+        if self.coefs.is_empty() {
+            *output = input.clone();
+            return false;
+        }
+
+        // handle tail time
+        if input.is_silent() {
+            // input is silent and filter history is clean
+            if self.state.iter().flatten().all(BiquadState::is_silent) {
+                output.make_silent();
+                return false;
+            }
+        } else {
+            // if in tail time, we continue with the previous number of channels
+            let num_channels = input.number_of_channels();
+            self.state
+                .resize(num_channels, vec![BiquadState::default(); self.coefs.len()]);
+        }
+
+        output.set_number_of_channels(self.state.len());
+
+        let coefs = &self.coefs;
+        let mut buffer = [0.; RENDER_QUANTUM_SIZE];
+
+        output
+            .channels_mut()
+            .iter_mut()
+            .zip(self.state.iter_mut())
+            .enumerate()
+            .for_each(|(channel_number, (output_channel, channel_state))| {
+                // zero-filled when in tail time
+                output_channel.copy_from_slice(input.channel_data(channel_number));
+
+                for (state, &coef) in channel_state.iter_mut().zip(coefs.iter()) {
+                    buffer.copy_from_slice(output_channel);
+                    state.process(&[coef; RENDER_QUANTUM_SIZE], &buffer, output_channel);
+                }
+            });
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::{AudioScheduledSourceNode, BiquadFilterNode, BiquadFilterOptions};
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.;
+
+    fn bands() -> Vec<EqBand> {
+        vec![
+            EqBand {
+                type_: BiquadFilterType::Lowshelf,
+                frequency: 200.,
+                gain: 6.,
+                ..EqBand::default()
+            },
+            EqBand {
+                frequency: 2000.,
+                q: 4.,
+                gain: -12.,
+                ..EqBand::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let eq = ParametricEqNode::new(&context, ParametricEqOptions::default());
+        assert!(eq.bands().is_empty());
+
+        let options = ParametricEqOptions {
+            bands: bands(),
+            ..ParametricEqOptions::default()
+        };
+        let eq = ParametricEqNode::new(&context, options);
+        assert_eq!(eq.bands(), bands());
+    }
+
+    #[test]
+    fn test_set_band() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let options = ParametricEqOptions {
+            bands: bands(),
+            ..ParametricEqOptions::default()
+        };
+        let eq = ParametricEqNode::new(&context, options);
+
+        let band = EqBand {
+            gain: 3.,
+            ..EqBand::default()
+        };
+        eq.set_band(1, band);
+        assert_eq!(eq.bands()[0], bands()[0]);
+        assert_eq!(eq.bands()[1], band);
+
+        eq.set_bands(vec![band]);
+        assert_eq!(eq.bands(), vec![band]);
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_set_band_out_of_range() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let eq = ParametricEqNode::new(&context, ParametricEqOptions::default());
+        eq.set_band(0, EqBand::default());
+    }
+
+    #[test]
+    fn test_no_bands_passthrough() {
+        let length = RENDER_QUANTUM_SIZE * 2;
+        let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let eq = ParametricEqNode::new(&context, ParametricEqOptions::default());
+        eq.connect(&context.destination());
+
+        let src = context.create_oscillator();
+        src.connect(&eq);
+        src.connect(&context.destination());
+        src.start();
+
+        // eq output and direct path sum up to twice the oscillator
+        let reference = {
+            let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+            let src = context.create_oscillator();
+            src.connect(&context.destination());
+            src.start();
+            context.start_rendering_sync()
+        };
+
+        let result = context.start_rendering_sync();
+        let expected: Vec<f32> = reference
+            .get_channel_data(0)
+            .iter()
+            .map(|s| 2. * s)
+            .collect();
+        assert_float_eq!(result.get_channel_data(0), &expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_matches_biquad_chain() {
+        let length = RENDER_QUANTUM_SIZE * 4;
+
+        let mut signal = vec![0.; length];
+        signal[0] = 1.;
+
+        let render_eq = || {
+            let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+            let options = ParametricEqOptions {
+                bands: bands(),
+                ..ParametricEqOptions::default()
+            };
+            let eq = ParametricEqNode::new(&context, options);
+            eq.connect(&context.destination());
+
+            let mut buffer = context.create_buffer(1, length, SAMPLE_RATE);
+            buffer.copy_to_channel(&signal, 0);
+            let src = context.create_buffer_source();
+            src.set_buffer(buffer);
+            src.connect(&eq);
+            src.start();
+
+            context.start_rendering_sync()
+        };
+
+        let render_chain = || {
+            let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+            let biquads: Vec<_> = bands()
+                .iter()
+                .map(|band| {
+                    let options = BiquadFilterOptions {
+                        type_: band.type_,
+                        frequency: band.frequency,
+                        q: band.q,
+                        gain: band.gain,
+                        ..BiquadFilterOptions::default()
+                    };
+                    BiquadFilterNode::new(&context, options)
+                })
+                .collect();
+            biquads[0].connect(&biquads[1]);
+            biquads[1].connect(&context.destination());
+
+            let mut buffer = context.create_buffer(1, length, SAMPLE_RATE);
+            buffer.copy_to_channel(&signal, 0);
+            let src = context.create_buffer_source();
+            src.set_buffer(buffer);
+            src.connect(&biquads[0]);
+            src.start();
+
+            context.start_rendering_sync()
+        };
+
+        let eq = render_eq();
+        let chain = render_chain();
+        assert_float_eq!(
+            eq.get_channel_data(0),
+            chain.get_channel_data(0),
+            abs_all <= 1e-6
+        );
+    }
+
+    #[test]
+    fn test_frequency_response() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let options = ParametricEqOptions {
+            bands: bands(),
+            ..ParametricEqOptions::default()
+        };
+        let eq = ParametricEqNode::new(&context, options);
+
+        let frequency_hz = [0., 2000., SAMPLE_RATE / 2., -1., SAMPLE_RATE];
+        let mut mag_response = [0.; 5];
+        let mut phase_response = [0.; 5];
+        eq.get_frequency_response(&frequency_hz, &mut mag_response, &mut phase_response);
+
+        // lowshelf boost at DC
+        assert_float_eq!(mag_response[0], 10_f32.powf(6. / 20.), rmax <= 1e-3);
+        // peaking cut at its center frequency
+        assert_float_eq!(mag_response[1], 10_f32.powf(-12. / 20.), rmax <= 1e-2);
+        assert_float_eq!(mag_response[2], 1., rmax <= 1e-3);
+        assert!(mag_response[3].is_nan() && phase_response[3].is_nan());
+        assert!(mag_response[4].is_nan() && phase_response[4].is_nan());
+
+        // response of the empty eq is flat
+        eq.set_bands(vec![]);
+        eq.get_frequency_response(&frequency_hz, &mut mag_response, &mut phase_response);
+        assert_float_eq!(mag_response[..3], [1.; 3][..], abs_all <= 0.);
+        assert_float_eq!(phase_response[..3], [0.; 3][..], abs_all <= 0.);
+    }
+}
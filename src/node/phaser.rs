@@ -0,0 +1,473 @@
+use std::f64::consts::PI;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
+
+use super::bypass::Bypass;
+use super::{AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Maximum number of all-pass stages
+const MAX_STAGES: usize = 32;
+
+/// Maximum depth of the sweep, in octaves
+const MAX_DEPTH: f32 = 8.;
+
+/// Options for constructing a [`PhaserNode`]
+#[derive(Clone, Debug)]
+pub struct PhaserOptions {
+    /// Number of all-pass stages, each pair adds a notch to the frequency response
+    pub stages: usize,
+    pub frequency: f32,
+    pub rate: f32,
+    pub depth: f32,
+    pub feedback: f32,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for PhaserOptions {
+    fn default() -> Self {
+        Self {
+            stages: 4,
+            frequency: 1000., // Hz
+            rate: 0.5,        // Hz
+            depth: 1.,        // octaves
+            feedback: 0.5,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Assert that the number of stages is valid
+///
+/// # Panics
+///
+/// This function panics if the given number is not in the [1, 32] range
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_stages(stages: usize) {
+    if !(1..=MAX_STAGES).contains(&stages) {
+        panic!(
+            "RangeError - Invalid number of stages: {:?} is outside range [1, {:?}]",
+            stages, MAX_STAGES
+        );
+    }
+}
+
+/// `PhaserNode` is a phaser effect, the input is mixed with a copy of itself
+/// passed through a cascade of all-pass filters whose center frequency is
+/// swept by a low frequency oscillator.
+///
+/// The resulting notches in the frequency response move up and down with the
+/// LFO. The feedback param emphasizes the peaks between the notches.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, OscillatorType};
+/// use web_audio_api::node::{PhaserNode, PhaserOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let options = PhaserOptions {
+///     stages: 8,
+///     feedback: 0.7,
+///     ..PhaserOptions::default()
+/// };
+/// let phaser = PhaserNode::new(&context, options);
+/// phaser.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.set_type(OscillatorType::Sawtooth);
+/// osc.connect(&phaser);
+/// osc.start();
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct PhaserNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    stages: usize,
+    frequency: AudioParam,
+    rate: AudioParam,
+    depth: AudioParam,
+    feedback: AudioParam,
+    bypass: Bypass,
+}
+
+impl AudioNode for PhaserNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioEffectNode for PhaserNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
+}
+
+impl PhaserNode {
+    /// # Panics
+    ///
+    /// Will panic if `options.stages` is outside the [1, 32] range
+    pub fn new<C: BaseAudioContext>(context: &C, options: PhaserOptions) -> Self {
+        context.register(move |registration| {
+            let PhaserOptions {
+                stages,
+                frequency,
+                rate,
+                depth,
+                feedback,
+                channel_config,
+            } = options;
+
+            assert_valid_stages(stages);
+
+            let nyquist = context.sample_rate() / 2.;
+
+            let frequency_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: nyquist,
+                default_value: 1000.,
+                automation_rate: AutomationRate::K,
+            };
+            let (frequency_param, frequency_proc) =
+                context.create_audio_param(frequency_param_opts, &registration);
+            frequency_param.set_value(frequency);
+
+            let rate_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: nyquist,
+                default_value: 0.5,
+                automation_rate: AutomationRate::K,
+            };
+            let (rate_param, rate_proc) =
+                context.create_audio_param(rate_param_opts, &registration);
+            rate_param.set_value(rate);
+
+            let depth_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: MAX_DEPTH,
+                default_value: 1.,
+                automation_rate: AutomationRate::K,
+            };
+            let (depth_param, depth_proc) =
+                context.create_audio_param(depth_param_opts, &registration);
+            depth_param.set_value(depth);
+
+            let feedback_param_opts = AudioParamDescriptor {
+                min_value: -0.99,
+                max_value: 0.99,
+                default_value: 0.5,
+                automation_rate: AutomationRate::K,
+            };
+            let (feedback_param, feedback_proc) =
+                context.create_audio_param(feedback_param_opts, &registration);
+            feedback_param.set_value(feedback);
+
+            let render = PhaserRenderer {
+                frequency: frequency_proc,
+                rate: rate_proc,
+                depth: depth_proc,
+                feedback: feedback_proc,
+                stages,
+                lfo_phase: 0.,
+                state: Vec::with_capacity(MAX_CHANNELS),
+            };
+
+            let node = PhaserNode {
+                registration,
+                bypass: Bypass::default(),
+                channel_config: channel_config.into(),
+                stages,
+                frequency: frequency_param,
+                rate: rate_param,
+                depth: depth_param,
+                feedback: feedback_param,
+            };
+
+            let render = node.bypass.wrap(render, 0);
+            (node, Box::new(render))
+        })
+    }
+
+    /// Number of all-pass stages
+    pub fn stages(&self) -> usize {
+        self.stages
+    }
+
+    /// K-rate [`AudioParam`] defining the center frequency (in Hz) of the sweep
+    pub fn frequency(&self) -> &AudioParam {
+        &self.frequency
+    }
+
+    /// K-rate [`AudioParam`] defining the frequency (in Hz) of the LFO
+    pub fn rate(&self) -> &AudioParam {
+        &self.rate
+    }
+
+    /// K-rate [`AudioParam`] defining the amplitude of the sweep, in octaves
+    /// around the center frequency
+    pub fn depth(&self) -> &AudioParam {
+        &self.depth
+    }
+
+    /// K-rate [`AudioParam`] defining the amount of the all-pass output fed
+    /// back into its input
+    pub fn feedback(&self) -> &AudioParam {
+        &self.feedback
+    }
+}
+
+/// Filter history of a single channel
+#[derive(Clone, Debug)]
+struct PhaserState {
+    /// Previous input and output of each all-pass stage
+    stages: Vec<(f64, f64)>,
+    /// Previous output of the all-pass cascade
+    last_output: f64,
+}
+
+impl PhaserState {
+    fn new(stages: usize) -> Self {
+        Self {
+            stages: vec![(0., 0.); stages],
+            last_output: 0.,
+        }
+    }
+
+    /// Returns true if the history only contains (sub-normal) silence
+    fn is_silent(&self) -> bool {
+        !(self.last_output.is_normal()
+            || self
+                .stages
+                .iter()
+                .any(|(x1, y1)| x1.is_normal() || y1.is_normal()))
+    }
+
+    /// Process a single sample, returns the output of the all-pass cascade
+    #[inline(always)]
+    fn process(&mut self, input: f64, coef: f64, feedback: f64) -> f64 {
+        let input = input + feedback * self.last_output;
+
+        let output = self.stages.iter_mut().fold(input, |x, (x1, y1)| {
+            // first order all-pass: y(n) = a x(n) + x(n-1) - a y(n-1)
+            let y = coef * x + *x1 - coef * *y1;
+            *x1 = x;
+            *y1 = y;
+            y
+        });
+
+        self.last_output = output;
+        output
+    }
+}
+
+struct PhaserRenderer {
+    frequency: AudioParamId,
+    rate: AudioParamId,
+    depth: AudioParamId,
+    feedback: AudioParamId,
+    stages: usize,
+    /// Phase of the LFO, in the [0, 1) range
+    lfo_phase: f64,
+    state: Vec<PhaserState>,
+}
+
+impl AudioProcessor for PhaserRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+        let sample_rate = f64::from(scope.sample_rate);
+
+        // handle tail time
+        if input.is_silent() {
+            // input is silent and filter history is clean
+            if self.state.iter().all(PhaserState::is_silent) {
+                output.make_silent();
+                return false;
+            }
+        } else {
+            // if in tail time, we continue with the previous number of channels
+            let num_channels = input.number_of_channels();
+            self.state
+                .resize(num_channels, PhaserState::new(self.stages));
+        }
+
+        output.set_number_of_channels(self.state.len());
+
+        let frequency = f64::from(params.get(&self.frequency)[0]);
+        let rate = f64::from(params.get(&self.rate)[0]);
+        let depth = f64::from(params.get(&self.depth)[0]);
+        let feedback = f64::from(params.get(&self.feedback)[0]);
+
+        // all-pass coefficient for each frame, shared by all channels
+        let lfo_incr = rate / sample_rate;
+        let max_frequency = sample_rate / 2. * 0.99;
+        let mut coefs = [0.; RENDER_QUANTUM_SIZE];
+        coefs.iter_mut().for_each(|coef| {
+            let lfo = (2. * PI * self.lfo_phase).sin();
+            let f = (frequency * (depth * lfo).exp2()).clamp(1., max_frequency);
+            let tan = (PI * f / sample_rate).tan();
+            *coef = (tan - 1.) / (tan + 1.);
+
+            self.lfo_phase += lfo_incr;
+            self.lfo_phase -= self.lfo_phase.floor();
+        });
+
+        output
+            .channels_mut()
+            .iter_mut()
+            .zip(self.state.iter_mut())
+            .enumerate()
+            .for_each(|(channel_number, (output_channel, state))| {
+                // zero-filled when in tail time
+                let input_channel = input.channel_data(channel_number);
+
+                output_channel
+                    .iter_mut()
+                    .zip(input_channel.iter())
+                    .zip(coefs.iter())
+                    .for_each(|((o, &i), &coef)| {
+                        let x = f64::from(i);
+                        let y = state.process(x, coef, feedback);
+                        // equal mix of the dry and phase shifted signals
+                        *o = (0.5 * (x + y)) as f32;
+                    });
+            });
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.;
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let phaser = PhaserNode::new(&context, PhaserOptions::default());
+
+        assert_eq!(phaser.stages(), 4);
+        assert_float_eq!(phaser.frequency().value(), 1000., abs <= 0.);
+        assert_float_eq!(phaser.rate().value(), 0.5, abs <= 0.);
+        assert_float_eq!(phaser.depth().value(), 1., abs <= 0.);
+        assert_float_eq!(phaser.feedback().value(), 0.5, abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "RangeError")]
+    fn test_invalid_stages() {
+        let context = OfflineAudioContext::new(1, 0, SAMPLE_RATE);
+        let options = PhaserOptions {
+            stages: 0,
+            ..PhaserOptions::default()
+        };
+        let _ = PhaserNode::new(&context, options);
+    }
+
+    #[test]
+    fn test_allpass_is_flat() {
+        // each all-pass stage has a unity magnitude response, so without
+        // modulation nor mixing, the energy of an impulse is preserved
+        let mut state = PhaserState::new(4);
+        let coef = -0.5;
+
+        let energy: f64 = (0..10_000)
+            .map(|i| {
+                let x = if i == 0 { 1. } else { 0. };
+                state.process(x, coef, 0.).powi(2)
+            })
+            .sum();
+        assert_float_eq!(energy, 1., abs <= 1e-9);
+    }
+
+    #[test]
+    fn test_notch() {
+        // a sine at the center frequency of a static 2-stage phaser is
+        // shifted by 180 degrees and cancels out with the dry signal
+        let frequency = 1000.;
+        let length = RENDER_QUANTUM_SIZE * 20;
+        let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let options = PhaserOptions {
+            stages: 2,
+            frequency,
+            depth: 0.,
+            feedback: 0.,
+            ..PhaserOptions::default()
+        };
+        let phaser = PhaserNode::new(&context, options);
+        phaser.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(frequency);
+        osc.connect(&phaser);
+        osc.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+        // skip the transient
+        let peak = channel[length / 2..]
+            .iter()
+            .fold(0_f32, |acc, s| acc.max(s.abs()));
+        assert!(peak < 1e-3);
+    }
+
+    #[test]
+    fn test_tail_time() {
+        let length = RENDER_QUANTUM_SIZE * 4;
+        let context = OfflineAudioContext::new(1, length, SAMPLE_RATE);
+
+        let phaser = PhaserNode::new(&context, PhaserOptions::default());
+        phaser.connect(&context.destination());
+
+        let mut buffer = context.create_buffer(1, 1, SAMPLE_RATE);
+        buffer.copy_to_channel(&[1.], 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&phaser);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+        // the all-pass response rings after the impulse
+        assert!(channel[RENDER_QUANTUM_SIZE..].iter().any(|&s| s != 0.));
+        assert!(channel.iter().all(|s| s.is_finite()));
+    }
+}
@@ -0,0 +1,553 @@
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::bypass::Bypass;
+use super::{
+    AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode,
+    ChannelInterpretation,
+};
+
+/// Maximum pre-delay time, in seconds
+const MAX_PRE_DELAY: f64 = 1.;
+
+/// Comb filter delays of the left channel, in samples at 44.1kHz
+const COMB_TUNING: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+/// Allpass filter delays of the left channel, in samples at 44.1kHz
+const ALLPASS_TUNING: [usize; 4] = [556, 441, 341, 225];
+/// Additional delay of the right channel filters, in samples at 44.1kHz
+const STEREO_SPREAD: usize = 23;
+
+const FIXED_GAIN: f32 = 0.015;
+const SCALE_WET: f32 = 3.;
+const SCALE_DAMPING: f32 = 0.4;
+const SCALE_ROOM: f32 = 0.28;
+const OFFSET_ROOM: f32 = 0.7;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+/// Level (-120dB) below which the reverb tail is considered silent
+const SILENCE_THRESHOLD: f32 = 1e-6;
+
+/// Options for constructing a [`ReverbNode`]
+#[derive(Clone, Debug)]
+pub struct ReverbOptions {
+    pub room_size: f32,
+    pub damping: f32,
+    pub pre_delay: f32,
+    pub wet: f32,
+    pub dry: f32,
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for ReverbOptions {
+    fn default() -> Self {
+        Self {
+            room_size: 0.5,
+            damping: 0.5,
+            pre_delay: 0.,
+            wet: 0.33,
+            dry: 1.,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// `ReverbNode` is an algorithmic stereo reverb, based on the Freeverb design
+/// (a bank of parallel lowpass-feedback comb filters followed by serial
+/// allpass filters, for each output channel).
+///
+/// It is a much lighter alternative to a [`ConvolverNode`](super::ConvolverNode)
+/// with a reverb impulse response, and its parameters can be changed interactively.
+///
+/// The input is mixed down to mono to feed the reverb, the output is always stereo.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{ReverbNode, ReverbOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let options = ReverbOptions {
+///     room_size: 0.8,
+///     pre_delay: 0.02,
+///     ..ReverbOptions::default()
+/// };
+/// let reverb = ReverbNode::new(&context, options);
+/// reverb.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&reverb);
+/// osc.start();
+/// osc.stop_at(0.2);
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct ReverbNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    room_size: AudioParam,
+    damping: AudioParam,
+    pre_delay: AudioParam,
+    wet: AudioParam,
+    dry: AudioParam,
+    bypass: Bypass,
+}
+
+impl AudioNode for ReverbNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioEffectNode for ReverbNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
+}
+
+impl ReverbNode {
+    pub fn new<C: BaseAudioContext>(context: &C, options: ReverbOptions) -> Self {
+        context.register(move |registration| {
+            let ReverbOptions {
+                room_size,
+                damping,
+                pre_delay,
+                wet,
+                dry,
+                channel_config,
+            } = options;
+
+            let unit_param_opts = |default_value, automation_rate| AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1.,
+                default_value,
+                automation_rate,
+            };
+
+            let (room_size_param, room_size_proc) =
+                context.create_audio_param(unit_param_opts(0.5, AutomationRate::K), &registration);
+            room_size_param.set_value(room_size);
+
+            let (damping_param, damping_proc) =
+                context.create_audio_param(unit_param_opts(0.5, AutomationRate::K), &registration);
+            damping_param.set_value(damping);
+
+            let pre_delay_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: MAX_PRE_DELAY as f32,
+                default_value: 0.,
+                automation_rate: AutomationRate::K,
+            };
+            let (pre_delay_param, pre_delay_proc) =
+                context.create_audio_param(pre_delay_param_opts, &registration);
+            pre_delay_param.set_value(pre_delay);
+
+            let (wet_param, wet_proc) =
+                context.create_audio_param(unit_param_opts(0.33, AutomationRate::A), &registration);
+            wet_param.set_value(wet);
+
+            let (dry_param, dry_proc) =
+                context.create_audio_param(unit_param_opts(1., AutomationRate::A), &registration);
+            dry_param.set_value(dry);
+
+            let sample_rate = context.sample_rate();
+            let pre_delay_len = (MAX_PRE_DELAY * f64::from(sample_rate)).ceil() as usize + 1;
+
+            let tanks = [
+                Tank::new(sample_rate, 0),
+                Tank::new(sample_rate, STEREO_SPREAD),
+            ];
+            let comb_delay_frames = tanks[1]
+                .combs
+                .iter()
+                .map(|comb| comb.delay_line.buffer.len())
+                .fold(0, usize::max);
+
+            let render = ReverbRenderer {
+                room_size: room_size_proc,
+                damping: damping_proc,
+                pre_delay: pre_delay_proc,
+                wet: wet_proc,
+                dry: dry_proc,
+                pre_delay_line: vec![0.; pre_delay_len],
+                pre_delay_index: 0,
+                tanks,
+                comb_delay_frames,
+                frames_since_input: usize::MAX,
+            };
+
+            let node = ReverbNode {
+                registration,
+                bypass: Bypass::default(),
+                channel_config: channel_config.into(),
+                room_size: room_size_param,
+                damping: damping_param,
+                pre_delay: pre_delay_param,
+                wet: wet_param,
+                dry: dry_param,
+            };
+
+            let render = node.bypass.wrap(render, 0);
+            (node, Box::new(render))
+        })
+    }
+
+    /// K-rate [`AudioParam`] in the [0, 1] range defining the decay time of the reverb
+    pub fn room_size(&self) -> &AudioParam {
+        &self.room_size
+    }
+
+    /// K-rate [`AudioParam`] in the [0, 1] range defining the absorption of
+    /// high frequencies
+    pub fn damping(&self) -> &AudioParam {
+        &self.damping
+    }
+
+    /// K-rate [`AudioParam`] defining the delay (in seconds) before the reverb
+    /// starts, in the [0, 1] range
+    pub fn pre_delay(&self) -> &AudioParam {
+        &self.pre_delay
+    }
+
+    /// A-rate [`AudioParam`] defining the gain of the reverberated signal
+    pub fn wet(&self) -> &AudioParam {
+        &self.wet
+    }
+
+    /// A-rate [`AudioParam`] defining the gain of the unprocessed signal
+    pub fn dry(&self) -> &AudioParam {
+        &self.dry
+    }
+}
+
+/// Delay line of a comb or allpass filter
+#[derive(Clone, Debug)]
+struct DelayLine {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl DelayLine {
+    fn new(tuning: usize, sample_rate: f32) -> Self {
+        let len = ((tuning as f32 * sample_rate / 44_100.).round() as usize).max(1);
+        Self {
+            buffer: vec![0.; len],
+            index: 0,
+        }
+    }
+
+    /// Read the oldest sample, and replace it with the given one
+    #[inline(always)]
+    fn tick(&mut self, f: impl FnOnce(f32) -> f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = f(delayed);
+        self.index += 1;
+        if self.index == self.buffer.len() {
+            self.index = 0;
+        }
+        delayed
+    }
+}
+
+/// Lowpass-feedback comb filter
+#[derive(Clone, Debug)]
+struct Comb {
+    delay_line: DelayLine,
+    filter_store: f32,
+}
+
+impl Comb {
+    #[inline(always)]
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let filter_store = &mut self.filter_store;
+        self.delay_line.tick(|delayed| {
+            *filter_store = delayed * (1. - damping) + *filter_store * damping;
+            input + *filter_store * feedback
+        })
+    }
+}
+
+/// Comb and allpass filters of a single output channel
+#[derive(Clone, Debug)]
+struct Tank {
+    combs: Vec<Comb>,
+    allpasses: Vec<DelayLine>,
+}
+
+impl Tank {
+    fn new(sample_rate: f32, spread: usize) -> Self {
+        let combs = COMB_TUNING
+            .iter()
+            .map(|&tuning| Comb {
+                delay_line: DelayLine::new(tuning + spread, sample_rate),
+                filter_store: 0.,
+            })
+            .collect();
+        let allpasses = ALLPASS_TUNING
+            .iter()
+            .map(|&tuning| DelayLine::new(tuning + spread, sample_rate))
+            .collect();
+
+        Self { combs, allpasses }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let comb_output: f32 = self
+            .combs
+            .iter_mut()
+            .map(|comb| comb.process(input, feedback, damping))
+            .sum();
+
+        self.allpasses.iter_mut().fold(comb_output, |acc, allpass| {
+            let delayed = allpass.tick(|delayed| acc + delayed * ALLPASS_FEEDBACK);
+            delayed - acc
+        })
+    }
+}
+
+struct ReverbRenderer {
+    room_size: AudioParamId,
+    damping: AudioParamId,
+    pre_delay: AudioParamId,
+    wet: AudioParamId,
+    dry: AudioParamId,
+    pre_delay_line: Vec<f32>,
+    pre_delay_index: usize,
+    tanks: [Tank; 2],
+    /// Length of the longest comb delay line, in frames
+    comb_delay_frames: usize,
+    /// Number of frames rendered since the input went silent
+    frames_since_input: usize,
+}
+
+impl AudioProcessor for ReverbRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        let pre_delay = params.get(&self.pre_delay)[0];
+        let pre_delay_frames = ((f64::from(pre_delay) * f64::from(scope.sample_rate)).round()
+            as usize)
+            .min(self.pre_delay_line.len() - 1);
+
+        // handle tail time
+        if input.is_silent() {
+            // the reverb tail has faded out, see below
+            if self.frames_since_input == usize::MAX {
+                output.make_silent();
+                return false;
+            }
+            self.frames_since_input += RENDER_QUANTUM_SIZE;
+        } else {
+            self.frames_since_input = 0;
+        }
+
+        let feedback = params.get(&self.room_size)[0] * SCALE_ROOM + OFFSET_ROOM;
+        let damping = params.get(&self.damping)[0] * SCALE_DAMPING;
+        let wet = params.get(&self.wet);
+        let dry = params.get(&self.dry);
+
+        let number_of_input_channels = input.number_of_channels();
+        let input_channels = input.channels();
+
+        output.set_number_of_channels(2);
+        let [left, right] = output.stereo_mut();
+
+        let mut tail_peak = 0_f32;
+
+        for (i, ((l, r), (&wet, &dry))) in left
+            .iter_mut()
+            .zip(right.iter_mut())
+            .zip(wet.iter().cycle().zip(dry.iter().cycle()))
+            .enumerate()
+        {
+            let (input_left, input_right) = if number_of_input_channels == 1 {
+                let sample = input_channels[0][i];
+                (sample, sample)
+            } else {
+                (input_channels[0][i], input_channels[1][i])
+            };
+
+            // mono feed, through the pre-delay
+            let feed = input_channels.iter().map(|c| c[i]).sum::<f32>()
+                / number_of_input_channels as f32
+                * FIXED_GAIN;
+            let len = self.pre_delay_line.len();
+            self.pre_delay_line[self.pre_delay_index] = feed;
+            let delayed =
+                self.pre_delay_line[(self.pre_delay_index + len - pre_delay_frames) % len];
+            self.pre_delay_index = (self.pre_delay_index + 1) % len;
+
+            let reverb_left = self.tanks[0].process(delayed, feedback, damping);
+            let reverb_right = self.tanks[1].process(delayed, feedback, damping);
+            tail_peak = tail_peak.max(reverb_left.abs()).max(reverb_right.abs());
+
+            let wet = wet * SCALE_WET;
+            *l = reverb_left * wet + input_left * dry;
+            *r = reverb_right * wet + input_right * dry;
+        }
+
+        // the pre-delay and comb delay lines have been flushed, and the reverb
+        // has faded out
+        if self.frames_since_input > pre_delay_frames + self.comb_delay_frames
+            && tail_peak < SILENCE_THRESHOLD
+        {
+            self.frames_since_input = usize::MAX;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44_100.;
+
+    fn render_impulse(options: ReverbOptions, length: usize) -> crate::AudioBuffer {
+        let context = OfflineAudioContext::new(2, length, SAMPLE_RATE);
+
+        let reverb = ReverbNode::new(&context, options);
+        reverb.connect(&context.destination());
+
+        let mut buffer = context.create_buffer(1, 1, SAMPLE_RATE);
+        buffer.copy_to_channel(&[1.], 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&reverb);
+        src.start();
+
+        context.start_rendering_sync()
+    }
+
+    fn energy(samples: &[f32]) -> f32 {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(2, 0, SAMPLE_RATE);
+        let reverb = ReverbNode::new(&context, ReverbOptions::default());
+
+        assert_float_eq!(reverb.room_size().value(), 0.5, abs <= 0.);
+        assert_float_eq!(reverb.damping().value(), 0.5, abs <= 0.);
+        assert_float_eq!(reverb.pre_delay().value(), 0., abs <= 0.);
+        assert_float_eq!(reverb.wet().value(), 0.33, abs <= 0.);
+        assert_float_eq!(reverb.dry().value(), 1., abs <= 0.);
+        assert_eq!(reverb.channel_count(), 2);
+    }
+
+    #[test]
+    fn test_dry_only() {
+        let options = ReverbOptions {
+            wet: 0.,
+            ..ReverbOptions::default()
+        };
+        let result = render_impulse(options, RENDER_QUANTUM_SIZE * 4);
+
+        for channel in 0..2 {
+            let data = result.get_channel_data(channel);
+            assert_float_eq!(data[0], 1., abs <= 0.);
+            assert_float_eq!(
+                data[1..],
+                [0.; RENDER_QUANTUM_SIZE * 4 - 1][..],
+                abs_all <= 0.
+            );
+        }
+    }
+
+    #[test]
+    fn test_impulse_response() {
+        let length = SAMPLE_RATE as usize;
+        let options = ReverbOptions {
+            dry: 0.,
+            ..ReverbOptions::default()
+        };
+        let result = render_impulse(options, length);
+        let left = result.get_channel_data(0);
+        let right = result.get_channel_data(1);
+
+        // nothing comes out before the shortest comb delay
+        assert_float_eq!(left[..1116], [0.; 1116][..], abs_all <= 0.);
+        assert!(left[1116..].iter().any(|&s| s != 0.));
+        // decorrelated stereo output
+        assert!(left.iter().zip(right).any(|(l, r)| l != r));
+        // decaying tail
+        let quarter = length / 4;
+        assert!(energy(&left[quarter..2 * quarter]) > energy(&left[3 * quarter..]));
+        assert!(left.iter().all(|s| s.is_finite()));
+    }
+
+    #[test]
+    fn test_pre_delay() {
+        let length = SAMPLE_RATE as usize / 4;
+        let pre_delay_frames = 1000;
+        let options = ReverbOptions {
+            dry: 0.,
+            pre_delay: pre_delay_frames as f32 / SAMPLE_RATE,
+            ..ReverbOptions::default()
+        };
+        let result = render_impulse(options, length);
+        let left = result.get_channel_data(0);
+
+        let onset = 1116 + pre_delay_frames;
+        assert_float_eq!(left[..onset], [0.; 2116][..], abs_all <= 0.);
+        assert!(left[onset] != 0.);
+    }
+
+    #[test]
+    fn test_room_size() {
+        let length = SAMPLE_RATE as usize;
+
+        let render = |room_size| {
+            let options = ReverbOptions {
+                dry: 0.,
+                room_size,
+                ..ReverbOptions::default()
+            };
+            let result = render_impulse(options, length);
+            energy(&result.get_channel_data(0)[length / 2..])
+        };
+
+        // a larger room has a longer tail
+        assert!(render(0.9) > 10. * render(0.1));
+    }
+}
@@ -69,7 +69,7 @@ fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
 /// - `gain_left = (x * PI / 2.).cos()`
 /// - `gain_right = (x * PI / 2.).sin()`
 #[inline(always)]
-fn get_stereo_gains(x: f32) -> [f32; 2] {
+pub(super) fn get_stereo_gains(x: f32) -> [f32; 2] {
     let idx = (x * TABLE_LENGTH_BY_4_F32) as usize;
     let gain_left = SINETABLE[idx + TABLE_LENGTH_BY_4_USIZE];
     let gain_right = SINETABLE[idx];
@@ -309,6 +309,10 @@ impl AudioProcessor for StereoPannerRenderer {
 
         false
     }
+
+    fn skip_when_silent(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,408 @@
+//! The mid/side stereo width control and renderer parts
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::bypass::Bypass;
+use super::{
+    AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode,
+    ChannelInterpretation,
+};
+
+/// Options for constructing a [`StereoWidthNode`]
+#[derive(Clone, Debug)]
+pub struct StereoWidthOptions {
+    /// initial value for the mid parameter
+    pub mid: f32,
+    /// initial value for the side parameter
+    pub side: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for StereoWidthOptions {
+    fn default() -> Self {
+        Self {
+            mid: 1.,
+            side: 1.,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Assert that the channel count is valid for the StereoWidthNode
+///
+/// # Panics
+///
+/// This function panics if given count is greater than 2
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count(count: usize) {
+    if count > 2 {
+        panic!("NotSupportedError: StereoWidthNode channel count cannot be greater than two");
+    }
+}
+
+/// Assert that the channel count mode is valid for the StereoWidthNode
+///
+/// # Panics
+///
+/// This function panics if given count mode is [`ChannelCountMode::Max`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
+    if mode == ChannelCountMode::Max {
+        panic!("NotSupportedError: StereoWidthNode channel count mode cannot be set to max");
+    }
+}
+
+/// `StereoWidthNode` adjusts the stereo image of its input
+///
+/// The left and right channels are encoded to mid (`(L + R) / 2`) and side
+/// (`(L - R) / 2`) signals, which are scaled independently before being decoded
+/// back to left and right. With the default gains of 1 the input is unchanged,
+/// a side gain of 0 collapses the image to mono and a side gain above 1 widens it.
+///
+/// A mono input only has a mid component, so it is only affected by the mid gain.
+/// The output is always stereo.
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::fs::File;
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{StereoWidthNode, StereoWidthOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let file = File::open("samples/think-stereo-48000.wav").unwrap();
+/// let buffer = context.decode_audio_data_sync(file).unwrap();
+///
+/// // widen the stereo image, with a slight level compensation
+/// let options = StereoWidthOptions {
+///     mid: 0.9,
+///     side: 1.5,
+///     ..StereoWidthOptions::default()
+/// };
+/// let width = StereoWidthNode::new(&context, options);
+/// width.connect(&context.destination());
+///
+/// let src = context.create_buffer_source();
+/// src.set_buffer(buffer);
+/// src.connect(&width);
+/// src.start();
+/// ```
+pub struct StereoWidthNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Gain applied to the mid signal
+    mid: AudioParam,
+    /// Gain applied to the side signal
+    side: AudioParam,
+    bypass: Bypass,
+}
+
+impl AudioNode for StereoWidthNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn set_channel_count_mode(&self, mode: ChannelCountMode) {
+        assert_valid_channel_count_mode(mode);
+        self.channel_config.set_count_mode(mode);
+    }
+
+    fn set_channel_count(&self, count: usize) {
+        assert_valid_channel_count(count);
+        self.channel_config.set_count(count);
+    }
+}
+
+impl AudioEffectNode for StereoWidthNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
+}
+
+impl StereoWidthNode {
+    /// returns a `StereoWidthNode` instance
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `options.channel_config.count` is greater than 2
+    /// * `options.channel_config.mode` is `ChannelCountMode::Max`
+    ///
+    pub fn new<C: BaseAudioContext>(context: &C, options: StereoWidthOptions) -> Self {
+        context.register(move |registration| {
+            assert_valid_channel_count_mode(options.channel_config.count_mode);
+            assert_valid_channel_count(options.channel_config.count);
+
+            let gain_options = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 1.,
+                automation_rate: AutomationRate::A,
+            };
+
+            let (mid_param, mid_proc) =
+                context.create_audio_param(gain_options.clone(), &registration);
+            mid_param.set_value(options.mid);
+
+            let (side_param, side_proc) = context.create_audio_param(gain_options, &registration);
+            side_param.set_value(options.side);
+
+            let renderer = StereoWidthRenderer {
+                mid: mid_proc,
+                side: side_proc,
+            };
+
+            let node = Self {
+                registration,
+                bypass: Bypass::default(),
+                channel_config: options.channel_config.into(),
+                mid: mid_param,
+                side: side_param,
+            };
+
+            let renderer = node.bypass.wrap(renderer, 0);
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Returns the gain audio parameter of the mid signal
+    #[must_use]
+    pub fn mid(&self) -> &AudioParam {
+        &self.mid
+    }
+
+    /// Returns the gain audio parameter of the side signal, i.e. the width
+    #[must_use]
+    pub fn side(&self) -> &AudioParam {
+        &self.side
+    }
+}
+
+/// `StereoWidthRenderer` represents the rendering part of `StereoWidthNode`
+struct StereoWidthRenderer {
+    mid: AudioParamId,
+    side: AudioParamId,
+}
+
+impl AudioProcessor for StereoWidthRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        if input.is_silent() {
+            output.make_silent();
+            return false;
+        }
+
+        // a-rate params
+        let mid_values = params.get(&self.mid);
+        let side_values = params.get(&self.side);
+
+        let number_of_channels = input.number_of_channels();
+        output.set_number_of_channels(2);
+        let [left, right] = output.stereo_mut();
+
+        match number_of_channels {
+            1 => {
+                left.iter_mut()
+                    .zip(right.iter_mut())
+                    .zip(mid_values.iter().cycle())
+                    .zip(input.channel_data(0).iter())
+                    .for_each(|(((l, r), &mid), &input)| {
+                        *l = input * mid;
+                        *r = input * mid;
+                    });
+            }
+            2 => {
+                left.iter_mut()
+                    .zip(right.iter_mut())
+                    .zip(mid_values.iter().cycle().zip(side_values.iter().cycle()))
+                    .zip(input.channel_data(0).iter())
+                    .zip(input.channel_data(1).iter())
+                    .for_each(|((((l, r), (&mid, &side)), &input_left), &input_right)| {
+                        let m = (input_left + input_right) * 0.5 * mid;
+                        let s = (input_left - input_right) * 0.5 * side;
+
+                        *l = m + s;
+                        *r = m - s;
+                    });
+            }
+            _ => panic!("StereoWidthNode should not have more than 2 channels to process"),
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    fn render_stereo(
+        options: StereoWidthOptions,
+        left: &[f32],
+        right: &[f32],
+    ) -> crate::AudioBuffer {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44_100.);
+
+        let width = StereoWidthNode::new(&context, options);
+        width.connect(&context.destination());
+
+        let mut buffer = context.create_buffer(2, left.len(), 44_100.);
+        buffer.copy_to_channel(left, 0);
+        buffer.copy_to_channel(right, 1);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&width);
+        src.start();
+
+        context.start_rendering_sync()
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let width = StereoWidthNode::new(&context, StereoWidthOptions::default());
+
+        assert_float_eq!(width.mid().value(), 1., abs <= 0.);
+        assert_float_eq!(width.side().value(), 1., abs <= 0.);
+        assert_eq!(width.channel_count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let width = StereoWidthNode::new(&context, StereoWidthOptions::default());
+        width.set_channel_count(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "NotSupportedError")]
+    fn test_invalid_channel_count_mode() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let width = StereoWidthNode::new(&context, StereoWidthOptions::default());
+        width.set_channel_count_mode(ChannelCountMode::Max);
+    }
+
+    #[test]
+    fn test_stereo_width() {
+        let left = [1., 0.5, 0., -1.];
+        let right = [0., 0.5, 1., 1.];
+
+        // unity gains, the input is unchanged
+        let result = render_stereo(StereoWidthOptions::default(), &left, &right);
+        assert_float_eq!(result.get_channel_data(0)[..4], left[..], abs_all <= 1e-7);
+        assert_float_eq!(result.get_channel_data(1)[..4], right[..], abs_all <= 1e-7);
+
+        // no side, mono output
+        let options = StereoWidthOptions {
+            side: 0.,
+            ..StereoWidthOptions::default()
+        };
+        let result = render_stereo(options, &left, &right);
+        let expected = [0.5, 0.5, 0.5, 0.];
+        assert_float_eq!(
+            result.get_channel_data(0)[..4],
+            expected[..],
+            abs_all <= 1e-7
+        );
+        assert_float_eq!(
+            result.get_channel_data(1)[..4],
+            expected[..],
+            abs_all <= 1e-7
+        );
+
+        // no mid, only the difference remains
+        let options = StereoWidthOptions {
+            mid: 0.,
+            ..StereoWidthOptions::default()
+        };
+        let result = render_stereo(options, &left, &right);
+        let expected_left = [0.5, 0., -0.5, -1.];
+        let expected_right = [-0.5, 0., 0.5, 1.];
+        assert_float_eq!(
+            result.get_channel_data(0)[..4],
+            expected_left[..],
+            abs_all <= 1e-7
+        );
+        assert_float_eq!(
+            result.get_channel_data(1)[..4],
+            expected_right[..],
+            abs_all <= 1e-7
+        );
+    }
+
+    #[test]
+    fn test_mono_input() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44_100.);
+
+        let options = StereoWidthOptions {
+            mid: 0.5,
+            side: 2.,
+            ..StereoWidthOptions::default()
+        };
+        let width = StereoWidthNode::new(&context, options);
+        width.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&width);
+        src.start();
+
+        let result = context.start_rendering_sync();
+        assert_float_eq!(
+            result.get_channel_data(0),
+            &[0.5; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            result.get_channel_data(1),
+            &[0.5; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+}
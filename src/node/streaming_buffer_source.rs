@@ -0,0 +1,472 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use creek::read::ReadError;
+use creek::{ReadDiskStream, SeekMode, SymphoniaDecoder};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::control::{frame_index, Scheduler};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
+
+/// Options for constructing a [`StreamingBufferSourceNode`]
+#[derive(Clone, Debug, Default)]
+pub struct StreamingBufferSourceOptions {
+    pub loop_: bool,
+}
+
+/// `StreamingBufferSourceNode` plays back an audio file from disk, without
+/// loading it entirely in memory.
+///
+/// Contrary to the [`AudioBufferSourceNode`](super::AudioBufferSourceNode),
+/// which requires the whole file to be decoded into an
+/// [`AudioBuffer`](crate::AudioBuffer), the file is read and decoded ahead of
+/// the playhead by a worker thread into a prefetch ring buffer. This allows to
+/// play hour-long files, with the scheduling of the other source nodes.
+///
+/// The playback is resampled to the sample rate of the audio context if
+/// needed. When the disk cannot keep up with the playback, the node outputs
+/// silence until enough data has been prefetched. An `OfflineAudioContext`
+/// waits for the data instead.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{StreamingBufferSourceNode, StreamingBufferSourceOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let options = StreamingBufferSourceOptions::default();
+/// let src = StreamingBufferSourceNode::new(&context, "samples/major-scale.ogg", options)
+///     .unwrap();
+/// src.connect(&context.destination());
+///
+/// // start in one second, two seconds into the file
+/// src.start_at_with_offset(context.current_time() + 1., 2.);
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct StreamingBufferSourceNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    scheduler: Scheduler,
+    loop_: Arc<AtomicBool>,
+    duration: f64,
+    stream: Mutex<Option<ReadDiskStream<SymphoniaDecoder>>>,
+    sender: Sender<ReadDiskStream<SymphoniaDecoder>>,
+}
+
+impl AudioNode for StreamingBufferSourceNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for StreamingBufferSourceNode {
+    fn start(&self) {
+        let when = self.registration.context().current_time();
+        self.start_at_with_offset(when, 0.);
+    }
+
+    fn start_at(&self, when: f64) {
+        self.start_at_with_offset(when, 0.);
+    }
+
+    fn stop(&self) {
+        let when = self.registration.context().current_time();
+        self.stop_at(when);
+    }
+
+    fn stop_at(&self, when: f64) {
+        self.scheduler.stop_at(when);
+    }
+}
+
+impl StreamingBufferSourceNode {
+    /// Create a new `StreamingBufferSourceNode` playing the file at `path`
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the file cannot be opened or decoded.
+    pub fn new<C: BaseAudioContext, P: Into<PathBuf>>(
+        context: &C,
+        path: P,
+        options: StreamingBufferSourceOptions,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut stream = ReadDiskStream::<SymphoniaDecoder>::new(path, 0, Default::default())?;
+
+        // keep the start of the file in the cache with index `0`, to loop seamlessly
+        let _ = stream.cache(0, 0);
+
+        let info = stream.info();
+        let file_sample_rate = info
+            .sample_rate
+            .map(|sample_rate| sample_rate as f32)
+            .unwrap_or_else(|| context.sample_rate());
+        let number_of_channels = usize::from(info.num_channels);
+        let duration = info.num_frames as f64 / f64::from(file_sample_rate);
+        // the worker thread prefetches blocks of this size
+        let block_size = stream.block_size();
+
+        let mut node = context.register(move |registration| {
+            let scheduler = Scheduler::new();
+            let loop_ = Arc::new(AtomicBool::new(options.loop_));
+
+            // Channel to send the stream to the renderer, once it is seeked to the start offset
+            let (sender, receiver) = crossbeam_channel::bounded(1);
+
+            let ratio = f64::from(file_sample_rate) / f64::from(context.sample_rate());
+            let capacity = block_size + (RENDER_QUANTUM_SIZE as f64 * ratio) as usize + 2;
+
+            let render = StreamingBufferSourceRenderer {
+                scheduler: scheduler.clone(),
+                loop_: Arc::clone(&loop_),
+                receiver,
+                stream: None,
+                blocking: context.base().offline(),
+                ratio,
+                frames: vec![Vec::with_capacity(capacity); number_of_channels.max(1)],
+                position: 0.,
+                end_of_file: false,
+                ended_triggered: false,
+            };
+
+            let node = StreamingBufferSourceNode {
+                registration,
+                channel_config: ChannelConfig::default(),
+                scheduler,
+                loop_,
+                duration,
+                stream: Mutex::new(None),
+                sender,
+            };
+
+            (node, Box::new(render))
+        });
+
+        node.stream = Mutex::new(Some(stream));
+        Ok(node)
+    }
+
+    /// Start the playback at the given time and with a given offset, in seconds
+    ///
+    /// The file is read from the offset before this method returns, so the
+    /// playback can start on time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source was already started
+    pub fn start_at_with_offset(&self, start: f64, offset: f64) {
+        let mut stream = self
+            .stream
+            .lock()
+            .unwrap()
+            .take()
+            .expect("InvalidStateError: Cannot call `start` twice");
+
+        let sample_rate = stream.info().sample_rate.unwrap_or(1) as f64;
+        let frame = (offset.max(0.) * sample_rate) as usize;
+        // errors are reported when reading the stream
+        let _ = stream.seek(frame, SeekMode::default());
+        let _ = stream.block_until_ready();
+
+        let _ = self.sender.send(stream); // can fail when render thread shut down
+        self.scheduler.start_at(start);
+    }
+
+    /// Duration of the file, in seconds
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    /// Defines if the playback of the file should be looped
+    pub fn loop_(&self) -> bool {
+        self.loop_.load(Ordering::SeqCst)
+    }
+
+    pub fn set_loop(&self, value: bool) {
+        self.loop_.store(value, Ordering::SeqCst);
+    }
+}
+
+struct StreamingBufferSourceRenderer {
+    scheduler: Scheduler,
+    loop_: Arc<AtomicBool>,
+    receiver: Receiver<ReadDiskStream<SymphoniaDecoder>>,
+    stream: Option<ReadDiskStream<SymphoniaDecoder>>,
+    /// Wait for the stream when it is buffering, when rendering offline
+    blocking: bool,
+    /// Ratio between the sample rates of the file and of the context
+    ratio: f64,
+    /// Frames of each channel read from the stream and not played yet
+    frames: Vec<Vec<f32>>,
+    /// Fractional read position in `frames`
+    position: f64,
+    /// Set when the whole file has been read
+    end_of_file: bool,
+    ended_triggered: bool,
+}
+
+impl StreamingBufferSourceRenderer {
+    /// Read from the stream until `frames` holds at least `length` frames
+    ///
+    /// Returns `false` when the stream is still buffering.
+    fn fill(&mut self, length: usize) -> bool {
+        let stream = match self.stream.as_mut() {
+            Some(stream) => stream,
+            None => return false,
+        };
+
+        while self.frames[0].len() < length && !self.end_of_file {
+            match stream.is_ready() {
+                Ok(true) => (),
+                Ok(false) if !self.blocking => return false,
+                Ok(false) => {
+                    if let Err(e) = stream.block_until_ready() {
+                        log::error!("streaming buffer source: {}", e);
+                        self.end_of_file = true;
+                        break;
+                    }
+                }
+                Err(e) => {
+                    log::error!("streaming buffer source: {}", e);
+                    self.end_of_file = true;
+                    break;
+                }
+            }
+
+            let missing = length - self.frames[0].len();
+            let reached_end_of_file = match stream.read(missing) {
+                Ok(data) => {
+                    self.frames.iter_mut().enumerate().for_each(|(i, frames)| {
+                        frames.extend_from_slice(data.read_channel(i.min(data.num_channels() - 1)))
+                    });
+                    data.reached_end_of_file()
+                }
+                Err(ReadError::EndOfFile) => true,
+                Err(e) => {
+                    log::error!("streaming buffer source: {}", e);
+                    self.end_of_file = true;
+                    break;
+                }
+            };
+
+            if reached_end_of_file {
+                if self.loop_.load(Ordering::SeqCst) {
+                    let _ = stream.seek(0, SeekMode::default());
+                } else {
+                    self.end_of_file = true;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl AudioProcessor for StreamingBufferSourceRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        // handle the stream, seeked to the start offset
+        if let Ok(stream) = self.receiver.try_recv() {
+            self.stream = Some(stream);
+        }
+
+        let dt = 1. / scope.sample_rate as f64;
+        let next_block_time = scope.current_time + dt * RENDER_QUANTUM_SIZE as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if start_time >= next_block_time {
+            output.make_silent();
+            // keep alive until started, unless the control handle is gone
+            return !self.scheduler.is_abandoned();
+        }
+
+        let sample_rate = scope.sample_rate as f64;
+        let start_index = frame_index(start_time, scope.current_time, sample_rate);
+        let stop_index = frame_index(stop_time, scope.current_time, sample_rate);
+
+        // frames needed to interpolate the output of this render quantum
+        let length = (self.position + (stop_index - start_index) as f64 * self.ratio) as usize + 2;
+        if !self.fill(length) {
+            // buffering, resume once the data is available
+            output.make_silent();
+            return stop_time >= next_block_time;
+        }
+
+        output.set_number_of_channels(self.frames.len());
+        output.channels_mut().iter_mut().for_each(|c| c.fill(0.));
+
+        let available = self.frames[0].len();
+        let mut finished = false;
+        for i in start_index..stop_index {
+            let floored = self.position.floor();
+            let index = floored as usize;
+            if index >= available {
+                finished = self.end_of_file;
+                break;
+            }
+
+            let k = (self.position - floored) as f32;
+            output
+                .channels_mut()
+                .iter_mut()
+                .zip(self.frames.iter())
+                .for_each(|(o, frames)| {
+                    let next = frames.get(index + 1).copied().unwrap_or(0.);
+                    o[i] = (1. - k) * frames[index] + k * next;
+                });
+
+            self.position += self.ratio;
+        }
+
+        // release the played frames
+        let consumed = (self.position.floor() as usize).min(available);
+        self.frames.iter_mut().for_each(|frames| {
+            frames.drain(..consumed);
+        });
+        self.position -= consumed as f64;
+
+        finished |= self.end_of_file && self.frames[0].is_empty();
+        let still_running = !finished && stop_time >= next_block_time;
+
+        if !still_running && !self.ended_triggered {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+        }
+
+        still_running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::AudioBuffer;
+
+    use super::*;
+
+    fn decode_sample() -> AudioBuffer {
+        let context = OfflineAudioContext::new(1, 1, 44100.);
+        let file = std::fs::File::open("samples/sample.wav").unwrap();
+        context.decode_audio_data_sync(file).unwrap()
+    }
+
+    fn max_error(output: &[f32], expected: &[f32]) -> f32 {
+        assert_eq!(output.len(), expected.len());
+        output
+            .iter()
+            .zip(expected)
+            .map(|(o, e)| (o - e).abs())
+            .fold(0., f32::max)
+    }
+
+    fn play(context: OfflineAudioContext, options: StreamingBufferSourceOptions) -> AudioBuffer {
+        let src = StreamingBufferSourceNode::new(&context, "samples/sample.wav", options).unwrap();
+        src.connect(&context.destination());
+        src.start_at(100. / context.sample_rate() as f64);
+        context.start_rendering_sync()
+    }
+
+    #[test]
+    fn test_playback() {
+        let expected = decode_sample();
+        let length = expected.length();
+        let context = OfflineAudioContext::new(2, length + 1000, 44100.);
+
+        let options = StreamingBufferSourceOptions::default();
+        let output = play(context, options);
+
+        for c in 0..2 {
+            let channel = output.get_channel_data(c);
+            assert_eq!(max_error(&channel[..100], &[0.; 100]), 0.);
+            let error = max_error(&channel[100..100 + length], expected.get_channel_data(c));
+            assert!(error < 1e-6, "error {}", error);
+            assert_eq!(max_error(&channel[100 + length..], &[0.; 900]), 0.);
+        }
+    }
+
+    #[test]
+    fn test_offset() {
+        let expected = decode_sample();
+        let context = OfflineAudioContext::new(2, 1000, 44100.);
+
+        let options = StreamingBufferSourceOptions::default();
+        let src = StreamingBufferSourceNode::new(&context, "samples/sample.wav", options).unwrap();
+        src.connect(&context.destination());
+        src.start_at_with_offset(0., 5000. / 44100.);
+
+        let output = context.start_rendering_sync();
+        let error = max_error(
+            output.get_channel_data(0),
+            &expected.get_channel_data(0)[5000..6000],
+        );
+        assert!(error < 1e-6, "error {}", error);
+    }
+
+    #[test]
+    fn test_loop() {
+        let expected = decode_sample();
+        let length = expected.length();
+        let context = OfflineAudioContext::new(2, 2 * length + 100, 44100.);
+
+        let options = StreamingBufferSourceOptions { loop_: true };
+        let output = play(context, options);
+
+        let channel = output.get_channel_data(0);
+        let error = max_error(&channel[100 + length..], expected.get_channel_data(0));
+        assert!(error < 1e-6, "error {}", error);
+    }
+
+    #[test]
+    fn test_resample() {
+        let length = decode_sample().length();
+        let context = OfflineAudioContext::new(2, 2 * length + 1000, 88200.);
+
+        let options = StreamingBufferSourceOptions::default();
+        let output = play(context, options);
+
+        // the playback lasts twice as many frames at twice the sample rate
+        let channel = output.get_channel_data(0);
+        let end = 100 + 2 * length;
+        assert!(channel[end - 1000..end - 10].iter().any(|s| *s != 0.));
+        assert_eq!(max_error(&channel[end..], &[0.; 900]), 0.);
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let context = OfflineAudioContext::new(1, 128, 44100.);
+        let options = StreamingBufferSourceOptions::default();
+        assert!(StreamingBufferSourceNode::new(&context, "samples/missing.wav", options).is_err());
+    }
+}
@@ -0,0 +1,695 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::buffer::AudioBuffer;
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::control::{frame_index, Scheduler};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
+
+/// Playback state waiting to be picked up by the renderer
+type PendingState = Arc<Mutex<Option<TimeStretchRendererInner>>>;
+
+/// Length of the grains, in sample-frames
+const GRAIN_SIZE: usize = 2048;
+/// Distance between two consecutive output grains, in sample-frames
+const HOP_SIZE: usize = GRAIN_SIZE / 2;
+/// Maximum deviation from the nominal grain position when searching for the
+/// best overlap, in sample-frames
+const SEARCH_TOLERANCE: isize = 256;
+/// Decimation factor used when computing the cross-correlation, and step of
+/// the coarse search over the candidate positions
+///
+/// Each hop evaluates `2 * SEARCH_TOLERANCE / SEARCH_STEP + 1` coarse and
+/// `2 * (SEARCH_STEP - 1)` refined candidates, with `HOP_SIZE / SEARCH_STEP`
+/// multiply-adds each: about 35k multiply-adds per 1024 output frames.
+const SEARCH_STEP: usize = 4;
+
+/// Options for constructing a [`TimeStretchNode`]
+#[derive(Clone, Debug)]
+pub struct TimeStretchOptions {
+    pub buffer: Option<AudioBuffer>,
+    pub loop_: bool,
+    pub rate: f32,
+    pub pitch: f32,
+}
+
+impl Default for TimeStretchOptions {
+    fn default() -> Self {
+        Self {
+            buffer: None,
+            loop_: false,
+            rate: 1.,
+            pitch: 0.,
+        }
+    }
+}
+
+/// `TimeStretchNode` plays back an [`AudioBuffer`] with independent control
+/// over its speed and its pitch.
+///
+/// Contrary to the `playback_rate` of the
+/// [`AudioBufferSourceNode`](super::AudioBufferSourceNode), changing the `rate`
+/// of this node does not alter the pitch of the played back audio, and the
+/// `pitch` can be shifted without changing the duration of the playback.
+///
+/// The processing is based on WSOLA (Waveform Similarity Overlap-Add): Hann
+/// windowed grains of the buffer are overlap-added at a fixed hop size, while
+/// the position in the buffer advances according to `rate`. Each grain is
+/// aligned on the previous one within a small search window to avoid phase
+/// cancellations. Pitch shifting is achieved by resampling the grains.
+///
+/// The alignment search runs on the render thread once per 1024 output frames
+/// and costs about 35k multiply-adds, on top of the interpolated reads of the
+/// grains of each channel.
+///
+/// This node is only available with the `time-stretch` feature and is not
+/// part of the Web Audio API specification.
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::fs::File;
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{TimeStretchNode, TimeStretchOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let file = File::open("samples/sample.wav").unwrap();
+/// let buffer = context.decode_audio_data_sync(file).unwrap();
+///
+/// let options = TimeStretchOptions {
+///     buffer: Some(buffer),
+///     ..TimeStretchOptions::default()
+/// };
+/// let src = TimeStretchNode::new(&context, options);
+/// src.connect(&context.destination());
+///
+/// // play twice as slow, one octave higher
+/// src.rate().set_value(0.5);
+/// src.pitch().set_value(1200.);
+/// src.start();
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct TimeStretchNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    rate: AudioParam,
+    pitch: AudioParam,
+    scheduler: Scheduler,
+    loop_: Arc<AtomicBool>,
+    buffer: Mutex<Option<AudioBuffer>>,
+    /// State for the new buffer, the latest one wins when set multiple times in between
+    /// two render quanta
+    pending: PendingState,
+    /// States replaced by the renderer, to be dropped on the control thread
+    garbage: Receiver<TimeStretchRendererInner>,
+}
+
+impl AudioNode for TimeStretchNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for TimeStretchNode {
+    fn start(&self) {
+        let when = self.registration.context().current_time();
+        self.start_at(when);
+    }
+
+    fn start_at(&self, when: f64) {
+        self.scheduler.start_at(when);
+    }
+
+    fn stop(&self) {
+        let when = self.registration.context().current_time();
+        self.stop_at(when);
+    }
+
+    fn stop_at(&self, when: f64) {
+        self.scheduler.stop_at(when);
+    }
+}
+
+impl TimeStretchNode {
+    pub fn new<C: BaseAudioContext>(context: &C, options: TimeStretchOptions) -> Self {
+        context.register(move |registration| {
+            let TimeStretchOptions {
+                buffer,
+                loop_,
+                rate,
+                pitch,
+            } = options;
+
+            let rate_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: f32::MAX,
+                default_value: 1.,
+                automation_rate: AutomationRate::K,
+            };
+            let (rate_param, rate_proc) =
+                context.create_audio_param(rate_param_opts, &registration);
+            rate_param.set_value(rate);
+
+            let pitch_param_opts = AudioParamDescriptor {
+                min_value: -2400.,
+                max_value: 2400.,
+                default_value: 0.,
+                automation_rate: AutomationRate::K,
+            };
+            let (pitch_param, pitch_proc) =
+                context.create_audio_param(pitch_param_opts, &registration);
+            pitch_param.set_value(pitch);
+
+            let scheduler = Scheduler::new();
+            let loop_ = Arc::new(AtomicBool::new(loop_));
+
+            // The renderer picks up at most one state per render quantum and the control
+            // thread collects the replaced one before posting the next, so a capacity of 1
+            // suffices to return the replaced states
+            let pending: PendingState = Arc::new(Mutex::new(None));
+            let (garbage_sender, garbage) = crossbeam_channel::bounded(1);
+
+            let render = TimeStretchRenderer {
+                rate: rate_proc,
+                pitch: pitch_proc,
+                scheduler: scheduler.clone(),
+                loop_: Arc::clone(&loop_),
+                pending: Arc::clone(&pending),
+                garbage: garbage_sender,
+                inner: None,
+                ended_triggered: false,
+            };
+
+            let node = TimeStretchNode {
+                registration,
+                channel_config: ChannelConfig::default(),
+                rate: rate_param,
+                pitch: pitch_param,
+                scheduler,
+                loop_,
+                buffer: Mutex::new(None),
+                pending,
+                garbage,
+            };
+
+            if let Some(buffer) = buffer {
+                node.set_buffer(buffer);
+            }
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Current buffer value (nullable)
+    #[allow(clippy::missing_panics_doc)]
+    pub fn buffer(&self) -> Option<AudioBuffer> {
+        self.buffer.lock().unwrap().clone()
+    }
+
+    /// Provide an [`AudioBuffer`] as the source of data to be played back
+    ///
+    /// The buffer is resampled to the sample rate of the audio context if
+    /// needed. Setting a new buffer during playback restarts the playback from
+    /// the beginning of the new buffer.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn set_buffer(&self, mut buffer: AudioBuffer) {
+        buffer.resample(self.context().sample_rate());
+
+        let inner = TimeStretchRendererInner::new(buffer.clone());
+
+        // drop the states replaced by the renderer, before it can replace the next one
+        self.garbage.try_iter().for_each(drop);
+        // a pending state that was never picked up is dropped here as well
+        *self.pending.lock().unwrap() = Some(inner);
+
+        *self.buffer.lock().unwrap() = Some(buffer);
+    }
+
+    /// K-rate [`AudioParam`] defining the speed factor of the playback,
+    /// without affecting the pitch
+    pub fn rate(&self) -> &AudioParam {
+        &self.rate
+    }
+
+    /// K-rate [`AudioParam`] defining the transposition of the playback in
+    /// cents, without affecting the speed
+    pub fn pitch(&self) -> &AudioParam {
+        &self.pitch
+    }
+
+    /// Defines if the playback of the buffer should be looped
+    pub fn loop_(&self) -> bool {
+        self.loop_.load(Ordering::SeqCst)
+    }
+
+    pub fn set_loop(&self, value: bool) {
+        self.loop_.store(value, Ordering::SeqCst);
+    }
+}
+
+/// Linear interpolated read of `data` at the fractional position `position`
+#[inline]
+fn read_sample(data: &[f32], position: f64, loop_: bool) -> f32 {
+    let length = data.len() as isize;
+    let floored = position.floor();
+    let k = (position - floored) as f32;
+    let index = floored as isize;
+
+    let sample = |i: isize| -> f32 {
+        if loop_ {
+            data[i.rem_euclid(length) as usize]
+        } else if i >= 0 && i < length {
+            data[i as usize]
+        } else {
+            0.
+        }
+    };
+
+    let prev = sample(index);
+    let next = sample(index + 1);
+
+    (1. - k) * prev + k * next
+}
+
+/// Playback state that depends on the buffer, built on the control thread
+/// to avoid allocations in the render thread
+struct TimeStretchRendererInner {
+    buffer: AudioBuffer,
+    window: Vec<f32>,
+    /// Overlap-add accumulator of each channel, `GRAIN_SIZE` long
+    accumulator: Vec<Vec<f32>>,
+    /// Completed output of each channel, `HOP_SIZE` long
+    ready: Vec<Vec<f32>>,
+    /// Read index in `ready`, which is drained when equal to `HOP_SIZE`
+    ready_index: usize,
+    /// Nominal position in the buffer of the next grain
+    position: f64,
+    /// Position in the buffer of the previous grain
+    previous_grain: Option<f64>,
+    /// Set when the end of the buffer has been reached, the accumulator is
+    /// then flushed before the playback ends
+    flushing: bool,
+    finished: bool,
+}
+
+impl TimeStretchRendererInner {
+    fn new(buffer: AudioBuffer) -> Self {
+        let number_of_channels = buffer.number_of_channels();
+
+        // periodic Hann window, which sums to unity when overlapped at half its length
+        let window = (0..GRAIN_SIZE)
+            .map(|i| {
+                let phase = 2. * std::f32::consts::PI * i as f32 / GRAIN_SIZE as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+
+        Self {
+            buffer,
+            window,
+            accumulator: vec![vec![0.; GRAIN_SIZE]; number_of_channels],
+            ready: vec![vec![0.; HOP_SIZE]; number_of_channels],
+            ready_index: HOP_SIZE,
+            position: 0.,
+            previous_grain: None,
+            flushing: false,
+            finished: false,
+        }
+    }
+
+    /// Find the grain position close to `nominal` whose content best matches
+    /// the natural continuation of the previous grain
+    fn search_grain_position(&self, nominal: f64, natural: f64, pitch: f64, loop_: bool) -> f64 {
+        let data = self.buffer.get_channel_data(0);
+
+        let score = |candidate: f64| {
+            let mut correlation = 0.;
+            let mut energy = 0.;
+
+            for k in (0..HOP_SIZE).step_by(SEARCH_STEP) {
+                let offset = k as f64 * pitch;
+                let c = read_sample(data, candidate + offset, loop_);
+                let n = read_sample(data, natural + offset, loop_);
+                correlation += c * n;
+                energy += c * c;
+            }
+
+            correlation / (energy + 1e-9).sqrt()
+        };
+
+        // coarse search over the whole window, refined around the best candidate
+        let mut best_delta = 0;
+        let mut best_score = f32::MIN;
+        for delta in (-SEARCH_TOLERANCE..=SEARCH_TOLERANCE).step_by(SEARCH_STEP) {
+            let score = score(nominal + delta as f64);
+            if score > best_score {
+                best_score = score;
+                best_delta = delta;
+            }
+        }
+
+        let coarse_delta = best_delta;
+        let refine = SEARCH_STEP as isize - 1;
+        for delta in (coarse_delta - refine)..=(coarse_delta + refine) {
+            if delta == coarse_delta || delta.abs() > SEARCH_TOLERANCE {
+                continue;
+            }
+            let score = score(nominal + delta as f64);
+            if score > best_score {
+                best_score = score;
+                best_delta = delta;
+            }
+        }
+
+        nominal + best_delta as f64
+    }
+
+    /// Overlap-add the next grain and fill `ready` with the completed samples
+    fn next_hop(&mut self, rate: f64, pitch: f64, loop_: bool) {
+        let length = self.buffer.length() as f64;
+
+        if self.flushing {
+            self.finished = true;
+            return;
+        }
+
+        if loop_ && length > 0. {
+            while self.position >= length {
+                self.position -= length;
+                if let Some(previous_grain) = self.previous_grain.as_mut() {
+                    *previous_grain -= length;
+                }
+            }
+        }
+
+        if self.position >= length {
+            // no new grain, emit the remaining overlapped samples
+            self.flushing = true;
+        } else {
+            let grain_position = match self.previous_grain {
+                None => self.position,
+                Some(previous_grain) => {
+                    let natural = previous_grain + HOP_SIZE as f64 * pitch;
+                    self.search_grain_position(self.position, natural, pitch, loop_)
+                }
+            };
+
+            let window = &self.window;
+            for (channel_number, acc) in self.accumulator.iter_mut().enumerate() {
+                let data = self.buffer.get_channel_data(channel_number);
+                acc.iter_mut()
+                    .zip(window.iter())
+                    .enumerate()
+                    .for_each(|(k, (a, w))| {
+                        let position = grain_position + k as f64 * pitch;
+                        *a += w * read_sample(data, position, loop_);
+                    });
+            }
+
+            self.previous_grain = Some(grain_position);
+            self.position += HOP_SIZE as f64 * rate;
+        }
+
+        self.accumulator
+            .iter_mut()
+            .zip(self.ready.iter_mut())
+            .for_each(|(acc, ready)| {
+                ready.copy_from_slice(&acc[..HOP_SIZE]);
+                acc.copy_within(HOP_SIZE.., 0);
+                acc[GRAIN_SIZE - HOP_SIZE..].fill(0.);
+            });
+
+        self.ready_index = 0;
+    }
+}
+
+struct TimeStretchRenderer {
+    rate: AudioParamId,
+    pitch: AudioParamId,
+    scheduler: Scheduler,
+    loop_: Arc<AtomicBool>,
+    pending: PendingState,
+    garbage: Sender<TimeStretchRendererInner>,
+    inner: Option<TimeStretchRendererInner>,
+    ended_triggered: bool,
+}
+
+impl AudioProcessor for TimeStretchRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        // handle new buffer, the control thread holds the lock only briefly
+        let pending = self
+            .pending
+            .try_lock()
+            .ok()
+            .and_then(|mut pending| pending.take());
+        if let Some(inner) = pending {
+            if let Some(previous) = self.inner.replace(inner) {
+                // hand the previous state back to be dropped on the control thread, the
+                // channel is only full if the control thread did not collect the last one
+                let _ = self.garbage.try_send(previous);
+            }
+        }
+
+        let dt = 1. / scope.sample_rate as f64;
+        let next_block_time = scope.current_time + dt * RENDER_QUANTUM_SIZE as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if start_time >= next_block_time {
+            output.make_silent();
+            // keep alive until started, unless the control handle is gone
+            return !self.scheduler.is_abandoned();
+        }
+
+        let inner = match self.inner.as_mut() {
+            Some(inner) if !inner.finished && inner.buffer.number_of_channels() > 0 => inner,
+            _ => {
+                output.make_silent();
+                let finished = self.inner.as_ref().map(|i| i.finished).unwrap_or(false);
+                let still_running = !finished && stop_time >= next_block_time;
+                if !still_running && !self.ended_triggered {
+                    scope.send_ended_event();
+                    self.ended_triggered = true;
+                }
+                return still_running;
+            }
+        };
+
+        let rate = f64::from(params.get(&self.rate)[0]);
+        let pitch = 2_f64.powf(f64::from(params.get(&self.pitch)[0]) / 1200.);
+        let loop_ = self.loop_.load(Ordering::SeqCst);
+
+        output.set_number_of_channels(inner.buffer.number_of_channels());
+        output.channels_mut().iter_mut().for_each(|c| c.fill(0.));
+
+        let sample_rate = scope.sample_rate as f64;
+        let start_index = frame_index(start_time, scope.current_time, sample_rate);
+        let stop_index = frame_index(stop_time, scope.current_time, sample_rate);
+
+        for i in start_index..stop_index {
+            if inner.ready_index == HOP_SIZE {
+                inner.next_hop(rate, pitch, loop_);
+            }
+
+            if inner.finished {
+                break;
+            }
+
+            let ready_index = inner.ready_index;
+            output
+                .channels_mut()
+                .iter_mut()
+                .zip(inner.ready.iter())
+                .for_each(|(o, ready)| o[i] = ready[ready_index]);
+
+            inner.ready_index += 1;
+        }
+
+        let still_running = !inner.finished && stop_time >= next_block_time;
+
+        if !still_running && !self.ended_triggered {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+        }
+
+        still_running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::*;
+
+    fn sine_buffer(frequency: f32, length: usize, sample_rate: f32) -> AudioBuffer {
+        let samples = (0..length)
+            .map(|i| (2. * std::f32::consts::PI * frequency * i as f32 / sample_rate).sin())
+            .collect();
+        AudioBuffer::from(vec![samples], sample_rate)
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|w| (w[0] < 0.) != (w[1] < 0.))
+            .count()
+    }
+
+    #[test]
+    fn test_unity_rate_and_pitch() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 48000, sample_rate);
+
+        let options = TimeStretchOptions {
+            buffer: Some(sine_buffer(440., 48000, sample_rate)),
+            ..TimeStretchOptions::default()
+        };
+        let src = TimeStretchNode::new(&context, options);
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // skip the fade-in of the first grain
+        let steady = &channel[GRAIN_SIZE..40000];
+        assert_float_eq!(rms(steady), std::f32::consts::FRAC_1_SQRT_2, abs <= 0.02);
+        let crossings = zero_crossings(steady) as f32;
+        let expected = 2. * 440. * steady.len() as f32 / sample_rate;
+        assert_float_eq!(crossings, expected, r2nd <= 0.02);
+    }
+
+    #[test]
+    fn test_set_buffer_twice() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 24000, sample_rate);
+
+        // the control thread does not block while the renderer is not running, the last
+        // buffer wins
+        let src = TimeStretchNode::new(&context, TimeStretchOptions::default());
+        src.set_buffer(sine_buffer(440., 48000, sample_rate));
+        src.set_buffer(sine_buffer(880., 48000, sample_rate));
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let steady = &output.get_channel_data(0)[GRAIN_SIZE..20000];
+        let crossings = zero_crossings(steady) as f32;
+        let expected = 2. * 880. * steady.len() as f32 / sample_rate;
+        assert_float_eq!(crossings, expected, r2nd <= 0.02);
+    }
+
+    #[test]
+    fn test_rate_changes_duration() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 48000, sample_rate);
+
+        let options = TimeStretchOptions {
+            buffer: Some(sine_buffer(440., 48000, sample_rate)),
+            rate: 2.,
+            ..TimeStretchOptions::default()
+        };
+        let src = TimeStretchNode::new(&context, options);
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // playback lasts for half a second, at the original pitch
+        let steady = &channel[GRAIN_SIZE..20000];
+        assert_float_eq!(rms(steady), std::f32::consts::FRAC_1_SQRT_2, abs <= 0.05);
+        let crossings = zero_crossings(steady) as f32;
+        let expected = 2. * 440. * steady.len() as f32 / sample_rate;
+        assert_float_eq!(crossings, expected, r2nd <= 0.02);
+
+        assert_float_eq!(channel[28000..], vec![0.; 20000][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_pitch_keeps_duration() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 48000, sample_rate);
+
+        let options = TimeStretchOptions {
+            buffer: Some(sine_buffer(220., 24000, sample_rate)),
+            pitch: 1200.,
+            ..TimeStretchOptions::default()
+        };
+        let src = TimeStretchNode::new(&context, options);
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // playback lasts for half a second, one octave higher
+        let steady = &channel[GRAIN_SIZE..20000];
+        let crossings = zero_crossings(steady) as f32;
+        let expected = 2. * 440. * steady.len() as f32 / sample_rate;
+        assert_float_eq!(crossings, expected, r2nd <= 0.02);
+
+        assert_float_eq!(channel[28000..], vec![0.; 20000][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_loop() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 48000, sample_rate);
+
+        let options = TimeStretchOptions {
+            buffer: Some(sine_buffer(480., 4800, sample_rate)),
+            loop_: true,
+            rate: 0.5,
+            ..TimeStretchOptions::default()
+        };
+        let src = TimeStretchNode::new(&context, options);
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        let steady = &channel[GRAIN_SIZE..];
+        assert_float_eq!(rms(steady), std::f32::consts::FRAC_1_SQRT_2, abs <= 0.05);
+    }
+}
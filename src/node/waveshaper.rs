@@ -10,9 +10,11 @@ use rubato::{FftFixedInOut, Resampler};
 use crate::{
     context::{AudioContextRegistration, BaseAudioContext},
     render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope},
+    RENDER_QUANTUM_SIZE,
 };
 
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+use super::bypass::Bypass;
+use super::{AudioEffectNode, AudioNode, ChannelConfig, ChannelConfigOptions};
 
 struct CurveMessage(Vec<f32>);
 
@@ -135,6 +137,7 @@ pub struct WaveShaperNode {
     oversample: Arc<AtomicU32>,
     /// Channel between node and renderer (sender part)
     sender: Sender<CurveMessage>,
+    bypass: Bypass,
 }
 
 impl AudioNode for WaveShaperNode {
@@ -155,6 +158,16 @@ impl AudioNode for WaveShaperNode {
     }
 }
 
+impl AudioEffectNode for WaveShaperNode {
+    fn bypass(&self) -> bool {
+        self.bypass.get()
+    }
+
+    fn set_bypass(&self, value: bool) {
+        self.bypass.set(value);
+    }
+}
+
 impl WaveShaperNode {
     /// returns a `WaveShaperNode` instance
     ///
@@ -187,6 +200,7 @@ impl WaveShaperNode {
             let renderer = WaveShaperRenderer::new(config);
             let node = Self {
                 registration,
+                bypass: Bypass::default(),
                 channel_config,
                 curve: OnceCell::new(),
                 oversample,
@@ -197,6 +211,7 @@ impl WaveShaperNode {
                 node.set_curve(c);
             }
 
+            let renderer = node.bypass.wrap(renderer, 0);
             (node, Box::new(renderer))
         })
     }
@@ -258,26 +273,12 @@ struct RendererConfig {
 
 /// `WaveShaperRenderer` represents the rendering part of `WaveShaperNode`
 struct WaveShaperRenderer {
-    /// Sample rate (equals to audio context sample rate)
-    sample_rate: usize,
     /// oversample factor
     oversample: Arc<AtomicU32>,
-    /// Number of channels used to build the up/down sampler X2
-    channels_x2: usize,
-    /// Number of channels used to build the up/down sampler X4
-    channels_x4: usize,
-    // up sampler configured to multiply by 2 the input signal
-    upsampler_x2: FftFixedInOut<f32>,
-    // up sampler configured to multiply by 4 the input signal
-    upsampler_x4: FftFixedInOut<f32>,
-    // down sampler configured to divide by 4 the upsampled signal
-    downsampler_x2: FftFixedInOut<f32>,
-    // down sampler configured to divide by 4 the upsampled signal
-    downsampler_x4: FftFixedInOut<f32>,
-    /// distortion curve
-    curve: Option<Vec<f32>>,
     /// Channel between node and renderer (receiver part)
     receiver: Receiver<CurveMessage>,
+    /// The actual distortion DSP
+    shaper: WaveShaper,
 }
 
 impl AudioProcessor for WaveShaperRenderer {
@@ -299,95 +300,16 @@ impl AudioProcessor for WaveShaperRenderer {
 
         // Check if a curve have been set at k-rate
         if let Ok(msg) = self.receiver.try_recv() {
-            self.curve = Some(msg.0);
+            self.shaper.set_curve(msg.0);
         }
 
-        *output = input.clone();
-
-        if self.curve.is_some() {
-            match self.oversample.load(Ordering::SeqCst).into() {
-                OverSampleType::None => {
-                    output.modify_channels(|channel| {
-                        channel.iter_mut().for_each(|o| *o = self.apply_curve(*o));
-                    });
-                }
-                OverSampleType::X2 => {
-                    let channels = output.channels();
-
-                    // recreate up/down sampler if number of channels changed
-                    if channels.len() != self.channels_x2 {
-                        self.channels_x2 = channels.len();
-
-                        self.upsampler_x2 = FftFixedInOut::<f32>::new(
-                            self.sample_rate,
-                            self.sample_rate * 2,
-                            256,
-                            self.channels_x2,
-                        )
-                        .unwrap();
-
-                        self.downsampler_x2 = FftFixedInOut::<f32>::new(
-                            self.sample_rate * 2,
-                            self.sample_rate,
-                            128,
-                            self.channels_x2,
-                        )
-                        .unwrap();
-                    }
-
-                    let mut up_channels = self.upsampler_x2.process(channels, None).unwrap();
-
-                    for channel in up_channels.iter_mut() {
-                        for s in channel.iter_mut() {
-                            *s = self.apply_curve(*s);
-                        }
-                    }
-
-                    let down_channels = self.downsampler_x2.process(&up_channels, None).unwrap();
-
-                    for (processed, output) in down_channels.iter().zip(output.channels_mut()) {
-                        output.copy_from_slice(&processed[..]);
-                    }
-                }
-                OverSampleType::X4 => {
-                    let channels = output.channels();
-
-                    // recreate up/down sampler if number of channels changed
-                    if channels.len() != self.channels_x4 {
-                        self.channels_x4 = channels.len();
-
-                        self.upsampler_x4 = FftFixedInOut::<f32>::new(
-                            self.sample_rate,
-                            self.sample_rate * 4,
-                            512,
-                            self.channels_x4,
-                        )
-                        .unwrap();
-
-                        self.downsampler_x4 = FftFixedInOut::<f32>::new(
-                            self.sample_rate * 4,
-                            self.sample_rate,
-                            128,
-                            self.channels_x4,
-                        )
-                        .unwrap();
-                    }
-
-                    let mut up_channels = self.upsampler_x4.process(channels, None).unwrap();
-
-                    for channel in up_channels.iter_mut() {
-                        for s in channel.iter_mut() {
-                            *s = self.apply_curve(*s);
-                        }
-                    }
+        self.shaper
+            .set_oversample(self.oversample.load(Ordering::SeqCst).into());
 
-                    let down_channels = self.downsampler_x4.process(&up_channels, None).unwrap();
+        *output = input.clone();
 
-                    for (processed, output) in down_channels.iter().zip(output.channels_mut()) {
-                        output.copy_from_slice(&processed[..]);
-                    }
-                }
-            }
+        if self.shaper.curve().is_some() {
+            self.shaper.shape_channels(output.channels_mut());
         }
 
         // @tbc - rubato::FftFixedInOut doesn't seem to introduce any latency
@@ -397,7 +319,6 @@ impl AudioProcessor for WaveShaperRenderer {
 
 impl WaveShaperRenderer {
     /// returns an `WaveShaperRenderer` instance
-    #[allow(clippy::missing_const_for_fn)]
     fn new(config: RendererConfig) -> Self {
         let RendererConfig {
             sample_rate,
@@ -405,6 +326,75 @@ impl WaveShaperRenderer {
             receiver,
         } = config;
 
+        let options = WaveShaperOptions {
+            oversample: oversample.load(Ordering::SeqCst).into(),
+            ..WaveShaperOptions::default()
+        };
+        let shaper = WaveShaper::new(sample_rate as f32, options);
+
+        Self {
+            oversample,
+            receiver,
+            shaper,
+        }
+    }
+}
+
+/// Standalone wave shaper, processing blocks of audio outside of any audio context
+///
+/// This runs the same DSP as the [`WaveShaperNode`] renderer, including the oversampling, so it
+/// can be reused in e.g. plugins or tests without setting up an audio graph.
+///
+/// # Usage
+///
+/// ```
+/// use web_audio_api::node::{WaveShaper, WaveShaperOptions};
+/// use web_audio_api::RENDER_QUANTUM_SIZE;
+///
+/// let options = WaveShaperOptions {
+///     curve: Some(vec![-0.5, 0., 0.5]),
+///     ..WaveShaperOptions::default()
+/// };
+/// let mut shaper = WaveShaper::new(44_100., options);
+///
+/// let input = [[1.; RENDER_QUANTUM_SIZE]];
+/// let output = shaper.process(&input);
+/// assert_eq!(output[0][0], 0.5);
+/// ```
+pub struct WaveShaper {
+    /// Sample rate (equals to audio context sample rate)
+    sample_rate: usize,
+    /// oversample factor
+    oversample: OverSampleType,
+    /// Number of channels used to build the up/down sampler X2
+    channels_x2: usize,
+    /// Number of channels used to build the up/down sampler X4
+    channels_x4: usize,
+    // up sampler configured to multiply by 2 the input signal
+    upsampler_x2: FftFixedInOut<f32>,
+    // up sampler configured to multiply by 4 the input signal
+    upsampler_x4: FftFixedInOut<f32>,
+    // down sampler configured to divide by 4 the upsampled signal
+    downsampler_x2: FftFixedInOut<f32>,
+    // down sampler configured to divide by 4 the upsampled signal
+    downsampler_x4: FftFixedInOut<f32>,
+    /// distortion curve
+    curve: Option<Vec<f32>>,
+    /// output buffers of the standalone `process`
+    output: Vec<[f32; RENDER_QUANTUM_SIZE]>,
+}
+
+impl WaveShaper {
+    /// Create a new wave shaper for the given sample rate
+    ///
+    /// The `channel_config` of the options is ignored, the number of channels is determined by
+    /// the processed blocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resamplers for oversampling cannot be created for the given sample rate
+    pub fn new(sample_rate: f32, options: WaveShaperOptions) -> Self {
+        let sample_rate = sample_rate as usize;
         let channels_x2 = 1;
         let channels_x4 = 1;
 
@@ -422,42 +412,171 @@ impl WaveShaperRenderer {
 
         Self {
             sample_rate,
-            oversample,
+            oversample: options.oversample,
             channels_x2,
             channels_x4,
             upsampler_x2,
             upsampler_x4,
             downsampler_x2,
             downsampler_x4,
-            curve: None,
-            receiver,
+            curve: options.curve,
+            output: vec![],
+        }
+    }
+
+    /// Returns the distortion curve
+    #[must_use]
+    pub fn curve(&self) -> Option<&[f32]> {
+        self.curve.as_deref()
+    }
+
+    /// Set the distortion curve
+    ///
+    /// Unlike [`WaveShaperNode::set_curve`], the curve can be replaced.
+    pub fn set_curve(&mut self, curve: Vec<f32>) {
+        self.curve = Some(curve);
+    }
+
+    /// Returns the oversampling type
+    #[must_use]
+    pub fn oversample(&self) -> OverSampleType {
+        self.oversample
+    }
+
+    /// Set the oversampling type
+    pub fn set_oversample(&mut self, oversample: OverSampleType) {
+        self.oversample = oversample;
+    }
+
+    /// Shape a block of audio, one array per channel
+    ///
+    /// Without a curve, the input is passed through unchanged.
+    pub fn process(
+        &mut self,
+        input: &[[f32; RENDER_QUANTUM_SIZE]],
+    ) -> &[[f32; RENDER_QUANTUM_SIZE]] {
+        let mut output = std::mem::take(&mut self.output);
+        output.clear();
+        output.extend_from_slice(input);
+
+        if self.curve.is_some() {
+            self.shape_channels(&mut output);
         }
+
+        self.output = output;
+        &self.output
     }
 
-    #[inline]
-    fn apply_curve(&self, input: f32) -> f32 {
+    /// Apply the curve in place to channels of `RENDER_QUANTUM_SIZE` samples
+    fn shape_channels<C: AsRef<[f32]> + AsMut<[f32]>>(&mut self, channels: &mut [C]) {
         // curve is always set at this point
         let curve = self.curve.as_deref().unwrap();
 
-        if curve.is_empty() {
-            return 0.;
-        }
+        match self.oversample {
+            OverSampleType::None => {
+                channels.iter_mut().for_each(|channel| {
+                    channel
+                        .as_mut()
+                        .iter_mut()
+                        .for_each(|o| *o = apply_curve(curve, *o));
+                });
+            }
+            OverSampleType::X2 => {
+                // recreate up/down sampler if number of channels changed
+                if channels.len() != self.channels_x2 {
+                    self.channels_x2 = channels.len();
+
+                    self.upsampler_x2 = FftFixedInOut::<f32>::new(
+                        self.sample_rate,
+                        self.sample_rate * 2,
+                        256,
+                        self.channels_x2,
+                    )
+                    .unwrap();
+
+                    self.downsampler_x2 = FftFixedInOut::<f32>::new(
+                        self.sample_rate * 2,
+                        self.sample_rate,
+                        128,
+                        self.channels_x2,
+                    )
+                    .unwrap();
+                }
+
+                let mut up_channels = self.upsampler_x2.process(channels, None).unwrap();
+
+                for channel in up_channels.iter_mut() {
+                    for s in channel.iter_mut() {
+                        *s = apply_curve(curve, *s);
+                    }
+                }
+
+                let down_channels = self.downsampler_x2.process(&up_channels, None).unwrap();
+
+                for (processed, output) in down_channels.iter().zip(channels.iter_mut()) {
+                    output.as_mut().copy_from_slice(&processed[..]);
+                }
+            }
+            OverSampleType::X4 => {
+                // recreate up/down sampler if number of channels changed
+                if channels.len() != self.channels_x4 {
+                    self.channels_x4 = channels.len();
+
+                    self.upsampler_x4 = FftFixedInOut::<f32>::new(
+                        self.sample_rate,
+                        self.sample_rate * 4,
+                        512,
+                        self.channels_x4,
+                    )
+                    .unwrap();
+
+                    self.downsampler_x4 = FftFixedInOut::<f32>::new(
+                        self.sample_rate * 4,
+                        self.sample_rate,
+                        128,
+                        self.channels_x4,
+                    )
+                    .unwrap();
+                }
+
+                let mut up_channels = self.upsampler_x4.process(channels, None).unwrap();
+
+                for channel in up_channels.iter_mut() {
+                    for s in channel.iter_mut() {
+                        *s = apply_curve(curve, *s);
+                    }
+                }
 
-        let n = curve.len() as f32;
-        let v = (n - 1.) / 2.0 * (input + 1.);
-
-        if v <= 0. {
-            curve[0]
-        } else if v >= n - 1. {
-            curve[(n - 1.) as usize]
-        } else {
-            let k = v.floor();
-            let f = v - k;
-            (1. - f) * curve[k as usize] + f * curve[(k + 1.) as usize]
+                let down_channels = self.downsampler_x4.process(&up_channels, None).unwrap();
+
+                for (processed, output) in down_channels.iter().zip(channels.iter_mut()) {
+                    output.as_mut().copy_from_slice(&processed[..]);
+                }
+            }
         }
     }
 }
 
+#[inline]
+fn apply_curve(curve: &[f32], input: f32) -> f32 {
+    if curve.is_empty() {
+        return 0.;
+    }
+
+    let n = curve.len() as f32;
+    let v = (n - 1.) / 2.0 * (input + 1.);
+
+    if v <= 0. {
+        curve[0]
+    } else if v >= n - 1. {
+        curve[(n - 1.) as usize]
+    } else {
+        let k = v.floor();
+        let f = v - k;
+        (1. - f) * curve[k as usize] + f * curve[(k + 1.) as usize]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
@@ -625,4 +744,42 @@ mod tests {
 
         assert_float_eq!(channel[..], expected[..], abs_all <= 0.);
     }
+
+    #[test]
+    fn test_standalone_matches_node() {
+        let sample_rate = 44_100.;
+        let length = 4 * RENDER_QUANTUM_SIZE;
+        let signal: Vec<f32> = (0..length).map(|i| (i as f32 * 0.05).sin()).collect();
+
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+        let options = WaveShaperOptions {
+            curve: Some(vec![-1., -0.8, 0., 0.8, 1.]),
+            oversample: OverSampleType::X2,
+            ..WaveShaperOptions::default()
+        };
+        let node = WaveShaperNode::new(&context, options.clone());
+        node.connect(&context.destination());
+
+        let mut buffer = context.create_buffer(1, length, sample_rate);
+        buffer.copy_to_channel(&signal, 0);
+
+        let src = context.create_buffer_source();
+        src.connect(&node);
+        src.set_buffer(buffer);
+        src.start();
+
+        let result = context.start_rendering_sync();
+
+        let mut standalone = WaveShaper::new(sample_rate, options);
+        signal
+            .chunks(RENDER_QUANTUM_SIZE)
+            .zip(result.get_channel_data(0).chunks(RENDER_QUANTUM_SIZE))
+            .for_each(|(input, expected)| {
+                let mut block = [0.; RENDER_QUANTUM_SIZE];
+                block.copy_from_slice(input);
+                let output = standalone.process(&[block]);
+                assert_eq!(output.len(), 1);
+                assert_float_eq!(output[0][..], expected[..], abs_all <= 0.);
+            });
+    }
 }
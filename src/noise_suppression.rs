@@ -0,0 +1,278 @@
+//! Noise suppression of media streams
+//!
+//! Stationary background noise (fans, hum, hiss) is estimated per frequency band and removed by
+//! spectral subtraction, in frames of a short-time Fourier transform.
+use std::sync::Arc;
+
+use crate::fft::{self, Complex, ComplexToRealFft, RealToComplexFft};
+use crate::{AudioBuffer, FallibleBuffer};
+
+/// Size of the analysis frames, the frames overlap by half
+const FRAME_SIZE: usize = 512;
+const HOP_SIZE: usize = FRAME_SIZE / 2;
+/// Smoothing factor of the power spectrum, per frame
+const POWER_SMOOTHING: f32 = 0.7;
+/// Number of frames the noise estimate is learned from, regardless of the signal (about 0.1 s)
+const LEARNING_FRAMES: usize = 20;
+/// Smoothing factor of the noise estimate, per frame
+const NOISE_SMOOTHING: f32 = 0.95;
+/// Ratio of the power to the noise estimate above which a band contains a signal
+const SIGNAL_THRESHOLD: f32 = 4.;
+/// Growth of the noise estimate per frame in the presence of a signal, to follow increasing noise
+const NOISE_RISE: f32 = 1.005;
+/// Oversubtraction of the noise estimate, reduces the residual noise
+const OVERSUBTRACTION: f32 = 3.;
+/// Minimum gain of a band (-20 dB), limits the artifacts of the subtraction
+const GAIN_FLOOR: f32 = 0.1;
+
+/// Spectral subtraction denoiser of a single channel
+pub(crate) struct NoiseSuppressor {
+    r2c: Arc<dyn RealToComplexFft>,
+    c2r: Arc<dyn ComplexToRealFft>,
+    scratch: Vec<Complex<f32>>,
+    /// square root of a periodic Hann window, applied on analysis and synthesis
+    window: Vec<f32>,
+    /// the last `FRAME_SIZE` input samples
+    input: Vec<f32>,
+    /// overlap-add accumulator of the output
+    output: Vec<f32>,
+    /// number of samples of the current hop
+    position: usize,
+    frame: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    power: Vec<f32>,
+    /// estimated noise power per band
+    noise: Vec<f32>,
+    /// number of processed frames, up to `LEARNING_FRAMES`
+    frames: usize,
+}
+
+impl NoiseSuppressor {
+    pub fn new() -> Self {
+        let r2c = fft::plan_forward(FRAME_SIZE);
+        let c2r = fft::plan_inverse(FRAME_SIZE);
+        let scratch = vec![Complex::default(); r2c.scratch_len().max(c2r.scratch_len())];
+        let spectrum = vec![Complex::default(); FRAME_SIZE / 2 + 1];
+
+        let window = (0..FRAME_SIZE)
+            .map(|i| {
+                let phase = std::f32::consts::PI * i as f32 / FRAME_SIZE as f32;
+                phase.sin()
+            })
+            .collect();
+
+        Self {
+            r2c,
+            c2r,
+            scratch,
+            window,
+            input: vec![0.; FRAME_SIZE],
+            output: vec![0.; FRAME_SIZE],
+            position: 0,
+            frame: vec![0.; FRAME_SIZE],
+            power: vec![0.; spectrum.len()],
+            noise: vec![0.; spectrum.len()],
+            spectrum,
+            frames: 0,
+        }
+    }
+
+    /// Denoise the next sample, the output is delayed by `FRAME_SIZE` samples
+    pub fn process(&mut self, sample: f32) -> f32 {
+        self.input[HOP_SIZE + self.position] = sample;
+        let output = self.output[self.position];
+
+        self.position += 1;
+        if self.position == HOP_SIZE {
+            self.process_frame();
+            self.position = 0;
+        }
+
+        output
+    }
+
+    fn process_frame(&mut self) {
+        self.frame
+            .iter_mut()
+            .zip(self.input.iter().zip(&self.window))
+            .for_each(|(f, (i, w))| *f = i * w);
+        self.input.copy_within(HOP_SIZE.., 0);
+
+        // the sizes of the buffers match the plan
+        self.r2c
+            .process(&mut self.frame, &mut self.spectrum, &mut self.scratch);
+
+        // average the first frames, assuming they contain no signal
+        let learning = self.frames < LEARNING_FRAMES;
+        let learning_smoothing = self.frames as f32 / (self.frames + 1) as f32;
+        self.frames = (self.frames + 1).min(LEARNING_FRAMES);
+
+        self.spectrum
+            .iter_mut()
+            .zip(self.power.iter_mut())
+            .zip(self.noise.iter_mut())
+            .for_each(|((bin, power), noise)| {
+                *power = POWER_SMOOTHING * *power + (1. - POWER_SMOOTHING) * bin.norm_sqr();
+
+                if learning {
+                    *noise =
+                        learning_smoothing * *noise + (1. - learning_smoothing) * bin.norm_sqr();
+                } else if *power < SIGNAL_THRESHOLD * *noise {
+                    *noise = NOISE_SMOOTHING * *noise + (1. - NOISE_SMOOTHING) * *power;
+                } else {
+                    *noise *= NOISE_RISE;
+                }
+
+                let gain = (1. - OVERSUBTRACTION * *noise / (*power + f32::MIN_POSITIVE))
+                    .max(GAIN_FLOOR * GAIN_FLOOR)
+                    .sqrt();
+                *bin *= gain;
+            });
+
+        self.c2r
+            .process(&mut self.spectrum, &mut self.frame, &mut self.scratch);
+
+        let scale = 1. / FRAME_SIZE as f32;
+        self.output.copy_within(HOP_SIZE.., 0);
+        self.output[HOP_SIZE..].fill(0.);
+        self.output
+            .iter_mut()
+            .zip(self.frame.iter().zip(&self.window))
+            .for_each(|(o, (f, w))| *o += f * w * scale);
+    }
+}
+
+/// Media stream with the stationary background noise removed
+pub(crate) struct NoiseSuppressedStream<I> {
+    input: I,
+    /// a denoiser per channel of the input, set up with the first buffer
+    suppressors: Vec<NoiseSuppressor>,
+}
+
+impl<I> NoiseSuppressedStream<I> {
+    pub fn new(input: I) -> Self {
+        Self {
+            input,
+            suppressors: Vec::new(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = FallibleBuffer>> Iterator for NoiseSuppressedStream<I> {
+    type Item = FallibleBuffer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buffer = match self.input.next()? {
+            Ok(buffer) => buffer,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if buffer.length() == 0 {
+            return Some(Ok(buffer));
+        }
+
+        if self.suppressors.len() != buffer.number_of_channels() {
+            self.suppressors = (0..buffer.number_of_channels())
+                .map(|_| NoiseSuppressor::new())
+                .collect();
+        }
+
+        let channels = self
+            .suppressors
+            .iter_mut()
+            .enumerate()
+            .map(|(i, suppressor)| {
+                buffer
+                    .get_channel_data(i)
+                    .iter()
+                    .map(|&v| suppressor.process(v))
+                    .collect()
+            })
+            .collect();
+
+        Some(Ok(AudioBuffer::from(channels, buffer.sample_rate())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic white noise in [-amplitude, amplitude]
+    fn noise(len: usize, amplitude: f32) -> Vec<f32> {
+        let mut state = 12345_u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                ((state >> 16) as f32 / 32768. - 1.) * amplitude
+            })
+            .collect()
+    }
+
+    fn energy(samples: &[f32]) -> f32 {
+        samples.iter().map(|v| v * v).sum()
+    }
+
+    #[test]
+    fn test_reconstruction() {
+        // without any noise estimate, the frames are reconstructed perfectly
+        let mut suppressor = NoiseSuppressor::new();
+        suppressor.frames = LEARNING_FRAMES;
+
+        let input: Vec<f32> = (0..4800).map(|i| (i as f32 * 0.05).sin()).collect();
+        let output: Vec<f32> = input.iter().map(|&v| suppressor.process(v)).collect();
+
+        let latency = FRAME_SIZE;
+        input
+            .iter()
+            .zip(&output[latency..])
+            .skip(FRAME_SIZE)
+            .for_each(|(i, o)| assert!((i - o).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_suppress_noise() {
+        let input = noise(96_000, 0.1);
+        let mut suppressor = NoiseSuppressor::new();
+        let output: Vec<f32> = input.iter().map(|&v| suppressor.process(v)).collect();
+
+        // more than 15 dB of noise reduction, once the noise is estimated
+        let tail = 48_000..;
+        let reduction = 10. * (energy(&input[tail.clone()]) / energy(&output[tail])).log10();
+        assert!(reduction > 15., "reduction {}", reduction);
+    }
+
+    #[test]
+    fn test_preserve_signal() {
+        // a tone switched on and off over the noise
+        let background = noise(96_000, 0.01);
+        let tone: Vec<f32> = (0..96_000)
+            .map(|i| {
+                let on = (i / 12_000) % 2 == 1;
+                if on {
+                    0.5 * (i as f32 * 0.05).sin()
+                } else {
+                    0.
+                }
+            })
+            .collect();
+        let input: Vec<f32> = tone.iter().zip(&background).map(|(t, b)| t + b).collect();
+
+        let chunks: Vec<FallibleBuffer> = input
+            .chunks(480)
+            .map(|chunk| Ok(AudioBuffer::from(vec![chunk.to_vec()], 48_000.)))
+            .collect();
+        let output: Vec<f32> = NoiseSuppressedStream::new(chunks.into_iter())
+            .flat_map(|buffer| buffer.unwrap().get_channel_data(0).to_vec())
+            .collect();
+        assert_eq!(output.len(), input.len());
+
+        // the last tone burst keeps its level within 1 dB
+        let latency = FRAME_SIZE;
+        let burst = 86_000..95_000;
+        let level = 10.
+            * (energy(&output[burst.start + latency..burst.end + latency]) / energy(&tone[burst]))
+                .log10();
+        assert!(level.abs() < 1., "level {}", level);
+    }
+}
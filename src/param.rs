@@ -1,13 +1,13 @@
 //! AudioParam interface
 use std::slice::{Iter, IterMut};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use crate::context::AudioContextRegistration;
+use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::node::{
     AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
 };
-use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::render::{simd, AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use crate::{AtomicF32, RENDER_QUANTUM_SIZE};
 
 use crossbeam_channel::{Receiver, Sender};
@@ -78,7 +78,7 @@ pub struct AudioParamDescriptor {
     pub max_value: f32,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum AudioParamEventType {
     SetValue,
     SetValueAtTime,
@@ -90,7 +90,7 @@ enum AudioParamEventType {
     SetValueCurveAtTime,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub(crate) struct AudioParamEvent {
     event_type: AudioParamEventType,
     value: f32,
@@ -101,6 +101,34 @@ pub(crate) struct AudioParamEvent {
     values: Option<Box<[f32]>>, // populated by `SetValueCurveAtTime` events
 }
 
+/// Automation event of an [`AudioParam`] that is scheduled but not completed yet
+///
+/// Times are relative to the moment the automation was retrieved, see
+/// [`GraphDocument`](crate::context::GraphDocument).
+#[derive(Clone, Debug, PartialEq)]
+pub enum AutomationEvent {
+    /// See [`AudioParam::set_value_at_time`]
+    SetValueAtTime { value: f32, start_time: f64 },
+    /// See [`AudioParam::linear_ramp_to_value_at_time`]
+    LinearRampToValueAtTime { value: f32, end_time: f64 },
+    /// See [`AudioParam::exponential_ramp_to_value_at_time`]
+    ExponentialRampToValueAtTime { value: f32, end_time: f64 },
+    /// See [`AudioParam::set_target_at_time`]
+    SetTargetAtTime {
+        value: f32,
+        start_time: f64,
+        time_constant: f64,
+    },
+    /// See [`AudioParam::cancel_and_hold_at_time`]
+    CancelAndHoldAtTime { cancel_time: f64 },
+    /// See [`AudioParam::set_value_curve_at_time`]
+    SetValueCurveAtTime {
+        values: Vec<f32>,
+        start_time: f64,
+        duration: f64,
+    },
+}
+
 // Event queue that contains `AudioParamEvent`s, most of the time, events must be
 // ordered (using stable sort), some operation may break this ordering (e.g. `push`)
 // in which cases `sort` must be called explicitely.
@@ -201,6 +229,7 @@ pub struct AudioParam {
     max_value: f32,     // readonly
     current_value: Arc<AtomicF32>,
     sender: Sender<AudioParamEvent>,
+    automation: Arc<Mutex<Vec<AudioParamEvent>>>,
 }
 
 // helper struct to attach / detach to context (for borrow reasons)
@@ -213,6 +242,7 @@ pub(crate) struct AudioParamRaw {
     max_value: f32,
     current_value: Arc<AtomicF32>,
     sender: Sender<AudioParamEvent>,
+    automation: Arc<Mutex<Vec<AudioParamEvent>>>,
 }
 
 lazy_static! {
@@ -280,19 +310,33 @@ impl AudioParam {
         self.automation_rate_constrained = value;
     }
 
+    /// Initial value of the `AudioParam`
     pub fn default_value(&self) -> f32 {
         self.default_value
     }
 
+    /// Lower bound of the nominal range of the `AudioParam`
+    ///
+    /// The computed value is clamped to the nominal range, the values of the
+    /// automation events are not.
     pub fn min_value(&self) -> f32 {
         self.min_value
     }
 
+    /// Upper bound of the nominal range of the `AudioParam`
+    ///
+    /// The computed value is clamped to the nominal range, the values of the
+    /// automation events are not.
     pub fn max_value(&self) -> f32 {
         self.max_value
     }
 
     /// Retrieve the current value of the `AudioParam`.
+    ///
+    /// This is the computed value of the first sample of the last rendered block,
+    /// i.e. the intrinsic value resulting from the automation events summed with
+    /// the audio inputs connected to the param, or the last value set with
+    /// [`Self::set_value`] if no block was rendered since.
     //
     // @note: the choice here is to have this coherent with the first sample of
     // the last rendered block, which means `intrisic_value` must be calculated
@@ -547,6 +591,7 @@ impl AudioParam {
             max_value: self.max_value,
             current_value: self.current_value,
             sender: self.sender,
+            automation: self.automation,
         }
     }
 
@@ -564,10 +609,169 @@ impl AudioParam {
             max_value: parts.max_value,
             current_value: parts.current_value,
             sender: parts.sender,
+            automation: parts.automation,
+        }
+    }
+
+    /// Automation events that are scheduled but not completed at `now`, with times
+    /// relative to `now`
+    ///
+    /// The automation that already happened is represented by the current value of
+    /// the param. A running value curve is cut at `now`, its values that already
+    /// passed are dropped.
+    pub(crate) fn pending_automation(&self, now: f64) -> Vec<AutomationEvent> {
+        let mut automation = self.automation.lock().unwrap();
+        prune_automation(&mut automation, now);
+
+        // a hold that already happened cuts the ramp that was scheduled before it
+        let held = automation
+            .iter()
+            .rposition(|e| e.event_type == AudioParamEventType::CancelAndHoldAtTime && e.time < now)
+            .unwrap_or(0);
+
+        automation[held..]
+            .iter()
+            .filter_map(|event| {
+                let time = (event.time - now).max(0.);
+                let pending = match event.event_type {
+                    AudioParamEventType::SetValueAtTime if event.time >= now => {
+                        AutomationEvent::SetValueAtTime {
+                            value: event.value,
+                            start_time: time,
+                        }
+                    }
+                    AudioParamEventType::LinearRampToValueAtTime if event.time >= now => {
+                        AutomationEvent::LinearRampToValueAtTime {
+                            value: event.value,
+                            end_time: time,
+                        }
+                    }
+                    AudioParamEventType::ExponentialRampToValueAtTime if event.time >= now => {
+                        AutomationEvent::ExponentialRampToValueAtTime {
+                            value: event.value,
+                            end_time: time,
+                        }
+                    }
+                    AudioParamEventType::CancelAndHoldAtTime if event.time >= now => {
+                        AutomationEvent::CancelAndHoldAtTime { cancel_time: time }
+                    }
+                    // a target never completes, it runs until the next event
+                    AudioParamEventType::SetTargetAtTime => AutomationEvent::SetTargetAtTime {
+                        value: event.value,
+                        start_time: time,
+                        time_constant: event.time_constant.unwrap(),
+                    },
+                    AudioParamEventType::SetValueCurveAtTime => {
+                        let values = event.values.as_ref().unwrap();
+                        let duration = event.duration.unwrap();
+                        let end_time = event.time + duration;
+                        if end_time <= now {
+                            return None;
+                        }
+                        if event.time >= now {
+                            AutomationEvent::SetValueCurveAtTime {
+                                values: values.to_vec(),
+                                start_time: time,
+                                duration,
+                            }
+                        } else {
+                            // keep the curve points that are still ahead, starting
+                            // from the value at `now`
+                            let step = duration / (values.len() - 1) as f64;
+                            let next = ((now - event.time) / step).floor() as usize + 1;
+                            let mut remaining = Vec::with_capacity(values.len() - next + 1);
+                            remaining.push(self.value());
+                            remaining.extend_from_slice(&values[next..]);
+                            AutomationEvent::SetValueCurveAtTime {
+                                values: remaining,
+                                start_time: 0.,
+                                duration: end_time - now,
+                            }
+                        }
+                    }
+                    _ => return None,
+                };
+
+                Some(pending)
+            })
+            .collect()
+    }
+
+    /// Schedule an automation event retrieved with [`Self::pending_automation`]
+    pub(crate) fn schedule_automation(&self, event: &AutomationEvent) {
+        match *event {
+            AutomationEvent::SetValueAtTime { value, start_time } => {
+                self.set_value_at_time(value, start_time);
+            }
+            AutomationEvent::LinearRampToValueAtTime { value, end_time } => {
+                self.linear_ramp_to_value_at_time(value, end_time);
+            }
+            AutomationEvent::ExponentialRampToValueAtTime { value, end_time } => {
+                self.exponential_ramp_to_value_at_time(value, end_time);
+            }
+            AutomationEvent::SetTargetAtTime {
+                value,
+                start_time,
+                time_constant,
+            } => {
+                self.set_target_at_time(value, start_time, time_constant);
+            }
+            AutomationEvent::CancelAndHoldAtTime { cancel_time } => {
+                self.cancel_and_hold_at_time(cancel_time);
+            }
+            AutomationEvent::SetValueCurveAtTime {
+                ref values,
+                start_time,
+                duration,
+            } => {
+                self.set_value_curve_at_time(values, start_time, duration);
+            }
+        }
+    }
+
+    // keep track of the scheduled automation on the control thread, so it can
+    // be serialized without querying the render thread
+    fn record_event(&self, event: &AudioParamEvent) {
+        let now = self.context().current_time();
+        let mut automation = self.automation.lock().unwrap();
+
+        match event.event_type {
+            AudioParamEventType::SetValue => {
+                // the renderer applies this event at the start of the next block, after
+                // the events that are due, it is represented by the current value which
+                // is updated right away
+                automation.retain(|e| e.time > now);
+                let mut event = event.clone();
+                event.time = now;
+                automation.push(event);
+            }
+            AudioParamEventType::CancelScheduledValues => {
+                automation.retain(|e| e.time < event.time);
+            }
+            AudioParamEventType::CancelAndHoldAtTime => {
+                // keep the ramp that is running at the cancel time, the renderer
+                // computes the value to hold from it
+                let running_ramp = automation
+                    .iter()
+                    .filter(|e| e.time >= event.time)
+                    .min_by(|a, b| a.time.partial_cmp(&b.time).unwrap())
+                    .filter(|e| {
+                        e.event_type == AudioParamEventType::LinearRampToValueAtTime
+                            || e.event_type == AudioParamEventType::ExponentialRampToValueAtTime
+                    })
+                    .map(|e| e.time);
+                automation.retain(|e| e.time < event.time || Some(e.time) == running_ramp);
+                automation.push(event.clone());
+            }
+            _ => automation.push(event.clone()),
         }
+
+        prune_automation(&mut automation, now);
     }
 
     fn send_event(&self, event: AudioParamEvent) {
+        self.record_event(&event);
+
         if cfg!(test) {
             // bypass audiocontext enveloping of control messages for simpler testing
             self.sender.send(event).unwrap();
@@ -577,6 +781,18 @@ impl AudioParam {
     }
 }
 
+// Drop the recorded events that completed before `now`: only the events that
+// started last before `now` still have an effect on the automation to come.
+// The events are kept in insertion order, so they can be replayed as is.
+fn prune_automation(automation: &mut Vec<AudioParamEvent>, now: f64) {
+    let latest = automation
+        .iter()
+        .map(|e| e.time)
+        .filter(|&time| time <= now)
+        .fold(f64::NEG_INFINITY, f64::max);
+    automation.retain(|e| e.time >= latest);
+}
+
 #[derive(Debug)]
 pub(crate) struct AudioParamProcessor {
     intrisic_value: f32,
@@ -589,6 +805,8 @@ pub(crate) struct AudioParamProcessor {
     event_timeline: AudioParamEventTimeline,
     last_event: Option<AudioParamEvent>,
     buffer: Vec<f32>,
+    /// Always render a full render quantum of values at a-rate, even when constant
+    full_buffer: bool,
 }
 
 impl AudioProcessor for AudioParamProcessor {
@@ -599,11 +817,22 @@ impl AudioProcessor for AudioParamProcessor {
         _params: AudioParamValues,
         scope: &RenderScope,
     ) -> bool {
-        let period = 1. / scope.sample_rate as f64;
-
         let input = &inputs[0]; // single input mode
         let output = &mut outputs[0];
 
+        // The constant value of the previous block holds when no automation is pending, which
+        // is the common case for the params of idle nodes in large graphs
+        if output.is_constant()
+            && !self.full_buffer
+            && input.is_silent()
+            && self.event_timeline.is_empty()
+            && self.receiver.is_empty()
+        {
+            return true;
+        }
+
+        let period = 1. / scope.sample_rate as f64;
+
         self.compute_intrisic_values(scope.current_time, period, RENDER_QUANTUM_SIZE);
         self.mix_to_output(input, output);
 
@@ -635,10 +864,23 @@ impl AudioParamProcessor {
                 value = self.default_value;
             }
 
-            output.set_single_valued(true);
-
-            let output_channel = output.channel_data_mut(0);
-            output_channel[0] = value.clamp(self.min_value, self.max_value);
+            let value = value.clamp(self.min_value, self.max_value);
+
+            if self.full_buffer && self.is_a_rate.load(Ordering::SeqCst) {
+                // the block of the previous render quantum is reused when it holds the same
+                // constant value, which saves the fill and a copy of shared channel data
+                let cached = output.is_constant()
+                    && !output.single_valued()
+                    && output.channel_data(0)[0].to_bits() == value.to_bits();
+                if !cached {
+                    output.set_single_valued(false);
+                    output.channel_data_mut(0).fill(value);
+                }
+            } else {
+                output.set_single_valued(true);
+                output.channel_data_mut(0)[0] = value;
+            }
+            output.set_constant(true);
         } else {
             // @note: we could add two other optimizations here:
             // - when buffer.len() == 1 and buffer[0] == 0., then we don't need to
@@ -647,6 +889,7 @@ impl AudioParamProcessor {
             //   output and then just clamp
             *output = input.clone();
             output.set_single_valued(false);
+            output.set_constant(false);
 
             output
                 .channel_data_mut(0)
@@ -662,6 +905,11 @@ impl AudioParamProcessor {
                     *o = o.clamp(self.min_value, self.max_value)
                 });
         }
+
+        // share the computed value, including the input connections, with the
+        // control thread
+        let computed = output.channel_data(0)[0];
+        self.current_value.store(computed, Ordering::SeqCst);
     }
 
     // 𝑣(𝑡) = 𝑉0 + (𝑉1−𝑉0) * ((𝑡−𝑇0) / (𝑇1−𝑇0))
@@ -1113,22 +1361,20 @@ impl AudioParamProcessor {
                                 // compute "real" value according to `t` then clamp it
                                 // cf. Example 7 https://www.w3.org/TR/webaudio/#computation-of-value
                                 if end_index_clipped > start_index {
-                                    let mut time = (start_index as f64).mul_add(dt, block_time);
+                                    let time = (start_index as f64).mul_add(dt, block_time);
 
-                                    for _ in start_index..end_index_clipped {
-                                        let value = self.compute_linear_ramp_sample(
-                                            start_time,
-                                            duration,
-                                            start_value,
-                                            diff,
-                                            time,
-                                        );
-
-                                        self.buffer.push(value);
+                                    self.buffer.resize(end_index_clipped, 0.);
+                                    simd::linear_ramp(
+                                        &mut self.buffer[start_index..],
+                                        time,
+                                        dt,
+                                        start_time,
+                                        duration,
+                                        start_value,
+                                        diff,
+                                    );
 
-                                        time += dt;
-                                        self.intrisic_value = value;
-                                    }
+                                    self.intrisic_value = self.buffer[end_index_clipped - 1];
                                 }
                             }
 
@@ -1546,6 +1792,7 @@ pub(crate) fn audio_param_pair(
         max_value: opts.max_value,
         current_value: current_value.clone(),
         sender,
+        automation: Arc::new(Mutex::new(Vec::new())),
     };
 
     let render = AudioParamProcessor {
@@ -1559,16 +1806,29 @@ pub(crate) fn audio_param_pair(
         event_timeline: AudioParamEventTimeline::new(),
         last_event: None,
         buffer: Vec::with_capacity(RENDER_QUANTUM_SIZE),
+        full_buffer: false,
     };
 
     (param, render)
 }
 
+/// Like [`audio_param_pair`], with a processor always rendering a full render quantum of values
+/// at a-rate, as expected by custom processors
+pub(crate) fn full_buffer_audio_param_pair(
+    opts: AudioParamDescriptor,
+    registration: AudioContextRegistration,
+) -> (AudioParam, AudioParamProcessor) {
+    let (param, mut render) = audio_param_pair(opts, registration);
+    render.full_buffer = true;
+
+    (param, render)
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
 
-    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::context::OfflineAudioContext;
     use crate::render::Alloc;
 
     use super::*;
@@ -3280,6 +3540,94 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_full_buffer_param() {
+        let alloc = Alloc::with_capacity(1);
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 2.,
+            min_value: 0.,
+            max_value: 10.,
+        };
+        let (param, mut render) = full_buffer_audio_param_pair(opts, context.mock_registration());
+
+        // a-rate, constant value is repeated over the render quantum
+        {
+            let vs = render.compute_intrisic_values(0., 1., 128);
+            assert_float_eq!(vs, &[2.; 1][..], abs_all <= 0.);
+
+            let input = AudioRenderQuantum::from(alloc.silence());
+            let mut output = AudioRenderQuantum::from(alloc.silence());
+            render.mix_to_output(&input, &mut output);
+
+            assert!(!output.single_valued());
+            assert_float_eq!(output.channel_data(0)[..], &[2.; 128][..], abs_all <= 0.);
+        }
+
+        // k-rate, single value
+        {
+            param.set_automation_rate(AutomationRate::K);
+
+            let _ = render.compute_intrisic_values(0., 1., 128);
+
+            let input = AudioRenderQuantum::from(alloc.silence());
+            let mut output = AudioRenderQuantum::from(alloc.silence());
+            render.mix_to_output(&input, &mut output);
+
+            assert!(output.single_valued());
+            assert_float_eq!(output.channel_data(0)[0], 2., abs <= 0.);
+        }
+    }
+
+    #[test]
+    fn test_constant_block_cache() {
+        let alloc = Alloc::with_capacity(1);
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 2.,
+            min_value: 0.,
+            max_value: 10.,
+        };
+        let (param, mut render) = full_buffer_audio_param_pair(opts, context.mock_registration());
+
+        let input = AudioRenderQuantum::from(alloc.silence());
+        let mut output = AudioRenderQuantum::from(alloc.silence());
+
+        let _ = render.compute_intrisic_values(0., 1., 128);
+        render.mix_to_output(&input, &mut output);
+        assert!(output.is_constant());
+
+        // the constant block is reused, without copying the data shared downstream
+        let shared = output.clone();
+        let _ = render.compute_intrisic_values(128., 1., 128);
+        render.mix_to_output(&input, &mut output);
+        assert!(output.is_constant());
+        assert_eq!(
+            output.channel_data(0).as_ptr(),
+            shared.channel_data(0).as_ptr()
+        );
+
+        // a new value invalidates the block, it is applied as an event during the next block
+        param.set_value(3.);
+        let _ = render.compute_intrisic_values(256., 1., 128);
+        render.mix_to_output(&input, &mut output);
+        let _ = render.compute_intrisic_values(384., 1., 128);
+        render.mix_to_output(&input, &mut output);
+        assert!(output.is_constant());
+        assert_float_eq!(output.channel_data(0)[..], &[3.; 128][..], abs_all <= 0.);
+        assert_float_eq!(shared.channel_data(0)[..], &[2.; 128][..], abs_all <= 0.);
+
+        // ramps are not constant
+        param.linear_ramp_to_value_at_time(5., 1000.);
+        let _ = render.compute_intrisic_values(512., 1., 128);
+        render.mix_to_output(&input, &mut output);
+        assert!(!output.is_constant());
+    }
+
     #[test]
     fn test_full_render_chain() {
         let alloc = Alloc::with_capacity(1);
@@ -1,14 +1,27 @@
 //! The audio graph topology and render algorithm
+use std::any::Any;
+#[cfg(feature = "parallel-rendering")]
+use std::cell::Cell;
 use std::cell::RefCell;
 use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "parallel-rendering")]
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use crate::context::AudioNodeId;
+#[cfg(feature = "parallel-rendering")]
 use rustc_hash::FxHashMap;
+use rustc_hash::FxHashSet;
 use smallvec::{smallvec, SmallVec};
 
+use super::node_collection::NodeCollection;
+#[cfg(feature = "parallel-rendering")]
+use super::RenderPool;
 use super::{Alloc, AudioParamValues, AudioProcessor, AudioRenderQuantum};
 use crate::node::ChannelConfig;
 use crate::render::RenderScope;
+use crate::watchdog::{Watchdog, WatchdogAction};
+use crate::WatchdogOptions;
 
 /// Connection between two audio nodes
 struct OutgoingEdge {
@@ -16,10 +29,32 @@ struct OutgoingEdge {
     self_index: usize,
     /// reference to the other Node
     other_id: AudioNodeId,
+    /// slot of the other Node in the node collection
+    other_slot: usize,
     /// index of the other Nodes input port
     other_index: usize,
 }
 
+/// Renderer of a node whose processor panicked, outputs silence
+struct FailedProcessor;
+
+impl AudioProcessor for FailedProcessor {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        outputs.iter_mut().for_each(AudioRenderQuantum::make_silent);
+        false
+    }
+
+    fn onmessage(&mut self, _msg: Box<dyn Any + Send>) {
+        // the processor that could handle it is gone
+    }
+}
+
 /// Renderer Node in the Audio Graph
 pub struct Node {
     /// Renderer: converts inputs to outputs
@@ -38,6 +73,14 @@ pub struct Node {
     has_inputs_connected: bool,
     /// Indicates if the node can act as a cycle breaker (only DelayNode for now)
     cycle_breaker: bool,
+    /// Indicates if the watchdog replaced the processing by passing the input through
+    bypassed: bool,
+    /// Duration of the last call to the processor, only measured for the watchdog
+    process_duration: Duration,
+    /// Indicates if the processor is skipped while its inputs are silent
+    skip_when_silent: bool,
+    /// Tail time reported by the last call to the processor
+    tail_time: bool,
 }
 
 impl Node {
@@ -47,8 +90,23 @@ impl Node {
             .process(&self.inputs[..], &mut self.outputs[..], params, scope)
     }
 
+    /// Check whether the watchdog can bypass this node
+    fn can_bypass(&self) -> bool {
+        // audio params are connected to the 'hidden' usize::MAX input of their node
+        self.inputs.len() == 1
+            && self.outputs.len() == 1
+            && !self.cycle_breaker
+            && !self
+                .outgoing_edges
+                .iter()
+                .any(|edge| edge.other_index == usize::MAX)
+    }
+
     /// Determine if this node is done playing and can be removed from the audio graph
-    fn can_free(&self, tail_time: bool) -> bool {
+    ///
+    /// With `drop_tails`, nodes with inputs are removed without waiting for their tail, source
+    /// nodes are not affected.
+    fn can_free(&self, tail_time: bool, drop_tails: bool) -> bool {
         // Only drop when the Control thread has dropped its handle.
         // Otherwise the node can be reconnected/restarted etc.
         if !self.free_when_finished {
@@ -57,7 +115,8 @@ impl Node {
 
         // Drop, when the node does not have any inputs connected,
         // and if the processor reports it won't yield output.
-        if !self.has_inputs_connected && !tail_time {
+        let drop_tail = drop_tails && !self.inputs.is_empty();
+        if !self.has_inputs_connected && (!tail_time || drop_tail) {
             return true;
         }
 
@@ -75,32 +134,90 @@ impl Node {
 /// The audio graph
 pub(crate) struct Graph {
     /// Processing Nodes
-    nodes: FxHashMap<AudioNodeId, RefCell<Node>>,
+    nodes: NodeCollection,
     /// Allocator for audio buffers
     alloc: Alloc,
 
     /// Topological ordering of the nodes
     ordered: Vec<AudioNodeId>,
-    /// Topological sorting helper
-    marked: Vec<AudioNodeId>,
+    /// Topological sorting helper, a set as the lookups are frequent in large graphs
+    marked: FxHashSet<AudioNodeId>,
     /// Topological sorting helper
     marked_temp: Vec<AudioNodeId>,
     /// Topological sorting helper
     in_cycle: Vec<AudioNodeId>,
+    /// Set when the muted cycles may have become removable, after a node was released, an edge
+    /// removed or the graph reordered
+    check_muted: bool,
+    /// Scratch storage of the muted nodes that can be dropped
+    muted: Vec<AudioNodeId>,
+    /// Scratch storage of the candidates for dropping
+    muted_candidates: FxHashSet<AudioNodeId>,
+    /// Scratch storage of the candidates fed by other nodes
+    muted_fed: FxHashSet<AudioNodeId>,
+    /// Scratch storage of the nodes that finished in the current render quantum
+    dropped: Vec<AudioNodeId>,
+    /// Scratch storage of the AudioParams dropped along with their node
+    dropped_params: Vec<AudioNodeId>,
+    /// Scratch storage of all the nodes removed in the current render quantum
+    removed: FxHashSet<AudioNodeId>,
     /// Topological sorting helper
     cycle_breakers: Vec<AudioNodeId>,
+    /// Measure the processing duration of each node, for the watchdog
+    measure_nodes: bool,
+    /// Release nodes without waiting for their tail, for the watchdog
+    drop_tails: bool,
+    /// Watchdog state, kept with the graph so it survives a change of render thread
+    watchdog: Option<Watchdog>,
+    /// Worker threads rendering independent nodes in parallel, for offline rendering
+    #[cfg(feature = "parallel-rendering")]
+    pool: Option<Box<RenderPool>>,
+    /// End indices of the levels in `ordered` when rendering in parallel, the nodes of a level
+    /// do not depend on each other
+    #[cfg(feature = "parallel-rendering")]
+    levels: Vec<usize>,
+}
+
+/// Nodes of the graph, shared with the worker threads of a parallel rendering
+#[cfg(feature = "parallel-rendering")]
+struct SharedNodes<'a>(&'a NodeCollection);
+
+#[cfg(feature = "parallel-rendering")]
+impl<'a> SharedNodes<'a> {
+    fn nodes(&self) -> &'a NodeCollection {
+        self.0
+    }
 }
 
+// SAFETY: the worker threads only read the nodes through `AudioParamValues::from_shared`, while
+// the borrows are held by the thread distributing the work
+#[cfg(feature = "parallel-rendering")]
+unsafe impl Sync for SharedNodes<'_> {}
+
 impl Graph {
     pub fn new() -> Self {
         Graph {
-            nodes: FxHashMap::default(),
+            nodes: NodeCollection::new(),
             ordered: vec![],
-            marked: vec![],
+            marked: FxHashSet::default(),
             marked_temp: vec![],
             in_cycle: vec![],
+            check_muted: false,
+            muted: vec![],
+            muted_candidates: FxHashSet::default(),
+            muted_fed: FxHashSet::default(),
+            dropped: Vec::with_capacity(64),
+            dropped_params: Vec::with_capacity(64),
+            removed: FxHashSet::with_capacity_and_hasher(64, Default::default()),
             cycle_breakers: vec![],
+            measure_nodes: false,
+            drop_tails: false,
+            watchdog: None,
             alloc: Alloc::with_capacity(64),
+            #[cfg(feature = "parallel-rendering")]
+            pool: None,
+            #[cfg(feature = "parallel-rendering")]
+            levels: vec![],
         }
     }
 
@@ -119,6 +236,7 @@ impl Graph {
         let inputs = vec![AudioRenderQuantum::from(self.alloc.silence()); number_of_inputs];
         let outputs = vec![AudioRenderQuantum::from(self.alloc.silence()); number_of_outputs];
 
+        let skip_when_silent = processor.skip_when_silent();
         self.nodes.insert(
             index,
             RefCell::new(Node {
@@ -130,51 +248,93 @@ impl Graph {
                 free_when_finished: false,
                 has_inputs_connected: false,
                 cycle_breaker: false,
+                bypassed: false,
+                process_duration: Duration::ZERO,
+                skip_when_silent,
+                tail_time: true,
             }),
         );
+
+        self.ordered.clear(); // void current ordering
+    }
+
+    /// Replace the processor of a node, the previous processor is dropped
+    pub fn replace_processor(&mut self, index: AudioNodeId, processor: Box<dyn AudioProcessor>) {
+        // the node may already have been removed from the graph after its handle was dropped
+        if let Some(node) = self.nodes.get_mut(&index) {
+            let node = node.get_mut();
+            node.skip_when_silent = processor.skip_when_silent();
+            node.tail_time = true;
+            node.processor = processor;
+        }
+    }
+
+    /// Deliver a message to the processor of a node
+    pub fn route_message(&mut self, index: AudioNodeId, msg: Box<dyn Any + Send>) {
+        // the node may already have been removed from the graph after its handle was dropped
+        if let Some(node) = self.nodes.get_mut(&index) {
+            node.get_mut().processor.onmessage(msg);
+        }
     }
 
+    /// Add an edge between two ports, duplicate connections are ignored
     pub fn add_edge(&mut self, source: (AudioNodeId, usize), dest: (AudioNodeId, usize)) {
-        self.nodes
+        let other_slot = self
+            .nodes
+            .slot_of(&dest.0)
+            .unwrap_or_else(|| panic!("cannot connect {:?} to {:?}", source, dest));
+        let edges = &mut self
+            .nodes
             .get_mut(&source.0)
             .unwrap_or_else(|| panic!("cannot connect {:?} to {:?}", source, dest))
             .get_mut()
-            .outgoing_edges
-            .push(OutgoingEdge {
-                self_index: source.1,
-                other_id: dest.0,
-                other_index: dest.1,
-            });
+            .outgoing_edges;
 
-        self.ordered.clear(); // void current ordering
-    }
+        let duplicate = edges.iter().any(|edge| {
+            edge.self_index == source.1 && edge.other_id == dest.0 && edge.other_index == dest.1
+        });
+        if duplicate {
+            return;
+        }
 
-    pub fn remove_edge(&mut self, source: AudioNodeId, dest: AudioNodeId) {
-        self.nodes
-            .get_mut(&source)
-            .unwrap_or_else(|| panic!("cannot remove the edge from {:?} to {:?}", source, dest))
-            .get_mut()
-            .outgoing_edges
-            .retain(|edge| edge.other_id != dest);
+        edges.push(OutgoingEdge {
+            self_index: source.1,
+            other_id: dest.0,
+            other_slot,
+            other_index: dest.1,
+        });
 
         self.ordered.clear(); // void current ordering
     }
 
-    pub fn remove_edges_from(&mut self, source: AudioNodeId) {
-        self.nodes
-            .get_mut(&source)
-            .unwrap_or_else(|| panic!("cannot remove edges from {:?}", source))
-            .get_mut()
-            .outgoing_edges
-            .clear();
-
-        self.nodes.values_mut().for_each(|node| {
-            node.get_mut()
-                .outgoing_edges
-                .retain(|edge| edge.other_id != source);
-        });
+    /// Remove the outgoing edges of `source` from the given output port to the given destination
+    /// node and input port, `None` matching any value.
+    ///
+    /// Edges to the hidden AudioParam input port are only removed when explicitly targeted, they
+    /// are not part of the user facing connections.
+    pub fn remove_edges(
+        &mut self,
+        source: AudioNodeId,
+        output: Option<usize>,
+        dest: Option<AudioNodeId>,
+        input: Option<usize>,
+    ) {
+        // The source node may have been dropped from the audio graph already, e.g. when the
+        // connection of a capture is removed after its source has finished playing.
+        if let Some(node) = self.nodes.get_mut(&source) {
+            node.get_mut().outgoing_edges.retain(|edge| {
+                let matches = (output.is_none() || output == Some(edge.self_index))
+                    && (dest.is_none() || dest == Some(edge.other_id))
+                    && match input {
+                        None => edge.other_index != usize::MAX,
+                        Some(i) => edge.other_index == i,
+                    };
+                !matches
+            });
+        }
 
         self.ordered.clear(); // void current ordering
+        self.check_muted = true;
     }
 
     pub fn mark_free_when_finished(&mut self, index: AudioNodeId) {
@@ -183,6 +343,7 @@ impl Graph {
         // Therefore, do not assume this node still exists:
         if let Some(node) = self.nodes.get_mut(&index) {
             node.get_mut().free_when_finished = true;
+            self.check_muted = true;
         }
     }
 
@@ -190,6 +351,79 @@ impl Graph {
         self.nodes.get_mut(&index).unwrap().get_mut().cycle_breaker = true;
     }
 
+    /// Enable, update or disable the watchdog, the current degradations are lifted
+    pub fn set_watchdog(&mut self, options: Option<WatchdogOptions>) {
+        self.drop_tails = false;
+        self.clear_bypass();
+        self.measure_nodes = matches!(&options, Some(o) if o.bypass_heavy_nodes);
+        self.watchdog = options.map(Watchdog::new);
+    }
+
+    /// Render the nodes that do not depend on each other on the given number of threads
+    #[cfg(feature = "parallel-rendering")]
+    pub fn set_render_threads(&mut self, number_of_threads: usize) {
+        self.pool =
+            (number_of_threads > 1).then(|| Box::new(RenderPool::new(number_of_threads - 1)));
+        self.alloc.set_shared(self.pool.is_some());
+        self.levels.clear();
+        self.ordered.clear(); // void current ordering
+    }
+
+    /// Register the load of a render callback with the watchdog and apply its response
+    ///
+    /// Returns the new state, degraded or not, when the watchdog intervened.
+    pub fn watchdog_tick(&mut self, load: f64) -> Option<bool> {
+        let watchdog = self.watchdog.as_mut()?;
+
+        match watchdog.tick(load) {
+            WatchdogAction::None => None,
+            WatchdogAction::Degrade => {
+                let options = watchdog.options();
+                self.drop_tails = options.drop_tails;
+                if options.bypass_heavy_nodes {
+                    self.bypass_heaviest_node();
+                }
+                Some(true)
+            }
+            WatchdogAction::Recover => {
+                self.drop_tails = false;
+                self.clear_bypass();
+                Some(false)
+            }
+        }
+    }
+
+    /// Pass the input of the node that took the longest to process in the last render quantum
+    /// through, instead of processing it
+    fn bypass_heaviest_node(&mut self) {
+        let heaviest = self
+            .nodes
+            .iter()
+            .filter(|(id, _)| id.0 >= 2) // never bypass Listener and Destination node
+            .map(|(_, node)| node.borrow_mut())
+            .filter(|node| !node.bypassed && node.can_bypass())
+            .max_by_key(|node| node.process_duration);
+
+        if let Some(mut node) = heaviest {
+            node.bypassed = true;
+        }
+    }
+
+    /// Process all nodes again
+    fn clear_bypass(&mut self) {
+        self.nodes
+            .values_mut()
+            .for_each(|node| node.get_mut().bypassed = false);
+    }
+
+    /// Number of nodes bypassed by the watchdog
+    pub fn bypassed_nodes(&self) -> usize {
+        self.nodes
+            .values()
+            .filter(|node| node.borrow().bypassed)
+            .count()
+    }
+
     /// Helper function for `order_nodes` - traverse node and outgoing edges
     ///
     /// The return value indicates `cycle_breaker_applied`:
@@ -198,7 +432,7 @@ impl Graph {
     fn visit(
         &self,
         node_id: AudioNodeId,
-        marked: &mut Vec<AudioNodeId>,
+        marked: &mut FxHashSet<AudioNodeId>,
         marked_temp: &mut Vec<AudioNodeId>,
         ordered: &mut Vec<AudioNodeId>,
         in_cycle: &mut Vec<AudioNodeId>,
@@ -228,13 +462,11 @@ impl Graph {
             }
         }
 
-        // Do not visit nodes multiple times
-        if marked.contains(&node_id) {
+        // Do not visit nodes multiple times, add node to the visited list otherwise
+        if !marked.insert(node_id) {
             return false;
         }
 
-        // Add node to the visited list
-        marked.push(node_id);
         // Add node to the current cycle detection list
         marked_temp.push(node_id);
 
@@ -350,6 +582,264 @@ impl Graph {
         self.marked_temp = marked_temp;
         self.in_cycle = in_cycle;
         self.cycle_breakers = cycle_breakers;
+        self.check_muted = true;
+
+        #[cfg(feature = "parallel-rendering")]
+        if self.pool.is_some() {
+            self.group_levels();
+        }
+    }
+
+    /// Group the ordered nodes by level, for parallel rendering
+    ///
+    /// The level of a node is the length of the longest path leading to it, so the nodes of a
+    /// level only depend on the nodes of the previous levels. The ordering stays topological.
+    #[cfg(feature = "parallel-rendering")]
+    fn group_levels(&mut self) {
+        let mut levels: FxHashMap<AudioNodeId, usize> =
+            self.ordered.iter().map(|&id| (id, 0)).collect();
+        for id in self.ordered.iter() {
+            let level = levels[id];
+            // nodes muted in a cycle are not ordered, they are skipped
+            for edge in self.nodes[id].borrow().outgoing_edges.iter() {
+                if let Some(other) = levels.get_mut(&edge.other_id) {
+                    *other = (*other).max(level + 1);
+                }
+            }
+        }
+
+        // stable sort, the order of the nodes within a level is kept
+        self.ordered.sort_by_key(|id| levels[id]);
+
+        self.levels.clear();
+        for (i, id) in self.ordered.iter().enumerate() {
+            let next = self.ordered.get(i + 1);
+            if next.map(|next| levels[next]) != Some(levels[id]) {
+                self.levels.push(i + 1);
+            }
+        }
+    }
+
+    /// Validate the internal consistency of the graph, used by the
+    /// [`testing`](crate::testing) module
+    ///
+    /// All edges must point to existing nodes and ports, and the `expected_edges` (as tuples of
+    /// source, output port, destination and input port) must be present.
+    pub(crate) fn check_consistency(
+        &self,
+        expected_edges: &[(AudioNodeId, usize, AudioNodeId, usize)],
+    ) -> Result<(), String> {
+        for (id, node) in self.nodes.iter() {
+            let node = node.borrow();
+            for edge in node.outgoing_edges.iter() {
+                let other = self.nodes.get(&edge.other_id).ok_or_else(|| {
+                    format!("{:?} has an edge to removed node {:?}", id, edge.other_id)
+                })?;
+                if self.nodes.slot_of(&edge.other_id) != Some(edge.other_slot) {
+                    return Err(format!(
+                        "{:?} has an edge to the wrong slot of {:?}",
+                        id, edge.other_id
+                    ));
+                }
+                if edge.self_index >= node.outputs.len() {
+                    return Err(format!(
+                        "{:?} has an edge from unknown output {}",
+                        id, edge.self_index
+                    ));
+                }
+                if edge.other_index != usize::MAX && edge.other_index >= other.borrow().inputs.len()
+                {
+                    return Err(format!(
+                        "{:?} has an edge to unknown input {} of {:?}",
+                        id, edge.other_index, edge.other_id
+                    ));
+                }
+            }
+        }
+
+        if let Some(id) = self.ordered.iter().find(|id| !self.nodes.contains_key(id)) {
+            return Err(format!("removed node {:?} is still ordered", id));
+        }
+
+        for &(from, output, to, input) in expected_edges {
+            let present = self.nodes.get(&from).is_some_and(|node| {
+                let node = node.borrow();
+                // the outgoing edges of cycle breakers are cleared when a cycle is detected
+                node.cycle_breaker
+                    || node.outgoing_edges.iter().any(|edge| {
+                        edge.self_index == output
+                            && edge.other_id == to
+                            && edge.other_index == input
+                    })
+            });
+            if !present {
+                return Err(format!(
+                    "connection {:?}:{} -> {:?}:{} is missing from the graph",
+                    from, output, to, input
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ids of the nodes in the graph
+    pub(crate) fn node_ids(&self) -> impl Iterator<Item = AudioNodeId> + '_ {
+        self.nodes.keys().copied()
+    }
+
+    /// Render a node: mix its inputs and run its processor, catching any panic that may occur
+    ///
+    /// Returns the tail time reported by the processor.
+    fn process_node(
+        node: &mut Node,
+        params: AudioParamValues<'_>,
+        scope: &RenderScope,
+        measure_nodes: bool,
+    ) -> bool {
+        // Idle branch: the processor reported no tail time and its inputs are still silent,
+        // so its outputs are silent too without calling it
+        if node.skip_when_silent
+            && !node.tail_time
+            && !node.bypassed
+            && node.inputs.iter().all(AudioRenderQuantum::is_silent)
+        {
+            node.outputs
+                .iter_mut()
+                .filter(|output| !output.is_silent())
+                .for_each(AudioRenderQuantum::make_silent);
+            return false;
+        }
+
+        // make sure all input buffers have the correct number of channels, this might not be
+        // the case if the node has no inputs connected or the channel count has just changed
+        let interpretation = node.channel_config.interpretation();
+        let count = node.channel_config.count();
+        node.inputs
+            .iter_mut()
+            .for_each(|i| i.mix(count, interpretation));
+
+        // We are abusing AssertUnwindSafe here, we cannot guarantee it upholds.
+        // This may lead to logic bugs later on, but it is the best that we can do.
+        // The alternative is to crash and reboot the render thread.
+        let catch_me = AssertUnwindSafe(|| {
+            if node.bypassed {
+                node.outputs[0] = node.inputs[0].clone();
+                return false;
+            }
+
+            if !measure_nodes {
+                return node.process(params, scope);
+            }
+
+            let start = Instant::now();
+            let tail_time = node.process(params, scope);
+            node.process_duration = start.elapsed();
+            tail_time
+        });
+        let tail_time = match panic::catch_unwind(catch_me) {
+            Ok(tail_time) => tail_time,
+            Err(e) => {
+                // Replace the node with silence. It stays in the graph with its
+                // connections, so the rest of the graph is not affected and the node
+                // handle on the control thread remains valid.
+                node.processor = Box::new(FailedProcessor);
+                node.outputs
+                    .iter_mut()
+                    .for_each(AudioRenderQuantum::make_silent);
+                node.skip_when_silent = false;
+                scope.report_error(e);
+                false
+            }
+        };
+        node.tail_time = tail_time;
+
+        tail_time
+    }
+
+    /// Process the nodes of a level on the threads of the pool
+    ///
+    /// Returns the tail times reported by the processors.
+    #[cfg(feature = "parallel-rendering")]
+    fn process_level(
+        pool: &RenderPool,
+        nodes: &NodeCollection,
+        level: &[AudioNodeId],
+        scope: &RenderScope,
+        measure_nodes: bool,
+    ) -> Vec<bool> {
+        let mut borrowed: Vec<_> = level
+            .iter()
+            .map(|id| nodes.get(id).unwrap().borrow_mut())
+            .collect();
+        let jobs: Vec<_> = level
+            .iter()
+            .zip(borrowed.iter_mut())
+            .map(|(&id, node)| Mutex::new((id, &mut **node, false)))
+            .collect();
+
+        let shared = SharedNodes(nodes);
+        let (current_frame, current_time, sample_rate) =
+            (scope.current_frame, scope.current_time, scope.sample_rate);
+        let event_sender = &scope.event_sender;
+        pool.for_each(jobs.len(), &|i| {
+            let mut job = jobs[i].lock().unwrap();
+            let (id, node, tail_time) = &mut *job;
+
+            let scope = RenderScope {
+                current_frame,
+                current_time,
+                sample_rate,
+                node_id: Cell::new(*id),
+                event_sender: event_sender.clone(),
+            };
+            // SAFETY: only the nodes of this level are mutably borrowed, their params and the
+            // listener belong to the previous levels
+            let params = unsafe { AudioParamValues::from_shared(shared.nodes()) };
+            *tail_time = Self::process_node(node, params, &scope, measure_nodes);
+        });
+
+        jobs.into_iter()
+            .map(|job| job.into_inner().unwrap().2)
+            .collect()
+    }
+
+    /// Accumulate the outputs of a processed node into the inputs of the nodes it is connected
+    /// to
+    ///
+    /// Returns whether the node has finished, it is decommissioned by the caller.
+    fn propagate(nodes: &NodeCollection, slot: usize, tail_time: bool, drop_tails: bool) -> bool {
+        let mut node = nodes.slot(slot).borrow_mut();
+
+        // iterate all outgoing edges, lookup these nodes and accumulate into their input.
+        // Silent outputs are skipped by the summing, they only affect the channel count.
+        node.outgoing_edges
+            .iter()
+            // audio params are connected to the 'hidden' usize::MAX output, ignore them here
+            .filter(|edge| edge.other_index != usize::MAX)
+            .for_each(|edge| {
+                let mut output_node = nodes.slot(edge.other_slot).borrow_mut();
+                let output_node = &mut *output_node;
+                output_node.has_inputs_connected = true;
+                let signal = &node.outputs[edge.self_index];
+
+                output_node.inputs[edge.other_index].add(signal, &output_node.channel_config);
+            });
+
+        let can_free = node.can_free(tail_time, drop_tails);
+
+        // Node is not dropped.
+        if !can_free {
+            // Reset input buffers as they will be summed up in the next render quantum.
+            node.inputs
+                .iter_mut()
+                .for_each(AudioRenderQuantum::make_silent);
+
+            // Reset input state
+            node.has_inputs_connected = false;
+        }
+
+        can_free
     }
 
     /// Render a single audio quantum by traversing the node list
@@ -359,105 +849,191 @@ impl Graph {
             self.order_nodes();
         }
 
-        // keep track of end-of-lifecyle nodes
-        let mut nodes_dropped = false;
+        // keep track of end-of-lifecyle nodes, they are decommissioned at once after the
+        // rendering so large graphs are only traversed once
+        let mut dropped = std::mem::take(&mut self.dropped);
 
         // for borrow-checker reasons, move mutable borrow of nodes out of self
         let nodes = &mut self.nodes;
+        let measure_nodes = self.measure_nodes;
+        let drop_tails = self.drop_tails;
 
-        // process every node, in topological sorted order
-        self.ordered.iter().for_each(|index| {
+        // process a single node and hand over its output, returns whether the node was dropped
+        let render_node = |nodes: &NodeCollection, index: AudioNodeId| {
             // acquire a mutable borrow of the current processing node
-            let mut node = nodes.get(index).unwrap().borrow_mut();
-
-            // make sure all input buffers have the correct number of channels, this might not be
-            // the case if the node has no inputs connected or the channel count has just changed
-            let interpretation = node.channel_config.interpretation();
-            let count = node.channel_config.count();
-            node.inputs
-                .iter_mut()
-                .for_each(|i| i.mix(count, interpretation));
-
-            // let the current node process (catch any panics that may occur)
-            let params = AudioParamValues::from(&*nodes);
-            scope.node_id.set(*index);
-            let (success, tail_time) = {
-                // We are abusing AssertUnwindSafe here, we cannot guarantee it upholds.
-                // This may lead to logic bugs later on, but it is the best that we can do.
-                // The alternative is to crash and reboot the render thread.
-                let catch_me = AssertUnwindSafe(|| node.process(params, scope));
-                match panic::catch_unwind(catch_me) {
-                    Ok(tail_time) => (true, tail_time),
-                    Err(e) => {
-                        node.outgoing_edges.clear();
-                        scope.report_error(e);
-                        (false, false)
-                    }
-                }
-            };
+            let slot = nodes.slot_of(&index).unwrap();
+            let mut node = nodes.slot(slot).borrow_mut();
+            let params = AudioParamValues::from(nodes);
+            scope.node_id.set(index);
+            let tail_time = Self::process_node(&mut node, params, scope, measure_nodes);
+            drop(node);
 
-            // iterate all outgoing edges, lookup these nodes and add to their input
-            node.outgoing_edges
-                .iter()
-                // audio params are connected to the 'hidden' usize::MAX output, ignore them here
-                .filter(|edge| edge.other_index != usize::MAX)
-                .for_each(|edge| {
-                    let mut output_node = nodes.get(&edge.other_id).unwrap().borrow_mut();
-                    output_node.has_inputs_connected = true;
-                    let signal = &node.outputs[edge.self_index];
-                    let channel_config = &output_node.channel_config.clone();
-
-                    output_node.inputs[edge.other_index].add(signal, channel_config);
-                });
+            Self::propagate(nodes, slot, tail_time, drop_tails)
+        };
 
-            let can_free = !success || node.can_free(tail_time);
+        // process every node, in topological sorted order
+        #[cfg(not(feature = "parallel-rendering"))]
+        self.ordered.iter().for_each(|&index| {
+            if render_node(nodes, index) {
+                dropped.push(index);
+            }
+        });
 
-            // Node is not dropped.
-            if !can_free {
-                // Reset input buffers as they will be summed up in the next render quantum.
-                node.inputs
-                    .iter_mut()
-                    .for_each(AudioRenderQuantum::make_silent);
+        #[cfg(feature = "parallel-rendering")]
+        match &self.pool {
+            // process every node, in topological sorted order
+            None => self.ordered.iter().for_each(|&index| {
+                if render_node(nodes, index) {
+                    dropped.push(index);
+                }
+            }),
+            // process the levels in order, the nodes of a level in parallel
+            Some(pool) => {
+                let mut start = 0;
+                for &end in self.levels.iter() {
+                    let level = &self.ordered[start..end];
+                    start = end;
+
+                    if level.len() == 1 {
+                        if render_node(nodes, level[0]) {
+                            dropped.push(level[0]);
+                        }
+                        continue;
+                    }
 
-                // Reset input state
-                node.has_inputs_connected = false;
+                    let tail_times = Self::process_level(pool, nodes, level, scope, measure_nodes);
+                    level
+                        .iter()
+                        .zip(tail_times)
+                        .for_each(|(&index, tail_time)| {
+                            let slot = nodes.slot_of(&index).unwrap();
+                            if Self::propagate(nodes, slot, tail_time, drop_tails) {
+                                dropped.push(index);
+                            }
+                        });
+                }
             }
+        }
 
-            drop(node); // release borrow of self.nodes
+        // Ids of all the nodes removed in this render quantum
+        let mut removed = std::mem::take(&mut self.removed);
 
-            // Check if we can decommission this node (end of life)
-            if can_free {
-                // Node is dropped, remove it from the node list
-                nodes.remove(index);
+        if !dropped.is_empty() {
+            // Remove the finished nodes from the node list
+            removed.extend(dropped.iter().copied());
+            dropped.iter().for_each(|id| {
+                nodes.remove(id);
+            });
+
+            // Nodes are only dropped when they do not have incoming connections.
+            // But they may have AudioParams feeding into them, these can de dropped too.
+            let params = &mut self.dropped_params;
+            nodes.retain(|id, n| {
+                let keep = id.0 < 2 // never drop Listener and Destination node
+                    || !n
+                        .borrow()
+                        .outgoing_edges
+                        .iter()
+                        .any(|e| removed.contains(&e.other_id));
+                if !keep {
+                    params.push(*id);
+                }
+                keep
+            });
+            removed.extend(params.drain(..));
+        }
 
-                // And remove it from the ordering after we have processed all nodes
-                nodes_dropped = true;
+        // Nodes that are part of a cycle without cycle breaker are muted and not processed, so
+        // they are not decommissioned above. Drop them once the control thread has released all
+        // of them and no other node is feeding into the cycle anymore (AudioParams excluded,
+        // these are dropped along with their node).
+        //
+        // This is only checked after a change that can release a cycle, with preallocated
+        // scratch storage.
+        if self.check_muted && !self.in_cycle.is_empty() {
+            let muted = &mut self.muted;
+            let candidates = &mut self.muted_candidates;
+            let fed = &mut self.muted_fed;
+
+            muted.clear();
+            muted.extend(
+                self.in_cycle
+                    .iter()
+                    .copied()
+                    .filter(|id| matches!(nodes.get(id), Some(n) if n.borrow().free_when_finished)),
+            );
 
-                // Nodes are only dropped when they do not have incoming connections.
-                // But they may have AudioParams feeding into them, these can de dropped too.
-                nodes.retain(|id, n| {
-                    id.0 < 2 // never drop Listener and Destination node
-                        || !n
-                            .borrow()
+            loop {
+                candidates.clear();
+                candidates.extend(muted.iter().copied());
+
+                // candidates fed by a node that is not dropped along
+                fed.clear();
+                nodes
+                    .iter()
+                    .filter(|(id, _)| !candidates.contains(id))
+                    .for_each(|(_, node)| {
+                        node.borrow()
                             .outgoing_edges
                             .iter()
-                            .any(|e| e.other_id == *index)
+                            .filter(|e| e.other_index != usize::MAX)
+                            .filter(|e| candidates.contains(&e.other_id))
+                            .for_each(|e| {
+                                fed.insert(e.other_id);
+                            })
+                    });
+
+                muted.retain(|id| !fed.contains(id));
+                if muted.len() == candidates.len() {
+                    break;
+                }
+            }
+
+            // `candidates` now holds the muted nodes
+            if !muted.is_empty() {
+                muted.iter().for_each(|id| {
+                    nodes.remove(id);
                 });
+                nodes.retain(|id, n| {
+                    let keep = id.0 < 2 // never drop Listener and Destination node
+                        || !n.borrow().outgoing_edges.iter().any(|e| {
+                            e.other_index == usize::MAX && candidates.contains(&e.other_id)
+                        });
+                    if !keep {
+                        removed.insert(*id);
+                    }
+                    keep
+                });
+                self.in_cycle.retain(|id| !candidates.contains(id));
+                removed.extend(muted.iter().copied());
             }
-        });
+        }
+        self.check_muted = !removed.is_empty(); // dropped nodes may have fed a cycle
 
         // If there were any nodes decomissioned, remove from graph order
-        if nodes_dropped {
-            let mut i = 0;
-            while i < self.ordered.len() {
-                if !nodes.contains_key(&self.ordered[i]) {
-                    self.ordered.remove(i);
-                } else {
-                    i += 1;
-                }
+        if !removed.is_empty() {
+            // Nodes may still be connected to the AudioParams that were dropped along with their
+            // node, remove these dangling edges
+            nodes.values_mut().for_each(|node| {
+                node.get_mut()
+                    .outgoing_edges
+                    .retain(|edge| !removed.contains(&edge.other_id))
+            });
+
+            self.ordered.retain(|id| !removed.contains(id));
+
+            #[cfg(feature = "parallel-rendering")]
+            if self.pool.is_some() {
+                self.group_levels();
             }
         }
 
+        // Re-instate the scratch storage, keeping its capacity
+        dropped.clear();
+        removed.clear();
+        self.dropped = dropped;
+        self.removed = removed;
+
         // Return the output buffer of destination node
         self.nodes
             .get_mut(&AudioNodeId(0))
@@ -471,6 +1047,7 @@ impl Graph {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::Cell;
 
     #[derive(Debug, Clone)]
     struct TestNode {}
@@ -529,7 +1106,7 @@ mod tests {
         assert!(pos2 < pos1); // node 1 depends on node 2
 
         // Detach node 1 (and thus node 2) from the root node
-        graph.remove_edge(AudioNodeId(1), AudioNodeId(0));
+        graph.remove_edges(AudioNodeId(1), None, Some(AudioNodeId(0)), None);
         graph.order_nodes();
 
         // sorting is not deterministic, but this should uphold:
@@ -547,6 +1124,109 @@ mod tests {
         assert!(pos2 < pos1); // node 1 depends on node 2
     }
 
+    #[test]
+    fn test_reuse_slots() {
+        let mut graph = Graph::new();
+
+        let node = Box::new(TestNode {});
+        graph.add_node(AudioNodeId(0), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(2), node.clone(), 0, 1, config());
+        graph.add_node(AudioNodeId(3), node.clone(), 1, 1, config());
+        graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(3), 0));
+        graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(0), 0));
+        let slot = graph.nodes.slot_of(&AudioNodeId(2)).unwrap();
+
+        // the finished source is dropped, its slot is free
+        graph.mark_free_when_finished(AudioNodeId(2));
+        let scope = RenderScope {
+            current_frame: 0,
+            current_time: 0.,
+            sample_rate: 48000.,
+            node_id: Cell::new(AudioNodeId(0)),
+            event_sender: None,
+        };
+        graph.render(&scope);
+        assert!(!graph.nodes.contains_key(&AudioNodeId(2)));
+
+        // the next node takes the slot, edges address it by its own id
+        graph.add_node(AudioNodeId(4), node, 0, 1, config());
+        assert_eq!(graph.nodes.slot_of(&AudioNodeId(4)), Some(slot));
+        graph.add_edge((AudioNodeId(4), 0), (AudioNodeId(3), 0));
+        graph.render(&scope);
+
+        let edges = [
+            (AudioNodeId(4), 0, AudioNodeId(3), 0),
+            (AudioNodeId(3), 0, AudioNodeId(0), 0),
+        ];
+        assert_eq!(graph.check_consistency(&edges), Ok(()));
+        assert_eq!(graph.node_ids().count(), 3);
+    }
+
+    #[test]
+    fn test_reuse_scratch_storage() {
+        let mut graph = Graph::new();
+
+        let node = Box::new(TestNode {});
+        graph.add_node(AudioNodeId(0), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(2), node.clone(), 0, 1, config());
+        graph.add_node(AudioNodeId(3), node, 0, 1, config());
+        graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(0), 0));
+        // node 3 feeds an AudioParam of node 2
+        graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(2), usize::MAX));
+
+        let dropped = graph.dropped.as_ptr();
+        let dropped_params = graph.dropped_params.as_ptr();
+
+        graph.mark_free_when_finished(AudioNodeId(2));
+        let scope = RenderScope {
+            current_frame: 0,
+            current_time: 0.,
+            sample_rate: 48000.,
+            node_id: Cell::new(AudioNodeId(0)),
+            event_sender: None,
+        };
+        graph.render(&scope);
+
+        // the source and its param are removed, without reallocating the scratch storage
+        assert!(!graph.nodes.contains_key(&AudioNodeId(2)));
+        assert!(!graph.nodes.contains_key(&AudioNodeId(3)));
+        assert!(graph.dropped.is_empty());
+        assert!(graph.dropped_params.is_empty());
+        assert!(graph.removed.is_empty());
+        assert_eq!(graph.dropped.as_ptr(), dropped);
+        assert_eq!(graph.dropped_params.as_ptr(), dropped_params);
+        assert!(graph.removed.capacity() >= 64);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel-rendering")]
+    fn test_levels() {
+        let mut graph = Graph::new();
+        graph.set_render_threads(2);
+
+        let node = Box::new(TestNode {});
+        graph.add_node(AudioNodeId(0), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(1), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(2), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(3), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(4), node, 1, 1, config());
+
+        // 2 -> 1 -> 0, 3 -> 0 and 4 -> param of 1
+        graph.add_edge((AudioNodeId(1), 0), (AudioNodeId(0), 0));
+        graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(1), 0));
+        graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(0), 0));
+        graph.add_edge((AudioNodeId(4), 0), (AudioNodeId(1), usize::MAX));
+
+        graph.order_nodes();
+
+        assert_eq!(graph.levels, vec![3, 4, 5]);
+        let mut first: Vec<_> = graph.ordered[..3].to_vec();
+        first.sort_by_key(|id| id.0);
+        assert_eq!(first, vec![AudioNodeId(2), AudioNodeId(3), AudioNodeId(4)]);
+        assert_eq!(graph.ordered[3], AudioNodeId(1));
+        assert_eq!(graph.ordered[4], AudioNodeId(0));
+    }
+
     #[test]
     fn test_remove_all() {
         let mut graph = Graph::new();
@@ -568,7 +1248,7 @@ mod tests {
             vec![AudioNodeId(1), AudioNodeId(2), AudioNodeId(0)]
         );
 
-        graph.remove_edges_from(AudioNodeId(1));
+        graph.remove_edges(AudioNodeId(1), None, None, None);
         graph.order_nodes();
 
         // sorting is not deterministic, but this should uphold:
@@ -586,6 +1266,43 @@ mod tests {
         assert!(pos2 < pos0); // node 1 depends on node 0
     }
 
+    #[test]
+    fn test_remove_edges_by_port() {
+        let mut graph = Graph::new();
+
+        let node = Box::new(TestNode {});
+        graph.add_node(AudioNodeId(0), node.clone(), 2, 1, config());
+        graph.add_node(AudioNodeId(1), node, 1, 2, config());
+
+        // link 1:0->0:0, 1:0->0:1, 1:1->0:0 and the hidden AudioParam port
+        graph.add_edge((AudioNodeId(1), 0), (AudioNodeId(0), 0));
+        graph.add_edge((AudioNodeId(1), 0), (AudioNodeId(0), 1));
+        graph.add_edge((AudioNodeId(1), 1), (AudioNodeId(0), 0));
+        graph.add_edge((AudioNodeId(1), 0), (AudioNodeId(0), usize::MAX));
+
+        let edges = |graph: &mut Graph| {
+            graph
+                .nodes
+                .get_mut(&AudioNodeId(1))
+                .unwrap()
+                .get_mut()
+                .outgoing_edges
+                .iter()
+                .map(|e| (e.self_index, e.other_index))
+                .collect::<Vec<_>>()
+        };
+
+        graph.remove_edges(AudioNodeId(1), Some(0), Some(AudioNodeId(0)), Some(1));
+        assert_eq!(edges(&mut graph), vec![(0, 0), (1, 0), (0, usize::MAX)]);
+
+        graph.remove_edges(AudioNodeId(1), Some(1), None, None);
+        assert_eq!(edges(&mut graph), vec![(0, 0), (0, usize::MAX)]);
+
+        // the hidden AudioParam port is only removed when targeted explicitly
+        graph.remove_edges(AudioNodeId(1), None, None, None);
+        assert_eq!(edges(&mut graph), vec![(0, usize::MAX)]);
+    }
+
     #[test]
     fn test_cycle() {
         let mut graph = Graph::new();
@@ -620,4 +1337,186 @@ mod tests {
         // a-cyclic part should be present
         assert!(pos3.unwrap() < pos0.unwrap());
     }
+
+    #[test]
+    fn test_drop_muted_cycle() {
+        let mut graph = Graph::new();
+
+        let node = Box::new(TestNode {});
+        graph.add_node(AudioNodeId(0), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(2), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(3), node, 1, 1, config());
+
+        // cycle 2<>3, feeding into the destination
+        graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(3), 0));
+        graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(2), 0));
+        graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(0), 0));
+
+        let scope = RenderScope {
+            current_frame: 0,
+            current_time: 0.,
+            sample_rate: 48000.,
+            node_id: Cell::new(AudioNodeId(0)),
+            event_sender: None,
+        };
+
+        // the cycle is only inspected after a change
+        graph.render(&scope);
+        assert!(!graph.check_muted);
+        assert_eq!(graph.in_cycle.len(), 2);
+
+        graph.mark_free_when_finished(AudioNodeId(2));
+        graph.render(&scope);
+        assert_eq!(graph.node_ids().count(), 3);
+
+        // the cycle is dropped once all of its nodes are released
+        graph.mark_free_when_finished(AudioNodeId(3));
+        assert!(graph.check_muted);
+        graph.render(&scope);
+        assert_eq!(graph.node_ids().collect::<Vec<_>>(), vec![AudioNodeId(0)]);
+        assert!(graph.in_cycle.is_empty());
+        assert_eq!(graph.check_consistency(&[]), Ok(()));
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let mut graph = Graph::new();
+        let node = Box::new(TestNode {});
+        graph.add_node(AudioNodeId(0), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(2), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(3), node.clone(), 1, 1, config());
+        graph.add_node(AudioNodeId(4), node, 1, 1, config());
+
+        // node 4 is an AudioParam of node 3
+        graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(0), 0));
+        graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(0), 0));
+        graph.add_edge((AudioNodeId(4), 0), (AudioNodeId(3), usize::MAX));
+
+        let durations = [(2, 10), (3, 20), (4, 30)];
+        durations.iter().for_each(|&(id, micros)| {
+            graph
+                .nodes
+                .get_mut(&AudioNodeId(id))
+                .unwrap()
+                .get_mut()
+                .process_duration = Duration::from_micros(micros);
+        });
+
+        let options = WatchdogOptions {
+            max_missed_deadlines: 2,
+            recover_after: 1,
+            drop_tails: true,
+            bypass_heavy_nodes: true,
+        };
+        graph.set_watchdog(Some(options));
+
+        assert_eq!(graph.watchdog_tick(2.), None);
+        assert_eq!(graph.watchdog_tick(2.), Some(true));
+        assert!(graph.drop_tails);
+
+        // the param is never bypassed
+        let bypassed = |graph: &Graph, id| graph.nodes[&AudioNodeId(id)].borrow().bypassed;
+        assert_eq!(graph.bypassed_nodes(), 1);
+        assert!(bypassed(&graph, 3));
+
+        graph.watchdog_tick(2.);
+        graph.watchdog_tick(2.);
+        assert_eq!(graph.bypassed_nodes(), 2);
+        assert!(bypassed(&graph, 2));
+
+        assert_eq!(graph.watchdog_tick(0.5), Some(false));
+        assert_eq!(graph.bypassed_nodes(), 0);
+        assert!(!graph.drop_tails);
+    }
+
+    #[test]
+    fn test_drop_tails() {
+        let mut graph = Graph::new();
+        let node = Box::new(TestNode {});
+        graph.add_node(AudioNodeId(2), node.clone(), 0, 1, config());
+        graph.add_node(AudioNodeId(3), node, 1, 1, config());
+
+        let can_free = |graph: &Graph, id, drop_tails| {
+            graph.nodes[&AudioNodeId(id)]
+                .borrow()
+                .can_free(true, drop_tails)
+        };
+
+        graph.mark_free_when_finished(AudioNodeId(2));
+        graph.mark_free_when_finished(AudioNodeId(3));
+
+        assert!(!can_free(&graph, 3, false));
+        assert!(can_free(&graph, 3, true));
+
+        // playing sources are not affected
+        assert!(!can_free(&graph, 2, true));
+    }
+
+    #[test]
+    fn test_skip_when_silent() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        struct CountingNode(Arc<AtomicUsize>);
+
+        impl AudioProcessor for CountingNode {
+            fn process(
+                &mut self,
+                inputs: &[AudioRenderQuantum],
+                outputs: &mut [AudioRenderQuantum],
+                _params: AudioParamValues,
+                _scope: &RenderScope,
+            ) -> bool {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                outputs[0] = inputs[0].clone();
+                false
+            }
+
+            fn skip_when_silent(&self) -> bool {
+                true
+            }
+        }
+
+        struct ConstantNode;
+
+        impl AudioProcessor for ConstantNode {
+            fn process(
+                &mut self,
+                _inputs: &[AudioRenderQuantum],
+                outputs: &mut [AudioRenderQuantum],
+                _params: AudioParamValues,
+                _scope: &RenderScope,
+            ) -> bool {
+                outputs[0].channel_data_mut(0).fill(1.);
+                true
+            }
+        }
+
+        let mut graph = Graph::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        graph.add_node(AudioNodeId(0), Box::new(TestNode {}), 1, 1, config());
+        let counting = Box::new(CountingNode(Arc::clone(&calls)));
+        graph.add_node(AudioNodeId(2), counting, 1, 1, config());
+        graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(0), 0));
+
+        let scope = RenderScope {
+            current_frame: 0,
+            current_time: 0.,
+            sample_rate: 48000.,
+            node_id: Cell::new(AudioNodeId(0)),
+            event_sender: None,
+        };
+
+        // the first quantum is always processed, the idle node is skipped afterwards
+        graph.render(&scope);
+        graph.render(&scope);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // the node is processed again as soon as its input carries a signal
+        graph.add_node(AudioNodeId(3), Box::new(ConstantNode), 0, 1, config());
+        graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(2), 0));
+        graph.render(&scope);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(!graph.nodes[&AudioNodeId(2)].borrow().outputs[0].is_silent());
+    }
 }
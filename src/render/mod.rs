@@ -2,8 +2,14 @@
 
 // private mods
 pub(crate) mod graph;
+mod node_collection;
 
 // pub(crate) mods
+#[cfg(feature = "parallel-rendering")]
+mod pool;
+#[cfg(feature = "parallel-rendering")]
+pub(crate) use pool::RenderPool;
+pub(crate) mod simd;
 mod thread;
 pub(crate) use thread::*;
 
@@ -12,3 +18,7 @@ mod processor;
 pub use processor::*;
 mod quantum;
 pub use quantum::*;
+mod shared_buffer;
+pub use shared_buffer::*;
+mod spectral;
+pub use spectral::*;
@@ -0,0 +1,131 @@
+//! Compact storage for the nodes of the audio graph
+use std::cell::RefCell;
+use std::ops::Index;
+
+use rustc_hash::FxHashMap;
+
+use super::graph::Node;
+use crate::context::AudioNodeId;
+
+/// Arena of the render nodes of the audio graph
+///
+/// The nodes are stored next to each other in a `Vec` and addressed by small slot indices, so
+/// traversing graphs with thousands of nodes stays cache friendly. Slots are recycled when nodes
+/// are removed, the `AudioNodeId`s (which are never reused) are only mapped to their slot when
+/// the graph is addressed from the control thread.
+pub(crate) struct NodeCollection {
+    /// Node storage, `None` for a vacant slot
+    slots: Vec<Option<(AudioNodeId, RefCell<Node>)>>,
+    /// Vacant slots, reused before the storage grows
+    free: Vec<usize>,
+    /// Slot of each node
+    index: FxHashMap<AudioNodeId, usize>,
+}
+
+impl NodeCollection {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::with_capacity(64),
+            free: vec![],
+            index: FxHashMap::default(),
+        }
+    }
+
+    /// Store a node, returns its slot
+    pub fn insert(&mut self, id: AudioNodeId, node: RefCell<Node>) -> usize {
+        if let Some(&slot) = self.index.get(&id) {
+            self.slots[slot] = Some((id, node));
+            return slot;
+        }
+
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some((id, node));
+                slot
+            }
+            None => {
+                self.slots.push(Some((id, node)));
+                self.slots.len() - 1
+            }
+        };
+        self.index.insert(id, slot);
+
+        slot
+    }
+
+    /// Remove a node, its slot is reused by the nodes inserted afterwards
+    pub fn remove(&mut self, id: &AudioNodeId) -> Option<RefCell<Node>> {
+        let slot = self.index.remove(id)?;
+        self.free.push(slot);
+        self.slots[slot].take().map(|(_, node)| node)
+    }
+
+    /// Only keep the nodes for which `f` returns `true`
+    pub fn retain(&mut self, mut f: impl FnMut(&AudioNodeId, &RefCell<Node>) -> bool) {
+        for (slot, entry) in self.slots.iter_mut().enumerate() {
+            let keep = match entry {
+                Some((id, node)) => f(id, node),
+                None => continue,
+            };
+            if !keep {
+                let (id, _) = entry.take().unwrap();
+                self.index.remove(&id);
+                self.free.push(slot);
+            }
+        }
+    }
+
+    /// Slot of the given node
+    pub fn slot_of(&self, id: &AudioNodeId) -> Option<usize> {
+        self.index.get(id).copied()
+    }
+
+    /// Node stored at the given slot
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slot is vacant.
+    pub fn slot(&self, slot: usize) -> &RefCell<Node> {
+        &self.slots[slot].as_ref().unwrap().1
+    }
+
+    pub fn contains_key(&self, id: &AudioNodeId) -> bool {
+        self.index.contains_key(id)
+    }
+
+    pub fn get(&self, id: &AudioNodeId) -> Option<&RefCell<Node>> {
+        self.slot_of(id).map(|slot| self.slot(slot))
+    }
+
+    pub fn get_mut(&mut self, id: &AudioNodeId) -> Option<&mut RefCell<Node>> {
+        let slot = self.slot_of(id)?;
+        self.slots[slot].as_mut().map(|(_, node)| node)
+    }
+
+    /// Iterate the nodes in slot order
+    pub fn iter(&self) -> impl Iterator<Item = (&AudioNodeId, &RefCell<Node>)> {
+        self.slots.iter().flatten().map(|(id, node)| (id, node))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &AudioNodeId> {
+        self.iter().map(|(id, _)| id)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &RefCell<Node>> {
+        self.iter().map(|(_, node)| node)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut RefCell<Node>> {
+        self.slots.iter_mut().flatten().map(|(_, node)| node)
+    }
+}
+
+impl Index<&AudioNodeId> for NodeCollection {
+    type Output = RefCell<Node>;
+
+    #[track_caller]
+    fn index(&self, id: &AudioNodeId) -> &Self::Output {
+        self.get(id)
+            .unwrap_or_else(|| panic!("node {:?} is not in the graph", id))
+    }
+}
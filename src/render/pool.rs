@@ -0,0 +1,148 @@
+//! Worker threads sharing the rendering of the audio graph
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::JoinHandle;
+
+use crossbeam_channel::{Receiver, Sender};
+
+/// Work shared with the workers, with its lifetime erased
+struct Task(&'static (dyn Fn() + Sync));
+
+/// Pool of threads rendering the independent nodes of the audio graph in parallel
+///
+/// The threads are kept alive for the whole rendering, so distributing the work of a render
+/// quantum only costs a few channel messages.
+pub(crate) struct RenderPool {
+    senders: Vec<Sender<Task>>,
+    handles: Vec<JoinHandle<()>>,
+    done: Receiver<()>,
+}
+
+/// Waits for the workers to finish a task, even when the calling thread unwinds
+struct Pending<'a> {
+    done: &'a Receiver<()>,
+    count: usize,
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        for _ in 0..self.count {
+            // the workers always report back, unless they have been shut down
+            let _ = self.done.recv();
+        }
+    }
+}
+
+impl RenderPool {
+    /// Spawn the given number of worker threads, the calling thread takes part in the work too
+    pub fn new(number_of_workers: usize) -> Self {
+        let (done_sender, done) = crossbeam_channel::unbounded();
+
+        let (senders, handles) = (0..number_of_workers)
+            .map(|i| {
+                let (sender, receiver) = crossbeam_channel::bounded::<Task>(1);
+                let done_sender = done_sender.clone();
+                let handle = std::thread::Builder::new()
+                    .name(format!("web-audio-render-{}", i + 1))
+                    .spawn(move || {
+                        for task in receiver {
+                            // a panic must not leave the caller waiting, it is reported by the
+                            // caller's own processing anyway
+                            let _ = panic::catch_unwind(AssertUnwindSafe(task.0));
+                            let _ = done_sender.send(());
+                        }
+                    })
+                    .expect("Unable to spawn render worker thread");
+                (sender, handle)
+            })
+            .unzip();
+
+        Self {
+            senders,
+            handles,
+            done,
+        }
+    }
+
+    /// Call `f` for every index in `0..count`, distributed over the threads of the pool
+    ///
+    /// Each index is processed exactly once. This method returns when all calls have finished.
+    pub fn for_each(&self, count: usize, f: &(dyn Fn(usize) + Sync)) {
+        let next = AtomicUsize::new(0);
+        let work = || loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            if index >= count {
+                break;
+            }
+            f(index);
+        };
+        let work: &(dyn Fn() + Sync) = &work;
+
+        // SAFETY: the borrowed `work` outlives its use by the workers, as `pending` waits for
+        // every worker the task is sent to before this function returns or unwinds
+        let task: &'static (dyn Fn() + Sync) = unsafe { std::mem::transmute(work) };
+
+        let mut pending = Pending {
+            done: &self.done,
+            count: 0,
+        };
+        for sender in self.senders.iter().take(count.saturating_sub(1)) {
+            if sender.send(Task(task)).is_ok() {
+                pending.count += 1;
+            }
+        }
+
+        work();
+    }
+}
+
+impl Drop for RenderPool {
+    fn drop(&mut self) {
+        // closing the channels stops the workers
+        self.senders.clear();
+        self.handles.drain(..).for_each(|handle| {
+            let _ = handle.join();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_each() {
+        let pool = RenderPool::new(3);
+
+        for count in [0, 1, 2, 10, 100] {
+            let calls: Vec<_> = (0..count).map(|_| AtomicUsize::new(0)).collect();
+            pool.for_each(count, &|i| {
+                calls[i].fetch_add(1, Ordering::Relaxed);
+            });
+            assert!(calls.iter().all(|c| c.load(Ordering::Relaxed) == 1));
+        }
+    }
+
+    #[test]
+    fn test_parallel() {
+        let pool = RenderPool::new(1);
+        let threads = std::sync::Mutex::new(vec![]);
+        let started = std::sync::atomic::AtomicBool::new(false);
+
+        // the first call waits for the second one, which has to run on another thread
+        pool.for_each(2, &|i| {
+            threads.lock().unwrap().push(std::thread::current().id());
+            if i == 0 {
+                while !started.load(Ordering::SeqCst) {
+                    std::thread::yield_now();
+                }
+            } else {
+                started.store(true, Ordering::SeqCst);
+            }
+        });
+
+        let threads = threads.into_inner().unwrap();
+        assert_eq!(threads.len(), 2);
+        assert_ne!(threads[0], threads[1]);
+    }
+}
@@ -3,11 +3,10 @@ use crate::context::{AudioNodeId, AudioParamId};
 use crate::events::{ErrorEvent, EventDispatch};
 use crate::{Event, RENDER_QUANTUM_SIZE};
 
-use super::{graph::Node, AudioRenderQuantum};
+use super::{graph::Node, node_collection::NodeCollection, AudioRenderQuantum};
 
 use crossbeam_channel::Sender;
-use rustc_hash::FxHashMap;
-use std::cell::{Cell, RefCell};
+use std::cell::Cell;
 
 use std::any::Any;
 use std::ops::Deref;
@@ -34,6 +33,17 @@ impl RenderScope {
         }
     }
 
+    /// Send a message to the control thread
+    ///
+    /// The message is delivered to the `onmessage` callback of the
+    /// [`MessagePort`](crate::MessagePort) of the node. Messages are only delivered by an
+    /// [`AudioContext`](crate::context::AudioContext), an `OfflineAudioContext` has no event loop.
+    pub fn post_message(&self, msg: Box<dyn Any + Send>) {
+        if let Some(sender) = self.event_sender.as_ref() {
+            let _ = sender.try_send(EventDispatch::message(self.node_id.get(), msg));
+        }
+    }
+
     pub(crate) fn report_error(&self, error: Box<dyn Any + Send + 'static>) {
         pub fn type_name_of_val<T: ?Sized>(_val: &T) -> &'static str {
             std::any::type_name::<T>()
@@ -46,7 +56,7 @@ impl RenderScope {
             type_name_of_val(&error).to_string()
         };
         eprintln!(
-            "Panic occured in Audio Processor: '{}'. Removing node from graph.",
+            "Panic occured in Audio Processor: '{}'. Node output replaced with silence.",
             &message
         );
 
@@ -97,15 +107,48 @@ pub trait AudioProcessor: Send {
         params: AudioParamValues,
         scope: &RenderScope,
     ) -> bool;
+
+    /// Handle a message posted to the [`MessagePort`](crate::MessagePort) of the node
+    ///
+    /// Messages are delivered at render quantum boundaries, before the call to
+    /// [`process`](Self::process). Note that dropping the message deallocates it on the render
+    /// thread, keep its contents (e.g. by swapping them) to avoid that.
+    ///
+    /// The default implementation ignores the message.
+    fn onmessage(&mut self, msg: Box<dyn Any + Send>) {
+        log::warn!("AudioProcessor has no message handler, message dropped");
+        drop(msg);
+    }
+
+    /// Whether the processor may be skipped while its inputs are silent
+    ///
+    /// Return `true` when the processor outputs silence for silent inputs and has no side
+    /// effects, e.g. no internal clock. It is then not called as long as its inputs are silent
+    /// and its previous call returned `false`, its outputs are silenced instead. This saves the
+    /// processing of idle branches in large graphs, such as the effects of the inactive voices
+    /// of a sampler.
+    ///
+    /// The value is queried once, when the processor is added to the audio graph. The default
+    /// implementation returns `false`.
+    fn skip_when_silent(&self) -> bool {
+        false
+    }
 }
 
-struct DerefAudioRenderQuantumChannel<'a>(std::cell::Ref<'a, Node>);
+enum DerefAudioRenderQuantumChannel<'a> {
+    Borrowed(std::cell::Ref<'a, Node>),
+    Shared(&'a Node),
+}
 
 impl Deref for DerefAudioRenderQuantumChannel<'_> {
     type Target = [f32];
 
     fn deref(&self) -> &Self::Target {
-        let buffer = self.0.get_buffer();
+        let node = match self {
+            Self::Borrowed(node) => node,
+            Self::Shared(node) => *node,
+        };
+        let buffer = node.get_buffer();
         let len = if buffer.single_valued() {
             1
         } else {
@@ -120,12 +163,31 @@ impl Deref for DerefAudioRenderQuantumChannel<'_> {
 ///
 /// Provided to implementations of [`AudioProcessor`] in the render thread
 pub struct AudioParamValues<'a> {
-    nodes: &'a FxHashMap<AudioNodeId, RefCell<Node>>,
+    nodes: &'a NodeCollection,
+    /// Read the nodes without tracking the borrows, when rendering on multiple threads
+    shared: bool,
 }
 
 impl<'a> AudioParamValues<'a> {
-    pub(crate) fn from(nodes: &'a FxHashMap<AudioNodeId, RefCell<Node>>) -> Self {
-        Self { nodes }
+    pub(crate) fn from(nodes: &'a NodeCollection) -> Self {
+        Self {
+            nodes,
+            shared: false,
+        }
+    }
+
+    /// Accessor for a render thread processing nodes in parallel with other threads
+    ///
+    /// # Safety
+    ///
+    /// None of the `nodes` may be mutably borrowed while the accessor exists, except for the
+    /// nodes being processed, whose params are not accessed.
+    #[cfg(feature = "parallel-rendering")]
+    pub(crate) unsafe fn from_shared(nodes: &'a NodeCollection) -> Self {
+        Self {
+            nodes,
+            shared: true,
+        }
     }
 
     /// Get the computed values for the given [`crate::param::AudioParam`]
@@ -133,9 +195,38 @@ impl<'a> AudioParamValues<'a> {
     /// For k-rate params or if the (a-rate) parameter is constant for this block, it will provide
     /// a slice of length 1. In other cases, i.e. a-rate param with scheduled automations it will
     /// provide a slice of length equal to the render quantum size (default: 128)
+    ///
+    /// The a-rate parameters of an [`AudioWorkletNode`](crate::node::AudioWorkletNode) always
+    /// provide a slice of length equal to the render quantum size.
     #[allow(clippy::missing_panics_doc)]
     pub fn get(&self, index: &AudioParamId) -> impl Deref<Target = [f32]> + '_ {
-        DerefAudioRenderQuantumChannel(self.nodes.get(&index.into()).unwrap().borrow())
+        let node = self.nodes.get(&index.into()).unwrap();
+        if self.shared {
+            // SAFETY: the node is not mutably borrowed, see `from_shared`. The borrow flag is
+            // only read, so the threads do not race on it.
+            let node = unsafe { node.try_borrow_unguarded() }.unwrap();
+            DerefAudioRenderQuantumChannel::Shared(node)
+        } else {
+            DerefAudioRenderQuantumChannel::Borrowed(node.borrow())
+        }
+    }
+
+    /// Whether the values of the given [`crate::param::AudioParam`] are constant during the
+    /// current render quantum
+    ///
+    /// This also holds for the full length slices of the a-rate parameters of an
+    /// [`AudioWorkletNode`](crate::node::AudioWorkletNode), so processors can skip their
+    /// per-sample parameter handling.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn is_constant(&self, index: &AudioParamId) -> bool {
+        let node = self.nodes.get(&index.into()).unwrap();
+        if self.shared {
+            // SAFETY: see `get`
+            let node = unsafe { node.try_borrow_unguarded() }.unwrap();
+            node.get_buffer().is_constant()
+        } else {
+            node.borrow().get_buffer().is_constant()
+        }
     }
 
     pub(crate) fn listener_params(&self) -> [impl Deref<Target = [f32]> + '_; 9] {
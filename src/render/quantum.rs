@@ -1,51 +1,85 @@
 //! Optimized audio signal data structures, used in `AudioProcessors`
 use arrayvec::ArrayVec;
 use std::cell::RefCell;
-use std::rc::Rc;
+#[cfg(not(feature = "parallel-rendering"))]
+use std::rc::Rc as Shared;
+#[cfg(feature = "parallel-rendering")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "parallel-rendering")]
+use std::sync::Arc as Shared;
 
 use crate::node::{ChannelConfig, ChannelCountMode, ChannelInterpretation};
 
+use super::simd;
+
 use crate::assert_valid_number_of_channels;
 use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
 
 // object pool for `AudioRenderQuantumChannel`s, only allocate if the pool is empty
+//
+// The buffers are only shared between threads by the parallel rendering of the
+// OfflineAudioContext, which bypasses the pool. They are reference counted without atomics unless
+// the `parallel-rendering` feature is enabled.
 pub(crate) struct Alloc {
-    inner: Rc<AllocInner>,
+    inner: Shared<AllocInner>,
 }
 
 #[derive(Debug)]
 struct AllocInner {
-    pool: RefCell<Vec<Rc<[f32; RENDER_QUANTUM_SIZE]>>>,
-    zeroes: Rc<[f32; RENDER_QUANTUM_SIZE]>,
+    /// Only accessed by the thread owning the graph, while the buffers are not shared
+    pool: RefCell<Vec<Shared<[f32; RENDER_QUANTUM_SIZE]>>>,
+    /// Whether the buffers are used by several threads
+    #[cfg(feature = "parallel-rendering")]
+    shared: AtomicBool,
+    zeroes: Shared<[f32; RENDER_QUANTUM_SIZE]>,
 }
 
+// SAFETY:
+// The pool is only accessed when the buffers are not shared, i.e. by the single thread rendering
+// the graph. The sharing is only switched by that thread, outside of the parallel rendering.
+#[cfg(feature = "parallel-rendering")]
+unsafe impl Sync for AllocInner {}
+
 impl Alloc {
     pub fn with_capacity(n: usize) -> Self {
-        let pool: Vec<_> = (0..n).map(|_| Rc::new([0.; RENDER_QUANTUM_SIZE])).collect();
-        let zeroes = Rc::new([0.; RENDER_QUANTUM_SIZE]);
+        let pool: Vec<_> = (0..n)
+            .map(|_| Shared::new([0.; RENDER_QUANTUM_SIZE]))
+            .collect();
+        let zeroes = Shared::new([0.; RENDER_QUANTUM_SIZE]);
 
         let inner = AllocInner {
             pool: RefCell::new(pool),
+            #[cfg(feature = "parallel-rendering")]
+            shared: AtomicBool::new(false),
             zeroes,
         };
 
         Self {
-            inner: Rc::new(inner),
+            inner: Shared::new(inner),
         }
     }
 
+    /// Share the buffers with the threads of a parallel rendering, or stop sharing them
+    ///
+    /// The buffers are allocated and freed without the pool while shared, this must only be
+    /// called when no other thread is rendering.
+    #[cfg(feature = "parallel-rendering")]
+    pub fn set_shared(&self, shared: bool) {
+        self.inner.shared.store(shared, Ordering::Relaxed);
+    }
+
     #[cfg(test)]
     pub fn allocate(&self) -> AudioRenderQuantumChannel {
         AudioRenderQuantumChannel {
             data: self.inner.allocate(),
-            alloc: Rc::clone(&self.inner),
+            alloc: Shared::clone(&self.inner),
         }
     }
 
     pub fn silence(&self) -> AudioRenderQuantumChannel {
         AudioRenderQuantumChannel {
-            data: Rc::clone(&self.inner.zeroes),
-            alloc: Rc::clone(&self.inner),
+            data: Shared::clone(&self.inner.zeroes),
+            alloc: Shared::clone(&self.inner),
         }
     }
 
@@ -56,20 +90,33 @@ impl Alloc {
 }
 
 impl AllocInner {
-    fn allocate(&self) -> Rc<[f32; RENDER_QUANTUM_SIZE]> {
-        if let Some(rc) = self.pool.borrow_mut().pop() {
-            // re-use from pool
-            rc
+    #[cfg(feature = "parallel-rendering")]
+    fn is_shared(&self) -> bool {
+        self.shared.load(Ordering::Relaxed)
+    }
+
+    #[cfg(not(feature = "parallel-rendering"))]
+    fn is_shared(&self) -> bool {
+        false
+    }
+
+    fn allocate(&self) -> Shared<[f32; RENDER_QUANTUM_SIZE]> {
+        let reused = if self.is_shared() {
+            None
         } else {
-            // allocate
-            Rc::new([0.; RENDER_QUANTUM_SIZE])
-        }
+            self.pool.borrow_mut().pop() // infallible when single threaded
+        };
+        // re-use from pool, or allocate
+        reused.unwrap_or_else(|| Shared::new([0.; RENDER_QUANTUM_SIZE]))
     }
 
-    fn push(&self, data: Rc<[f32; RENDER_QUANTUM_SIZE]>) {
-        self.pool
-            .borrow_mut() // infallible when single threaded
-            .push(data);
+    fn push(&self, data: Shared<[f32; RENDER_QUANTUM_SIZE]>) {
+        // deallocate while shared
+        if !self.is_shared() {
+            self.pool
+                .borrow_mut() // infallible when single threaded
+                .push(data);
+        }
     }
 }
 
@@ -96,26 +143,26 @@ impl AllocInner {
 /// ```
 #[derive(Clone, Debug)]
 pub struct AudioRenderQuantumChannel {
-    data: Rc<[f32; RENDER_QUANTUM_SIZE]>,
-    alloc: Rc<AllocInner>,
+    data: Shared<[f32; RENDER_QUANTUM_SIZE]>,
+    alloc: Shared<AllocInner>,
 }
 
 impl AudioRenderQuantumChannel {
     fn make_mut(&mut self) -> &mut [f32; RENDER_QUANTUM_SIZE] {
-        if Rc::strong_count(&self.data) != 1 {
+        if Shared::strong_count(&self.data) != 1 {
             let mut new = self.alloc.allocate();
-            Rc::make_mut(&mut new).copy_from_slice(self.data.deref());
+            Shared::make_mut(&mut new).copy_from_slice(self.data.deref());
             self.data = new;
         }
 
-        Rc::make_mut(&mut self.data)
+        Shared::make_mut(&mut self.data)
     }
 
     /// `O(1)` check if this buffer is equal to the 'silence buffer'
     ///
     /// If this function returns false, it is still possible for all samples to be zero.
     pub(crate) fn is_silent(&self) -> bool {
-        Rc::ptr_eq(&self.data, &self.alloc.zeroes)
+        Shared::ptr_eq(&self.data, &self.alloc.zeroes)
     }
 
     /// Sum two channels
@@ -123,14 +170,14 @@ impl AudioRenderQuantumChannel {
         if self.is_silent() {
             *self = other.clone();
         } else if !other.is_silent() {
-            self.iter_mut().zip(other.iter()).for_each(|(a, b)| *a += b)
+            simd::add(self.make_mut(), other.data.deref());
         }
     }
 
     pub(crate) fn silence(&self) -> Self {
         Self {
             data: self.alloc.zeroes.clone(),
-            alloc: Rc::clone(&self.alloc),
+            alloc: Shared::clone(&self.alloc),
         }
     }
 }
@@ -157,11 +204,17 @@ impl AsRef<[f32]> for AudioRenderQuantumChannel {
     }
 }
 
+impl AsMut<[f32]> for AudioRenderQuantumChannel {
+    fn as_mut(&mut self) -> &mut [f32] {
+        self.make_mut()
+    }
+}
+
 impl std::ops::Drop for AudioRenderQuantumChannel {
     fn drop(&mut self) {
-        if Rc::strong_count(&self.data) == 1 {
-            let rc = std::mem::replace(&mut self.data, self.alloc.zeroes.clone());
-            self.alloc.push(rc);
+        if Shared::strong_count(&self.data) == 1 {
+            let data = std::mem::replace(&mut self.data, self.alloc.zeroes.clone());
+            self.alloc.push(data);
         }
     }
 }
@@ -198,6 +251,9 @@ pub struct AudioRenderQuantum {
     // this field is only used by AudioParam so that when we know the param is
     // constant for a render_quantum it return a slice of length 1 instead of 128
     single_valued: bool,
+    // also only used by AudioParam, set when the values are constant for a render quantum, even
+    // when a full render quantum of values is provided
+    constant: bool,
 }
 
 impl AudioRenderQuantum {
@@ -209,6 +265,7 @@ impl AudioRenderQuantum {
         Self {
             channels,
             single_valued: false,
+            constant: false,
         }
     }
 
@@ -220,6 +277,14 @@ impl AudioRenderQuantum {
         self.single_valued = value;
     }
 
+    pub(crate) fn is_constant(&self) -> bool {
+        self.constant
+    }
+
+    pub(crate) fn set_constant(&mut self, value: bool) {
+        self.constant = value;
+    }
+
     /// Number of channels in this AudioRenderQuantum
     pub fn number_of_channels(&self) -> usize {
         self.channels.len()
@@ -311,13 +376,7 @@ impl AudioRenderQuantum {
         // cf. https://www.w3.org/TR/webaudio/#channel-up-mixing-and-down-mixing
         // handle discrete interpretation
         if interpretation == ChannelInterpretation::Discrete {
-            // upmix by filling with silence
-            for _ in self.number_of_channels()..computed_number_of_channels {
-                self.channels.push(silence.clone());
-            }
-
-            // downmix by truncating
-            self.channels.truncate(computed_number_of_channels);
+            self.mix_discrete(computed_number_of_channels);
         } else if interpretation == ChannelInterpretation::Speakers {
             match (self.number_of_channels(), computed_number_of_channels) {
                 // ------------------------------------------
@@ -444,7 +503,6 @@ impl AudioRenderQuantum {
                     self.channels.push(silence);
                 }
                 // 6 -> 8 : up-mix from 5.1 to 8
-                // 4 -> 8 : up-mix from quad to 8
                 //   output.FL = input.L;
                 //   output.FR = input.R;
                 //   output.FC = input.C;
@@ -466,10 +524,9 @@ impl AudioRenderQuantum {
                 (2, 1) => {
                     let right = self.channels[1].clone();
 
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(right.iter())
-                        .for_each(|(l, r)| *l = 0.5 * (*l + *r));
+                    let left = &mut self.channels[0];
+                    simd::add(left, &right);
+                    simd::scale(left, 0.5);
 
                     self.channels.truncate(1);
                 }
@@ -480,12 +537,11 @@ impl AudioRenderQuantum {
                     let s_left = self.channels[2].clone();
                     let s_right = self.channels[3].clone();
 
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(right.iter())
-                        .zip(s_left.iter())
-                        .zip(s_right.iter())
-                        .for_each(|(((l, r), sl), sr)| *l = 0.25 * (*l + *r + *sl + *sr));
+                    let left = &mut self.channels[0];
+                    simd::add(left, &right);
+                    simd::add(left, &s_left);
+                    simd::add(left, &s_right);
+                    simd::scale(left, 0.25);
 
                     self.channels.truncate(1);
                 }
@@ -498,15 +554,12 @@ impl AudioRenderQuantum {
                     let s_right = self.channels[5].clone();
                     let sqrt05 = (0.5_f32).sqrt();
 
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(right.iter())
-                        .zip(center.iter())
-                        .zip(s_left.iter())
-                        .zip(s_right.iter())
-                        .for_each(|((((l, r), c), sl), sr)| {
-                            *l = sqrt05.mul_add(*l + *r, 0.5f32.mul_add(*sl + *sr, *c))
-                        });
+                    let left = &mut self.channels[0];
+                    simd::add(left, &right);
+                    simd::scale(left, sqrt05);
+                    simd::add(left, &center);
+                    simd::add_scaled(left, &s_left, 0.5);
+                    simd::add_scaled(left, &s_right, 0.5);
 
                     self.channels.truncate(1);
                 }
@@ -517,15 +570,13 @@ impl AudioRenderQuantum {
                     let s_left = self.channels[2].clone();
                     let s_right = self.channels[3].clone();
 
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(s_left.iter())
-                        .for_each(|(l, sl)| *l = 0.5 * (*l + *sl));
+                    let left = &mut self.channels[0];
+                    simd::add(left, &s_left);
+                    simd::scale(left, 0.5);
 
-                    self.channels[1]
-                        .iter_mut()
-                        .zip(s_right.iter())
-                        .for_each(|(r, sr)| *r = 0.5 * (*r + *sr));
+                    let right = &mut self.channels[1];
+                    simd::add(right, &s_right);
+                    simd::scale(right, 0.5);
 
                     self.channels.truncate(2);
                 }
@@ -538,17 +589,8 @@ impl AudioRenderQuantum {
                     let s_right = self.channels[5].clone();
                     let sqrt05 = (0.5_f32).sqrt();
 
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(center.iter())
-                        .zip(s_left.iter())
-                        .for_each(|((l, c), sl)| *l += sqrt05 * (*c + *sl));
-
-                    self.channels[1]
-                        .iter_mut()
-                        .zip(center.iter())
-                        .zip(s_right.iter())
-                        .for_each(|((r, c), sr)| *r += sqrt05 * (*c + *sr));
+                    simd::add_sum_scaled(&mut self.channels[0], &center, &s_left, sqrt05);
+                    simd::add_sum_scaled(&mut self.channels[1], &center, &s_right, sqrt05);
 
                     self.channels.truncate(2)
                 }
@@ -562,31 +604,30 @@ impl AudioRenderQuantum {
                     let center = self.channels.swap_remove(2); // swap lf to index 2
                     let sqrt05 = (0.5_f32).sqrt();
 
-                    self.channels[0]
-                        .iter_mut()
-                        .zip(center.iter())
-                        .for_each(|(l, c)| *l += sqrt05 * c);
-
-                    self.channels[1]
-                        .iter_mut()
-                        .zip(center.iter())
-                        .for_each(|(r, c)| *r += sqrt05 * c);
+                    simd::add_scaled(&mut self.channels[0], &center, sqrt05);
+                    simd::add_scaled(&mut self.channels[1], &center, sqrt05);
                 }
 
-                _ => panic!(
-                    "{mixing} from {from} to {to} channels not supported",
-                    mixing = if self.number_of_channels() < computed_number_of_channels {
-                        "Up-mixing"
-                    } else {
-                        "Down-mixing"
-                    },
-                    from = self.number_of_channels(),
-                    to = computed_number_of_channels,
-                ),
+                // [spec] Other layouts fall back to the discrete interpretation, e.g. when
+                // routing multichannel or ambisonics streams
+                _ => self.mix_discrete(computed_number_of_channels),
             }
         }
     }
 
+    /// Up/Down-mix to the desired number of channels using the discrete interpretation
+    fn mix_discrete(&mut self, computed_number_of_channels: usize) {
+        let silence = self.channels[0].silence();
+
+        // upmix by filling with silence
+        for _ in self.number_of_channels()..computed_number_of_channels {
+            self.channels.push(silence.clone());
+        }
+
+        // downmix by truncating
+        self.channels.truncate(computed_number_of_channels);
+    }
+
     /// Convert this buffer to silence
     ///
     /// `O(1)` operation to convert this buffer to the 'silence buffer' which will enable some
@@ -603,15 +644,11 @@ impl AudioRenderQuantum {
         self.channels.truncate(1);
     }
 
-    /// Modify every channel in the same way
-    pub(crate) fn modify_channels<F: Fn(&mut AudioRenderQuantumChannel)>(&mut self, fun: F) {
-        // todo, optimize for Rcs that are equal
-        self.channels.iter_mut().for_each(fun)
-    }
-
     /// Sum two `AudioRenderQuantum`s
     ///
-    /// Both buffers will be mixed up front according to the supplied `channel_config`
+    /// Both buffers will be mixed up front according to the supplied `channel_config`. Silent
+    /// channels are skipped, and `other` is accumulated in place when it already has the desired
+    /// channel count.
     pub(crate) fn add(&mut self, other: &Self, channel_config: &ChannelConfig) {
         // gather initial channel counts
         let channels_self = self.number_of_channels();
@@ -629,8 +666,19 @@ impl AudioRenderQuantum {
             ChannelCountMode::ClampedMax => max_channels.min(count),
         };
 
-        // fast path if both buffers are upmixed mono signals
+        // A silent buffer only contributes its channel count
+        if other.is_silent() {
+            self.mix(new_channels, interpretation);
+            return;
+        }
+
+        // Fast path if both buffers are (upmixed) mono signals. This is only valid if mixing an
+        // upmixed mono signal yields the same result as mixing the mono signal itself, which
+        // does not hold for e.g. stereo to 5.1 (left/right) vs mono to 5.1 (center).
+        let mono_equivalent = |n: usize| n == 1 || (n == 2 && matches!(new_channels, 1 | 2 | 4));
         if interpretation == ChannelInterpretation::Speakers
+            && mono_equivalent(channels_self)
+            && mono_equivalent(channels_other)
             && self.all_channels_identical()
             && other.all_channels_identical()
         {
@@ -642,6 +690,15 @@ impl AudioRenderQuantum {
 
         self.mix(new_channels, interpretation);
 
+        // Accumulate directly when no mixing of the other buffer is required
+        if channels_other == new_channels {
+            self.channels
+                .iter_mut()
+                .zip(other.channels.iter())
+                .for_each(|(s, o)| s.add(o));
+            return;
+        }
+
         let mut other_mixed = other.clone();
         other_mixed.mix(new_channels, interpretation);
 
@@ -659,7 +716,7 @@ impl AudioRenderQuantum {
         let mut channels = self.channels.iter();
         let first = channels.next().unwrap();
         for c in channels {
-            if !Rc::ptr_eq(&first.data, &c.data) {
+            if !Shared::ptr_eq(&first.data, &c.data) {
                 return false;
             }
         }
@@ -866,6 +923,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_audiobuffer_mix_speakers_fallback() {
+        let alloc = Alloc::with_capacity(1);
+
+        let mut signal = alloc.silence();
+        signal.copy_from_slice(&[1.; RENDER_QUANTUM_SIZE]);
+
+        let mut buffer = AudioRenderQuantum::from(signal);
+
+        // no speaker layout for 32 channels, falls back to discrete
+        buffer.mix(MAX_CHANNELS, ChannelInterpretation::Speakers);
+        assert_eq!(buffer.number_of_channels(), MAX_CHANNELS);
+        assert_float_eq!(
+            &buffer.channel_data(0)[..],
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert!(buffer.channels()[1..].iter().all(|c| c.is_silent()));
+
+        buffer.mix(3, ChannelInterpretation::Speakers);
+        assert_eq!(buffer.number_of_channels(), 3);
+        assert_float_eq!(
+            &buffer.channel_data(0)[..],
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
     #[test]
     fn test_audiobuffer_upmix_speakers() {
         let alloc = Alloc::with_capacity(1);
@@ -1659,6 +1744,108 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_audiobuffer_add_upmixed_mono_speakers() {
+        let alloc = Alloc::with_capacity(1);
+
+        let mut signal = alloc.silence();
+        signal.copy_from_slice(&[1.; RENDER_QUANTUM_SIZE]);
+        let mut buffer = AudioRenderQuantum::from(signal);
+        buffer.mix(2, ChannelInterpretation::Speakers);
+
+        let mut signal2 = alloc.silence();
+        signal2.copy_from_slice(&[2.; RENDER_QUANTUM_SIZE]);
+        let mut buffer2 = AudioRenderQuantum::from(signal2);
+        buffer2.mix(2, ChannelInterpretation::Speakers);
+
+        let channel_config = crate::node::ChannelConfigOptions {
+            count: 6,
+            count_mode: ChannelCountMode::Explicit,
+            interpretation: ChannelInterpretation::Speakers,
+        }
+        .into();
+
+        buffer.add(&buffer2, &channel_config);
+
+        // stereo to 5.1 keeps the signal in the left and right channels
+        assert_eq!(buffer.number_of_channels(), 6);
+        let expected = [3., 3., 0., 0., 0., 0.];
+        for (i, value) in expected.iter().enumerate() {
+            assert_float_eq!(
+                &buffer.channel_data(i)[..],
+                &[*value; RENDER_QUANTUM_SIZE][..],
+                abs_all <= 0.
+            );
+        }
+    }
+
+    #[test]
+    fn test_audiobuffer_add_silence() {
+        let alloc = Alloc::with_capacity(1);
+
+        let mut signal = alloc.silence();
+        signal.copy_from_slice(&[1.; RENDER_QUANTUM_SIZE]);
+        let mut buffer = AudioRenderQuantum::from(signal);
+
+        // silent stereo input
+        let mut silence = AudioRenderQuantum::from(alloc.silence());
+        silence.mix(2, ChannelInterpretation::Speakers);
+
+        let channel_config = crate::node::ChannelConfigOptions {
+            count: 2,
+            count_mode: ChannelCountMode::Max,
+            interpretation: ChannelInterpretation::Discrete,
+        }
+        .into();
+
+        buffer.add(&silence, &channel_config);
+
+        // the silent input still determines the computed number of channels
+        assert_eq!(buffer.number_of_channels(), 2);
+        assert_float_eq!(
+            &buffer.channel_data(0)[..],
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert!(buffer.channel_data(1).is_silent());
+    }
+
+    #[test]
+    fn test_audiobuffer_add_many() {
+        let alloc = Alloc::with_capacity(1);
+
+        let mut signal = alloc.silence();
+        signal.copy_from_slice(&[1.; RENDER_QUANTUM_SIZE]);
+        let mut input = AudioRenderQuantum::from(signal);
+        input.mix(2, ChannelInterpretation::Discrete);
+
+        let channel_config = crate::node::ChannelConfigOptions {
+            count: 2,
+            count_mode: ChannelCountMode::Max,
+            interpretation: ChannelInterpretation::Discrete,
+        }
+        .into();
+
+        let mut buffer = AudioRenderQuantum::from(alloc.silence());
+        for _ in 0..16 {
+            buffer.add(&input, &channel_config);
+        }
+
+        // the input is not mutated by the summing
+        assert_float_eq!(
+            &input.channel_data(0)[..],
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert_eq!(buffer.number_of_channels(), 2);
+        assert_float_eq!(
+            &buffer.channel_data(0)[..],
+            &[16.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert!(buffer.channel_data(1).is_silent());
+    }
+
     #[test]
     fn test_is_silent_quantum() {
         let alloc = Alloc::with_capacity(1);
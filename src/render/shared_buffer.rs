@@ -0,0 +1,188 @@
+//! Triple buffer to share state between the control and render thread
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Flag of the middle slot index, set when it holds a value not yet seen by the reader
+const DIRTY: usize = 0b100;
+const INDEX_MASK: usize = 0b011;
+
+struct Slots<T> {
+    values: [UnsafeCell<T>; 3],
+    /// Index of the slot in between the writer and the reader, with the `DIRTY` flag
+    middle: AtomicUsize,
+}
+
+// The writer and reader only ever access the slot they own, ownership of the slots is
+// exchanged through the atomic swap of the middle index.
+unsafe impl<T: Send> Send for Slots<T> {}
+unsafe impl<T: Send> Sync for Slots<T> {}
+
+/// Control side of a state shared with an [`AudioProcessor`](super::AudioProcessor)
+///
+/// This is a triple buffer: the control thread writes a new value at any time and the render
+/// thread picks up the most recent one with [`SharedRenderBufferReader::read`], without locking
+/// or allocating. It is meant for state that is too large or changes too often to be posted as
+/// messages, e.g. wavetables or blocks of parameters.
+///
+/// The previous value is dropped on the control thread when the slot it occupied is reused, so
+/// the render thread never deallocates.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::render::{
+///     AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope, SharedRenderBuffer,
+///     SharedRenderBufferReader,
+/// };
+///
+/// struct WavetableProcessor {
+///     wavetable: SharedRenderBufferReader<Vec<f32>>,
+///     phase: usize,
+/// }
+///
+/// impl AudioProcessor for WavetableProcessor {
+///     fn process(
+///         &mut self,
+///         _inputs: &[AudioRenderQuantum],
+///         outputs: &mut [AudioRenderQuantum],
+///         _params: AudioParamValues,
+///         _scope: &RenderScope,
+///     ) -> bool {
+///         let wavetable = self.wavetable.read();
+///         let mut phase = self.phase;
+///         outputs[0].channels_mut()[0].iter_mut().for_each(|s| {
+///             phase = (phase + 1) % wavetable.len();
+///             *s = wavetable[phase];
+///         });
+///         self.phase = phase;
+///         true
+///     }
+/// }
+///
+/// let (wavetable, reader) = SharedRenderBuffer::new(vec![0.; 1024]);
+/// let processor = WavetableProcessor { wavetable: reader, phase: 0 };
+///
+/// // e.g. when the user draws a new waveform
+/// let saw = (0..1024).map(|i| i as f32 / 512. - 1.).collect();
+/// wavetable.write(saw);
+/// ```
+pub struct SharedRenderBuffer<T> {
+    slots: Arc<Slots<T>>,
+    /// Index of the slot owned by the writer, the lock serializes concurrent writes
+    back: Mutex<usize>,
+}
+
+impl<T: Clone + Send> SharedRenderBuffer<T> {
+    /// Create a shared buffer holding the initial value, and the reader to move to the
+    /// render thread
+    pub fn new(initial: T) -> (Self, SharedRenderBufferReader<T>) {
+        let slots = Arc::new(Slots {
+            values: [
+                UnsafeCell::new(initial.clone()),
+                UnsafeCell::new(initial.clone()),
+                UnsafeCell::new(initial),
+            ],
+            middle: AtomicUsize::new(1),
+        });
+
+        let writer = Self {
+            slots: Arc::clone(&slots),
+            back: Mutex::new(2),
+        };
+        let reader = SharedRenderBufferReader { slots, front: 0 };
+
+        (writer, reader)
+    }
+}
+
+impl<T: Send> SharedRenderBuffer<T> {
+    /// Publish a new value, to be picked up by the next read of the render thread
+    ///
+    /// Values that were written but never read are discarded.
+    pub fn write(&self, value: T) {
+        // the slot index is always valid, a panic while dropping a previous value can be ignored
+        let mut back = self.back.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // the previous value of the slot is dropped here, on the control thread
+        // SAFETY: the back slot is owned by the writer, the lock guarantees a single writer
+        unsafe {
+            *self.slots.values[*back].get() = value;
+        }
+
+        let previous = self.slots.middle.swap(*back | DIRTY, Ordering::AcqRel);
+        *back = previous & INDEX_MASK;
+    }
+}
+
+/// Render side of a [`SharedRenderBuffer`]
+pub struct SharedRenderBufferReader<T> {
+    slots: Arc<Slots<T>>,
+    /// Index of the slot owned by the reader
+    front: usize,
+}
+
+impl<T: Send> SharedRenderBufferReader<T> {
+    /// The most recently written value
+    ///
+    /// This never blocks nor allocates, it is safe to call in each render quantum.
+    pub fn read(&mut self) -> &T {
+        if self.has_update() {
+            let previous = self.slots.middle.swap(self.front, Ordering::AcqRel);
+            self.front = previous & INDEX_MASK;
+        }
+
+        // SAFETY: the front slot is owned by the reader, which is borrowed mutably
+        unsafe { &*self.slots.values[self.front].get() }
+    }
+
+    /// Check whether a value was written since the last read
+    pub fn has_update(&self) -> bool {
+        self.slots.middle.load(Ordering::Acquire) & DIRTY != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_write() {
+        let (writer, mut reader) = SharedRenderBuffer::new(0);
+        assert!(!reader.has_update());
+        assert_eq!(*reader.read(), 0);
+
+        writer.write(1);
+        assert!(reader.has_update());
+        assert_eq!(*reader.read(), 1);
+        assert!(!reader.has_update());
+        assert_eq!(*reader.read(), 1);
+
+        // only the latest value is read
+        writer.write(2);
+        writer.write(3);
+        writer.write(4);
+        assert_eq!(*reader.read(), 4);
+        assert_eq!(*reader.read(), 4);
+    }
+
+    #[test]
+    fn test_concurrent() {
+        let (writer, mut reader) = SharedRenderBuffer::new(vec![0; 64]);
+
+        let handle = std::thread::spawn(move || {
+            (1..=1000).for_each(|i| writer.write(vec![i; 64]));
+        });
+
+        let mut last = 0;
+        while last < 1000 {
+            let value = reader.read();
+            // values are never torn nor going back in time
+            assert!(value.iter().all(|&v| v == value[0]));
+            assert!(value[0] >= last);
+            last = value[0];
+        }
+
+        handle.join().unwrap();
+    }
+}
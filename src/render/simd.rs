@@ -0,0 +1,397 @@
+//! Vectorized primitives for the hot loops of the render thread
+//!
+//! The loops are written in chunks of [`LANES`] samples so the compiler maps them onto the SIMD
+//! registers of the target. Each kernel is compiled several times: for the baseline target
+//! (SSE2 on x86_64), for AVX2 and FMA on x86_64 and for NEON on aarch64. The best set supported
+//! by the CPU is selected once, at startup, so prebuilt binaries get the fast paths without
+//! requiring `target-cpu=native` builds. The scalar remainder of a chunked loop handles slices
+//! of any length.
+//!
+//! Multiply-adds are only fused where the kernels ask for it with `mul_add`, which rounds the
+//! same with or without FMA instructions, so the results are bit-identical across all kernel
+//! sets.
+use std::convert::TryInto;
+
+use lazy_static::lazy_static;
+
+/// Number of samples processed per iteration of the vectorized loops
+const LANES: usize = 8;
+
+/// Instruction set a kernel set is compiled for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum InstructionSet {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+}
+
+impl InstructionSet {
+    /// All instruction sets supported by the current CPU, the preferred one first
+    fn supported() -> Vec<Self> {
+        let mut supported = vec![];
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            supported.push(Self::Avx2);
+        }
+        #[cfg(target_arch = "aarch64")]
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            supported.push(Self::Neon);
+        }
+        supported.push(Self::Scalar);
+        supported
+    }
+}
+
+/// Define the kernel set of an instruction set, in a module of the given name
+macro_rules! kernels {
+    ($module:ident, $($feature:literal)?) => {
+        mod $module {
+            use super::*;
+
+            $(#[target_feature(enable = $feature)])?
+            pub(super) unsafe fn add(dst: &mut [f32], src: &[f32]) {
+                zip_lanes(dst, src, |d, s| *d += s)
+            }
+
+            $(#[target_feature(enable = $feature)])?
+            pub(super) unsafe fn mul(dst: &mut [f32], src: &[f32]) {
+                zip_lanes(dst, src, |d, s| *d *= s)
+            }
+
+            $(#[target_feature(enable = $feature)])?
+            pub(super) unsafe fn scale(dst: &mut [f32], gain: f32) {
+                for_each_lane(dst, |d| *d *= gain)
+            }
+
+            $(#[target_feature(enable = $feature)])?
+            pub(super) unsafe fn add_scaled(dst: &mut [f32], src: &[f32], gain: f32) {
+                zip_lanes(dst, src, |d, s| *d += s * gain)
+            }
+
+            $(#[target_feature(enable = $feature)])?
+            pub(super) unsafe fn add_sum_scaled(
+                dst: &mut [f32],
+                a: &[f32],
+                b: &[f32],
+                gain: f32,
+            ) {
+                zip3_lanes(dst, a, b, |d, a, b| *d += gain * (a + b))
+            }
+
+            $(#[target_feature(enable = $feature)])?
+            pub(super) unsafe fn linear_ramp(
+                dst: &mut [f32],
+                time: f64,
+                dt: f64,
+                start_time: f64,
+                duration: f64,
+                start_value: f32,
+                diff: f32,
+            ) {
+                dst.iter_mut().enumerate().for_each(|(i, v)| {
+                    let phase = (i as f64 * dt + time - start_time) / duration;
+                    *v = diff.mul_add(phase as f32, start_value);
+                })
+            }
+
+            pub(super) const KERNELS: Kernels = Kernels {
+                add,
+                mul,
+                scale,
+                add_scaled,
+                add_sum_scaled,
+                linear_ramp,
+            };
+        }
+    };
+}
+
+kernels!(scalar,);
+#[cfg(target_arch = "x86_64")]
+kernels!(avx2, "avx2,fma");
+#[cfg(target_arch = "aarch64")]
+kernels!(neon, "neon");
+
+/// Table of the kernels compiled for one instruction set
+///
+/// The kernels are unsafe to call, as they may use instructions the CPU does not support.
+struct Kernels {
+    add: unsafe fn(&mut [f32], &[f32]),
+    mul: unsafe fn(&mut [f32], &[f32]),
+    scale: unsafe fn(&mut [f32], f32),
+    add_scaled: unsafe fn(&mut [f32], &[f32], f32),
+    add_sum_scaled: unsafe fn(&mut [f32], &[f32], &[f32], f32),
+    linear_ramp: unsafe fn(&mut [f32], f64, f64, f64, f64, f32, f32),
+}
+
+impl Kernels {
+    fn get(instruction_set: InstructionSet) -> &'static Self {
+        match instruction_set {
+            InstructionSet::Scalar => &scalar::KERNELS,
+            #[cfg(target_arch = "x86_64")]
+            InstructionSet::Avx2 => &avx2::KERNELS,
+            #[cfg(target_arch = "aarch64")]
+            InstructionSet::Neon => &neon::KERNELS,
+        }
+    }
+}
+
+lazy_static! {
+    /// The preferred kernels supported by the CPU
+    static ref KERNELS: &'static Kernels = {
+        let selected = InstructionSet::supported()[0];
+        log::debug!("Selected {:?} DSP kernels", selected);
+        Kernels::get(selected)
+    };
+}
+
+/// Detect the CPU features and select the kernels, so this does not happen while rendering
+pub(crate) fn init() {
+    lazy_static::initialize(&KERNELS);
+}
+
+/// Apply `f` to each sample of `dst`
+#[inline(always)]
+fn for_each_lane(dst: &mut [f32], f: impl Fn(&mut f32)) {
+    let mut chunks = dst.chunks_exact_mut(LANES);
+    (&mut chunks).for_each(|d| {
+        let d: &mut [f32; LANES] = d.try_into().unwrap();
+        d.iter_mut().for_each(&f);
+    });
+    chunks.into_remainder().iter_mut().for_each(f);
+}
+
+/// Apply `f` to each pair of samples of `dst` and `src`
+#[inline(always)]
+fn zip_lanes(dst: &mut [f32], src: &[f32], f: impl Fn(&mut f32, f32)) {
+    assert_eq!(dst.len(), src.len());
+
+    let mut dst_chunks = dst.chunks_exact_mut(LANES);
+    let mut src_chunks = src.chunks_exact(LANES);
+    (&mut dst_chunks).zip(&mut src_chunks).for_each(|(d, s)| {
+        let d: &mut [f32; LANES] = d.try_into().unwrap();
+        let s: &[f32; LANES] = s.try_into().unwrap();
+        d.iter_mut().zip(s).for_each(|(d, s)| f(d, *s));
+    });
+    dst_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(src_chunks.remainder())
+        .for_each(|(d, s)| f(d, *s));
+}
+
+/// Apply `f` to each triple of samples of `dst`, `a` and `b`
+#[inline(always)]
+fn zip3_lanes(dst: &mut [f32], a: &[f32], b: &[f32], f: impl Fn(&mut f32, f32, f32)) {
+    assert_eq!(dst.len(), a.len());
+    assert_eq!(dst.len(), b.len());
+
+    let mut dst_chunks = dst.chunks_exact_mut(LANES);
+    let mut a_chunks = a.chunks_exact(LANES);
+    let mut b_chunks = b.chunks_exact(LANES);
+    (&mut dst_chunks)
+        .zip(&mut a_chunks)
+        .zip(&mut b_chunks)
+        .for_each(|((d, a), b)| {
+            let d: &mut [f32; LANES] = d.try_into().unwrap();
+            let a: &[f32; LANES] = a.try_into().unwrap();
+            let b: &[f32; LANES] = b.try_into().unwrap();
+            d.iter_mut()
+                .zip(a)
+                .zip(b)
+                .for_each(|((d, a), b)| f(d, *a, *b));
+        });
+    dst_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(a_chunks.remainder())
+        .zip(b_chunks.remainder())
+        .for_each(|((d, a), b)| f(d, *a, *b));
+}
+
+/// Sum `src` into `dst`
+///
+/// # Panics
+///
+/// Panics if the slices differ in length.
+#[inline]
+pub(crate) fn add(dst: &mut [f32], src: &[f32]) {
+    // SAFETY: the selected kernels are supported by the CPU
+    unsafe { (KERNELS.add)(dst, src) }
+}
+
+/// Multiply `dst` with `src` sample by sample, e.g. to apply a-rate gain
+///
+/// # Panics
+///
+/// Panics if the slices differ in length.
+#[inline]
+pub(crate) fn mul(dst: &mut [f32], src: &[f32]) {
+    // SAFETY: the selected kernels are supported by the CPU
+    unsafe { (KERNELS.mul)(dst, src) }
+}
+
+/// Multiply all samples of `dst` with `gain`
+#[inline]
+pub(crate) fn scale(dst: &mut [f32], gain: f32) {
+    // SAFETY: the selected kernels are supported by the CPU
+    unsafe { (KERNELS.scale)(dst, gain) }
+}
+
+/// Sum `src` multiplied with `gain` into `dst`
+///
+/// # Panics
+///
+/// Panics if the slices differ in length.
+#[inline]
+pub(crate) fn add_scaled(dst: &mut [f32], src: &[f32], gain: f32) {
+    // SAFETY: the selected kernels are supported by the CPU
+    unsafe { (KERNELS.add_scaled)(dst, src, gain) }
+}
+
+/// Sum the sum of `a` and `b` multiplied with `gain` into `dst`
+///
+/// # Panics
+///
+/// Panics if the slices differ in length.
+#[inline]
+pub(crate) fn add_sum_scaled(dst: &mut [f32], a: &[f32], b: &[f32], gain: f32) {
+    // SAFETY: the selected kernels are supported by the CPU
+    unsafe { (KERNELS.add_sum_scaled)(dst, a, b, gain) }
+}
+
+/// Fill `dst` with the values of a linear ramp, starting at `time` and advancing by `dt`
+///
+/// The ramp goes from `start_value` at `start_time` to `start_value + diff` after `duration`.
+#[inline]
+pub(crate) fn linear_ramp(
+    dst: &mut [f32],
+    time: f64,
+    dt: f64,
+    start_time: f64,
+    duration: f64,
+    start_value: f32,
+    diff: f32,
+) {
+    // SAFETY: the selected kernels are supported by the CPU
+    unsafe { (KERNELS.linear_ramp)(dst, time, dt, start_time, duration, start_value, diff) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(len: usize, seed: f32) -> Vec<f32> {
+        (0..len).map(|i| (i as f32 * seed).sin()).collect()
+    }
+
+    // lengths with and without a scalar remainder
+    const LENGTHS: [usize; 5] = [0, 3, 8, 128, 131];
+
+    #[test]
+    fn test_add() {
+        for len in LENGTHS {
+            let mut dst = signal(len, 0.1);
+            let src = signal(len, 0.3);
+            let expected: Vec<f32> = dst.iter().zip(&src).map(|(d, s)| d + s).collect();
+            add(&mut dst, &src);
+            assert_eq!(dst, expected);
+        }
+    }
+
+    #[test]
+    fn test_mul() {
+        for len in LENGTHS {
+            let mut dst = signal(len, 0.1);
+            let src = signal(len, 0.3);
+            let expected: Vec<f32> = dst.iter().zip(&src).map(|(d, s)| d * s).collect();
+            mul(&mut dst, &src);
+            assert_eq!(dst, expected);
+        }
+    }
+
+    #[test]
+    fn test_scale() {
+        for len in LENGTHS {
+            let mut dst = signal(len, 0.1);
+            let expected: Vec<f32> = dst.iter().map(|d| d * 0.7).collect();
+            scale(&mut dst, 0.7);
+            assert_eq!(dst, expected);
+        }
+    }
+
+    #[test]
+    fn test_add_scaled() {
+        for len in LENGTHS {
+            let mut dst = signal(len, 0.1);
+            let src = signal(len, 0.3);
+            let expected: Vec<f32> = dst.iter().zip(&src).map(|(d, s)| d + s * 0.7).collect();
+            add_scaled(&mut dst, &src, 0.7);
+            assert_eq!(dst, expected);
+        }
+    }
+
+    #[test]
+    fn test_add_sum_scaled() {
+        for len in LENGTHS {
+            let mut dst = signal(len, 0.1);
+            let a = signal(len, 0.3);
+            let b = signal(len, 0.5);
+            let expected: Vec<f32> = dst
+                .iter()
+                .zip(&a)
+                .zip(&b)
+                .map(|((d, a), b)| d + 0.7 * (a + b))
+                .collect();
+            add_sum_scaled(&mut dst, &a, &b, 0.7);
+            assert_eq!(dst, expected);
+        }
+    }
+
+    #[test]
+    fn test_linear_ramp() {
+        let mut dst = [0.; 10];
+        linear_ramp(&mut dst, 2., 1., 2., 3., 5., 3.);
+        assert_eq!(dst, [5., 6., 7., 8., 9., 10., 11., 12., 13., 14.]);
+    }
+
+    #[test]
+    fn test_instruction_sets() {
+        let supported = InstructionSet::supported();
+        assert_eq!(supported.last(), Some(&InstructionSet::Scalar));
+        assert!(std::ptr::eq(*KERNELS, Kernels::get(supported[0])));
+
+        let reference = Kernels::get(InstructionSet::Scalar);
+        for instruction_set in supported {
+            let kernels = Kernels::get(instruction_set);
+
+            for len in LENGTHS {
+                let a = signal(len, 0.3);
+                let b = signal(len, 0.5);
+                let run = |kernels: &Kernels| {
+                    let mut results = vec![signal(len, 0.1); 6];
+                    // SAFETY: the instruction set is supported by the CPU
+                    unsafe {
+                        (kernels.add)(&mut results[0], &a);
+                        (kernels.mul)(&mut results[1], &a);
+                        (kernels.scale)(&mut results[2], 0.7);
+                        (kernels.add_scaled)(&mut results[3], &a, 0.7);
+                        (kernels.add_sum_scaled)(&mut results[4], &a, &b, 0.7);
+                        (kernels.linear_ramp)(&mut results[5], 0.1, 0.01, 0., 2.5, 1., -0.5);
+                    }
+                    results
+                };
+
+                assert_eq!(run(kernels), run(reference), "{:?}", instruction_set);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_length_mismatch() {
+        add(&mut [0.; 4], &[0.; 3]);
+    }
+}
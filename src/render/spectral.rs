@@ -0,0 +1,382 @@
+//! Frequency domain processing of the inputs of custom nodes
+use std::any::Any;
+use std::sync::Arc;
+
+use crate::analysis::{assert_valid_fft_size, generate_hann};
+use crate::fft::{self, ComplexToRealFft, RealToComplexFft};
+use crate::RENDER_QUANTUM_SIZE;
+
+use super::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+pub use crate::fft::Complex;
+
+/// Processor of the frequency bins of the input, run by a [`SpectralAdapter`]
+///
+/// The adapter takes care of the short-time Fourier transform: the input is cut in
+/// overlapping frames, windowed and transformed, then the modified bins are transformed back
+/// and overlap-added to the output.
+pub trait SpectralProcessor: Send {
+    /// Modify the `fft_size / 2 + 1` frequency bins of a frame of the given channel
+    ///
+    /// Called once per hop for every channel, on the render thread. The bins are not
+    /// normalized, the imaginary part of the first and the last bin is ignored.
+    fn process_frame(
+        &mut self,
+        bins: &mut [Complex<f32>],
+        channel_number: usize,
+        scope: &RenderScope,
+    );
+
+    /// Handle a message posted to the [`MessagePort`](crate::MessagePort) of the node, see
+    /// [`AudioProcessor::onmessage`]
+    ///
+    /// The default implementation ignores the message.
+    fn onmessage(&mut self, msg: Box<dyn Any + Send>) {
+        log::warn!("SpectralProcessor has no message handler, message dropped");
+        drop(msg);
+    }
+}
+
+/// Options for constructing a [`SpectralAdapter`]
+#[derive(Clone, Debug)]
+pub struct SpectralOptions {
+    /// Size of the FFT, must be a power of two in the range [32, 32768]
+    pub fft_size: usize,
+    /// Number of sample frames between the start of two successive frames, must be a power of
+    /// two and at most a quarter of the FFT size
+    pub hop_size: usize,
+}
+
+impl Default for SpectralOptions {
+    fn default() -> Self {
+        Self {
+            fft_size: 2048,
+            hop_size: 512,
+        }
+    }
+}
+
+/// Analysis and synthesis state of a channel
+struct SpectralChannel {
+    /// the last `fft_size` input samples
+    input: Vec<f32>,
+    /// overlap-added output, the first `hop_size` samples are complete
+    accumulator: Vec<f32>,
+    /// completed output samples, played while the next hop is collected
+    output: Vec<f32>,
+}
+
+/// [`AudioProcessor`] running a [`SpectralProcessor`] on the first input, e.g. as the
+/// processor of an [`AudioWorkletNode`](crate::node::AudioWorkletNode)
+///
+/// The input is analyzed with a Hann window and resynthesized with the same window, so the
+/// output equals the input, delayed by `fft_size` sample frames, when the bins are left
+/// untouched. The node has a tail time of a full frame, after which the processor is not
+/// called for silent inputs.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::node::{
+///     AudioWorkletNode, AudioWorkletNodeOptions, AudioWorkletProcessorOptions,
+/// };
+/// use web_audio_api::render::{
+///     AudioProcessor, Complex, RenderScope, SpectralAdapter, SpectralOptions,
+///     SpectralProcessor,
+/// };
+///
+/// /// Robotization: keep the magnitudes, drop the phases
+/// struct Robotize;
+///
+/// impl SpectralProcessor for Robotize {
+///     fn process_frame(&mut self, bins: &mut [Complex<f32>], _: usize, _: &RenderScope) {
+///         bins.iter_mut().for_each(|bin| *bin = Complex::new(bin.norm(), 0.));
+///     }
+/// }
+///
+/// let context = AudioContext::default();
+/// context.register_processor("robotize", |_options: AudioWorkletProcessorOptions| {
+///     let options = SpectralOptions {
+///         fft_size: 1024,
+///         hop_size: 128,
+///     };
+///     Box::new(SpectralAdapter::new(Robotize, options)) as Box<dyn AudioProcessor>
+/// });
+///
+/// let robot = AudioWorkletNode::new(&context, "robotize", AudioWorkletNodeOptions::default());
+/// robot.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&robot);
+/// osc.start();
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// ```
+pub struct SpectralAdapter<P> {
+    processor: P,
+    fft_size: usize,
+    hop_size: usize,
+    window: Vec<f32>,
+    /// gain compensating the windows and the unnormalized inverse FFT
+    scale: f32,
+    r2c: Arc<dyn RealToComplexFft>,
+    c2r: Arc<dyn ComplexToRealFft>,
+    fft_input: Vec<f32>,
+    fft_scratch: Vec<Complex<f32>>,
+    bins: Vec<Complex<f32>>,
+    channels: Vec<SpectralChannel>,
+    /// position in the current hop
+    position: usize,
+    /// remaining sample frames to render after the input has become silent
+    tail: usize,
+}
+
+impl<P: SpectralProcessor> SpectralAdapter<P> {
+    /// Run the given processor with the given frame and hop size
+    ///
+    /// # Panics
+    ///
+    /// This function panics if:
+    /// - the FFT size is not a power of two in the range [32, 32768]
+    /// - the hop size is not a power of two, or larger than a quarter of the FFT size
+    pub fn new(processor: P, options: SpectralOptions) -> Self {
+        let SpectralOptions { fft_size, hop_size } = options;
+        assert_valid_fft_size(fft_size);
+        assert!(
+            hop_size.is_power_of_two() && hop_size <= fft_size / 4,
+            "IndexSizeError - Invalid hop size: {:?} is not a power of two in the range [1, {:?}]",
+            hop_size,
+            fft_size / 4
+        );
+
+        let window: Vec<f32> = generate_hann(fft_size).collect();
+        // the squared windows of the overlapping frames add up to a constant
+        let overlap: f32 = window.iter().map(|w| w * w).sum::<f32>() / hop_size as f32;
+        let scale = 1. / (overlap * fft_size as f32);
+
+        let r2c = fft::plan_forward(fft_size);
+        let c2r = fft::plan_inverse(fft_size);
+        let fft_input = vec![0.; fft_size];
+        let scratch_len = r2c.scratch_len().max(c2r.scratch_len());
+        let fft_scratch = vec![Complex::default(); scratch_len];
+        let bins = vec![Complex::default(); fft_size / 2 + 1];
+
+        Self {
+            processor,
+            fft_size,
+            hop_size,
+            window,
+            scale,
+            r2c,
+            c2r,
+            fft_input,
+            fft_scratch,
+            bins,
+            channels: Vec::new(),
+            position: 0,
+            tail: 0,
+        }
+    }
+
+    /// The wrapped processor
+    pub fn processor(&self) -> &P {
+        &self.processor
+    }
+
+    /// The wrapped processor, mutably
+    pub fn processor_mut(&mut self) -> &mut P {
+        &mut self.processor
+    }
+
+    /// Transform the collected frame of each channel, and overlap-add the result
+    fn process_hop(&mut self, scope: &RenderScope) {
+        let fft_size = self.fft_size;
+        let hop_size = self.hop_size;
+        let scale = self.scale;
+
+        for (channel_number, channel) in self.channels.iter_mut().enumerate() {
+            self.fft_input
+                .iter_mut()
+                .zip(channel.input.iter().zip(self.window.iter()))
+                .for_each(|(o, (i, w))| *o = i * w);
+            self.r2c
+                .process(&mut self.fft_input, &mut self.bins, &mut self.fft_scratch);
+
+            self.processor
+                .process_frame(&mut self.bins, channel_number, scope);
+
+            self.c2r
+                .process(&mut self.bins, &mut self.fft_input, &mut self.fft_scratch);
+
+            channel
+                .accumulator
+                .iter_mut()
+                .zip(self.fft_input.iter().zip(self.window.iter()))
+                .for_each(|(a, (i, w))| *a += i * w * scale);
+
+            // hand over the completed samples and advance by a hop
+            channel
+                .output
+                .copy_from_slice(&channel.accumulator[..hop_size]);
+            channel.accumulator.copy_within(hop_size.., 0);
+            channel.accumulator[fft_size - hop_size..].fill(0.);
+            channel.input.copy_within(hop_size.., 0);
+        }
+    }
+}
+
+impl<P: SpectralProcessor> AudioProcessor for SpectralAdapter<P> {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        if input.is_silent() {
+            if self.tail == 0 {
+                output.make_silent();
+                return false;
+            }
+            self.tail = self.tail.saturating_sub(RENDER_QUANTUM_SIZE);
+        } else {
+            self.tail = self.fft_size;
+        }
+
+        let number_of_channels = input.number_of_channels();
+        if self.channels.len() != number_of_channels {
+            let (fft_size, hop_size) = (self.fft_size, self.hop_size);
+            self.channels
+                .resize_with(number_of_channels, || SpectralChannel {
+                    input: vec![0.; fft_size],
+                    accumulator: vec![0.; fft_size],
+                    output: vec![0.; hop_size],
+                });
+        }
+        output.set_number_of_channels(number_of_channels);
+
+        // the current hop is collected at the end of the frame
+        let latency = self.fft_size - self.hop_size;
+        let mut offset = 0;
+        while offset < RENDER_QUANTUM_SIZE {
+            let count = (self.hop_size - self.position).min(RENDER_QUANTUM_SIZE - offset);
+
+            for (channel_number, channel) in self.channels.iter_mut().enumerate() {
+                let start = latency + self.position;
+                channel.input[start..start + count]
+                    .copy_from_slice(&input.channel_data(channel_number)[offset..offset + count]);
+                output.channel_data_mut(channel_number)[offset..offset + count]
+                    .copy_from_slice(&channel.output[self.position..self.position + count]);
+            }
+
+            offset += count;
+            self.position += count;
+            if self.position == self.hop_size {
+                self.position = 0;
+                self.process_hop(scope);
+            }
+        }
+
+        self.tail > 0
+    }
+
+    fn onmessage(&mut self, msg: Box<dyn Any + Send>) {
+        self.processor.onmessage(msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{
+        AudioNode, AudioScheduledSourceNode, AudioWorkletNode, AudioWorkletNodeOptions,
+        AudioWorkletProcessorOptions,
+    };
+
+    struct Identity;
+
+    impl SpectralProcessor for Identity {
+        fn process_frame(&mut self, _: &mut [Complex<f32>], _: usize, _: &RenderScope) {}
+    }
+
+    /// Zeroes the bins above the given one
+    struct LowPass(usize);
+
+    impl SpectralProcessor for LowPass {
+        fn process_frame(&mut self, bins: &mut [Complex<f32>], _: usize, _: &RenderScope) {
+            bins[self.0..].fill(Complex::default());
+        }
+    }
+
+    fn render<P, F>(processor: F, options: SpectralOptions, frequency: f32) -> Vec<f32>
+    where
+        P: SpectralProcessor + 'static,
+        F: Fn() -> P + Send + Sync + 'static,
+    {
+        let context = OfflineAudioContext::new(1, 8192, 44_100.);
+        context.register_processor("spectral", move |_: AudioWorkletProcessorOptions| {
+            Box::new(SpectralAdapter::new(processor(), options.clone())) as Box<dyn AudioProcessor>
+        });
+
+        let node = AudioWorkletNode::new(&context, "spectral", AudioWorkletNodeOptions::default());
+        node.connect(&context.destination());
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(frequency);
+        osc.connect(&node);
+        osc.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_identity() {
+        let options = SpectralOptions {
+            fft_size: 1024,
+            hop_size: 64,
+        };
+        let output = render(|| Identity, options, 441.);
+
+        // the output is delayed by a full frame
+        assert_float_eq!(output[..1024], [0.; 1024][..], abs_all <= 1e-6);
+        let expected: Vec<f32> = (0..8192 - 1024)
+            .map(|i| (2. * std::f32::consts::PI * 441. * i as f32 / 44_100.).sin())
+            .collect();
+        assert_float_eq!(output[1024..], expected[..], abs_all <= 1e-3);
+    }
+
+    #[test]
+    fn test_low_pass() {
+        // the tone is in bin 60 of 512 bins
+        let options = SpectralOptions {
+            fft_size: 1024,
+            hop_size: 256,
+        };
+        let frequency = 60. * 44_100. / 1024.;
+
+        let output = render(move || LowPass(80), options.clone(), frequency);
+        let peak = output[4096..].iter().fold(0., |m: f32, s| m.max(s.abs()));
+        assert_float_eq!(peak, 1., abs <= 1e-2);
+
+        let output = render(move || LowPass(40), options, frequency);
+        let peak = output[4096..].iter().fold(0., |m: f32, s| m.max(s.abs()));
+        assert!(peak < 1e-3, "peak {}", peak);
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexSizeError")]
+    fn test_invalid_hop_size() {
+        let options = SpectralOptions {
+            fft_size: 1024,
+            hop_size: 512,
+        };
+        let _ = SpectralAdapter::new(Identity, options);
+    }
+}
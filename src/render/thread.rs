@@ -2,23 +2,41 @@
 
 use std::cell::Cell;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::{Receiver, Sender, TrySendError};
 use dasp_sample::FromSample;
 
-use super::AudioRenderQuantum;
+use super::{simd, AudioRenderQuantum};
 use crate::buffer::{AudioBuffer, AudioBufferOptions};
-use crate::context::AudioNodeId;
+use crate::context::{AudioNodeId, SharedClock};
 use crate::events::EventDispatch;
 use crate::message::ControlMessage;
-use crate::node::ChannelInterpretation;
 use crate::render::RenderScope;
-use crate::{AudioRenderCapacityLoad, RENDER_QUANTUM_SIZE};
+use crate::resampling::StreamResampler;
+use crate::{AudioRenderCapacityLoad, OverloadEvent, RENDER_QUANTUM_SIZE};
 
 use super::graph::Graph;
 
+/// Timing and taps of the render callbacks, shared by the render thread with the control thread
+#[derive(Debug, Default)]
+pub(crate) struct RenderTiming {
+    /// Number of render callbacks that missed their deadline
+    pub underruns: AtomicU64,
+    /// Frame position and instant of the start of the last render callback
+    pub last_callback: Mutex<Option<(u64, Instant)>>,
+    /// Clock of another context the frame position is aligned with
+    pub clock: Mutex<Option<SharedClock>>,
+    /// Receivers of the rendered output mixed down to mono, the reference of the echo cancellation
+    pub echo_references: Mutex<Vec<Sender<Vec<f32>>>>,
+}
+
+/// Smoothing factor of the offset to the followed clock, per render callback
+const CLOCK_SMOOTHING: f64 = 0.01;
+/// Offset to the followed clock, in seconds, above which the position jumps right away
+const CLOCK_RESYNC_THRESHOLD: f64 = 0.1;
+
 /// Operations running off the system-level audio callback
 pub(crate) struct RenderThread {
     graph: Option<Graph>,
@@ -29,13 +47,21 @@ pub(crate) struct RenderThread {
     buffer_offset: Option<(usize, AudioRenderQuantum)>,
     load_value_sender: Option<Sender<AudioRenderCapacityLoad>>,
     event_sender: Option<Sender<EventDispatch>>,
+    timing: Arc<RenderTiming>,
+    /// Smoothed offset to the followed clock, in frames
+    clock_offset: Option<f64>,
+    /// Converter to the sample rate of the output device, if it differs from the context
+    output_resampler: Option<StreamResampler>,
+    /// Number of threads rendering the graph
+    #[cfg(feature = "parallel-rendering")]
+    render_threads: usize,
 }
 
 // SAFETY:
-// The RenderThread is not Send/Sync since it contains `AudioRenderQuantum`s (which use Rc), but
+// The RenderThread is not Send/Sync since it contains the nodes of the graph (in a RefCell), but
 // these are only accessed within the same thread (the render thread). Due to the cpal constraints
 // we can neither move the RenderThread object into the render thread, nor can we initialize the
-// Rc's in that thread.
+// graph in that thread.
 #[allow(clippy::non_send_fields_in_send_ty)]
 unsafe impl Send for Graph {}
 unsafe impl Sync for Graph {}
@@ -50,7 +76,10 @@ impl RenderThread {
         frames_played: Arc<AtomicU64>,
         load_value_sender: Option<Sender<AudioRenderCapacityLoad>>,
         event_sender: Option<Sender<EventDispatch>>,
+        timing: Arc<RenderTiming>,
     ) -> Self {
+        simd::init();
+
         Self {
             graph: None,
             sample_rate,
@@ -60,70 +89,144 @@ impl RenderThread {
             buffer_offset: None,
             load_value_sender,
             event_sender,
+            timing,
+            clock_offset: None,
+            output_resampler: None,
+            #[cfg(feature = "parallel-rendering")]
+            render_threads: 1,
         }
     }
 
+    /// Render the independent nodes of the graph on the given number of threads
+    #[cfg(feature = "parallel-rendering")]
+    pub fn set_render_threads(&mut self, number_of_threads: usize) {
+        self.render_threads = number_of_threads;
+        if let Some(graph) = self.graph.as_mut() {
+            graph.set_render_threads(number_of_threads);
+        }
+    }
+
+    /// Convert the rendered audio to the sample rate of the output device, when the device
+    /// does not support the sample rate of the context
+    ///
+    /// Only cpal needs this, cubeb converts the sample rate of its streams itself.
+    #[cfg(feature = "cpal")]
+    pub fn set_output_sample_rate(&mut self, sample_rate: f32) {
+        self.output_resampler = if sample_rate == self.sample_rate {
+            None
+        } else {
+            log::info!(
+                "Converting the rendered audio from {} Hz to {} Hz",
+                self.sample_rate,
+                sample_rate
+            );
+            Some(StreamResampler::new(
+                self.number_of_channels,
+                self.sample_rate,
+                sample_rate,
+            ))
+        };
+    }
+
     fn handle_control_messages(&mut self) {
-        let receiver = match &self.receiver {
+        let receiver = match self.receiver.take() {
             None => return,
             Some(receiver) => receiver,
         };
 
         for msg in receiver.try_iter() {
-            use ControlMessage::*;
+            if !self.handle_control_message(msg) {
+                return; // the receiver is dropped after a shutdown
+            }
+        }
 
-            match msg {
-                RegisterNode {
-                    id: node_id,
+        self.receiver = Some(receiver);
+    }
+
+    /// Apply a control message to the audio graph, returns `false` after a shutdown
+    fn handle_control_message(&mut self, msg: ControlMessage) -> bool {
+        use ControlMessage::*;
+
+        match msg {
+            RegisterNode {
+                id: node_id,
+                node,
+                inputs,
+                outputs,
+                channel_config,
+            } => {
+                self.graph.as_mut().unwrap().add_node(
+                    node_id,
                     node,
                     inputs,
                     outputs,
                     channel_config,
-                } => {
-                    self.graph.as_mut().unwrap().add_node(
-                        node_id,
-                        node,
-                        inputs,
-                        outputs,
-                        channel_config,
-                    );
-                }
-                ConnectNode {
-                    from,
-                    to,
-                    output,
-                    input,
-                } => {
-                    self.graph
-                        .as_mut()
-                        .unwrap()
-                        .add_edge((from, output), (to, input));
-                }
-                DisconnectNode { from, to } => {
-                    self.graph.as_mut().unwrap().remove_edge(from, to);
-                }
-                DisconnectAll { from } => {
-                    self.graph.as_mut().unwrap().remove_edges_from(from);
-                }
-                FreeWhenFinished { id } => {
-                    self.graph.as_mut().unwrap().mark_free_when_finished(id);
-                }
-                AudioParamEvent { to, event } => {
-                    to.send(event).expect("Audioparam disappeared unexpectedly")
-                }
-                MarkCycleBreaker { id } => {
-                    self.graph.as_mut().unwrap().mark_cycle_breaker(id);
-                }
-                Shutdown { sender } => {
-                    let _ = sender.send(self.graph.take().unwrap());
-                    self.receiver = None;
-                    return; // no further handling of ctrl msgs
+                );
+            }
+            ReplaceProcessor { id, processor } => {
+                self.graph
+                    .as_mut()
+                    .unwrap()
+                    .replace_processor(id, processor);
+            }
+            NodeMessage { id, msg } => {
+                self.graph.as_mut().unwrap().route_message(id, msg);
+            }
+            ConnectNode {
+                from,
+                to,
+                output,
+                input,
+            } => {
+                self.graph
+                    .as_mut()
+                    .unwrap()
+                    .add_edge((from, output), (to, input));
+            }
+            DisconnectNode {
+                from,
+                output,
+                to,
+                input,
+            } => {
+                self.graph
+                    .as_mut()
+                    .unwrap()
+                    .remove_edges(from, output, to, input);
+            }
+            FreeWhenFinished { id } => {
+                self.graph.as_mut().unwrap().mark_free_when_finished(id);
+            }
+            AudioParamEvent { to, event } => {
+                to.send(event).expect("Audioparam disappeared unexpectedly")
+            }
+            SetWatchdog { options } => {
+                self.graph.as_mut().unwrap().set_watchdog(options);
+            }
+            Batch { messages } => {
+                for msg in messages {
+                    if !self.handle_control_message(msg) {
+                        return false;
+                    }
                 }
-                Startup { graph } => {
-                    self.graph = Some(graph);
+            }
+            MarkCycleBreaker { id } => {
+                self.graph.as_mut().unwrap().mark_cycle_breaker(id);
+            }
+            Shutdown { sender } => {
+                let _ = sender.send(self.graph.take().unwrap());
+                return false; // no further handling of ctrl msgs
+            }
+            Startup { graph } => {
+                self.graph = Some(*graph);
+                #[cfg(feature = "parallel-rendering")]
+                if self.render_threads > 1 {
+                    self.set_render_threads(self.render_threads);
                 }
             }
         }
+
+        true
     }
 
     // Render method of the `OfflineAudioContext::start_redering_sync`
@@ -131,7 +234,14 @@ impl RenderThread {
     // don't launch a thread.
     //
     // cf. https://webaudio.github.io/web-audio-api/#dom-offlineaudiocontext-startrendering
-    pub fn render_audiobuffer(mut self, length: usize) -> AudioBuffer {
+    //
+    // `on_quantum` is called with the number of rendered frames after every render quantum, the
+    // rendering stops when it returns `false`. The buffer is then truncated to the rendered frames.
+    pub fn render_audiobuffer<F: FnMut(usize) -> bool>(
+        mut self,
+        length: usize,
+        mut on_quantum: F,
+    ) -> AudioBuffer {
         let options = AudioBufferOptions {
             number_of_channels: self.number_of_channels,
             length,
@@ -171,17 +281,58 @@ impl RenderThread {
                     );
                 },
             );
+
+            let rendered_frames = (current_frame as usize + RENDER_QUANTUM_SIZE).min(length);
+            if !on_quantum(rendered_frames) {
+                buffer.split_off(rendered_frames);
+                break;
+            }
         }
 
         buffer
     }
 
+    /// Process the pending control messages and give access to the audio graph, used by the
+    /// [`testing`](crate::testing) module
+    pub fn sync_graph(&mut self) -> Option<&Graph> {
+        self.handle_control_messages();
+        self.graph.as_ref()
+    }
+
     pub fn render<S: FromSample<f32> + Clone>(&mut self, buffer: &mut [S]) {
         // collect timing information
         let render_start = Instant::now();
 
+        // position of the first frame of the callback, frames left over from the previous call
+        // belong to the last rendered quantum
+        let mut frame = self.frames_played.load(Ordering::SeqCst);
+        if let Some((offset, _)) = &self.buffer_offset {
+            frame -= (RENDER_QUANTUM_SIZE - offset) as u64;
+        }
+        if let Some(resampler) = &self.output_resampler {
+            frame = frame.saturating_sub(resampler.buffered_frames() as u64);
+        }
+
+        let frame = self.follow_clock(frame, render_start);
+
+        // the render thread never blocks, the previous value is kept when the lock is contended
+        if let Ok(mut last_callback) = self.timing.last_callback.try_lock() {
+            *last_callback = Some((frame, render_start));
+        }
+
         // perform actual rendering
-        self.render_inner(buffer);
+        let mut frames = buffer.len() / self.number_of_channels;
+        match self.output_resampler.take() {
+            Some(mut resampler) => {
+                self.render_resampled(buffer, &mut resampler);
+                // the deadline is relative to the rendered frames
+                frames = (frames as f64 * resampler.ratio()) as usize;
+                self.output_resampler = Some(resampler);
+            }
+            None => self.render_inner(buffer),
+        }
+
+        self.check_deadline(render_start, frames);
 
         // calculate load value and ship to control thread
         if let Some(load_value_sender) = &self.load_value_sender {
@@ -198,6 +349,176 @@ impl RenderThread {
         }
     }
 
+    /// Align the frame position of the callback starting at `now` with the followed clock, if
+    /// any, and return the corrected position
+    ///
+    /// The offset to the clock is smoothed to reject the jitter of the callbacks, the position is
+    /// corrected by whole frames once the smoothed offset reaches a frame.
+    fn follow_clock(&mut self, frame: u64, now: Instant) -> u64 {
+        // the render thread never blocks, the correction is postponed when a lock is contended
+        let clock = match self.timing.clock.try_lock() {
+            Ok(clock) => clock.clone(),
+            Err(_) => return frame,
+        };
+        let clock = match clock {
+            Some(clock) => clock,
+            None => {
+                self.clock_offset = None;
+                return frame;
+            }
+        };
+        let reference = match clock.timing.last_callback.try_lock() {
+            Ok(last_callback) => *last_callback,
+            Err(_) => return frame,
+        };
+        // the followed context has not started rendering yet
+        let (reference_frame, reference_start) = match reference {
+            Some(reference) => reference,
+            None => return frame,
+        };
+
+        // time of the followed clock at the start of this callback
+        let elapsed = match now.checked_duration_since(reference_start) {
+            Some(elapsed) => elapsed.as_secs_f64(),
+            None => -reference_start.duration_since(now).as_secs_f64(),
+        };
+        let time = reference_frame as f64 / f64::from(clock.sample_rate) + elapsed;
+
+        let sample_rate = f64::from(self.sample_rate);
+        let offset = time * sample_rate - frame as f64;
+        let smoothed = match self.clock_offset {
+            Some(previous) if (offset - previous).abs() < CLOCK_RESYNC_THRESHOLD * sample_rate => {
+                previous + (offset - previous) * CLOCK_SMOOTHING
+            }
+            // first alignment, or the clocks went apart (e.g. after a suspension)
+            _ => offset.round(),
+        };
+
+        let correction = smoothed.trunc();
+        self.clock_offset = Some(smoothed - correction);
+
+        if correction == 0. {
+            return frame;
+        }
+
+        // the position can not be moved before the start of the context
+        let correction = (correction as i64).max(-(frame as i64));
+        if correction > 0 {
+            self.frames_played
+                .fetch_add(correction as u64, Ordering::SeqCst);
+        } else {
+            self.frames_played
+                .fetch_sub(correction.unsigned_abs(), Ordering::SeqCst);
+        }
+
+        (frame as i64 + correction) as u64
+    }
+
+    /// Check the render duration of a callback of the given number of frames against its deadline
+    fn check_deadline(&mut self, render_start: Instant, frames: usize) {
+        let graph = match self.graph.as_mut() {
+            Some(graph) if frames > 0 => graph,
+            _ => return,
+        };
+
+        let duration = render_start.elapsed().as_secs_f64();
+        let max_duration = frames as f64 / self.sample_rate as f64;
+        let load = duration / max_duration;
+
+        if load > 1. {
+            self.timing.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+
+        if let Some(degraded) = graph.watchdog_tick(load) {
+            if let Some(sender) = self.event_sender.as_ref() {
+                let timestamp =
+                    self.frames_played.load(Ordering::SeqCst) as f64 / self.sample_rate as f64;
+                let event = OverloadEvent::new(timestamp, load, degraded, graph.bypassed_nodes());
+                let _ = sender.try_send(EventDispatch::overload(event));
+            }
+        }
+    }
+
+    /// Render the next quantum of the audio graph
+    fn render_quantum(&mut self) -> AudioRenderQuantum {
+        // update time
+        let current_frame = self
+            .frames_played
+            .fetch_add(RENDER_QUANTUM_SIZE as u64, Ordering::SeqCst);
+        let current_time = current_frame as f64 / self.sample_rate as f64;
+
+        let scope = RenderScope {
+            current_frame,
+            current_time,
+            sample_rate: self.sample_rate,
+            event_sender: self.event_sender.clone(),
+            node_id: Cell::new(AudioNodeId(0)), // placeholder value
+        };
+
+        // render audio graph
+        let rendered = self.graph.as_mut().unwrap().render(&scope);
+        self.send_echo_reference(&rendered);
+
+        rendered
+    }
+
+    /// Provide the rendered quantum to the echo cancellation of media streams, if any
+    fn send_echo_reference(&self, rendered: &AudioRenderQuantum) {
+        // never block the render thread, skip this quantum when the list is being updated
+        let mut references = match self.timing.echo_references.try_lock() {
+            Ok(references) if !references.is_empty() => references,
+            _ => return,
+        };
+
+        let number_of_channels = rendered.number_of_channels();
+        let mut mono = vec![0.; RENDER_QUANTUM_SIZE];
+        rendered.channels().iter().for_each(|channel| {
+            mono.iter_mut()
+                .zip(channel.iter())
+                .for_each(|(m, v)| *m += v / number_of_channels as f32);
+        });
+
+        // a full channel means the stream is not being read, drop the quantum
+        references.retain(|sender| {
+            !matches!(
+                sender.try_send(mono.clone()),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+    }
+
+    /// Render into the `buffer` of an output device running at another sample rate
+    fn render_resampled<S: FromSample<f32> + Clone>(
+        &mut self,
+        buffer: &mut [S],
+        resampler: &mut StreamResampler,
+    ) {
+        // handle addition/removal of nodes/edges
+        self.handle_control_messages();
+
+        // if the thread is still booting, or shutting down, fill with silence
+        if self.graph.is_none() {
+            buffer.fill(S::from_sample_(0.));
+            return;
+        }
+
+        for frame in buffer.chunks_mut(self.number_of_channels) {
+            while resampler.needs_input() {
+                let rendered = self.render_quantum();
+                resampler.push(&rendered);
+
+                // handle addition/removal of nodes/edges
+                self.handle_control_messages();
+                if self.graph.is_none() {
+                    buffer.fill(S::from_sample_(0.));
+                    return;
+                }
+            }
+
+            resampler.write_frame(frame);
+        }
+    }
+
     fn render_inner<S: FromSample<f32> + Clone>(&mut self, mut buffer: &mut [S]) {
         // There may be audio frames left over from the previous render call,
         // if the cpal buffer size did not align with our internal RENDER_QUANTUM_SIZE
@@ -207,14 +528,7 @@ impl RenderThread {
             let (first, next) = buffer.split_at_mut(leftover_len.min(buffer.len()));
 
             // copy rendered audio into output slice
-            for i in 0..self.number_of_channels {
-                let output = first.iter_mut().skip(i).step_by(self.number_of_channels);
-                let channel = prev_rendered.channel_data(i)[offset..].iter();
-                for (sample, input) in output.zip(channel) {
-                    let value = S::from_sample_(*input);
-                    *sample = value;
-                }
-            }
+            copy_interleaved(&prev_rendered, offset, first, self.number_of_channels);
 
             // exit early if we are done filling the buffer with the previously rendered data
             if next.is_empty() {
@@ -243,37 +557,10 @@ impl RenderThread {
         let chunk_size = RENDER_QUANTUM_SIZE * self.number_of_channels;
 
         for data in buffer.chunks_mut(chunk_size) {
-            // update time
-            let current_frame = self
-                .frames_played
-                .fetch_add(RENDER_QUANTUM_SIZE as u64, Ordering::SeqCst);
-            let current_time = current_frame as f64 / self.sample_rate as f64;
-
-            let scope = RenderScope {
-                current_frame,
-                current_time,
-                sample_rate: self.sample_rate,
-                event_sender: self.event_sender.clone(),
-                node_id: Cell::new(AudioNodeId(0)), // placeholder value
-            };
-
-            // render audio graph
-            let mut rendered = self.graph.as_mut().unwrap().render(&scope);
-
-            // online AudioContext allows channel count to be less than no of hardware channels
-            if rendered.number_of_channels() != self.number_of_channels {
-                rendered.mix(self.number_of_channels, ChannelInterpretation::Discrete);
-            }
+            let rendered = self.render_quantum();
 
             // copy rendered audio into output slice
-            for i in 0..self.number_of_channels {
-                let output = data.iter_mut().skip(i).step_by(self.number_of_channels);
-                let channel = rendered.channel_data(i).iter();
-                for (sample, input) in output.zip(channel) {
-                    let value = S::from_sample_(*input);
-                    *sample = value;
-                }
-            }
+            copy_interleaved(&rendered, 0, data, self.number_of_channels);
 
             if data.len() != chunk_size {
                 // this is the last chunk, and it contained less than RENDER_QUANTUM_SIZE samples
@@ -288,8 +575,106 @@ impl RenderThread {
     }
 }
 
+/// Copy the rendered frames starting at `offset` into the interleaved `output` slice
+///
+/// An online AudioContext allows its channel count to be less than the number of hardware
+/// channels, which may also exceed `MAX_CHANNELS`. Hardware channels without a rendered
+/// counterpart are filled with silence (discrete channel interpretation).
+fn copy_interleaved<S: FromSample<f32> + Clone>(
+    rendered: &AudioRenderQuantum,
+    offset: usize,
+    output: &mut [S],
+    number_of_channels: usize,
+) {
+    let rendered_channels = rendered.number_of_channels().min(number_of_channels);
+
+    for i in 0..number_of_channels {
+        let output = output.iter_mut().skip(i).step_by(number_of_channels);
+        if i < rendered_channels {
+            let channel = rendered.channel_data(i)[offset..].iter();
+            for (sample, input) in output.zip(channel) {
+                *sample = S::from_sample_(*input);
+            }
+        } else {
+            output
+                .take(RENDER_QUANTUM_SIZE - offset)
+                .for_each(|sample| *sample = S::from_sample_(0.));
+        }
+    }
+}
+
 impl Drop for RenderThread {
     fn drop(&mut self) {
+        // The stream may stop calling back before the shutdown is handled, e.g. when the output
+        // device was lost. Hand over the audio graph from here in that case.
+        if let (Some(receiver), Some(graph)) = (self.receiver.take(), self.graph.take()) {
+            let shutdown = receiver.try_iter().find_map(|msg| match msg {
+                ControlMessage::Shutdown { sender } => Some(sender),
+                _ => None,
+            });
+            if let Some(sender) = shutdown {
+                let _ = sender.send(graph);
+            }
+        }
+
         log::info!("Audio render thread has been dropped");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn render_thread(sample_rate: f32, timing: Arc<RenderTiming>) -> RenderThread {
+        let (_sender, receiver) = crossbeam_channel::unbounded();
+        RenderThread::new(
+            sample_rate,
+            2,
+            receiver,
+            Arc::new(AtomicU64::new(0)),
+            None,
+            None,
+            timing,
+        )
+    }
+
+    #[test]
+    fn test_follow_clock() {
+        let main = Arc::new(RenderTiming::default());
+        let mut second = render_thread(44_100., Arc::new(RenderTiming::default()));
+        *second.timing.clock.lock().unwrap() = Some(SharedClock {
+            timing: Arc::clone(&main),
+            sample_rate: 48_000.,
+        });
+
+        // the followed context has not started rendering
+        let start = Instant::now();
+        assert_eq!(second.follow_clock(0, start), 0);
+
+        // the followed context is at 1 second, 10 ms before this callback
+        *main.last_callback.lock().unwrap() = Some((48_000, start));
+        let now = start + Duration::from_millis(10);
+        assert_eq!(second.follow_clock(0, now), 44_541);
+        assert_eq!(second.frames_played.load(Ordering::SeqCst), 44_541);
+
+        // a drift below a frame is not corrected right away
+        *main.last_callback.lock().unwrap() = Some((48_000 + 480, now));
+        assert_eq!(second.follow_clock(44_541, now), 44_541);
+
+        // a drift of two frames is corrected gradually, by a single frame at a time, down to
+        // less than a frame
+        let mut frame = 44_539;
+        for _ in 0..1000 {
+            let corrected = second.follow_clock(frame, now);
+            assert!(corrected - frame <= 1);
+            frame = corrected;
+        }
+        assert!(frame == 44_540 || frame == 44_541);
+
+        // run freely again
+        *second.timing.clock.lock().unwrap() = None;
+        assert_eq!(second.follow_clock(100, now), 100);
+    }
+}
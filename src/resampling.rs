@@ -1,7 +1,233 @@
 use std::error::Error;
 
+use dasp_sample::FromSample;
+
 use crate::buffer::{AudioBuffer, AudioBufferOptions};
-use crate::AudioBufferIter;
+use crate::control::frames_before;
+use crate::render::AudioRenderQuantum;
+use crate::{AudioBufferIter, RENDER_QUANTUM_SIZE};
+
+/// Number of zero crossings of the windowed sinc kernel on each side
+const SINC_ZERO_CROSSINGS: usize = 32;
+/// Resolution of the tabulated kernel, in entries per zero crossing
+const SINC_TABLE_OVERSAMPLING: usize = 512;
+/// Shape parameter of the Kaiser window, about -90dB stopband attenuation
+const KAISER_BETA: f64 = 9.;
+/// Cutoff frequency relative to the lowest Nyquist frequency, leaves room for
+/// the transition band of the filter
+const SINC_ROLLOFF: f64 = 0.95;
+
+/// Zeroth order modified Bessel function of the first kind
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.;
+    let mut term = 1.;
+    let half_x = x / 2.;
+    let mut k = 1.;
+
+    while term > sum * 1e-12 {
+        term *= (half_x / k) * (half_x / k);
+        sum += term;
+        k += 1.;
+    }
+
+    sum
+}
+
+/// Tabulated right half of a Kaiser windowed sinc kernel
+fn sinc_table() -> Vec<f32> {
+    let len = SINC_ZERO_CROSSINGS * SINC_TABLE_OVERSAMPLING;
+    let norm = bessel_i0(KAISER_BETA);
+
+    (0..=len)
+        .map(|i| {
+            let x = i as f64 / SINC_TABLE_OVERSAMPLING as f64;
+            let sinc = if i == 0 {
+                1.
+            } else {
+                let px = std::f64::consts::PI * x;
+                px.sin() / px
+            };
+            let r = i as f64 / len as f64;
+            let window = bessel_i0(KAISER_BETA * (1. - r * r).sqrt()) / norm;
+            (sinc * window) as f32
+        })
+        .collect()
+}
+
+/// Windowed sinc kernel of a low-pass filter, for a given ratio of sample rates
+struct SincKernel {
+    table: Vec<f32>,
+    /// cutoff relative to the source Nyquist frequency
+    cutoff: f64,
+}
+
+impl SincKernel {
+    /// Kernel for a conversion from `source_sr` to `target_sr`
+    fn new(source_sr: f64, target_sr: f64) -> Self {
+        let ratio = target_sr / source_sr;
+
+        Self {
+            table: sinc_table(),
+            cutoff: ratio.min(1.) * SINC_ROLLOFF,
+        }
+    }
+
+    /// Half width of the kernel, in source samples
+    fn half_width(&self) -> f64 {
+        SINC_ZERO_CROSSINGS as f64 / self.cutoff
+    }
+
+    /// Normalized weight of the source sample at distance `x`, in source samples
+    fn weight(&self, x: f64) -> f32 {
+        // position in the table
+        let position = x.abs() * self.cutoff * SINC_TABLE_OVERSAMPLING as f64;
+        let index = position as usize;
+        if index >= self.table.len() - 1 {
+            return 0.;
+        }
+        let k = (position - index as f64) as f32;
+        (self.table[index] * (1. - k) + self.table[index + 1] * k) * self.cutoff as f32
+    }
+}
+
+/// Band-limited resampling of a signal, with a windowed sinc interpolation
+///
+/// The signal is considered to be silent outside of the given samples. The
+/// length of the output is ceiled according to the ratio of sample rates.
+pub(crate) fn sinc_resample(samples: &[f32], source_sr: f64, target_sr: f64) -> Vec<f32> {
+    let ratio = target_sr / source_sr;
+    // do not add a frame because of rounding errors of the ratio
+    let target_length = frames_before(samples.len() as f64 * ratio) as usize;
+
+    let kernel = SincKernel::new(source_sr, target_sr);
+    let half_width = kernel.half_width();
+    let last_index = samples.len() as isize - 1;
+
+    (0..target_length)
+        .map(|i| {
+            let position = i as f64 / ratio;
+            let first = ((position - half_width).ceil() as isize).max(0);
+            let last = ((position + half_width).floor() as isize).min(last_index);
+
+            let mut value = 0.;
+            for j in first..=last {
+                value += samples[j as usize] * kernel.weight(position - j as f64);
+            }
+
+            value
+        })
+        .collect()
+}
+
+/// Band-limited sample rate converter of a continuous multichannel stream
+///
+/// Used at the boundary of the render thread and the audio output device when the
+/// sample rate of the context differs from the sample rate of the device. The
+/// converter only buffers the input frames required by the lookahead of its
+/// kernel, the converted signal is not delayed.
+pub(crate) struct StreamResampler {
+    kernel: SincKernel,
+    /// input frames per output frame
+    ratio: f64,
+    /// buffered input frames of each channel
+    history: Vec<Vec<f32>>,
+    /// position of the next output frame in the buffered input frames
+    position: f64,
+    /// weights of the input frames of the current output frame
+    weights: Vec<f32>,
+}
+
+impl StreamResampler {
+    #[cfg(any(test, feature = "cpal"))]
+    pub fn new(number_of_channels: usize, source_sr: f32, target_sr: f32) -> Self {
+        let kernel = SincKernel::new(f64::from(source_sr), f64::from(target_sr));
+
+        // the signal is silent before the first input frame
+        let padding = kernel.half_width().ceil() as usize;
+        let weights = Vec::with_capacity(2 * padding + 1);
+
+        Self {
+            kernel,
+            ratio: f64::from(source_sr) / f64::from(target_sr),
+            history: vec![vec![0.; padding]; number_of_channels],
+            position: padding as f64,
+            weights,
+        }
+    }
+
+    /// Number of input frames per output frame
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Number of buffered input frames which are not yet converted
+    pub fn buffered_frames(&self) -> f64 {
+        self.history[0].len() as f64 - self.position
+    }
+
+    /// Whether more input is required to compute the next output frame
+    pub fn needs_input(&self) -> bool {
+        (self.position + self.kernel.half_width()) as usize >= self.history[0].len()
+    }
+
+    /// Append a render quantum to the input, missing channels are silent
+    pub fn push(&mut self, input: &AudioRenderQuantum) {
+        let number_of_channels = input.number_of_channels();
+
+        self.history
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, history)| {
+                if i < number_of_channels {
+                    history.extend_from_slice(&input.channel_data(i)[..]);
+                } else {
+                    history.resize(history.len() + RENDER_QUANTUM_SIZE, 0.);
+                }
+            });
+    }
+
+    /// Compute the next output frame into the interleaved `output` slice
+    ///
+    /// # Panics
+    ///
+    /// Panics if more input is required, see [`Self::needs_input`]
+    pub fn write_frame<S: FromSample<f32>>(&mut self, output: &mut [S]) {
+        assert!(!self.needs_input());
+
+        let half_width = self.kernel.half_width();
+        let first = (self.position - half_width).ceil() as usize;
+        let last = (self.position + half_width).floor() as usize;
+
+        let position = self.position;
+        let kernel = &self.kernel;
+        self.weights.clear();
+        self.weights
+            .extend((first..=last).map(|j| kernel.weight(position - j as f64)));
+
+        output
+            .iter_mut()
+            .zip(self.history.iter())
+            .for_each(|(sample, history)| {
+                let value: f32 = history[first..=last]
+                    .iter()
+                    .zip(self.weights.iter())
+                    .map(|(x, w)| x * w)
+                    .sum();
+                *sample = S::from_sample_(value);
+            });
+
+        self.position += self.ratio;
+
+        // drop the input frames out of reach of the kernel, once in a while
+        let consumed = (self.position - half_width).floor() as usize;
+        if consumed >= RENDER_QUANTUM_SIZE {
+            self.history.iter_mut().for_each(|history| {
+                history.drain(..consumed);
+            });
+            self.position -= consumed as f64;
+        }
+    }
+}
 
 /// Sample rate converter and buffer chunk splitter.
 ///
@@ -70,7 +296,7 @@ impl<M: AudioBufferIter> Iterator for Resampler<M> {
                 None => return None,
                 Some(Err(e)) => return Some(Err(e)),
                 Some(Ok(mut data)) => {
-                    data.resample(self.sample_rate);
+                    data.resample_linear(self.sample_rate);
                     data
                 }
             },
@@ -79,7 +305,12 @@ impl<M: AudioBufferIter> Iterator for Resampler<M> {
 
         while buffer.length() < self.sample_len {
             // buffer is smaller than desired len
-            match self.input.next() {
+            let next = match self.input.next() {
+                // an empty buffer signals that no data is available yet
+                Some(Ok(data)) if data.length() == 0 => None,
+                next => next,
+            };
+            match next {
                 None => {
                     let options = AudioBufferOptions {
                         number_of_channels: buffer.number_of_channels(),
@@ -94,7 +325,7 @@ impl<M: AudioBufferIter> Iterator for Resampler<M> {
                 }
                 Some(Err(e)) => return Some(Err(e)),
                 Some(Ok(mut data)) => {
-                    data.resample(self.sample_rate);
+                    data.resample_linear(self.sample_rate);
                     buffer.extend(&data)
                 }
             }
@@ -110,10 +341,209 @@ impl<M: AudioBufferIter> Iterator for Resampler<M> {
     }
 }
 
+/// Maximum deviation of the adaptive resampling ratio from the nominal ratio,
+/// clock drifts of audio devices are in the order of 100 ppm
+const MAX_DRIFT_RATIO: f64 = 0.005;
+/// Smoothing factor of the measured fill level, per output chunk
+const FILL_SMOOTHING: f64 = 0.005;
+/// Proportional gain of the ratio controller, per relative fill error
+const DRIFT_KP: f64 = 0.005;
+/// Integral gain of the ratio controller, per relative fill error and output chunk
+const DRIFT_KI: f64 = 0.000_001;
+/// The buffered frames are dropped when the fill level exceeds the target by this factor
+const MAX_FILL_FACTOR: usize = 8;
+
+/// Sample rate converter compensating the clock drift of a live stream
+///
+/// When a live stream (e.g. a microphone) and the render thread are driven by
+/// different clocks, their effective rates slightly differ. Played back at its
+/// nominal rate, the stream either accumulates latency or runs out of data.
+///
+/// The `AdaptiveResampler` buffers the incoming frames and adjusts the resampling
+/// ratio around the nominal ratio, so the fill level of its buffer is kept around
+/// a target. An empty buffer yielded by the input signals that no new data is
+/// available. Inputs which never yield an empty buffer are not considered live
+/// and are played back at their nominal rate.
+pub(crate) struct AdaptiveResampler<I> {
+    /// desired sample rate
+    sample_rate: f32,
+    /// desired sample length
+    sample_len: usize,
+    /// input stream
+    input: I,
+    /// buffered frames of each channel, at the desired sample rate
+    fifo: Vec<Vec<f32>>,
+    /// fractional read position in the buffered frames
+    position: f64,
+    /// the input has signaled that no data was available
+    live: bool,
+    /// target fill level, in frames
+    target: usize,
+    /// smoothed fill level, in frames
+    fill: f64,
+    /// integral term of the ratio controller
+    integral: f64,
+    /// input frames consumed per output frame
+    ratio: f64,
+}
+
+impl<M: AudioBufferIter> AdaptiveResampler<M> {
+    pub fn new(sample_rate: f32, sample_len: usize, input: M) -> Self {
+        Self {
+            sample_rate,
+            sample_len,
+            input,
+            fifo: Vec::new(),
+            position: 0.,
+            live: false,
+            target: 2 * sample_len,
+            fill: 0.,
+            integral: 0.,
+            ratio: 1.,
+        }
+    }
+
+    fn buffered_frames(&self) -> usize {
+        self.fifo.first().map(Vec::len).unwrap_or(0)
+    }
+
+    /// Append a chunk of the input to the buffered frames
+    fn push(&mut self, mut data: AudioBuffer) {
+        data.resample_linear(self.sample_rate);
+
+        if data.number_of_channels() != self.fifo.len() {
+            // the channel layout of the stream changed, restart from scratch
+            self.fifo = vec![Vec::new(); data.number_of_channels()];
+            self.position = 0.;
+        }
+
+        self.fifo
+            .iter_mut()
+            .zip(data.channels())
+            .for_each(|(fifo, channel)| fifo.extend_from_slice(channel.as_slice()));
+
+        // leave room for the jitter of the input chunks
+        self.target = self.target.max(data.length() + self.sample_len);
+    }
+
+    /// Adjust the resampling ratio according to the fill level of the buffer
+    fn update_ratio(&mut self) {
+        let fill = self.buffered_frames() as f64 - self.position;
+        self.fill += (fill - self.fill) * FILL_SMOOTHING;
+
+        let error = (self.fill - self.target as f64) / self.target as f64;
+        self.integral = (self.integral + error * DRIFT_KI).clamp(-MAX_DRIFT_RATIO, MAX_DRIFT_RATIO);
+        let drift = (error * DRIFT_KP + self.integral).clamp(-MAX_DRIFT_RATIO, MAX_DRIFT_RATIO);
+
+        self.ratio = 1. + drift;
+    }
+}
+
+impl<M: AudioBufferIter> Iterator for AdaptiveResampler<M> {
+    type Item = Result<AudioBuffer, Box<dyn Error + Send + Sync>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // drain the available input, so the buffering of a live stream is accounted
+        // for in the fill level
+        let mut ended = false;
+        loop {
+            if self.buffered_frames() > MAX_FILL_FACTOR * self.target {
+                break;
+            }
+
+            match self.input.next() {
+                None => {
+                    ended = true;
+                    break;
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                Some(Ok(data)) if data.length() == 0 => {
+                    if !self.live {
+                        // start at the target fill level
+                        self.live = true;
+                        self.fill = self.target as f64;
+                    }
+                    break;
+                }
+                Some(Ok(data)) => self.push(data),
+            }
+        }
+
+        if ended && self.buffered_frames() == 0 {
+            return None;
+        }
+
+        if self.live {
+            if self.buffered_frames() > MAX_FILL_FACTOR * self.target {
+                // way too much latency, e.g. after a stall of the render thread
+                log::debug!("adaptive resampler: buffer overflow, dropping frames");
+                let drop = self.buffered_frames() - self.target;
+                self.fifo.iter_mut().for_each(|fifo| {
+                    fifo.drain(..drop);
+                });
+                self.position = 0.;
+                self.fill = self.target as f64;
+            }
+
+            self.update_ratio();
+        }
+
+        let number_of_channels = self.fifo.len().max(1);
+        let mut channels = vec![vec![0.; self.sample_len]; number_of_channels];
+
+        let available = self.buffered_frames();
+        let mut position = self.position;
+        let mut rendered = 0;
+
+        for i in 0..self.sample_len {
+            let index = position as usize;
+            if index + 1 >= available {
+                break;
+            }
+
+            let k = (position - index as f64) as f32;
+            channels
+                .iter_mut()
+                .zip(self.fifo.iter())
+                .for_each(|(output, fifo)| {
+                    output[i] = fifo[index] * (1. - k) + fifo[index + 1] * k;
+                });
+
+            rendered += 1;
+            position += self.ratio;
+        }
+
+        if rendered < self.sample_len {
+            if ended {
+                // the last frame can not be interpolated
+                self.fifo.iter_mut().for_each(Vec::clear);
+                return Some(Ok(AudioBuffer::from(channels, self.sample_rate)));
+            }
+            log::debug!("adaptive resampler: buffer underrun, inserting silence");
+        }
+
+        // remove the consumed frames
+        let consumed = (position as usize).min(available);
+        self.fifo.iter_mut().for_each(|fifo| {
+            fifo.drain(..consumed);
+        });
+        self.position = if rendered < self.sample_len {
+            0.
+        } else {
+            position - consumed as f64
+        };
+
+        Some(Ok(AudioBuffer::from(channels, self.sample_rate)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
 
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
     use super::*;
     use crate::buffer::{AudioBuffer, ChannelData};
 
@@ -168,4 +598,111 @@ mod tests {
 
         assert!(resampler.next().is_none());
     }
+
+    /// Live input producing chunks of frames at a rate relative to the consumer
+    struct DriftingInput {
+        clock: Arc<AtomicU64>,
+        rate: f64,
+        chunk_len: usize,
+        produced: usize,
+    }
+
+    impl Iterator for DriftingInput {
+        type Item = Result<AudioBuffer, Box<dyn Error + Send + Sync>>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let length = if ((self.produced + self.chunk_len) as f64)
+                < self.clock.load(Ordering::Relaxed) as f64 * self.rate
+            {
+                self.produced += self.chunk_len;
+                self.chunk_len
+            } else {
+                0
+            };
+            Some(Ok(AudioBuffer::from(vec![vec![1.; length]], 44_100.)))
+        }
+    }
+
+    fn assert_drift_compensated(rate: f64) {
+        let clock = Arc::new(AtomicU64::new(0));
+        let input = DriftingInput {
+            clock: clock.clone(),
+            rate,
+            chunk_len: 441,
+            produced: 0,
+        };
+        let mut resampler = AdaptiveResampler::new(44_100., 128, input);
+
+        // about 5 minutes
+        let mut dropouts = 0;
+        for i in 0..100_000 {
+            clock.store((i + 1) * 128 + 1024, Ordering::Relaxed);
+            let next = resampler.next().unwrap().unwrap();
+            assert_eq!(next.length(), 128);
+            if i > 1000 && next.get_channel_data(0).contains(&0.) {
+                dropouts += 1;
+            }
+        }
+
+        assert_eq!(dropouts, 0);
+        assert_float_eq!(resampler.ratio, rate, abs <= 1e-4);
+        // latency does not accumulate
+        assert!(resampler.buffered_frames() < 2 * resampler.target);
+    }
+
+    #[test]
+    fn test_adaptive_resampler_faster_input() {
+        assert_drift_compensated(1.002);
+    }
+
+    #[test]
+    fn test_adaptive_resampler_slower_input() {
+        assert_drift_compensated(0.998);
+    }
+
+    #[test]
+    fn test_adaptive_resampler_not_live() {
+        let channel = ChannelData::from((0..20).map(|i| i as f32).collect::<Vec<_>>());
+        let input_buf = AudioBuffer::from_channels(vec![channel], 44_100.);
+        let input = vec![input_buf; 2].into_iter().map(Ok);
+        let mut resampler = AdaptiveResampler::new(44_100., 10, input);
+
+        // played back at the nominal rate
+        let next = resampler.next().unwrap().unwrap();
+        let expected: Vec<f32> = (0..10).map(|i| i as f32).collect();
+        assert_float_eq!(next.get_channel_data(0), &expected[..], abs_all <= 0.);
+        let next = resampler.next().unwrap().unwrap();
+        let expected: Vec<f32> = (10..20).map(|i| i as f32).collect();
+        assert_float_eq!(next.get_channel_data(0), &expected[..], abs_all <= 0.);
+        assert_float_eq!(resampler.ratio, 1., abs <= 0.);
+    }
+
+    #[test]
+    fn test_stream_resampler() {
+        let alloc = crate::render::Alloc::with_capacity(1);
+        let mut signal = alloc.silence();
+        signal.copy_from_slice(&[1.; RENDER_QUANTUM_SIZE]);
+        let input = AudioRenderQuantum::from(signal);
+
+        let mut resampler = StreamResampler::new(2, 44_100., 48_000.);
+        assert_float_eq!(resampler.ratio(), 44_100. / 48_000., abs <= 1e-12);
+
+        let mut output = vec![0.; 2 * 4800];
+        for frame in output.chunks_mut(2) {
+            while resampler.needs_input() {
+                resampler.push(&input);
+            }
+            resampler.write_frame(frame);
+        }
+
+        // the signal is not delayed
+        assert!(output[0] > 0.9);
+        // unity gain once the kernel is filled, the missing channel is silent
+        output[2 * 100..].chunks(2).for_each(|frame| {
+            assert_float_eq!(frame[0], 1., abs <= 1e-2);
+            assert_float_eq!(frame[1], 0., abs <= 0.);
+        });
+        // the buffered input is bounded
+        assert!(resampler.buffered_frames() < 2. * RENDER_QUANTUM_SIZE as f64);
+    }
 }
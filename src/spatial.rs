@@ -271,7 +271,7 @@ pub fn distance(source_position: Vector3<f32>, listener_position: Vector3<f32>)
     vec3_len(vec3_sub(source_position, listener_position))
 }
 
-/// Angle between two vectors in 3D
+/// Angle between the source orientation and the direction from the source to the listener
 pub fn angle(
     source_position: Vector3<f32>,
     source_orientation: Vector3<f32>,
@@ -283,7 +283,7 @@ pub fn angle(
     }
     let normalized_source_orientation = vec3_normalized(source_orientation);
 
-    let relative_pos = vec3_sub(source_position, listener_position);
+    let relative_pos = vec3_sub(listener_position, source_position);
     // Handle degenerate case if source and listener are at the same point.
     if vec3_square_len(relative_pos) <= f32::MIN_POSITIVE {
         return 0.;
@@ -388,4 +388,17 @@ mod tests {
 
         assert_float_eq!(angle, 90., abs <= 0.);
     }
+
+    #[test]
+    fn test_angle_facing_listener() {
+        let pos = [1., 0., 0.];
+
+        let orientation = [-1., 0., 0.];
+        let angle_towards = angle(pos, orientation, LP);
+        assert_float_eq!(angle_towards, 0., abs <= 0.);
+
+        let orientation = [1., 0., 0.];
+        let angle_away = angle(pos, orientation, LP);
+        assert_float_eq!(angle_away, 180., abs <= 0.);
+    }
 }
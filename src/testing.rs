@@ -0,0 +1,866 @@
+//! Deterministic offline rendering for regression tests
+//!
+//! [`render`] builds an audio graph in an [`OfflineAudioContext`], renders it
+//! and returns the output split per render quantum, together with timing
+//! metadata. No audio hardware is involved, and all randomness is derived
+//! from a fixed seed, so the output of a graph is bit-exact across runs and
+//! can be compared with [`RenderedOutput::first_mismatch`] in CI.
+//!
+//! In addition, [`run_graph_operations`] applies arbitrary sequences of graph
+//! mutations and validates the internal consistency of the audio graph after
+//! each of them. It is designed to be driven by a fuzzer, see the `fuzz`
+//! directory of the repository.
+//!
+//! ```
+//! use web_audio_api::context::{BaseAudioContext, OfflineAudioContext};
+//! use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, NoiseNode, NoiseOptions};
+//! use web_audio_api::testing::{render, RenderOptions, SeedSequence};
+//!
+//! fn graph(context: &OfflineAudioContext, seeds: &mut SeedSequence) {
+//!     let options = NoiseOptions {
+//!         seed: Some(seeds.next_seed()),
+//!         ..NoiseOptions::default()
+//!     };
+//!     let noise = NoiseNode::new(context, options);
+//!     noise.connect(&context.destination());
+//!     noise.start();
+//! }
+//!
+//! let options = RenderOptions {
+//!     number_of_quanta: 4,
+//!     seed: 42,
+//!     ..RenderOptions::default()
+//! };
+//!
+//! let output = render(options.clone(), graph);
+//! let reference = render(options, graph);
+//! assert_eq!(output.quanta.len(), 4);
+//! assert_eq!(output.first_mismatch(&reference), None);
+//! ```
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use crate::context::{BaseAudioContext, OfflineAudioContext, LISTENER_PARAM_IDS};
+use crate::node::{
+    AudioNode, AudioScheduledSourceNode, ChannelMergerNode, ChannelSplitterNode,
+    ConstantSourceNode, DelayNode, GainNode, OscillatorNode,
+};
+use crate::{AudioBuffer, AudioParam, RENDER_QUANTUM_SIZE};
+
+/// Options for [`render`]
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// Number of output channels of the offline context
+    pub number_of_channels: usize,
+    /// Number of render quanta to render
+    pub number_of_quanta: usize,
+    /// Sample rate of the offline context
+    pub sample_rate: f32,
+    /// Seed of the [`SeedSequence`] handed to the graph setup
+    pub seed: u64,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            number_of_channels: 1,
+            number_of_quanta: 1,
+            sample_rate: 44100.,
+            seed: 0,
+        }
+    }
+}
+
+/// Deterministic sequence of seeds, to be used for the random number
+/// generators of the nodes in a rendered graph
+///
+/// See e.g. [`NoiseOptions::seed`](crate::node::NoiseOptions::seed)
+#[derive(Clone, Debug)]
+pub struct SeedSequence {
+    state: u64,
+}
+
+impl SeedSequence {
+    /// Create a new sequence starting from the given seed
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Next seed of the sequence
+    pub fn next_seed(&mut self) -> u64 {
+        // splitmix64, cf. <https://prng.di.unimi.it/splitmix64.c>
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// Output of a single render quantum
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderedQuantum {
+    /// Index of the render quantum
+    pub index: usize,
+    /// Index of the first sample frame of the render quantum
+    pub frame: usize,
+    /// Time in seconds of the first sample frame of the render quantum
+    pub time: f64,
+    /// Sample data of each channel, `RENDER_QUANTUM_SIZE` frames long
+    pub channels: Vec<Vec<f32>>,
+}
+
+/// Output of [`render`]
+#[derive(Clone, Debug)]
+pub struct RenderedOutput {
+    /// Sample rate of the rendering
+    pub sample_rate: f32,
+    /// Rendered quanta, in order
+    pub quanta: Vec<RenderedQuantum>,
+    /// Wall clock time spent rendering, not taken into account for comparisons
+    pub render_duration: Duration,
+}
+
+/// First difference between two [`RenderedOutput`]s, see [`RenderedOutput::first_mismatch`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    /// Index of the render quantum
+    pub quantum: usize,
+    /// Index of the channel
+    pub channel: usize,
+    /// Index of the sample frame within the render quantum
+    pub frame: usize,
+    /// Sample value of the reference output, `None` if it is missing
+    pub expected: Option<f32>,
+    /// Sample value of the compared output, `None` if it is missing
+    pub actual: Option<f32>,
+}
+
+impl RenderedOutput {
+    /// Number of channels of the rendering
+    pub fn number_of_channels(&self) -> usize {
+        self.quanta.first().map_or(0, |q| q.channels.len())
+    }
+
+    /// Concatenated sample data of the given channel
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the channel index is out of bounds
+    pub fn channel_data(&self, channel: usize) -> Vec<f32> {
+        self.quanta
+            .iter()
+            .flat_map(|q| q.channels[channel].iter().copied())
+            .collect()
+    }
+
+    /// Concatenate the rendered quanta into an [`AudioBuffer`], e.g. to store it
+    /// as a reference file with [`AudioBuffer::to_wav`]
+    pub fn to_audio_buffer(&self) -> AudioBuffer {
+        let channels = (0..self.number_of_channels())
+            .map(|c| self.channel_data(c))
+            .collect();
+        AudioBuffer::from(channels, self.sample_rate)
+    }
+
+    /// Compare the sample data bit by bit with the `reference` output and
+    /// return the first difference, if any
+    pub fn first_mismatch(&self, reference: &Self) -> Option<Mismatch> {
+        let number_of_quanta = self.quanta.len().max(reference.quanta.len());
+        let number_of_channels = self
+            .number_of_channels()
+            .max(reference.number_of_channels());
+
+        for quantum in 0..number_of_quanta {
+            for channel in 0..number_of_channels {
+                let sample = |output: &Self, frame: usize| {
+                    output
+                        .quanta
+                        .get(quantum)
+                        .and_then(|q| q.channels.get(channel))
+                        .map(|c| c[frame])
+                };
+
+                for frame in 0..RENDER_QUANTUM_SIZE {
+                    let expected = sample(reference, frame);
+                    let actual = sample(self, frame);
+                    if expected.map(f32::to_bits) != actual.map(f32::to_bits) {
+                        return Some(Mismatch {
+                            quantum,
+                            channel,
+                            frame,
+                            expected,
+                            actual,
+                        });
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Build an audio graph with `setup` and render it offline
+///
+/// The `setup` closure receives the [`OfflineAudioContext`] to build the graph
+/// in, and a [`SeedSequence`] derived from [`RenderOptions::seed`] to seed the
+/// random number generators of the nodes.
+///
+/// # Panics
+///
+/// This function panics if the number of channels or the sample rate are
+/// invalid for an [`OfflineAudioContext`]
+pub fn render<F>(options: RenderOptions, setup: F) -> RenderedOutput
+where
+    F: FnOnce(&OfflineAudioContext, &mut SeedSequence),
+{
+    let RenderOptions {
+        number_of_channels,
+        number_of_quanta,
+        sample_rate,
+        seed,
+    } = options;
+
+    let length = number_of_quanta * RENDER_QUANTUM_SIZE;
+    let context = OfflineAudioContext::new(number_of_channels, length, sample_rate);
+    setup(&context, &mut SeedSequence::new(seed));
+
+    let start = Instant::now();
+    let buffer = context.start_rendering_sync();
+    let render_duration = start.elapsed();
+
+    let quanta = (0..number_of_quanta)
+        .map(|index| {
+            let frame = index * RENDER_QUANTUM_SIZE;
+            let channels = (0..buffer.number_of_channels())
+                .map(|c| buffer.get_channel_data(c)[frame..frame + RENDER_QUANTUM_SIZE].to_vec())
+                .collect();
+
+            RenderedQuantum {
+                index,
+                frame,
+                time: frame as f64 / sample_rate as f64,
+                channels,
+            }
+        })
+        .collect();
+
+    RenderedOutput {
+        sample_rate,
+        quanta,
+        render_duration,
+    }
+}
+
+/// Type of the node created by [`GraphOperation::Create`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuzzNodeType {
+    ConstantSource,
+    Oscillator,
+    Gain,
+    Delay,
+    ChannelSplitter,
+    ChannelMerger,
+}
+
+impl From<u8> for FuzzNodeType {
+    fn from(i: u8) -> Self {
+        match i % 6 {
+            0 => FuzzNodeType::ConstantSource,
+            1 => FuzzNodeType::Oscillator,
+            2 => FuzzNodeType::Gain,
+            3 => FuzzNodeType::Delay,
+            4 => FuzzNodeType::ChannelSplitter,
+            _ => FuzzNodeType::ChannelMerger,
+        }
+    }
+}
+
+/// Mutation of the audio graph, see [`run_graph_operations`]
+///
+/// Nodes are referred to by their index in the list of live nodes, wrapping around, so every
+/// operation is applicable. Destination indices start with the `AudioDestinationNode` at index
+/// 0. Operations that would be rejected by the API (e.g. stopping a source that was not started)
+/// are skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphOperation {
+    /// Create a new node
+    Create(FuzzNodeType),
+    /// Connect an output of a node to an input of a node
+    Connect {
+        from: usize,
+        to: usize,
+        output: usize,
+        input: usize,
+    },
+    /// Connect a node to the `AudioParam` of a node, if it has any
+    ConnectParam { from: usize, to: usize },
+    /// Disconnect all outgoing connections of a node
+    Disconnect { node: usize },
+    /// Disconnect a node from a given node, or its `AudioParam`
+    DisconnectDestination { from: usize, to: usize, param: bool },
+    /// Start a source node
+    Start { node: usize },
+    /// Stop a source node
+    Stop { node: usize },
+    /// Drop the handle of a node
+    ///
+    /// Playing sources are stopped and delay nodes are disconnected first, as they would
+    /// otherwise keep themselves (and a feedback loop they are part of) alive forever.
+    Drop { node: usize },
+    /// Render the given number of render quanta
+    Render { number_of_quanta: usize },
+}
+
+impl GraphOperation {
+    /// Decode a sequence of operations from arbitrary bytes, e.g. the input of a fuzzer
+    ///
+    /// Each operation is decoded from 4 bytes, trailing bytes are ignored.
+    pub fn decode(data: &[u8]) -> Vec<Self> {
+        data.chunks_exact(4)
+            .map(|c| {
+                let (a, b, d) = (c[1] as usize, c[2] as usize, c[3] as usize);
+                match c[0] % 9 {
+                    0 => GraphOperation::Create(FuzzNodeType::from(c[1])),
+                    1 => GraphOperation::Connect {
+                        from: a,
+                        to: b,
+                        output: d & 0x0F,
+                        input: d >> 4,
+                    },
+                    2 => GraphOperation::ConnectParam { from: a, to: b },
+                    3 => GraphOperation::Disconnect { node: a },
+                    4 => GraphOperation::DisconnectDestination {
+                        from: a,
+                        to: b,
+                        param: d & 1 == 1,
+                    },
+                    5 => GraphOperation::Start { node: a },
+                    6 => GraphOperation::Stop { node: a },
+                    7 => GraphOperation::Drop { node: a },
+                    _ => GraphOperation::Render {
+                        number_of_quanta: a % 4 + 1,
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+/// Inconsistency of the audio graph detected by [`run_graph_operations`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GraphConsistencyError {
+    /// Index of the operation after which the inconsistency was detected, equal to the number of
+    /// operations when it was detected after the final teardown
+    pub operation: usize,
+    /// Description of the inconsistency
+    pub message: String,
+}
+
+impl std::fmt::Display for GraphConsistencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "after operation {}: {}", self.operation, self.message)
+    }
+}
+
+impl std::error::Error for GraphConsistencyError {}
+
+enum FuzzNode {
+    ConstantSource(ConstantSourceNode),
+    Oscillator(OscillatorNode),
+    Gain(GainNode),
+    Delay(DelayNode),
+    ChannelSplitter(ChannelSplitterNode),
+    ChannelMerger(ChannelMergerNode),
+}
+
+impl FuzzNode {
+    fn new(context: &OfflineAudioContext, type_: FuzzNodeType) -> Self {
+        match type_ {
+            FuzzNodeType::ConstantSource => Self::ConstantSource(context.create_constant_source()),
+            FuzzNodeType::Oscillator => Self::Oscillator(context.create_oscillator()),
+            FuzzNodeType::Gain => Self::Gain(context.create_gain()),
+            FuzzNodeType::Delay => Self::Delay(context.create_delay(0.01)),
+            FuzzNodeType::ChannelSplitter => {
+                Self::ChannelSplitter(context.create_channel_splitter(2))
+            }
+            FuzzNodeType::ChannelMerger => Self::ChannelMerger(context.create_channel_merger(2)),
+        }
+    }
+
+    fn node(&self) -> &dyn AudioNode {
+        match self {
+            Self::ConstantSource(n) => n,
+            Self::Oscillator(n) => n,
+            Self::Gain(n) => n,
+            Self::Delay(n) => n,
+            Self::ChannelSplitter(n) => n,
+            Self::ChannelMerger(n) => n,
+        }
+    }
+
+    fn param(&self) -> Option<&AudioParam> {
+        match self {
+            Self::ConstantSource(n) => Some(n.offset()),
+            Self::Oscillator(n) => Some(n.frequency()),
+            Self::Gain(n) => Some(n.gain()),
+            Self::Delay(n) => Some(n.delay_time()),
+            Self::ChannelSplitter(_) | Self::ChannelMerger(_) => None,
+        }
+    }
+
+    fn is_source(&self) -> bool {
+        matches!(self, Self::ConstantSource(_) | Self::Oscillator(_))
+    }
+
+    fn start(&self) {
+        match self {
+            Self::ConstantSource(n) => n.start(),
+            Self::Oscillator(n) => n.start(),
+            _ => (),
+        }
+    }
+
+    fn stop(&self) {
+        match self {
+            Self::ConstantSource(n) => n.stop(),
+            Self::Oscillator(n) => n.stop(),
+            _ => (),
+        }
+    }
+}
+
+/// Destination of a connection in the model of [`GraphFuzzer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum FuzzTarget {
+    Destination,
+    Node(usize),
+    Param(usize),
+}
+
+struct FuzzSlot {
+    id: usize,
+    node: FuzzNode,
+    started: bool,
+    stopped: bool,
+}
+
+/// Applies [`GraphOperation`]s to an audio context, tracking which connections exist so only
+/// valid disconnections are issued
+struct GraphFuzzer {
+    context: OfflineAudioContext,
+    slots: Vec<FuzzSlot>,
+    next_id: usize,
+    connections: HashSet<(usize, FuzzTarget)>,
+}
+
+impl GraphFuzzer {
+    fn new() -> Self {
+        Self {
+            context: OfflineAudioContext::new(1, 0, 44100.),
+            slots: vec![],
+            next_id: 0,
+            connections: HashSet::new(),
+        }
+    }
+
+    fn slot(&self, index: usize) -> Option<&FuzzSlot> {
+        if self.slots.is_empty() {
+            None
+        } else {
+            Some(&self.slots[index % self.slots.len()])
+        }
+    }
+
+    /// Resolve a destination index, 0 being the destination node
+    fn target(&self, index: usize, param: bool) -> Option<FuzzTarget> {
+        let index = index % (self.slots.len() + 1);
+        if index == 0 {
+            return (!param).then_some(FuzzTarget::Destination);
+        }
+        let slot = &self.slots[index - 1];
+        if param {
+            slot.node.param().map(|_| FuzzTarget::Param(slot.id))
+        } else {
+            Some(FuzzTarget::Node(slot.id))
+        }
+    }
+
+    fn target_node(&self, target: FuzzTarget) -> Box<dyn AudioNode + '_> {
+        let node = |id| &self.slots.iter().find(|s| s.id == id).unwrap().node;
+        match target {
+            FuzzTarget::Destination => Box::new(self.context.destination()),
+            FuzzTarget::Node(id) => Box::new(NodeRef(node(id).node())),
+            FuzzTarget::Param(id) => Box::new(NodeRef(node(id).param().unwrap())),
+        }
+    }
+
+    fn apply(&mut self, operation: GraphOperation) {
+        match operation {
+            GraphOperation::Create(type_) => {
+                let node = FuzzNode::new(&self.context, type_);
+                self.slots.push(FuzzSlot {
+                    id: self.next_id,
+                    node,
+                    started: false,
+                    stopped: false,
+                });
+                self.next_id += 1;
+            }
+            GraphOperation::Connect {
+                from,
+                to,
+                output,
+                input,
+            } => self.connect(from, to, false, output, input),
+            GraphOperation::ConnectParam { from, to } => self.connect(from, to, true, 0, 0),
+            GraphOperation::Disconnect { node } => {
+                if let Some(slot) = self.slot(node) {
+                    slot.node.node().disconnect();
+                    let id = slot.id;
+                    self.connections.retain(|&(from, _)| from != id);
+                }
+            }
+            GraphOperation::DisconnectDestination { from, to, param } => {
+                let (from, target) = match (self.slot(from), self.target(to, param)) {
+                    (Some(slot), Some(target)) => (slot.id, target),
+                    _ => return,
+                };
+                if self.connections.remove(&(from, target)) {
+                    let dest = self.target_node(target);
+                    let slot = self.slots.iter().find(|s| s.id == from).unwrap();
+                    slot.node.node().disconnect_destination(&*dest);
+                }
+            }
+            GraphOperation::Start { node } => {
+                if !self.slots.is_empty() {
+                    let index = node % self.slots.len();
+                    let slot = &mut self.slots[index];
+                    if slot.node.is_source() && !slot.started {
+                        slot.node.start();
+                        slot.started = true;
+                    }
+                }
+            }
+            GraphOperation::Stop { node } => {
+                if !self.slots.is_empty() {
+                    let index = node % self.slots.len();
+                    let slot = &mut self.slots[index];
+                    if slot.node.is_source() && slot.started && !slot.stopped {
+                        slot.node.stop();
+                        slot.stopped = true;
+                    }
+                }
+            }
+            GraphOperation::Drop { node } => {
+                if !self.slots.is_empty() {
+                    let index = node % self.slots.len();
+                    let slot = self.slots.remove(index);
+                    if slot.node.is_source() && slot.started && !slot.stopped {
+                        slot.node.stop();
+                    }
+                    if let FuzzNode::Delay(delay) = &slot.node {
+                        delay.disconnect();
+                    }
+                    let id = slot.id;
+                    self.connections.retain(|&(from, target)| {
+                        from != id
+                            && target != FuzzTarget::Node(id)
+                            && target != FuzzTarget::Param(id)
+                    });
+                }
+            }
+            GraphOperation::Render { number_of_quanta } => {
+                self.context.render_quanta(number_of_quanta);
+            }
+        }
+    }
+
+    fn connect(&mut self, from: usize, to: usize, param: bool, output: usize, input: usize) {
+        let (from, target) = match (self.slot(from), self.target(to, param)) {
+            (Some(slot), Some(target)) => (slot.id, target),
+            _ => return,
+        };
+        let dest = self.target_node(target);
+        let slot = self.slots.iter().find(|s| s.id == from).unwrap();
+        if slot
+            .node
+            .node()
+            .try_connect_at(&*dest, output, input)
+            .is_ok()
+        {
+            drop(dest);
+            self.connections.insert((from, target));
+        }
+    }
+
+    /// Disconnect, stop and drop all nodes, and let the render thread clean up
+    fn teardown(&mut self) {
+        for slot in self.slots.iter() {
+            slot.node.node().disconnect();
+            // sources that were never started are freed without being stopped
+            if slot.node.is_source() && slot.started && !slot.stopped {
+                slot.node.stop();
+            }
+        }
+        self.slots.clear();
+        self.connections.clear();
+
+        // allow for the tail time of the delay nodes
+        self.context.render_quanta(8);
+    }
+}
+
+/// Wrapper to pass a borrowed node where an owned `dyn AudioNode` is expected
+struct NodeRef<'a, T: AudioNode + ?Sized>(&'a T);
+
+impl<'a, T: AudioNode + ?Sized> AudioNode for NodeRef<'a, T> {
+    fn registration(&self) -> &crate::context::AudioContextRegistration {
+        self.0.registration()
+    }
+
+    fn channel_config(&self) -> &crate::node::ChannelConfig {
+        self.0.channel_config()
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        self.0.number_of_inputs()
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        self.0.number_of_outputs()
+    }
+}
+
+/// Apply the given graph mutations and validate the internal consistency of the audio graph
+///
+/// After every operation, the render graph may not contain edges to removed nodes or
+/// non-existing ports, and it must contain all connections known to the control thread. After
+/// the last operation all nodes are disconnected, stopped and dropped, after which no node other
+/// than the destination and the listener may remain in the render graph.
+///
+/// # Errors
+///
+/// Returns the first inconsistency found
+///
+/// # Panics
+///
+/// A panic of this function indicates a bug in the graph handling code
+pub fn run_graph_operations(operations: &[GraphOperation]) -> Result<(), GraphConsistencyError> {
+    let mut fuzzer = GraphFuzzer::new();
+
+    for (index, &operation) in operations.iter().enumerate() {
+        fuzzer.apply(operation);
+        fuzzer
+            .context
+            .check_graph_consistency()
+            .map_err(|message| GraphConsistencyError {
+                operation: index,
+                message,
+            })?;
+    }
+
+    let error = |message| GraphConsistencyError {
+        operation: operations.len(),
+        message,
+    };
+
+    fuzzer.teardown();
+    let remaining = fuzzer.context.check_graph_consistency().map_err(error)?;
+    let mut leaked: Vec<_> = remaining
+        .into_iter()
+        .filter(|id| id.0 >= LISTENER_PARAM_IDS.end)
+        .collect();
+    if !leaked.is_empty() {
+        leaked.sort_by_key(|id| id.0);
+        return Err(error(format!("nodes {:?} were never freed", leaked)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::context::BaseAudioContext;
+    use crate::node::{AudioNode, AudioScheduledSourceNode, NoiseNode, NoiseOptions};
+
+    fn noise_graph(context: &OfflineAudioContext, seeds: &mut SeedSequence) {
+        for _ in 0..2 {
+            let noise = NoiseNode::new(
+                context,
+                NoiseOptions {
+                    seed: Some(seeds.next_seed()),
+                    ..NoiseOptions::default()
+                },
+            );
+            noise.connect(&context.destination());
+            noise.start();
+        }
+    }
+
+    #[test]
+    fn test_render_metadata() {
+        let options = RenderOptions {
+            number_of_channels: 2,
+            number_of_quanta: 3,
+            sample_rate: 48000.,
+            seed: 0,
+        };
+
+        let output = render(options, |context, _| {
+            let src = context.create_constant_source();
+            src.connect(&context.destination());
+            src.start_at(128. / 48000.);
+        });
+
+        assert_eq!(output.sample_rate, 48000.);
+        assert_eq!(output.number_of_channels(), 2);
+        assert_eq!(output.quanta.len(), 3);
+        for (i, quantum) in output.quanta.iter().enumerate() {
+            assert_eq!(quantum.index, i);
+            assert_eq!(quantum.frame, i * RENDER_QUANTUM_SIZE);
+            assert_eq!(quantum.time, (i * RENDER_QUANTUM_SIZE) as f64 / 48000.);
+        }
+
+        assert_eq!(output.quanta[0].channels[0], vec![0.; RENDER_QUANTUM_SIZE]);
+        assert_eq!(output.quanta[1].channels[1], vec![1.; RENDER_QUANTUM_SIZE]);
+        assert_eq!(output.channel_data(0).len(), 3 * RENDER_QUANTUM_SIZE);
+
+        let buffer = output.to_audio_buffer();
+        assert_eq!(buffer.number_of_channels(), 2);
+        assert_eq!(buffer.length(), 3 * RENDER_QUANTUM_SIZE);
+        assert_eq!(buffer.get_channel_data(1), &output.channel_data(1)[..]);
+    }
+
+    #[test]
+    fn test_render_is_deterministic() {
+        let options = RenderOptions {
+            number_of_quanta: 4,
+            seed: 7,
+            ..RenderOptions::default()
+        };
+
+        let a = render(options.clone(), noise_graph);
+        let b = render(options.clone(), noise_graph);
+        assert_eq!(a.first_mismatch(&b), None);
+
+        let c = render(
+            RenderOptions {
+                seed: 8,
+                ..options.clone()
+            },
+            noise_graph,
+        );
+        let mismatch = c.first_mismatch(&a).unwrap();
+        assert_eq!((mismatch.quantum, mismatch.channel), (0, 0));
+
+        let d = render(
+            RenderOptions {
+                number_of_quanta: 5,
+                ..options
+            },
+            noise_graph,
+        );
+        let mismatch = d.first_mismatch(&a).unwrap();
+        assert_eq!(mismatch.quantum, 4);
+        assert_eq!(mismatch.expected, None);
+        assert!(mismatch.actual.is_some());
+    }
+
+    #[test]
+    fn test_graph_operations_audio_param() {
+        // connect a source to the param of a node, and drop that node
+        let operations = [
+            GraphOperation::Create(FuzzNodeType::ConstantSource),
+            GraphOperation::Create(FuzzNodeType::Gain),
+            GraphOperation::Start { node: 0 },
+            GraphOperation::ConnectParam { from: 0, to: 2 },
+            GraphOperation::Render {
+                number_of_quanta: 1,
+            },
+            GraphOperation::Drop { node: 1 },
+            GraphOperation::Render {
+                number_of_quanta: 2,
+            },
+        ];
+        assert_eq!(run_graph_operations(&operations), Ok(()));
+    }
+
+    #[test]
+    fn test_graph_operations_cycle() {
+        let operations = [
+            GraphOperation::Create(FuzzNodeType::Gain),
+            GraphOperation::Create(FuzzNodeType::Delay),
+            GraphOperation::Connect {
+                from: 0,
+                to: 2,
+                output: 0,
+                input: 0,
+            },
+            GraphOperation::Connect {
+                from: 1,
+                to: 1,
+                output: 0,
+                input: 0,
+            },
+            GraphOperation::Render {
+                number_of_quanta: 1,
+            },
+            GraphOperation::DisconnectDestination {
+                from: 1,
+                to: 1,
+                param: false,
+            },
+            GraphOperation::Drop { node: 0 },
+            GraphOperation::Drop { node: 0 },
+        ];
+        assert_eq!(run_graph_operations(&operations), Ok(()));
+    }
+
+    #[test]
+    fn test_graph_operations_freed() {
+        // source that is never started
+        let operations = [
+            GraphOperation::Create(FuzzNodeType::Oscillator),
+            GraphOperation::Drop { node: 0 },
+        ];
+        assert_eq!(run_graph_operations(&operations), Ok(()));
+
+        // node without connections that is created after the first render
+        let operations = [
+            GraphOperation::Render {
+                number_of_quanta: 1,
+            },
+            GraphOperation::Create(FuzzNodeType::ChannelSplitter),
+            GraphOperation::Drop { node: 0 },
+        ];
+        assert_eq!(run_graph_operations(&operations), Ok(()));
+
+        // muted cycle through an AudioParam
+        let operations = [
+            GraphOperation::Create(FuzzNodeType::Gain),
+            GraphOperation::ConnectParam { from: 0, to: 0 },
+            GraphOperation::Drop { node: 0 },
+        ];
+        assert_eq!(run_graph_operations(&operations), Ok(()));
+    }
+
+    #[test]
+    fn test_graph_operations_random() {
+        let mut seeds = SeedSequence::new(0);
+        for _ in 0..200 {
+            let data: Vec<u8> = (0..32)
+                .flat_map(|_| seeds.next_seed().to_le_bytes())
+                .collect();
+            let operations = GraphOperation::decode(&data);
+            assert_eq!(operations.len(), 64);
+            if let Err(e) = run_graph_operations(&operations) {
+                panic!("{} in {:?}", e, operations);
+            }
+        }
+    }
+}
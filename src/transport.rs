@@ -0,0 +1,746 @@
+//! Musical transport and look-ahead scheduling
+//!
+//! A [`Transport`] maps musical time, expressed in beats, to the time of an
+//! audio context, taking into account tempo changes and swing. It can be used
+//! to schedule sources and automation on a musical grid.
+//!
+//! A [`LookAheadScheduler`] periodically invokes a callback from a control
+//! thread to schedule the events of the upcoming time window, so long
+//! sequences do not have to be scheduled all at once.
+//!
+//! ```no_run
+//! use web_audio_api::context::{BaseAudioContext, AudioContext};
+//! use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+//! use web_audio_api::transport::{Transport, TransportOptions};
+//!
+//! let context = AudioContext::default();
+//!
+//! let transport = Transport::new(TransportOptions {
+//!     bpm: 96.,
+//!     start_time: context.current_time() + 0.1,
+//!     ..TransportOptions::default()
+//! });
+//!
+//! // play a short blip on each beat of the first 4 bars
+//! for beat in 0..16 {
+//!     let osc = context.create_oscillator();
+//!     osc.connect(&context.destination());
+//!     transport.start_at_beat(&osc, beat as f64);
+//!     transport.stop_at_beat(&osc, beat as f64 + 0.25);
+//! }
+//! ```
+
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{RecvTimeoutError, Sender};
+
+use crate::context::{BaseAudioContext, ConcreteBaseAudioContext};
+use crate::node::AudioScheduledSourceNode;
+use crate::AudioParam;
+
+/// Time signature of a [`Transport`], e.g. 3/4 or 6/8
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeSignature {
+    /// Number of beats in a bar
+    pub beats_per_bar: u32,
+    /// Note value of a beat, e.g. 4 for a quarter note
+    pub beat_unit: u32,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        Self {
+            beats_per_bar: 4,
+            beat_unit: 4,
+        }
+    }
+}
+
+/// Options for constructing a [`Transport`]
+#[derive(Clone, Debug)]
+pub struct TransportOptions {
+    /// Initial tempo, in beats per minute
+    pub bpm: f64,
+    /// Time signature
+    pub time_signature: TimeSignature,
+    /// Amount of swing, in the [0, 1) range, 0 meaning straight timing
+    pub swing: f64,
+    /// Length (in beats) of the notes affected by swing, e.g. 0.5 for eighth
+    /// notes in a 4/4 time signature
+    pub swing_subdivision: f64,
+    /// Context time of the first beat
+    pub start_time: f64,
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
+        Self {
+            bpm: 120.,
+            time_signature: TimeSignature::default(),
+            swing: 0.,
+            swing_subdivision: 0.5,
+            start_time: 0.,
+        }
+    }
+}
+
+/// Position in a bar based representation of musical time
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BarsBeats {
+    /// Bar number, starting from 0
+    pub bar: u32,
+    /// Beat within the bar, starting from 0, may be fractional
+    pub beat: f64,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TempoChange {
+    beat: f64,
+    bpm: f64,
+    /// Context time of `beat`, relative to the transport start time
+    time: f64,
+}
+
+#[track_caller]
+#[inline(always)]
+fn assert_valid_bpm(bpm: f64) {
+    if !bpm.is_finite() || bpm <= 0. {
+        panic!("RangeError - Invalid bpm: {:?} should be positive", bpm);
+    }
+}
+
+#[track_caller]
+#[inline(always)]
+fn assert_valid_swing(swing: f64) {
+    if !(0. ..1.).contains(&swing) {
+        panic!(
+            "RangeError - Invalid swing: {:?} should be in the [0, 1) range",
+            swing
+        );
+    }
+}
+
+/// Tempo aware clock converting musical time to context time
+///
+/// Musical time is expressed in beats, starting from 0 at `start_time`. Tempo
+/// changes are instantaneous and happen on a given beat. Swing delays every
+/// other `swing_subdivision` note: with a swing of 1/3, eighth notes are
+/// played with a triplet feel.
+///
+/// The transport only computes times, sources and parameters are scheduled
+/// with the regular `start_at` and `*_at_time` methods. Hence tempo changes do
+/// not affect events that are already scheduled.
+#[derive(Clone, Debug)]
+pub struct Transport {
+    start_time: f64,
+    time_signature: TimeSignature,
+    swing: f64,
+    swing_subdivision: f64,
+    /// Tempo map, sorted by beat, the first entry being at beat 0
+    tempo_changes: Vec<TempoChange>,
+}
+
+impl Transport {
+    /// # Panics
+    ///
+    /// This function panics if:
+    /// - `bpm` is not strictly positive
+    /// - `swing` is not in the [0, 1) range
+    /// - `swing_subdivision` is not strictly positive
+    /// - the time signature contains a zero
+    pub fn new(options: TransportOptions) -> Self {
+        let TransportOptions {
+            bpm,
+            time_signature,
+            swing,
+            swing_subdivision,
+            start_time,
+        } = options;
+
+        assert_valid_bpm(bpm);
+        assert_valid_swing(swing);
+        assert!(
+            swing_subdivision.is_finite() && swing_subdivision > 0.,
+            "RangeError - Invalid swing subdivision: {:?} should be positive",
+            swing_subdivision
+        );
+        assert!(
+            time_signature.beats_per_bar > 0 && time_signature.beat_unit > 0,
+            "RangeError - Invalid time signature: {:?}",
+            time_signature
+        );
+
+        Self {
+            start_time,
+            time_signature,
+            swing,
+            swing_subdivision,
+            tempo_changes: vec![TempoChange {
+                beat: 0.,
+                bpm,
+                time: 0.,
+            }],
+        }
+    }
+
+    /// Context time of the first beat
+    pub fn start_time(&self) -> f64 {
+        self.start_time
+    }
+
+    /// Move the transport, so the first beat happens at the given context time
+    pub fn set_start_time(&mut self, start_time: f64) {
+        self.start_time = start_time;
+    }
+
+    pub fn time_signature(&self) -> TimeSignature {
+        self.time_signature
+    }
+
+    pub fn swing(&self) -> f64 {
+        self.swing
+    }
+
+    /// # Panics
+    ///
+    /// This function panics if `swing` is not in the [0, 1) range
+    pub fn set_swing(&mut self, swing: f64) {
+        assert_valid_swing(swing);
+        self.swing = swing;
+    }
+
+    /// Tempo, in beats per minute, at the given beat
+    pub fn bpm_at_beat(&self, beat: f64) -> f64 {
+        self.tempo_change_at_beat(beat).bpm
+    }
+
+    /// Change the tempo from the given beat onwards
+    ///
+    /// Tempo changes scheduled at later beats are kept.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `bpm` is not strictly positive or if `beat` is
+    /// negative
+    pub fn set_bpm_at_beat(&mut self, bpm: f64, beat: f64) {
+        assert_valid_bpm(bpm);
+        assert!(
+            beat.is_finite() && beat >= 0.,
+            "RangeError - Invalid beat: {:?} should be positive",
+            beat
+        );
+
+        match self
+            .tempo_changes
+            .iter()
+            .position(|change| change.beat >= beat)
+        {
+            Some(index) if self.tempo_changes[index].beat == beat => {
+                self.tempo_changes[index].bpm = bpm
+            }
+            Some(index) => self.tempo_changes.insert(
+                index,
+                TempoChange {
+                    beat,
+                    bpm,
+                    time: 0.,
+                },
+            ),
+            None => self.tempo_changes.push(TempoChange {
+                beat,
+                bpm,
+                time: 0.,
+            }),
+        }
+
+        // recompute the time of all tempo changes
+        let mut time = 0.;
+        let mut previous: Option<TempoChange> = None;
+        self.tempo_changes.iter_mut().for_each(|change| {
+            if let Some(previous) = previous {
+                time += (change.beat - previous.beat) * 60. / previous.bpm;
+            }
+            change.time = time;
+            previous = Some(*change);
+        });
+    }
+
+    fn tempo_change_at_beat(&self, beat: f64) -> &TempoChange {
+        let index = self
+            .tempo_changes
+            .partition_point(|change| change.beat <= beat)
+            .max(1);
+        &self.tempo_changes[index - 1]
+    }
+
+    /// Apply the swing to a straight beat position
+    fn swing_beat(&self, beat: f64) -> f64 {
+        if self.swing == 0. {
+            return beat;
+        }
+
+        let s = self.swing_subdivision;
+        let pair_start = (beat / (2. * s)).floor() * 2. * s;
+        let x = beat - pair_start;
+
+        let swung = if x < s {
+            x * (1. + self.swing)
+        } else {
+            (1. + self.swing) * s + (x - s) * (1. - self.swing)
+        };
+
+        pair_start + swung
+    }
+
+    /// Remove the swing from a swung beat position
+    fn unswing_beat(&self, beat: f64) -> f64 {
+        if self.swing == 0. {
+            return beat;
+        }
+
+        let s = self.swing_subdivision;
+        let pair_start = (beat / (2. * s)).floor() * 2. * s;
+        let x = beat - pair_start;
+        let offbeat = (1. + self.swing) * s;
+
+        let straight = if x < offbeat {
+            x / (1. + self.swing)
+        } else {
+            s + (x - offbeat) / (1. - self.swing)
+        };
+
+        pair_start + straight
+    }
+
+    /// Convert a (straight) beat position to a context time
+    pub fn beat_to_time(&self, beat: f64) -> f64 {
+        let beat = self.swing_beat(beat);
+        let change = self.tempo_change_at_beat(beat);
+        self.start_time + change.time + (beat - change.beat) * 60. / change.bpm
+    }
+
+    /// Convert a context time to a (straight) beat position
+    pub fn time_to_beat(&self, time: f64) -> f64 {
+        let time = time - self.start_time;
+        let index = self
+            .tempo_changes
+            .partition_point(|change| change.time <= time)
+            .max(1);
+        let change = &self.tempo_changes[index - 1];
+        let beat = change.beat + (time - change.time) * change.bpm / 60.;
+        self.unswing_beat(beat)
+    }
+
+    /// Convert a bar and beat position to a beat position
+    pub fn bars_beats_to_beat(&self, position: BarsBeats) -> f64 {
+        f64::from(position.bar) * f64::from(self.time_signature.beats_per_bar) + position.beat
+    }
+
+    /// Convert a beat position to a bar and beat position
+    ///
+    /// Negative beat positions are clamped to 0.
+    pub fn beat_to_bars_beats(&self, beat: f64) -> BarsBeats {
+        let beats_per_bar = f64::from(self.time_signature.beats_per_bar);
+        let beat = beat.max(0.);
+        let bar = (beat / beats_per_bar).floor();
+
+        BarsBeats {
+            bar: bar as u32,
+            beat: beat - bar * beats_per_bar,
+        }
+    }
+
+    /// Context time of the given bar and beat position
+    pub fn bars_beats_to_time(&self, position: BarsBeats) -> f64 {
+        self.beat_to_time(self.bars_beats_to_beat(position))
+    }
+
+    /// First beat position on a grid of `division` beats that happens at or
+    /// after the given context time
+    ///
+    /// This is typically used to quantize an event on the next beat (division
+    /// of 1) or on the next bar (division of the number of beats per bar).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `division` is not strictly positive
+    pub fn next_grid_beat(&self, time: f64, division: f64) -> f64 {
+        assert!(
+            division.is_finite() && division > 0.,
+            "RangeError - Invalid grid division: {:?} should be positive",
+            division
+        );
+
+        let beat = self.time_to_beat(time).max(0.);
+        let grid_beat = (beat / division).ceil() * division;
+
+        // guard against rounding errors of the time to beat conversion
+        if grid_beat - division >= 0. && self.beat_to_time(grid_beat - division) >= time {
+            grid_beat - division
+        } else {
+            grid_beat
+        }
+    }
+
+    /// Schedule the start of a source on the given beat
+    pub fn start_at_beat<N: AudioScheduledSourceNode>(&self, node: &N, beat: f64) {
+        node.start_at(self.beat_to_time(beat));
+    }
+
+    /// Schedule the stop of a source on the given beat
+    pub fn stop_at_beat<N: AudioScheduledSourceNode>(&self, node: &N, beat: f64) {
+        node.stop_at(self.beat_to_time(beat));
+    }
+
+    /// Schedule a parameter change on the given beat
+    ///
+    /// # Panics
+    ///
+    /// See [`AudioParam::set_value_at_time`]
+    pub fn set_value_at_beat<'a>(
+        &self,
+        param: &'a AudioParam,
+        value: f32,
+        beat: f64,
+    ) -> &'a AudioParam {
+        param.set_value_at_time(value, self.beat_to_time(beat))
+    }
+
+    /// Schedule a linear ramp of a parameter ending on the given beat
+    ///
+    /// # Panics
+    ///
+    /// See [`AudioParam::linear_ramp_to_value_at_time`]
+    pub fn linear_ramp_to_value_at_beat<'a>(
+        &self,
+        param: &'a AudioParam,
+        value: f32,
+        beat: f64,
+    ) -> &'a AudioParam {
+        param.linear_ramp_to_value_at_time(value, self.beat_to_time(beat))
+    }
+
+    /// Schedule an exponential ramp of a parameter ending on the given beat
+    ///
+    /// # Panics
+    ///
+    /// See [`AudioParam::exponential_ramp_to_value_at_time`]
+    pub fn exponential_ramp_to_value_at_beat<'a>(
+        &self,
+        param: &'a AudioParam,
+        value: f32,
+        beat: f64,
+    ) -> &'a AudioParam {
+        param.exponential_ramp_to_value_at_time(value, self.beat_to_time(beat))
+    }
+}
+
+/// Options for constructing a [`LookAheadScheduler`]
+#[derive(Clone, Debug)]
+pub struct LookAheadSchedulerOptions {
+    /// Wake up interval of the scheduler thread
+    pub interval: Duration,
+    /// Duration (in seconds) of context time scheduled ahead of `current_time`
+    ///
+    /// This should be larger than `interval` plus the worst case delay of the
+    /// scheduler thread, otherwise events may be scheduled too late.
+    pub look_ahead: f64,
+}
+
+impl Default for LookAheadSchedulerOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(25),
+            look_ahead: 0.1,
+        }
+    }
+}
+
+/// Control thread scheduler invoking a callback ahead of the context time
+///
+/// This implements the "tale of two clocks" pattern: a thread wakes up every
+/// `interval` and invokes the callback with the window of context time that
+/// should be scheduled, up to `current_time + look_ahead`. Successive windows
+/// are contiguous, i.e. the start of a window equals the end of the previous
+/// one, so no event is missed or scheduled twice when the thread is late.
+///
+/// The wake up times are derived from the creation time of the scheduler
+/// rather than from the previous wake up, so the thread does not drift. The
+/// windows follow `current_time`: when the context is suspended, the callback
+/// is not invoked until the context time progresses again.
+///
+/// The scheduler stops when dropped.
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::transport::{LookAheadScheduler, LookAheadSchedulerOptions};
+///
+/// let context = AudioContext::default();
+///
+/// // play a blip every 250ms, forever
+/// let mut next_blip = context.current_time();
+/// let scheduler = LookAheadScheduler::new(
+///     &context,
+///     LookAheadSchedulerOptions::default(),
+///     move |context, _start, end| {
+///         while next_blip < end {
+///             let osc = context.create_oscillator();
+///             osc.connect(&context.destination());
+///             osc.start_at(next_blip);
+///             osc.stop_at(next_blip + 0.05);
+///             next_blip += 0.25;
+///         }
+///     },
+/// );
+///
+/// std::thread::sleep(std::time::Duration::from_secs(4));
+/// drop(scheduler);
+/// ```
+pub struct LookAheadScheduler {
+    /// Dropping the sender signals the thread to exit
+    stop_sender: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for LookAheadScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LookAheadScheduler")
+            .field("running", &self.stop_sender.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl LookAheadScheduler {
+    /// Start a scheduler thread invoking `callback` with the context and the
+    /// `start` and `end` context times of the window to schedule
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `interval` is zero or if `look_ahead` is not
+    /// strictly positive
+    pub fn new<C, F>(context: &C, options: LookAheadSchedulerOptions, mut callback: F) -> Self
+    where
+        C: BaseAudioContext,
+        F: FnMut(&ConcreteBaseAudioContext, f64, f64) + Send + 'static,
+    {
+        let LookAheadSchedulerOptions {
+            interval,
+            look_ahead,
+        } = options;
+
+        assert!(
+            !interval.is_zero(),
+            "RangeError - Invalid interval: should be positive"
+        );
+        assert!(
+            look_ahead.is_finite() && look_ahead > 0.,
+            "RangeError - Invalid look ahead: {:?} should be positive",
+            look_ahead
+        );
+
+        let context = context.base().clone();
+        let (stop_sender, stop_receiver) = crossbeam_channel::bounded::<()>(0);
+
+        let handle = std::thread::spawn(move || {
+            let epoch = Instant::now();
+            let mut tick: u32 = 0;
+            let mut scheduled_until = context.current_time();
+
+            loop {
+                let end = context.current_time() + look_ahead;
+                if end > scheduled_until {
+                    callback(&context, scheduled_until, end);
+                    scheduled_until = end;
+                }
+
+                // compute the next wake up time from the epoch to avoid drift,
+                // skipping the ticks that were missed
+                let elapsed = epoch.elapsed();
+                tick = tick.max((elapsed.as_nanos() / interval.as_nanos()) as u32) + 1;
+                let deadline = epoch + interval * tick;
+                let timeout = deadline.saturating_duration_since(Instant::now());
+
+                match stop_receiver.recv_timeout(timeout) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            }
+        });
+
+        Self {
+            stop_sender: Some(stop_sender),
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop the scheduler, waiting for a running callback to return
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        drop(self.stop_sender.take());
+        if let Some(handle) = self.handle.take() {
+            // a panic of the callback has already been reported
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for LookAheadScheduler {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioNode;
+
+    use super::*;
+
+    #[test]
+    fn test_constant_tempo() {
+        let transport = Transport::new(TransportOptions {
+            bpm: 120.,
+            start_time: 1.,
+            ..TransportOptions::default()
+        });
+
+        assert_float_eq!(transport.beat_to_time(0.), 1., abs <= 1e-12);
+        assert_float_eq!(transport.beat_to_time(1.), 1.5, abs <= 1e-12);
+        assert_float_eq!(transport.beat_to_time(8.), 5., abs <= 1e-12);
+        assert_float_eq!(transport.time_to_beat(5.), 8., abs <= 1e-12);
+
+        let position = BarsBeats { bar: 2, beat: 1.5 };
+        assert_float_eq!(transport.bars_beats_to_beat(position), 9.5, abs <= 0.);
+        assert_eq!(transport.beat_to_bars_beats(9.5), position);
+        assert_float_eq!(transport.bars_beats_to_time(position), 5.75, abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_tempo_changes() {
+        let mut transport = Transport::new(TransportOptions::default());
+        // 4 beats at 120 bpm, then 60 bpm
+        transport.set_bpm_at_beat(60., 4.);
+
+        assert_float_eq!(transport.bpm_at_beat(3.9), 120., abs <= 0.);
+        assert_float_eq!(transport.bpm_at_beat(4.), 60., abs <= 0.);
+        assert_float_eq!(transport.beat_to_time(4.), 2., abs <= 1e-12);
+        assert_float_eq!(transport.beat_to_time(6.), 4., abs <= 1e-12);
+        assert_float_eq!(transport.time_to_beat(4.), 6., abs <= 1e-12);
+
+        // insert a change before the existing one, 240 bpm from beat 2
+        transport.set_bpm_at_beat(240., 2.);
+        assert_float_eq!(transport.beat_to_time(4.), 1.5, abs <= 1e-12);
+        assert_float_eq!(transport.beat_to_time(6.), 3.5, abs <= 1e-12);
+        assert_float_eq!(transport.time_to_beat(3.5), 6., abs <= 1e-12);
+    }
+
+    #[test]
+    fn test_swing() {
+        let transport = Transport::new(TransportOptions {
+            bpm: 60.,
+            swing: 1. / 3.,
+            ..TransportOptions::default()
+        });
+
+        // beats are unaffected
+        assert_float_eq!(transport.beat_to_time(1.), 1., abs <= 1e-12);
+        // off-beat eighth notes have a triplet feel
+        assert_float_eq!(transport.beat_to_time(0.5), 2. / 3., abs <= 1e-12);
+        assert_float_eq!(transport.beat_to_time(1.5), 1. + 2. / 3., abs <= 1e-12);
+
+        for beat in [0.25, 0.5, 0.75, 3.1] {
+            let time = transport.beat_to_time(beat);
+            assert_float_eq!(transport.time_to_beat(time), beat, abs <= 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_next_grid_beat() {
+        let transport = Transport::new(TransportOptions::default());
+
+        assert_float_eq!(transport.next_grid_beat(0., 1.), 0., abs <= 0.);
+        assert_float_eq!(transport.next_grid_beat(0.1, 1.), 1., abs <= 0.);
+        assert_float_eq!(transport.next_grid_beat(0.5, 1.), 1., abs <= 0.);
+        assert_float_eq!(transport.next_grid_beat(0.6, 4.), 4., abs <= 0.);
+        assert_float_eq!(transport.next_grid_beat(2.1, 4.), 8., abs <= 0.);
+    }
+
+    #[test]
+    fn test_schedule_on_beats() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 48000, sample_rate);
+
+        let transport = Transport::new(TransportOptions {
+            bpm: 240.,
+            ..TransportOptions::default()
+        });
+
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+        transport.start_at_beat(&src, 1.);
+        transport.stop_at_beat(&src, 3.);
+        transport.set_value_at_beat(src.offset(), 0.5, 2.);
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // a beat lasts 12000 frames
+        assert_float_eq!(channel[11999], 0., abs <= 0.);
+        assert_float_eq!(channel[12000], 1., abs <= 0.);
+        assert_float_eq!(channel[23999], 1., abs <= 0.);
+        assert_float_eq!(channel[24000], 0.5, abs <= 0.);
+        assert_float_eq!(channel[35999], 0.5, abs <= 0.);
+        assert_float_eq!(channel[36000], 0., abs <= 0.);
+    }
+
+    #[test]
+    fn test_look_ahead_scheduler() {
+        use std::sync::{Arc, Mutex};
+
+        let context = OfflineAudioContext::new(1, 48000 * 10, 48000.);
+        let windows = Arc::new(Mutex::new(Vec::new()));
+
+        let options = LookAheadSchedulerOptions {
+            interval: Duration::from_millis(1),
+            look_ahead: 0.1,
+        };
+        let windows_clone = Arc::clone(&windows);
+        let scheduler = LookAheadScheduler::new(&context, options, move |_, start, end| {
+            windows_clone.lock().unwrap().push((start, end));
+        });
+
+        // the context time does not progress before rendering
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(&*windows.lock().unwrap(), &[(0., 0.1)]);
+
+        let _ = context.start_rendering_sync();
+        std::thread::sleep(Duration::from_millis(20));
+        scheduler.stop();
+
+        let windows = windows.lock().unwrap();
+        assert!(windows.len() > 1);
+        // windows are contiguous
+        windows.windows(2).for_each(|w| assert_eq!(w[0].1, w[1].0));
+        // the last window reaches beyond the end of the rendering
+        assert_float_eq!(windows.last().unwrap().1, 10.1, abs <= 1e-9);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_bpm() {
+        let _ = Transport::new(TransportOptions {
+            bpm: 0.,
+            ..TransportOptions::default()
+        });
+    }
+}
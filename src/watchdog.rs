@@ -0,0 +1,166 @@
+//! Detection of and response to persistent render thread overload
+use crate::Event;
+
+/// Options for the render thread watchdog of an [`AudioContext`](crate::context::AudioContext)
+///
+/// A render callback misses its deadline when it takes longer to render than to play out. A
+/// single miss produces a short glitch, the watchdog only intervenes when several deadlines are
+/// missed in a row and degrades the rendering instead of producing continuous crackling.
+#[derive(Clone, Debug)]
+pub struct WatchdogOptions {
+    /// Number of consecutive missed deadlines before the watchdog intervenes. When the overload
+    /// persists, the watchdog intervenes again after the same number of missed deadlines.
+    pub max_missed_deadlines: usize,
+    /// Number of consecutive render callbacks meeting their deadline before the degradations
+    /// are lifted
+    pub recover_after: usize,
+    /// Release the effect nodes that are only kept alive to render their tail, e.g. a reverb
+    /// whose handle was dropped and whose input has ended
+    pub drop_tails: bool,
+    /// Bypass the most expensive node with a single input and output on each intervention, its
+    /// input is passed through unprocessed
+    pub bypass_heavy_nodes: bool,
+}
+
+impl Default for WatchdogOptions {
+    fn default() -> Self {
+        Self {
+            max_missed_deadlines: 8,
+            recover_after: 400,
+            drop_tails: true,
+            bypass_heavy_nodes: false,
+        }
+    }
+}
+
+/// Notification of an intervention of the watchdog, see [`WatchdogOptions`]
+///
+/// Increasing the buffer size is up to the application, e.g. by creating a new context with a
+/// higher [`latency_hint`](crate::context::AudioContextOptions::latency_hint) when the overload
+/// keeps coming back.
+#[derive(Clone, Debug)]
+pub struct OverloadEvent {
+    /// The time of the intervention in terms of the context's currentTime
+    pub timestamp: f64,
+    /// Load value of the last render callback, see
+    /// [`AudioRenderCapacity`](crate::AudioRenderCapacity)
+    pub load: f64,
+    /// `true` when the rendering is degraded, `false` when the degradations are lifted
+    pub degraded: bool,
+    /// Number of nodes bypassed by the watchdog
+    pub bypassed_nodes: usize,
+    /// Inherits from this base Event
+    pub event: Event,
+}
+
+impl OverloadEvent {
+    pub(crate) fn new(timestamp: f64, load: f64, degraded: bool, bypassed_nodes: usize) -> Self {
+        Self {
+            timestamp,
+            load,
+            degraded,
+            bypassed_nodes,
+            event: Event {
+                type_: "OverloadEvent",
+            },
+        }
+    }
+}
+
+/// Response of the watchdog to the load of a render callback
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum WatchdogAction {
+    None,
+    Degrade,
+    Recover,
+}
+
+/// Render side state of the watchdog
+pub(crate) struct Watchdog {
+    options: WatchdogOptions,
+    /// Consecutive missed deadlines
+    missed: usize,
+    /// Consecutive met deadlines while degraded
+    met: usize,
+    degraded: bool,
+}
+
+impl Watchdog {
+    pub fn new(options: WatchdogOptions) -> Self {
+        Self {
+            options,
+            missed: 0,
+            met: 0,
+            degraded: false,
+        }
+    }
+
+    pub fn options(&self) -> &WatchdogOptions {
+        &self.options
+    }
+
+    /// Register the load of a render callback, a load above 1 means its deadline was missed
+    pub fn tick(&mut self, load: f64) -> WatchdogAction {
+        if load > 1. {
+            self.met = 0;
+            self.missed += 1;
+
+            if self.missed >= self.options.max_missed_deadlines {
+                self.missed = 0;
+                self.degraded = true;
+                return WatchdogAction::Degrade;
+            }
+        } else {
+            self.missed = 0;
+
+            if self.degraded {
+                self.met += 1;
+
+                if self.met >= self.options.recover_after {
+                    self.met = 0;
+                    self.degraded = false;
+                    return WatchdogAction::Recover;
+                }
+            }
+        }
+
+        WatchdogAction::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degrade_and_recover() {
+        let options = WatchdogOptions {
+            max_missed_deadlines: 3,
+            recover_after: 2,
+            ..WatchdogOptions::default()
+        };
+        let mut watchdog = Watchdog::new(options);
+
+        // isolated misses are tolerated
+        assert_eq!(watchdog.tick(1.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(1.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(0.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(1.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(1.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(1.5), WatchdogAction::Degrade);
+
+        // persistent overload escalates
+        assert_eq!(watchdog.tick(1.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(1.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(1.5), WatchdogAction::Degrade);
+
+        assert_eq!(watchdog.tick(0.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(1.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(0.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(0.5), WatchdogAction::Recover);
+
+        // not degraded anymore
+        assert_eq!(watchdog.tick(0.5), WatchdogAction::None);
+        assert_eq!(watchdog.tick(0.5), WatchdogAction::None);
+    }
+}
@@ -0,0 +1,448 @@
+//! Minimal built-in WAV codec for [`AudioBuffer`]
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::{Read, Write};
+
+use crate::buffer::AudioBuffer;
+use crate::MAX_CHANNELS;
+
+const WAVE_FORMAT_PCM: u16 = 1;
+pub(crate) const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+/// Size of the `fmt ` chunk of the extensible format, the largest one in use
+const MAX_FMT_CHUNK_SIZE: u32 = 40;
+
+/// Sample format of the data written by [`AudioBuffer::to_wav`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WavSampleFormat {
+    /// 16 bits signed integer PCM
+    Int16,
+    /// 24 bits signed integer PCM
+    Int24,
+    /// 32 bits signed integer PCM
+    Int32,
+    /// 32 bits IEEE floating point
+    Float32,
+}
+
+impl WavSampleFormat {
+    pub(crate) fn bits_per_sample(self) -> u16 {
+        match self {
+            Self::Int16 => 16,
+            Self::Int24 => 24,
+            Self::Int32 | Self::Float32 => 32,
+        }
+    }
+
+    /// Append the little-endian encoding of `sample` to `output`
+    pub(crate) fn encode_sample(self, sample: f32, output: &mut Vec<u8>) {
+        match self {
+            Self::Int16 => {
+                let value = (sample * 32768.).round().clamp(-32768., 32767.) as i16;
+                output.extend_from_slice(&value.to_le_bytes());
+            }
+            Self::Int24 => {
+                let value = (sample * 8_388_608.).round().clamp(-8_388_608., 8_388_607.) as i32;
+                output.extend_from_slice(&value.to_le_bytes()[..3]);
+            }
+            Self::Int32 => {
+                let value = (f64::from(sample) * 2_147_483_648.).round();
+                // saturating cast
+                output.extend_from_slice(&(value as i32).to_le_bytes());
+            }
+            Self::Float32 => output.extend_from_slice(&sample.to_le_bytes()),
+        }
+    }
+}
+
+/// Write the 44 bytes header of a WAV file with `data_size` bytes of samples
+///
+/// An odd sized data chunk must be followed by a pad byte, which is accounted for in the size of
+/// the RIFF chunk.
+pub(crate) fn write_header<W: Write>(
+    writer: &mut W,
+    number_of_channels: usize,
+    sample_rate: u32,
+    format: WavSampleFormat,
+    data_size: u32,
+) -> std::io::Result<()> {
+    let bits_per_sample = format.bits_per_sample();
+    let block_align = number_of_channels * usize::from(bits_per_sample / 8);
+    let format_tag = match format {
+        WavSampleFormat::Float32 => WAVE_FORMAT_IEEE_FLOAT,
+        _ => WAVE_FORMAT_PCM,
+    };
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size + data_size % 2).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16_u32.to_le_bytes())?;
+    writer.write_all(&format_tag.to_le_bytes())?;
+    writer.write_all(&(number_of_channels as u16).to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&(block_align as u16).to_le_bytes())?;
+    writer.write_all(&bits_per_sample.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())
+}
+
+pub(crate) fn invalid_data(message: String) -> Box<dyn Error + Send + Sync> {
+    Box::new(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message,
+    ))
+}
+
+fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decoded `fmt ` chunk
+pub(crate) struct WavFormat {
+    pub format_tag: u16,
+    pub number_of_channels: usize,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    pub block_align: usize,
+}
+
+impl WavFormat {
+    pub fn parse(chunk: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        if chunk.len() < 16 {
+            return Err(invalid_data("WAV fmt chunk is too short".into()));
+        }
+
+        let u16_at = |i: usize| u16::from_le_bytes([chunk[i], chunk[i + 1]]);
+        let u32_at =
+            |i: usize| u32::from_le_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]]);
+
+        let mut format_tag = u16_at(0);
+        if format_tag == WAVE_FORMAT_EXTENSIBLE {
+            if chunk.len() < 26 {
+                return Err(invalid_data("WAV extensible fmt chunk is too short".into()));
+            }
+            // the format tag is the first field of the sub format GUID
+            format_tag = u16_at(24);
+        }
+
+        Ok(Self {
+            format_tag,
+            number_of_channels: usize::from(u16_at(2)),
+            sample_rate: u32_at(4),
+            block_align: usize::from(u16_at(12)),
+            bits_per_sample: u16_at(14),
+        })
+    }
+
+    /// Decode a single sample, `bytes` containing exactly one sample
+    fn decode_sample(&self, bytes: &[u8]) -> f32 {
+        match (self.format_tag, bytes.len()) {
+            (WAVE_FORMAT_PCM, 1) => (f32::from(bytes[0]) - 128.) / 128.,
+            (WAVE_FORMAT_PCM, 2) => f32::from(i16::from_le_bytes([bytes[0], bytes[1]])) / 32768.,
+            (WAVE_FORMAT_PCM, 3) => {
+                let value = i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8;
+                value as f32 / 8_388_608.
+            }
+            (WAVE_FORMAT_PCM, 4) => {
+                let value = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (f64::from(value) / 2_147_483_648.) as f32
+            }
+            (WAVE_FORMAT_IEEE_FLOAT, 4) => {
+                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            }
+            (WAVE_FORMAT_IEEE_FLOAT, 8) => {
+                let mut b = [0; 8];
+                b.copy_from_slice(bytes);
+                f64::from_le_bytes(b) as f32
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl AudioBuffer {
+    /// Decode a WAV file into an `AudioBuffer`
+    ///
+    /// This is a minimal built-in decoder, which does not depend on the decoding features of
+    /// this crate. It supports 8, 16, 24 and 32 bits integer PCM and 32 and 64 bits floating
+    /// point data. The sample rate of the file is preserved, use
+    /// [`resample`](AudioBuffer::resample) to match the sample rate of an audio context.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the input cannot be read, if it is not a WAV file or if
+    /// its sample format, sample rate or number of channels is not supported.
+    pub fn from_wav<R: Read>(mut reader: R) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let riff: [u8; 4] = read_bytes(&mut reader)?;
+        let _riff_size: [u8; 4] = read_bytes(&mut reader)?;
+        let wave: [u8; 4] = read_bytes(&mut reader)?;
+        if &riff != b"RIFF" || &wave != b"WAVE" {
+            return Err(invalid_data("Not a RIFF WAVE file".into()));
+        }
+
+        let mut format: Option<WavFormat> = None;
+
+        let data = loop {
+            let id: [u8; 4] = read_bytes(&mut reader)?;
+            let size = u32::from_le_bytes(read_bytes(&mut reader)?);
+
+            match &id {
+                b"fmt " => {
+                    // the size is untrusted, the fields past the extensible format are skipped
+                    let used_size = size.min(MAX_FMT_CHUNK_SIZE);
+                    let mut chunk = vec![0; used_size as usize];
+                    reader.read_exact(&mut chunk)?;
+                    let skipped_size = u64::from(size - used_size);
+                    std::io::copy(
+                        &mut reader.by_ref().take(skipped_size),
+                        &mut std::io::sink(),
+                    )?;
+                    format = Some(WavFormat::parse(&chunk)?);
+                }
+                b"data" => {
+                    let mut data = Vec::new();
+                    // streamed files may not know the size of their data chunk
+                    if size == 0 || size == u32::MAX {
+                        reader.read_to_end(&mut data)?;
+                    } else {
+                        reader
+                            .by_ref()
+                            .take(u64::from(size))
+                            .read_to_end(&mut data)?;
+                    }
+                    break data;
+                }
+                _ => {
+                    // chunks are padded to an even size
+                    let padded_size = u64::from(size) + u64::from(size % 2);
+                    std::io::copy(&mut reader.by_ref().take(padded_size), &mut std::io::sink())?;
+                }
+            }
+
+            // chunks are padded to an even size
+            if &id == b"fmt " && size % 2 == 1 {
+                let _pad: [u8; 1] = read_bytes(&mut reader)?;
+            }
+        };
+
+        let format = format.ok_or_else(|| invalid_data("Missing WAV fmt chunk".into()))?;
+
+        let bytes_per_sample = usize::from(format.bits_per_sample).div_euclid(8);
+        let supported = match format.format_tag {
+            WAVE_FORMAT_PCM => (1..=4).contains(&bytes_per_sample),
+            WAVE_FORMAT_IEEE_FLOAT => bytes_per_sample == 4 || bytes_per_sample == 8,
+            _ => false,
+        };
+        if !supported || format.bits_per_sample % 8 != 0 {
+            return Err(invalid_data(format!(
+                "Unsupported WAV sample format {:?} with {:?} bits per sample",
+                format.format_tag, format.bits_per_sample
+            )));
+        }
+        if format.number_of_channels == 0 || format.number_of_channels > MAX_CHANNELS {
+            return Err(invalid_data(format!(
+                "Unsupported number of channels: {:?}",
+                format.number_of_channels
+            )));
+        }
+        // same bound as `assert_valid_sample_rate`
+        if format.sample_rate <= 1000 {
+            return Err(invalid_data(format!(
+                "Unsupported sample rate: {:?}",
+                format.sample_rate
+            )));
+        }
+        let block_align = format
+            .block_align
+            .max(format.number_of_channels * bytes_per_sample);
+
+        let length = data.len() / block_align;
+        let mut channels = vec![Vec::with_capacity(length); format.number_of_channels];
+
+        data.chunks_exact(block_align).for_each(|frame| {
+            channels
+                .iter_mut()
+                .zip(frame.chunks_exact(bytes_per_sample))
+                .for_each(|(channel, bytes)| channel.push(format.decode_sample(bytes)));
+        });
+
+        Ok(AudioBuffer::from(channels, format.sample_rate as f32))
+    }
+
+    /// Encode the `AudioBuffer` as a WAV file
+    ///
+    /// Samples are clamped to the [-1, 1] range when written as integers. The sample rate is
+    /// rounded to the nearest integer.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if writing to the output fails or if the data does not fit
+    /// in a WAV file (4GB).
+    pub fn to_wav<W: Write>(&self, mut writer: W, format: WavSampleFormat) -> std::io::Result<()> {
+        let number_of_channels = self.number_of_channels();
+        let bits_per_sample = format.bits_per_sample();
+        let bytes_per_sample = usize::from(bits_per_sample / 8);
+        let block_align = number_of_channels * bytes_per_sample;
+        let sample_rate = self.sample_rate().round() as u32;
+
+        let data_size = self.length() * block_align;
+        // chunks are padded to an even size
+        let padding = data_size % 2;
+        let data_size = u32::try_from(data_size)
+            .ok()
+            .filter(|size| u64::from(*size) + padding as u64 <= u64::from(u32::MAX - 36))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "AudioBuffer is too large to be encoded as a WAV file",
+                )
+            })?;
+
+        write_header(
+            &mut writer,
+            number_of_channels,
+            sample_rate,
+            format,
+            data_size,
+        )?;
+
+        // interleaved samples, written frame by frame
+        let mut frame = Vec::with_capacity(block_align);
+        for i in 0..self.length() {
+            frame.clear();
+
+            for channel_number in 0..number_of_channels {
+                let sample = self.get_channel_data(channel_number)[i];
+                format.encode_sample(sample, &mut frame);
+            }
+
+            writer.write_all(&frame)?;
+        }
+
+        if padding == 1 {
+            writer.write_all(&[0])?;
+        }
+
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use super::*;
+
+    fn test_buffer() -> AudioBuffer {
+        let left = (0..100).map(|i| (i as f32 / 50.) - 1.).collect();
+        let right = (0..100).map(|i| 1. - (i as f32 / 50.)).collect();
+        AudioBuffer::from(vec![left, right], 44100.)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let buffer = test_buffer();
+
+        for (format, tolerance) in [
+            (WavSampleFormat::Int16, 1. / 32768.),
+            (WavSampleFormat::Int24, 1. / 8_388_608.),
+            (WavSampleFormat::Int32, 1e-7),
+            (WavSampleFormat::Float32, 0.),
+        ] {
+            let mut encoded = Vec::new();
+            buffer.to_wav(&mut encoded, format).unwrap();
+
+            let block_align = 2 * usize::from(format.bits_per_sample() / 8);
+            assert_eq!(encoded.len(), 44 + 100 * block_align);
+
+            let decoded = AudioBuffer::from_wav(&encoded[..]).unwrap();
+            assert_eq!(decoded.number_of_channels(), 2);
+            assert_eq!(decoded.length(), 100);
+            assert_float_eq!(decoded.sample_rate(), 44100., abs <= 0.);
+
+            for channel_number in 0..2 {
+                assert_float_eq!(
+                    decoded.get_channel_data(channel_number),
+                    buffer.get_channel_data(channel_number),
+                    abs_all <= tolerance
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_odd_data_size() {
+        let buffer = AudioBuffer::from(vec![vec![0.5; 3]], 44100.);
+
+        let mut encoded = Vec::new();
+        buffer.to_wav(&mut encoded, WavSampleFormat::Int24).unwrap();
+
+        // the 9 bytes of data are followed by a pad byte, included in the RIFF size
+        assert_eq!(encoded.len(), 44 + 9 + 1);
+        assert_eq!(encoded[4..8], 46_u32.to_le_bytes());
+        assert_eq!(encoded[40..44], 9_u32.to_le_bytes());
+        assert_eq!(encoded[53], 0);
+
+        let decoded = AudioBuffer::from_wav(&encoded[..]).unwrap();
+        assert_eq!(decoded.length(), 3);
+    }
+
+    #[test]
+    fn test_large_fmt_chunk() {
+        let mut encoded = Vec::new();
+        test_buffer()
+            .to_wav(&mut encoded, WavSampleFormat::Int16)
+            .unwrap();
+
+        // an oversized fmt chunk is not allocated, and its extra bytes are skipped
+        let mut input = encoded[..16].to_vec();
+        input.extend_from_slice(&1000_u32.to_le_bytes());
+        input.extend_from_slice(&encoded[20..36]);
+        input.extend_from_slice(&[0; 1000 - 16]);
+        input.extend_from_slice(&encoded[36..]);
+        let decoded = AudioBuffer::from_wav(&input[..]).unwrap();
+        assert_eq!(decoded.length(), 100);
+
+        // a size past the end of the input is an error
+        let mut input = encoded[..16].to_vec();
+        input.extend_from_slice(&u32::MAX.to_le_bytes());
+        input.extend_from_slice(&encoded[20..36]);
+        assert!(AudioBuffer::from_wav(&input[..]).is_err());
+    }
+
+    #[test]
+    fn test_decode_sample_file() {
+        let file = std::fs::File::open("samples/sample.wav").unwrap();
+        let buffer = AudioBuffer::from_wav(file).unwrap();
+
+        let file = std::fs::File::open("samples/sample.wav").unwrap();
+        let context = crate::context::OfflineAudioContext::new(1, 1, buffer.sample_rate());
+        let expected = crate::context::BaseAudioContext::decode_audio_data_sync(&context, file);
+
+        let expected = expected.unwrap();
+        assert_eq!(buffer.number_of_channels(), expected.number_of_channels());
+        assert_eq!(buffer.length(), expected.length());
+        assert_float_eq!(
+            buffer.get_channel_data(0),
+            expected.get_channel_data(0),
+            abs_all <= 1e-6
+        );
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(AudioBuffer::from_wav(&b"RIFX0000WAVE"[..]).is_err());
+        assert!(AudioBuffer::from_wav(&b"RIFF"[..]).is_err());
+
+        // data chunk without fmt chunk
+        let mut input = b"RIFF\0\0\0\0WAVEdata".to_vec();
+        input.extend_from_slice(&0_u32.to_le_bytes());
+        assert!(AudioBuffer::from_wav(&input[..]).is_err());
+    }
+}
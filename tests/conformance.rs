@@ -0,0 +1,221 @@
+//! Golden-file conformance tests
+//!
+//! Canonical graphs are rendered offline and compared against the reference
+//! renderings stored in `samples/golden`, so DSP changes cannot silently alter
+//! the output of the nodes.
+//!
+//! When an output change is intended, regenerate the reference files with
+//!
+//! ```sh
+//! UPDATE_GOLDEN=1 cargo test --test conformance
+//! ```
+//!
+//! and review the updated files before committing them.
+
+use std::f32::consts::PI;
+use std::fs::File;
+use std::path::PathBuf;
+
+use web_audio_api::context::{BaseAudioContext, OfflineAudioContext};
+use web_audio_api::node::{
+    AudioNode, AudioScheduledSourceNode, BiquadFilterNode, BiquadFilterOptions, BiquadFilterType,
+    DynamicsCompressorNode, DynamicsCompressorOptions, PannerNode, PannerOptions, PanningModelType,
+};
+use web_audio_api::testing::{render, RenderOptions, RenderedOutput, SeedSequence};
+use web_audio_api::{AudioBuffer, WavSampleFormat};
+
+/// Maximum absolute difference allowed between a rendering and its reference
+const TOLERANCE: f32 = 1E-4;
+
+const SAMPLE_RATE: f32 = 44100.;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("samples")
+        .join("golden")
+        .join(format!("{}.wav", name))
+}
+
+fn assert_golden(name: &str, output: &RenderedOutput) {
+    let path = golden_path(name);
+    let rendered = output.to_audio_buffer();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let file = File::create(&path).unwrap();
+        rendered
+            .to_wav(std::io::BufWriter::new(file), WavSampleFormat::Float32)
+            .unwrap();
+        return;
+    }
+
+    let file = File::open(&path)
+        .unwrap_or_else(|e| panic!("cannot open reference {}: {}", path.display(), e));
+    let reference = AudioBuffer::from_wav(file).unwrap();
+
+    assert_eq!(
+        rendered.number_of_channels(),
+        reference.number_of_channels(),
+        "{}: number of channels differs from reference",
+        name
+    );
+    assert_eq!(
+        rendered.length(),
+        reference.length(),
+        "{}: length differs from reference",
+        name
+    );
+    assert_eq!(rendered.sample_rate(), reference.sample_rate());
+
+    for channel in 0..reference.number_of_channels() {
+        let actual = rendered.get_channel_data(channel);
+        let expected = reference.get_channel_data(channel);
+        if let Some((frame, (a, e))) = actual
+            .iter()
+            .zip(expected)
+            .enumerate()
+            .find(|(_, (a, e))| (*a - *e).abs() > TOLERANCE)
+        {
+            panic!(
+                "{}: channel {} frame {} is {} while reference is {}",
+                name, channel, frame, a, e
+            );
+        }
+    }
+}
+
+/// Exponential sine sweep from 50Hz to 15kHz over the length of the context
+fn sweep(context: &OfflineAudioContext) -> web_audio_api::node::OscillatorNode {
+    let duration = context.length() as f64 / context.sample_rate() as f64;
+    let osc = context.create_oscillator();
+    osc.frequency().set_value_at_time(50., 0.);
+    osc.frequency()
+        .exponential_ramp_to_value_at_time(15000., duration);
+    osc.start();
+    osc
+}
+
+#[test]
+fn test_biquad_filter_sweeps() {
+    let types = [
+        (BiquadFilterType::Lowpass, "lowpass"),
+        (BiquadFilterType::Highpass, "highpass"),
+        (BiquadFilterType::Bandpass, "bandpass"),
+        (BiquadFilterType::Notch, "notch"),
+        (BiquadFilterType::Allpass, "allpass"),
+        (BiquadFilterType::Peaking, "peaking"),
+        (BiquadFilterType::Lowshelf, "lowshelf"),
+        (BiquadFilterType::Highshelf, "highshelf"),
+    ];
+
+    for (type_, name) in types {
+        let options = RenderOptions {
+            number_of_quanta: 32,
+            sample_rate: SAMPLE_RATE,
+            ..RenderOptions::default()
+        };
+
+        let output = render(options, |context, _| {
+            let gain = context.create_gain();
+            gain.gain().set_value(0.5);
+            gain.connect(&context.destination());
+
+            let filter = BiquadFilterNode::new(
+                context,
+                BiquadFilterOptions {
+                    type_,
+                    frequency: 1000.,
+                    q: 2.,
+                    gain: 6.,
+                    ..BiquadFilterOptions::default()
+                },
+            );
+            filter.connect(&gain);
+            sweep(context).connect(&filter);
+        });
+
+        assert_golden(&format!("biquad-{}", name), &output);
+    }
+}
+
+#[test]
+fn test_dynamics_compressor_step_response() {
+    let options = RenderOptions {
+        number_of_quanta: 64,
+        sample_rate: SAMPLE_RATE,
+        ..RenderOptions::default()
+    };
+
+    let output = render(options, |context, _| {
+        let compressor = DynamicsCompressorNode::new(context, DynamicsCompressorOptions::default());
+        compressor.connect(&context.destination());
+
+        // 1kHz tone stepping from -20dBFS to 0dBFS and back
+        let envelope = context.create_gain();
+        envelope.gain().set_value_at_time(0.1, 0.);
+        envelope.gain().set_value_at_time(1., 1024. / 44100.);
+        envelope.gain().set_value_at_time(0.1, 5120. / 44100.);
+        envelope.connect(&compressor);
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(1000.);
+        osc.connect(&envelope);
+        osc.start();
+    });
+
+    assert_golden("compressor-step", &output);
+}
+
+fn panner_orbit(context: &OfflineAudioContext, _: &mut SeedSequence, model: PanningModelType) {
+    let panner = PannerNode::new(
+        context,
+        PannerOptions {
+            panning_model: model,
+            ..PannerOptions::default()
+        },
+    );
+    panner.connect(&context.destination());
+
+    // one full orbit around the listener at a distance of 2
+    let steps = 64;
+    let x: Vec<f32> = (0..=steps)
+        .map(|i| 2. * (2. * PI * i as f32 / steps as f32).cos())
+        .collect();
+    let z: Vec<f32> = (0..=steps)
+        .map(|i| 2. * (2. * PI * i as f32 / steps as f32).sin())
+        .collect();
+    let duration = context.length() as f64 / context.sample_rate() as f64;
+    panner
+        .position_x()
+        .set_value_curve_at_time(&x, 0., duration);
+    panner
+        .position_z()
+        .set_value_curve_at_time(&z, 0., duration);
+
+    let osc = context.create_oscillator();
+    osc.frequency().set_value(440.);
+    osc.connect(&panner);
+    osc.start();
+}
+
+#[test]
+fn test_panner_orbits() {
+    let models = [
+        (PanningModelType::EqualPower, "equalpower"),
+        (PanningModelType::HRTF, "hrtf"),
+    ];
+
+    for (model, name) in models {
+        let options = RenderOptions {
+            number_of_channels: 2,
+            number_of_quanta: 32,
+            sample_rate: SAMPLE_RATE,
+            ..RenderOptions::default()
+        };
+
+        let output = render(options, |context, seeds| {
+            panner_orbit(context, seeds, model)
+        });
+
+        assert_golden(&format!("panner-orbit-{}", name), &output);
+    }
+}
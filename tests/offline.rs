@@ -2,8 +2,11 @@ use float_eq::assert_float_eq;
 use web_audio_api::context::BaseAudioContext;
 use web_audio_api::context::OfflineAudioContext;
 use web_audio_api::node::{
-    AudioNode, AudioScheduledSourceNode, OscillatorNode, OscillatorOptions, OscillatorType,
+    AudioNode, AudioScheduledSourceNode, ConnectError, OscillatorNode, OscillatorOptions,
+    OscillatorType,
 };
+use web_audio_api::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use web_audio_api::MAX_CHANNELS;
 
 const RENDER_QUANTUM_SIZE: usize = 128;
 
@@ -167,6 +170,33 @@ fn test_listener() {
     assert_float_eq!(listener.position_x().value(), 1., abs <= 0.);
 }
 
+#[test]
+fn test_param_value_includes_inputs() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 2, 44_100.);
+
+    let gain = context.create_gain();
+    gain.gain().set_value(0.5);
+    gain.connect(&context.destination());
+
+    let src = context.create_constant_source();
+    src.offset().set_value(0.25);
+    src.connect(gain.gain());
+    src.start();
+
+    // the node itself must be processed for its params to be rendered
+    let input = context.create_constant_source();
+    input.connect(&gain);
+    input.start();
+
+    // value reflects the last set value until the first render quantum
+    assert_float_eq!(gain.gain().value(), 0.5, abs <= 0.);
+
+    let _ = context.start_rendering_sync();
+
+    // intrinsic value plus input connection
+    assert_float_eq!(gain.gain().value(), 0.75, abs <= 0.);
+}
+
 #[test]
 fn test_cycle() {
     let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 48000.);
@@ -288,3 +318,381 @@ fn test_spatial() {
         abs_all <= 0.001
     );
 }
+
+#[test]
+fn test_start_stop_on_frame_boundaries() {
+    for sample_rate in [22_050., 44_100., 48_000., 96_000.] {
+        let length = RENDER_QUANTUM_SIZE * 40;
+        // a lot of onsets, scattered over the render quanta
+        let onsets: Vec<usize> = (0..300).map(|k| 3 + k * 17).collect();
+
+        let expected: Vec<f32> = (0..length)
+            .map(|i| {
+                if onsets.iter().any(|&f| i >= f && i < f + 3) {
+                    1.
+                } else {
+                    0.
+                }
+            })
+            .collect();
+
+        // constant source
+        {
+            let context = OfflineAudioContext::new(1, length, sample_rate);
+
+            for &onset in &onsets {
+                let src = context.create_constant_source();
+                src.connect(&context.destination());
+                src.start_at(onset as f64 / sample_rate as f64);
+                src.stop_at((onset + 3) as f64 / sample_rate as f64);
+            }
+
+            let output = context.start_rendering_sync();
+            assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+        }
+
+        // buffer source
+        {
+            let context = OfflineAudioContext::new(1, length, sample_rate);
+            let mut buffer = context.create_buffer(1, 10, sample_rate);
+            buffer.copy_to_channel(&[1.; 10], 0);
+
+            for &onset in &onsets {
+                let src = context.create_buffer_source();
+                src.set_buffer(buffer.clone());
+                src.connect(&context.destination());
+                src.start_at(onset as f64 / sample_rate as f64);
+                src.stop_at((onset + 3) as f64 / sample_rate as f64);
+            }
+
+            let output = context.start_rendering_sync();
+            assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 0.);
+        }
+    }
+}
+
+#[test]
+fn test_oscillator_sub_sample_start() {
+    let sample_rate = 48_000.;
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 2, sample_rate);
+
+    let osc = context.create_oscillator();
+    osc.frequency().set_value(1_000.);
+    osc.connect(&context.destination());
+    // start between two frames of the second render quantum
+    let start = 130.25 / sample_rate as f64;
+    osc.start_at(start);
+
+    let output = context.start_rendering_sync();
+    let channel = output.get_channel_data(0);
+
+    assert_float_eq!(channel[..131], &[0.; 131][..], abs_all <= 0.);
+
+    let expected: Vec<f32> = (131..RENDER_QUANTUM_SIZE * 2)
+        .map(|i| {
+            let t = i as f64 / sample_rate as f64 - start;
+            (2. * std::f64::consts::PI * 1_000. * t).sin() as f32
+        })
+        .collect();
+    assert_float_eq!(channel[131..], &expected[..], abs_all <= 1e-3);
+}
+
+#[test]
+fn test_max_channels_end_to_end() {
+    let context = OfflineAudioContext::new(MAX_CHANNELS, RENDER_QUANTUM_SIZE, 44_100.);
+    assert_eq!(context.destination().max_channels_count(), MAX_CHANNELS);
+
+    let merger = context.create_channel_merger(MAX_CHANNELS);
+    let splitter = context.create_channel_splitter(MAX_CHANNELS);
+    let reverse = context.create_channel_merger(MAX_CHANNELS);
+
+    for i in 0..MAX_CHANNELS {
+        let src = context.create_constant_source();
+        src.offset().set_value(i as f32);
+        src.connect_at(&merger, 0, i);
+        src.start();
+
+        // reverse the channel order
+        splitter.connect_at(&reverse, i, MAX_CHANNELS - 1 - i);
+    }
+
+    merger.connect(&splitter);
+    reverse.connect(&context.destination());
+
+    let output = context.start_rendering_sync();
+    assert_eq!(output.number_of_channels(), MAX_CHANNELS);
+
+    for i in 0..MAX_CHANNELS {
+        let expected = (MAX_CHANNELS - 1 - i) as f32;
+        assert_float_eq!(
+            output.get_channel_data(i),
+            &[expected; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+}
+
+#[test]
+fn test_channel_merger_downmixes_inputs() {
+    let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44_100.);
+
+    // stereo signal with 1. left and 3. right
+    let stereo = context.create_channel_merger(2);
+    for (i, value) in [1., 3.].iter().enumerate() {
+        let src = context.create_constant_source();
+        src.offset().set_value(*value);
+        src.connect_at(&stereo, 0, i);
+        src.start();
+    }
+
+    // each input of a merger is down-mixed to mono
+    let merger = context.create_channel_merger(2);
+    assert_eq!(merger.channel_count(), 1);
+    stereo.connect_at(&merger, 0, 1);
+    merger.connect(&context.destination());
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[0.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+    assert_float_eq!(
+        output.get_channel_data(1),
+        &[2.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+fn test_disconnect_with_indices() {
+    let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44_100.);
+
+    // stereo signal with 1. left and 2. right
+    let stereo = context.create_channel_merger(2);
+    for (i, value) in [1., 2.].iter().enumerate() {
+        let src = context.create_constant_source();
+        src.offset().set_value(*value);
+        src.connect_at(&stereo, 0, i);
+        src.start();
+    }
+
+    // swap the channels and connect twice to both inputs of the output merger
+    let splitter = context.create_channel_splitter(2);
+    stereo.connect(&splitter);
+    let merger = context.create_channel_merger(2);
+    splitter.connect_at(&merger, 0, 1);
+    splitter.connect_at(&merger, 1, 0);
+    splitter.connect_at(&merger, 1, 1);
+    merger.connect(&context.destination());
+
+    // remove the right to right connection and all connections from the left channel
+    splitter.disconnect_destination_output_input(&merger, 1, 1);
+    splitter.disconnect_output(0);
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[2.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+    assert_float_eq!(
+        output.get_channel_data(1),
+        &[0.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+fn test_disconnect_destination() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+    let src = context.create_constant_source();
+    let gain = context.create_gain();
+    src.connect(&gain);
+    src.connect(&context.destination());
+    gain.connect(&context.destination());
+    src.start();
+
+    src.disconnect_destination(&context.destination());
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[1.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+#[should_panic(expected = "InvalidAccessError")]
+fn test_disconnect_destination_not_connected() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+    let src = context.create_constant_source();
+    let gain = context.create_gain();
+    src.connect(&gain);
+    src.disconnect_destination_output_input(&gain, 0, 0);
+
+    // already disconnected
+    src.disconnect_destination(&gain);
+}
+
+#[test]
+#[should_panic(expected = "IndexSizeError")]
+fn test_disconnect_invalid_output() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+    let src = context.create_constant_source();
+    src.disconnect_output(1);
+}
+
+#[test]
+fn test_disconnect_keeps_audio_param_automation() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+    let src = context.create_constant_source();
+    src.start();
+    let gain = context.create_gain();
+    gain.gain().set_value(0.5);
+    src.connect(&gain);
+
+    // disconnecting the node or its params must not drop the internal param connection
+    gain.disconnect();
+    gain.gain().disconnect();
+    gain.connect(&context.destination());
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[0.5; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+fn test_try_connect() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+    let other = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+    let src = context.create_constant_source();
+    src.start();
+    let gain = context.create_gain();
+
+    assert_eq!(
+        src.try_connect(&other.destination()).err(),
+        Some(ConnectError::InvalidAccessError)
+    );
+    assert_eq!(
+        src.try_connect_at(&gain, 1, 0).err(),
+        Some(ConnectError::OutputIndexSizeError(1))
+    );
+    assert_eq!(
+        src.try_connect_at(&gain, 0, 1).err(),
+        Some(ConnectError::InputIndexSizeError(1))
+    );
+
+    // the failed attempts left no connections behind
+    let delay = context.create_delay(1.);
+    assert_eq!(
+        delay.try_connect_at(&gain, 1, 0).err(),
+        Some(ConnectError::OutputIndexSizeError(1))
+    );
+    assert!(src.try_connect(&gain).is_ok());
+    assert!(gain.try_connect(&context.destination()).is_ok());
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[1.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+#[should_panic(expected = "IndexSizeError: input port 1 is out of bounds")]
+fn test_connect_invalid_input() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+    let src = context.create_constant_source();
+    src.connect_at(&context.destination(), 0, 1);
+}
+
+#[test]
+fn test_fan_in_summing() {
+    let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 44_100.);
+
+    let gain = context.create_gain();
+    gain.connect(&context.destination());
+
+    for i in 0..32 {
+        let src = context.create_constant_source();
+        // every other source is silent
+        if i % 2 == 0 {
+            src.start();
+        }
+        src.connect(&gain);
+        // duplicate connections are ignored
+        src.connect(&gain);
+    }
+
+    let output = context.start_rendering_sync();
+    for channel in 0..2 {
+        assert_float_eq!(
+            output.get_channel_data(channel),
+            &[16.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+}
+
+struct DoublingProcessor;
+
+impl AudioProcessor for DoublingProcessor {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        outputs[0] = inputs[0].clone();
+        outputs[0]
+            .channels_mut()
+            .iter_mut()
+            .for_each(|channel| channel.iter_mut().for_each(|s| *s *= 2.));
+        false
+    }
+}
+
+#[test]
+fn test_replace_processor() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+    let src = context.create_constant_source();
+    src.start();
+    let gain = context.create_gain();
+    gain.gain().set_value(0.5);
+    src.connect(&gain);
+    gain.connect(&context.destination());
+
+    // the connections of the node are kept
+    context.replace_processor(&gain, Box::new(DoublingProcessor));
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[2.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+#[should_panic(expected = "InvalidAccessError")]
+fn test_replace_processor_other_context() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+    let other = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+    let gain = other.create_gain();
+    context.replace_processor(&gain, Box::new(DoublingProcessor));
+}
@@ -4,9 +4,14 @@
 //! using the 'none' audio backend.
 
 use web_audio_api::context::{
-    AudioContext, AudioContextOptions, AudioContextState, BaseAudioContext,
+    AdaptiveLatencyOptions, AudioContext, AudioContextOptions, AudioContextState, BaseAudioContext,
 };
-use web_audio_api::node::AudioNode;
+use web_audio_api::node::{
+    AudioNode, AudioScheduledSourceNode, AudioWorkletNode, AudioWorkletNodeOptions,
+    AudioWorkletProcessorOptions, MediaStreamAudioDestinationNode,
+    MediaStreamAudioDestinationOptions,
+};
+use web_audio_api::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
 use std::sync::atomic::{AtomicBool, Ordering};
 use web_audio_api::MAX_CHANNELS;
@@ -75,6 +80,90 @@ fn test_none_sink_id() {
     assert!(sink_stable.load(Ordering::SeqCst));
 }
 
+#[test]
+fn test_latency() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        sample_rate: Some(48000.),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+
+    // the 'none' backend renders a render quantum per callback and has no output device
+    assert_eq!(context.base_latency(), 128. / 48000.);
+    assert_eq!(context.output_latency(), 0.);
+}
+
+#[test]
+fn test_output_timestamp() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+
+    // wait for the render thread to get going, without relying on its start up time
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let timestamp = loop {
+        let timestamp = context.get_output_timestamp();
+        if timestamp.context_time > 0. {
+            break timestamp;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "the context did not start rendering"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    };
+    assert!(timestamp.context_time <= context.current_time());
+
+    // the performance time is relative to the creation of the context
+    let elapsed = context.time_origin().elapsed().as_secs_f64() * 1000.;
+    assert!(timestamp.performance_time > 0.);
+    assert!(timestamp.performance_time <= elapsed);
+}
+
+#[test]
+#[ignore = "depends on the wall clock timing of the render threads"]
+fn test_shared_clock() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let main = AudioContext::new(options);
+
+    std::thread::sleep(std::time::Duration::from_millis(200));
+
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        sample_rate: Some(44_100.),
+        ..AudioContextOptions::default()
+    };
+    let second = AudioContext::new(options);
+    second.set_clock(Some(main.clock()));
+
+    std::thread::sleep(std::time::Duration::from_millis(200));
+
+    // both clocks advance by a render quantum per callback, the bound allows for a few delayed
+    // callbacks on a busy machine
+    let offset = main.current_time() - second.current_time();
+    assert!(offset.abs() < 0.1, "offset {}", offset);
+
+    // run freely again
+    second.set_clock(None);
+}
+
+#[test]
+#[should_panic]
+fn test_shared_clock_self() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+    context.set_clock(Some(context.clock()));
+}
+
 #[test]
 fn test_channels() {
     let options = AudioContextOptions {
@@ -89,3 +178,139 @@ fn test_channels() {
     context.destination().set_channel_count(5);
     assert_eq!(context.destination().channel_count(), 5);
 }
+
+#[test]
+fn test_max_channel_count() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        max_channel_count: Some(4),
+        ..AudioContextOptions::default()
+    };
+
+    let context = AudioContext::new(options);
+    assert_eq!(context.destination().max_channels_count(), 4);
+    assert_eq!(context.destination().channel_count(), 2);
+
+    context.destination().set_channel_count(4);
+    assert_eq!(context.destination().channel_count(), 4);
+}
+
+#[test]
+#[should_panic]
+fn test_invalid_max_channel_count() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        max_channel_count: Some(MAX_CHANNELS + 1),
+        ..AudioContextOptions::default()
+    };
+
+    let _context = AudioContext::new(options);
+}
+
+struct SlowProcessor;
+
+impl AudioProcessor for SlowProcessor {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        _outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // a render quantum lasts less than 3 ms at 48 kHz
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        true
+    }
+}
+
+#[test]
+fn test_adaptive_latency() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+
+    context.register_processor("slow", |_options: AudioWorkletProcessorOptions| {
+        Box::new(SlowProcessor) as Box<dyn AudioProcessor>
+    });
+    let slow = AudioWorkletNode::new(&context, "slow", AudioWorkletNodeOptions::default());
+    slow.connect(&context.destination());
+
+    let options = AdaptiveLatencyOptions {
+        max_latency: 1.,
+        interval: 0.05,
+        max_underruns: 0,
+    };
+    context.set_adaptive_latency(Some(options));
+
+    // the backend is replaced a few times, the graph keeps rendering
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert_eq!(context.state(), AudioContextState::Running);
+
+    let time = context.current_time();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(context.current_time() > time);
+
+    context.set_adaptive_latency(None);
+    context.close_sync();
+}
+
+#[test]
+#[should_panic]
+fn test_invalid_adaptive_latency() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+
+    let options = AdaptiveLatencyOptions {
+        interval: 0.,
+        ..AdaptiveLatencyOptions::default()
+    };
+    context.set_adaptive_latency(Some(options));
+}
+
+#[test]
+fn test_media_stream_destination_dc_blocker() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+
+    let blocked = context.create_media_stream_destination();
+    let options = MediaStreamAudioDestinationOptions {
+        dc_blocker: false,
+        ..MediaStreamAudioDestinationOptions::default()
+    };
+    let unblocked = MediaStreamAudioDestinationNode::new(&context, options);
+
+    let src = context.create_constant_source();
+    src.connect(&blocked);
+    src.connect(&unblocked);
+    src.start();
+
+    // the DC offset has decayed after a fraction of a second
+    let mut frames = 0;
+    let mut last_sample = 1.;
+    for item in blocked.stream().get_tracks()[0].iter() {
+        let buffer = item.unwrap();
+        frames += buffer.length();
+        last_sample = *buffer.get_channel_data(0).last().unwrap();
+        if frames as f32 > context.sample_rate() / 4. {
+            break;
+        }
+    }
+    assert!(last_sample.abs() < 1e-3);
+
+    let buffer = unblocked.stream().get_tracks()[0]
+        .iter()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert!(buffer.get_channel_data(0).iter().all(|&s| s == 1.));
+
+    context.close_sync();
+}
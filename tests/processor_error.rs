@@ -80,3 +80,32 @@ fn test_processor_error() {
     // error branch should be muted, and other source should be processed
     assert_float_eq!(output.get_channel_data(0), &[1.; 128][..], abs_all <= 0.);
 }
+
+#[test]
+fn test_processor_error_recovery() {
+    let length = 128 * 4;
+    let context = OfflineAudioContext::new(1, length, 48000.);
+
+    // keep the handles alive while rendering
+    let source1 = context.create_constant_source();
+    source1.offset().set_value(1.);
+    source1.connect(&context.destination());
+    source1.start();
+
+    let source2 = context.create_constant_source();
+    source2.offset().set_value(2.);
+    let panic = PanicNode::new(&context);
+    source2.connect(&panic);
+    source2.connect(&context.destination());
+    panic.connect(&context.destination());
+    source2.start();
+
+    // the failed node outputs silence and does not affect the other nodes, also the ones
+    // feeding into it
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &vec![3.; length][..],
+        abs_all <= 0.
+    );
+}